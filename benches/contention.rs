@@ -0,0 +1,73 @@
+//! Many clients depositing at the same time, each from its own task, as the
+//! async stream processor does. With fewer shards than clients, this measures
+//! how much unrelated clients get in each other's way.
+
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use jouet_paiement::{
+    account::{AccountStore, SimpleAccountTransactor},
+    model::{Amount, ClientId, Transaction, TransactionKind},
+    transaction_processor::{SimpleTransactionProcessor, TransactionProcessor},
+};
+use tokio::runtime::Builder;
+
+const TRANSACTIONS_PER_CLIENT: u32 = 1_000;
+
+fn contention(c: &mut Criterion) {
+    let runtime = Builder::new_multi_thread()
+        .worker_threads(8)
+        .build()
+        .unwrap();
+    let mut group = c.benchmark_group("contention");
+    for client_count in [8u16, 64, 512] {
+        group.throughput(Throughput::Elements(
+            u64::from(client_count) * u64::from(TRANSACTIONS_PER_CLIENT),
+        ));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(client_count),
+            &client_count,
+            |b, &client_count| {
+                b.to_async(&runtime)
+                    .iter(|| deposit_concurrently(client_count))
+            },
+        );
+    }
+    group.finish();
+}
+
+async fn deposit_concurrently(client_count: u16) {
+    let processor = Arc::new(SimpleTransactionProcessor::new(
+        Arc::new(AccountStore::new()),
+        Box::new(SimpleAccountTransactor::new()),
+    ));
+    let handles: Vec<_> = (1..=client_count)
+        .map(|client_id| {
+            let processor = processor.clone();
+            tokio::spawn(async move {
+                for transaction_id in 1..=TRANSACTIONS_PER_CLIENT {
+                    processor
+                        .process(deposit(client_id, transaction_id))
+                        .await
+                        .unwrap();
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.await.unwrap();
+    }
+}
+
+fn deposit(client_id: ClientId, transaction_id: u32) -> Transaction {
+    Transaction::new(
+        client_id,
+        transaction_id,
+        TransactionKind::Deposit {
+            amount: Amount::new(10_000),
+        },
+    )
+}
+
+criterion_group!(benches, contention);
+criterion_main!(benches);
@@ -0,0 +1,61 @@
+//! A single client taking many deposits, each of which is then disputed and
+//! resolved, so that most of the time goes into the account's maps of
+//! transactions. Run it with and without the `fxhash` feature to compare the
+//! hashers:
+//! `cargo bench --bench hashing` then `cargo bench --bench hashing --features fxhash`.
+
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use jouet_paiement::{
+    account::{AccountStore, SimpleAccountTransactor},
+    model::{Amount, Transaction, TransactionKind},
+    transaction_processor::{SimpleTransactionProcessor, TransactionProcessor},
+};
+use tokio::runtime::Builder;
+
+fn hashing(c: &mut Criterion) {
+    let runtime = Builder::new_current_thread().build().unwrap();
+    let mut group = c.benchmark_group("hashing");
+    group.sample_size(10);
+    for deposit_count in [10_000u32, 1_000_000] {
+        group.throughput(Throughput::Elements(u64::from(deposit_count) * 3));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(deposit_count),
+            &deposit_count,
+            |b, &deposit_count| b.to_async(&runtime).iter(|| transact(deposit_count)),
+        );
+    }
+    group.finish();
+}
+
+async fn transact(deposit_count: u32) {
+    let processor = SimpleTransactionProcessor::new(
+        Arc::new(AccountStore::new()),
+        Box::new(SimpleAccountTransactor::new()),
+    );
+    for transaction_id in 1..=deposit_count {
+        let deposit = TransactionKind::Deposit {
+            amount: Amount::new(10_000),
+        };
+        processor
+            .process(transaction(transaction_id, deposit))
+            .await
+            .unwrap();
+    }
+    for kind in [TransactionKind::Dispute, TransactionKind::Resolve] {
+        for transaction_id in 1..=deposit_count {
+            processor
+                .process(transaction(transaction_id, kind.clone()))
+                .await
+                .unwrap();
+        }
+    }
+}
+
+fn transaction(transaction_id: u32, kind: TransactionKind) -> Transaction {
+    Transaction::new(1, transaction_id, kind)
+}
+
+criterion_group!(benches, hashing);
+criterion_main!(benches);
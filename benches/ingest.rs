@@ -0,0 +1,116 @@
+//! A large CSV input run through the whole engine, from reading the rows to
+//! the workers applying them, and read alone, so that what reading each row
+//! costs shows apart from the rest.
+//! With the `io-uring` feature, the input is also read from a file with a
+//! `BufReader` and with a `UringReader`.
+
+use async_trait::async_trait;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use jouet_paiement::{
+    account::SuccessStatus,
+    engine::{Engine, EngineConfig},
+    model::Transaction,
+    transaction_processor::{TransactionProcessor, TransactionProcessorError},
+    transaction_stream_processor::{
+        csv_stream_processor::CsvStreamProcessor, TransactionStreamProcessor,
+    },
+};
+use tokio::runtime::Builder;
+
+const CLIENT_COUNT: u32 = 1_000;
+const ROW_COUNT: u32 = 200_000;
+
+fn ingest(c: &mut Criterion) {
+    let runtime = Builder::new_multi_thread().build().unwrap();
+    let input = input();
+    let mut group = c.benchmark_group("ingest");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(u64::from(ROW_COUNT)));
+    group.bench_function("csv", |b| {
+        b.to_async(&runtime).iter(|| async {
+            let engine = Engine::new(EngineConfig {
+                expected_client_count: CLIENT_COUNT as usize,
+                ..Default::default()
+            });
+            engine.process(input.as_bytes()).await.unwrap();
+        })
+    });
+    group.bench_function("csv_parsed_in_parallel", |b| {
+        b.to_async(&runtime).iter(|| async {
+            let engine = Engine::new(EngineConfig {
+                expected_client_count: CLIENT_COUNT as usize,
+                parsers: Some(4),
+                ..Default::default()
+            });
+            engine.process(input.as_bytes()).await.unwrap();
+        })
+    });
+    group.bench_function("read", |b| {
+        b.to_async(&runtime).iter(|| async {
+            CsvStreamProcessor::new(Box::new(Discard))
+                .process(input.as_bytes())
+                .await
+                .unwrap();
+        })
+    });
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    {
+        use std::{env, fs, fs::File, io::BufReader};
+
+        use jouet_paiement::transaction_stream_processor::UringReader;
+
+        let path = env::temp_dir().join("jouet_paiement_ingest.csv");
+        fs::write(&path, &input).unwrap();
+        group.bench_function("read_file_with_buf_reader", |b| {
+            b.to_async(&runtime).iter(|| async {
+                CsvStreamProcessor::new(Box::new(Discard))
+                    .process(BufReader::new(File::open(&path).unwrap()))
+                    .await
+                    .unwrap();
+            })
+        });
+        group.bench_function("read_file_with_io_uring", |b| {
+            b.to_async(&runtime).iter(|| async {
+                CsvStreamProcessor::new(Box::new(Discard))
+                    .process(UringReader::open(&path).unwrap())
+                    .await
+                    .unwrap();
+            })
+        });
+        fs::remove_file(&path).unwrap();
+    }
+    group.finish();
+}
+
+/// Takes the transactions without doing anything with them.
+struct Discard;
+
+#[async_trait]
+impl TransactionProcessor for Discard {
+    async fn process(
+        &self,
+        _transaction: Transaction,
+    ) -> Result<SuccessStatus, TransactionProcessorError> {
+        Ok(SuccessStatus::Transacted)
+    }
+}
+
+/// Deposits and withdrawals spread across the clients, with the odd dispute
+/// and resolve.
+fn input() -> String {
+    let mut input = String::from("type,client,tx,amount\n");
+    for transaction_id in 1..=ROW_COUNT {
+        let client_id = transaction_id % CLIENT_COUNT;
+        let row = match transaction_id % 10 {
+            0..=5 => format!("deposit,{client_id},{transaction_id},12.3456\n"),
+            6..=7 => format!("withdrawal,{client_id},{transaction_id},1.5\n"),
+            8 => format!("dispute,{client_id},{},\n", transaction_id - 8),
+            _ => format!("resolve,{client_id},{},\n", transaction_id - 9),
+        };
+        input.push_str(&row);
+    }
+    input
+}
+
+criterion_group!(benches, ingest);
+criterion_main!(benches);
@@ -0,0 +1,50 @@
+/* The C API of jouet-paiement, built with the `cdylib` feature, see
+ * src/ffi.rs. */
+#ifndef JOUET_PAIEMENT_H
+#define JOUET_PAIEMENT_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct JpEngine JpEngine;
+
+typedef enum JpStatus {
+    JP_STATUS_TRANSACTED = 0,
+    JP_STATUS_DUPLICATE = 1,
+    JP_STATUS_NO_OP = 2,
+    /* left out of the account, e.g. for want of funds */
+    JP_STATUS_REJECTED = 3,
+    /* could not be read, e.g. of an unknown type */
+    JP_STATUS_INVALID = -1,
+} JpStatus;
+
+/* owned by the library, to be given back with jp_buffer_free */
+typedef struct JpBuffer {
+    uint8_t *data;
+    size_t len;
+} JpBuffer;
+
+JpEngine *jp_engine_new(void);
+
+/* `amount`, e.g. "1.5", is only read for a deposit or a withdrawal and can
+ * be NULL otherwise. */
+JpStatus jp_engine_push(JpEngine *engine, const char *kind, uint16_t client,
+                        uint32_t tx, const char *amount);
+
+/* the summaries of the accounts as CSV, ordered by client, not
+ * NUL-terminated */
+JpBuffer jp_engine_summaries(const JpEngine *engine);
+
+void jp_buffer_free(JpBuffer buffer);
+
+void jp_engine_free(JpEngine *engine);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,22 @@
+-- The accounts of the clients, with their balances and status kept apart
+-- from the rest of the account for them to be queried as they are.
+CREATE TABLE IF NOT EXISTS accounts (
+    client_id INTEGER PRIMARY KEY,
+    status TEXT,
+    available NUMERIC(24, 4),
+    held NUMERIC(24, 4),
+    -- the whole account as the engine serialises it, versioned, NULL until
+    -- the first transaction of the client is applied
+    account JSONB
+);
+
+-- The transactions applied to the accounts, each written in the same
+-- database transaction as the account it changed, so that one sent again,
+-- e.g. by a batch run again after a restart, is not applied twice.
+CREATE TABLE IF NOT EXISTS applied_transactions (
+    client_id INTEGER NOT NULL,
+    transaction_id BIGINT NOT NULL,
+    kind TEXT NOT NULL,
+    applied_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (client_id, transaction_id, kind)
+);
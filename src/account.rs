@@ -1,12 +1,73 @@
+mod account_book;
+mod account_merge;
+#[cfg(feature = "engine")]
+mod account_store;
 pub(crate) mod account_transactor;
-pub use account_transactor::SimpleAccountTransactor;
+mod auto_resolution;
+mod checkpoints;
+mod credit_limits;
+mod dispute_window;
+mod fee_policy;
+#[cfg(feature = "postgres")]
+mod postgres_account_store;
+#[cfg(feature = "redis")]
+mod redis_account_store;
+mod transaction_limits;
+pub use account_book::AccountBook;
+pub use account_merge::AccountMergeError;
+#[cfg(feature = "engine")]
+pub(crate) use account_store::lock;
+#[cfg(feature = "engine")]
+pub use account_store::{AccountStore, StoreStats};
+pub use account_transactor::{
+    AccountPolicy, AccountTransactor, AccountTransactorError, SimpleAccountTransactor,
+    SuccessStatus,
+};
+pub use auto_resolution::AutoResolution;
+pub use checkpoints::{Checkpoint, Checkpointing};
+pub use credit_limits::CreditLimits;
+pub use dispute_window::DisputeWindow;
+pub use fee_policy::{Fee, FeePolicy};
+#[cfg(feature = "postgres")]
+pub use postgres_account_store::{PostgresAccountStore, PostgresStoreError};
+#[cfg(feature = "redis")]
+pub use redis_account_store::{RedisAccountStore, RedisStoreError, DEFAULT_REDIS_KEY_PREFIX};
+pub use transaction_limits::TransactionLimits;
 mod transactors;
+pub use transactors::{
+    backcharger::{Backcharger, BackchargerError},
+    depositor::{Depositor, DepositorError},
+    disputer::{Disputer, DisputerError},
+    representer::{Representer, RepresenterError},
+    resolver::{Resolver, ResolverError},
+    withdrawer::{Withdrawer, WithdrawerError},
+    Incompatibility,
+};
 
-use std::collections::HashMap;
+use alloc::{format, string::String, vec::Vec};
 
-use thiserror::Error;
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::model::{Amount, Amount4DecimalBased, ClientId, TransactionId};
+use checkpoints::Checkpoints;
+
+use crate::{
+    collections::HashMap,
+    model::{amount_format, Amount, ClientId, Memo, Timestamp, TransactionId},
+};
+
+/// The hasher of the maps keyed by client or by transaction, FxHash with the
+/// `fxhash` feature as SipHash is measurably slower for millions of entries.
+/// FxHash is not resistant to collisions crafted on purpose, which the ids of
+/// the input are trusted not to be.
+#[cfg(all(not(feature = "fxhash"), feature = "std"))]
+pub(crate) type BuildIdHasher = std::collections::hash_map::RandomState;
+#[cfg(all(not(feature = "fxhash"), not(feature = "std")))]
+pub(crate) type BuildIdHasher = hashbrown::DefaultHashBuilder;
+#[cfg(feature = "fxhash")]
+pub(crate) type BuildIdHasher = rustc_hash::FxBuildHasher;
+
+/// A map keyed by transaction, see [`BuildIdHasher`].
+pub(crate) type TransactionMap<V> = HashMap<TransactionId, V, BuildIdHasher>;
 
 /// The snapshot of an account.
 /// An account consists of a series of chronologically ordered transactions
@@ -14,39 +75,77 @@ use crate::model::{Amount, Amount4DecimalBased, ClientId, TransactionId};
 /// To capture the account's state, replaying all these transactions is time
 /// consuming and a snapshot is helpful to keep track of certain key attributes
 /// of an account.
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub struct AccountSnapshot {
-    pub(crate) available: Amount,
-    pub(crate) held: Amount,
+    #[serde(with = "amount_format")]
+    pub available: Amount,
+    #[serde(with = "amount_format")]
+    pub held: Amount,
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum AccountStatus {
     /// The account is active, and is open to transactions.
     Active,
 
     /// The account is locked and is closed to transactions.
     Locked,
+
+    /// The account has been closed for good by a
+    /// [`TransactionKind::Close`], and rejects any further transaction.
+    ///
+    /// [`TransactionKind::Close`]: crate::model::TransactionKind::Close
+    Closed,
 }
 
 /// An account structure used to process transactions.
-#[derive(Debug, PartialEq, Clone)]
+/// It is serialised along with the version of its layout, see
+/// [`ACCOUNT_VERSION`].
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(remote = "Self")]
 pub struct Account {
     pub(crate) client_id: ClientId,
     pub(crate) status: AccountStatus,
     pub(crate) account_snapshot: AccountSnapshot,
-    deposits: HashMap<TransactionId, Deposit>,
-    withdrawals: HashMap<TransactionId, Withdrawal>,
+    deposits: TransactionMap<Deposit>,
+    withdrawals: TransactionMap<Withdrawal>,
+    fees: Vec<FeeCharge>,
+    adjustments: Vec<Adjustment>,
+    /// When the deposits on hold were disputed, kept only if they can be
+    /// resolved automatically.
+    holds: TransactionMap<Hold>,
+    auto_resolved: Vec<AutoResolved>,
+    /// What upstream attached to the deposits, withdrawals and adjustments,
+    /// for those that came with anything.
+    #[serde(default)]
+    memos: TransactionMap<Memo>,
+    /// The balances kept along the way, see [`Checkpointing`].
+    #[serde(default)]
+    checkpoints: Checkpoints,
 }
 
 impl Account {
+    #[cfg(test)]
     pub(crate) fn active(client_id: ClientId) -> Self {
+        Self::active_with_capacity(client_id, 0)
+    }
+
+    /// An active account with room for `capacity` deposits and as many
+    /// withdrawals before its maps need to grow.
+    pub(crate) fn active_with_capacity(client_id: ClientId, capacity: usize) -> Self {
         Account {
             client_id,
             status: AccountStatus::Active,
             account_snapshot: AccountSnapshot::empty(),
-            deposits: HashMap::new(),
-            withdrawals: HashMap::new(),
+            deposits: HashMap::with_capacity_and_hasher(capacity, BuildIdHasher::default()),
+            withdrawals: HashMap::with_capacity_and_hasher(capacity, BuildIdHasher::default()),
+            fees: Vec::new(),
+            adjustments: Vec::new(),
+            holds: HashMap::default(),
+            auto_resolved: Vec::new(),
+            memos: HashMap::default(),
+            checkpoints: Checkpoints::default(),
         }
     }
 
@@ -62,13 +161,213 @@ impl Account {
             client_id,
             status,
             account_snapshot,
-            deposits,
-            withdrawals,
+            deposits: deposits.into_iter().collect(),
+            withdrawals: withdrawals.into_iter().collect(),
+            fees: Vec::new(),
+            adjustments: Vec::new(),
+            holds: HashMap::default(),
+            auto_resolved: Vec::new(),
+            memos: HashMap::default(),
+            checkpoints: Checkpoints::default(),
         }
     }
+
+    pub fn client_id(&self) -> ClientId {
+        self.client_id
+    }
+
+    pub fn status(&self) -> AccountStatus {
+        self.status
+    }
+
+    pub fn set_status(&mut self, status: AccountStatus) {
+        self.status = status;
+    }
+
+    pub fn snapshot(&self) -> AccountSnapshot {
+        self.account_snapshot
+    }
+
+    pub fn available(&self) -> Amount {
+        self.account_snapshot.available
+    }
+
+    pub fn held(&self) -> Amount {
+        self.account_snapshot.held
+    }
+
+    /// The available and held funds together.
+    pub fn total(&self) -> Amount {
+        self.account_snapshot.available + self.account_snapshot.held
+    }
+
+    /// The balances, for a transactor to move funds between them.
+    pub fn snapshot_mut(&mut self) -> &mut AccountSnapshot {
+        &mut self.account_snapshot
+    }
+
+    pub fn deposit(&self, transaction_id: TransactionId) -> Option<&Deposit> {
+        self.deposits.get(&transaction_id)
+    }
+
+    pub fn deposit_mut(&mut self, transaction_id: TransactionId) -> Option<&mut Deposit> {
+        self.deposits.get_mut(&transaction_id)
+    }
+
+    /// All the deposits of the account, in no particular order.
+    pub fn deposits(&self) -> impl Iterator<Item = (TransactionId, &Deposit)> {
+        self.deposits
+            .iter()
+            .map(|(transaction_id, deposit)| (*transaction_id, deposit))
+    }
+
+    pub fn withdrawal(&self, transaction_id: TransactionId) -> Option<&Withdrawal> {
+        self.withdrawals.get(&transaction_id)
+    }
+
+    /// All the withdrawals of the account, in no particular order.
+    pub fn withdrawals(&self) -> impl Iterator<Item = (TransactionId, &Withdrawal)> {
+        self.withdrawals
+            .iter()
+            .map(|(transaction_id, withdrawal)| (*transaction_id, withdrawal))
+    }
+
+    /// Records an accepted deposit. The balances are left to the caller.
+    pub fn record_deposit(
+        &mut self,
+        transaction_id: TransactionId,
+        amount: Amount,
+        timestamp: Option<Timestamp>,
+    ) {
+        let sequence = self.deposits.len() + self.withdrawals.len();
+        self.deposits.insert(
+            transaction_id,
+            Deposit {
+                amount,
+                status: DepositStatus::Accepted,
+                timestamp,
+                sequence,
+            },
+        );
+    }
+
+    /// Records an accepted withdrawal. The balances are left to the caller.
+    pub fn record_withdrawal(&mut self, transaction_id: TransactionId, amount: Amount) {
+        self.withdrawals.insert(
+            transaction_id,
+            Withdrawal {
+                amount,
+                status: WithdrawalStatus::Accepted,
+            },
+        );
+    }
+
+    /// Takes the fee for the transaction from the available funds.
+    pub(crate) fn charge_fee(
+        &mut self,
+        transaction_id: TransactionId,
+        kind: FeeKind,
+        amount: Amount,
+    ) {
+        self.account_snapshot.available -= amount;
+        self.fees.push(FeeCharge {
+            transaction_id,
+            kind,
+            amount,
+        });
+    }
+
+    /// Applies the adjustment to the available funds and records it.
+    pub(crate) fn adjust(&mut self, transaction_id: TransactionId, amount: Amount, reason: String) {
+        self.account_snapshot.available += amount;
+        self.adjustments.push(Adjustment {
+            transaction_id,
+            amount,
+            reason,
+        });
+    }
+
+    /// The fees charged to the account, in the order they were.
+    pub fn fees(&self) -> &[FeeCharge] {
+        &self.fees
+    }
+
+    /// The adjustments applied to the account, in the order they were.
+    pub fn adjustments(&self) -> &[Adjustment] {
+        &self.adjustments
+    }
+
+    /// The disputes resolved automatically, in the order they were.
+    pub fn auto_resolved(&self) -> &[AutoResolved] {
+        &self.auto_resolved
+    }
+
+    /// What upstream attached to the given deposit, withdrawal or adjustment,
+    /// if anything.
+    pub fn memo(&self, transaction_id: TransactionId) -> Option<&Memo> {
+        self.memos.get(&transaction_id)
+    }
+
+    pub(crate) fn keep_memo(&mut self, transaction_id: TransactionId, memo: Memo) {
+        self.memos.insert(transaction_id, memo);
+    }
+
+    /// The total of the fees charged to the account.
+    pub fn total_fees(&self) -> Amount {
+        self.fees.iter().map(|fee| fee.amount).sum()
+    }
+
+    /// The number of deposits disputed but neither resolved nor charged back
+    /// yet.
+    pub fn open_disputes(&self) -> usize {
+        self.held_deposits().count()
+    }
+
+    /// Roughly how many bytes the account takes, going by the room allocated
+    /// for its transactions rather than by how many it has.
+    #[cfg(feature = "engine")]
+    pub(crate) fn approximate_bytes(&self) -> usize {
+        size_of::<Self>()
+            + self.deposits.capacity() * size_of::<(TransactionId, Deposit)>()
+            + self.withdrawals.capacity() * size_of::<(TransactionId, Withdrawal)>()
+            + self.fees.capacity() * size_of::<FeeCharge>()
+            + self.adjustments.capacity() * size_of::<Adjustment>()
+            + self.holds.capacity() * size_of::<(TransactionId, Hold)>()
+            + self.memos.capacity() * size_of::<(TransactionId, Memo)>()
+            + self.checkpoints.kept.capacity() * size_of::<Checkpoint>()
+    }
+
+    /// The number of deposits and withdrawals kept, to tell duplicates and
+    /// to be disputed.
+    #[cfg(feature = "engine")]
+    pub(crate) fn transaction_count(&self) -> usize {
+        self.deposits.len() + self.withdrawals.len()
+    }
+
+    /// Gives back the room allocated for transactions the account never
+    /// took, e.g. when it was pre-sized for more of them.
+    #[cfg(feature = "engine")]
+    pub(crate) fn compact(&mut self) {
+        self.deposits.shrink_to_fit();
+        self.withdrawals.shrink_to_fit();
+        self.fees.shrink_to_fit();
+        self.adjustments.shrink_to_fit();
+        self.holds.shrink_to_fit();
+        self.auto_resolved.shrink_to_fit();
+        self.memos.shrink_to_fit();
+    }
+
+    /// The deposits disputed but neither resolved nor charged back yet.
+    pub fn held_deposits(&self) -> impl Iterator<Item = (TransactionId, &Deposit)> {
+        self.deposits
+            .iter()
+            .filter(|(_, deposit)| deposit.status == DepositStatus::Held)
+            .map(|(transaction_id, deposit)| (*transaction_id, deposit))
+    }
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum DepositStatus {
     /// This is the initial state of an accepted deposit.
     Accepted,
@@ -80,7 +379,8 @@ pub enum DepositStatus {
     /// on the subsequent transaction that concludes it.
     Held,
 
-    /// A disputed deposit can be resolved.
+    /// A disputed deposit can be resolved, and so can a charged back one, by
+    /// a representment.
     /// Once resolved, the funds associated with the deposit will be available.
     Resolved,
 
@@ -89,35 +389,167 @@ pub enum DepositStatus {
     ChargedBack,
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub struct Deposit {
+    #[serde(with = "amount_format")]
     pub amount: Amount,
     pub status: DepositStatus,
+
+    /// When the deposit took place, if the input says so.
+    pub timestamp: Option<Timestamp>,
+
+    /// The number of deposits and withdrawals the account had taken before
+    /// this one.
+    pub sequence: usize,
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum WithdrawalStatus {
     /// This is the initial state of an accepted withdrawal.
     Accepted,
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub struct Withdrawal {
-    amount: Amount,
-    status: WithdrawalStatus,
+    #[serde(with = "amount_format")]
+    pub amount: Amount,
+    pub status: WithdrawalStatus,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeKind {
+    Withdrawal,
+    Chargeback,
+}
+
+/// A fee charged to an account, for the transaction it is recorded against.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct FeeCharge {
+    pub transaction_id: TransactionId,
+    pub kind: FeeKind,
+    #[serde(with = "amount_format")]
+    pub amount: Amount,
+}
+
+/// A manual correction applied to an account, with the reason given for it.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Adjustment {
+    pub transaction_id: TransactionId,
+    #[serde(with = "amount_format")]
+    pub amount: Amount,
+    pub reason: String,
+}
+
+/// When a deposit was put on hold by its dispute.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct Hold {
+    /// The number of deposits and withdrawals the account had taken by then.
+    pub sequence: usize,
+
+    /// When the dispute took place, if the input says so.
+    pub timestamp: Option<Timestamp>,
+}
+
+/// A dispute resolved automatically, having been on hold for too long.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct AutoResolved {
+    pub transaction_id: TransactionId,
+    #[serde(with = "amount_format")]
+    pub amount: Amount,
+    pub held: Hold,
 }
 
-#[derive(Debug, Error)]
-pub(crate) enum AccountStoreError {}
+/// The version of the layout [`Account`]s are serialised with, e.g.
+/// `{"version":"1","account":{"client_id":1,...}}`, to be bumped on any
+/// change that would not read back what was serialised before.
+pub const ACCOUNT_VERSION: &str = "1";
+
+#[derive(Serialize)]
+struct VersionedAccountRef<'a> {
+    version: &'a str,
+    #[serde(serialize_with = "Account::serialize")]
+    account: &'a Account,
+}
+
+#[derive(Deserialize)]
+struct VersionedAccount {
+    version: String,
+    #[serde(deserialize_with = "Account::deserialize")]
+    account: Account,
+}
+
+impl Serialize for Account {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        VersionedAccountRef {
+            version: ACCOUNT_VERSION,
+            account: self,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Account {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let VersionedAccount { version, account } = VersionedAccount::deserialize(deserializer)?;
+        if version != ACCOUNT_VERSION {
+            return Err(D::Error::custom(format!(
+                "Unsupported account version `{version}`, expected `{ACCOUNT_VERSION}`"
+            )));
+        }
+        Ok(account)
+    }
+}
 
 impl AccountSnapshot {
     pub fn new(available: i64, held: i64) -> Self {
         AccountSnapshot {
-            available: Amount4DecimalBased(available),
-            held: Amount4DecimalBased(held),
+            available: Amount::new(available),
+            held: Amount::new(held),
         }
     }
     pub(crate) fn empty() -> Self {
         Self::new(0, 0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::model::Amount;
+
+    use super::{Account, AccountStatus, FeeKind};
+
+    #[test]
+    fn accounts_are_serialised_with_their_version() {
+        let mut account = Account::active(1);
+        account.record_deposit(1, Amount::new(15_000), Some(1_700_000_000));
+        account.snapshot_mut().available = Amount::new(15_000);
+        account.record_withdrawal(2, Amount::new(5_000));
+        account.charge_fee(2, FeeKind::Withdrawal, Amount::new(100));
+        account.adjust(3, Amount::new(-1), "rounding".to_string());
+        account.set_status(AccountStatus::Locked);
+
+        let json = serde_json::to_value(&account).unwrap();
+        assert_eq!(json["version"], "1");
+        assert_eq!(json["account"]["status"], "locked");
+        assert_eq!(json["account"]["account_snapshot"]["available"], "1.4899");
+        assert_eq!(json["account"]["deposits"]["1"]["amount"], "1.5000");
+        assert_eq!(json["account"]["fees"][0]["kind"], "withdrawal");
+
+        assert_eq!(serde_json::from_value::<Account>(json).unwrap(), account);
+    }
+
+    #[test]
+    fn accounts_of_an_unknown_version_are_rejected() {
+        let json = serde_json::to_string(&Account::active(1))
+            .unwrap()
+            .replace(r#""version":"1""#, r#""version":"2""#);
+        assert_eq!(
+            serde_json::from_str::<Account>(&json)
+                .unwrap_err()
+                .to_string(),
+            "Unsupported account version `2`, expected `1`"
+        );
+    }
+}
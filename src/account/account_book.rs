@@ -0,0 +1,108 @@
+use alloc::{boxed::Box, collections::BTreeMap};
+
+use crate::model::{ClientId, Transaction};
+
+use super::{
+    Account, AccountTransactor, AccountTransactorError, SimpleAccountTransactor, SuccessStatus,
+};
+
+/// The accounts of the clients, applying one transaction at a time on the
+/// calling thread, for the embedders that have no use for the concurrency of
+/// the engine, e.g. in WASM or behind a C API.
+pub struct AccountBook {
+    accounts: BTreeMap<ClientId, Account>,
+    transactor: Box<dyn AccountTransactor + Send + Sync>,
+}
+
+impl AccountBook {
+    pub fn new(transactor: Box<dyn AccountTransactor + Send + Sync>) -> Self {
+        Self {
+            accounts: BTreeMap::new(),
+            transactor,
+        }
+    }
+
+    /// Applies the transaction to the account of its client, creating an
+    /// active one if the client has not been seen before.
+    pub fn apply(
+        &mut self,
+        transaction: Transaction,
+    ) -> Result<SuccessStatus, AccountTransactorError> {
+        let client_id = transaction.client_id;
+        let account = self
+            .accounts
+            .entry(client_id)
+            .or_insert_with(|| Account::active_with_capacity(client_id, 0));
+        self.transactor.transact(account, transaction)
+    }
+
+    pub fn get(&self, client_id: ClientId) -> Option<&Account> {
+        self.accounts.get(&client_id)
+    }
+
+    /// Ordered by client.
+    pub fn accounts(&self) -> impl Iterator<Item = &Account> {
+        self.accounts.values()
+    }
+}
+
+impl Default for AccountBook {
+    fn default() -> Self {
+        Self::new(Box::new(SimpleAccountTransactor::new()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        account::{AccountTransactorError, SuccessStatus, WithdrawerError},
+        model::{Amount, Transaction, TransactionKind},
+    };
+
+    use super::AccountBook;
+
+    fn transaction(client_id: u16, transaction_id: u32, kind: TransactionKind) -> Transaction {
+        Transaction {
+            client_id,
+            transaction_id,
+            kind,
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
+        }
+    }
+
+    #[test]
+    fn each_client_has_an_account_of_its_own() {
+        let mut book = AccountBook::default();
+        let deposit = TransactionKind::Deposit {
+            amount: Amount::new(20_000),
+        };
+        let withdrawal = TransactionKind::Withdrawal {
+            amount: Amount::new(30_000),
+        };
+
+        assert_eq!(
+            book.apply(transaction(2, 1, deposit.clone())),
+            Ok(SuccessStatus::Transacted)
+        );
+        assert_eq!(
+            book.apply(transaction(1, 2, deposit)),
+            Ok(SuccessStatus::Transacted)
+        );
+        assert_eq!(
+            book.apply(transaction(1, 3, withdrawal)),
+            Err(AccountTransactorError::Withdrawal(
+                WithdrawerError::InsufficientFund
+            ))
+        );
+        assert_eq!(
+            book.accounts()
+                .map(|account| account.client_id())
+                .collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert_eq!(book.get(1).unwrap().available(), Amount::new(20_000));
+        assert!(book.get(3).is_none());
+    }
+}
@@ -0,0 +1,308 @@
+use alloc::collections::BTreeSet;
+
+use thiserror::Error;
+
+use crate::model::{ClientId, TransactionId};
+
+use super::{Account, AccountStatus};
+
+/// Why two accounts could not be merged, see [`Account::merge`].
+#[derive(Debug, Error, PartialEq, Clone)]
+pub enum AccountMergeError {
+    #[error("The account of client {0} cannot be merged with that of client {1}")]
+    DifferentClients(ClientId, ClientId),
+
+    #[error("The transaction {1} of client {0} is in both accounts")]
+    DuplicateTransaction(ClientId, TransactionId),
+}
+
+impl Account {
+    /// Merges in the account of the same client kept by another engine, e.g.
+    /// for another shard or chunk of the input, as if its transactions had
+    /// been applied after those of this account.
+    /// - The balances are added up.
+    /// - The deposits, withdrawals, adjustments and memos of both are kept.
+    ///   A transaction id found in both is a conflict, as there is no telling
+    ///   which of the two is the right one, and the merge fails.
+    /// - The fees and the disputes resolved automatically of `other` follow
+    ///   those of this account.
+    /// - The status is the more restrictive of the two, closed over locked
+    ///   over active.
+    /// - The checkpoints of `other` follow those of this account, their
+    ///   sequences and balances being on top of its own.
+    ///
+    /// Merging is associative for accounts with disjoint transactions. The
+    /// account is left as it is if the merge fails.
+    pub fn merge(&mut self, other: Account) -> Result<(), AccountMergeError> {
+        self.check_merge(&other)?;
+        let offset = self.deposits.len() + self.withdrawals.len();
+        let applied = self.checkpoints.applied;
+        let snapshot = self.account_snapshot;
+        self.checkpoints.applied += other.checkpoints.applied;
+        self.checkpoints
+            .kept
+            .extend(other.checkpoints.kept.into_iter().map(|mut checkpoint| {
+                checkpoint.sequence += applied;
+                checkpoint.snapshot.available += snapshot.available;
+                checkpoint.snapshot.held += snapshot.held;
+                checkpoint
+            }));
+        self.status = restrictive(self.status, other.status);
+        self.account_snapshot.available += other.account_snapshot.available;
+        self.account_snapshot.held += other.account_snapshot.held;
+        self.deposits.extend(
+            other
+                .deposits
+                .into_iter()
+                .map(|(transaction_id, mut deposit)| {
+                    deposit.sequence += offset;
+                    (transaction_id, deposit)
+                }),
+        );
+        self.withdrawals.extend(other.withdrawals);
+        self.fees.extend(other.fees);
+        self.adjustments.extend(other.adjustments);
+        self.holds
+            .extend(other.holds.into_iter().map(|(transaction_id, mut hold)| {
+                hold.sequence += offset;
+                (transaction_id, hold)
+            }));
+        self.auto_resolved
+            .extend(other.auto_resolved.into_iter().map(|mut auto_resolved| {
+                auto_resolved.held.sequence += offset;
+                auto_resolved
+            }));
+        self.memos.extend(other.memos);
+        Ok(())
+    }
+
+    /// Fails the way [`Account::merge`] would, without merging.
+    pub(crate) fn check_merge(&self, other: &Account) -> Result<(), AccountMergeError> {
+        if self.client_id != other.client_id {
+            return Err(AccountMergeError::DifferentClients(
+                self.client_id,
+                other.client_id,
+            ));
+        }
+        let transaction_ids = self.transaction_ids();
+        match other
+            .transaction_ids()
+            .into_iter()
+            .find(|transaction_id| transaction_ids.contains(transaction_id))
+        {
+            Some(transaction_id) => Err(AccountMergeError::DuplicateTransaction(
+                self.client_id,
+                transaction_id,
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// The ids of the deposits, withdrawals and adjustments, and of what the
+    /// memos are attached to.
+    fn transaction_ids(&self) -> BTreeSet<TransactionId> {
+        self.deposits
+            .keys()
+            .chain(self.withdrawals.keys())
+            .chain(self.memos.keys())
+            .copied()
+            .chain(
+                self.adjustments
+                    .iter()
+                    .map(|adjustment| adjustment.transaction_id),
+            )
+            .collect()
+    }
+}
+
+fn restrictive(status: AccountStatus, other: AccountStatus) -> AccountStatus {
+    match (status, other) {
+        (AccountStatus::Closed, _) | (_, AccountStatus::Closed) => AccountStatus::Closed,
+        (AccountStatus::Locked, _) | (_, AccountStatus::Locked) => AccountStatus::Locked,
+        (AccountStatus::Active, AccountStatus::Active) => AccountStatus::Active,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::num::NonZeroU64;
+
+    use proptest::{collection::vec, prelude::*};
+    use rstest::rstest;
+
+    use crate::{
+        account::{
+            Account, AccountSnapshot, AccountStatus, Checkpoint, Checkpointing, DepositStatus,
+            FeeKind,
+        },
+        model::Amount,
+    };
+
+    use super::AccountMergeError;
+
+    /// What an account may have taken, to be spread across the accounts
+    /// merged.
+    #[derive(Debug, Clone)]
+    enum Taken {
+        Deposit { amount: i64, disputed: bool },
+        Withdrawal { amount: i64, fee: i64 },
+        Adjustment { amount: i64 },
+    }
+
+    fn taken() -> impl Strategy<Value = Taken> {
+        prop_oneof![
+            (1..100_000i64, any::<bool>())
+                .prop_map(|(amount, disputed)| Taken::Deposit { amount, disputed }),
+            (1..100_000i64, 0..100i64).prop_map(|(amount, fee)| Taken::Withdrawal { amount, fee }),
+            (-100_000..100_000i64).prop_map(|amount| Taken::Adjustment { amount }),
+        ]
+    }
+
+    fn status() -> impl Strategy<Value = AccountStatus> {
+        prop_oneof![
+            Just(AccountStatus::Active),
+            Just(AccountStatus::Locked),
+            Just(AccountStatus::Closed),
+        ]
+    }
+
+    /// An account of client 1 having taken the transactions, numbered from
+    /// `first_id` so that the accounts merged do not share any.
+    fn account(first_id: u32, status: AccountStatus, taken: &[Taken]) -> Account {
+        let mut account = Account::active(1);
+        for (transaction_id, taken) in (first_id..).zip(taken) {
+            match *taken {
+                Taken::Deposit { amount, disputed } => {
+                    account.record_deposit(transaction_id, Amount::new(amount), None);
+                    account.snapshot_mut().available += Amount::new(amount);
+                    if disputed {
+                        account.deposit_mut(transaction_id).unwrap().status = DepositStatus::Held;
+                        account.snapshot_mut().available -= Amount::new(amount);
+                        account.snapshot_mut().held += Amount::new(amount);
+                    }
+                }
+                Taken::Withdrawal { amount, fee } => {
+                    account.record_withdrawal(transaction_id, Amount::new(amount));
+                    account.snapshot_mut().available -= Amount::new(amount);
+                    account.charge_fee(transaction_id, FeeKind::Withdrawal, Amount::new(fee));
+                }
+                Taken::Adjustment { amount } => {
+                    account.adjust(transaction_id, Amount::new(amount), "merge".to_string());
+                }
+            }
+        }
+        account.set_status(status);
+        account
+    }
+
+    fn merged(mut account: Account, other: Account) -> Account {
+        account.merge(other).unwrap();
+        account
+    }
+
+    proptest! {
+        #[test]
+        fn merging_accounts_of_disjoint_transactions_is_associative(
+            a in (status(), vec(taken(), 0..16)),
+            b in (status(), vec(taken(), 0..16)),
+            c in (status(), vec(taken(), 0..16)),
+        ) {
+            let a = account(0, a.0, &a.1);
+            let b = account(1_000, b.0, &b.1);
+            let c = account(2_000, c.0, &c.1);
+
+            let left = merged(merged(a.clone(), b.clone()), c.clone());
+            let right = merged(a.clone(), merged(b.clone(), c.clone()));
+            prop_assert_eq!(&left, &right);
+            prop_assert_eq!(left.total(), a.total() + b.total() + c.total());
+            let count = |account: &Account| account.deposits().count() + account.withdrawals().count();
+            prop_assert_eq!(count(&left), count(&a) + count(&b) + count(&c));
+        }
+    }
+
+    #[rstest]
+    #[case(AccountStatus::Active, AccountStatus::Active, AccountStatus::Active)]
+    #[case(AccountStatus::Active, AccountStatus::Locked, AccountStatus::Locked)]
+    #[case(AccountStatus::Locked, AccountStatus::Active, AccountStatus::Locked)]
+    #[case(AccountStatus::Locked, AccountStatus::Closed, AccountStatus::Closed)]
+    #[case(AccountStatus::Closed, AccountStatus::Active, AccountStatus::Closed)]
+    fn the_more_restrictive_status_is_kept(
+        #[case] status: AccountStatus,
+        #[case] other: AccountStatus,
+        #[case] expected: AccountStatus,
+    ) {
+        assert_eq!(
+            merged(account(0, status, &[]), account(0, other, &[])).status(),
+            expected
+        );
+    }
+
+    #[test]
+    fn the_deposits_merged_in_follow_those_of_the_account() {
+        let deposit = Taken::Deposit {
+            amount: 10_000,
+            disputed: false,
+        };
+        let account = merged(
+            account(
+                1,
+                AccountStatus::Active,
+                &[deposit.clone(), deposit.clone()],
+            ),
+            account(3, AccountStatus::Active, &[deposit]),
+        );
+        assert_eq!(account.deposit(3).unwrap().sequence, 2);
+        assert_eq!(account.available(), Amount::new(30_000));
+    }
+
+    #[test]
+    fn the_checkpoints_merged_in_follow_those_of_the_account() {
+        let checkpointing = Checkpointing {
+            interval: NonZeroU64::new(1).unwrap(),
+            max_checkpoints: 1,
+        };
+        let deposit = Taken::Deposit {
+            amount: 10_000,
+            disputed: false,
+        };
+        let (mut first, mut second) = (
+            account(1, AccountStatus::Active, core::slice::from_ref(&deposit)),
+            account(2, AccountStatus::Active, &[deposit]),
+        );
+        checkpointing.record(&mut first);
+        checkpointing.record(&mut second);
+        let account = merged(first, second);
+        assert_eq!(account.applied(), 2);
+        assert_eq!(
+            account.checkpoints().copied().collect::<Vec<_>>(),
+            vec![
+                Checkpoint {
+                    sequence: 1,
+                    snapshot: AccountSnapshot::new(10_000, 0),
+                },
+                Checkpoint {
+                    sequence: 2,
+                    snapshot: AccountSnapshot::new(20_000, 0),
+                },
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case(account(1, AccountStatus::Active, &[Taken::Deposit { amount: 1, disputed: false }]), AccountMergeError::DuplicateTransaction(1, 1))]
+    #[case(account(1, AccountStatus::Active, &[Taken::Adjustment { amount: 1 }]), AccountMergeError::DuplicateTransaction(1, 1))]
+    #[case(Account::active(2), AccountMergeError::DifferentClients(1, 2))]
+    fn conflicting_accounts_are_not_merged(
+        #[case] other: Account,
+        #[case] expected: AccountMergeError,
+    ) {
+        let mut account = account(
+            1,
+            AccountStatus::Active,
+            &[Taken::Withdrawal { amount: 1, fee: 0 }],
+        );
+        let before = account.clone();
+        assert_eq!(account.merge(other), Err(expected));
+        assert_eq!(account, before);
+    }
+}
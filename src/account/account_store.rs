@@ -0,0 +1,465 @@
+use std::{
+    mem::size_of,
+    sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard},
+};
+
+use dashmap::DashMap;
+
+use crate::model::{AccountSummary, ClientId, ExtendedAccountSummary};
+
+use super::{Account, AccountMergeError, BuildIdHasher};
+
+/// How much the [`AccountStore`] holds, see [`AccountStore::stats`].
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct StoreStats {
+    pub accounts: usize,
+
+    /// The deposits and withdrawals kept across all the accounts, which are
+    /// what the store grows with over a long run.
+    pub transactions: usize,
+
+    /// As per [`AccountStore::approximate_bytes`].
+    pub approximate_bytes: usize,
+}
+
+/// The accounts of all the clients seen so far.
+/// Each account sits behind its own lock, so that the map's shard lock is
+/// only held to look the account up, and not while a transaction is being
+/// applied to it. Transactions of clients sharing a shard can then be
+/// processed concurrently.
+/// Transactions are applied under a shared quiesce lock, which
+/// [`AccountStore::summaries_snapshot`] takes exclusively to hold them all
+/// back for as long as it summarises the accounts.
+#[derive(Debug, Default)]
+pub struct AccountStore {
+    accounts: DashMap<ClientId, Arc<Mutex<Account>>, BuildIdHasher>,
+    transactions_per_account: usize,
+    quiesce: RwLock<()>,
+}
+
+impl AccountStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A store pre-sized for `client_count` accounts, each of which with room
+    /// for `transactions_per_account` deposits and withdrawals, so that the
+    /// maps do not keep rehashing as they fill up.
+    /// `shard_amount` is the number of shards the accounts are spread across,
+    /// as per [`DashMap::with_capacity_and_shard_amount`]. It is rounded up to
+    /// a power of two. The default is four times the number of CPUs.
+    pub fn with_capacity(
+        client_count: usize,
+        transactions_per_account: usize,
+        shard_amount: Option<usize>,
+    ) -> Self {
+        let accounts = match shard_amount {
+            Some(shard_amount) => DashMap::with_capacity_and_hasher_and_shard_amount(
+                client_count,
+                BuildIdHasher::default(),
+                shard_amount.max(2).next_power_of_two(),
+            ),
+            None => DashMap::with_capacity_and_hasher(client_count, BuildIdHasher::default()),
+        };
+        Self {
+            accounts,
+            transactions_per_account,
+            quiesce: RwLock::new(()),
+        }
+    }
+
+    /// Returns the account of the client, creating an active one if the
+    /// client has not been seen before.
+    pub(crate) fn get_or_create(&self, client_id: ClientId) -> Arc<Mutex<Account>> {
+        self.accounts
+            .entry(client_id)
+            .or_insert_with(|| {
+                Arc::new(Mutex::new(Account::active_with_capacity(
+                    client_id,
+                    self.transactions_per_account,
+                )))
+            })
+            .value()
+            .clone()
+    }
+
+    /// To be held while a transaction is applied, so that it is either in a
+    /// snapshot of the accounts or not at all.
+    pub(crate) fn transacting(&self) -> RwLockReadGuard<'_, ()> {
+        self.quiesce
+            .read()
+            .expect("The quiesce lock is never held across a panic.")
+    }
+
+    /// Returns a copy of the client's account as it currently is.
+    pub fn get(&self, client_id: ClientId) -> Option<Account> {
+        self.accounts
+            .get(&client_id)
+            .map(|entry| lock(entry.value()).clone())
+    }
+
+    /// The summary of the client's account as it currently is, without
+    /// copying the account.
+    pub fn summary(&self, client_id: ClientId) -> Option<AccountSummary> {
+        self.accounts
+            .get(&client_id)
+            .map(|entry| AccountSummary::from(&*lock(entry.value())))
+    }
+
+    /// Maps the client's account as it currently is, without copying it.
+    pub(crate) fn inspect<T>(
+        &self,
+        client_id: ClientId,
+        f: impl FnOnce(&Account) -> T,
+    ) -> Option<T> {
+        self.accounts
+            .get(&client_id)
+            .map(|entry| f(&lock(entry.value())))
+    }
+
+    #[cfg(test)]
+    pub(crate) fn insert(&self, account: Account) {
+        self.accounts
+            .insert(account.client_id, Arc::new(Mutex::new(account)));
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    /// The summaries of all the accounts, in no particular order.
+    pub fn summaries(&self) -> Vec<AccountSummary> {
+        self.map(|account| AccountSummary::from(account))
+    }
+
+    /// The summaries of all the accounts at a single point in time, in no
+    /// particular order.
+    /// Unlike [`AccountStore::summaries`], which summarises each account as
+    /// it is when it gets to it, no transaction is applied in the meantime,
+    /// so that the balances across the clients add up. Processing is held
+    /// back until all the accounts are summarised.
+    pub fn summaries_snapshot(&self) -> Vec<AccountSummary> {
+        self.quiesced(|| self.summaries())
+    }
+
+    /// Runs `f` with no transaction being applied in the meantime.
+    pub(crate) fn quiesced<T>(&self, f: impl FnOnce() -> T) -> T {
+        let _quiesced = self
+            .quiesce
+            .write()
+            .expect("The quiesce lock is never held across a panic.");
+        f()
+    }
+
+    /// The summaries of all the accounts with the fees charged to them, in no
+    /// particular order.
+    pub fn extended_summaries(&self) -> Vec<ExtendedAccountSummary> {
+        self.map(|account| ExtendedAccountSummary::from(account))
+    }
+
+    /// Roughly how many bytes the accounts take, locking one at a time.
+    pub fn approximate_bytes(&self) -> usize {
+        let entries = self.len() * size_of::<(ClientId, Arc<Mutex<Account>>)>();
+        entries
+            + self
+                .map(|account| account.approximate_bytes())
+                .into_iter()
+                .sum::<usize>()
+    }
+
+    /// How many accounts and transactions the store holds, and roughly how
+    /// many bytes they take, e.g. to keep an eye on its growth during a long
+    /// run. The accounts are locked one at a time.
+    pub fn stats(&self) -> StoreStats {
+        let (transactions, bytes): (Vec<usize>, Vec<usize>) = self
+            .map(|account| (account.transaction_count(), account.approximate_bytes()))
+            .into_iter()
+            .unzip();
+        StoreStats {
+            accounts: transactions.len(),
+            transactions: transactions.into_iter().sum(),
+            approximate_bytes: self.len() * size_of::<(ClientId, Arc<Mutex<Account>>)>()
+                + bytes.into_iter().sum::<usize>(),
+        }
+    }
+
+    /// Gives back the room allocated for transactions that never came, e.g.
+    /// when the accounts were pre-sized for more of them than the clients
+    /// took, locking one account at a time.
+    /// The transactions themselves are all kept, as any of them may still be
+    /// disputed or sent again as a duplicate.
+    pub fn compact(&self) {
+        for entry in self.accounts.iter() {
+            lock(entry.value()).compact();
+        }
+    }
+
+    /// Merges in the accounts of another store, e.g. of an engine that went
+    /// through another shard or chunk of the input. The accounts of clients
+    /// found in both are merged as per [`Account::merge`], the others are
+    /// taken as they are.
+    /// Either all the accounts are merged or, should any two of them
+    /// conflict, none is. No transaction is applied in the meantime.
+    pub fn merge(&self, other: AccountStore) -> Result<(), AccountMergeError> {
+        let others: Vec<Account> = other
+            .accounts
+            .into_iter()
+            .map(|(_, account)| match Arc::try_unwrap(account) {
+                Ok(account) => account
+                    .into_inner()
+                    .expect("An account is never left poisoned as transactions do not panic."),
+                Err(account) => lock(&account).clone(),
+            })
+            .collect();
+        self.quiesced(|| {
+            for other in &others {
+                if let Some(entry) = self.accounts.get(&other.client_id) {
+                    lock(entry.value()).check_merge(other)?;
+                }
+            }
+            for other in others {
+                match self.accounts.get(&other.client_id) {
+                    Some(entry) => lock(entry.value()).merge(other)?,
+                    None => {
+                        self.accounts
+                            .insert(other.client_id, Arc::new(Mutex::new(other)));
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Takes the accounts of clients that have none yet, e.g. opened with
+    /// balances carried over. Either all of them are taken or, should a
+    /// client have an account already, none is, that client being given.
+    /// No transaction is applied in the meantime.
+    pub(crate) fn open(&self, accounts: Vec<Account>) -> Result<(), ClientId> {
+        self.quiesced(|| {
+            if let Some(account) = accounts
+                .iter()
+                .find(|account| self.accounts.contains_key(&account.client_id))
+            {
+                return Err(account.client_id);
+            }
+            for account in accounts {
+                self.accounts
+                    .insert(account.client_id, Arc::new(Mutex::new(account)));
+            }
+            Ok(())
+        })
+    }
+
+    /// Maps each of the accounts, in no particular order, locking one at a
+    /// time.
+    pub(crate) fn map<T>(&self, f: impl Fn(&Account) -> T) -> Vec<T> {
+        self.accounts
+            .iter()
+            .map(|entry| f(&lock(entry.value())))
+            .collect()
+    }
+}
+
+/// Locks the account.
+/// A poisoned lock means a transaction panicked half way through, in which
+/// case the account can no longer be trusted and there is no point carrying
+/// on.
+pub(crate) fn lock(account: &Mutex<Account>) -> MutexGuard<'_, Account> {
+    account
+        .lock()
+        .expect("An account is never left poisoned as transactions do not panic.")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        sync::{mpsc::channel, Arc},
+        thread,
+        time::Duration,
+    };
+
+    use proptest::{collection::btree_map, prelude::*};
+
+    use crate::{
+        account::{Account, AccountMergeError},
+        model::{AccountSummary, Amount},
+    };
+
+    use super::{lock, AccountStore};
+
+    /// A store of the clients' deposits, numbered from `first_id`.
+    fn store(first_id: u32, deposits: &BTreeMap<u16, Vec<i64>>) -> AccountStore {
+        let store = AccountStore::new();
+        for (client_id, amounts) in deposits {
+            let account = store.get_or_create(*client_id);
+            let mut account = lock(&account);
+            for (transaction_id, amount) in (first_id..).zip(amounts) {
+                account.record_deposit(transaction_id, Amount::new(*amount), None);
+                account.snapshot_mut().available += Amount::new(*amount);
+            }
+        }
+        store
+    }
+
+    fn merged(store: AccountStore, other: AccountStore) -> AccountStore {
+        store.merge(other).unwrap();
+        store
+    }
+
+    fn accounts(store: &AccountStore) -> BTreeMap<u16, Account> {
+        store
+            .accounts
+            .iter()
+            .map(|entry| (*entry.key(), lock(entry.value()).clone()))
+            .collect()
+    }
+
+    #[test]
+    fn creates_an_active_account_for_a_new_client() {
+        let store = AccountStore::new();
+        assert_eq!(store.get(1), None);
+
+        store.get_or_create(1);
+        assert_eq!(store.get(1), Some(Account::active(1)));
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn returns_the_same_account_for_the_same_client() {
+        let store = AccountStore::new();
+        let account = store.get_or_create(1);
+        assert!(Arc::ptr_eq(&account, &store.get_or_create(1)));
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn pre_sizes_the_accounts() {
+        let store = AccountStore::with_capacity(1_000, 64, Some(6));
+        assert!(store.accounts.capacity() >= 1_000);
+
+        let account = store.get_or_create(1);
+        let account = lock(&account);
+        assert!(account.deposits.capacity() >= 64);
+        assert!(account.withdrawals.capacity() >= 64);
+        assert_eq!(*account, Account::active(1));
+    }
+
+    #[test]
+    fn approximate_bytes_grow_with_the_room_for_transactions() {
+        let store = AccountStore::new();
+        assert_eq!(store.approximate_bytes(), 0);
+
+        store.get_or_create(1);
+        let small = store.approximate_bytes();
+        let presized = AccountStore::with_capacity(0, 64, None);
+        presized.get_or_create(1);
+        assert!(small > 0);
+        assert!(presized.approximate_bytes() > small);
+    }
+
+    #[test]
+    fn compaction_gives_back_the_room_of_the_transactions_that_never_came() {
+        let store = AccountStore::with_capacity(0, 1_024, None);
+        for client_id in 1..=2 {
+            lock(&store.get_or_create(client_id)).record_deposit(1, Amount::new(10_000), None);
+        }
+        let before = store.stats();
+        store.compact();
+        let after = store.stats();
+        assert_eq!((after.accounts, after.transactions), (2, 2));
+        assert_eq!((before.accounts, before.transactions), (2, 2));
+        assert!(after.approximate_bytes < before.approximate_bytes);
+        assert_eq!(after.approximate_bytes, store.approximate_bytes());
+    }
+
+    #[test]
+    fn snapshot_waits_for_the_transactions_being_applied() {
+        let store = Arc::new(AccountStore::new());
+        store.get_or_create(1);
+        let transacting = store.transacting();
+
+        let (sender, receiver) = channel();
+        let other = store.clone();
+        let snapshot = thread::spawn(move || sender.send(other.summaries_snapshot()).unwrap());
+        assert!(receiver.recv_timeout(Duration::from_millis(50)).is_err());
+
+        drop(transacting);
+        assert_eq!(receiver.recv().unwrap().len(), 1);
+        snapshot.join().unwrap();
+    }
+
+    #[test]
+    fn holding_an_account_does_not_block_other_clients() {
+        let store = Arc::new(AccountStore::new());
+        let account = store.get_or_create(1);
+        let _guard = lock(&account);
+
+        let other = store.clone();
+        // would dead lock if the shard lock was held along with the account
+        thread::spawn(move || {
+            for client_id in 2..=64 {
+                other.get_or_create(client_id);
+            }
+        })
+        .join()
+        .unwrap();
+        assert_eq!(store.len(), 64);
+    }
+
+    proptest! {
+        #[test]
+        fn merging_stores_of_disjoint_transactions_is_associative(
+            a in btree_map(1..8u16, prop::collection::vec(1..100_000i64, 0..8), 0..8),
+            b in btree_map(1..8u16, prop::collection::vec(1..100_000i64, 0..8), 0..8),
+            c in btree_map(1..8u16, prop::collection::vec(1..100_000i64, 0..8), 0..8),
+        ) {
+            let left = merged(merged(store(0, &a), store(100, &b)), store(200, &c));
+            let right = merged(store(0, &a), merged(store(100, &b), store(200, &c)));
+            prop_assert_eq!(accounts(&left), accounts(&right));
+        }
+    }
+
+    #[test]
+    fn no_account_is_merged_if_any_conflicts() {
+        let merging = store(1, &BTreeMap::from([(1, vec![10_000]), (2, vec![10_000])]));
+        let conflicting = store(2, &BTreeMap::from([(1, vec![10_000]), (3, vec![10_000])]));
+        lock(&conflicting.get_or_create(2)).record_deposit(1, Amount::new(10_000), None);
+        let before = accounts(&merging);
+
+        assert_eq!(
+            merging.merge(conflicting),
+            Err(AccountMergeError::DuplicateTransaction(2, 1))
+        );
+        assert_eq!(accounts(&merging), before);
+
+        merging
+            .merge(store(
+                2,
+                &BTreeMap::from([(1, vec![5_000]), (3, vec![5_000])]),
+            ))
+            .unwrap();
+        let mut totals = merging
+            .summaries()
+            .into_iter()
+            .map(
+                |AccountSummary {
+                     client_id, total, ..
+                 }| (client_id, total),
+            )
+            .collect::<Vec<_>>();
+        totals.sort();
+        assert_eq!(
+            totals,
+            vec![
+                (1, Amount::new(15_000)),
+                (2, Amount::new(10_000)),
+                (3, Amount::new(5_000))
+            ]
+        );
+    }
+}
@@ -1,32 +1,85 @@
+use alloc::{boxed::Box, vec::Vec};
+
 use thiserror::Error;
 
 use crate::{
-    account::Account,
-    model::{Transaction, TransactionKind},
+    account::{
+        Account, AccountStatus, AutoResolution, AutoResolved, Checkpointing, CreditLimits,
+        DepositStatus, DisputeWindow, FeeKind, FeePolicy, Hold, TransactionLimits,
+    },
+    model::{Amount, Timestamp, Transaction, TransactionId, TransactionKind},
 };
 
 use super::transactors::{
     backcharger::{Backcharger, BackchargerError, CreditBackcharger},
     depositor::{Depositor, DepositorError, SimpleDepositor},
     disputer::{CreditDisputer, Disputer, DisputerError},
+    representer::{CreditRepresenter, Representer, RepresenterError},
     resolver::{CreditResolver, Resolver, ResolverError},
     withdrawer::{SimpleWithdrawer, Withdrawer, WithdrawerError},
 };
 
+/// Applies a transaction to the account of its client.
+/// The account is left untouched when the transaction is rejected.
 pub trait AccountTransactor {
     fn transact(
         &self,
         account: &mut Account,
         transaction: Transaction,
-    ) -> Result<(), AccountTransactorError>;
+    ) -> Result<SuccessStatus, AccountTransactorError>;
 }
 
+/// The [`AccountTransactor`] of the crate, handing each kind of transaction
+/// to a transactor of its own and holding the account to an
+/// [`AccountPolicy`].
 pub struct SimpleAccountTransactor {
     depositor: Box<dyn Depositor + Send + Sync>,
     withdrawer: Box<dyn Withdrawer + Send + Sync>,
     disputer: Box<dyn Disputer + Send + Sync>,
     resolver: Box<dyn Resolver + Send + Sync>,
     backcharger: Box<dyn Backcharger + Send + Sync>,
+    representer: Box<dyn Representer + Send + Sync>,
+    dispute_window: Option<DisputeWindow>,
+    fee_policy: FeePolicy,
+    credit_limits: CreditLimits,
+    limits: TransactionLimits,
+    auto_resolution: Option<AutoResolution>,
+    max_open_disputes: Option<usize>,
+    checkpointing: Option<Checkpointing>,
+}
+
+/// The rules an account is held to, beyond those of the transactions
+/// themselves.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AccountPolicy {
+    /// How long after a deposit it can still be disputed, forever if not set.
+    pub dispute_window: Option<DisputeWindow>,
+
+    /// Whether a representment unlocks the account, once none of its
+    /// deposits is charged back any more.
+    pub reactivate_on_representment: bool,
+
+    /// The fees charged for the transactions.
+    pub fee_policy: FeePolicy,
+
+    /// How far below zero withdrawals can take the available funds.
+    pub credit_limits: CreditLimits,
+
+    /// Bounds on the amounts of the transactions.
+    pub limits: TransactionLimits,
+
+    /// How long a dispute can stay on hold before it is resolved
+    /// automatically, forever if not set.
+    pub auto_resolution: Option<AutoResolution>,
+
+    /// How many deposits of an account can be disputed at the same time, any
+    /// number of them if not set, so that a client cannot flood the engine
+    /// with disputes.
+    pub max_open_disputes: Option<usize>,
+
+    /// How often the balances of an account are kept as it applies
+    /// transactions, none of them if not set, see [`Account::balance_at`].
+    pub checkpointing: Option<Checkpointing>,
 }
 
 impl AccountTransactor for SimpleAccountTransactor {
@@ -34,40 +87,138 @@ impl AccountTransactor for SimpleAccountTransactor {
         &self,
         account: &mut Account,
         transaction: Transaction,
-    ) -> Result<(), AccountTransactorError> {
+    ) -> Result<SuccessStatus, AccountTransactorError> {
         let Transaction {
             transaction_id,
             kind,
             client_id: _,
+            timestamp,
+            memo,
+            ingest_sequence: _,
         } = transaction;
-        match kind {
+        if account.status == AccountStatus::Closed {
+            return match kind {
+                TransactionKind::Close => Ok(SuccessStatus::Duplicate),
+                _ => Err(AccountTransactorError::AccountClosed),
+            };
+        }
+        self.limits.check(&kind)?;
+        let keeps_memo = matches!(
+            kind,
+            TransactionKind::Deposit { .. }
+                | TransactionKind::Withdrawal { .. }
+                | TransactionKind::Adjustment { .. }
+        );
+        let status = match kind {
             TransactionKind::Deposit { amount } => {
-                let _status = self.depositor.deposit(account, transaction_id, amount)?;
+                self.depositor
+                    .deposit(account, transaction_id, amount, timestamp)?
             }
             TransactionKind::Withdrawal { amount } => {
-                let _status = self.withdrawer.withdraw(account, transaction_id, amount)?;
+                let fee = self.check_withdrawal(account, transaction_id, amount)?;
+                let status = self.withdrawer.withdraw(account, transaction_id, amount)?;
+                if status == SuccessStatus::Transacted && !fee.is_zero() {
+                    account.charge_fee(transaction_id, FeeKind::Withdrawal, fee);
+                }
+                status
             }
             TransactionKind::Dispute => {
-                let _status = self.disputer.dispute(account, transaction_id)?;
+                self.check_dispute_window(account, transaction_id, timestamp)?;
+                self.check_open_disputes(account, transaction_id)?;
+                let status = self.disputer.dispute(account, transaction_id)?;
+                if status == SuccessStatus::Transacted && self.auto_resolution.is_some() {
+                    let sequence = account.deposits.len() + account.withdrawals.len();
+                    account.holds.insert(
+                        transaction_id,
+                        Hold {
+                            sequence,
+                            timestamp,
+                        },
+                    );
+                }
+                status
             }
             TransactionKind::Resolve => {
-                let _status = self.resolver.resolve(account, transaction_id)?;
+                let status = self.resolver.resolve(account, transaction_id)?;
+                account.holds.remove(&transaction_id);
+                status
             }
             TransactionKind::ChargeBack => {
-                let _status = self.backcharger.chargeback(account, transaction_id)?;
+                let status = self.backcharger.chargeback(account, transaction_id)?;
+                account.holds.remove(&transaction_id);
+                if let (SuccessStatus::Transacted, Some(fee), Some(deposit)) = (
+                    status,
+                    self.fee_policy.chargeback,
+                    account.deposits.get(&transaction_id),
+                ) {
+                    let fee = fee.on(deposit.amount);
+                    if !fee.is_zero() {
+                        account.charge_fee(transaction_id, FeeKind::Chargeback, fee);
+                    }
+                }
+                status
+            }
+            TransactionKind::Representment => {
+                self.representer.represent(account, transaction_id)?
+            }
+            TransactionKind::Close => {
+                account.status = AccountStatus::Closed;
+                SuccessStatus::Transacted
+            }
+            TransactionKind::Adjustment { amount, reason } => {
+                match account
+                    .adjustments
+                    .iter()
+                    .find(|adjustment| adjustment.transaction_id == transaction_id)
+                {
+                    Some(adjustment) if adjustment.amount == amount => SuccessStatus::Duplicate,
+                    Some(_) => return Err(AccountTransactorError::ConflictingAdjustment),
+                    None => {
+                        account.adjust(transaction_id, amount, reason);
+                        SuccessStatus::Transacted
+                    }
+                }
+            }
+        };
+        if status == SuccessStatus::Transacted {
+            if let Some(memo) = memo.filter(|_| keeps_memo) {
+                account.keep_memo(transaction_id, *memo);
+            }
+            self.auto_resolve(account, timestamp);
+            if let Some(checkpointing) = &self.checkpointing {
+                checkpointing.record(account);
             }
         }
-        Ok(())
+        Ok(status)
+    }
+}
+
+impl Default for SimpleAccountTransactor {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl SimpleAccountTransactor {
     pub fn new() -> Self {
+        Self::with_policy(AccountPolicy::default())
+    }
+
+    /// Disputes of deposits outside of the policy's window, if any, are
+    /// rejected with [`AccountTransactorError::DisputeWindowExpired`], and
+    /// those beyond its number of open disputes, if any, with
+    /// [`AccountTransactorError::TooManyOpenDisputes`].
+    pub fn with_policy(policy: AccountPolicy) -> Self {
         let depositor = SimpleDepositor;
-        let withdrawer = SimpleWithdrawer;
+        let withdrawer = SimpleWithdrawer {
+            credit_limits: policy.credit_limits.clone(),
+        };
         let disputer = CreditDisputer;
         let resolver = CreditResolver;
         let backcharger = CreditBackcharger;
+        let representer = CreditRepresenter {
+            reactivate: policy.reactivate_on_representment,
+        };
 
         Self {
             depositor: Box::new(depositor),
@@ -75,101 +226,295 @@ impl SimpleAccountTransactor {
             disputer: Box::new(disputer),
             resolver: Box::new(resolver),
             backcharger: Box::new(backcharger),
+            representer: Box::new(representer),
+            dispute_window: policy.dispute_window,
+            fee_policy: policy.fee_policy,
+            credit_limits: policy.credit_limits,
+            limits: policy.limits,
+            auto_resolution: policy.auto_resolution,
+            max_open_disputes: policy.max_open_disputes,
+            checkpointing: policy.checkpointing,
+        }
+    }
+
+    /// Hands the deposits to the given depositor instead of the default one.
+    pub fn with_depositor(self, depositor: Box<dyn Depositor + Send + Sync>) -> Self {
+        Self { depositor, ..self }
+    }
+
+    /// Hands the withdrawals to the given withdrawer instead of the default one.
+    pub fn with_withdrawer(self, withdrawer: Box<dyn Withdrawer + Send + Sync>) -> Self {
+        Self { withdrawer, ..self }
+    }
+
+    /// Hands the disputes to the given disputer instead of the default one.
+    pub fn with_disputer(self, disputer: Box<dyn Disputer + Send + Sync>) -> Self {
+        Self { disputer, ..self }
+    }
+
+    /// Hands the resolves to the given resolver instead of the default one.
+    pub fn with_resolver(self, resolver: Box<dyn Resolver + Send + Sync>) -> Self {
+        Self { resolver, ..self }
+    }
+
+    /// Hands the chargebacks to the given backcharger instead of the default one.
+    pub fn with_backcharger(self, backcharger: Box<dyn Backcharger + Send + Sync>) -> Self {
+        Self {
+            backcharger,
+            ..self
+        }
+    }
+
+    /// Hands the representments to the given representer instead of the default one.
+    pub fn with_representer(self, representer: Box<dyn Representer + Send + Sync>) -> Self {
+        Self {
+            representer,
+            ..self
+        }
+    }
+
+    /// The fee for the withdrawal, once the account is found able to afford
+    /// both the withdrawal and its fee, and to stay above its minimum balance
+    /// if any.
+    /// Withdrawals the account has already taken, or cannot take as it is
+    /// locked, are left to the withdrawer.
+    fn check_withdrawal(
+        &self,
+        account: &Account,
+        transaction_id: TransactionId,
+        amount: Amount,
+    ) -> Result<Amount, AccountTransactorError> {
+        let fee = match self.fee_policy.withdrawal {
+            Some(fee) => fee.on(amount),
+            None => Amount::ZERO,
+        };
+        if account.status != AccountStatus::Active
+            || account.withdrawals.contains_key(&transaction_id)
+            || (amount + fee).is_zero()
+        {
+            return Ok(fee);
+        }
+        let remaining = account.account_snapshot.available - amount - fee;
+        if (remaining + self.credit_limits.of(account.client_id)).is_negative() {
+            return Err(WithdrawerError::InsufficientFund.into());
+        }
+        if let Some(minimum_balance) = self.limits.minimum_balance {
+            if remaining < minimum_balance {
+                return Err(AccountTransactorError::MinimumBalanceBreached);
+            }
+        }
+        Ok(fee)
+    }
+
+    /// Resolves the disputes on hold for longer than the policy allows, as
+    /// of the transaction just taken by the account.
+    /// The holds of a locked account are dropped rather than resolved, as
+    /// its disputes stay as they are.
+    /// A hold on a deposit the account does not have, which nothing but a
+    /// hand-made account could give it, is left as it is, the transaction
+    /// just taken having gone through already.
+    fn auto_resolve(&self, account: &mut Account, now: Option<Timestamp>) {
+        let Some(auto_resolution) = self.auto_resolution else {
+            return;
+        };
+        let due: Vec<(TransactionId, Hold, Amount)> = account
+            .holds
+            .iter()
+            .filter(|(_, hold)| auto_resolution.is_due(account, hold, now))
+            .filter_map(|(transaction_id, hold)| {
+                let deposit = account.deposits.get(transaction_id)?;
+                Some((*transaction_id, *hold, deposit.amount))
+            })
+            .collect();
+        for (transaction_id, held, amount) in due {
+            account.holds.remove(&transaction_id);
+            if self.resolver.resolve(account, transaction_id) == Ok(SuccessStatus::Transacted) {
+                account.auto_resolved.push(AutoResolved {
+                    transaction_id,
+                    amount,
+                    held,
+                });
+            }
+        }
+    }
+
+    /// Only an accepted deposit of an active account is checked, as nothing
+    /// would come of disputing it otherwise anyway.
+    fn check_dispute_window(
+        &self,
+        account: &Account,
+        transaction_id: TransactionId,
+        dispute_timestamp: Option<Timestamp>,
+    ) -> Result<(), AccountTransactorError> {
+        let (Some(dispute_window), Some(deposit)) =
+            (self.dispute_window, account.deposits.get(&transaction_id))
+        else {
+            return Ok(());
+        };
+        if account.status == AccountStatus::Active
+            && deposit.status == DepositStatus::Accepted
+            && dispute_window.has_expired(account, deposit, dispute_timestamp)
+        {
+            return Err(AccountTransactorError::DisputeWindowExpired);
+        }
+        Ok(())
+    }
+
+    /// As for the dispute window, only an accepted deposit of an active
+    /// account is checked.
+    fn check_open_disputes(
+        &self,
+        account: &Account,
+        transaction_id: TransactionId,
+    ) -> Result<(), AccountTransactorError> {
+        let (Some(max_open_disputes), Some(deposit)) = (
+            self.max_open_disputes,
+            account.deposits.get(&transaction_id),
+        ) else {
+            return Ok(());
+        };
+        if account.status == AccountStatus::Active
+            && deposit.status == DepositStatus::Accepted
+            && account.open_disputes() >= max_open_disputes
+        {
+            return Err(AccountTransactorError::TooManyOpenDisputes);
         }
+        Ok(())
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+/// How a transaction that was not rejected went.
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum SuccessStatus {
+    /// The transaction changed the account.
     Transacted,
+
+    /// The transaction had already been applied, and was let through as is.
     Duplicate,
+
+    /// The transaction was let through without getting to the account, e.g.
+    /// skipped by a filter.
+    NoOp,
 }
 
+/// Why a transaction was not applied to an account.
+/// The errors of the transactors are carried as they are, as the source of
+/// the variant of their transaction kind.
 #[derive(Debug, Error, PartialEq, Clone)]
 pub enum AccountTransactorError {
-    #[error("The account is locked")]
-    AccountLocked,
+    #[error("{0}")]
+    Deposit(#[source] DepositorError),
+
+    #[error("{0}")]
+    Withdrawal(#[source] WithdrawerError),
 
-    #[error("The transaction is incompatible")]
-    IncompatibleTransaction,
+    #[error("{0}")]
+    Dispute(#[source] DisputerError),
 
-    #[error("Insufficient fund for withdrawal")]
-    InsufficientFundForWithdrawal,
+    #[error("{0}")]
+    Resolve(#[source] ResolverError),
 
-    #[error("No tranasction found")]
-    NoTransactionFound,
+    #[error("{0}")]
+    Chargeback(#[source] BackchargerError),
+
+    #[error("{0}")]
+    Representment(#[source] RepresenterError),
+
+    #[error("The account is closed")]
+    AccountClosed,
+
+    #[error("The adjustment conflicts with a previous one of the same id")]
+    ConflictingAdjustment,
+
+    #[error("The dispute window has expired")]
+    DisputeWindowExpired,
+
+    #[error("The account has as many open disputes as it is allowed")]
+    TooManyOpenDisputes,
+
+    #[error("The deposit exceeds the maximum deposit amount")]
+    DepositLimitExceeded,
+
+    #[error("The withdrawal exceeds the maximum withdrawal amount")]
+    WithdrawalLimitExceeded,
+
+    #[error("The transaction exceeds the maximum transaction amount")]
+    TransactionLimitExceeded,
+
+    #[error("The withdrawal would take the account below its minimum balance")]
+    MinimumBalanceBreached,
+
+    #[error("The client is not allowed")]
+    ClientNotAllowed,
 }
 
 impl From<DepositorError> for AccountTransactorError {
     fn from(err: DepositorError) -> Self {
-        match err {
-            DepositorError::AccountLocked => Self::AccountLocked,
-        }
+        Self::Deposit(err)
     }
 }
 
 impl From<WithdrawerError> for AccountTransactorError {
     fn from(err: WithdrawerError) -> Self {
-        match err {
-            WithdrawerError::AccountLocked => Self::AccountLocked,
-            WithdrawerError::InsufficientFund => Self::InsufficientFundForWithdrawal,
-        }
+        Self::Withdrawal(err)
     }
 }
 
 impl From<DisputerError> for AccountTransactorError {
     fn from(err: DisputerError) -> Self {
-        match err {
-            DisputerError::AccountLocked => Self::AccountLocked,
-            DisputerError::NoTransactionFound => Self::NoTransactionFound,
-        }
+        Self::Dispute(err)
     }
 }
 
 impl From<ResolverError> for AccountTransactorError {
     fn from(err: ResolverError) -> Self {
-        match err {
-            ResolverError::AccountLocked => Self::AccountLocked,
-            ResolverError::NonDisputedTransaction => Self::IncompatibleTransaction,
-            ResolverError::NoTransactionFound => Self::NoTransactionFound,
-        }
+        Self::Resolve(err)
     }
 }
 
 impl From<BackchargerError> for AccountTransactorError {
     fn from(err: BackchargerError) -> Self {
-        match err {
-            BackchargerError::AccountLocked => Self::AccountLocked,
-            BackchargerError::NoTransactionFound => Self::NoTransactionFound,
-            BackchargerError::NonDisputedTransaction => Self::IncompatibleTransaction,
-        }
+        Self::Chargeback(err)
+    }
+}
+
+impl From<RepresenterError> for AccountTransactorError {
+    fn from(err: RepresenterError) -> Self {
+        Self::Representment(err)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use std::{error::Error, num::NonZeroU64};
 
     use rstest::rstest;
 
     use crate::{
         account::{
+            checkpoints::Checkpoints,
             transactors::{
                 backcharger::{mock::MockBackcharger, BackchargerError},
                 depositor::{mock::MockDepositor, DepositorError},
                 disputer::{mock::MockDisputer, DisputerError},
-                resolver::{mock::MockResolver, ResolverError},
+                representer::{mock::MockRepresenter, RepresenterError},
+                resolver::{mock::MockResolver, Resolver, ResolverError},
                 withdrawer::{mock::MockWithdrawer, WithdrawerError},
             },
-            Account, AccountSnapshot, AccountStatus,
-        },
-        model::{
-            Amount, Amount4DecimalBased, ClientId, Transaction, TransactionId, TransactionKind,
+            Account, AccountSnapshot,
+            AccountStatus::{self, Active, Closed, Locked},
+            Adjustment, AutoResolution, Checkpoint, Checkpointing, CreditLimits, Deposit,
+            DepositStatus::{self, Accepted, Held},
+            DisputeWindow, Fee, FeeCharge, FeeKind, FeePolicy, Hold, Incompatibility,
+            TransactionLimits,
         },
+        collections::HashMap,
+        model::{Amount, ClientId, Memo, Transaction, TransactionId, TransactionKind},
     };
 
     use super::{
-        AccountTransactor, AccountTransactorError, SimpleAccountTransactor, SuccessStatus,
+        AccountPolicy, AccountTransactor, AccountTransactorError, SimpleAccountTransactor,
+        SuccessStatus::{self, Duplicate, Transacted},
     };
 
     impl SimpleAccountTransactor {
@@ -186,6 +531,14 @@ mod tests {
                 disputer: Box::new(disputer),
                 resolver: Box::new(resolver),
                 backcharger: Box::new(backcharger),
+                representer: Box::new(MockRepresenter::new()),
+                dispute_window: None,
+                fee_policy: FeePolicy::default(),
+                credit_limits: CreditLimits::default(),
+                limits: TransactionLimits::default(),
+                auto_resolution: None,
+                max_open_disputes: None,
+                checkpointing: None,
             }
         }
     }
@@ -195,14 +548,14 @@ mod tests {
     fn calls_depositor_for_deposit() {
         let mut account = some_account();
         let transaction_id: TransactionId = 0;
-        let amount: Amount = Amount4DecimalBased(0);
+        let amount: Amount = Amount::new(0);
 
         let depositor = MockDepositor::new();
         let withdrawer = MockWithdrawer::new();
         let disputer = MockDisputer::new();
         let resolver = MockResolver::new();
         let backcharger = MockBackcharger::new();
-        depositor.expect(&mut account, transaction_id, amount);
+        depositor.expect(&mut account, transaction_id, amount, None);
         depositor.to_return(Ok(SuccessStatus::Transacted));
         let processor = SimpleAccountTransactor::new_for_test(
             depositor,
@@ -215,21 +568,24 @@ mod tests {
     }
 
     #[rstest]
-    #[case(DepositorError::AccountLocked, AccountTransactorError::AccountLocked)]
+    #[case(
+        DepositorError::AccountLocked,
+        AccountTransactorError::Deposit(DepositorError::AccountLocked)
+    )]
     fn error_returned_from_depositor_is_propagated(
         #[case] depositor_error: DepositorError,
         #[case] expected_error: AccountTransactorError,
     ) {
         let mut account = some_account();
         let transaction_id: TransactionId = 0;
-        let amount: Amount = Amount4DecimalBased(0);
+        let amount: Amount = Amount::new(0);
 
         let depositor = MockDepositor::new();
         let withdrawer = MockWithdrawer::new();
         let disputer = MockDisputer::new();
         let resolver = MockResolver::new();
         let backcharger = MockBackcharger::new();
-        depositor.expect(&mut account.clone(), transaction_id, amount);
+        depositor.expect(&mut account.clone(), transaction_id, amount, None);
         depositor.to_return(Err(depositor_error));
         let processor = SimpleAccountTransactor::new_for_test(
             depositor,
@@ -249,7 +605,7 @@ mod tests {
     fn calls_withdrawer_for_withdrawal() {
         let mut account = some_account();
         let transaction_id: TransactionId = 0;
-        let amount: Amount = Amount4DecimalBased(0);
+        let amount: Amount = Amount::new(0);
 
         let depositor = MockDepositor::new();
         let withdrawer = MockWithdrawer::new();
@@ -269,10 +625,28 @@ mod tests {
     }
 
     #[rstest]
-    #[case(WithdrawerError::AccountLocked, AccountTransactorError::AccountLocked)]
+    #[case(WithdrawerError::InsufficientFund.into(), "Insufficient fund for withdrawal")]
+    #[case(
+        ResolverError::NonDisputedTransaction(incompatible(TransactionKind::Resolve)).into(),
+        "The transaction is incompatible: resolve on a deposit in Accepted state (client 1234, tx 0)"
+    )]
+    #[case(DisputerError::NoTransactionFound.into(), "No tranasction found")]
+    fn transactor_error_is_displayed_as_is_and_kept_as_the_source(
+        #[case] error: AccountTransactorError,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(error.to_string(), expected);
+        assert_eq!(error.source().unwrap().to_string(), expected);
+    }
+
+    #[rstest]
+    #[case(
+        WithdrawerError::AccountLocked,
+        AccountTransactorError::Withdrawal(WithdrawerError::AccountLocked)
+    )]
     #[case(
         WithdrawerError::InsufficientFund,
-        AccountTransactorError::InsufficientFundForWithdrawal
+        AccountTransactorError::Withdrawal(WithdrawerError::InsufficientFund)
     )]
     fn error_returned_from_withdrawer_is_propagated(
         #[case] withdrawer_error: WithdrawerError,
@@ -280,7 +654,7 @@ mod tests {
     ) {
         let mut account = some_account();
         let transaction_id: TransactionId = 0;
-        let amount: Amount = Amount4DecimalBased(0);
+        let amount: Amount = Amount::new(0);
 
         let depositor = MockDepositor::new();
         let withdrawer = MockWithdrawer::new();
@@ -326,10 +700,13 @@ mod tests {
     }
 
     #[rstest]
-    #[case(DisputerError::AccountLocked, AccountTransactorError::AccountLocked)]
+    #[case(
+        DisputerError::AccountLocked,
+        AccountTransactorError::Dispute(DisputerError::AccountLocked)
+    )]
     #[case(
         DisputerError::NoTransactionFound,
-        AccountTransactorError::NoTransactionFound
+        AccountTransactorError::Dispute(DisputerError::NoTransactionFound)
     )]
     fn error_returned_from_disputer_is_propagated(
         #[case] disputer_error: DisputerError,
@@ -359,6 +736,110 @@ mod tests {
         );
     }
 
+    #[rstest]
+    #[case(
+        DisputeWindow::Transactions(1),
+        Active,
+        Accepted,
+        Err(AccountTransactorError::DisputeWindowExpired)
+    )]
+    #[case(DisputeWindow::Transactions(2), Active, Accepted, Ok(Transacted))]
+    #[case(DisputeWindow::Transactions(1), Active, Held, Ok(Transacted))]
+    #[case(DisputeWindow::Transactions(1), Locked, Accepted, Ok(Transacted))]
+    fn dispute_window_is_checked_before_disputing(
+        #[case] dispute_window: DisputeWindow,
+        #[case] status: AccountStatus,
+        #[case] deposit_status: DepositStatus,
+        #[case] expected: Result<SuccessStatus, AccountTransactorError>,
+    ) {
+        // the deposit being disputed is followed by 2 more
+        let mut account = Account {
+            status,
+            deposits: (0..3)
+                .map(|sequence| {
+                    (
+                        sequence as TransactionId,
+                        Deposit {
+                            amount: Amount::new(0),
+                            status: deposit_status,
+                            timestamp: None,
+                            sequence,
+                        },
+                    )
+                })
+                .collect(),
+            ..some_account()
+        };
+
+        let disputer = MockDisputer::new();
+        if expected.is_ok() {
+            disputer.expect(&mut account, 0);
+            disputer.to_return(Ok(SuccessStatus::Transacted));
+        }
+        let processor = SimpleAccountTransactor {
+            dispute_window: Some(dispute_window),
+            ..SimpleAccountTransactor::new_for_test(
+                MockDepositor::new(),
+                MockWithdrawer::new(),
+                disputer,
+                MockResolver::new(),
+                MockBackcharger::new(),
+            )
+        };
+        assert_eq!(processor.transact(&mut account, dispute(0)), expected);
+    }
+
+    #[rstest]
+    #[case(2, Active, Accepted, Err(AccountTransactorError::TooManyOpenDisputes))]
+    #[case(3, Active, Accepted, Ok(Transacted))]
+    #[case(2, Active, Held, Ok(Transacted))]
+    #[case(2, Locked, Accepted, Ok(Transacted))]
+    fn open_disputes_are_counted_before_disputing(
+        #[case] max_open_disputes: usize,
+        #[case] status: AccountStatus,
+        #[case] deposit_status: DepositStatus,
+        #[case] expected: Result<SuccessStatus, AccountTransactorError>,
+    ) {
+        // the deposit being disputed and 2 more already on hold
+        let mut account = Account {
+            status,
+            deposits: (0..3)
+                .map(|sequence| {
+                    (
+                        sequence as TransactionId,
+                        Deposit {
+                            amount: Amount::new(0),
+                            status: match sequence {
+                                0 => deposit_status,
+                                _ => Held,
+                            },
+                            timestamp: None,
+                            sequence,
+                        },
+                    )
+                })
+                .collect(),
+            ..some_account()
+        };
+
+        let disputer = MockDisputer::new();
+        if expected.is_ok() {
+            disputer.expect(&mut account, 0);
+            disputer.to_return(Ok(SuccessStatus::Transacted));
+        }
+        let processor = SimpleAccountTransactor {
+            max_open_disputes: Some(max_open_disputes),
+            ..SimpleAccountTransactor::new_for_test(
+                MockDepositor::new(),
+                MockWithdrawer::new(),
+                disputer,
+                MockResolver::new(),
+                MockBackcharger::new(),
+            )
+        };
+        assert_eq!(processor.transact(&mut account, dispute(0)), expected);
+    }
+
     #[test]
     fn calls_resolver_for_resolve() {
         let mut account = some_account();
@@ -382,14 +863,19 @@ mod tests {
     }
 
     #[rstest]
-    #[case(ResolverError::AccountLocked, AccountTransactorError::AccountLocked)]
+    #[case(
+        ResolverError::AccountLocked,
+        AccountTransactorError::Resolve(ResolverError::AccountLocked)
+    )]
     #[case(
         ResolverError::NoTransactionFound,
-        AccountTransactorError::NoTransactionFound
+        AccountTransactorError::Resolve(ResolverError::NoTransactionFound)
     )]
     #[case(
-        ResolverError::NonDisputedTransaction,
-        AccountTransactorError::IncompatibleTransaction
+        ResolverError::NonDisputedTransaction(incompatible(TransactionKind::Resolve)),
+        AccountTransactorError::Resolve(ResolverError::NonDisputedTransaction(incompatible(
+            TransactionKind::Resolve
+        )))
     )]
     fn error_returned_from_resolver_is_propagated(
         #[case] disputer_error: ResolverError,
@@ -442,14 +928,19 @@ mod tests {
     }
 
     #[rstest]
-    #[case(BackchargerError::AccountLocked, AccountTransactorError::AccountLocked)]
+    #[case(
+        BackchargerError::AccountLocked,
+        AccountTransactorError::Chargeback(BackchargerError::AccountLocked)
+    )]
     #[case(
         BackchargerError::NoTransactionFound,
-        AccountTransactorError::NoTransactionFound
+        AccountTransactorError::Chargeback(BackchargerError::NoTransactionFound)
     )]
     #[case(
-        BackchargerError::NonDisputedTransaction,
-        AccountTransactorError::IncompatibleTransaction
+        BackchargerError::NonDisputedTransaction(incompatible(TransactionKind::ChargeBack)),
+        AccountTransactorError::Chargeback(BackchargerError::NonDisputedTransaction(
+            incompatible(TransactionKind::ChargeBack)
+        ))
     )]
     fn error_returned_from_backcharger_is_propagated(
         #[case] disputer_error: BackchargerError,
@@ -479,13 +970,366 @@ mod tests {
         );
     }
 
+    #[rstest]
+    #[case(Ok(SuccessStatus::Transacted), Ok(SuccessStatus::Transacted))]
+    #[case(
+        Err(RepresenterError::NonChargedBackTransaction(incompatible(
+            TransactionKind::Representment
+        ))),
+        Err(
+            AccountTransactorError::Representment(RepresenterError::NonChargedBackTransaction(
+                incompatible(TransactionKind::Representment)
+            ))
+        )
+    )]
+    #[case(
+        Err(RepresenterError::NoTransactionFound),
+        Err(AccountTransactorError::Representment(RepresenterError::NoTransactionFound))
+    )]
+    fn calls_representer_for_representment(
+        #[case] representer_result: Result<SuccessStatus, RepresenterError>,
+        #[case] expected: Result<SuccessStatus, AccountTransactorError>,
+    ) {
+        let mut account = some_account();
+        let transaction_id: TransactionId = 0;
+
+        let representer = MockRepresenter::new();
+        representer.expect(&mut account.clone(), transaction_id);
+        representer.to_return(representer_result);
+        let processor = SimpleAccountTransactor {
+            representer: Box::new(representer),
+            ..SimpleAccountTransactor::new_for_test(
+                MockDepositor::new(),
+                MockWithdrawer::new(),
+                MockDisputer::new(),
+                MockResolver::new(),
+                MockBackcharger::new(),
+            )
+        };
+
+        assert_eq!(processor.transact(&mut account, representment(0)), expected);
+    }
+
+    #[rstest]
+    #[rustfmt::skip(case)]
+    // the mocked withdrawer leaves the funds untouched, only the fee is taken
+    #[case(percentage(50_000), Ok(Transacted), 10_000, Ok(Transacted),                                  9_900, vec![withdrawal_fee(100)])]
+    #[case(percentage(50_000), Ok(Duplicate),  10_000, Ok(Duplicate),                                  10_000, vec![]                   )]
+    #[case(flat(50),           Ok(Transacted),  2_050, Ok(Transacted),                                  2_000, vec![withdrawal_fee(50)] )]
+    #[case(flat(50),           Ok(Transacted),  2_049, Err(WithdrawerError::InsufficientFund.into()),   2_049, vec![]                   )]
+    fn withdrawal_fee_is_charged(
+        #[case] fee: Fee,
+        #[case] withdrawer_result: Result<SuccessStatus, WithdrawerError>,
+        #[case] available: i64,
+        #[case] expected: Result<SuccessStatus, AccountTransactorError>,
+        #[case] expected_available: i64,
+        #[case] expected_fees: Vec<FeeCharge>,
+    ) {
+        let mut account = Account {
+            account_snapshot: AccountSnapshot::new(available, 0),
+            ..some_account()
+        };
+
+        let withdrawer = MockWithdrawer::new();
+        if expected.is_ok() {
+            withdrawer.expect(&mut account.clone(), 0, Amount::new(2_000));
+            withdrawer.to_return(withdrawer_result);
+        }
+        let processor = SimpleAccountTransactor {
+            fee_policy: FeePolicy {
+                withdrawal: Some(fee),
+                ..Default::default()
+            },
+            ..SimpleAccountTransactor::new_for_test(
+                MockDepositor::new(),
+                withdrawer,
+                MockDisputer::new(),
+                MockResolver::new(),
+                MockBackcharger::new(),
+            )
+        };
+
+        assert_eq!(
+            processor.transact(&mut account, withdrawal(0, 2_000)),
+            expected
+        );
+        assert_eq!(
+            account.account_snapshot,
+            AccountSnapshot::new(expected_available, 0)
+        );
+        assert_eq!(account.fees, expected_fees);
+    }
+
+    #[rstest]
+    #[rustfmt::skip(case)]
+    #[case(Active, transaction(0, TransactionKind::Close), Ok(Transacted), Closed)]
+    #[case(Locked, transaction(0, TransactionKind::Close), Ok(Transacted), Closed)]
+    #[case(Closed, transaction(0, TransactionKind::Close), Ok(Duplicate), Closed)]
+    #[case(
+        Closed,
+        deposit(1, 10),
+        Err(AccountTransactorError::AccountClosed),
+        Closed
+    )]
+    #[case(Closed, dispute(0), Err(AccountTransactorError::AccountClosed), Closed)]
+    fn closed_account_rejects_all_but_its_closure(
+        #[case] status: AccountStatus,
+        #[case] transaction: Transaction,
+        #[case] expected: Result<SuccessStatus, AccountTransactorError>,
+        #[case] expected_status: AccountStatus,
+    ) {
+        let mut account = Account {
+            status,
+            ..some_account()
+        };
+        let processor = SimpleAccountTransactor::new_for_test(
+            MockDepositor::new(),
+            MockWithdrawer::new(),
+            MockDisputer::new(),
+            MockResolver::new(),
+            MockBackcharger::new(),
+        );
+        assert_eq!(processor.transact(&mut account, transaction), expected);
+        assert_eq!(account.status, expected_status);
+    }
+
+    #[rstest]
+    #[rustfmt::skip(case)]
+    #[case(Active, vec![],              5,  Ok(Transacted),                                  15, 1)]
+    #[case(Active, vec![],              -15, Ok(Transacted),                                 -5, 1)]
+    #[case(Locked, vec![],              5,  Ok(Transacted),                                  15, 1)]
+    #[case(Active, vec![(0, 5, "fix")], 5,  Ok(Duplicate),                                   10, 1)]
+    #[case(Active, vec![(0, 5, "fix")], 4,  Err(AccountTransactorError::ConflictingAdjustment), 10, 1)]
+    fn adjustments_are_applied_once_and_recorded(
+        #[case] status: AccountStatus,
+        #[case] previous: Vec<(TransactionId, i64, &str)>,
+        #[case] amount: i64,
+        #[case] expected: Result<SuccessStatus, AccountTransactorError>,
+        #[case] expected_available: i64,
+        #[case] expected_adjustments: usize,
+    ) {
+        let mut account = Account {
+            status,
+            account_snapshot: AccountSnapshot::new(10, 0),
+            adjustments: previous
+                .into_iter()
+                .map(|(transaction_id, amount, reason)| Adjustment {
+                    transaction_id,
+                    amount: Amount::new(amount),
+                    reason: reason.to_string(),
+                })
+                .collect(),
+            ..some_account()
+        };
+        let processor = SimpleAccountTransactor::new();
+        let adjustment = transaction(
+            0,
+            TransactionKind::Adjustment {
+                amount: Amount::new(amount),
+                reason: "fix".to_string(),
+            },
+        );
+        assert_eq!(processor.transact(&mut account, adjustment), expected);
+        assert_eq!(
+            account.snapshot().available,
+            Amount::new(expected_available)
+        );
+        assert_eq!(account.adjustments().len(), expected_adjustments);
+    }
+
+    #[test]
+    fn only_the_transactions_applied_count_towards_the_checkpoints() {
+        let mut account = some_account();
+        let processor = SimpleAccountTransactor::with_policy(AccountPolicy {
+            checkpointing: Some(Checkpointing {
+                interval: NonZeroU64::new(2).unwrap(),
+                max_checkpoints: 4,
+            }),
+            ..AccountPolicy::default()
+        });
+        for transaction in [
+            deposit(1, 10_000),
+            // let through as a duplicate
+            deposit(1, 10_000),
+            // rejected, as there are not enough funds
+            withdrawal(2, 20_000),
+            withdrawal(3, 4_000),
+            dispute(1),
+        ] {
+            let _ = processor.transact(&mut account, transaction);
+        }
+        assert_eq!(account.applied(), 3);
+        assert_eq!(
+            account.checkpoints().copied().collect::<Vec<_>>(),
+            vec![Checkpoint {
+                sequence: 2,
+                snapshot: AccountSnapshot::new(6_000, 0),
+            }]
+        );
+        assert_eq!(
+            account.balance_at(3),
+            Some(AccountSnapshot::new(-4_000, 10_000))
+        );
+    }
+
+    #[test]
+    fn memos_are_kept_with_the_deposits_withdrawals_and_adjustments_taken() {
+        let mut account = some_account();
+        let processor = SimpleAccountTransactor::new();
+        for transaction in [
+            deposit(1, 10_000),
+            withdrawal(2, 4_000),
+            // rejected, as there are not enough funds left
+            withdrawal(3, 9_000),
+            dispute(1),
+        ] {
+            let memo = Memo {
+                text: Some(format!(
+                    "{} {}",
+                    transaction.kind.name(),
+                    transaction.transaction_id
+                )),
+                ..Memo::default()
+            };
+            let _ = processor.transact(
+                &mut account,
+                Transaction {
+                    memo: Some(Box::new(memo)),
+                    ..transaction
+                },
+            );
+        }
+        assert_eq!(
+            account.memo(1).and_then(|memo| memo.text.as_deref()),
+            Some("deposit 1")
+        );
+        assert_eq!(
+            account.memo(2).and_then(|memo| memo.text.as_deref()),
+            Some("withdrawal 2")
+        );
+        assert_eq!(account.memo(3), None);
+    }
+
+    #[rstest]
+    #[case(Ok(Transacted), 9_400, vec![chargeback_fee(600)])]
+    #[case(Ok(Duplicate), 10_000, vec![])]
+    fn chargeback_fee_is_charged(
+        #[case] backcharger_result: Result<SuccessStatus, BackchargerError>,
+        #[case] expected_available: i64,
+        #[case] expected_fees: Vec<FeeCharge>,
+    ) {
+        let mut account = Account {
+            account_snapshot: AccountSnapshot::new(10_000, 0),
+            deposits: HashMap::from_iter([(
+                0,
+                Deposit {
+                    amount: Amount::new(30_000),
+                    status: DepositStatus::ChargedBack,
+                    timestamp: None,
+                    sequence: 0,
+                },
+            )]),
+            ..some_account()
+        };
+
+        let backcharger = MockBackcharger::new();
+        backcharger.expect(&mut account.clone(), 0);
+        backcharger.to_return(backcharger_result);
+        let processor = SimpleAccountTransactor {
+            fee_policy: FeePolicy {
+                chargeback: Some(percentage(20_000)),
+                ..Default::default()
+            },
+            ..SimpleAccountTransactor::new_for_test(
+                MockDepositor::new(),
+                MockWithdrawer::new(),
+                MockDisputer::new(),
+                MockResolver::new(),
+                backcharger,
+            )
+        };
+
+        processor.transact(&mut account, chargeback(0)).unwrap();
+        assert_eq!(
+            account.account_snapshot,
+            AccountSnapshot::new(expected_available, 0)
+        );
+        assert_eq!(account.fees, expected_fees);
+    }
+
+    /// Resolves whatever it is asked to, whether there is such a deposit or
+    /// not.
+    struct CarelessResolver;
+
+    impl Resolver for CarelessResolver {
+        fn resolve(
+            &self,
+            _account: &mut Account,
+            _transaction_id: TransactionId,
+        ) -> Result<SuccessStatus, ResolverError> {
+            Ok(Transacted)
+        }
+    }
+
+    #[test]
+    fn a_hold_on_a_deposit_the_account_does_not_have_is_not_auto_resolved() {
+        let mut account = some_account();
+        let hold = Hold {
+            sequence: 0,
+            timestamp: None,
+        };
+        account.holds.insert(7, hold);
+        let processor = SimpleAccountTransactor::with_policy(AccountPolicy {
+            auto_resolution: Some(AutoResolution::Transactions(1)),
+            ..AccountPolicy::default()
+        })
+        .with_resolver(Box::new(CarelessResolver));
+        assert_eq!(
+            processor.transact(&mut account, deposit(0, 10_000)),
+            Ok(Transacted)
+        );
+        assert_eq!(account.account_snapshot, AccountSnapshot::new(10_000, 0));
+        assert_eq!(account.holds, HashMap::from_iter([(7, hold)]));
+        assert_eq!(account.auto_resolved, vec![]);
+    }
+
+    fn flat(amount: i64) -> Fee {
+        Fee::Flat(Amount::new(amount))
+    }
+
+    fn percentage(percentage: i64) -> Fee {
+        Fee::Percentage(Amount::new(percentage))
+    }
+
+    fn withdrawal_fee(amount: i64) -> FeeCharge {
+        FeeCharge {
+            transaction_id: 0,
+            kind: FeeKind::Withdrawal,
+            amount: Amount::new(amount),
+        }
+    }
+
+    fn chargeback_fee(amount: i64) -> FeeCharge {
+        FeeCharge {
+            transaction_id: 0,
+            kind: FeeKind::Chargeback,
+            amount: Amount::new(amount),
+        }
+    }
+
     fn some_account() -> Account {
         Account {
             client_id: 1234,
             status: AccountStatus::Active,
             account_snapshot: AccountSnapshot::empty(),
-            deposits: HashMap::new(),
-            withdrawals: HashMap::new(),
+            deposits: HashMap::default(),
+            withdrawals: HashMap::default(),
+            fees: Vec::new(),
+            adjustments: Vec::new(),
+            holds: HashMap::default(),
+            auto_resolved: Vec::new(),
+            memos: HashMap::default(),
+            checkpoints: Checkpoints::default(),
         }
     }
 
@@ -494,8 +1338,11 @@ mod tests {
             client_id: CLIENT_ID,
             transaction_id,
             kind: TransactionKind::Deposit {
-                amount: Amount4DecimalBased(amount),
+                amount: Amount::new(amount),
             },
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
         }
     }
 
@@ -504,8 +1351,11 @@ mod tests {
             client_id: CLIENT_ID,
             transaction_id,
             kind: TransactionKind::Withdrawal {
-                amount: Amount4DecimalBased(amount),
+                amount: Amount::new(amount),
             },
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
         }
     }
 
@@ -521,11 +1371,27 @@ mod tests {
         transaction(transaction_id, TransactionKind::ChargeBack)
     }
 
+    fn representment(transaction_id: TransactionId) -> Transaction {
+        transaction(transaction_id, TransactionKind::Representment)
+    }
+
     fn transaction(transaction_id: TransactionId, kind: TransactionKind) -> Transaction {
         Transaction {
             client_id: CLIENT_ID,
             transaction_id,
             kind,
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
+        }
+    }
+
+    fn incompatible(requested: TransactionKind) -> Incompatibility {
+        Incompatibility {
+            client_id: 1234,
+            transaction_id: 0,
+            requested,
+            status: Accepted,
         }
     }
 }
@@ -0,0 +1,74 @@
+use crate::model::Timestamp;
+
+use super::{dispute_window::SECONDS_PER_DAY, Account, Hold};
+
+/// How long a disputed deposit can stay on hold before it is resolved in
+/// favour of the client, as per the network rules where the merchant wins a
+/// stale dispute.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AutoResolution {
+    /// After the given number of days since the dispute, going by the
+    /// timestamps of the dispute and of a later transaction of the account.
+    /// A hold without a timestamp to go by is never resolved this way.
+    Days(u64),
+
+    /// After the given number of deposits and withdrawals taken by the
+    /// account since the dispute.
+    Transactions(usize),
+}
+
+impl AutoResolution {
+    pub(crate) fn is_due(&self, account: &Account, hold: &Hold, now: Option<Timestamp>) -> bool {
+        match *self {
+            AutoResolution::Days(days) => match (hold.timestamp, now) {
+                (Some(held_at), Some(now)) => now.saturating_sub(held_at) >= days * SECONDS_PER_DAY,
+                _ => false,
+            },
+            AutoResolution::Transactions(count) => {
+                let taken = account.deposits.len() + account.withdrawals.len();
+                taken - hold.sequence >= count
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::{
+        account::{dispute_window::SECONDS_PER_DAY, Account, Hold},
+        model::{Amount, Timestamp},
+    };
+
+    use super::AutoResolution;
+
+    const HELD_AT: Timestamp = 1_700_000_000;
+
+    #[rustfmt::skip]
+    #[rstest]
+    #[case(AutoResolution::Days(7),         Some(HELD_AT), Some(HELD_AT + 7 * SECONDS_PER_DAY - 1), 0, false)]
+    #[case(AutoResolution::Days(7),         Some(HELD_AT), Some(HELD_AT + 7 * SECONDS_PER_DAY),     0, true)]
+    #[case(AutoResolution::Days(7),         None,          Some(HELD_AT + 90 * SECONDS_PER_DAY),    0, false)]
+    #[case(AutoResolution::Days(7),         Some(HELD_AT), None,                                    0, false)]
+    // the account has taken 3 deposits
+    #[case(AutoResolution::Transactions(2), None,          None,                                    1, true)]
+    #[case(AutoResolution::Transactions(3), None,          None,                                    1, false)]
+    fn holds_are_due_once_the_limit_is_reached(
+        #[case] auto_resolution: AutoResolution,
+        #[case] held_at: Option<Timestamp>,
+        #[case] now: Option<Timestamp>,
+        #[case] sequence: usize,
+        #[case] expected: bool,
+    ) {
+        let mut account = Account::active(1);
+        for transaction_id in 0..3 {
+            account.record_deposit(transaction_id, Amount::new(10_000), None);
+        }
+        let hold = Hold {
+            sequence,
+            timestamp: held_at,
+        };
+        assert_eq!(auto_resolution.is_due(&account, &hold, now), expected);
+    }
+}
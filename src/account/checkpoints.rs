@@ -0,0 +1,181 @@
+use alloc::collections::VecDeque;
+use core::num::NonZeroU64;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Account, AccountSnapshot};
+
+/// How often the balances of an account are kept as it applies transactions,
+/// so that its balances at an earlier point can be told, or a replay started
+/// from there, without going through all of its transactions again.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Checkpointing {
+    /// Every how many transactions applied to the account its balances are
+    /// kept.
+    pub interval: NonZeroU64,
+
+    /// The most checkpoints kept for an account, the oldest ones being
+    /// dropped first.
+    pub max_checkpoints: usize,
+}
+
+impl Default for Checkpointing {
+    fn default() -> Self {
+        Self {
+            interval: NonZeroU64::new(1000).expect("1000 is not zero."),
+            max_checkpoints: 16,
+        }
+    }
+}
+
+/// The balances of an account once it had applied that many transactions.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub sequence: u64,
+    pub snapshot: AccountSnapshot,
+}
+
+/// The checkpoints of an account, oldest first, along with the number of
+/// transactions it has applied, counted only while checkpointing.
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct Checkpoints {
+    pub(crate) applied: u64,
+    pub(crate) kept: VecDeque<Checkpoint>,
+}
+
+impl Checkpointing {
+    /// Counts a transaction just applied to the account, keeping its
+    /// balances if it is due a checkpoint.
+    pub(crate) fn record(&self, account: &mut Account) {
+        let checkpoints = &mut account.checkpoints;
+        checkpoints.applied += 1;
+        if !checkpoints.applied.is_multiple_of(self.interval.get()) || self.max_checkpoints == 0 {
+            return;
+        }
+        while checkpoints.kept.len() >= self.max_checkpoints {
+            checkpoints.kept.pop_front();
+        }
+        checkpoints.kept.push_back(Checkpoint {
+            sequence: checkpoints.applied,
+            snapshot: account.account_snapshot,
+        });
+    }
+}
+
+impl Account {
+    /// The number of transactions applied to the account, those rejected or
+    /// let through as duplicates aside, as counted with
+    /// [`AccountPolicy::checkpointing`] set.
+    ///
+    /// [`AccountPolicy::checkpointing`]: super::AccountPolicy::checkpointing
+    pub fn applied(&self) -> u64 {
+        self.checkpoints.applied
+    }
+
+    /// The checkpoints kept, oldest first.
+    pub fn checkpoints(&self) -> impl Iterator<Item = &Checkpoint> {
+        self.checkpoints.kept.iter()
+    }
+
+    /// The balances of the account once it had applied that many
+    /// transactions, if that is its current sequence or one it kept a
+    /// checkpoint for.
+    pub fn balance_at(&self, sequence: u64) -> Option<AccountSnapshot> {
+        if sequence == self.applied() {
+            return Some(self.account_snapshot);
+        }
+        if sequence == 0 {
+            return Some(AccountSnapshot::empty());
+        }
+        self.checkpoint_before(sequence)
+            .filter(|checkpoint| checkpoint.sequence == sequence)
+            .map(|checkpoint| checkpoint.snapshot)
+    }
+
+    /// The latest checkpoint at or before the sequence, from which a replay
+    /// up to it need only apply the transactions after the checkpoint.
+    pub fn checkpoint_before(&self, sequence: u64) -> Option<&Checkpoint> {
+        let kept = &self.checkpoints.kept;
+        match kept.partition_point(|checkpoint| checkpoint.sequence <= sequence) {
+            0 => None,
+            after => kept.get(after - 1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::num::NonZeroU64;
+
+    use rstest::rstest;
+
+    use crate::{
+        account::{Account, AccountSnapshot},
+        model::Amount,
+    };
+
+    use super::{Checkpoint, Checkpointing};
+
+    /// An account having applied 7 deposits of 1 each, a checkpoint every 2
+    /// of them, at most 2 checkpoints being kept.
+    fn account() -> Account {
+        let checkpointing = Checkpointing {
+            interval: NonZeroU64::new(2).unwrap(),
+            max_checkpoints: 2,
+        };
+        let mut account = Account::active(1);
+        for _ in 0..7 {
+            account.snapshot_mut().available += Amount::new(10_000);
+            checkpointing.record(&mut account);
+        }
+        account
+    }
+
+    #[test]
+    fn the_latest_checkpoints_are_kept() {
+        assert_eq!(
+            account().checkpoints().copied().collect::<Vec<_>>(),
+            vec![
+                Checkpoint {
+                    sequence: 4,
+                    snapshot: AccountSnapshot::new(40_000, 0),
+                },
+                Checkpoint {
+                    sequence: 6,
+                    snapshot: AccountSnapshot::new(60_000, 0),
+                },
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case::start(0, Some(0))]
+    #[case::dropped(2, None)]
+    #[case::between(5, None)]
+    #[case::checkpoint(6, Some(60_000))]
+    #[case::current(7, Some(70_000))]
+    #[case::ahead(8, None)]
+    fn balances_are_told_at_the_checkpoints(#[case] sequence: u64, #[case] expected: Option<i64>) {
+        assert_eq!(
+            account().balance_at(sequence),
+            expected.map(|available| AccountSnapshot::new(available, 0))
+        );
+    }
+
+    #[rstest]
+    #[case(3, None)]
+    #[case(4, Some(4))]
+    #[case(5, Some(4))]
+    #[case(100, Some(6))]
+    fn replays_start_from_the_latest_checkpoint_before(
+        #[case] sequence: u64,
+        #[case] expected: Option<u64>,
+    ) {
+        assert_eq!(
+            account()
+                .checkpoint_before(sequence)
+                .map(|checkpoint| checkpoint.sequence),
+            expected
+        );
+    }
+}
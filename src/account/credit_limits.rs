@@ -0,0 +1,24 @@
+use crate::{
+    collections::HashMap,
+    model::{Amount, ClientId},
+};
+
+/// How far below zero withdrawals can take the available funds of the
+/// accounts.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct CreditLimits {
+    /// The limit of the clients that have none of their own, none by default.
+    pub default: Amount,
+
+    /// The limits of particular clients, in place of the default.
+    pub per_client: HashMap<ClientId, Amount>,
+}
+
+impl CreditLimits {
+    pub fn of(&self, client_id: ClientId) -> Amount {
+        self.per_client
+            .get(&client_id)
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
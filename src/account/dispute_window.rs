@@ -0,0 +1,117 @@
+use crate::model::Timestamp;
+
+use super::{Account, Deposit};
+
+pub(super) const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// How long after a deposit it can still be disputed.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DisputeWindow {
+    /// Within the given number of days of the deposit, going by the
+    /// timestamps of the deposit and of the dispute.
+    /// There is no window to tell if either of them has no timestamp, in
+    /// which case the dispute is let through.
+    Days(u64),
+
+    /// Within the given number of deposits and withdrawals taken by the
+    /// account after the deposit.
+    Transactions(usize),
+}
+
+impl DisputeWindow {
+    pub(crate) fn has_expired(
+        &self,
+        account: &Account,
+        deposit: &Deposit,
+        dispute_timestamp: Option<Timestamp>,
+    ) -> bool {
+        match *self {
+            DisputeWindow::Days(days) => match (deposit.timestamp, dispute_timestamp) {
+                (Some(deposited_at), Some(disputed_at)) => {
+                    disputed_at.saturating_sub(deposited_at) > days * SECONDS_PER_DAY
+                }
+                _ => false,
+            },
+            DisputeWindow::Transactions(count) => {
+                let taken = account.deposits.len() + account.withdrawals.len();
+                taken - deposit.sequence - 1 > count
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::{
+        account::{Account, AccountSnapshot, AccountStatus, Deposit, DepositStatus},
+        collections::HashMap,
+        model::{Amount, Timestamp},
+    };
+
+    use super::{DisputeWindow, SECONDS_PER_DAY};
+
+    const DEPOSITED_AT: Timestamp = 1_700_000_000;
+
+    #[rstest]
+    #[case(DisputeWindow::Days(30), Some(DEPOSITED_AT), Some(DEPOSITED_AT), false)]
+    #[case(DisputeWindow::Days(30), Some(DEPOSITED_AT),                          Some(DEPOSITED_AT + 30 * SECONDS_PER_DAY),    false)]
+    #[case(DisputeWindow::Days(30), Some(DEPOSITED_AT),                          Some(DEPOSITED_AT + 30 * SECONDS_PER_DAY + 1), true)]
+    #[case(DisputeWindow::Days(30), Some(DEPOSITED_AT),                          Some(DEPOSITED_AT - 1),                       false)]
+    #[case(DisputeWindow::Days(30), None,                                        Some(DEPOSITED_AT + 90 * SECONDS_PER_DAY),    false)]
+    #[case(DisputeWindow::Days(30), Some(DEPOSITED_AT), None, false)]
+    fn days_window_works(
+        #[case] window: DisputeWindow,
+        #[case] deposited_at: Option<Timestamp>,
+        #[case] disputed_at: Option<Timestamp>,
+        #[case] expected: bool,
+    ) {
+        let deposit = deposit(0, deposited_at);
+        let account = account(vec![deposit]);
+        assert_eq!(
+            window.has_expired(&account, &deposit, disputed_at),
+            expected
+        );
+    }
+
+    #[rstest]
+    // the deposit being disputed is the first of 4 deposits
+    #[case(DisputeWindow::Transactions(3), 0, false)]
+    #[case(DisputeWindow::Transactions(2), 0, true)]
+    #[case(DisputeWindow::Transactions(0), 3, false)]
+    #[case(DisputeWindow::Transactions(0), 2, true)]
+    fn transactions_window_works(
+        #[case] window: DisputeWindow,
+        #[case] sequence: usize,
+        #[case] expected: bool,
+    ) {
+        let account = account((0..4).map(|sequence| deposit(sequence, None)).collect());
+        assert_eq!(
+            window.has_expired(&account, &deposit(sequence, None), None),
+            expected
+        );
+    }
+
+    fn deposit(sequence: usize, timestamp: Option<Timestamp>) -> Deposit {
+        Deposit {
+            amount: Amount::new(10_000),
+            status: DepositStatus::Accepted,
+            timestamp,
+            sequence,
+        }
+    }
+
+    fn account(deposits: Vec<Deposit>) -> Account {
+        Account::new(
+            1,
+            AccountStatus::Active,
+            AccountSnapshot::new(0, 0),
+            deposits
+                .into_iter()
+                .map(|deposit| (deposit.sequence as u32, deposit))
+                .collect(),
+            HashMap::new(),
+        )
+    }
+}
@@ -0,0 +1,61 @@
+use crate::model::Amount;
+
+/// How much is charged for a transaction.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Fee {
+    /// The same amount whatever the amount of the transaction.
+    Flat(Amount),
+
+    /// A percentage of the amount of the transaction, e.g. `1.5` for 1.5%.
+    /// Fractions of the smallest unit are truncated, unless the amounts are
+    /// backed by decimals with the `decimal` feature.
+    Percentage(Amount),
+}
+
+impl Fee {
+    pub(crate) fn on(&self, amount: Amount) -> Amount {
+        match *self {
+            Fee::Flat(fee) => fee,
+            Fee::Percentage(percentage) => amount.percentage(percentage),
+        }
+    }
+}
+
+/// The fees charged to the accounts, none unless set.
+/// A fee is taken from the available funds of the account, and recorded as
+/// a [`FeeCharge`](super::FeeCharge) of its own.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct FeePolicy {
+    /// Charged for every withdrawal, which is rejected if the account cannot
+    /// afford both.
+    pub withdrawal: Option<Fee>,
+
+    /// Charged for every chargeback, even if it leaves the account short.
+    pub chargeback: Option<Fee>,
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::model::Amount;
+
+    use super::Fee;
+
+    #[rstest]
+    #[case(Fee::Flat(Amount::new(5_000)), 100_000, 5_000)]
+    #[case(Fee::Flat(Amount::new(5_000)), 0, 5_000)]
+    #[case(Fee::Percentage(Amount::new(15_000)), 100_000, 1_500)]
+    // the fractions of the smallest unit are kept with the `decimal` feature
+    #[cfg_attr(
+        not(feature = "decimal"),
+        case(Fee::Percentage(Amount::new(15_000)), 333, 4)
+    )]
+    #[cfg_attr(
+        not(feature = "decimal"),
+        case(Fee::Percentage(Amount::new(10_000)), 99, 0)
+    )]
+    fn fee_works(#[case] fee: Fee, #[case] amount: i64, #[case] expected: i64) {
+        assert_eq!(fee.on(Amount::new(amount)), Amount::new(expected));
+    }
+}
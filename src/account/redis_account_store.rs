@@ -0,0 +1,445 @@
+use std::collections::{BTreeMap, HashMap};
+
+use redis::{aio::MultiplexedConnection, AsyncCommands, Client, Script};
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+
+use crate::model::{AccountSummary, ClientId, TransactionId};
+
+use super::{Account, AccountSnapshot};
+
+/// The prefix of the keys unless told otherwise.
+pub const DEFAULT_REDIS_KEY_PREFIX: &str = "jouet_paiement";
+
+/// Writes the fields of an account that changed, unless its version moved on
+/// since it was loaded, in which case nothing is written and 0 is returned.
+/// KEYS: the account's hash, its transactions' hash and the set of clients.
+/// ARGV: the version loaded, the client, the number of account field and
+/// value arguments, then those, then the transaction field and value
+/// arguments.
+const SAVE_SCRIPT: &str = r"
+local version = redis.call('HGET', KEYS[1], 'version') or '0'
+if version ~= ARGV[1] then
+    return 0
+end
+local fields = tonumber(ARGV[3])
+redis.call('HSET', KEYS[1], 'version', tostring(tonumber(version) + 1))
+if fields > 0 then
+    redis.call('HSET', KEYS[1], unpack(ARGV, 4, 3 + fields))
+end
+if #ARGV > 3 + fields then
+    redis.call('HSET', KEYS[2], unpack(ARGV, 4 + fields, #ARGV))
+end
+redis.call('SADD', KEYS[3], ARGV[2])
+return 1
+";
+
+#[derive(Debug, Error, PartialEq)]
+pub enum RedisStoreError {
+    #[error("Failed to reach Redis: {0}")]
+    Redis(String),
+
+    #[error("The account of client {0} is not readable from Redis: {1}")]
+    Unreadable(ClientId, String),
+}
+
+/// The accounts of the clients kept in Redis rather than in memory, so that
+/// several engines, e.g. behind a load balancer in server mode, can share
+/// them.
+/// Each account is a hash of its balances, status, fees and the like, along
+/// with a hash of its deposits, withdrawals and memos, one field per
+/// transaction, so that only the fields a transaction changed are written.
+/// An account is loaded, the transaction applied to it, and the changes
+/// written only if no other engine wrote the account in the meantime, going
+/// by the account's version, see [`RedisTransactionProcessor`].
+/// The whole account is loaded for each transaction, which takes longer the
+/// more transactions the client has.
+///
+/// [`RedisTransactionProcessor`]: crate::transaction_processor::RedisTransactionProcessor
+#[derive(Clone)]
+pub struct RedisAccountStore {
+    connection: MultiplexedConnection,
+    prefix: String,
+    save: Script,
+}
+
+/// An account as loaded, to tell what a transaction changed.
+pub(crate) struct Loaded {
+    client_id: ClientId,
+    version: u64,
+    fields: AccountFields,
+}
+
+/// The fields of the hashes an account is kept in.
+#[derive(Debug, PartialEq)]
+struct AccountFields {
+    account: BTreeMap<String, String>,
+    transactions: BTreeMap<String, String>,
+}
+
+impl RedisAccountStore {
+    /// Connects to the Redis server at the URL, e.g. `redis://127.0.0.1/`.
+    pub async fn connect(url: &str) -> Result<Self, RedisStoreError> {
+        let connection = Client::open(url)
+            .map_err(redis_error)?
+            .get_multiplexed_async_connection()
+            .await
+            .map_err(redis_error)?;
+        Ok(Self {
+            connection,
+            prefix: DEFAULT_REDIS_KEY_PREFIX.to_string(),
+            save: Script::new(SAVE_SCRIPT),
+        })
+    }
+
+    /// The keys are prefixed with `prefix`, e.g. to keep the accounts of
+    /// several deployments apart in one server.
+    pub fn with_prefix(self, prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            ..self
+        }
+    }
+
+    /// Returns the client's account as it currently is.
+    pub async fn get(&self, client_id: ClientId) -> Result<Option<Account>, RedisStoreError> {
+        let (account, loaded) = self.load(client_id).await?;
+        Ok((loaded.version > 0).then_some(account))
+    }
+
+    /// The summaries of all the accounts, in no particular order.
+    pub async fn summaries(&self) -> Result<Vec<AccountSummary>, RedisStoreError> {
+        let client_ids: Vec<ClientId> = self
+            .connection
+            .clone()
+            .smembers(self.clients_key())
+            .await
+            .map_err(redis_error)?;
+        let mut summaries = Vec::with_capacity(client_ids.len());
+        for client_id in client_ids {
+            if let Some(account) = self.get(client_id).await? {
+                summaries.push(AccountSummary::from(&account));
+            }
+        }
+        Ok(summaries)
+    }
+
+    /// The client's account, a new one if the client was never seen.
+    pub(crate) async fn load(
+        &self,
+        client_id: ClientId,
+    ) -> Result<(Account, Loaded), RedisStoreError> {
+        let (account, transactions): (HashMap<String, String>, HashMap<String, String>) =
+            redis::pipe()
+                .atomic()
+                .hgetall(self.account_key(client_id))
+                .hgetall(self.transactions_key(client_id))
+                .query_async(&mut self.connection.clone())
+                .await
+                .map_err(redis_error)?;
+        let mut fields = AccountFields {
+            account: account.into_iter().collect(),
+            transactions: transactions.into_iter().collect(),
+        };
+        let version = match fields.account.remove("version") {
+            Some(version) => version
+                .parse()
+                .map_err(|_| RedisStoreError::Unreadable(client_id, version))?,
+            None => 0,
+        };
+        let account = if version == 0 {
+            Account::active_with_capacity(client_id, 0)
+        } else {
+            fields
+                .to_account(client_id)
+                .map_err(|err| RedisStoreError::Unreadable(client_id, err))?
+        };
+        Ok((
+            account,
+            Loaded {
+                client_id,
+                version,
+                fields,
+            },
+        ))
+    }
+
+    /// Writes the fields of the account that changed since it was loaded,
+    /// returning false, without writing anything, if another engine wrote
+    /// it in the meantime.
+    pub(crate) async fn save(
+        &self,
+        loaded: &Loaded,
+        account: &Account,
+    ) -> Result<bool, RedisStoreError> {
+        let fields = AccountFields::from(account);
+        let account_changes = changes(&loaded.fields.account, &fields.account);
+        let transaction_changes = changes(&loaded.fields.transactions, &fields.transactions);
+        if account_changes.is_empty() && transaction_changes.is_empty() {
+            return Ok(true);
+        }
+        let mut invocation = self.save.prepare_invoke();
+        invocation
+            .key(self.account_key(loaded.client_id))
+            .key(self.transactions_key(loaded.client_id))
+            .key(self.clients_key())
+            .arg(loaded.version)
+            .arg(loaded.client_id)
+            .arg(account_changes.len() * 2);
+        for (field, value) in account_changes.into_iter().chain(transaction_changes) {
+            invocation.arg(field).arg(value);
+        }
+        let saved: u8 = invocation
+            .invoke_async(&mut self.connection.clone())
+            .await
+            .map_err(redis_error)?;
+        Ok(saved == 1)
+    }
+
+    fn account_key(&self, client_id: ClientId) -> String {
+        format!("{}:account:{client_id}", self.prefix)
+    }
+
+    fn transactions_key(&self, client_id: ClientId) -> String {
+        format!("{}:account:{client_id}:transactions", self.prefix)
+    }
+
+    fn clients_key(&self) -> String {
+        format!("{}:clients", self.prefix)
+    }
+}
+
+impl From<&Account> for AccountFields {
+    fn from(account: &Account) -> Self {
+        let AccountSnapshot { available, held } = account.account_snapshot;
+        let account_fields = BTreeMap::from([
+            ("status".to_string(), to_json(&account.status)),
+            ("available".to_string(), available.to_str()),
+            ("held".to_string(), held.to_str()),
+            ("fees".to_string(), to_json(&account.fees)),
+            ("adjustments".to_string(), to_json(&account.adjustments)),
+            // in order, so that they are written only if they changed
+            (
+                "holds".to_string(),
+                to_json(&account.holds.iter().collect::<BTreeMap<_, _>>()),
+            ),
+            ("auto_resolved".to_string(), to_json(&account.auto_resolved)),
+            ("checkpoints".to_string(), to_json(&account.checkpoints)),
+        ]);
+        let transactions =
+            account
+                .deposits
+                .iter()
+                .map(|(transaction_id, deposit)| {
+                    (format!("deposit:{transaction_id}"), to_json(deposit))
+                })
+                .chain(
+                    account
+                        .withdrawals
+                        .iter()
+                        .map(|(transaction_id, withdrawal)| {
+                            (format!("withdrawal:{transaction_id}"), to_json(withdrawal))
+                        }),
+                )
+                .chain(account.memos.iter().map(|(transaction_id, memo)| {
+                    (format!("memo:{transaction_id}"), to_json(memo))
+                }))
+                .collect();
+        Self {
+            account: account_fields,
+            transactions,
+        }
+    }
+}
+
+impl AccountFields {
+    fn to_account(&self, client_id: ClientId) -> Result<Account, String> {
+        let mut account = Account::active_with_capacity(client_id, 0);
+        account.status = from_json(self.field("status")?)?;
+        account.account_snapshot = AccountSnapshot {
+            available: self
+                .field("available")?
+                .parse()
+                .map_err(|err| format!("{err:?}"))?,
+            held: self
+                .field("held")?
+                .parse()
+                .map_err(|err| format!("{err:?}"))?,
+        };
+        account.fees = from_json(self.field("fees")?)?;
+        account.adjustments = from_json(self.field("adjustments")?)?;
+        account.holds = from_json(self.field("holds")?)?;
+        account.auto_resolved = from_json(self.field("auto_resolved")?)?;
+        // missing from the accounts written before there were checkpoints
+        if let Some(checkpoints) = self.account.get("checkpoints") {
+            account.checkpoints = from_json(checkpoints)?;
+        }
+        for (field, value) in &self.transactions {
+            let (kind, transaction_id) = field
+                .split_once(':')
+                .and_then(|(kind, id)| Some((kind, id.parse::<TransactionId>().ok()?)))
+                .ok_or_else(|| format!("Unexpected transaction field `{field}`"))?;
+            match kind {
+                "deposit" => {
+                    account.deposits.insert(transaction_id, from_json(value)?);
+                }
+                "withdrawal" => {
+                    account
+                        .withdrawals
+                        .insert(transaction_id, from_json(value)?);
+                }
+                "memo" => {
+                    account.memos.insert(transaction_id, from_json(value)?);
+                }
+                _ => return Err(format!("Unexpected transaction field `{field}`")),
+            }
+        }
+        Ok(account)
+    }
+
+    fn field(&self, name: &str) -> Result<&str, String> {
+        self.account
+            .get(name)
+            .map(String::as_str)
+            .ok_or_else(|| format!("Missing field `{name}`"))
+    }
+}
+
+/// The fields whose value is new or different.
+fn changes(
+    before: &BTreeMap<String, String>,
+    after: &BTreeMap<String, String>,
+) -> Vec<(String, String)> {
+    after
+        .iter()
+        .filter(|(field, value)| before.get(*field) != Some(*value))
+        .map(|(field, value)| (field.clone(), value.clone()))
+        .collect()
+}
+
+fn to_json(value: &impl Serialize) -> String {
+    serde_json::to_string(value).expect("The parts of an account are always serialisable.")
+}
+
+fn from_json<T: DeserializeOwned>(json: &str) -> Result<T, String> {
+    serde_json::from_str(json).map_err(|err| err.to_string())
+}
+
+fn redis_error(err: redis::RedisError) -> RedisStoreError {
+    RedisStoreError::Redis(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process, sync::Arc};
+
+    use tokio::task::JoinSet;
+
+    use crate::{
+        account::{AccountStatus, DepositStatus, FeeKind, SimpleAccountTransactor},
+        model::{Amount, Memo, Transaction, TransactionKind},
+        transaction_processor::{
+            RedisTransactionProcessor, RetryPolicy, Retrying, TransactionProcessor,
+        },
+    };
+
+    use super::{changes, Account, AccountFields, RedisAccountStore};
+
+    fn account() -> Account {
+        let mut account = Account::active(1);
+        account.record_deposit(1, Amount::new(15_000), Some(1_700_000_000));
+        account.snapshot_mut().available = Amount::new(15_000);
+        account.record_withdrawal(2, Amount::new(5_000));
+        account.charge_fee(2, FeeKind::Withdrawal, Amount::new(100));
+        account.adjust(3, Amount::new(-1), "rounding".to_string());
+        account.keep_memo(
+            1,
+            Memo {
+                text: Some("refund".to_string()),
+                ..Default::default()
+            },
+        );
+        account.set_status(AccountStatus::Locked);
+        account
+    }
+
+    #[test]
+    fn accounts_read_back_from_their_fields() {
+        let account = account();
+        assert_eq!(
+            AccountFields::from(&account).to_account(1).unwrap(),
+            account
+        );
+    }
+
+    #[test]
+    fn only_the_fields_a_transaction_changed_are_written() {
+        let mut account = account();
+        let before = AccountFields::from(&account);
+        account.deposit_mut(1).unwrap().status = DepositStatus::Held;
+        account.snapshot_mut().held = Amount::new(15_000);
+        let after = AccountFields::from(&account);
+        assert_eq!(
+            changes(&before.account, &after.account),
+            vec![("held".to_string(), "1.5000".to_string())]
+        );
+        assert_eq!(
+            changes(&before.transactions, &after.transactions)
+                .into_iter()
+                .map(|(field, _)| field)
+                .collect::<Vec<String>>(),
+            vec!["deposit:1"]
+        );
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server, at $REDIS_URL or on localhost"]
+    async fn engines_sharing_the_store_apply_every_transaction() {
+        let url = env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
+        let accounts = RedisAccountStore::connect(&url)
+            .await
+            .unwrap()
+            .with_prefix(format!("jouet_paiement_test_{}", process::id()));
+        // two engines, each with a processor of its own
+        let processors: Vec<_> = (0..2)
+            .map(|_| {
+                Arc::new(Retrying::new(
+                    Arc::new(RedisTransactionProcessor::new(
+                        accounts.clone(),
+                        Box::new(SimpleAccountTransactor::new()),
+                    )),
+                    RetryPolicy {
+                        max_attempts: 100,
+                        ..Default::default()
+                    },
+                    Default::default(),
+                ))
+            })
+            .collect();
+        let mut deposits = JoinSet::new();
+        for transaction_id in 0..20 {
+            let processor = processors[transaction_id as usize % 2].clone();
+            deposits.spawn(async move {
+                processor
+                    .process(Transaction {
+                        client_id: 1,
+                        transaction_id,
+                        kind: TransactionKind::Deposit {
+                            amount: Amount::new(10_000),
+                        },
+                        timestamp: None,
+                        memo: None,
+                        ingest_sequence: None,
+                    })
+                    .await
+            });
+        }
+        while let Some(deposited) = deposits.join_next().await {
+            deposited.unwrap().unwrap();
+        }
+        assert_eq!(
+            accounts.get(1).await.unwrap().unwrap().available(),
+            Amount::new(200_000)
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use crate::model::{Amount, TransactionKind};
+
+use super::account_transactor::AccountTransactorError;
+
+/// Bounds on the amounts of the transactions, so that obviously corrupt
+/// records are rejected rather than making their way into the balances.
+/// None of them apply unless set.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct TransactionLimits {
+    pub max_deposit: Option<Amount>,
+    pub max_withdrawal: Option<Amount>,
+
+    /// The largest amount of any transaction that comes with one.
+    pub max_transaction: Option<Amount>,
+
+    /// What a withdrawal, and its fee, has to leave available at least.
+    pub minimum_balance: Option<Amount>,
+}
+
+impl TransactionLimits {
+    /// Checks the amount of the transaction, if it has one, against the
+    /// limits for its kind first and then against the one for all
+    /// transactions.
+    pub(crate) fn check(&self, kind: &TransactionKind) -> Result<(), AccountTransactorError> {
+        let (amount, max, exceeded) = match *kind {
+            TransactionKind::Deposit { amount } => (
+                amount,
+                self.max_deposit,
+                AccountTransactorError::DepositLimitExceeded,
+            ),
+            TransactionKind::Withdrawal { amount } => (
+                amount,
+                self.max_withdrawal,
+                AccountTransactorError::WithdrawalLimitExceeded,
+            ),
+            TransactionKind::Adjustment { amount, .. } => (
+                amount.abs(),
+                None,
+                AccountTransactorError::TransactionLimitExceeded,
+            ),
+            _ => return Ok(()),
+        };
+        if max.is_some_and(|max| amount > max) {
+            return Err(exceeded);
+        }
+        if self.max_transaction.is_some_and(|max| amount > max) {
+            return Err(AccountTransactorError::TransactionLimitExceeded);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::{
+        account::account_transactor::AccountTransactorError::{
+            self, DepositLimitExceeded, TransactionLimitExceeded, WithdrawalLimitExceeded,
+        },
+        model::{Amount, TransactionKind},
+    };
+
+    use super::TransactionLimits;
+
+    #[rstest]
+    #[case(deposit(100),    Ok(()))]
+    #[case(deposit(101), Err(DepositLimitExceeded))]
+    #[case(deposit(1_001), Err(DepositLimitExceeded))]
+    #[case(withdrawal(200), Ok(()))]
+    #[case(withdrawal(201), Err(WithdrawalLimitExceeded))]
+    #[case(TransactionKind::Dispute, Ok(()))]
+    fn limits_of_each_kind_are_checked(
+        #[case] kind: TransactionKind,
+        #[case] expected: Result<(), AccountTransactorError>,
+    ) {
+        let limits = TransactionLimits {
+            max_deposit: Some(Amount::new(100)),
+            max_withdrawal: Some(Amount::new(200)),
+            max_transaction: Some(Amount::new(1_000)),
+            minimum_balance: None,
+        };
+        assert_eq!(limits.check(&kind), expected);
+    }
+
+    #[rstest]
+    #[case(deposit(1_000),    Ok(()))]
+    #[case(deposit(1_001), Err(TransactionLimitExceeded))]
+    #[case(withdrawal(1_001), Err(TransactionLimitExceeded))]
+    #[case(adjustment(1_000), Ok(()))]
+    #[case(adjustment(-1_001), Err(TransactionLimitExceeded))]
+    fn limit_of_all_transactions_is_checked(
+        #[case] kind: TransactionKind,
+        #[case] expected: Result<(), AccountTransactorError>,
+    ) {
+        let limits = TransactionLimits {
+            max_transaction: Some(Amount::new(1_000)),
+            ..Default::default()
+        };
+        assert_eq!(limits.check(&kind), expected);
+    }
+
+    fn deposit(amount: i64) -> TransactionKind {
+        TransactionKind::Deposit {
+            amount: Amount::new(amount),
+        }
+    }
+
+    fn adjustment(amount: i64) -> TransactionKind {
+        TransactionKind::Adjustment {
+            amount: Amount::new(amount),
+            reason: "correction".to_string(),
+        }
+    }
+
+    fn withdrawal(amount: i64) -> TransactionKind {
+        TransactionKind::Withdrawal {
+            amount: Amount::new(amount),
+        }
+    }
+}
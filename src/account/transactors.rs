@@ -1,5 +1,59 @@
 pub(super) mod backcharger;
 pub(super) mod depositor;
+mod dispute_state_machine;
 pub(super) mod disputer;
+pub(super) mod representer;
 pub(super) mod resolver;
 pub(super) mod withdrawer;
+
+use core::fmt::{self, Display, Formatter};
+
+use crate::model::{ClientId, TransactionId, TransactionKind};
+
+use super::DepositStatus;
+
+/// A transaction referring to a deposit whose status it does not go with,
+/// e.g. the resolve of a deposit that is not disputed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Incompatibility {
+    pub client_id: ClientId,
+    pub transaction_id: TransactionId,
+    /// The kind of the transaction referring to the deposit.
+    pub requested: TransactionKind,
+    /// The status of the deposit at the time.
+    pub status: DepositStatus,
+}
+
+impl Display for Incompatibility {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} on a deposit in {:?} state (client {}, tx {})",
+            self.requested.name(),
+            self.status,
+            self.client_id,
+            self.transaction_id
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{account::DepositStatus, model::TransactionKind};
+
+    use super::Incompatibility;
+
+    #[test]
+    fn incompatibility_can_be_displayed() {
+        let incompatibility = Incompatibility {
+            client_id: 1,
+            transaction_id: 2,
+            requested: TransactionKind::Resolve,
+            status: DepositStatus::Accepted,
+        };
+        assert_eq!(
+            incompatibility.to_string(),
+            "resolve on a deposit in Accepted state (client 1, tx 2)"
+        );
+    }
+}
@@ -1,19 +1,50 @@
 mod credit_backcharger;
-// mod credit_debit_backcharger;
+use thiserror::Error;
+
 use crate::{
-    account::{account_transactor::SuccessStatus, Account},
+    account::{
+        account_transactor::SuccessStatus, transactors::dispute_state_machine::DisputeRejection,
+        Account, Incompatibility,
+    },
     model::TransactionId,
 };
 pub(crate) use credit_backcharger::CreditBackcharger;
 
-#[derive(Debug, Clone, PartialEq)]
-pub(crate) enum BackchargerError {
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum BackchargerError {
+    #[error("The account is locked")]
     AccountLocked,
-    NonDisputedTransaction,
+
+    #[error("The transaction is incompatible: {0}")]
+    NonDisputedTransaction(Incompatibility),
+
+    #[error("No tranasction found")]
     NoTransactionFound,
 }
 
-pub(crate) trait Backcharger {
+impl From<DisputeRejection> for BackchargerError {
+    fn from(rejection: DisputeRejection) -> Self {
+        match rejection {
+            DisputeRejection::AccountLocked => Self::AccountLocked,
+            DisputeRejection::NoTransactionFound => Self::NoTransactionFound,
+            DisputeRejection::Incompatible(incompatibility) => {
+                Self::NonDisputedTransaction(incompatibility)
+            }
+        }
+    }
+}
+
+/// Charges back a disputed deposit of an account.
+/// A held deposit is charged back, its amount taken out of the held funds,
+/// and the account locked. Charging back a charged back deposit is a
+/// duplicate, charging back one that is not disputed is an incompatible
+/// transaction.
+/// The chargeback fee of the [`AccountPolicy`] is charged once the
+/// backcharger is done.
+/// The account must be left untouched whenever an error is returned.
+///
+/// [`AccountPolicy`]: crate::account::AccountPolicy
+pub trait Backcharger {
     fn chargeback(
         &self,
         account: &mut Account,
@@ -1,5 +1,9 @@
 use crate::{
-    account::{account_transactor::SuccessStatus, Account, AccountStatus, DepositStatus},
+    account::{
+        account_transactor::SuccessStatus,
+        transactors::dispute_state_machine::{DisputeEvent, DisputeStateMachine},
+        Account, AccountStatus,
+    },
     model::TransactionId,
 };
 
@@ -13,32 +17,14 @@ impl Backcharger for CreditBackcharger {
         account: &mut Account,
         transaction_id: TransactionId,
     ) -> Result<SuccessStatus, BackchargerError> {
-        match account.deposits.get_mut(&transaction_id) {
-            Some(deposit) => match deposit.status {
-                DepositStatus::Held => {
-                    if account.status == AccountStatus::Locked {
-                        return Err(BackchargerError::AccountLocked);
-                    }
-                    account.account_snapshot.held.0 -= deposit.amount.0;
-                    deposit.status = DepositStatus::ChargedBack;
-                    account.status = AccountStatus::Locked;
-                    return Ok(SuccessStatus::Transacted);
-                }
-                DepositStatus::ChargedBack => return Ok(SuccessStatus::Duplicate),
-                _ => {
-                    if account.status == AccountStatus::Locked {
-                        return Err(BackchargerError::AccountLocked);
-                    }
-                    return Err(BackchargerError::NonDisputedTransaction);
-                }
-            },
-            None => {
-                if account.status == AccountStatus::Locked {
-                    return Err(BackchargerError::AccountLocked);
-                }
-                Err(BackchargerError::NoTransactionFound)
-            }
-        }
+        let Some(amount) =
+            DisputeStateMachine::apply(DisputeEvent::Chargeback, account, transaction_id)?
+        else {
+            return Ok(SuccessStatus::Duplicate);
+        };
+        account.account_snapshot.held -= amount;
+        account.status = AccountStatus::Locked;
+        Ok(SuccessStatus::Transacted)
     }
 }
 
@@ -55,12 +41,14 @@ mod tests {
             transactors::backcharger::BackchargerError,
             transactors::backcharger::BackchargerError::AccountLocked,
             transactors::backcharger::BackchargerError::NoTransactionFound,
-            transactors::backcharger::BackchargerError::NonDisputedTransaction,
             Account, AccountSnapshot,
             AccountStatus::{self, Active, Locked},
-            Deposit, DepositStatus, Withdrawal,
+            Deposit,
+            DepositStatus::{self, Accepted, Resolved},
+            Incompatibility, Withdrawal,
         },
-        model::{Amount, Amount4DecimalBased, TransactionId},
+        collections::HashMap,
+        model::{Amount, TransactionId, TransactionKind},
     };
 
     use super::Backcharger;
@@ -69,20 +57,20 @@ mod tests {
     #[rstest]
     #[rustfmt::skip(case)]
     // disputing credit transactions
-    //    |------------------ input ----------------------| |---------------------------- output ------------------------------------|
-    //     original_account,                            tx                               expected_account
-    //        avail, held, deposits,                    id, expected_status,                avail, held, deposits
-    #[case(active(7,    5, vec![(0, accepted_dep(3))]),  0, Err(NonDisputedTransaction), active(7,    5, vec![(0, accepted_dep(3))]) )]
-    #[case(active(7,    5, vec![(0, held_dep(3))]),      0, Ok(Transacted),              locked(7,    2, vec![(0, chrgd_bck_dep(3))]))]
-    #[case(active(7,    5, vec![(0, resolved_dep(3))]),  0, Err(NonDisputedTransaction), active(7,    5, vec![(0, resolved_dep(3))]) )]
-    #[case(active(7,    5, vec![(0, chrgd_bck_dep(3))]), 0, Ok(Duplicate),               active(7,    5, vec![(0, chrgd_bck_dep(3))]))]
-    #[case(active(7,    5, vec![(0, chrgd_bck_dep(3))]), 1, Err(NoTransactionFound),     active(7,    5, vec![(0, chrgd_bck_dep(3))]))]
+    //    |------------------ input ----------------------| |-------------------------------- output ------------------------------------|
+    //     original_account,                            tx                                   expected_account
+    //        avail, held, deposits,                    id, expected_status,                    avail, held, deposits
+    #[case(active(7,    5, vec![(0, accepted_dep(3))]),  0, Err(non_disputed(0, Accepted)), active(7,    5, vec![(0, accepted_dep(3))]) )]
+    #[case(active(7,    5, vec![(0, held_dep(3))]),      0, Ok(Transacted),                 locked(7,    2, vec![(0, chrgd_bck_dep(3))]))]
+    #[case(active(7,    5, vec![(0, resolved_dep(3))]),  0, Err(non_disputed(0, Resolved)), active(7,    5, vec![(0, resolved_dep(3))]) )]
+    #[case(active(7,    5, vec![(0, chrgd_bck_dep(3))]), 0, Ok(Duplicate),                  active(7,    5, vec![(0, chrgd_bck_dep(3))]))]
+    #[case(active(7,    5, vec![(0, chrgd_bck_dep(3))]), 1, Err(NoTransactionFound),        active(7,    5, vec![(0, chrgd_bck_dep(3))]))]
     // locked cases
-    #[case(locked(7,    5, vec![(0, accepted_dep(3))]),  0, Err(AccountLocked),          locked(7,    5, vec![(0, accepted_dep(3))]) )]
-    #[case(locked(7,    5, vec![(0, held_dep(3))]),      0, Err(AccountLocked),          locked(7,    5, vec![(0, held_dep(3))])     )]
-    #[case(locked(7,    5, vec![(0, resolved_dep(3))]),  0, Err(AccountLocked),          locked(7,    5, vec![(0, resolved_dep(3))]) )]
-    #[case(locked(7,    5, vec![(0, chrgd_bck_dep(3))]), 0, Ok(Duplicate),               locked(7,    5, vec![(0, chrgd_bck_dep(3))]))]
-    #[case(locked(7,    5, vec![(0, chrgd_bck_dep(3))]), 1, Err(AccountLocked),          locked(7,    5, vec![(0, chrgd_bck_dep(3))]))]
+    #[case(locked(7,    5, vec![(0, accepted_dep(3))]),  0, Err(AccountLocked),             locked(7,    5, vec![(0, accepted_dep(3))]) )]
+    #[case(locked(7,    5, vec![(0, held_dep(3))]),      0, Err(AccountLocked),             locked(7,    5, vec![(0, held_dep(3))])     )]
+    #[case(locked(7,    5, vec![(0, resolved_dep(3))]),  0, Err(AccountLocked),             locked(7,    5, vec![(0, resolved_dep(3))]) )]
+    #[case(locked(7,    5, vec![(0, chrgd_bck_dep(3))]), 0, Ok(Duplicate),                  locked(7,    5, vec![(0, chrgd_bck_dep(3))]))]
+    #[case(locked(7,    5, vec![(0, chrgd_bck_dep(3))]), 1, Err(AccountLocked),             locked(7,    5, vec![(0, chrgd_bck_dep(3))]))]
     fn active_account_cases(
         #[case] mut original: Account,
         #[case] transaction_id: TransactionId,
@@ -117,6 +105,12 @@ mod tests {
             account_snapshot: AccountSnapshot::new(available, held),
             deposits: deposits.into_iter().collect(),
             withdrawals: withdrawals.into_iter().collect(),
+            fees: Vec::new(),
+            adjustments: Vec::new(),
+            holds: HashMap::default(),
+            auto_resolved: Vec::new(),
+            memos: HashMap::default(),
+            checkpoints: Default::default(),
         }
     }
 
@@ -140,10 +134,21 @@ mod tests {
         Deposit {
             amount: amount(amount_i64),
             status,
+            timestamp: None,
+            sequence: 0,
         }
     }
 
     fn amount(amount: i64) -> Amount {
-        Amount4DecimalBased(amount)
+        Amount::new(amount)
+    }
+
+    fn non_disputed(transaction_id: TransactionId, status: DepositStatus) -> BackchargerError {
+        BackchargerError::NonDisputedTransaction(Incompatibility {
+            client_id: 1234,
+            transaction_id,
+            requested: TransactionKind::ChargeBack,
+            status,
+        })
     }
 }
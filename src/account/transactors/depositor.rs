@@ -1,21 +1,32 @@
+use thiserror::Error;
+
 use crate::{
-    account::{
-        account_transactor::SuccessStatus, Account, AccountStatus, Deposit, DepositStatus::Accepted,
-    },
-    model::{Amount, TransactionId},
+    account::{account_transactor::SuccessStatus, Account, AccountStatus},
+    model::{Amount, Timestamp, TransactionId},
 };
 
-#[derive(Debug, Clone, PartialEq)]
-pub(crate) enum DepositorError {
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum DepositorError {
+    #[error("The account is locked")]
     AccountLocked,
+
+    #[error("The deposit conflicts with a previous one of the same id")]
+    ConflictingWithPreviousTransaction,
 }
 
-pub(crate) trait Depositor {
+/// Takes a deposit into an account.
+/// A new deposit is recorded with [`Account::record_deposit`] and its amount
+/// added to the available funds, unless the account is locked. A deposit
+/// already recorded is a duplicate and leaves the account untouched, unless
+/// its amount differs, which is a conflict.
+/// The account must be left untouched whenever an error is returned.
+pub trait Depositor {
     fn deposit(
         &self,
         account: &mut Account,
         transaction_id: TransactionId,
         amount: Amount,
+        timestamp: Option<Timestamp>,
     ) -> Result<SuccessStatus, DepositorError>;
 }
 
@@ -27,24 +38,19 @@ impl Depositor for SimpleDepositor {
         account: &mut Account,
         transaction_id: TransactionId,
         amount: Amount,
+        timestamp: Option<Timestamp>,
     ) -> Result<SuccessStatus, DepositorError> {
         match account.deposits.get(&transaction_id) {
-            Some(existing) => {
-                assert_eq!(existing.amount, amount);
-                Ok(SuccessStatus::Duplicate)
+            Some(existing) if existing.amount != amount => {
+                Err(DepositorError::ConflictingWithPreviousTransaction)
             }
+            Some(_) => Ok(SuccessStatus::Duplicate),
             None => {
                 if account.status == AccountStatus::Locked {
                     return Err(DepositorError::AccountLocked);
                 }
-                account.account_snapshot.available.0 += amount.0;
-                account.deposits.insert(
-                    transaction_id,
-                    Deposit {
-                        amount,
-                        status: Accepted,
-                    },
-                );
+                account.account_snapshot.available += amount;
+                account.record_deposit(transaction_id, amount, timestamp);
                 Ok(SuccessStatus::Transacted)
             }
         }
@@ -58,14 +64,16 @@ pub(crate) mod mock {
 
     use crate::{
         account::{account_transactor::SuccessStatus, Account},
-        model::{Amount, TransactionId},
+        model::{Amount, Timestamp, TransactionId},
     };
 
     use super::{Depositor, DepositorError};
 
+    type Request = (Account, TransactionId, Amount, Option<Timestamp>);
+
     pub(crate) struct MockDepositor {
-        expected_requests: Arc<Mutex<Vec<(Account, TransactionId, Amount)>>>,
-        actual_requests: Arc<Mutex<Vec<(Account, TransactionId, Amount)>>>,
+        expected_requests: Arc<Mutex<Vec<Request>>>,
+        actual_requests: Arc<Mutex<Vec<Request>>>,
         return_vals: Arc<Mutex<Vec<Result<SuccessStatus, DepositorError>>>>,
     }
 
@@ -83,11 +91,14 @@ pub(crate) mod mock {
             account: &mut Account,
             transaction_id: TransactionId,
             amount: Amount,
+            timestamp: Option<Timestamp>,
         ) {
-            self.expected_requests
-                .lock()
-                .unwrap()
-                .push((account.clone(), transaction_id, amount));
+            self.expected_requests.lock().unwrap().push((
+                account.clone(),
+                transaction_id,
+                amount,
+                timestamp,
+            ));
         }
 
         pub(crate) fn to_return(&self, result: Result<SuccessStatus, DepositorError>) {
@@ -101,11 +112,14 @@ pub(crate) mod mock {
             account: &mut Account,
             transaction_id: TransactionId,
             amount: Amount,
+            timestamp: Option<Timestamp>,
         ) -> Result<SuccessStatus, DepositorError> {
-            self.actual_requests
-                .lock()
-                .unwrap()
-                .push((account.clone(), transaction_id, amount));
+            self.actual_requests.lock().unwrap().push((
+                account.clone(),
+                transaction_id,
+                amount,
+                timestamp,
+            ));
             self.return_vals.lock().unwrap().remove(0)
         }
     }
@@ -123,8 +137,6 @@ pub(crate) mod mock {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
-
     use rstest::rstest;
 
     use crate::{
@@ -133,12 +145,15 @@ mod tests {
             account_transactor::SuccessStatus::Duplicate,
             account_transactor::SuccessStatus::Transacted,
             transactors::depositor::DepositorError,
-            transactors::depositor::DepositorError::AccountLocked,
+            transactors::depositor::DepositorError::{
+                AccountLocked, ConflictingWithPreviousTransaction,
+            },
             Account, AccountSnapshot,
             AccountStatus::{self, Active, Locked},
             Deposit, DepositStatus,
         },
-        model::{Amount, Amount4DecimalBased, TransactionId},
+        collections::HashMap,
+        model::{Amount, TransactionId},
     };
 
     use super::Depositor;
@@ -154,13 +169,15 @@ mod tests {
     #[case(active(3, vec![(0, held_dep(3))]),      0, 3, Ok(Duplicate),      active(3, vec![(0, held_dep(3))])                          )]
     #[case(active(3, vec![(0, resolved_dep(3))]),  0, 3, Ok(Duplicate),      active(3, vec![(0, resolved_dep(3))])                      )]
     #[case(active(3, vec![(0, chrgd_bck_dep(3))]), 0, 3, Ok(Duplicate),      active(3, vec![(0, chrgd_bck_dep(3))])                     )]
-    #[case(active(3, vec![(0, accepted_dep(3))]),  2, 5, Ok(Transacted),     active(8, vec![(0, accepted_dep(3)), (2, accepted_dep(5))]))]
+    #[case(active(3, vec![(0, accepted_dep(3))]),  2, 5, Ok(Transacted),     active(8, vec![(0, accepted_dep(3)), (2, second_dep(5))])  )]
+    #[case(active(3, vec![(0, accepted_dep(3))]),  0, 4, Err(ConflictingWithPreviousTransaction), active(3, vec![(0, accepted_dep(3))]))]
     // locked cases
     #[case(locked(3, vec![(0, accepted_dep(3))]),  0, 3, Ok(Duplicate),      locked(3, vec![(0, accepted_dep(3))])                      )]
     #[case(locked(3, vec![(0, held_dep(3))]),      0, 3, Ok(Duplicate),      locked(3, vec![(0, held_dep(3))])                          )]
     #[case(locked(3, vec![(0, resolved_dep(3))]),  0, 3, Ok(Duplicate),      locked(3, vec![(0, resolved_dep(3))])                      )]
     #[case(locked(3, vec![(0, chrgd_bck_dep(3))]), 0, 3, Ok(Duplicate),      locked(3, vec![(0, chrgd_bck_dep(3))])                     )]
     #[case(locked(3, vec![(0, accepted_dep(3))]),  1, 3, Err(AccountLocked), locked(3, vec![(0, accepted_dep(3))])                      )]
+    #[case(locked(3, vec![(0, accepted_dep(3))]),  0, 4, Err(ConflictingWithPreviousTransaction), locked(3, vec![(0, accepted_dep(3))]))]
     fn active_account_cases(
         #[case] mut original: Account,
         #[case] transaction_id: TransactionId,
@@ -170,12 +187,29 @@ mod tests {
     ) {
         let depositor = SimpleDepositor;
         assert_eq!(
-            depositor.deposit(&mut original, transaction_id, amount(amount_i64)),
+            depositor.deposit(&mut original, transaction_id, amount(amount_i64), None),
             expected_status
         );
         assert_eq!(original, expected);
     }
 
+    #[test]
+    fn deposit_keeps_its_timestamp_and_sequence() {
+        let mut account = active(3, vec![(0, accepted_dep(3))]);
+        SimpleDepositor
+            .deposit(&mut account, 2, amount(5), Some(1_700_000_000))
+            .unwrap();
+        assert_eq!(
+            account.deposits.get(&2),
+            Some(&Deposit {
+                amount: amount(5),
+                status: DepositStatus::Accepted,
+                timestamp: Some(1_700_000_000),
+                sequence: 1,
+            })
+        );
+    }
+
     fn active(available: i64, deposits: Vec<(TransactionId, Deposit)>) -> Account {
         account(Active, available, 0, deposits)
     }
@@ -195,7 +229,13 @@ mod tests {
             status,
             account_snapshot: AccountSnapshot::new(available, held),
             deposits: deposits.into_iter().collect(),
-            withdrawals: HashMap::new(),
+            withdrawals: HashMap::default(),
+            fees: Vec::new(),
+            adjustments: Vec::new(),
+            holds: HashMap::default(),
+            auto_resolved: Vec::new(),
+            memos: HashMap::default(),
+            checkpoints: Default::default(),
         }
     }
 
@@ -215,14 +255,23 @@ mod tests {
         deposit(amount_i64, DepositStatus::ChargedBack)
     }
 
+    fn second_dep(amount_i64: i64) -> Deposit {
+        Deposit {
+            sequence: 1,
+            ..accepted_dep(amount_i64)
+        }
+    }
+
     fn deposit(amount_i64: i64, status: DepositStatus) -> Deposit {
         Deposit {
             amount: amount(amount_i64),
             status,
+            timestamp: None,
+            sequence: 0,
         }
     }
 
     fn amount(amount: i64) -> Amount {
-        Amount4DecimalBased(amount)
+        Amount::new(amount)
     }
 }
@@ -0,0 +1,150 @@
+use crate::{
+    account::{Account, AccountStatus, DepositStatus, Incompatibility},
+    model::{Amount, TransactionId, TransactionKind},
+};
+
+/// The transactions taking a deposit through its dispute.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum DisputeEvent {
+    Dispute,
+    Resolve,
+    Chargeback,
+    Representment,
+}
+
+impl DisputeEvent {
+    fn kind(self) -> TransactionKind {
+        match self {
+            DisputeEvent::Dispute => TransactionKind::Dispute,
+            DisputeEvent::Resolve => TransactionKind::Resolve,
+            DisputeEvent::Chargeback => TransactionKind::ChargeBack,
+            DisputeEvent::Representment => TransactionKind::Representment,
+        }
+    }
+
+    /// Only a representment is accepted by a locked account, as it is the
+    /// chargeback that got the account locked in the first place.
+    fn is_allowed_when_locked(self) -> bool {
+        self == DisputeEvent::Representment
+    }
+}
+
+/// Where an event takes a deposit from the status it is in.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum Transition {
+    /// The deposit moves to the status.
+    To(DepositStatus),
+
+    /// The deposit is already where the event would take it.
+    Duplicate,
+
+    /// The event does not go with the status of the deposit.
+    Incompatible,
+}
+
+/// Why an event was not applied to a deposit.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum DisputeRejection {
+    AccountLocked,
+    NoTransactionFound,
+    Incompatible(Incompatibility),
+}
+
+/// The statuses a deposit goes through once disputed:
+/// `Accepted` → `Held` → `Resolved` or `ChargedBack`, the latter going to
+/// `Resolved` on a representment.
+/// A locked account rejects every event other than the duplicates, unless
+/// the event is allowed on a locked account.
+pub(crate) struct DisputeStateMachine;
+
+impl DisputeStateMachine {
+    /// The transition table.
+    pub(crate) fn transition(event: DisputeEvent, from: DepositStatus) -> Transition {
+        use DepositStatus::{Accepted, ChargedBack, Held, Resolved};
+        match (event, from) {
+            (DisputeEvent::Dispute, Accepted) => Transition::To(Held),
+            (DisputeEvent::Dispute, Held | Resolved | ChargedBack) => Transition::Duplicate,
+            (DisputeEvent::Resolve, Held) => Transition::To(Resolved),
+            (DisputeEvent::Resolve, Resolved) => Transition::Duplicate,
+            (DisputeEvent::Resolve, Accepted | ChargedBack) => Transition::Incompatible,
+            (DisputeEvent::Chargeback, Held) => Transition::To(ChargedBack),
+            (DisputeEvent::Chargeback, ChargedBack) => Transition::Duplicate,
+            (DisputeEvent::Chargeback, Accepted | Resolved) => Transition::Incompatible,
+            (DisputeEvent::Representment, ChargedBack) => Transition::To(Resolved),
+            (DisputeEvent::Representment, Resolved) => Transition::Duplicate,
+            (DisputeEvent::Representment, Accepted | Held) => Transition::Incompatible,
+        }
+    }
+
+    /// Moves the deposit to the status the event takes it to, returning its
+    /// amount for the caller to move the funds, or `None` for a duplicate.
+    /// The account is left untouched whenever an error is returned.
+    pub(crate) fn apply(
+        event: DisputeEvent,
+        account: &mut Account,
+        transaction_id: TransactionId,
+    ) -> Result<Option<Amount>, DisputeRejection> {
+        let locked = account.status == AccountStatus::Locked && !event.is_allowed_when_locked();
+        let client_id = account.client_id;
+        let Some(deposit) = account.deposits.get_mut(&transaction_id) else {
+            return Err(if locked {
+                DisputeRejection::AccountLocked
+            } else {
+                DisputeRejection::NoTransactionFound
+            });
+        };
+        match Self::transition(event, deposit.status) {
+            Transition::Duplicate => Ok(None),
+            _ if locked => Err(DisputeRejection::AccountLocked),
+            Transition::Incompatible => Err(DisputeRejection::Incompatible(Incompatibility {
+                client_id,
+                transaction_id,
+                requested: event.kind(),
+                status: deposit.status,
+            })),
+            Transition::To(status) => {
+                deposit.status = status;
+                Ok(Some(deposit.amount))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::account::DepositStatus::{self, Accepted, ChargedBack, Held, Resolved};
+
+    use super::{
+        DisputeEvent::{self, Chargeback, Dispute, Representment, Resolve},
+        DisputeStateMachine,
+        Transition::{self, Duplicate, Incompatible, To},
+    };
+
+    #[rustfmt::skip]
+    #[rstest]
+    #[case(Dispute,       Accepted,    To(Held))]
+    #[case(Dispute,       Held,        Duplicate)]
+    #[case(Dispute,       Resolved,    Duplicate)]
+    #[case(Dispute,       ChargedBack, Duplicate)]
+    #[case(Resolve,       Accepted,    Incompatible)]
+    #[case(Resolve,       Held,        To(Resolved))]
+    #[case(Resolve,       Resolved,    Duplicate)]
+    #[case(Resolve,       ChargedBack, Incompatible)]
+    #[case(Chargeback,    Accepted,    Incompatible)]
+    #[case(Chargeback,    Held,        To(ChargedBack))]
+    #[case(Chargeback,    Resolved,    Incompatible)]
+    #[case(Chargeback,    ChargedBack, Duplicate)]
+    #[case(Representment, Accepted,    Incompatible)]
+    #[case(Representment, Held,        Incompatible)]
+    #[case(Representment, Resolved,    Duplicate)]
+    #[case(Representment, ChargedBack, To(Resolved))]
+    fn transitions_follow_the_table(
+        #[case] event: DisputeEvent,
+        #[case] from: DepositStatus,
+        #[case] expected: Transition,
+    ) {
+        assert_eq!(DisputeStateMachine::transition(event, from), expected);
+    }
+}
@@ -1,19 +1,47 @@
-// mod credit_debit_disputer;
 mod credit_disputer;
 pub(crate) use credit_disputer::CreditDisputer;
 
+use thiserror::Error;
+
 use crate::{
-    account::{account_transactor::SuccessStatus, Account},
+    account::{
+        account_transactor::SuccessStatus, transactors::dispute_state_machine::DisputeRejection,
+        Account,
+    },
     model::TransactionId,
 };
 
-#[derive(Debug, Clone, PartialEq)]
-pub(crate) enum DisputerError {
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum DisputerError {
+    #[error("The account is locked")]
     AccountLocked,
+
+    #[error("No tranasction found")]
     NoTransactionFound,
 }
 
-pub(crate) trait Disputer {
+impl From<DisputeRejection> for DisputerError {
+    fn from(rejection: DisputeRejection) -> Self {
+        match rejection {
+            DisputeRejection::AccountLocked => Self::AccountLocked,
+            DisputeRejection::NoTransactionFound => Self::NoTransactionFound,
+            DisputeRejection::Incompatible(_) => {
+                unreachable!("A deposit can be disputed whatever its status.")
+            }
+        }
+    }
+}
+
+/// Disputes a deposit of an account.
+/// An accepted deposit is put on hold, its amount moved from the available
+/// funds to the held ones, unless the account is locked. Disputing a deposit
+/// in any other state is a duplicate and leaves the account untouched.
+/// The dispute window of the [`AccountPolicy`] is checked before the
+/// disputer is called.
+/// The account must be left untouched whenever an error is returned.
+///
+/// [`AccountPolicy`]: crate::account::AccountPolicy
+pub trait Disputer {
     fn dispute(
         &self,
         account: &mut Account,
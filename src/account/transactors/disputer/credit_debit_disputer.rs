@@ -1,2 +0,0 @@
-/// I'll see if i can get to this ...
-struct CreditDisputer;
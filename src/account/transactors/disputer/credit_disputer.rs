@@ -1,5 +1,9 @@
 use crate::{
-    account::{account_transactor::SuccessStatus, Account, AccountStatus, DepositStatus},
+    account::{
+        account_transactor::SuccessStatus,
+        transactors::dispute_state_machine::{DisputeEvent, DisputeStateMachine},
+        Account,
+    },
     model::TransactionId,
 };
 
@@ -13,26 +17,14 @@ impl Disputer for CreditDisputer {
         account: &mut Account,
         transaction_id: TransactionId,
     ) -> Result<SuccessStatus, DisputerError> {
-        match account.deposits.get_mut(&transaction_id) {
-            Some(deposit) => match deposit.status {
-                DepositStatus::Accepted => {
-                    if account.status == AccountStatus::Locked {
-                        return Err(DisputerError::AccountLocked);
-                    }
-                    account.account_snapshot.available.0 -= deposit.amount.0;
-                    account.account_snapshot.held.0 += deposit.amount.0;
-                    deposit.status = DepositStatus::Held;
-                    return Ok(SuccessStatus::Transacted);
-                }
-                _ => return Ok(SuccessStatus::Duplicate),
-            },
-            None => {
-                if account.status == AccountStatus::Locked {
-                    return Err(DisputerError::AccountLocked);
-                }
-                Err(DisputerError::NoTransactionFound)
-            }
-        }
+        let Some(amount) =
+            DisputeStateMachine::apply(DisputeEvent::Dispute, account, transaction_id)?
+        else {
+            return Ok(SuccessStatus::Duplicate);
+        };
+        account.account_snapshot.available -= amount;
+        account.account_snapshot.held += amount;
+        Ok(SuccessStatus::Transacted)
     }
 }
 
@@ -53,7 +45,8 @@ mod tests {
             AccountStatus::{self, Active, Locked},
             Deposit, DepositStatus, Withdrawal,
         },
-        model::{Amount, Amount4DecimalBased, TransactionId},
+        collections::HashMap,
+        model::{Amount, TransactionId},
     };
 
     use super::CreditDisputer;
@@ -112,6 +105,12 @@ mod tests {
             account_snapshot: AccountSnapshot::new(available, held),
             deposits: deposits.into_iter().collect(),
             withdrawals: withdrawals.into_iter().collect(),
+            fees: Vec::new(),
+            adjustments: Vec::new(),
+            holds: HashMap::default(),
+            auto_resolved: Vec::new(),
+            memos: HashMap::default(),
+            checkpoints: Default::default(),
         }
     }
 
@@ -135,10 +134,12 @@ mod tests {
         Deposit {
             amount: amount(amount_i64),
             status,
+            timestamp: None,
+            sequence: 0,
         }
     }
 
     fn amount(amount: i64) -> Amount {
-        Amount4DecimalBased(amount)
+        Amount::new(amount)
     }
 }
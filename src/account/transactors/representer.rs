@@ -0,0 +1,112 @@
+mod credit_representer;
+use thiserror::Error;
+
+use crate::{
+    account::{
+        account_transactor::SuccessStatus, transactors::dispute_state_machine::DisputeRejection,
+        Account, Incompatibility,
+    },
+    model::TransactionId,
+};
+pub(crate) use credit_representer::CreditRepresenter;
+
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum RepresenterError {
+    #[error("The transaction is incompatible: {0}")]
+    NonChargedBackTransaction(Incompatibility),
+
+    #[error("No tranasction found")]
+    NoTransactionFound,
+}
+
+impl From<DisputeRejection> for RepresenterError {
+    fn from(rejection: DisputeRejection) -> Self {
+        match rejection {
+            DisputeRejection::AccountLocked => {
+                unreachable!("A representment is accepted by a locked account.")
+            }
+            DisputeRejection::NoTransactionFound => Self::NoTransactionFound,
+            DisputeRejection::Incompatible(incompatibility) => {
+                Self::NonChargedBackTransaction(incompatibility)
+            }
+        }
+    }
+}
+
+/// Reverses the chargeback of a deposit of an account.
+/// A charged back deposit is resolved, its amount made available again.
+/// Unlike the other transactions, this is accepted by a locked account.
+/// Representing a resolved deposit is a duplicate, representing one that is
+/// not charged back is an incompatible transaction.
+/// The account must be left untouched whenever an error is returned.
+pub trait Representer {
+    fn represent(
+        &self,
+        account: &mut Account,
+        transaction_id: TransactionId,
+    ) -> Result<SuccessStatus, RepresenterError>;
+}
+
+#[cfg(test)]
+pub(crate) mod mock {
+
+    use std::sync::{Arc, Mutex};
+
+    use crate::{
+        account::{account_transactor::SuccessStatus, Account},
+        model::TransactionId,
+    };
+
+    use super::{Representer, RepresenterError};
+
+    pub(crate) struct MockRepresenter {
+        expected_requests: Arc<Mutex<Vec<(Account, TransactionId)>>>,
+        actual_requests: Arc<Mutex<Vec<(Account, TransactionId)>>>,
+        return_vals: Arc<Mutex<Vec<Result<SuccessStatus, RepresenterError>>>>,
+    }
+
+    impl MockRepresenter {
+        pub(crate) fn new() -> Self {
+            Self {
+                expected_requests: Arc::new(Mutex::new(Vec::new())),
+                actual_requests: Arc::new(Mutex::new(Vec::new())),
+                return_vals: Arc::new(Mutex::new(Vec::new())),
+            }
+        }
+
+        pub(crate) fn expect(&self, account: &mut Account, transaction_id: TransactionId) {
+            self.expected_requests
+                .lock()
+                .unwrap()
+                .push((account.clone(), transaction_id));
+        }
+
+        pub(crate) fn to_return(&self, result: Result<SuccessStatus, RepresenterError>) {
+            self.return_vals.lock().unwrap().push(result);
+        }
+    }
+
+    impl Representer for MockRepresenter {
+        fn represent(
+            &self,
+            account: &mut Account,
+            transaction_id: TransactionId,
+        ) -> Result<SuccessStatus, RepresenterError> {
+            self.actual_requests
+                .lock()
+                .unwrap()
+                .push((account.clone(), transaction_id));
+            self.return_vals.lock().unwrap().remove(0)
+        }
+    }
+
+    impl Drop for MockRepresenter {
+        fn drop(&mut self) {
+            assert_eq!(
+                *self.actual_requests.lock().unwrap(),
+                *self.expected_requests.lock().unwrap()
+            );
+            assert!(self.return_vals.lock().unwrap().is_empty());
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{
+    account::{
+        account_transactor::SuccessStatus,
+        transactors::dispute_state_machine::{DisputeEvent, DisputeStateMachine},
+        Account, AccountStatus, DepositStatus,
+    },
+    model::TransactionId,
+};
+
+use super::{Representer, RepresenterError};
+
+/// Reverses the chargeback of a deposit, making its funds available again.
+/// Unlike the other transactions, a representment is accepted by a locked
+/// account, as it is the chargeback that got the account locked in the first
+/// place.
+pub(crate) struct CreditRepresenter {
+    /// Whether the account is unlocked once none of its deposits is charged
+    /// back any more.
+    pub(crate) reactivate: bool,
+}
+
+impl Representer for CreditRepresenter {
+    fn represent(
+        &self,
+        account: &mut Account,
+        transaction_id: TransactionId,
+    ) -> Result<SuccessStatus, RepresenterError> {
+        let Some(amount) =
+            DisputeStateMachine::apply(DisputeEvent::Representment, account, transaction_id)?
+        else {
+            return Ok(SuccessStatus::Duplicate);
+        };
+        account.account_snapshot.available += amount;
+        if self.reactivate
+            && !account
+                .deposits
+                .values()
+                .any(|deposit| deposit.status == DepositStatus::ChargedBack)
+        {
+            account.status = AccountStatus::Active;
+        }
+        Ok(SuccessStatus::Transacted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rstest::rstest;
+
+    use crate::{
+        account::{
+            account_transactor::SuccessStatus,
+            account_transactor::SuccessStatus::Duplicate,
+            account_transactor::SuccessStatus::Transacted,
+            transactors::representer::RepresenterError,
+            transactors::representer::RepresenterError::NoTransactionFound,
+            Account, AccountSnapshot,
+            AccountStatus::{self, Active, Locked},
+            Deposit,
+            DepositStatus::{self, Accepted, Held},
+            Incompatibility, Withdrawal,
+        },
+        collections::HashMap,
+        model::{Amount, TransactionId, TransactionKind},
+    };
+
+    use super::CreditRepresenter;
+    use super::Representer;
+
+    #[rstest]
+    #[rustfmt::skip(case)]
+    //    |------------------------------- input ------------------------------------| |----------------------------------------- output -----------------------------------------|
+    //     reactivate, original_account,                                             tx                                  expected_account
+    //                    avail, held, deposits,                                     id, expected_status,                      avail, held, deposits
+    #[case(false, locked(4,    0, vec![(0, chrgd_bck_dep(3))]),                       0, Ok(Transacted),                     locked(7,    0, vec![(0, resolved_dep(3))])                      )]
+    #[case(true,  locked(4,    0, vec![(0, chrgd_bck_dep(3))]),                       0, Ok(Transacted),                     active(7,    0, vec![(0, resolved_dep(3))])                      )]
+    #[case(true,  locked(4,    0, vec![(0, chrgd_bck_dep(3)), (1, chrgd_bck_dep(2))]), 0, Ok(Transacted),                     locked(7,    0, vec![(0, resolved_dep(3)), (1, chrgd_bck_dep(2))]))]
+    #[case(true,  locked(7,    0, vec![(0, resolved_dep(3))]),                        0, Ok(Duplicate),                      locked(7,    0, vec![(0, resolved_dep(3))])                      )]
+    #[case(true,  locked(7,    0, vec![(0, chrgd_bck_dep(3)), (1, held_dep(2))]),     1, Err(non_charged_back(1, Held)),     locked(7,    0, vec![(0, chrgd_bck_dep(3)), (1, held_dep(2))])   )]
+    #[case(true,  locked(7,    0, vec![(0, chrgd_bck_dep(3))]),                       1, Err(NoTransactionFound),            locked(7,    0, vec![(0, chrgd_bck_dep(3))])                     )]
+    #[case(true,  active(7,    0, vec![(0, accepted_dep(3))]),                        0, Err(non_charged_back(0, Accepted)), active(7,    0, vec![(0, accepted_dep(3))])                      )]
+    fn representment_works(
+        #[case] reactivate: bool,
+        #[case] mut original: Account,
+        #[case] transaction_id: TransactionId,
+        #[case] expected_status: Result<SuccessStatus, RepresenterError>,
+        #[case] expected: Account,
+    ) {
+        let representer = CreditRepresenter { reactivate };
+        assert_eq!(
+            representer.represent(&mut original, transaction_id),
+            expected_status
+        );
+        assert_eq!(original, expected);
+    }
+
+    fn locked(available: i64, held: i64, deposits: Vec<(TransactionId, Deposit)>) -> Account {
+        account(Locked, available, held, deposits, vec![])
+    }
+    fn active(available: i64, held: i64, deposits: Vec<(TransactionId, Deposit)>) -> Account {
+        account(Active, available, held, deposits, vec![])
+    }
+
+    fn account(
+        status: AccountStatus,
+        available: i64,
+        held: i64,
+        deposits: Vec<(TransactionId, Deposit)>,
+        withdrawals: Vec<(TransactionId, Withdrawal)>,
+    ) -> Account {
+        Account {
+            client_id: 1234,
+            status,
+            account_snapshot: AccountSnapshot::new(available, held),
+            deposits: deposits.into_iter().collect(),
+            withdrawals: withdrawals.into_iter().collect(),
+            fees: Vec::new(),
+            adjustments: Vec::new(),
+            holds: HashMap::default(),
+            auto_resolved: Vec::new(),
+            memos: HashMap::default(),
+            checkpoints: Default::default(),
+        }
+    }
+
+    fn accepted_dep(amount_i64: i64) -> Deposit {
+        deposit(amount_i64, DepositStatus::Accepted)
+    }
+
+    fn held_dep(amount_i64: i64) -> Deposit {
+        deposit(amount_i64, DepositStatus::Held)
+    }
+
+    fn resolved_dep(amount_i64: i64) -> Deposit {
+        deposit(amount_i64, DepositStatus::Resolved)
+    }
+
+    fn chrgd_bck_dep(amount_i64: i64) -> Deposit {
+        deposit(amount_i64, DepositStatus::ChargedBack)
+    }
+
+    fn deposit(amount_i64: i64, status: DepositStatus) -> Deposit {
+        Deposit {
+            amount: amount(amount_i64),
+            status,
+            timestamp: None,
+            sequence: 0,
+        }
+    }
+
+    fn amount(amount: i64) -> Amount {
+        Amount::new(amount)
+    }
+
+    fn non_charged_back(transaction_id: TransactionId, status: DepositStatus) -> RepresenterError {
+        RepresenterError::NonChargedBackTransaction(Incompatibility {
+            client_id: 1234,
+            transaction_id,
+            requested: TransactionKind::Representment,
+            status,
+        })
+    }
+}
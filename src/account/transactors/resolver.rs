@@ -1,19 +1,46 @@
-// mod credit_debit_resolver;
 mod credit_resolver;
+use thiserror::Error;
+
 use crate::{
-    account::{account_transactor::SuccessStatus, Account},
+    account::{
+        account_transactor::SuccessStatus, transactors::dispute_state_machine::DisputeRejection,
+        Account, Incompatibility,
+    },
     model::TransactionId,
 };
 pub(crate) use credit_resolver::CreditResolver;
 
-#[derive(Debug, Clone, PartialEq)]
-pub(crate) enum ResolverError {
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum ResolverError {
+    #[error("The account is locked")]
     AccountLocked,
-    NonDisputedTransaction,
+
+    #[error("The transaction is incompatible: {0}")]
+    NonDisputedTransaction(Incompatibility),
+
+    #[error("No tranasction found")]
     NoTransactionFound,
 }
 
-pub(crate) trait Resolver {
+impl From<DisputeRejection> for ResolverError {
+    fn from(rejection: DisputeRejection) -> Self {
+        match rejection {
+            DisputeRejection::AccountLocked => Self::AccountLocked,
+            DisputeRejection::NoTransactionFound => Self::NoTransactionFound,
+            DisputeRejection::Incompatible(incompatibility) => {
+                Self::NonDisputedTransaction(incompatibility)
+            }
+        }
+    }
+}
+
+/// Resolves a disputed deposit of an account.
+/// A held deposit is resolved, its amount moved from the held funds back to
+/// the available ones, unless the account is locked. Resolving a resolved
+/// deposit is a duplicate, resolving one that is not disputed is an
+/// incompatible transaction.
+/// The account must be left untouched whenever an error is returned.
+pub trait Resolver {
     fn resolve(
         &self,
         account: &mut Account,
@@ -1,18 +1,33 @@
+use thiserror::Error;
+
 use crate::{
-    account::{
-        account_transactor::SuccessStatus, Account, AccountStatus, Withdrawal,
-        WithdrawalStatus::Accepted,
-    },
+    account::{account_transactor::SuccessStatus, Account, AccountStatus, CreditLimits},
     model::{Amount, TransactionId},
 };
 
-#[derive(Debug, Clone, PartialEq)]
-pub(crate) enum WithdrawerError {
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum WithdrawerError {
+    #[error("The account is locked")]
     AccountLocked,
+
+    #[error("The withdrawal conflicts with a previous one of the same id")]
+    ConflictingWithPreviousTransaction,
+
+    #[error("Insufficient fund for withdrawal")]
     InsufficientFund,
 }
 
-pub(crate) trait Withdrawer {
+/// Takes a withdrawal out of an account.
+/// A new withdrawal is recorded with [`Account::record_withdrawal`] and its
+/// amount taken from the available funds, unless the account is locked or
+/// cannot afford it. A withdrawal already recorded is a duplicate and leaves
+/// the account untouched, unless its amount differs, which is a conflict.
+/// The fee and minimum balance of the [`AccountPolicy`] are checked before
+/// the withdrawer is called.
+/// The account must be left untouched whenever an error is returned.
+///
+/// [`AccountPolicy`]: crate::account::AccountPolicy
+pub trait Withdrawer {
     fn withdraw(
         &self,
         account: &mut Account,
@@ -21,7 +36,9 @@ pub(crate) trait Withdrawer {
     ) -> Result<SuccessStatus, WithdrawerError>;
 }
 
-pub(crate) struct SimpleWithdrawer;
+pub(crate) struct SimpleWithdrawer {
+    pub(crate) credit_limits: CreditLimits,
+}
 
 impl Withdrawer for SimpleWithdrawer {
     fn withdraw(
@@ -30,29 +47,24 @@ impl Withdrawer for SimpleWithdrawer {
         transaction_id: TransactionId,
         amount: Amount,
     ) -> Result<SuccessStatus, WithdrawerError> {
+        let credit_limit = self.credit_limits.of(account.client_id);
         if account.status != AccountStatus::Locked
-            && amount.0 != 0
-            && account.account_snapshot.available.0 < amount.0
+            && !amount.is_zero()
+            && account.account_snapshot.available + credit_limit < amount
         {
             return Err(WithdrawerError::InsufficientFund);
         }
         match account.withdrawals.get(&transaction_id) {
-            Some(existing) => {
-                assert_eq!(existing.amount, amount);
-                Ok(SuccessStatus::Duplicate)
+            Some(existing) if existing.amount != amount => {
+                Err(WithdrawerError::ConflictingWithPreviousTransaction)
             }
+            Some(_) => Ok(SuccessStatus::Duplicate),
             None => {
                 if account.status == AccountStatus::Locked {
                     return Err(WithdrawerError::AccountLocked);
                 }
-                account.account_snapshot.available.0 -= amount.0;
-                account.withdrawals.insert(
-                    transaction_id,
-                    Withdrawal {
-                        amount,
-                        status: Accepted,
-                    },
-                );
+                account.account_snapshot.available -= amount;
+                account.record_withdrawal(transaction_id, amount);
                 Ok(SuccessStatus::Transacted)
             }
         }
@@ -131,8 +143,6 @@ pub(crate) mod mock {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
-
     use rstest::rstest;
 
     use crate::account::account_transactor::SuccessStatus;
@@ -141,12 +151,14 @@ mod tests {
             account_transactor::SuccessStatus::Duplicate,
             account_transactor::SuccessStatus::Transacted,
             transactors::withdrawer::WithdrawerError::AccountLocked,
+            transactors::withdrawer::WithdrawerError::ConflictingWithPreviousTransaction,
             transactors::withdrawer::WithdrawerError::InsufficientFund,
             Account, AccountSnapshot,
             AccountStatus::{self, Active, Locked},
-            Withdrawal, WithdrawalStatus,
+            CreditLimits, Withdrawal, WithdrawalStatus,
         },
-        model::{Amount, Amount4DecimalBased, TransactionId},
+        collections::HashMap,
+        model::{Amount, TransactionId},
     };
 
     use super::WithdrawerError;
@@ -165,9 +177,11 @@ mod tests {
     #[case(active(7, vec![]),                      0,      7, Ok(Transacted),        active(0, vec![(0, accepted_wdr(7))])                      )]
     #[case(active(7, vec![(0, accepted_wdr(3))]),  0,      3, Ok(Duplicate),         active(7, vec![(0, accepted_wdr(3))])                      )]
     #[case(active(7, vec![(0, accepted_wdr(3))]),  1,      5, Ok(Transacted),        active(2, vec![(0, accepted_wdr(3)), (1, accepted_wdr(5))]))]
+    #[case(active(7, vec![(0, accepted_wdr(3))]),  0,      4, Err(ConflictingWithPreviousTransaction), active(7, vec![(0, accepted_wdr(3))]))]
     // locked cases
     #[case(locked(7, vec![(0, accepted_wdr(3))]),  0,      3, Ok(Duplicate),         locked(7, vec![(0, accepted_wdr(3))])                      )]
     #[case(locked(7, vec![(0, accepted_wdr(3))]),  1,      3, Err(AccountLocked),    locked(7, vec![(0, accepted_wdr(3))])                      )]
+    #[case(locked(7, vec![(0, accepted_wdr(3))]),  0,      4, Err(ConflictingWithPreviousTransaction), locked(7, vec![(0, accepted_wdr(3))]))]
     fn active_account_cases(
         #[case] mut original: Account,
         #[case] transaction_id: TransactionId,
@@ -175,7 +189,42 @@ mod tests {
         #[case] expected_status: Result<SuccessStatus, WithdrawerError>,
         #[case] expected: Account,
     ) {
-        let withdrawer = SimpleWithdrawer;
+        let withdrawer = SimpleWithdrawer {
+            credit_limits: CreditLimits::default(),
+        };
+        assert_eq!(
+            withdrawer.withdraw(&mut original, transaction_id, amount(amount_i64)),
+            expected_status
+        );
+        assert_eq!(original, expected);
+    }
+
+    #[rstest]
+    //                                 |-- credit limits --|
+    //     original_account,        tx, default, client's, amount, expected_status,        expected_account
+    #[case(active(7, vec![]),        0,       5,     None,     12, Ok(Transacted),         active(-5, vec![(0, accepted_wdr(12))]))]
+    #[case(active(7, vec![]),        0,       5,     None,     13, Err(InsufficientFund),  active( 7, vec![])                     )]
+    #[case(active(7, vec![]),        0,       5,  Some(6),     13, Ok(Transacted),         active(-6, vec![(0, accepted_wdr(13))]))]
+    #[case(active(7, vec![]),        0,       5,  Some(0),      8, Err(InsufficientFund),  active( 7, vec![])                     )]
+    #[case(active(-2, vec![]),       0,       5,     None,      3, Ok(Transacted),         active(-5, vec![(0, accepted_wdr(3))]) )]
+    fn credit_limit_cases(
+        #[case] mut original: Account,
+        #[case] transaction_id: TransactionId,
+        #[case] default: i64,
+        #[case] client_limit: Option<i64>,
+        #[case] amount_i64: i64,
+        #[case] expected_status: Result<SuccessStatus, WithdrawerError>,
+        #[case] expected: Account,
+    ) {
+        let withdrawer = SimpleWithdrawer {
+            credit_limits: CreditLimits {
+                default: amount(default),
+                per_client: client_limit
+                    .map(|limit| (original.client_id, amount(limit)))
+                    .into_iter()
+                    .collect(),
+            },
+        };
         assert_eq!(
             withdrawer.withdraw(&mut original, transaction_id, amount(amount_i64)),
             expected_status
@@ -201,8 +250,14 @@ mod tests {
             client_id: 1234,
             status,
             account_snapshot: AccountSnapshot::new(available, held),
-            deposits: HashMap::new(),
+            deposits: HashMap::default(),
             withdrawals: withdrawals.into_iter().collect(),
+            fees: Vec::new(),
+            adjustments: Vec::new(),
+            holds: HashMap::default(),
+            auto_resolved: Vec::new(),
+            memos: HashMap::default(),
+            checkpoints: Default::default(),
         }
     }
 
@@ -218,6 +273,6 @@ mod tests {
     }
 
     fn amount(amount: i64) -> Amount {
-        Amount4DecimalBased(amount)
+        Amount::new(amount)
     }
 }
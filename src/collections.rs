@@ -0,0 +1,7 @@
+//! The maps of the core: those of `std` when built with it, otherwise those
+//! of `hashbrown`, which `std`'s are built on.
+
+#[cfg(not(feature = "std"))]
+pub use hashbrown::HashMap;
+#[cfg(feature = "std")]
+pub use std::collections::HashMap;
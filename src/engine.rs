@@ -0,0 +1,2435 @@
+mod account_events;
+mod closing_state;
+mod config_env;
+mod config_file;
+mod conflicts;
+mod currency_conversion;
+mod dispute_outcomes;
+mod error_report;
+#[cfg(feature = "graphql")]
+mod graphql;
+#[cfg(feature = "kafka")]
+mod kafka_sink;
+mod large_transactions;
+#[cfg(feature = "server")]
+mod listener;
+mod open_disputes;
+mod opening_balances;
+mod outcomes;
+mod replay;
+mod risk_scoring;
+mod run_manifest;
+mod snapshot_deltas;
+mod stats;
+#[cfg(feature = "websocket")]
+mod summary_stream;
+mod summary_updates;
+mod validation;
+mod verification;
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use dashmap::DashMap;
+use futures_util::Stream;
+use tokio::sync::{broadcast, mpsc};
+
+use crate::{
+    account::{
+        Account, AccountPolicy, AccountSnapshot, AccountStatus, AccountStore,
+        SimpleAccountTransactor, SuccessStatus,
+    },
+    model::{AccountSummary, AccountTotals, Amount, ClientId, ExtendedAccountSummary, Transaction},
+    risk::{RiskEvent, RiskReport, RiskRule, RiskScorer, ScoredTransaction},
+    transaction_processor::{
+        ClientFilter, ClientFiltering, Conflict, ConflictQuarantine, OrderViolation, OutOfOrder,
+        ProcessedIds, RejectionRecorder, ReplayGuard, RetryPolicy, Retrying, RiskScreener,
+        SequenceChecker, SimpleTransactionProcessor, TransactionProcessor,
+        TransactionProcessorError,
+    },
+    transaction_stream_processor::{
+        async_csv_stream_processor::AsyncCsvStreamProcessor,
+        byte_range_stream_processor::ByteRangeStreamProcessor,
+        csv_stream_processor::CsvStreamProcessor,
+        pipeline::{ClientRouter, DEFAULT_CHANNEL_CAPACITY},
+        AmountWarning, AmountWarnings, Cancellation, CsvOptions, MemoryBudget, Quarantine,
+        RateLimiter, Shard, Stall, Throughput, TransactionStreamProcessError,
+        TransactionStreamProcessor, Watchdog,
+    },
+};
+
+pub use account_events::AccountEvent;
+use account_events::AccountEventPublisher;
+pub use closing_state::{ClosingState, ClosingStateError};
+pub use config_env::ENV_PREFIX;
+pub use config_file::ConfigError;
+pub use conflicts::{ConflictReportWriter, ConflictReportWriterError};
+pub use currency_conversion::{ConvertedSummaryCsvWriter, CurrencyConversionError, ExchangeRates};
+use dispute_outcomes::{DisputeOutcomeRecorder, DisputeTracking};
+pub use dispute_outcomes::{
+    DisputeOutcomes, DisputeReport, DisputeReportWriter, DisputeReportWriterError,
+};
+pub use error_report::{ErrorRecord, ErrorReportWriter};
+#[cfg(feature = "graphql")]
+pub use graphql::{AccountQuery, AccountSchema};
+#[cfg(feature = "kafka")]
+pub use kafka_sink::{KafkaSink, KafkaSinkConfig, KafkaSinkError};
+use large_transactions::LargeTransactionRecorder;
+pub use large_transactions::{
+    LargeTransaction, LargeTransactionReportWriter, LargeTransactionReportWriterError,
+};
+pub use open_disputes::{
+    AutoResolvedDispute, OpenDispute, OpenDisputeReportWriter, OpenDisputeReportWriterError,
+};
+pub use opening_balances::{OpeningBalance, OpeningBalancesError};
+use outcomes::OutcomeRecorder;
+pub use outcomes::{Outcome, RecordOutcome};
+use replay::ReplayingProcessor;
+pub use replay::{HistoryPoint, ReplayStep};
+use risk_scoring::RiskScoring;
+pub use run_manifest::{ConfigDigest, FileDigest, RunManifest};
+use snapshot_deltas::DeltaRecorder;
+pub use snapshot_deltas::{
+    FieldChange, SnapshotDelta, SnapshotDeltaWriter, SnapshotDeltaWriterError,
+};
+use stats::StatsCollector;
+pub use stats::{ClientStats, ClientStatsCsvWriter, ClientStatsWriterError};
+use summary_updates::SummaryPublisher;
+pub use validation::ValidationReport;
+use verification::MirroringRouter;
+pub use verification::{Divergence, VerificationReport};
+
+/// How the [`Engine`] is set up.
+/// The sizes are hints to allocate the maps upfront rather than to rehash them
+/// as they grow. Underestimating them costs some rehashing, overestimating
+/// them costs memory.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EngineConfig {
+    /// The number of clients expected in the input.
+    pub expected_client_count: usize,
+
+    /// The number of deposits (and as many withdrawals) expected per client.
+    pub expected_transactions_per_client: usize,
+
+    /// The number of shards the accounts are spread across.
+    /// Rounded up to a power of two, four times the number of CPUs if not set.
+    pub shard_amount: Option<usize>,
+
+    /// How the CSV input is laid out.
+    pub csv_options: CsvOptions,
+
+    /// The rules the accounts are held to.
+    pub account_policy: AccountPolicy,
+
+    /// The clients whose transactions are processed, all of them if not set.
+    pub client_filter: Option<ClientFilter>,
+
+    /// Whether [`Engine::process`] keeps the numbers behind
+    /// [`Engine::stats`].
+    pub collect_stats: bool,
+
+    /// Whether [`Engine::process`] records the changes to the balances and
+    /// the lock of the accounts behind [`Engine::snapshot_deltas`].
+    pub collect_deltas: bool,
+
+    /// Roughly how many bytes the accounts and the transactions waiting to be
+    /// processed may take before the input is read at the pace of the
+    /// processing, no limit if not set. See [`MemoryBudget`].
+    pub memory_budget: Option<usize>,
+
+    /// The most records read per second, e.g. so that a batch run on a
+    /// shared host does not starve the services next to it, no limit if not
+    /// set. See [`RateLimiter`].
+    pub max_records_per_second: Option<u32>,
+
+    /// How the transactions failing with a retryable error are tried again,
+    /// not at all if not set. See [`Engine::retries`].
+    pub retry_policy: Option<RetryPolicy>,
+
+    /// Whether the accounts closed by a `close` transaction are left out of
+    /// the summaries, e.g. once the clients are offboarded.
+    pub exclude_closed_accounts: bool,
+
+    /// Whether the rows of a type the engine does not know of are put in
+    /// [`Engine::quarantine`] rather than failing the run.
+    pub quarantine_unknown_types: bool,
+
+    /// Whether the rows whose client id is above the largest one, 65535, are
+    /// put in [`Engine::quarantine`] rather than failing the run with an
+    /// error naming their line.
+    pub quarantine_out_of_range_clients: bool,
+
+    /// Whether a transaction conflicting with a previous one of the same id,
+    /// e.g. a deposit sent again with another amount, is put in
+    /// [`Engine::conflicts`] rather than failing the run, the first one being
+    /// kept.
+    pub quarantine_conflicts: bool,
+
+    /// The amount above which the deposits and withdrawals are listed in
+    /// [`Engine::large_transactions`], e.g. for an AML extract, none of
+    /// them if not set.
+    pub large_transaction_threshold: Option<Amount>,
+
+    /// The clients processed when several engines split one input, all of
+    /// them if not set.
+    pub shard: Option<Shard>,
+
+    /// How many transactions of a client can wait to be processed before
+    /// the input is read at the pace of their processing, 256 if not set.
+    pub channel_capacity: Option<usize>,
+
+    /// How long the worker of a client waits for its next transaction before
+    /// exiting, its channel being dropped until the client is seen again, so
+    /// that the clients seen once do not hold on to them for the whole run,
+    /// e.g. in a live stream. The workers wait until the end of the input if
+    /// not set.
+    pub worker_idle_timeout: Option<Duration>,
+
+    /// How long the run goes without any transaction being dispatched to a
+    /// worker, nor processed by one, before it is reported as stalled, see
+    /// [`Engine::stalls`], e.g. as a full channel holds the reading of the
+    /// input back while its worker is stuck. Stalls are not watched for if
+    /// not set, in the deterministic mode or with byte ranges, where no
+    /// transaction is ever waiting in a channel.
+    pub stall_timeout: Option<Duration>,
+
+    /// How many tasks parse the input at the same time, for when parsing
+    /// rather than processing holds the run back, e.g. with few clients
+    /// whose workers wait for their transactions. The input is parsed as it
+    /// is read if not set, or in the deterministic mode.
+    pub parsers: Option<usize>,
+
+    /// How many byte ranges a file given to [`Engine::process_file`] is
+    /// split into, each of them parsed at the same time, e.g. for a file of
+    /// tens of GB. The file is read as a whole if not set, or in the
+    /// deterministic mode. The memory budget does not apply to the ranges.
+    pub byte_ranges: Option<usize>,
+
+    /// Whether the transactions are processed one after another in the order
+    /// of the input rather than the clients' concurrently, e.g. to reproduce
+    /// a bug. The channel capacity and the memory budget then do not apply,
+    /// as no transaction is ever waiting to be processed.
+    pub deterministic: bool,
+
+    /// Whether the transactions of each client are checked to reach the
+    /// workers in the order of the input, going by their
+    /// [`Transaction::ingest_sequence`], see [`Engine::order_violations`].
+    ///
+    /// [`Transaction::ingest_sequence`]: crate::model::Transaction::ingest_sequence
+    pub check_ordering: bool,
+
+    /// Whether the admin transactions of a client, its adjustments and the
+    /// closing of its account, are processed ahead of its other transactions
+    /// waiting to be, e.g. in a live stream, those keeping their order. They
+    /// are then reported by the ordering check when they overtake any. Not in
+    /// the deterministic mode, where no transaction is ever waiting.
+    pub prioritise_admin_transactions: bool,
+}
+
+/// Processes CSV transaction streams into the accounts of the clients.
+/// The accounts are kept across calls to [`Engine::process`], so that several
+/// inputs can be processed one after another.
+pub struct Engine {
+    config: EngineConfig,
+    accounts: Arc<AccountStore>,
+    risk_rules: Vec<Arc<dyn RiskRule + Send + Sync>>,
+    risk_events: Arc<Mutex<Vec<RiskEvent>>>,
+    risk_scorer: Option<Arc<dyn RiskScorer + Send + Sync>>,
+    risk_scores: broadcast::Sender<ScoredTransaction>,
+    skipped: Arc<Mutex<BTreeMap<ClientId, usize>>>,
+    stats: Arc<Mutex<BTreeMap<ClientId, ClientStats>>>,
+    disputes: Arc<Mutex<BTreeMap<ClientId, DisputeTracking>>>,
+    cancellation: Cancellation,
+    rate_limiter: RateLimiter,
+    errors: broadcast::Sender<TransactionProcessorError>,
+    stalls: broadcast::Sender<Stall>,
+    summary_updates: broadcast::Sender<AccountSummary>,
+    retries: Arc<AtomicUsize>,
+    quarantine: Quarantine,
+    amount_warnings: AmountWarnings,
+    conflicts: Arc<Mutex<Vec<Conflict>>>,
+    large_transactions: Arc<Mutex<Vec<LargeTransaction>>>,
+    snapshot_deltas: Arc<Mutex<Vec<SnapshotDelta>>>,
+    order_violations: Arc<Mutex<Vec<OrderViolation>>>,
+    processed_ids: Option<Arc<ProcessedIds>>,
+    account_events: Option<mpsc::Sender<AccountEvent>>,
+}
+
+/// What became of a transaction given to [`Engine::apply`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct AppliedOutcome {
+    pub status: SuccessStatus,
+    /// The balances of the client's account once the transaction is
+    /// applied, none if the client has no account, e.g. as it is filtered
+    /// out.
+    pub snapshot: Option<AccountSnapshot>,
+}
+
+/// How many errors a receiver of [`Engine::errors`] can fall behind by before
+/// missing some.
+const ERROR_CHANNEL_CAPACITY: usize = 1024;
+
+/// How many stalls a receiver of [`Engine::stalls`] can fall behind by before
+/// missing some.
+const STALL_CHANNEL_CAPACITY: usize = 16;
+
+/// How many summaries a receiver of [`Engine::summary_updates`] can fall
+/// behind by before missing some.
+const SUMMARY_CHANNEL_CAPACITY: usize = 1024;
+
+/// How many scored transactions a receiver of [`Engine::risk_scores`] can
+/// fall behind by before missing some.
+const RISK_SCORE_CHANNEL_CAPACITY: usize = 1024;
+
+impl Engine {
+    pub fn new(config: EngineConfig) -> Self {
+        Self::with_risk_rules(config, Vec::new())
+    }
+
+    /// The transactions processed by [`Engine::process`] are screened by the
+    /// rules first, see [`Engine::risk_report`].
+    pub fn with_risk_rules(
+        config: EngineConfig,
+        risk_rules: Vec<Arc<dyn RiskRule + Send + Sync>>,
+    ) -> Self {
+        let accounts = Arc::new(AccountStore::with_capacity(
+            config.expected_client_count,
+            config.expected_transactions_per_client,
+            config.shard_amount,
+        ));
+        let rate_limiter = match config.max_records_per_second {
+            Some(records_per_second) => RateLimiter::new(records_per_second),
+            None => RateLimiter::unlimited(),
+        };
+        Self {
+            config,
+            accounts,
+            risk_rules,
+            risk_events: Arc::new(Mutex::new(Vec::new())),
+            risk_scorer: None,
+            risk_scores: broadcast::channel(RISK_SCORE_CHANNEL_CAPACITY).0,
+            skipped: Arc::new(Mutex::new(BTreeMap::new())),
+            stats: Arc::new(Mutex::new(BTreeMap::new())),
+            disputes: Arc::new(Mutex::new(BTreeMap::new())),
+            cancellation: Cancellation::default(),
+            rate_limiter,
+            errors: broadcast::channel(ERROR_CHANNEL_CAPACITY).0,
+            stalls: broadcast::channel(STALL_CHANNEL_CAPACITY).0,
+            summary_updates: broadcast::channel(SUMMARY_CHANNEL_CAPACITY).0,
+            retries: Arc::new(AtomicUsize::new(0)),
+            quarantine: Quarantine::default(),
+            amount_warnings: AmountWarnings::default(),
+            conflicts: Arc::new(Mutex::new(Vec::new())),
+            large_transactions: Arc::new(Mutex::new(Vec::new())),
+            snapshot_deltas: Arc::new(Mutex::new(Vec::new())),
+            order_violations: Arc::new(Mutex::new(Vec::new())),
+            processed_ids: None,
+            account_events: None,
+        }
+    }
+
+    /// The transactions processed by [`Engine::process`] are scored by the
+    /// scorer, without it having any say on whether they are applied, see
+    /// [`Engine::risk_scores`].
+    pub fn with_risk_scorer(self, risk_scorer: Arc<dyn RiskScorer + Send + Sync>) -> Self {
+        Self {
+            risk_scorer: Some(risk_scorer),
+            ..self
+        }
+    }
+
+    /// The transactions already in the processed ids, e.g. processed before a
+    /// restart and sent again by an upstream delivering them at least once,
+    /// are let through as duplicates rather than applied twice. See
+    /// [`ReplayGuard`].
+    pub fn with_processed_ids(self, processed_ids: ProcessedIds) -> Self {
+        Self {
+            processed_ids: Some(Arc::new(processed_ids)),
+            ..self
+        }
+    }
+
+    /// An [`AccountEvent`] is sent each time a transaction changes an
+    /// account, e.g. for a `KafkaSink` to publish it with the `kafka`
+    /// feature. Processing is held back while the events are not taken,
+    /// rather than any of them being missed.
+    pub fn with_account_events(self, events: mpsc::Sender<AccountEvent>) -> Self {
+        Self {
+            account_events: Some(events),
+            ..self
+        }
+    }
+
+    /// Cancelling it stops [`Engine::process`] from reading any further,
+    /// e.g. on a signal. The transactions read so far are still applied, so
+    /// that the accounts can be summarised as they stand, but the run fails
+    /// with [`TransactionStreamProcessError::Cancelled`].
+    pub fn cancellation(&self) -> Cancellation {
+        self.cancellation.clone()
+    }
+
+    /// The errors of [`Engine::process`] as they occur, including the ones
+    /// that do not fail the run, e.g. to raise an alert or cancel the run
+    /// without waiting for the input to be read to its end.
+    /// Only the errors occurring after the call are received. A receiver
+    /// falling more than 1024 errors behind misses the oldest of them.
+    pub fn errors(&self) -> broadcast::Receiver<TransactionProcessorError> {
+        self.errors.subscribe()
+    }
+
+    /// The stalls of [`Engine::process`], as per
+    /// [`EngineConfig::stall_timeout`], each with the channels of the clients
+    /// holding transactions then, e.g. to tell which client a stuck run
+    /// waits on.
+    /// Only the stalls occurring after the call are received. A receiver
+    /// falling more than 16 stalls behind misses the oldest of them.
+    pub fn stalls(&self) -> broadcast::Receiver<Stall> {
+        self.stalls.subscribe()
+    }
+
+    /// The summary of a client's account each time a transaction changes
+    /// it, e.g. to show the balances live while the input is processed.
+    /// Only the changes occurring after the call are received. A receiver
+    /// falling more than 1024 changes behind misses the oldest of them, and
+    /// can catch up with [`Engine::summaries`].
+    pub fn summary_updates(&self) -> broadcast::Receiver<AccountSummary> {
+        self.summary_updates.subscribe()
+    }
+
+    /// Each transaction of [`Engine::process`] along with its score, if
+    /// [`Engine::with_risk_scorer`] was given a scorer, whether it was
+    /// applied or not, e.g. to feed a case management tool.
+    /// The transactions blocked by the risk rules are not scored.
+    /// Only the transactions processed after the call are received. A
+    /// receiver falling more than 1024 transactions behind misses the oldest
+    /// of them.
+    pub fn risk_scores(&self) -> broadcast::Receiver<ScoredTransaction> {
+        self.risk_scores.subscribe()
+    }
+
+    /// Processes the input to its end, the clients' transactions concurrently
+    /// unless [`EngineConfig::deterministic`] is set.
+    pub async fn process(&self, r: impl Read + Send) -> Result<(), TransactionStreamProcessError> {
+        self.run(self.processor(), self.accounts.clone(), r).await
+    }
+
+    /// Processes the file at `path` as [`Engine::process`] does, split into
+    /// [`EngineConfig::byte_ranges`] if set, see [`ByteRangeStreamProcessor`].
+    pub async fn process_file(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<(), TransactionStreamProcessError> {
+        let ranges = match self.config.byte_ranges {
+            Some(ranges) if !self.config.deterministic => ranges,
+            _ => {
+                let file = File::open(path)
+                    .map_err(|err| TransactionStreamProcessError::ParsingError(err.to_string()))?;
+                return self.process(BufReader::new(file)).await;
+            }
+        };
+        let mut processor = ByteRangeStreamProcessor::with_options(
+            self.processor(),
+            self.config.csv_options.clone(),
+            ranges,
+        )
+        .with_cancellation(self.cancellation.clone())
+        .with_rate_limiter(self.rate_limiter.clone())
+        .with_amount_warnings(self.amount_warnings.clone())
+        .with_errors(self.errors.clone());
+        if self.config.quarantine_unknown_types {
+            processor = processor.with_quarantine(self.quarantine.clone());
+        }
+        if self.config.quarantine_out_of_range_clients {
+            processor = processor.with_client_quarantine(self.quarantine.clone());
+        }
+        if let Some(shard) = self.config.shard {
+            processor = processor.with_shard(shard);
+        }
+        processor.process_file(path).await
+    }
+
+    /// Processes the input as [`Engine::process`] does, giving what became
+    /// of each of its rows as it goes, e.g. for a report of one's own.
+    /// The clients being processed concurrently, the rows come in input
+    /// order for each client only, unless [`EngineConfig::deterministic`]
+    /// is set. Rejections do not stop the run. If it fails, e.g. on a
+    /// malformed row, its error comes last.
+    /// The input is only processed while the stream is polled.
+    pub fn process_with_outcomes<'a>(
+        &'a self,
+        r: impl Read + Send + 'a,
+    ) -> impl Stream<Item = Result<RecordOutcome, TransactionStreamProcessError>> + Send + 'a {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let processor = Arc::new(OutcomeRecorder::new(self.processor(), sender));
+        outcomes::outcome_stream(self.run(processor, self.accounts.clone(), r), receiver)
+    }
+
+    /// Applies a single transaction, e.g. for a REST handler, as
+    /// [`Engine::process`] would, the rules, the filter and the reports
+    /// included.
+    /// Unlike with [`Engine::process`], the error of a rejected transaction
+    /// is only returned, not sent to the receivers of [`Engine::errors`].
+    pub async fn apply(
+        &self,
+        transaction: Transaction,
+    ) -> Result<AppliedOutcome, TransactionProcessorError> {
+        let client_id = transaction.client_id;
+        let status = self.processor().process(transaction).await?;
+        Ok(AppliedOutcome {
+            status,
+            snapshot: self.accounts.inspect(client_id, Account::snapshot),
+        })
+    }
+
+    /// What the transactions go through to reach the accounts, as per the
+    /// config.
+    fn processor(&self) -> Arc<dyn TransactionProcessor + Send + Sync> {
+        let mut processor = self.retried(Arc::new(SimpleTransactionProcessor::new(
+            self.accounts.clone(),
+            Box::new(self.account_transactor()),
+        )));
+        if self.config.quarantine_conflicts {
+            processor = Arc::new(ConflictQuarantine::new(processor, self.conflicts.clone()));
+        }
+        if self.config.collect_deltas {
+            processor = Arc::new(DeltaRecorder::new(
+                processor,
+                self.accounts.clone(),
+                self.snapshot_deltas.clone(),
+            ));
+        }
+        processor = Arc::new(SummaryPublisher::new(
+            processor,
+            self.accounts.clone(),
+            self.summary_updates.clone(),
+        ));
+        if let Some(events) = &self.account_events {
+            processor = Arc::new(AccountEventPublisher::new(
+                processor,
+                self.accounts.clone(),
+                events.clone(),
+            ));
+        }
+        if let Some(risk_scorer) = &self.risk_scorer {
+            processor = Arc::new(RiskScoring::new(
+                processor,
+                self.accounts.clone(),
+                risk_scorer.clone(),
+                self.risk_scores.clone(),
+            ));
+        }
+        if !self.risk_rules.is_empty() {
+            processor = Arc::new(RiskScreener::new(
+                processor,
+                self.risk_rules.clone(),
+                self.risk_events.clone(),
+            ));
+        }
+        if let Some(processed_ids) = &self.processed_ids {
+            processor = Arc::new(ReplayGuard::new(processor, processed_ids.clone()));
+        }
+        processor = self.filtered(processor, self.skipped.clone());
+        if let Some(threshold) = self.config.large_transaction_threshold {
+            processor = Arc::new(LargeTransactionRecorder::new(
+                processor,
+                threshold,
+                self.large_transactions.clone(),
+            ));
+        }
+        if self.config.collect_stats {
+            processor = Arc::new(DisputeOutcomeRecorder::new(
+                processor,
+                self.disputes.clone(),
+            ));
+            processor = Arc::new(StatsCollector::new(processor, self.stats.clone()));
+        }
+        if self.config.check_ordering {
+            processor = Arc::new(SequenceChecker::new(
+                processor,
+                OutOfOrder::Report(self.order_violations.clone()),
+            ));
+        }
+        processor
+    }
+
+    /// Opens the accounts of the clients with the balances given, e.g. the
+    /// closing state of the previous day's batch read with
+    /// [`OpeningBalance::read`], so that the inputs processed next start
+    /// from them without their history being replayed.
+    /// Either all the accounts are opened or, should a client have an
+    /// account already, e.g. from an input processed or a store merged in,
+    /// none is.
+    pub fn open_accounts(&self, balances: &[OpeningBalance]) -> Result<(), OpeningBalancesError> {
+        self.accounts
+            .open(balances.iter().map(OpeningBalance::account).collect())
+            .map_err(OpeningBalancesError::AccountExists)
+    }
+
+    /// Opens the accounts of a [`ClosingState`], e.g. that of the previous
+    /// day's run, with their transactions, so that these can still be
+    /// disputed or told apart from those sent again.
+    /// Either all the accounts are opened or, should a client have an
+    /// account already, e.g. from opening balances, none is.
+    pub fn open_state(&self, state: ClosingState) -> Result<(), OpeningBalancesError> {
+        self.accounts
+            .open(state.accounts)
+            .map_err(OpeningBalancesError::AccountExists)
+    }
+
+    /// The accounts at a single point in time, ordered by client, with
+    /// everything they hold, for the next run to start from, see
+    /// [`Engine::open_state`].
+    pub fn closing_state(&self) -> ClosingState {
+        let mut accounts = self.accounts.quiesced(|| self.accounts.map(Account::clone));
+        accounts.sort_by_key(|account| account.client_id);
+        ClosingState { accounts }
+    }
+
+    /// The stats of each client across all the inputs processed, ordered by
+    /// client, if [`EngineConfig::collect_stats`] is set.
+    /// The transactions are counted as they are in the input, skipped and
+    /// rejected ones included.
+    pub fn stats(&self) -> Vec<ClientStats> {
+        stats::client_stats(&self.stats, &self.accounts)
+    }
+
+    /// What became of the disputes of each client across all the inputs
+    /// processed, and of those of all of them, if
+    /// [`EngineConfig::collect_stats`] is set.
+    pub fn dispute_report(&self) -> DisputeReport {
+        dispute_outcomes::dispute_report(&self.disputes, &self.accounts)
+    }
+
+    /// The rows of an unknown type across all the inputs processed, if
+    /// [`EngineConfig::quarantine_unknown_types`] is set, and those of an out
+    /// of range client if [`EngineConfig::quarantine_out_of_range_clients`]
+    /// is.
+    pub fn quarantine(&self) -> &Quarantine {
+        &self.quarantine
+    }
+
+    /// The amounts ignored across all the inputs processed as the types of
+    /// their records take none, e.g. a dispute with an amount, in the order
+    /// of their lines, unless the [`CsvOptions::amount_strictness`] rejects
+    /// such records.
+    pub fn amount_warnings(&self) -> Vec<AmountWarning> {
+        self.amount_warnings.warnings()
+    }
+
+    /// The transactions that conflicted with a previous one of the same id
+    /// across all the inputs processed, ordered by client, if
+    /// [`EngineConfig::quarantine_conflicts`] is set.
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let mut conflicts = self
+            .conflicts
+            .lock()
+            .expect("The conflicts are never locked across a panic.")
+            .clone();
+        // the clients are processed concurrently, but each in input order
+        conflicts.sort_by_key(|conflict| conflict.transaction.client_id);
+        conflicts
+    }
+
+    /// The deposits and withdrawals above
+    /// [`EngineConfig::large_transaction_threshold`] across all the inputs
+    /// processed, ordered by client, blocked and rejected ones included.
+    pub fn large_transactions(&self) -> Vec<LargeTransaction> {
+        let mut large_transactions = self
+            .large_transactions
+            .lock()
+            .expect("The large transactions are never locked across a panic.")
+            .clone();
+        // the clients are processed concurrently, but each in input order
+        large_transactions.sort_by_key(|large| large.transaction.client_id);
+        large_transactions
+    }
+
+    /// The changes to the available, held and total funds and to the lock of
+    /// each account across all the inputs processed, ordered by client, each
+    /// client's in the order they were made in, if
+    /// [`EngineConfig::collect_deltas`] is set.
+    pub fn snapshot_deltas(&self) -> Vec<SnapshotDelta> {
+        let mut snapshot_deltas = self
+            .snapshot_deltas
+            .lock()
+            .expect("The snapshot deltas are never locked across a panic.")
+            .clone();
+        // the clients are processed concurrently, but each in input order
+        snapshot_deltas.sort_by_key(|delta| delta.client_id);
+        snapshot_deltas
+    }
+
+    /// The records read so far across all the inputs processed, and how many
+    /// of them were per second since the previous call at least a second
+    /// ago, e.g. for a progress line, held to
+    /// [`EngineConfig::max_records_per_second`] if set.
+    pub fn throughput(&self) -> Throughput {
+        self.rate_limiter.throughput()
+    }
+
+    /// The number of times a transaction was tried again so far, across all
+    /// the inputs processed, if [`EngineConfig::retry_policy`] is set.
+    pub fn retries(&self) -> usize {
+        self.retries.load(Ordering::Relaxed)
+    }
+
+    /// The transactions that reached their worker out of order so far, across
+    /// all the inputs processed, if [`EngineConfig::check_ordering`] is set.
+    /// Any of them is a bug of the engine.
+    pub fn order_violations(&self) -> Vec<OrderViolation> {
+        self.order_violations
+            .lock()
+            .expect("The violations are never locked across a panic.")
+            .clone()
+    }
+
+    /// The number of transactions of each client skipped by the client
+    /// filter so far, across all the inputs processed.
+    pub fn skipped(&self) -> BTreeMap<ClientId, usize> {
+        self.skipped
+            .lock()
+            .expect("The skipped counts are never locked across a panic.")
+            .clone()
+    }
+
+    /// What the risk rules have flagged or blocked so far, across all the
+    /// inputs processed.
+    pub fn risk_report(&self) -> RiskReport {
+        let mut events = self
+            .risk_events
+            .lock()
+            .expect("The risk events are never locked across a panic.")
+            .clone();
+        // the clients are processed concurrently, but each in input order
+        events.sort_by_key(|event| event.transaction.client_id);
+        RiskReport { events }
+    }
+
+    /// Runs the input through the whole pipeline, as [`Engine::process`]
+    /// does, but on accounts of its own that are thrown away afterwards.
+    /// Rejections do not stop the run, so that all of them are reported.
+    /// The risk rules are left out, as they keep track of what they have
+    /// seen.
+    pub async fn validate(
+        &self,
+        r: impl Read + Send,
+    ) -> Result<ValidationReport, TransactionStreamProcessError> {
+        let accounts = Arc::new(self.scratch_accounts());
+        let rejections = Arc::new(Mutex::new(Vec::new()));
+        let skipped = Arc::new(Mutex::new(BTreeMap::new()));
+        self.run(
+            Arc::new(RejectionRecorder::new(
+                self.filtered(
+                    self.retried(Arc::new(SimpleTransactionProcessor::new(
+                        accounts.clone(),
+                        Box::new(self.account_transactor()),
+                    ))),
+                    skipped.clone(),
+                ),
+                rejections.clone(),
+            )),
+            accounts.clone(),
+            r,
+        )
+        .await?;
+
+        let mut rejections = rejections
+            .lock()
+            .expect("The rejections are never locked across a panic.")
+            .clone();
+        // the clients are processed concurrently, but each in input order
+        rejections.sort_by_key(|rejection| rejection.transaction.client_id);
+        // only the chargebacks lock a client, closed accounts being on purpose
+        let mut locked_clients: Vec<ClientId> = accounts
+            .map(|account| (account.client_id, account.status == AccountStatus::Locked))
+            .into_iter()
+            .filter(|(_, locked)| *locked)
+            .map(|(client_id, _)| client_id)
+            .collect();
+        locked_clients.sort();
+        let skipped = skipped
+            .lock()
+            .expect("The skipped counts are never locked across a panic.")
+            .clone();
+        Ok(ValidationReport {
+            rejections,
+            locked_clients,
+            skipped,
+        })
+    }
+
+    /// Runs the input through both the sequential pipeline of
+    /// [`EngineConfig::deterministic`] and the concurrent one, each on
+    /// accounts of its own, and compares the accounts they end up with, e.g.
+    /// to trust the concurrent pipeline with a real feed. The input is read
+    /// once, as it goes, each transaction being processed sequentially as it
+    /// is routed to the worker of its client.
+    /// The engine's accounts are left untouched. As in [`Engine::validate`],
+    /// rejections do not stop either run and the risk rules are left out.
+    pub async fn verify(
+        &self,
+        r: impl Read + Send,
+    ) -> Result<VerificationReport, TransactionStreamProcessError> {
+        let sequential = Arc::new(self.scratch_accounts());
+        let concurrent = Arc::new(self.scratch_accounts());
+        // read once, as it goes, each transaction going through the
+        // sequential pipeline as it is routed to the concurrent one
+        let processor =
+            self.concurrent_processor(self.recorded(concurrent.clone()), concurrent.clone());
+        let router = ClientRouter::new(
+            processor.sink(),
+            Arc::new(DashMap::with_capacity(self.config.expected_client_count)),
+        )
+        .with_channel_capacity(
+            self.config
+                .channel_capacity
+                .unwrap_or(DEFAULT_CHANNEL_CAPACITY),
+        )
+        .with_admin_priority(self.config.prioritise_admin_transactions)
+        .with_idle_timeout(self.config.worker_idle_timeout);
+        let processor = processor.with_router(Arc::new(MirroringRouter::new(
+            self.recorded(sequential.clone()),
+            router,
+        )));
+        let result = processor.process(r).await;
+        // as in a concurrent run, the workers are shut down either way
+        let shutdown_result = processor.shutdown().await;
+        result.and(shutdown_result)?;
+        let by_client = |accounts: &AccountStore| {
+            accounts
+                .map(|account| (account.client_id, account.clone()))
+                .into_iter()
+                .collect()
+        };
+        Ok(VerificationReport::compare(
+            by_client(&sequential),
+            by_client(&concurrent),
+        ))
+    }
+
+    fn scratch_accounts(&self) -> AccountStore {
+        AccountStore::with_capacity(
+            self.config.expected_client_count,
+            self.config.expected_transactions_per_client,
+            self.config.shard_amount,
+        )
+    }
+
+    /// The pipeline of [`Engine::verify`], whose rejections are recorded and
+    /// thrown away.
+    fn recorded(&self, accounts: Arc<AccountStore>) -> Arc<dyn TransactionProcessor + Send + Sync> {
+        Arc::new(RejectionRecorder::new(
+            self.filtered(
+                self.retried(Arc::new(SimpleTransactionProcessor::new(
+                    accounts,
+                    Box::new(self.account_transactor()),
+                ))),
+                Arc::new(Mutex::new(BTreeMap::new())),
+            ),
+            Arc::new(Mutex::new(Vec::new())),
+        ))
+    }
+
+    fn retried(
+        &self,
+        processor: Arc<dyn TransactionProcessor + Send + Sync>,
+    ) -> Arc<dyn TransactionProcessor + Send + Sync> {
+        match &self.config.retry_policy {
+            Some(policy) => Arc::new(Retrying::new(
+                processor,
+                policy.clone(),
+                self.retries.clone(),
+            )),
+            None => processor,
+        }
+    }
+
+    fn filtered(
+        &self,
+        processor: Arc<dyn TransactionProcessor + Send + Sync>,
+        skipped: Arc<Mutex<BTreeMap<ClientId, usize>>>,
+    ) -> Arc<dyn TransactionProcessor + Send + Sync> {
+        match &self.config.client_filter {
+            Some(filter) => Arc::new(ClientFiltering::new(processor, filter.clone(), skipped)),
+            None => processor,
+        }
+    }
+
+    async fn run(
+        &self,
+        consumer: Arc<dyn TransactionProcessor + Send + Sync>,
+        accounts: Arc<AccountStore>,
+        r: impl Read + Send,
+    ) -> Result<(), TransactionStreamProcessError> {
+        match self.config.deterministic {
+            true => self.run_sequentially(consumer, r).await,
+            false => self.run_concurrently(consumer, accounts, r).await,
+        }
+    }
+
+    async fn run_concurrently(
+        &self,
+        consumer: Arc<dyn TransactionProcessor + Send + Sync>,
+        accounts: Arc<AccountStore>,
+        r: impl Read + Send,
+    ) -> Result<(), TransactionStreamProcessError> {
+        let processor = self.concurrent_processor(consumer, accounts);
+        let result = processor.process(r).await;
+        // the workers are shut down even if the input could not be read to
+        // its end, so that no transaction is left half way
+        let shutdown_result = processor.shutdown().await;
+        result.and(shutdown_result)
+    }
+
+    /// Routes the transactions to a worker per client, as per the config.
+    fn concurrent_processor(
+        &self,
+        consumer: Arc<dyn TransactionProcessor + Send + Sync>,
+        accounts: Arc<AccountStore>,
+    ) -> AsyncCsvStreamProcessor {
+        let mut processor = AsyncCsvStreamProcessor::with_options(
+            consumer,
+            DashMap::with_capacity(self.config.expected_client_count),
+            self.config.csv_options.clone(),
+        )
+        .with_cancellation(self.cancellation.clone())
+        .with_rate_limiter(self.rate_limiter.clone())
+        .with_amount_warnings(self.amount_warnings.clone())
+        .with_errors(self.errors.clone());
+        if self.config.quarantine_unknown_types {
+            processor = processor.with_quarantine(self.quarantine.clone());
+        }
+        if self.config.quarantine_out_of_range_clients {
+            processor = processor.with_client_quarantine(self.quarantine.clone());
+        }
+        if let Some(shard) = self.config.shard {
+            processor = processor.with_shard(shard);
+        }
+        if let Some(channel_capacity) = self.config.channel_capacity {
+            processor = processor.with_channel_capacity(channel_capacity);
+        }
+        if let Some(parsers) = self.config.parsers {
+            processor = processor.with_parsers(parsers);
+        }
+        processor = processor.with_admin_priority(self.config.prioritise_admin_transactions);
+        if let Some(idle_timeout) = self.config.worker_idle_timeout {
+            processor = processor.with_idle_timeout(idle_timeout);
+        }
+        if let Some(stall_timeout) = self.config.stall_timeout {
+            processor = processor.with_watchdog(Watchdog::new(stall_timeout, self.stalls.clone()));
+        }
+        if let Some(bytes) = self.config.memory_budget {
+            processor = processor.with_memory_budget(MemoryBudget::new(bytes, accounts));
+        }
+        processor
+    }
+
+    async fn run_sequentially(
+        &self,
+        consumer: Arc<dyn TransactionProcessor + Send + Sync>,
+        r: impl Read + Send,
+    ) -> Result<(), TransactionStreamProcessError> {
+        let mut processor =
+            CsvStreamProcessor::with_options(Box::new(consumer), self.config.csv_options.clone())
+                .with_cancellation(self.cancellation.clone())
+                .with_rate_limiter(self.rate_limiter.clone())
+                .with_amount_warnings(self.amount_warnings.clone())
+                .with_errors(self.errors.clone());
+        if self.config.quarantine_unknown_types {
+            processor = processor.with_quarantine(self.quarantine.clone());
+        }
+        if self.config.quarantine_out_of_range_clients {
+            processor = processor.with_client_quarantine(self.quarantine.clone());
+        }
+        if let Some(shard) = self.config.shard {
+            processor = processor.with_shard(shard);
+        }
+        processor.process(r).await
+    }
+
+    /// Replays the transactions of a single client, one after another and in
+    /// the order of the input, on an account of its own.
+    /// The engine's accounts are left untouched. Transactions rejected along
+    /// the way are part of the trace rather than failing the replay.
+    pub async fn replay_client(
+        &self,
+        client_id: ClientId,
+        r: impl Read + Send,
+    ) -> Result<Vec<ReplayStep>, TransactionStreamProcessError> {
+        let steps = Arc::new(Mutex::new(Vec::new()));
+        let processor = CsvStreamProcessor::with_options(
+            Box::new(ReplayingProcessor::new(
+                client_id,
+                self.account_transactor(),
+                steps.clone(),
+            )),
+            self.config.csv_options.clone(),
+        );
+        processor.process(r).await?;
+        let steps = steps
+            .lock()
+            .expect("The steps are only ever locked by the replay itself.")
+            .clone();
+        Ok(steps)
+    }
+
+    /// The balances of a client at a point in its history, e.g. before a
+    /// chargeback, by replaying its transactions as
+    /// [`Engine::replay_client`] does.
+    pub async fn balance_at(
+        &self,
+        client_id: ClientId,
+        point: HistoryPoint,
+        r: impl Read + Send,
+    ) -> Result<AccountSnapshot, TransactionStreamProcessError> {
+        Ok(point.snapshot_of(&self.replay_client(client_id, r).await?))
+    }
+
+    fn account_transactor(&self) -> SimpleAccountTransactor {
+        SimpleAccountTransactor::with_policy(self.config.account_policy.clone())
+    }
+
+    pub fn accounts(&self) -> &AccountStore {
+        &self.accounts
+    }
+
+    /// The transactions still disputed, ordered by client and transaction.
+    pub fn open_disputes(&self) -> Vec<OpenDispute> {
+        let mut open_disputes: Vec<OpenDispute> = self
+            .accounts
+            .map(OpenDispute::of)
+            .into_iter()
+            .flatten()
+            .collect();
+        open_disputes
+            .sort_by_key(|open_dispute| (open_dispute.client_id, open_dispute.transaction_id));
+        open_disputes
+    }
+
+    /// The disputes resolved automatically as per
+    /// [`AccountPolicy::auto_resolution`], ordered by client and in the order
+    /// they were for each client.
+    pub fn auto_resolved_disputes(&self) -> Vec<AutoResolvedDispute> {
+        let mut auto_resolved: Vec<AutoResolvedDispute> = self
+            .accounts
+            .map(AutoResolvedDispute::of)
+            .into_iter()
+            .flatten()
+            .collect();
+        auto_resolved.sort_by_key(|auto_resolved| auto_resolved.client_id);
+        auto_resolved
+    }
+
+    /// The summaries of all the accounts, in no particular order.
+    /// The closed accounts are left out if
+    /// [`EngineConfig::exclude_closed_accounts`] is set.
+    pub fn summaries(&self) -> Vec<AccountSummary> {
+        self.reported(|account| AccountSummary::from(account))
+    }
+
+    /// The summaries of all the accounts at a single point in time, in no
+    /// particular order, e.g. for a report while an input is being processed.
+    /// See [`AccountStore::summaries_snapshot`].
+    pub fn summaries_snapshot(&self) -> Vec<AccountSummary> {
+        self.accounts.quiesced(|| self.summaries())
+    }
+
+    /// The summaries with the fees charged to each client, in no particular
+    /// order.
+    pub fn extended_summaries(&self) -> Vec<ExtendedAccountSummary> {
+        self.reported(|account| ExtendedAccountSummary::from(account))
+    }
+
+    /// What each client deposited, withdrew, had charged back and has
+    /// disputed still, in no particular order, for the summaries to be
+    /// written along with, see [`AccountSummaryCsvWriter::write_with_totals`].
+    ///
+    /// [`AccountSummaryCsvWriter::write_with_totals`]: crate::model::AccountSummaryCsvWriter::write_with_totals
+    pub fn totals(&self) -> Vec<AccountTotals> {
+        self.reported(|account| AccountTotals::from(account))
+    }
+
+    fn reported<T>(&self, summary: impl Fn(&Account) -> T) -> Vec<T> {
+        self.accounts
+            .map(|account| {
+                (!self.config.exclude_closed_accounts || account.status != AccountStatus::Closed)
+                    .then(|| summary(account))
+            })
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{BTreeMap, HashMap, HashSet},
+        env, fs, process,
+        sync::Arc,
+        time::Duration,
+    };
+
+    use assert_matches::assert_matches;
+    use futures_util::StreamExt;
+    use rstest::rstest;
+    use tokio::sync::mpsc;
+
+    use crate::{
+        account::{
+            AccountPolicy, AccountSnapshot, AccountTransactorError, AutoResolution, CreditLimits,
+            DisputeWindow, DisputerError, Fee, FeePolicy, SuccessStatus, TransactionLimits,
+            WithdrawerError,
+        },
+        model::{
+            AccountSummary, AccountSummaryCsvWriter, Amount, SummaryFormat, Transaction,
+            TransactionKind,
+        },
+        risk::{CumulativeWithdrawals, RiskOutcome, RuleBasedScorer, WithdrawalVelocity},
+        transaction_processor::{
+            ClientFilter, ClientList, FilterAction, ProcessedIds, TransactionProcessorError,
+        },
+        transaction_stream_processor::TransactionStreamProcessError,
+    };
+
+    use super::{
+        AppliedOutcome, AutoResolvedDispute, ClientStatsCsvWriter, ClosingState,
+        DisputeReportWriter, Engine, EngineConfig, FieldChange, HistoryPoint,
+        OpenDisputeReportWriter, OpeningBalance, OpeningBalancesError, Outcome,
+    };
+
+    #[tokio::test]
+    async fn accounts_are_kept_across_inputs() {
+        let engine = Engine::new(EngineConfig {
+            expected_client_count: 16,
+            expected_transactions_per_client: 4,
+            shard_amount: Some(4),
+            ..Default::default()
+        });
+        let first = "
+       type, client, tx, amount
+    deposit,      1,  1,    4.0
+    deposit,      2,  2,    6.0";
+        let second = "
+       type, client, tx, amount
+ withdrawal,      1,  3,    1.5";
+        engine.process(first.as_bytes()).await.unwrap();
+        engine.process(second.as_bytes()).await.unwrap();
+
+        let mut summaries = engine.summaries();
+        summaries.sort_by_key(|summary| summary.client_id);
+        assert_eq!(
+            String::from_utf8(AccountSummaryCsvWriter::write(summaries).unwrap()).unwrap(),
+            "\
+            client,available,held,total,locked\n\
+            1,2.5000,0.0000,2.5000,false\n\
+            2,6.0000,0.0000,6.0000,false\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn replay_traces_a_single_client() {
+        let engine = Engine::new(EngineConfig::default());
+        let input = "
+       type, client, tx, amount
+    deposit,      1,  1,    4.0
+    deposit,      2,  2,    6.0
+    dispute,      1,  1,
+ chargeback,      1,  1,
+    deposit,      1,  3,    1.0";
+        let trace: Vec<String> = engine
+            .replay_client(1, input.as_bytes())
+            .await
+            .unwrap()
+            .iter()
+            .map(|step| step.to_string())
+            .collect();
+        assert_eq!(
+            trace,
+            vec![
+                "tx 1 deposit 4.0000: available 0.0000 -> 4.0000, held 0.0000 -> 0.0000, active",
+                "tx 1 dispute: available 4.0000 -> 0.0000, held 0.0000 -> 4.0000, active",
+                "tx 1 chargeback: available 0.0000 -> 0.0000, held 4.0000 -> 0.0000, locked",
+                "tx 3 deposit 1.0000: available 0.0000 -> 0.0000, held 0.0000 -> 0.0000, locked, \
+                rejected: The account is locked",
+            ]
+        );
+        assert!(engine.accounts().is_empty());
+    }
+
+    #[tokio::test]
+    async fn balance_before_the_chargeback_is_found_by_replaying_the_client() {
+        let engine = Engine::new(EngineConfig::default());
+        let input = "
+       type, client, tx, amount
+    deposit,      1,  1,    4.0
+    deposit,      2,  2,    6.0
+    dispute,      1,  1,
+ chargeback,      1,  1,";
+        assert_eq!(
+            engine
+                .balance_at(1, HistoryPoint::Sequence(2), input.as_bytes())
+                .await
+                .unwrap(),
+            AccountSnapshot::new(0, 40_000)
+        );
+        assert!(engine.accounts().is_empty());
+    }
+
+    #[tokio::test]
+    async fn validation_reports_all_rejections_without_touching_the_accounts() {
+        let engine = Engine::new(EngineConfig::default());
+        let input = "
+       type, client, tx, amount
+    deposit,      1,  1,    4.0
+    deposit,      2,  2,    2.0
+ withdrawal,      1,  3,    5.0
+    dispute,      2,  2,
+ chargeback,      2,  2,
+    deposit,      2,  4,    1.0
+    deposit,      2,  5,    1.0";
+        let report = engine.validate(input.as_bytes()).await.unwrap();
+        assert_eq!(
+            report.to_string(),
+            "\
+            rejected: row 5, client 1, tx 3 (withdrawal): Insufficient fund for withdrawal\n\
+            rejected: row 8, client 2, tx 4 (deposit): The account is locked\n\
+            rejected: row 9, client 2, tx 5 (deposit): The account is locked\n\
+            locked: client 2\n"
+        );
+        assert!(engine.accounts().is_empty());
+    }
+
+    #[tokio::test]
+    async fn conflicting_duplicates_fail_the_run_rather_than_the_worker() {
+        let engine = Engine::new(EngineConfig::default());
+        let input = "
+       type, client, tx, amount
+    deposit,      1,  1,    4.0
+    deposit,      1,  1,    5.0
+    deposit,      2,  2,    5.0
+ withdrawal,      2,  3,    1.0
+ withdrawal,      2,  3,    2.0";
+        assert_eq!(
+            engine.validate(input.as_bytes()).await.unwrap().to_string(),
+            "\
+            rejected: row 4, client 1, tx 1 (deposit): The deposit conflicts with a previous one of the same id\n\
+            rejected: row 7, client 2, tx 3 (withdrawal): The withdrawal conflicts with a previous one of the same id\n"
+        );
+        assert_matches!(
+            engine.process(input.as_bytes()).await,
+            Err(TransactionStreamProcessError::ProcessError(_))
+        );
+        assert_eq!(
+            engine
+                .accounts()
+                .get(1)
+                .unwrap()
+                .snapshot()
+                .available
+                .to_str(),
+            "4.0000"
+        );
+    }
+
+    #[rstest]
+    #[case::included(false, "1,0.0000,0.0000,0.0000,true\n2,2.0000,0.0000,2.0000,false\n")]
+    #[case::excluded(true, "2,2.0000,0.0000,2.0000,false\n")]
+    #[tokio::test]
+    async fn closed_accounts_reject_transactions_and_can_be_left_out(
+        #[case] exclude_closed_accounts: bool,
+        #[case] expected_rows: &str,
+    ) {
+        let engine = Engine::new(EngineConfig {
+            exclude_closed_accounts,
+            ..Default::default()
+        });
+        let input = "
+       type, client, tx, amount
+    deposit,      1,  1,    4.0
+ withdrawal,      1,  2,    4.0
+      close,      1,  3,
+    deposit,      1,  4,    1.0
+    deposit,      2,  5,    2.0";
+        assert_eq!(
+            engine.validate(input.as_bytes()).await.unwrap().to_string(),
+            "rejected: row 6, client 1, tx 4 (deposit): The account is closed\n"
+        );
+        engine.process(input.as_bytes()).await.unwrap();
+        let mut summaries = engine.summaries();
+        summaries.sort_by_key(|summary| summary.client_id);
+        assert_eq!(
+            String::from_utf8(AccountSummaryCsvWriter::write(summaries).unwrap()).unwrap(),
+            format!("client,available,held,total,locked\n{expected_rows}")
+        );
+    }
+
+    #[tokio::test]
+    async fn disputes_outside_of_the_window_are_rejected() {
+        let engine = Engine::new(EngineConfig {
+            account_policy: AccountPolicy {
+                dispute_window: Some(DisputeWindow::Days(30)),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let input = "
+       type, client, tx, amount,  timestamp, currency, memo
+    deposit,      1,  1,    4.0, 1700000000,      EUR,
+    deposit,      2,  2,    6.0, 1700000000,      EUR,
+    dispute,      1,  1,       , 1702592000,         ,
+    dispute,      2,  2,       , 1702592001,         ,";
+        let report = engine.validate(input.as_bytes()).await.unwrap();
+        assert_eq!(
+            report.to_string(),
+            "rejected: row 6, client 2, tx 2 (dispute): The dispute window has expired\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn representment_can_reactivate_the_account() {
+        let engine = Engine::new(EngineConfig {
+            account_policy: AccountPolicy {
+                reactivate_on_representment: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let input = "
+          type, client, tx, amount
+       deposit,      1,  1,    4.0
+       dispute,      1,  1,
+    chargeback,      1,  1,
+ representment,      1,  1,
+       deposit,      1,  2,    1.0";
+        engine.process(input.as_bytes()).await.unwrap();
+        assert_eq!(
+            String::from_utf8(AccountSummaryCsvWriter::write(engine.summaries()).unwrap()).unwrap(),
+            "\
+            client,available,held,total,locked\n\
+            1,5.0000,0.0000,5.0000,false\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn fees_are_taken_and_reported() {
+        let engine = Engine::new(EngineConfig {
+            account_policy: AccountPolicy {
+                fee_policy: FeePolicy {
+                    withdrawal: Some(Fee::Flat(Amount::new(1_000))),
+                    chargeback: Some(Fee::Percentage(Amount::new(100_000))),
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let input = "
+          type, client, tx, amount
+       deposit,      1,  1,    4.0
+    withdrawal,      1,  2,    1.0
+    withdrawal,      1,  3,    2.9
+       deposit,      2,  4,    5.0
+       deposit,      2,  5,    2.0
+       dispute,      2,  5,
+    chargeback,      2,  5,";
+        engine.process(input.as_bytes()).await.unwrap();
+
+        let mut summaries = engine.extended_summaries();
+        summaries.sort_by_key(|summary| summary.client_id);
+        assert_eq!(
+            String::from_utf8(AccountSummaryCsvWriter::write_extended(summaries).unwrap()).unwrap(),
+            "\
+            client,available,held,total,locked,fees,credit_used\n\
+            1,2.9000,0.0000,2.9000,false,0.1000,0.0000\n\
+            2,4.8000,0.0000,4.8000,true,0.2000,0.0000\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn summaries_can_be_written_with_the_totals_of_the_clients() {
+        let engine = Engine::new(EngineConfig::default());
+        let input = "
+          type, client, tx, amount
+       deposit,      1,  1,    4.0
+    withdrawal,      1,  2,    1.5
+       deposit,      1,  3,    2.0
+       dispute,      1,  3,
+       deposit,      2,  4,    5.0
+       deposit,      2,  5,    2.0
+       dispute,      2,  5,
+    chargeback,      2,  5,";
+        engine.process(input.as_bytes()).await.unwrap();
+
+        let mut summaries = engine.summaries();
+        summaries.sort_by_key(|summary| summary.client_id);
+        assert_eq!(
+            String::from_utf8(
+                AccountSummaryCsvWriter::write_with_totals(
+                    summaries,
+                    engine.totals(),
+                    &SummaryFormat::default()
+                )
+                .unwrap()
+            )
+            .unwrap(),
+            "\
+            client,available,held,total,locked,total_deposited,total_withdrawn,total_charged_back,open_disputed_amount\n\
+            1,2.5000,2.0000,4.5000,false,6.0000,1.5000,0.0000,2.0000\n\
+            2,5.0000,0.0000,5.0000,true,7.0000,0.0000,2.0000,0.0000\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn credit_lines_let_withdrawals_go_below_zero() {
+        let engine = Engine::new(EngineConfig {
+            account_policy: AccountPolicy {
+                credit_limits: CreditLimits {
+                    default: Amount::new(10_000),
+                    per_client: HashMap::from([(2, Amount::new(0))]),
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let input = "
+          type, client, tx, amount
+       deposit,      1,  1,    4.0
+    withdrawal,      1,  2,    4.5
+    withdrawal,      1,  3,    1.0
+       deposit,      2,  4,    4.0
+    withdrawal,      2,  5,    4.5";
+        engine.process(input.as_bytes()).await.unwrap();
+
+        let mut summaries = engine.extended_summaries();
+        summaries.sort_by_key(|summary| summary.client_id);
+        assert_eq!(
+            String::from_utf8(AccountSummaryCsvWriter::write_extended(summaries).unwrap()).unwrap(),
+            "\
+            client,available,held,total,locked,fees,credit_used\n\
+            1,-0.5000,0.0000,-0.5000,false,0.0000,0.5000\n\
+            2,4.0000,0.0000,4.0000,false,0.0000,0.0000\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn amounts_beyond_the_limits_are_rejected() {
+        let engine = Engine::new(EngineConfig {
+            account_policy: AccountPolicy {
+                limits: TransactionLimits {
+                    max_deposit: Some(Amount::new(10_000_000)),
+                    max_withdrawal: Some(Amount::new(1_000_000)),
+                    max_transaction: Some(Amount::new(5_000_000)),
+                    minimum_balance: Some(Amount::new(100_000)),
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let input = "
+          type, client, tx,      amount
+       deposit,      1,  1,  9999999999
+       deposit,      1,  2,         600
+       deposit,      1,  3,         400
+    withdrawal,      1,  4,         150
+    withdrawal,      1,  5,          95
+       deposit,      2,  6,          50
+    withdrawal,      2,  7,          45";
+        let report = engine.validate(input.as_bytes()).await.unwrap();
+        assert_eq!(
+            report.to_string(),
+            "\
+            rejected: row 3, client 1, tx 1 (deposit): The deposit exceeds the maximum deposit amount\n\
+            rejected: row 4, client 1, tx 2 (deposit): The transaction exceeds the maximum transaction amount\n\
+            rejected: row 6, client 1, tx 4 (withdrawal): The withdrawal exceeds the maximum withdrawal amount\n\
+            rejected: row 9, client 2, tx 7 (withdrawal): The withdrawal would take the account below its minimum balance\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn risk_rules_screen_the_transactions() {
+        let engine = Engine::with_risk_rules(
+            EngineConfig::default(),
+            vec![
+                Arc::new(WithdrawalVelocity::new(1, 2, RiskOutcome::Flagged)),
+                Arc::new(CumulativeWithdrawals::new(
+                    Amount::new(50_000),
+                    RiskOutcome::Blocked,
+                )),
+            ],
+        );
+        let input = "
+          type, client, tx, amount
+       deposit,      1,  1,   10.0
+    withdrawal,      1,  2,    1.0
+    withdrawal,      1,  3,    1.0
+       deposit,      2,  4,   10.0
+    withdrawal,      2,  5,    4.0
+       deposit,      2,  6,    1.0
+    withdrawal,      2,  7,    2.0";
+        engine.process(input.as_bytes()).await.unwrap();
+
+        assert_eq!(
+            engine.risk_report().to_string(),
+            "\
+            flagged: row 5, client 1, tx 3 (withdrawal) by withdrawal_velocity\n\
+            blocked: row 9, client 2, tx 7 (withdrawal) by cumulative_withdrawals\n"
+        );
+        let mut summaries = engine.summaries();
+        summaries.sort_by_key(|summary| summary.client_id);
+        assert_eq!(
+            String::from_utf8(AccountSummaryCsvWriter::write(summaries).unwrap()).unwrap(),
+            "\
+            client,available,held,total,locked\n\
+            1,8.0000,0.0000,8.0000,false\n\
+            2,7.0000,0.0000,7.0000,false\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn risk_scores_annotate_the_transactions_without_blocking_them() {
+        let engine = Engine::new(EngineConfig::default())
+            .with_risk_scorer(Arc::new(RuleBasedScorer::new(Amount::new(50_000))));
+        let mut scores = engine.risk_scores();
+        let input = "
+          type, client, tx, amount
+       deposit,      1,  1,   10.0
+    withdrawal,      1,  2,    6.0";
+        engine.process(input.as_bytes()).await.unwrap();
+
+        let deposit = scores.try_recv().unwrap();
+        assert_eq!(deposit.transaction.transaction_id, 1);
+        assert_eq!(deposit.score.reasons, vec!["large_amount"]);
+        let withdrawal = scores.try_recv().unwrap();
+        assert_eq!(withdrawal.transaction.transaction_id, 2);
+        assert_eq!(
+            withdrawal.score.reasons,
+            vec!["large_amount", "draining_withdrawal"]
+        );
+        assert!(scores.try_recv().is_err());
+        assert_eq!(
+            engine.accounts().get(1).unwrap().available(),
+            Amount::new(40_000)
+        );
+    }
+
+    #[rstest]
+    #[case(false)]
+    #[case(true)]
+    #[tokio::test]
+    async fn each_row_comes_out_with_its_outcome(#[case] deterministic: bool) {
+        let engine = Engine::new(EngineConfig {
+            deterministic,
+            client_filter: Some(ClientFilter {
+                clients: ClientList::Block(HashSet::from([3])),
+                action: FilterAction::Skip,
+            }),
+            ..Default::default()
+        });
+        let input = "
+          type, client, tx, amount
+       deposit,      1,  1,    2.0
+    withdrawal,      1,  2,    5.0
+       deposit,      3,  3,    1.0
+       deposit,      1,  1,    2.0
+       deposit,      2,  4,    oops";
+        let mut outcomes: Vec<_> = engine
+            .process_with_outcomes(input.as_bytes())
+            .collect()
+            .await;
+
+        assert_matches!(
+            outcomes.pop(),
+            Some(Err(TransactionStreamProcessError::ParsingError(_)))
+        );
+        let mut outcomes: Vec<_> = outcomes
+            .into_iter()
+            .map(|outcome| {
+                let outcome = outcome.unwrap();
+                (outcome.sequence, outcome.outcome)
+            })
+            .collect();
+        outcomes.sort_by_key(|(sequence, _)| *sequence);
+        assert_eq!(
+            outcomes,
+            vec![
+                (3, Outcome::Applied),
+                (
+                    4,
+                    Outcome::Rejected(AccountTransactorError::Withdrawal(
+                        WithdrawerError::InsufficientFund
+                    ))
+                ),
+                (5, Outcome::Skipped),
+                (6, Outcome::Skipped),
+            ]
+        );
+        assert_eq!(
+            engine.accounts().get(1).unwrap().available(),
+            Amount::new(20_000)
+        );
+    }
+
+    #[tokio::test]
+    async fn transactions_can_be_applied_one_at_a_time() {
+        let engine = Engine::new(EngineConfig {
+            collect_stats: true,
+            ..Default::default()
+        });
+        let transaction = |transaction_id, kind| Transaction {
+            client_id: 1,
+            transaction_id,
+            kind,
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
+        };
+
+        let deposit = engine
+            .apply(transaction(
+                1,
+                TransactionKind::Deposit {
+                    amount: Amount::new(30_000),
+                },
+            ))
+            .await
+            .unwrap();
+        assert_eq!(deposit.status, SuccessStatus::Transacted);
+        assert_eq!(
+            deposit.snapshot,
+            Some(AccountSnapshot {
+                available: Amount::new(30_000),
+                held: Amount::new(0),
+            })
+        );
+        assert_matches!(
+            engine
+                .apply(transaction(
+                    2,
+                    TransactionKind::Withdrawal {
+                        amount: Amount::new(50_000),
+                    },
+                ))
+                .await,
+            Err(TransactionProcessorError::AccountTransactionError(
+                _,
+                AccountTransactorError::Withdrawal(WithdrawerError::InsufficientFund)
+            ))
+        );
+        let dispute = engine
+            .apply(transaction(1, TransactionKind::Dispute))
+            .await
+            .unwrap();
+        assert_eq!(
+            dispute.snapshot,
+            Some(AccountSnapshot {
+                available: Amount::new(0),
+                held: Amount::new(30_000),
+            })
+        );
+        assert_eq!(engine.stats()[0].deposits, 1);
+    }
+
+    #[rstest]
+    #[case(FilterAction::Skip, "skipped: client 2, 2 transaction(s)\n")]
+    #[case(
+        FilterAction::Reject,
+        "rejected: row 4, client 2, tx 2 (deposit): The client is not allowed\n\
+        rejected: row 5, client 2, tx 3 (withdrawal): The client is not allowed\n"
+    )]
+    #[tokio::test]
+    async fn blocked_clients_are_skipped_or_rejected(
+        #[case] action: FilterAction,
+        #[case] expected: &str,
+    ) {
+        let engine = Engine::new(EngineConfig {
+            client_filter: Some(ClientFilter {
+                clients: ClientList::Block(HashSet::from([2])),
+                action,
+            }),
+            ..Default::default()
+        });
+        let input = "
+          type, client, tx, amount
+       deposit,      1,  1,    4.0
+       deposit,      2,  2,    6.0
+    withdrawal,      2,  3,    1.0";
+        assert_eq!(
+            engine.validate(input.as_bytes()).await.unwrap().to_string(),
+            expected
+        );
+        if action == FilterAction::Skip {
+            engine.process(input.as_bytes()).await.unwrap();
+            assert_eq!(engine.skipped(), BTreeMap::from([(2, 2)]));
+            assert_eq!(engine.accounts().len(), 1);
+        } else {
+            assert!(engine.process(input.as_bytes()).await.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn stats_are_collected_per_client() {
+        let engine = Engine::new(EngineConfig {
+            collect_stats: true,
+            ..Default::default()
+        });
+        let input = "
+          type, client, tx, amount
+       deposit,      1,  1,    4.0
+       deposit,      1,  1,    4.0
+       deposit,      1,  2,    1.0
+       dispute,      1,  2,
+    withdrawal,      1,  3,    9.0
+       dispute,      1,  9,
+       deposit,      2,  4,    6.0
+       dispute,      2,  4,
+    chargeback,      2,  4,";
+        engine.process(input.as_bytes()).await.unwrap();
+        assert_eq!(
+            String::from_utf8(ClientStatsCsvWriter::write(engine.stats()).unwrap()).unwrap(),
+            "\
+            client,deposits,withdrawals,disputes,resolves,chargebacks,representments,closures,adjustments,duplicates,no_ops,rejections,open_disputes,locked\n\
+            1,3,1,2,0,0,0,0,0,1,0,Dispute(NoTransactionFound)=1;Withdrawal(InsufficientFund)=1,1,false\n\
+            2,1,0,1,0,1,0,0,0,0,0,,0,true\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn the_inputs_start_from_the_opening_balances() {
+        let engine = Engine::new(EngineConfig::default());
+        let balances = OpeningBalance::read(
+            "client,available,held,locked\n1,10.0,2.0,false\n2,5.0,0.0,true".as_bytes(),
+        )
+        .unwrap();
+        engine.open_accounts(&balances).unwrap();
+        let input = "
+          type, client, tx, amount
+    withdrawal,      1,  1,   11.0
+    withdrawal,      1,  2,    4.0
+       deposit,      3,  3,    1.0";
+        engine.process(input.as_bytes()).await.unwrap();
+
+        let mut summaries = engine.summaries();
+        summaries.sort_by_key(|summary| summary.client_id);
+        assert_eq!(
+            String::from_utf8(AccountSummaryCsvWriter::write(summaries).unwrap()).unwrap(),
+            "\
+            client,available,held,total,locked\n\
+            1,6.0000,2.0000,8.0000,false\n\
+            2,5.0000,0.0000,5.0000,true\n\
+            3,1.0000,0.0000,1.0000,false\n"
+        );
+        assert_eq!(
+            engine.open_accounts(&[OpeningBalance {
+                client_id: 3,
+                available: Amount::new(0),
+                held: Amount::new(0),
+                locked: false,
+            }]),
+            Err(OpeningBalancesError::AccountExists(3))
+        );
+    }
+
+    #[tokio::test]
+    async fn the_next_run_starts_from_the_closing_state() {
+        let engine = Engine::new(EngineConfig::default());
+        let input = "
+          type, client, tx, amount
+       deposit,      2,  1,    4.0
+       deposit,      1,  2,    1.0";
+        engine.process(input.as_bytes()).await.unwrap();
+        let state = ClosingState::read(engine.closing_state().write().unwrap().as_slice()).unwrap();
+        assert_eq!(
+            state
+                .accounts
+                .iter()
+                .map(|account| account.client_id)
+                .collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+
+        let next = Engine::new(EngineConfig::default());
+        next.open_state(state.clone()).unwrap();
+        // yesterday's deposit can be disputed today
+        let input = "
+          type, client, tx, amount
+       dispute,      2,  1,";
+        next.process(input.as_bytes()).await.unwrap();
+        assert_eq!(
+            next.accounts().summary(2).unwrap(),
+            AccountSummary {
+                client_id: 2,
+                available: Amount::new(0),
+                held: Amount::new(40_000),
+                total: Amount::new(40_000),
+                locked: false,
+            }
+        );
+        assert_eq!(
+            next.open_state(state),
+            Err(OpeningBalancesError::AccountExists(1))
+        );
+    }
+
+    #[tokio::test]
+    async fn the_outcomes_of_the_disputes_are_reported() {
+        let engine = Engine::new(EngineConfig {
+            collect_stats: true,
+            ..Default::default()
+        });
+        let input = "
+          type, client, tx, amount
+       deposit,      1,  1,    4.0
+       deposit,      1,  2,    1.0
+       dispute,      1,  1,
+    withdrawal,      1,  3,    0.5
+       resolve,      1,  1,
+       dispute,      1,  2,
+       dispute,      1,  9,
+       deposit,      2,  4,    6.0
+       dispute,      2,  4,
+    chargeback,      2,  4,
+       deposit,      3,  5,    1.0";
+        engine.process(input.as_bytes()).await.unwrap();
+        assert_eq!(
+            String::from_utf8(DisputeReportWriter::write(engine.dispute_report()).unwrap())
+                .unwrap(),
+            "\
+            client,opened,resolved,charged_back,auto_resolved,still_open,average_transactions_to_outcome\n\
+            1,2,1,0,0,1,1.0\n\
+            2,1,0,1,0,0,0.0\n\
+            all,3,1,1,0,1,0.5\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn open_disputes_are_reported() {
+        let engine = Engine::new(EngineConfig::default());
+        let input = "
+          type, client, tx, amount
+       deposit,      2,  1,    4.0
+       deposit,      1,  2,    1.0
+       deposit,      1,  3,    2.5
+       dispute,      1,  3,
+       dispute,      1,  2,
+       dispute,      2,  1,
+       resolve,      2,  1,";
+        engine.process(input.as_bytes()).await.unwrap();
+        assert_eq!(
+            String::from_utf8(OpenDisputeReportWriter::write(engine.open_disputes()).unwrap())
+                .unwrap(),
+            "\
+            client,tx,amount,kind\n\
+            1,2,1.0000,deposit\n\
+            1,3,2.5000,deposit\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn unknown_types_are_quarantined_rather_than_failing_the_run() {
+        let engine = Engine::new(EngineConfig {
+            quarantine_unknown_types: true,
+            ..Default::default()
+        });
+        let input = "
+          type, client, tx, amount
+       deposit,      1,  1,    4.0
+      transfer,      1,  2,    1.0
+       deposit,      2,  3,    2.0
+        refund,      2,  4,    1.0
+      transfer,      2,  5,    3.0";
+        engine.process(input.as_bytes()).await.unwrap();
+        assert_eq!(engine.accounts().len(), 2);
+        assert_eq!(
+            engine.quarantine().counts(),
+            BTreeMap::from([("refund".to_string(), 1), ("transfer".to_string(), 2)])
+        );
+        assert_eq!(
+            String::from_utf8(engine.quarantine().write().unwrap()).unwrap(),
+            "\
+            type,client,tx,amount\n\
+            transfer,1,2,1.0\n\
+            refund,2,4,1.0\n\
+            transfer,2,5,3.0\n"
+        );
+    }
+
+    #[rstest]
+    #[case::concurrent(false, None)]
+    #[case::sequential(true, None)]
+    #[case::parallel_parsers(false, Some(2))]
+    #[tokio::test]
+    async fn out_of_range_clients_are_quarantined_rather_than_failing_the_run(
+        #[case] deterministic: bool,
+        #[case] parsers: Option<usize>,
+    ) {
+        let input = "
+          type, client, tx, amount
+       deposit,      1,  1,    4.0
+       deposit,  65536,  2,    1.0";
+        let config = EngineConfig {
+            deterministic,
+            parsers,
+            ..Default::default()
+        };
+        assert_matches!(
+            Engine::new(config.clone()).process(input.as_bytes()).await,
+            Err(TransactionStreamProcessError::ParsingError(message))
+                if message == "The client id 65536 on line 4 is above the largest one, 65535."
+        );
+
+        let engine = Engine::new(EngineConfig {
+            quarantine_out_of_range_clients: true,
+            ..config
+        });
+        engine.process(input.as_bytes()).await.unwrap();
+        assert_eq!(engine.accounts().len(), 1);
+        assert_eq!(engine.quarantine().out_of_range_clients(), 1);
+    }
+
+    #[rstest]
+    #[case(1)]
+    #[case(7)]
+    #[tokio::test]
+    async fn a_file_split_into_byte_ranges_is_processed_as_a_whole_would_be(
+        #[case] byte_ranges: usize,
+    ) {
+        let mut input = String::from("type,client,tx,amount\n");
+        for tx in 0..300 {
+            let client = tx % 11;
+            input += &format!("deposit,{client},{tx},{}.5\n", tx % 7);
+            if tx % 5 == 0 {
+                input += &format!("dispute,{client},{tx},\n");
+            }
+            if tx % 10 == 0 {
+                input += &format!("resolve,{client},{tx},\n");
+            }
+            input += &format!("withdrawal,{client},{},1.0\n", tx + 1000);
+        }
+        let path = env::temp_dir().join(format!(
+            "jouet_paiement_byte_ranges_{byte_ranges}_{}",
+            process::id()
+        ));
+        fs::write(&path, &input).unwrap();
+        let config = EngineConfig {
+            large_transaction_threshold: Some(Amount::new(50_000)),
+            ..Default::default()
+        };
+        let whole = Engine::new(config.clone());
+        whole.process(input.as_bytes()).await.unwrap();
+        let ranged = Engine::new(EngineConfig {
+            byte_ranges: Some(byte_ranges),
+            ..config
+        });
+        ranged.process_file(&path).await.unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let sorted = |mut summaries: Vec<AccountSummary>| {
+            summaries.sort_by_key(|summary| summary.client_id);
+            summaries
+        };
+        assert_eq!(sorted(ranged.summaries()), sorted(whole.summaries()));
+        // the rows are numbered across the ranges
+        assert_eq!(ranged.large_transactions(), whole.large_transactions());
+    }
+
+    #[tokio::test]
+    async fn transactions_delivered_again_after_a_restart_are_duplicates() {
+        let path = env::temp_dir().join(format!("jouet_paiement_redelivered_{}", process::id()));
+        let _ = fs::remove_file(&path);
+        let transaction = |transaction_id, kind| Transaction {
+            client_id: 1,
+            transaction_id,
+            kind,
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
+        };
+        let deposit = |transaction_id, amount| {
+            transaction(
+                transaction_id,
+                TransactionKind::Deposit {
+                    amount: Amount::new(amount),
+                },
+            )
+        };
+        let withdrawal = transaction(
+            2,
+            TransactionKind::Withdrawal {
+                amount: Amount::new(50_000),
+            },
+        );
+
+        let engine = Engine::new(EngineConfig::default())
+            .with_processed_ids(ProcessedIds::open(&path).unwrap());
+        engine.apply(deposit(1, 10_000)).await.unwrap();
+        assert_matches!(
+            engine.apply(withdrawal.clone()).await,
+            Err(TransactionProcessorError::AccountTransactionError(_, _))
+        );
+        engine.apply(deposit(3, 100_000)).await.unwrap();
+        // the rejected withdrawal is not applied now that the funds are there
+        assert_eq!(
+            engine.apply(withdrawal).await.unwrap(),
+            AppliedOutcome {
+                status: SuccessStatus::Duplicate,
+                snapshot: Some(AccountSnapshot {
+                    available: Amount::new(110_000),
+                    held: Amount::new(0),
+                }),
+            }
+        );
+        drop(engine);
+
+        let restarted = Engine::new(EngineConfig::default())
+            .with_processed_ids(ProcessedIds::open(&path).unwrap());
+        assert_eq!(
+            restarted.apply(deposit(3, 100_000)).await.unwrap().status,
+            SuccessStatus::Duplicate
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn large_deposits_and_withdrawals_are_listed() {
+        let engine = Engine::new(EngineConfig {
+            large_transaction_threshold: Some(Amount::new(100_000)),
+            ..Default::default()
+        });
+        let input = "
+          type, client, tx, amount
+       deposit,      1,  1,   10.0
+       deposit,      2,  2,   50.0
+    withdrawal,      1,  3,   20.0
+    withdrawal,      2,  4,   15.0
+       dispute,      2,  2,";
+        engine.process(input.as_bytes()).await.unwrap();
+
+        assert_eq!(
+            engine
+                .large_transactions()
+                .iter()
+                .map(|large| (large.transaction.transaction_id, large.applied))
+                .collect::<Vec<_>>(),
+            vec![(3, false), (2, true), (4, true)]
+        );
+    }
+
+    #[tokio::test]
+    async fn each_change_to_a_snapshot_is_recorded_with_its_cause() {
+        let engine = Engine::new(EngineConfig {
+            collect_deltas: true,
+            ..Default::default()
+        });
+        let input = "
+          type, client, tx, amount
+       deposit,      1,  1,    2.0
+    withdrawal,      2,  2,    1.0
+       dispute,      1,  1,
+    chargeback,      1,  1,";
+        engine.process(input.as_bytes()).await.unwrap();
+
+        let two = Amount::new(20_000);
+        assert_eq!(
+            engine
+                .snapshot_deltas()
+                .into_iter()
+                .map(|delta| (delta.client_id, delta.change, delta.cause.kind.name()))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    1,
+                    FieldChange::Available {
+                        old: Amount::ZERO,
+                        new: two
+                    },
+                    "deposit"
+                ),
+                (
+                    1,
+                    FieldChange::Total {
+                        old: Amount::ZERO,
+                        new: two
+                    },
+                    "deposit"
+                ),
+                (
+                    1,
+                    FieldChange::Available {
+                        old: two,
+                        new: Amount::ZERO
+                    },
+                    "dispute"
+                ),
+                (
+                    1,
+                    FieldChange::Held {
+                        old: Amount::ZERO,
+                        new: two
+                    },
+                    "dispute"
+                ),
+                (
+                    1,
+                    FieldChange::Held {
+                        old: two,
+                        new: Amount::ZERO
+                    },
+                    "chargeback"
+                ),
+                (
+                    1,
+                    FieldChange::Total {
+                        old: two,
+                        new: Amount::ZERO
+                    },
+                    "chargeback"
+                ),
+                (
+                    1,
+                    FieldChange::Locked {
+                        old: false,
+                        new: true
+                    },
+                    "chargeback"
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn conflicting_ids_are_quarantined_rather_than_failing_the_run() {
+        let engine = Engine::new(EngineConfig {
+            quarantine_conflicts: true,
+            ..Default::default()
+        });
+        let input = "
+          type, client, tx, amount
+       deposit,      1,  1,    4.0
+       deposit,      1,  1,    5.0
+       deposit,      1,  2,    1.0";
+        engine.process(input.as_bytes()).await.unwrap();
+        assert_eq!(
+            engine.accounts().get(1).unwrap().available(),
+            Amount::new(50_000)
+        );
+        assert_eq!(
+            engine
+                .conflicts()
+                .into_iter()
+                .map(|conflict| conflict.transaction.ingest_sequence)
+                .collect::<Vec<Option<u64>>>(),
+            vec![Some(4)]
+        );
+    }
+
+    #[tokio::test]
+    async fn shards_split_the_clients_between_engines() {
+        let input = "
+          type, client, tx, amount
+       deposit,      1,  1,    1.0
+       deposit,      2,  2,    2.0
+       deposit,      3,  3,    3.0
+       deposit,      4,  4,    4.0";
+        let mut client_ids = Vec::new();
+        for shard in ["1/2", "2/2"] {
+            let engine = Engine::new(EngineConfig {
+                shard: Some(shard.parse().unwrap()),
+                ..Default::default()
+            });
+            engine.process(input.as_bytes()).await.unwrap();
+            let mut summaries = engine.summaries();
+            summaries.sort_by_key(|summary| summary.client_id);
+            client_ids.push(
+                summaries
+                    .into_iter()
+                    .map(|summary| summary.client_id)
+                    .collect::<Vec<_>>(),
+            );
+        }
+        assert_eq!(client_ids, vec![vec![2, 4], vec![1, 3]]);
+    }
+
+    #[tokio::test]
+    async fn stale_disputes_are_resolved_automatically() {
+        let engine = Engine::new(EngineConfig {
+            account_policy: AccountPolicy {
+                auto_resolution: Some(AutoResolution::Transactions(2)),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let input = "
+          type, client, tx, amount
+       deposit,      1,  1,    4.0
+       dispute,      1,  1,
+       deposit,      1,  2,    1.0
+       deposit,      2,  3,    2.0
+       dispute,      2,  3,
+       deposit,      2,  4,    1.0
+       deposit,      1,  5,    1.0";
+        engine.process(input.as_bytes()).await.unwrap();
+        assert_eq!(
+            engine.auto_resolved_disputes(),
+            vec![AutoResolvedDispute {
+                client_id: 1,
+                transaction_id: 1,
+                amount: Amount::new(40_000),
+                disputed_at: None,
+            }]
+        );
+        assert_eq!(
+            String::from_utf8(OpenDisputeReportWriter::write(engine.open_disputes()).unwrap())
+                .unwrap(),
+            "\
+            client,tx,amount,kind\n\
+            2,3,2.0000,deposit\n"
+        );
+    }
+
+    #[rstest]
+    #[case(false)]
+    #[case(true)]
+    #[tokio::test]
+    async fn reading_is_held_to_the_records_per_second(#[case] deterministic: bool) {
+        let engine = Engine::new(EngineConfig {
+            max_records_per_second: Some(200),
+            deterministic,
+            ..Default::default()
+        });
+        let input: String = std::iter::once("type, client, tx, amount".to_string())
+            .chain((1..=41).map(|tx| format!("deposit, {}, {tx}, 1.0", tx % 3)))
+            .collect::<Vec<String>>()
+            .join("\n");
+        let started = std::time::Instant::now();
+        engine.process(input.as_bytes()).await.unwrap();
+        // the first record is read straight away, each of the others 5ms
+        // after the previous one
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        assert_eq!(engine.throughput().records, 41);
+    }
+
+    #[tokio::test]
+    async fn run_over_memory_budget_is_throttled_rather_than_stopped() {
+        let engine = Engine::new(EngineConfig {
+            memory_budget: Some(1),
+            ..Default::default()
+        });
+        let input: String = std::iter::once("type, client, tx, amount".to_string())
+            .chain((1..=3_000).map(|tx| format!("deposit, {}, {tx}, 1.0", tx % 3)))
+            .collect::<Vec<String>>()
+            .join("\n");
+        engine.process(input.as_bytes()).await.unwrap();
+        let mut summaries = engine.summaries();
+        summaries.sort_by_key(|summary| summary.client_id);
+        assert_eq!(
+            String::from_utf8(AccountSummaryCsvWriter::write(summaries).unwrap()).unwrap(),
+            "\
+            client,available,held,total,locked\n\
+            0,1000.0000,0.0000,1000.0000,false\n\
+            1,1000.0000,0.0000,1000.0000,false\n\
+            2,1000.0000,0.0000,1000.0000,false\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn cancelled_run_stops_reading_the_input() {
+        let engine = Engine::new(EngineConfig::default());
+        let input = "
+          type, client, tx, amount
+       deposit,      1,  1,    1.0";
+        engine.process(input.as_bytes()).await.unwrap();
+        engine.cancellation().cancel();
+        assert_eq!(
+            engine
+                .process(
+                    "
+          type, client, tx, amount
+       deposit,      2,  2,    1.0"
+                        .as_bytes()
+                )
+                .await,
+            Err(TransactionStreamProcessError::Cancelled)
+        );
+        assert_eq!(
+            String::from_utf8(AccountSummaryCsvWriter::write(engine.summaries()).unwrap()).unwrap(),
+            "\
+            client,available,held,total,locked\n\
+            1,1.0000,0.0000,1.0000,false\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn errors_are_received_as_they_occur_even_if_suppressed() {
+        let engine = Engine::new(EngineConfig::default());
+        let mut errors = engine.errors();
+        let input = "
+          type, client, tx, amount
+       deposit,      1,  1,    1.0
+    withdrawal,      1,  2,    5.0
+       dispute,      1,  3,";
+        engine.process(input.as_bytes()).await.unwrap();
+        let mut reasons = Vec::new();
+        while let Ok(TransactionProcessorError::AccountTransactionError(_, reason)) =
+            errors.try_recv()
+        {
+            reasons.push(reason);
+        }
+        assert_eq!(
+            reasons,
+            vec![
+                AccountTransactorError::Withdrawal(WithdrawerError::InsufficientFund),
+                AccountTransactorError::Dispute(DisputerError::NoTransactionFound),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn summaries_are_received_as_the_accounts_change() {
+        let engine = Engine::new(EngineConfig::default());
+        let mut updates = engine.summary_updates();
+        let input = "
+          type, client, tx, amount
+       deposit,      1,  1,    1.0
+    withdrawal,      1,  2,    5.0
+       deposit,      1,  3,    2.0";
+        engine.process(input.as_bytes()).await.unwrap();
+        let mut available = Vec::new();
+        while let Ok(summary) = updates.try_recv() {
+            available.push(summary.available);
+        }
+        // not on the rejected withdrawal
+        assert_eq!(available, vec![Amount::new(10_000), Amount::new(30_000)]);
+    }
+
+    #[tokio::test]
+    async fn an_event_is_sent_for_each_transaction_applied() {
+        let (events, mut received) = mpsc::channel(1);
+        let engine = Engine::new(EngineConfig::default()).with_account_events(events);
+        let input = "
+          type, client, tx, amount
+       deposit,      1,  1,    1.0
+       deposit,      2,  2,    1.0
+       dispute,      1,  1,
+       deposit,      1,  1,    1.0";
+        let (processed, events) = tokio::join!(engine.process(input.as_bytes()), async {
+            let mut events = Vec::new();
+            while events.len() < 3 {
+                events.push(received.recv().await.unwrap());
+            }
+            events
+        });
+        processed.unwrap();
+        let client_1 = events
+            .iter()
+            .filter(|event| event.summary.client_id == 1)
+            .map(|event| serde_json::to_string(event).unwrap())
+            .collect::<Vec<_>>();
+        // not on the duplicate deposit
+        assert_eq!(
+            client_1,
+            vec![
+                r#"{"tx":1,"type":"deposit","client":1,"available":"1.0000","held":"0.0000","total":"1.0000","locked":false}"#,
+                r#"{"tx":1,"type":"dispute","client":1,"available":"0.0000","held":"1.0000","total":"1.0000","locked":false}"#,
+            ]
+        );
+        drop(engine);
+        assert!(received.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn deterministic_run_processes_the_input_in_order() {
+        let engine = Engine::new(EngineConfig {
+            deterministic: true,
+            ..Default::default()
+        });
+        let mut errors = engine.errors();
+        let input = "
+          type, client, tx, amount
+    withdrawal,      3,  1,    1.0
+    withdrawal,      1,  2,    1.0
+       deposit,      2,  3,    1.0
+    withdrawal,      2,  4,    5.0
+    withdrawal,      1,  5,    1.0";
+        engine.process(input.as_bytes()).await.unwrap();
+        let mut rejected = Vec::new();
+        while let Ok(TransactionProcessorError::AccountTransactionError(transaction, _)) =
+            errors.try_recv()
+        {
+            rejected.push(transaction.transaction_id);
+        }
+        assert_eq!(rejected, vec![1, 2, 4, 5]);
+        assert_eq!(engine.accounts().len(), 3);
+    }
+
+    #[rstest]
+    #[case::input_parsed_as_read(None, None)]
+    #[case::input_parsed_in_parallel(Some(4), None)]
+    #[case::workers_exiting_idle(None, Some(Duration::ZERO))]
+    #[tokio::test]
+    async fn both_pipelines_agree_on_the_accounts(
+        #[case] parsers: Option<usize>,
+        #[case] worker_idle_timeout: Option<Duration>,
+    ) {
+        let engine = Engine::new(EngineConfig {
+            parsers,
+            worker_idle_timeout,
+            ..Default::default()
+        });
+        let input = "
+          type, client, tx, amount
+       deposit,      1,  1,    4.0
+       deposit,      2,  2,    2.0
+    withdrawal,      1,  3,    5.0
+       dispute,      2,  2,
+    chargeback,      2,  2,
+       deposit,      2,  4,    1.0
+       deposit,      3,  5,    1.0";
+        let report = engine.verify(input.as_bytes()).await.unwrap();
+        assert!(report.is_consistent(), "{report}");
+        assert_eq!(report.accounts, 3);
+        assert!(engine.accounts().is_empty());
+    }
+
+    #[tokio::test]
+    async fn ordering_can_be_checked() {
+        let engine = Engine::new(EngineConfig {
+            check_ordering: true,
+            ..Default::default()
+        });
+        let input = "
+          type, client, tx, amount
+       deposit,      1,  1,    4.0
+       deposit,      2,  2,    2.0
+    withdrawal,      1,  3,    1.0
+       dispute,      2,  2,";
+        engine.process(input.as_bytes()).await.unwrap();
+        assert_eq!(engine.order_violations(), vec![]);
+        assert_eq!(
+            engine.accounts().get(1).unwrap().available(),
+            Amount::new(30_000)
+        );
+    }
+
+    #[tokio::test]
+    async fn unreadable_input_fails_the_run() {
+        let engine = Engine::new(EngineConfig::default());
+        let input = "
+       type, client, tx, amount
+    deposit,      1,  1,    4.0
+    deposit,  alice,  2,    6.0";
+        assert_matches!(
+            engine.process(input.as_bytes()).await,
+            Err(TransactionStreamProcessError::ParsingError(_))
+        );
+        assert_eq!(engine.accounts().len(), 1);
+    }
+}
@@ -0,0 +1,77 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+use crate::{
+    account::{AccountStore, SuccessStatus},
+    model::{AccountSummary, Transaction, TransactionId},
+    transaction_processor::{TransactionProcessor, TransactionProcessorError},
+};
+
+/// A transaction applied to a client's account, along with the summary of
+/// the account once applied, for a downstream system to keep a mirror of the
+/// accounts, written as JSON, e.g.
+/// `{"tx":1,"type":"deposit","client":1,"available":"1.5000","held":"0.0000","total":"1.5000","locked":false}`.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct AccountEvent {
+    #[serde(rename = "tx")]
+    pub transaction_id: TransactionId,
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    #[serde(flatten)]
+    pub summary: AccountSummary,
+}
+
+/// Sends an [`AccountEvent`] each time a transaction changes an account, on
+/// its way back from the processor it wraps. The events of a client are sent
+/// in the order its transactions were applied in.
+/// The transactions are held back while the events are not taken, so that
+/// none of them is missed, and fail with
+/// [`TransactionProcessorError::Unavailable`] once they no longer are.
+pub(super) struct AccountEventPublisher {
+    inner: Arc<dyn TransactionProcessor + Send + Sync>,
+    accounts: Arc<AccountStore>,
+    events: mpsc::Sender<AccountEvent>,
+}
+
+#[async_trait]
+impl TransactionProcessor for AccountEventPublisher {
+    async fn process(
+        &self,
+        transaction: Transaction,
+    ) -> Result<SuccessStatus, TransactionProcessorError> {
+        let result = self.inner.process(transaction.clone()).await;
+        if let Ok(SuccessStatus::Transacted) = result {
+            if let Some(summary) = self.accounts.summary(transaction.client_id) {
+                let event = AccountEvent {
+                    transaction_id: transaction.transaction_id,
+                    kind: transaction.kind.name(),
+                    summary,
+                };
+                if self.events.send(event).await.is_err() {
+                    return Err(TransactionProcessorError::Unavailable(
+                        Box::new(transaction),
+                        "The account events are no longer taken".to_string(),
+                    ));
+                }
+            }
+        }
+        result
+    }
+}
+
+impl AccountEventPublisher {
+    pub(super) fn new(
+        inner: Arc<dyn TransactionProcessor + Send + Sync>,
+        accounts: Arc<AccountStore>,
+        events: mpsc::Sender<AccountEvent>,
+    ) -> Self {
+        Self {
+            inner,
+            accounts,
+            events,
+        }
+    }
+}
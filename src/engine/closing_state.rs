@@ -0,0 +1,95 @@
+use std::{
+    collections::HashSet,
+    io::{BufRead, BufReader, Read},
+};
+
+use thiserror::Error;
+
+use crate::{account::Account, model::ClientId};
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ClosingStateError {
+    #[error("Failed to serialise the account of client {0}: {1}")]
+    SerialisationError(ClientId, String),
+    #[error("Failed to read line {0} of the closing state: {1}")]
+    ReadError(usize, String),
+    #[error("Client {0} has more than one account in the closing state.")]
+    DuplicateClient(ClientId),
+}
+
+/// The accounts of an engine at the end of a run, their transactions
+/// included, for the next run to start from, see [`Engine::open_state`].
+/// It is written as newline-delimited JSON, one account per line in the
+/// format accounts are serialised in, e.g.
+/// `{"version":"1","account":{"client_id":1,...}}`.
+///
+/// [`Engine::open_state`]: super::Engine::open_state
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ClosingState {
+    pub accounts: Vec<Account>,
+}
+
+impl ClosingState {
+    /// Reads the accounts back, a client given twice or an account of
+    /// another version failing the whole state. Blank lines are skipped.
+    pub fn read(r: impl Read) -> Result<Self, ClosingStateError> {
+        let mut clients = HashSet::new();
+        let mut accounts = Vec::new();
+        for (index, line) in BufReader::new(r).lines().enumerate() {
+            let read_error = |err: String| ClosingStateError::ReadError(index + 1, err);
+            let line = line.map_err(|err| read_error(err.to_string()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let account: Account =
+                serde_json::from_str(&line).map_err(|err| read_error(err.to_string()))?;
+            if !clients.insert(account.client_id) {
+                return Err(ClosingStateError::DuplicateClient(account.client_id));
+            }
+            accounts.push(account);
+        }
+        Ok(Self { accounts })
+    }
+
+    pub fn write(&self) -> Result<Vec<u8>, ClosingStateError> {
+        let mut written = Vec::new();
+        for account in &self.accounts {
+            serde_json::to_writer(&mut written, account).map_err(|err| {
+                ClosingStateError::SerialisationError(account.client_id, err.to_string())
+            })?;
+            written.push(b'\n');
+        }
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{account::Account, model::Amount};
+
+    use super::{ClosingState, ClosingStateError};
+
+    #[test]
+    fn the_closing_state_reads_back_as_it_was_written() {
+        let mut account = Account::active(1);
+        account.record_deposit(1, Amount::new(10_000), None);
+        account.snapshot_mut().available += Amount::new(10_000);
+        let state = ClosingState {
+            accounts: vec![account, Account::active(2)],
+        };
+        let written = state.write().unwrap();
+        assert_eq!(written.iter().filter(|byte| **byte == b'\n').count(), 2);
+        assert_eq!(ClosingState::read(written.as_slice()), Ok(state));
+    }
+
+    #[test]
+    fn a_client_given_twice_fails_the_closing_state() {
+        let state = ClosingState {
+            accounts: vec![Account::active(1), Account::active(1)],
+        };
+        assert_eq!(
+            ClosingState::read(state.write().unwrap().as_slice()),
+            Err(ClosingStateError::DuplicateClient(1))
+        );
+    }
+}
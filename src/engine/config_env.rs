@@ -0,0 +1,273 @@
+use std::{collections::HashMap, env, fmt::Display, str::FromStr};
+
+use super::{
+    config_file::{
+        parse_value, CheckpointSection, ConfigFile, CsvSection, DisputeSection, FeeSection,
+        LimitSection, RetrySection,
+    },
+    ConfigError, EngineConfig,
+};
+
+/// The prefix of the environment variables read by [`EngineConfig::from_env`].
+pub const ENV_PREFIX: &str = "JP_";
+
+/// The settings of the config file, in upper case and prefixed with
+/// [`ENV_PREFIX`], those of a section being prefixed with its name as well,
+/// e.g. `JP_CHANNEL_CAPACITY` or `JP_DISPUTES_WINDOW`.
+const VARIABLES: [&str; 43] = [
+    "JP_EXPECTED_CLIENT_COUNT",
+    "JP_EXPECTED_TRANSACTIONS_PER_CLIENT",
+    "JP_SHARD_AMOUNT",
+    "JP_CHANNEL_CAPACITY",
+    "JP_WORKER_IDLE_TIMEOUT_MS",
+    "JP_STALL_TIMEOUT_MS",
+    "JP_PARSERS",
+    "JP_BYTE_RANGES",
+    "JP_MEMORY_BUDGET",
+    "JP_MAX_RECORDS_PER_SECOND",
+    "JP_COLLECT_STATS",
+    "JP_COLLECT_DELTAS",
+    "JP_EXCLUDE_CLOSED_ACCOUNTS",
+    "JP_QUARANTINE_UNKNOWN_TYPES",
+    "JP_QUARANTINE_OUT_OF_RANGE_CLIENTS",
+    "JP_QUARANTINE_CONFLICTS",
+    "JP_LARGE_TRANSACTION_THRESHOLD",
+    "JP_SHARD",
+    "JP_DETERMINISTIC",
+    "JP_CHECK_ORDERING",
+    "JP_PRIORITISE_ADMIN_TRANSACTIONS",
+    "JP_CSV_DELIMITER",
+    "JP_CSV_HAS_HEADERS",
+    "JP_CSV_COLUMN_MAP",
+    "JP_CSV_AMOUNT_STRICTNESS",
+    "JP_CSV_SCIENTIFIC_NOTATION",
+    "JP_CSV_DECIMAL_SEPARATOR",
+    "JP_CSV_THOUSANDS_SEPARATOR",
+    "JP_DISPUTES_WINDOW",
+    "JP_DISPUTES_AUTO_RESOLUTION",
+    "JP_DISPUTES_REACTIVATE_ON_REPRESENTMENT",
+    "JP_DISPUTES_MAX_OPEN",
+    "JP_LIMITS_MAX_DEPOSIT",
+    "JP_LIMITS_MAX_WITHDRAWAL",
+    "JP_LIMITS_MAX_TRANSACTION",
+    "JP_LIMITS_MINIMUM_BALANCE",
+    "JP_LIMITS_CREDIT_LIMIT",
+    "JP_FEES_WITHDRAWAL",
+    "JP_FEES_CHARGEBACK",
+    "JP_RETRY_MAX_ATTEMPTS",
+    "JP_RETRY_BACKOFF_MS",
+    "JP_CHECKPOINTS_INTERVAL",
+    "JP_CHECKPOINTS_MAX",
+];
+
+impl EngineConfig {
+    /// Reads the config from the `JP_` environment variables, e.g. in a
+    /// container, the settings without one keeping their defaults. See
+    /// the README for the variables.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::default().with_env()
+    }
+
+    /// The config with the settings of the `JP_` environment variables in
+    /// place of its own, e.g. those of a config file.
+    /// A `JP_` variable that is not one of the settings is rejected, so that
+    /// a typo does not go unnoticed.
+    pub fn with_env(self) -> Result<Self, ConfigError> {
+        self.with_vars(env::vars())
+    }
+
+    fn with_vars(
+        self,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, ConfigError> {
+        let vars: HashMap<String, String> = vars
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(ENV_PREFIX))
+            .collect();
+        if let Some(unknown) = vars.keys().find(|name| !VARIABLES.contains(&name.as_str())) {
+            return Err(ConfigError::UnknownVariable(unknown.clone()));
+        }
+        let var = |name: &str| vars.get(name).cloned();
+        let typed = |name: &'static str| Typed(name, vars.get(name));
+        // either of them turns the retries on, as the `[retry]` section does
+        let retry = match ["JP_RETRY_MAX_ATTEMPTS", "JP_RETRY_BACKOFF_MS"]
+            .iter()
+            .any(|name| vars.contains_key(*name))
+        {
+            true => Some(RetrySection {
+                max_attempts: typed("JP_RETRY_MAX_ATTEMPTS").get()?,
+                backoff_ms: typed("JP_RETRY_BACKOFF_MS").get()?,
+            }),
+            false => None,
+        };
+        // as do the checkpoints, as the `[checkpoints]` section does
+        let checkpoints = match ["JP_CHECKPOINTS_INTERVAL", "JP_CHECKPOINTS_MAX"]
+            .iter()
+            .any(|name| vars.contains_key(*name))
+        {
+            true => Some(CheckpointSection {
+                interval: typed("JP_CHECKPOINTS_INTERVAL").get()?,
+                max: typed("JP_CHECKPOINTS_MAX").get()?,
+            }),
+            false => None,
+        };
+        ConfigFile {
+            expected_client_count: typed("JP_EXPECTED_CLIENT_COUNT").get()?,
+            expected_transactions_per_client: typed("JP_EXPECTED_TRANSACTIONS_PER_CLIENT").get()?,
+            shard_amount: typed("JP_SHARD_AMOUNT").get()?,
+            channel_capacity: typed("JP_CHANNEL_CAPACITY").get()?,
+            worker_idle_timeout_ms: typed("JP_WORKER_IDLE_TIMEOUT_MS").get()?,
+            stall_timeout_ms: typed("JP_STALL_TIMEOUT_MS").get()?,
+            parsers: typed("JP_PARSERS").get()?,
+            byte_ranges: typed("JP_BYTE_RANGES").get()?,
+            memory_budget: typed("JP_MEMORY_BUDGET").get()?,
+            max_records_per_second: typed("JP_MAX_RECORDS_PER_SECOND").get()?,
+            collect_stats: typed("JP_COLLECT_STATS").get()?,
+            collect_deltas: typed("JP_COLLECT_DELTAS").get()?,
+            exclude_closed_accounts: typed("JP_EXCLUDE_CLOSED_ACCOUNTS").get()?,
+            quarantine_unknown_types: typed("JP_QUARANTINE_UNKNOWN_TYPES").get()?,
+            quarantine_out_of_range_clients: typed("JP_QUARANTINE_OUT_OF_RANGE_CLIENTS").get()?,
+            quarantine_conflicts: typed("JP_QUARANTINE_CONFLICTS").get()?,
+            large_transaction_threshold: var("JP_LARGE_TRANSACTION_THRESHOLD"),
+            shard: var("JP_SHARD"),
+            deterministic: typed("JP_DETERMINISTIC").get()?,
+            check_ordering: typed("JP_CHECK_ORDERING").get()?,
+            prioritise_admin_transactions: typed("JP_PRIORITISE_ADMIN_TRANSACTIONS").get()?,
+            csv: CsvSection {
+                delimiter: typed("JP_CSV_DELIMITER").get()?,
+                has_headers: typed("JP_CSV_HAS_HEADERS").get()?,
+                column_map: var("JP_CSV_COLUMN_MAP")
+                    .map(|column_map| parse_column_map(&column_map))
+                    .transpose()?,
+                amount_strictness: typed("JP_CSV_AMOUNT_STRICTNESS").get()?,
+                scientific_notation: typed("JP_CSV_SCIENTIFIC_NOTATION").get()?,
+                decimal_separator: typed("JP_CSV_DECIMAL_SEPARATOR").get()?,
+                thousands_separator: typed("JP_CSV_THOUSANDS_SEPARATOR").get()?,
+            },
+            disputes: DisputeSection {
+                window: var("JP_DISPUTES_WINDOW"),
+                auto_resolution: var("JP_DISPUTES_AUTO_RESOLUTION"),
+                reactivate_on_representment: typed("JP_DISPUTES_REACTIVATE_ON_REPRESENTMENT")
+                    .get()?,
+                max_open: typed("JP_DISPUTES_MAX_OPEN").get()?,
+            },
+            limits: LimitSection {
+                max_deposit: var("JP_LIMITS_MAX_DEPOSIT"),
+                max_withdrawal: var("JP_LIMITS_MAX_WITHDRAWAL"),
+                max_transaction: var("JP_LIMITS_MAX_TRANSACTION"),
+                minimum_balance: var("JP_LIMITS_MINIMUM_BALANCE"),
+                credit_limit: var("JP_LIMITS_CREDIT_LIMIT"),
+            },
+            fees: FeeSection {
+                withdrawal: var("JP_FEES_WITHDRAWAL"),
+                chargeback: var("JP_FEES_CHARGEBACK"),
+            },
+            retry,
+            checkpoints,
+        }
+        .applied_to(self)
+    }
+}
+
+/// A variable, if set, to be parsed as the type of its setting.
+struct Typed<'a>(&'static str, Option<&'a String>);
+
+impl Typed<'_> {
+    fn get<T: FromStr>(&self) -> Result<Option<T>, ConfigError>
+    where
+        T::Err: Display,
+    {
+        self.1.map(|value| parse_value(self.0, value)).transpose()
+    }
+}
+
+/// e.g. `txn_type=type;client_id=client`.
+fn parse_column_map(column_map: &str) -> Result<HashMap<String, String>, ConfigError> {
+    column_map
+        .split(';')
+        .filter(|pair| !pair.trim().is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((from, to)) => Ok((from.trim().to_string(), to.trim().to_string())),
+            None => Err(ConfigError::InvalidValue(
+                "JP_CSV_COLUMN_MAP",
+                format!("`{column_map}`, expected e.g. `txn_type=type;client_id=client`"),
+            )),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::Duration};
+
+    use crate::{
+        account::DisputeWindow,
+        engine::{ConfigError, EngineConfig},
+        transaction_processor::RetryPolicy,
+    };
+
+    fn vars(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn config_can_be_read_from_the_variables() {
+        let config = EngineConfig::default()
+            .with_vars(vars(&[
+                ("JP_CHANNEL_CAPACITY", "1024"),
+                ("JP_DISPUTES_WINDOW", "90 days"),
+                ("JP_CSV_COLUMN_MAP", "txn_type=type;client_id=client"),
+                ("JP_RETRY_MAX_ATTEMPTS", "5"),
+                ("PATH", "/usr/bin"),
+            ]))
+            .unwrap();
+        assert_eq!(config.channel_capacity, Some(1024));
+        assert_eq!(
+            config.account_policy.dispute_window,
+            Some(DisputeWindow::Days(90))
+        );
+        assert_eq!(
+            config.csv_options.column_map,
+            HashMap::from([
+                ("txn_type".to_string(), "type".to_string()),
+                ("client_id".to_string(), "client".to_string()),
+            ])
+        );
+        assert_eq!(
+            config.retry_policy,
+            Some(RetryPolicy {
+                max_attempts: 5,
+                backoff: Duration::from_millis(10),
+            })
+        );
+    }
+
+    #[test]
+    fn variables_take_precedence_over_the_config_file() {
+        let config = EngineConfig::from_toml("channel_capacity = 64\nshard_amount = 8")
+            .unwrap()
+            .with_vars(vars(&[("JP_CHANNEL_CAPACITY", "1024")]))
+            .unwrap();
+        assert_eq!(config.channel_capacity, Some(1024));
+        assert_eq!(config.shard_amount, Some(8));
+    }
+
+    #[test]
+    fn unknown_and_invalid_variables_are_rejected() {
+        assert_eq!(
+            EngineConfig::default().with_vars(vars(&[("JP_CHANEL_CAPACITY", "1024")])),
+            Err(ConfigError::UnknownVariable(
+                "JP_CHANEL_CAPACITY".to_string()
+            ))
+        );
+        assert_eq!(
+            EngineConfig::default().with_vars(vars(&[("JP_COLLECT_STATS", "yes")])),
+            Err(ConfigError::InvalidValue(
+                "JP_COLLECT_STATS",
+                "`yes` (provided string was not `true` or `false`)".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,577 @@
+use std::{
+    collections::HashMap, fmt::Display, fs, num::NonZeroU64, path::Path, str::FromStr,
+    time::Duration,
+};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    account::{AutoResolution, Checkpointing, DisputeWindow, Fee},
+    model::Amount,
+    transaction_processor::RetryPolicy,
+    transaction_stream_processor::AmountStrictness,
+};
+
+use super::EngineConfig;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ConfigError {
+    #[error("Failed to read the config file: {0}")]
+    IoError(String),
+
+    #[error("Failed to parse the config: {0}")]
+    ParsingError(String),
+
+    #[error("Invalid `{0}` in the config: {1}")]
+    InvalidValue(&'static str, String),
+
+    #[error("Unsupported config file `{0}`, expected a `.toml`, `.yaml` or `.yml` one")]
+    UnsupportedFormat(String),
+
+    #[error("Unknown environment variable `{0}`")]
+    UnknownVariable(String),
+}
+
+/// The settings of an [`EngineConfig`] as they are written in a file, or in
+/// environment variables, any of them left out keeping its default.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(super) struct ConfigFile {
+    pub(super) expected_client_count: Option<usize>,
+    pub(super) expected_transactions_per_client: Option<usize>,
+    pub(super) shard_amount: Option<usize>,
+    pub(super) channel_capacity: Option<usize>,
+    pub(super) worker_idle_timeout_ms: Option<u64>,
+    pub(super) stall_timeout_ms: Option<u64>,
+    pub(super) parsers: Option<usize>,
+    pub(super) byte_ranges: Option<usize>,
+    pub(super) memory_budget: Option<usize>,
+    pub(super) max_records_per_second: Option<u32>,
+    pub(super) collect_stats: Option<bool>,
+    pub(super) collect_deltas: Option<bool>,
+    pub(super) exclude_closed_accounts: Option<bool>,
+    pub(super) quarantine_unknown_types: Option<bool>,
+    pub(super) quarantine_out_of_range_clients: Option<bool>,
+    pub(super) quarantine_conflicts: Option<bool>,
+    pub(super) large_transaction_threshold: Option<String>,
+    pub(super) shard: Option<String>,
+    pub(super) deterministic: Option<bool>,
+    pub(super) check_ordering: Option<bool>,
+    pub(super) prioritise_admin_transactions: Option<bool>,
+    pub(super) csv: CsvSection,
+    pub(super) disputes: DisputeSection,
+    pub(super) limits: LimitSection,
+    pub(super) fees: FeeSection,
+    pub(super) retry: Option<RetrySection>,
+    pub(super) checkpoints: Option<CheckpointSection>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(super) struct CsvSection {
+    pub(super) delimiter: Option<char>,
+    pub(super) has_headers: Option<bool>,
+    pub(super) column_map: Option<HashMap<String, String>>,
+    pub(super) amount_strictness: Option<AmountStrictness>,
+    pub(super) scientific_notation: Option<bool>,
+    pub(super) decimal_separator: Option<char>,
+    pub(super) thousands_separator: Option<char>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(super) struct DisputeSection {
+    /// e.g. `90 days` or `10 transactions`.
+    pub(super) window: Option<String>,
+    /// e.g. `30 days` or `5 transactions`.
+    pub(super) auto_resolution: Option<String>,
+    pub(super) reactivate_on_representment: Option<bool>,
+    pub(super) max_open: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(super) struct LimitSection {
+    pub(super) max_deposit: Option<String>,
+    pub(super) max_withdrawal: Option<String>,
+    pub(super) max_transaction: Option<String>,
+    pub(super) minimum_balance: Option<String>,
+    pub(super) credit_limit: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(super) struct FeeSection {
+    /// e.g. `0.5` for a flat fee or `1.5%` for a percentage.
+    pub(super) withdrawal: Option<String>,
+    pub(super) chargeback: Option<String>,
+}
+
+/// Its presence turns the retries on.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(super) struct RetrySection {
+    pub(super) max_attempts: Option<usize>,
+    pub(super) backoff_ms: Option<u64>,
+}
+
+/// Its presence turns the checkpoints of the accounts on.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(super) struct CheckpointSection {
+    pub(super) interval: Option<NonZeroU64>,
+    pub(super) max: Option<usize>,
+}
+
+impl EngineConfig {
+    /// Reads the config from a TOML or YAML file, going by its extension.
+    /// The settings left out of the file keep their defaults. See the README
+    /// for the settings.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let contents =
+            fs::read_to_string(path).map_err(|err| ConfigError::IoError(err.to_string()))?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Self::from_toml(&contents),
+            Some("yaml" | "yml") => Self::from_yaml(&contents),
+            _ => Err(ConfigError::UnsupportedFormat(path.display().to_string())),
+        }
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Self, ConfigError> {
+        toml::from_str::<ConfigFile>(contents)
+            .map_err(|err| ConfigError::ParsingError(err.to_string()))?
+            .applied_to(Self::default())
+    }
+
+    pub fn from_yaml(contents: &str) -> Result<Self, ConfigError> {
+        // an empty file is an empty config rather than a missing one
+        if contents.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        serde_yaml::from_str::<ConfigFile>(contents)
+            .map_err(|err| ConfigError::ParsingError(err.to_string()))?
+            .applied_to(Self::default())
+    }
+}
+
+impl ConfigFile {
+    /// The config with the settings of the file in place of its own, those
+    /// left out of the file being kept.
+    pub(super) fn applied_to(self, mut config: EngineConfig) -> Result<EngineConfig, ConfigError> {
+        set(
+            &mut config.expected_client_count,
+            self.expected_client_count,
+        );
+        set(
+            &mut config.expected_transactions_per_client,
+            self.expected_transactions_per_client,
+        );
+        set(&mut config.shard_amount, self.shard_amount.map(Some));
+        set(
+            &mut config.channel_capacity,
+            self.channel_capacity.map(Some),
+        );
+        set(
+            &mut config.worker_idle_timeout,
+            self.worker_idle_timeout_ms
+                .map(|idle_timeout| Some(Duration::from_millis(idle_timeout))),
+        );
+        set(
+            &mut config.stall_timeout,
+            self.stall_timeout_ms
+                .map(|stall_timeout| Some(Duration::from_millis(stall_timeout))),
+        );
+        set(&mut config.parsers, self.parsers.map(Some));
+        set(&mut config.byte_ranges, self.byte_ranges.map(Some));
+        set(&mut config.memory_budget, self.memory_budget.map(Some));
+        set(
+            &mut config.max_records_per_second,
+            self.max_records_per_second.map(Some),
+        );
+        set(&mut config.collect_stats, self.collect_stats);
+        set(&mut config.collect_deltas, self.collect_deltas);
+        set(
+            &mut config.exclude_closed_accounts,
+            self.exclude_closed_accounts,
+        );
+        set(
+            &mut config.quarantine_unknown_types,
+            self.quarantine_unknown_types,
+        );
+        set(
+            &mut config.quarantine_out_of_range_clients,
+            self.quarantine_out_of_range_clients,
+        );
+        set(&mut config.quarantine_conflicts, self.quarantine_conflicts);
+        set(
+            &mut config.large_transaction_threshold,
+            parse(
+                "large_transaction_threshold",
+                self.large_transaction_threshold,
+            )?
+            .map(Some),
+        );
+        set(&mut config.shard, parse("shard", self.shard)?.map(Some));
+        set(&mut config.deterministic, self.deterministic);
+        set(&mut config.check_ordering, self.check_ordering);
+        set(
+            &mut config.prioritise_admin_transactions,
+            self.prioritise_admin_transactions,
+        );
+
+        let csv = &mut config.csv_options;
+        if let Some(delimiter) = self.csv.delimiter {
+            csv.delimiter = u8::try_from(delimiter).map_err(|_| {
+                ConfigError::InvalidValue("csv.delimiter", format!("`{delimiter}` is not ASCII"))
+            })?;
+        }
+        set(&mut csv.has_headers, self.csv.has_headers);
+        set(&mut csv.column_map, self.csv.column_map);
+        set(&mut csv.amount_strictness, self.csv.amount_strictness);
+        let amounts = &mut csv.amount_profile;
+        set(
+            &mut amounts.scientific_notation,
+            self.csv.scientific_notation,
+        );
+        set(&mut amounts.decimal_separator, self.csv.decimal_separator);
+        set(
+            &mut amounts.thousands_separator,
+            self.csv.thousands_separator.map(Some),
+        );
+
+        let policy = &mut config.account_policy;
+        set(
+            &mut policy.dispute_window,
+            parse_window("disputes.window", self.disputes.window)?.map(|window| {
+                Some(match window {
+                    Window::Days(days) => DisputeWindow::Days(days),
+                    Window::Transactions(count) => DisputeWindow::Transactions(count),
+                })
+            }),
+        );
+        set(
+            &mut policy.auto_resolution,
+            parse_window("disputes.auto_resolution", self.disputes.auto_resolution)?.map(
+                |window| {
+                    Some(match window {
+                        Window::Days(days) => AutoResolution::Days(days),
+                        Window::Transactions(count) => AutoResolution::Transactions(count),
+                    })
+                },
+            ),
+        );
+        set(
+            &mut policy.reactivate_on_representment,
+            self.disputes.reactivate_on_representment,
+        );
+        set(
+            &mut policy.max_open_disputes,
+            self.disputes.max_open.map(Some),
+        );
+
+        let limits = &mut policy.limits;
+        for (limit, name, value) in [
+            (
+                &mut limits.max_deposit,
+                "limits.max_deposit",
+                self.limits.max_deposit,
+            ),
+            (
+                &mut limits.max_withdrawal,
+                "limits.max_withdrawal",
+                self.limits.max_withdrawal,
+            ),
+            (
+                &mut limits.max_transaction,
+                "limits.max_transaction",
+                self.limits.max_transaction,
+            ),
+            (
+                &mut limits.minimum_balance,
+                "limits.minimum_balance",
+                self.limits.minimum_balance,
+            ),
+        ] {
+            set(limit, parse(name, value)?.map(Some));
+        }
+        set(
+            &mut policy.credit_limits.default,
+            parse::<Amount>("limits.credit_limit", self.limits.credit_limit)?,
+        );
+
+        for (fee, name, value) in [
+            (
+                &mut policy.fee_policy.withdrawal,
+                "fees.withdrawal",
+                self.fees.withdrawal,
+            ),
+            (
+                &mut policy.fee_policy.chargeback,
+                "fees.chargeback",
+                self.fees.chargeback,
+            ),
+        ] {
+            set(fee, parse_fee(name, value)?.map(Some));
+        }
+        if let Some(checkpoints) = self.checkpoints {
+            let checkpointing = policy
+                .checkpointing
+                .get_or_insert_with(Checkpointing::default);
+            set(&mut checkpointing.interval, checkpoints.interval);
+            set(&mut checkpointing.max_checkpoints, checkpoints.max);
+        }
+
+        if let Some(retry) = self.retry {
+            let policy = config.retry_policy.get_or_insert_with(RetryPolicy::default);
+            set(&mut policy.max_attempts, retry.max_attempts);
+            set(
+                &mut policy.backoff,
+                retry.backoff_ms.map(Duration::from_millis),
+            );
+        }
+        Ok(config)
+    }
+}
+
+fn set<T>(setting: &mut T, value: Option<T>) {
+    if let Some(value) = value {
+        *setting = value;
+    }
+}
+
+fn parse<T: FromStr>(name: &'static str, value: Option<String>) -> Result<Option<T>, ConfigError>
+where
+    T::Err: Display,
+{
+    value.map(|value| parse_value(name, &value)).transpose()
+}
+
+pub(super) fn parse_value<T: FromStr>(name: &'static str, value: &str) -> Result<T, ConfigError>
+where
+    T::Err: Display,
+{
+    value
+        .trim()
+        .parse()
+        .map_err(|err| ConfigError::InvalidValue(name, format!("`{value}` ({err})")))
+}
+
+/// A flat fee, e.g. `0.5`, or a percentage, e.g. `1.5%`.
+fn parse_fee(name: &'static str, value: Option<String>) -> Result<Option<Fee>, ConfigError> {
+    value
+        .map(|value| match value.trim().strip_suffix('%') {
+            Some(percentage) => parse_value(name, percentage).map(Fee::Percentage),
+            None => parse_value(name, &value).map(Fee::Flat),
+        })
+        .transpose()
+}
+
+/// The shape shared by the dispute window and the auto resolution.
+enum Window {
+    Days(u64),
+    Transactions(usize),
+}
+
+/// e.g. `90 days` or `10 transactions`.
+fn parse_window(name: &'static str, value: Option<String>) -> Result<Option<Window>, ConfigError> {
+    let Some(value) = value else {
+        return Ok(None);
+    };
+    let invalid = || {
+        ConfigError::InvalidValue(
+            name,
+            format!("`{value}`, expected e.g. `90 days` or `10 transactions`"),
+        )
+    };
+    let (count, unit) = value.trim().split_once(' ').ok_or_else(invalid)?;
+    match unit.trim() {
+        "days" => count.parse().map(Window::Days).map_err(|_| invalid()),
+        "transactions" => count
+            .parse()
+            .map(Window::Transactions)
+            .map_err(|_| invalid()),
+        _ => Err(invalid()),
+    }
+    .map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, num::NonZeroU64, time::Duration};
+
+    use assert_matches::assert_matches;
+
+    use crate::{
+        account::{AccountPolicy, AutoResolution, Checkpointing, DisputeWindow, Fee},
+        engine::EngineConfig,
+        model::Amount,
+        transaction_processor::RetryPolicy,
+        transaction_stream_processor::{AmountProfile, AmountStrictness, CsvOptions, Shard},
+    };
+
+    use super::ConfigError;
+
+    const TOML: &str = r#"
+        expected_client_count = 1000
+        shard_amount = 64
+        channel_capacity = 1024
+        byte_ranges = 8
+        exclude_closed_accounts = true
+        quarantine_out_of_range_clients = true
+        large_transaction_threshold = "10000"
+        shard = "3/8"
+
+        [csv]
+        delimiter = ";"
+        column_map = { txn_type = "type" }
+        amount_strictness = "reject"
+        decimal_separator = ","
+        thousands_separator = "."
+
+        [disputes]
+        window = "90 days"
+        auto_resolution = "5 transactions"
+        max_open = 10
+
+        [limits]
+        max_deposit = "10000"
+        credit_limit = "50"
+
+        [fees]
+        withdrawal = "0.5"
+        chargeback = "1.5%"
+
+        [retry]
+        max_attempts = 5
+
+        [checkpoints]
+        interval = 100
+    "#;
+
+    const YAML: &str = r#"
+        expected_client_count: 1000
+        shard_amount: 64
+        channel_capacity: 1024
+        byte_ranges: 8
+        exclude_closed_accounts: true
+        quarantine_out_of_range_clients: true
+        large_transaction_threshold: "10000"
+        shard: 3/8
+        csv:
+          delimiter: ";"
+          column_map:
+            txn_type: type
+          amount_strictness: reject
+          decimal_separator: ","
+          thousands_separator: "."
+        disputes:
+          window: 90 days
+          auto_resolution: 5 transactions
+          max_open: 10
+        limits:
+          max_deposit: "10000"
+          credit_limit: "50"
+        fees:
+          withdrawal: "0.5"
+          chargeback: 1.5%
+        retry:
+          max_attempts: 5
+        checkpoints:
+          interval: 100
+    "#;
+
+    fn expected() -> EngineConfig {
+        let mut policy = AccountPolicy {
+            dispute_window: Some(DisputeWindow::Days(90)),
+            auto_resolution: Some(AutoResolution::Transactions(5)),
+            max_open_disputes: Some(10),
+            checkpointing: Some(Checkpointing {
+                interval: NonZeroU64::new(100).unwrap(),
+                ..Checkpointing::default()
+            }),
+            ..AccountPolicy::default()
+        };
+        policy.limits.max_deposit = Some(Amount::new(100_000_000));
+        policy.credit_limits.default = Amount::new(500_000);
+        policy.fee_policy.withdrawal = Some(Fee::Flat(Amount::new(5_000)));
+        policy.fee_policy.chargeback = Some(Fee::Percentage(Amount::new(15_000)));
+        EngineConfig {
+            expected_client_count: 1000,
+            shard_amount: Some(64),
+            channel_capacity: Some(1024),
+            byte_ranges: Some(8),
+            exclude_closed_accounts: true,
+            quarantine_out_of_range_clients: true,
+            large_transaction_threshold: Some(Amount::new(100_000_000)),
+            shard: Some(Shard::Hash { index: 3, count: 8 }),
+            csv_options: CsvOptions {
+                delimiter: b';',
+                column_map: HashMap::from([("txn_type".to_string(), "type".to_string())]),
+                amount_strictness: AmountStrictness::Reject,
+                amount_profile: AmountProfile {
+                    decimal_separator: ',',
+                    thousands_separator: Some('.'),
+                    ..AmountProfile::default()
+                },
+                ..CsvOptions::default()
+            },
+            account_policy: policy,
+            retry_policy: Some(RetryPolicy {
+                max_attempts: 5,
+                backoff: Duration::from_millis(10),
+            }),
+            ..EngineConfig::default()
+        }
+    }
+
+    #[test]
+    fn config_can_be_read_from_toml() {
+        assert_eq!(EngineConfig::from_toml(TOML), Ok(expected()));
+    }
+
+    #[test]
+    fn config_can_be_read_from_yaml() {
+        assert_eq!(EngineConfig::from_yaml(YAML), Ok(expected()));
+    }
+
+    #[test]
+    fn empty_config_is_the_default() {
+        assert_eq!(EngineConfig::from_toml(""), Ok(EngineConfig::default()));
+        assert_eq!(EngineConfig::from_yaml(""), Ok(EngineConfig::default()));
+    }
+
+    #[test]
+    fn unknown_settings_are_rejected() {
+        assert_matches!(
+            EngineConfig::from_toml("chanel_capacity = 1024"),
+            Err(ConfigError::ParsingError(_))
+        );
+    }
+
+    #[test]
+    fn invalid_values_are_rejected_with_their_setting() {
+        assert_matches!(
+            EngineConfig::from_toml("[disputes]\nwindow = \"90 weeks\""),
+            Err(ConfigError::InvalidValue("disputes.window", _))
+        );
+        assert_matches!(
+            EngineConfig::from_toml("[fees]\nwithdrawal = \"one%\""),
+            Err(ConfigError::InvalidValue("fees.withdrawal", _))
+        );
+    }
+
+    #[test]
+    fn example_config_is_valid() {
+        assert_matches!(EngineConfig::from_file("tests/config.toml"), Ok(_));
+    }
+
+    #[test]
+    fn config_files_are_told_apart_by_their_extension() {
+        assert_matches!(
+            EngineConfig::from_file("Cargo.lock"),
+            Err(ConfigError::UnsupportedFormat(_))
+        );
+    }
+}
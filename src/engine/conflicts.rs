@@ -0,0 +1,95 @@
+use csv::WriterBuilder;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::{
+    model::{ClientId, TransactionId, TransactionKind},
+    transaction_processor::Conflict,
+};
+
+#[derive(Debug, Error)]
+pub enum ConflictReportWriterError {
+    #[error("Failed to serialise the Conflict: {0}")]
+    SerialisationError(String),
+}
+
+#[derive(Serialize)]
+struct ConflictRow {
+    client: ClientId,
+    tx: TransactionId,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    amount: Option<String>,
+    reason: String,
+    /// The row of the input, if known.
+    row: Option<u64>,
+}
+
+/// Writes the conflicting transactions as CSV with the columns `client`,
+/// `tx`, `type`, `amount`, `reason` and `row`.
+pub struct ConflictReportWriter;
+
+impl ConflictReportWriter {
+    pub fn write(conflicts: Vec<Conflict>) -> Result<Vec<u8>, ConflictReportWriterError> {
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        for Conflict {
+            transaction,
+            reason,
+        } in conflicts
+        {
+            let amount = match &transaction.kind {
+                TransactionKind::Deposit { amount }
+                | TransactionKind::Withdrawal { amount }
+                | TransactionKind::Adjustment { amount, .. } => Some(amount.to_str()),
+                _ => None,
+            };
+            wtr.serialize(ConflictRow {
+                client: transaction.client_id,
+                tx: transaction.transaction_id,
+                kind: transaction.kind.name(),
+                amount,
+                reason: reason.to_string(),
+                row: transaction.ingest_sequence,
+            })
+            .map_err(|err| ConflictReportWriterError::SerialisationError(err.to_string()))?;
+        }
+        wtr.into_inner()
+            .map_err(|err| ConflictReportWriterError::SerialisationError(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        account::{account_transactor::AccountTransactorError, DepositorError},
+        model::{Amount, Transaction, TransactionKind},
+        transaction_processor::Conflict,
+    };
+
+    use super::ConflictReportWriter;
+
+    #[test]
+    fn conflicts_can_be_written_as_csv() {
+        let conflicts = vec![Conflict {
+            transaction: Transaction {
+                client_id: 1,
+                transaction_id: 2,
+                kind: TransactionKind::Deposit {
+                    amount: Amount::new(30_000),
+                },
+                timestamp: None,
+                memo: None,
+                ingest_sequence: Some(5),
+            },
+            reason: AccountTransactorError::Deposit(
+                DepositorError::ConflictingWithPreviousTransaction,
+            ),
+        }];
+        assert_eq!(
+            String::from_utf8(ConflictReportWriter::write(conflicts).unwrap()).unwrap(),
+            "\
+            client,tx,type,amount,reason,row\n\
+            1,2,deposit,3.0000,The deposit conflicts with a previous one of the same id,5\n"
+        );
+    }
+}
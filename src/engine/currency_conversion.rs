@@ -0,0 +1,278 @@
+use std::{collections::HashMap, io::Read};
+
+use csv::{ReaderBuilder, Trim, WriterBuilder};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::model::{AccountSummary, Amount, ClientId, SummaryFormat};
+
+#[derive(Debug, Error, PartialEq)]
+pub enum CurrencyConversionError {
+    #[error("Invalid rate `{rate}` for {currency}, expected a positive decimal number.")]
+    InvalidRate { currency: String, rate: String },
+    #[error("No rate to convert {0} into the reporting currency.")]
+    MissingRate(String),
+    #[error("The amount {0} is too large once converted.")]
+    Overflow(String),
+    #[error("Failed to read the rates: {0}")]
+    ReadError(String),
+    #[error("Failed to serialise the ConvertedSummary: {0}")]
+    SerialisationError(String),
+}
+
+/// A decimal number as its digits and the number of them after the point,
+/// e.g. `(125, 2)` for 1.25, so that the amounts are converted exactly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Decimal {
+    digits: i128,
+    scale: u32,
+}
+
+impl Decimal {
+    fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let (negative, unsigned) = match s.strip_prefix('-') {
+            Some(unsigned) => (true, unsigned),
+            None => (false, s),
+        };
+        let (integer, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+        let all_digits = |part: &str| part.bytes().all(|byte| byte.is_ascii_digit());
+        if integer.is_empty() || !all_digits(integer) || !all_digits(fraction) {
+            return None;
+        }
+        let digits: i128 = format!("{integer}{fraction}").parse().ok()?;
+        Some(Self {
+            digits: if negative { -digits } else { digits },
+            scale: u32::try_from(fraction.len()).ok()?,
+        })
+    }
+}
+
+/// The rates to convert the amounts of the accounts into a reporting
+/// currency, e.g. EUR, each of them being how much of the reporting currency
+/// a unit of another currency is worth.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExchangeRates {
+    reporting_currency: String,
+    rates: HashMap<String, Decimal>,
+}
+
+/// The columns of a rates file.
+#[derive(Deserialize)]
+struct RateRecord {
+    currency: String,
+    rate: String,
+}
+
+impl ExchangeRates {
+    /// No rates yet but that of the reporting currency itself.
+    pub fn new(reporting_currency: impl Into<String>) -> Self {
+        let reporting_currency = reporting_currency.into();
+        Self {
+            rates: HashMap::from([(
+                reporting_currency.clone(),
+                Decimal {
+                    digits: 1,
+                    scale: 0,
+                },
+            )]),
+            reporting_currency,
+        }
+    }
+
+    /// The rate of the currency, e.g. `0.92` for USD into EUR, in place of
+    /// any it had, e.g. as provided by an API.
+    pub fn with_rate(
+        mut self,
+        currency: impl Into<String>,
+        rate: &str,
+    ) -> Result<Self, CurrencyConversionError> {
+        let currency = currency.into();
+        match Decimal::parse(rate) {
+            Some(decimal) if decimal.digits > 0 => {
+                self.rates.insert(currency, decimal);
+                Ok(self)
+            }
+            _ => Err(CurrencyConversionError::InvalidRate {
+                currency,
+                rate: rate.to_string(),
+            }),
+        }
+    }
+
+    /// Reads the rates from a CSV file with the columns `currency` and
+    /// `rate`, e.g. `USD,0.92`.
+    pub fn read(
+        reporting_currency: impl Into<String>,
+        r: impl Read,
+    ) -> Result<Self, CurrencyConversionError> {
+        let mut rates = Self::new(reporting_currency);
+        let mut rdr = ReaderBuilder::new().trim(Trim::All).from_reader(r);
+        for record in rdr.deserialize::<RateRecord>() {
+            let record =
+                record.map_err(|err| CurrencyConversionError::ReadError(err.to_string()))?;
+            rates = rates.with_rate(record.currency, &record.rate)?;
+        }
+        Ok(rates)
+    }
+
+    pub fn reporting_currency(&self) -> &str {
+        &self.reporting_currency
+    }
+
+    /// The amount in the reporting currency, rounded half away from zero to
+    /// 4 decimal places.
+    pub fn convert(
+        &self,
+        amount: Amount,
+        currency: &str,
+    ) -> Result<Amount, CurrencyConversionError> {
+        let rate = self
+            .rates
+            .get(currency)
+            .ok_or_else(|| CurrencyConversionError::MissingRate(currency.to_string()))?;
+        let overflow = || CurrencyConversionError::Overflow(amount.to_str());
+        let amount = Decimal::parse(&amount.to_str()).ok_or_else(overflow)?;
+        let product = amount
+            .digits
+            .checked_mul(rate.digits)
+            .ok_or_else(overflow)?;
+        let scale = amount.scale + rate.scale;
+        let ten_thousandths = match scale.checked_sub(4) {
+            Some(excess) => {
+                let divisor = 10i128.checked_pow(excess).ok_or_else(overflow)?;
+                let (quotient, remainder) = (product / divisor, product % divisor);
+                match remainder.abs() * 2 >= divisor {
+                    true => quotient + product.signum(),
+                    false => quotient,
+                }
+            }
+            None => product * 10i128.pow(4 - scale),
+        };
+        i64::try_from(ten_thousandths)
+            .map(Amount::new)
+            .map_err(|_| overflow())
+    }
+}
+
+/// A summary along with its balances converted into the reporting currency.
+#[derive(Serialize)]
+struct ConvertedSummary {
+    client: ClientId,
+    available: String,
+    held: String,
+    total: String,
+    locked: bool,
+    currency: String,
+    reporting_currency: String,
+    reporting_available: String,
+    reporting_held: String,
+    reporting_total: String,
+}
+
+/// Writes the summaries of accounts held in a currency as CSV, with their
+/// balances converted into the reporting currency of the rates alongside
+/// them, both formatted as per the [`SummaryFormat`], e.g. for finance to
+/// consolidate the reports in EUR.
+pub struct ConvertedSummaryCsvWriter;
+
+impl ConvertedSummaryCsvWriter {
+    pub fn write(
+        summaries: Vec<AccountSummary>,
+        currency: &str,
+        rates: &ExchangeRates,
+        format: &SummaryFormat,
+    ) -> Result<Vec<u8>, CurrencyConversionError> {
+        let serialisation_error =
+            |err: csv::Error| CurrencyConversionError::SerialisationError(err.to_string());
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        for summary in summaries {
+            let convert = |amount| Ok(format.format(rates.convert(amount, currency)?));
+            wtr.serialize(ConvertedSummary {
+                client: summary.client_id,
+                available: format.format(summary.available),
+                held: format.format(summary.held),
+                total: format.format(summary.total),
+                locked: summary.locked,
+                currency: currency.to_string(),
+                reporting_currency: rates.reporting_currency().to_string(),
+                reporting_available: convert(summary.available)?,
+                reporting_held: convert(summary.held)?,
+                reporting_total: convert(summary.total)?,
+            })
+            .map_err(serialisation_error)?;
+        }
+        wtr.into_inner()
+            .map_err(|err| CurrencyConversionError::SerialisationError(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::model::{AccountSummary, Amount, SummaryFormat};
+
+    use super::{ConvertedSummaryCsvWriter, CurrencyConversionError, ExchangeRates};
+
+    fn rates() -> ExchangeRates {
+        ExchangeRates::read(
+            "EUR",
+            "currency,rate\nUSD,0.9234\nJPY,0.0061875\nXTS,0.00005\n".as_bytes(),
+        )
+        .unwrap()
+    }
+
+    #[rstest]
+    #[case::same_currency(15_000, "EUR", Ok(15_000))]
+    #[case::exact(10_000, "USD", Ok(9_234))]
+    #[case::rounded(12_345, "JPY", Ok(76))]
+    #[case::half_rounded_up(10_000, "XTS", Ok(1))]
+    #[case::half_rounded_away_from_zero(-10_000, "XTS", Ok(-1))]
+    #[case::missing_rate(10_000, "GBP", Err(CurrencyConversionError::MissingRate("GBP".to_string())))]
+    fn amounts_are_converted_into_the_reporting_currency(
+        #[case] ten_thousandths: i64,
+        #[case] currency: &str,
+        #[case] expected: Result<i64, CurrencyConversionError>,
+    ) {
+        assert_eq!(
+            rates().convert(Amount::new(ten_thousandths), currency),
+            expected.map(Amount::new)
+        );
+    }
+
+    #[rstest]
+    #[case("0")]
+    #[case("-1.2")]
+    #[case("1,2")]
+    #[case("")]
+    fn rates_must_be_positive_decimal_numbers(#[case] rate: &str) {
+        assert_eq!(
+            ExchangeRates::new("EUR").with_rate("USD", rate),
+            Err(CurrencyConversionError::InvalidRate {
+                currency: "USD".to_string(),
+                rate: rate.to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn summaries_are_written_with_their_converted_balances() {
+        let summaries = vec![AccountSummary {
+            client_id: 1,
+            available: Amount::new(15_000),
+            held: Amount::new(20_000),
+            total: Amount::new(35_000),
+            locked: false,
+        }];
+        let written =
+            ConvertedSummaryCsvWriter::write(summaries, "USD", &rates(), &SummaryFormat::default())
+                .unwrap();
+        assert_eq!(
+            String::from_utf8(written).unwrap(),
+            "\
+            client,available,held,total,locked,currency,reporting_currency,reporting_available,reporting_held,reporting_total\n\
+            1,1.5000,2.0000,3.5000,false,USD,EUR,1.3851,1.8468,3.2319\n"
+        );
+    }
+}
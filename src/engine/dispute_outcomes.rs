@@ -0,0 +1,264 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use async_trait::async_trait;
+use csv::WriterBuilder;
+use serde::{Serialize, Serializer};
+use thiserror::Error;
+
+use crate::{
+    account::{AccountStore, SuccessStatus},
+    model::{ClientId, Transaction, TransactionId, TransactionKind},
+    transaction_processor::{TransactionProcessor, TransactionProcessorError},
+};
+
+/// What became of the disputes of a client, or of all of them.
+#[derive(Debug, PartialEq, Clone, Default, Serialize)]
+pub struct DisputeOutcomes {
+    /// None for the disputes of all the clients, written as `all`.
+    #[serde(rename = "client", serialize_with = "serialize_client")]
+    pub client_id: Option<ClientId>,
+    pub opened: usize,
+    pub resolved: usize,
+    pub charged_back: usize,
+
+    /// The disputes resolved as they were on hold for too long, see
+    /// [`AutoResolution`].
+    ///
+    /// [`AutoResolution`]: crate::account::AutoResolution
+    pub auto_resolved: usize,
+
+    /// The disputes neither resolved nor charged back at the end.
+    pub still_open: usize,
+
+    /// How many transactions of the client came between a dispute and the
+    /// resolve or chargeback concluding it, on average, none if no dispute
+    /// was concluded so.
+    pub average_transactions_to_outcome: Option<f64>,
+}
+
+/// The outcomes of the disputes of each client that had any, ordered by
+/// client, and those of all of them.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct DisputeReport {
+    pub clients: Vec<DisputeOutcomes>,
+    pub overall: DisputeOutcomes,
+}
+
+#[derive(Debug, Error)]
+pub enum DisputeReportWriterError {
+    #[error("Failed to serialise the DisputeOutcomes: {0}")]
+    SerialisationError(String),
+}
+
+/// Writes the report as CSV, one row per client followed by that of all of
+/// them.
+pub struct DisputeReportWriter;
+
+impl DisputeReportWriter {
+    pub fn write(report: DisputeReport) -> Result<Vec<u8>, DisputeReportWriterError> {
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        for outcomes in report.clients.into_iter().chain([report.overall]) {
+            wtr.serialize(outcomes)
+                .map_err(|err| DisputeReportWriterError::SerialisationError(err.to_string()))?;
+        }
+        wtr.into_inner()
+            .map_err(|err| DisputeReportWriterError::SerialisationError(err.to_string()))
+    }
+}
+
+fn serialize_client<S: Serializer>(
+    client_id: &Option<ClientId>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match client_id {
+        Some(client_id) => serializer.serialize_u16(*client_id),
+        None => serializer.serialize_str("all"),
+    }
+}
+
+/// The disputes of a client as they went through the processor.
+#[derive(Debug, Default)]
+pub(super) struct DisputeTracking {
+    /// The number of transactions of the client seen so far.
+    seen: usize,
+    /// The disputes not concluded yet, by when they were opened.
+    disputed_at: HashMap<TransactionId, usize>,
+    opened: usize,
+    resolved: usize,
+    charged_back: usize,
+    /// The disputes concluded that were opened while tracked.
+    timed: usize,
+    /// The transactions between those disputes and their outcomes.
+    transactions_to_outcome: usize,
+}
+
+/// The report of the disputes tracked so far, along with what the accounts
+/// now say of those resolved automatically and of those still open.
+pub(super) fn dispute_report(
+    tracking: &Mutex<BTreeMap<ClientId, DisputeTracking>>,
+    accounts: &AccountStore,
+) -> DisputeReport {
+    let tracking = tracking
+        .lock()
+        .expect("The disputes are never locked across a panic.");
+    let mut clients: BTreeMap<ClientId, DisputeOutcomes> = tracking
+        .iter()
+        .map(|(client_id, tracking)| {
+            let outcomes = DisputeOutcomes {
+                client_id: Some(*client_id),
+                opened: tracking.opened,
+                resolved: tracking.resolved,
+                charged_back: tracking.charged_back,
+                average_transactions_to_outcome: average(
+                    tracking.transactions_to_outcome,
+                    tracking.timed,
+                ),
+                ..Default::default()
+            };
+            (*client_id, outcomes)
+        })
+        .collect();
+    for (client_id, auto_resolved, still_open) in accounts.map(|account| {
+        (
+            account.client_id,
+            account.auto_resolved().len(),
+            account.open_disputes(),
+        )
+    }) {
+        if let Some(outcomes) = clients.get_mut(&client_id) {
+            outcomes.auto_resolved = auto_resolved;
+            outcomes.still_open = still_open;
+        }
+    }
+    let clients: Vec<DisputeOutcomes> = clients
+        .into_values()
+        .filter(|outcomes| outcomes.opened + outcomes.auto_resolved + outcomes.still_open > 0)
+        .collect();
+    let mut overall = DisputeOutcomes::default();
+    for outcomes in &clients {
+        overall.opened += outcomes.opened;
+        overall.resolved += outcomes.resolved;
+        overall.charged_back += outcomes.charged_back;
+        overall.auto_resolved += outcomes.auto_resolved;
+        overall.still_open += outcomes.still_open;
+    }
+    overall.average_transactions_to_outcome = average(
+        tracking
+            .values()
+            .map(|tracking| tracking.transactions_to_outcome)
+            .sum(),
+        tracking.values().map(|tracking| tracking.timed).sum(),
+    );
+    DisputeReport { clients, overall }
+}
+
+fn average(total: usize, count: usize) -> Option<f64> {
+    (count > 0).then(|| total as f64 / count as f64)
+}
+
+/// Tracks the disputes of each client through the processor it wraps, from
+/// the dispute taken to the resolve or chargeback concluding it.
+pub(super) struct DisputeOutcomeRecorder {
+    inner: Arc<dyn TransactionProcessor + Send + Sync>,
+    tracking: Arc<Mutex<BTreeMap<ClientId, DisputeTracking>>>,
+}
+
+#[async_trait]
+impl TransactionProcessor for DisputeOutcomeRecorder {
+    async fn process(
+        &self,
+        transaction: Transaction,
+    ) -> Result<SuccessStatus, TransactionProcessorError> {
+        let client_id = transaction.client_id;
+        let transaction_id = transaction.transaction_id;
+        // only the unit kinds of the disputes are kept
+        let kind = match transaction.kind {
+            TransactionKind::Dispute | TransactionKind::Resolve | TransactionKind::ChargeBack => {
+                Some(transaction.kind.clone())
+            }
+            _ => None,
+        };
+        let result = self.inner.process(transaction).await;
+        let mut tracking = self.lock();
+        let tracking = tracking.entry(client_id).or_default();
+        let seen = tracking.seen;
+        tracking.seen += 1;
+        if result != Ok(SuccessStatus::Transacted) {
+            return result;
+        }
+        match kind {
+            Some(TransactionKind::Dispute) => {
+                tracking.opened += 1;
+                tracking.disputed_at.insert(transaction_id, seen);
+            }
+            Some(concluding) => {
+                match concluding {
+                    TransactionKind::Resolve => tracking.resolved += 1,
+                    _ => tracking.charged_back += 1,
+                }
+                // none if disputed before the engine was, e.g. in an earlier
+                // run on a persistent store
+                if let Some(disputed_at) = tracking.disputed_at.remove(&transaction_id) {
+                    tracking.timed += 1;
+                    tracking.transactions_to_outcome += seen - disputed_at - 1;
+                }
+            }
+            None => {}
+        }
+        result
+    }
+}
+
+impl DisputeOutcomeRecorder {
+    pub(super) fn new(
+        inner: Arc<dyn TransactionProcessor + Send + Sync>,
+        tracking: Arc<Mutex<BTreeMap<ClientId, DisputeTracking>>>,
+    ) -> Self {
+        Self { inner, tracking }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<ClientId, DisputeTracking>> {
+        self.tracking
+            .lock()
+            .expect("The disputes are never locked across a panic.")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DisputeOutcomes, DisputeReport, DisputeReportWriter};
+
+    #[test]
+    fn the_report_ends_with_the_outcomes_of_all_the_clients() {
+        let report = DisputeReport {
+            clients: vec![DisputeOutcomes {
+                client_id: Some(1),
+                opened: 2,
+                resolved: 1,
+                charged_back: 1,
+                auto_resolved: 0,
+                still_open: 0,
+                average_transactions_to_outcome: Some(1.5),
+            }],
+            overall: DisputeOutcomes {
+                client_id: None,
+                opened: 2,
+                resolved: 1,
+                charged_back: 1,
+                auto_resolved: 0,
+                still_open: 0,
+                average_transactions_to_outcome: Some(1.5),
+            },
+        };
+        assert_eq!(
+            String::from_utf8(DisputeReportWriter::write(report).unwrap()).unwrap(),
+            "\
+            client,opened,resolved,charged_back,auto_resolved,still_open,average_transactions_to_outcome\n\
+            1,2,1,1,0,0,1.5\n\
+            all,2,1,1,0,0,1.5\n"
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use serde::Serialize;
+
+use crate::{
+    model::{ClientId, TransactionId},
+    transaction_processor::TransactionProcessorError,
+    transaction_stream_processor::TransactionStreamProcessError,
+};
+
+/// An error of a run, structured for it to be triaged automatically, e.g.
+/// `{"code":"Withdrawal.InsufficientFund","line":4,"client":1,"tx":3,...}`.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct ErrorRecord {
+    /// The kind of the error, e.g. `Dispute.NoTransactionFound` or
+    /// `ParsingError`, the same for every error of that kind.
+    pub code: String,
+    /// The line of the input the error is about, if known.
+    pub line: Option<u64>,
+    pub client: Option<ClientId>,
+    pub tx: Option<TransactionId>,
+    /// What went wrong, without the transaction for an error about one.
+    pub message: String,
+}
+
+impl From<&TransactionProcessorError> for ErrorRecord {
+    fn from(err: &TransactionProcessorError) -> Self {
+        let (transaction, code, message) = match err {
+            TransactionProcessorError::AccountTransactionError(transaction, reason) => (
+                transaction,
+                code(&format!("{reason:?}")),
+                reason.to_string(),
+            ),
+            TransactionProcessorError::Unavailable(transaction, reason) => {
+                (transaction, "Unavailable".to_string(), reason.clone())
+            }
+        };
+        Self {
+            code,
+            line: transaction.ingest_sequence,
+            client: Some(transaction.client_id),
+            tx: Some(transaction.transaction_id),
+            message,
+        }
+    }
+}
+
+/// The error a run failed with, that of the transaction for one that failed
+/// the run when processed.
+impl From<&TransactionStreamProcessError> for ErrorRecord {
+    fn from(err: &TransactionStreamProcessError) -> Self {
+        if let TransactionStreamProcessError::ProcessError(err) = err {
+            return Self::from(err);
+        }
+        Self {
+            code: code(&format!("{err:?}")),
+            line: None,
+            client: None,
+            tx: None,
+            message: err.to_string(),
+        }
+    }
+}
+
+/// The names of the variants the `Debug` of an error starts with, without
+/// what they carry, e.g. `Resolve.NonDisputedTransaction` for
+/// `Resolve(NonDisputedTransaction(Incompatibility { .. }))`.
+fn code(debug: &str) -> String {
+    debug
+        .split('(')
+        .take(2)
+        .map(|name| name.split([' ', '{', ')', '"']).next().unwrap_or_default())
+        .take_while(|name| !name.is_empty())
+        .collect::<Vec<&str>>()
+        .join(".")
+}
+
+/// Writes the errors as a JSON array.
+pub struct ErrorReportWriter;
+
+impl ErrorReportWriter {
+    pub fn write_json(errors: &[ErrorRecord]) -> Vec<u8> {
+        serde_json::to_vec_pretty(errors).expect("The errors are always serialisable.")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::{
+        account::{account_transactor::AccountTransactorError, ResolverError, WithdrawerError},
+        model::{Amount, Transaction, TransactionKind},
+        transaction_processor::TransactionProcessorError,
+        transaction_stream_processor::TransactionStreamProcessError,
+    };
+
+    use super::{ErrorRecord, ErrorReportWriter};
+
+    fn withdrawal() -> Box<Transaction> {
+        Box::new(Transaction {
+            client_id: 2,
+            transaction_id: 7,
+            kind: TransactionKind::Withdrawal {
+                amount: Amount::new(10_000),
+            },
+            timestamp: None,
+            memo: None,
+            ingest_sequence: Some(4),
+        })
+    }
+
+    #[rstest]
+    #[case(
+        AccountTransactorError::Withdrawal(WithdrawerError::InsufficientFund),
+        "Withdrawal.InsufficientFund"
+    )]
+    #[case(
+        AccountTransactorError::Resolve(ResolverError::NoTransactionFound),
+        "Resolve.NoTransactionFound"
+    )]
+    #[case(AccountTransactorError::AccountClosed, "AccountClosed")]
+    fn rejections_are_coded_by_reason(#[case] reason: AccountTransactorError, #[case] code: &str) {
+        let message = reason.to_string();
+        let err = TransactionProcessorError::AccountTransactionError(withdrawal(), reason);
+        assert_eq!(
+            ErrorRecord::from(&err),
+            ErrorRecord {
+                code: code.to_string(),
+                line: Some(4),
+                client: Some(2),
+                tx: Some(7),
+                message,
+            }
+        );
+    }
+
+    #[test]
+    fn errors_failing_the_run_are_coded_by_kind() {
+        let err = TransactionStreamProcessError::ParsingError("bad amount".to_string());
+        assert_eq!(
+            ErrorRecord::from(&err),
+            ErrorRecord {
+                code: "ParsingError".to_string(),
+                line: None,
+                client: None,
+                tx: None,
+                message: err.to_string(),
+            }
+        );
+        assert_eq!(
+            ErrorRecord::from(&TransactionStreamProcessError::Cancelled).code,
+            "Cancelled"
+        );
+    }
+
+    #[test]
+    fn errors_are_written_as_a_json_array() {
+        let err = TransactionProcessorError::Unavailable(withdrawal(), "down".to_string());
+        let written: serde_json::Value =
+            serde_json::from_slice(&ErrorReportWriter::write_json(&[ErrorRecord::from(&err)]))
+                .unwrap();
+        assert_eq!(written[0]["code"], "Unavailable");
+        assert_eq!(written[0]["line"], 4);
+        assert_eq!(written[0]["client"], 2);
+        assert_eq!(written[0]["tx"], 7);
+        assert_eq!(written[0]["message"], "down");
+    }
+}
@@ -0,0 +1,481 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use async_graphql::{
+    http::GraphiQLSource, EmptyMutation, EmptySubscription, Enum, Object, Schema, SimpleObject,
+};
+use axum::{
+    extract::State,
+    response::{Html, IntoResponse},
+    routing::get,
+    Json, Router,
+};
+use tokio::{net::TcpListener, time::sleep};
+
+use crate::{
+    account::{self, Account, AccountStore},
+    model::{ClientId, TransactionId},
+    transaction_stream_processor::{Cancellation, TransactionStreamProcessError},
+};
+
+use super::{listener::CANCELLATION_CHECK, stats::client_stats, ClientStats, Engine};
+
+/// How many items a page holds unless asked otherwise.
+const DEFAULT_LIMIT: usize = 100;
+
+/// The GraphQL schema over the accounts of an [`Engine`], see
+/// [`Engine::graphql_schema`].
+pub type AccountSchema = Schema<AccountQuery, EmptyMutation, EmptySubscription>;
+
+impl Engine {
+    /// A GraphQL schema over the accounts, their deposits and withdrawals,
+    /// and the stats of the runs, as they currently are, e.g. to be served
+    /// by a server of the library user's own.
+    /// Only with the `graphql` feature.
+    pub fn graphql_schema(&self) -> AccountSchema {
+        Schema::new(
+            AccountQuery {
+                accounts: self.accounts.clone(),
+                stats: self.stats.clone(),
+                retries: self.retries.clone(),
+            },
+            EmptyMutation,
+            EmptySubscription,
+        )
+    }
+
+    /// Serves [`Engine::graphql_schema`] over HTTP until the engine is
+    /// cancelled, the queries being posted to `/`, where GraphiQL is served
+    /// to a browser.
+    /// Only with the `graphql` feature.
+    pub async fn serve_graphql(
+        &self,
+        listener: TcpListener,
+    ) -> Result<(), TransactionStreamProcessError> {
+        let router = Router::new()
+            .route("/", get(graphiql).post(query))
+            .with_state(self.graphql_schema());
+        axum::serve(listener, router)
+            .with_graceful_shutdown(cancelled(self.cancellation.clone()))
+            .await
+            .map_err(|err| TransactionStreamProcessError::InternalError(err.to_string()))
+    }
+}
+
+async fn query(
+    State(schema): State<AccountSchema>,
+    Json(request): Json<async_graphql::Request>,
+) -> Json<async_graphql::Response> {
+    Json(schema.execute(request).await)
+}
+
+async fn graphiql() -> impl IntoResponse {
+    Html(GraphiQLSource::build().endpoint("/").finish())
+}
+
+async fn cancelled(cancellation: Cancellation) {
+    while !cancellation.is_cancelled() {
+        sleep(CANCELLATION_CHECK).await;
+    }
+}
+
+/// The root of [`AccountSchema`].
+pub struct AccountQuery {
+    accounts: Arc<AccountStore>,
+    stats: Arc<Mutex<BTreeMap<ClientId, ClientStats>>>,
+    retries: Arc<AtomicUsize>,
+}
+
+#[Object(name = "Query")]
+impl AccountQuery {
+    /// The account of a client, if the client has been seen.
+    async fn account(&self, client: ClientId) -> Option<AccountObject> {
+        self.accounts.get(client).map(AccountObject)
+    }
+
+    /// The accounts matching all the filters given, ordered by client.
+    async fn accounts(
+        &self,
+        status: Option<AccountStatus>,
+        has_open_disputes: Option<bool>,
+        #[graphql(default)] offset: usize,
+        #[graphql(default_with = "DEFAULT_LIMIT")] limit: usize,
+    ) -> AccountPage {
+        let mut client_ids: Vec<ClientId> = self
+            .accounts
+            .map(|account| {
+                let matching = status.is_none_or(|status| account.status == status.into())
+                    && has_open_disputes.is_none_or(|has_open_disputes| {
+                        (account.open_disputes() > 0) == has_open_disputes
+                    });
+                (account.client_id, matching)
+            })
+            .into_iter()
+            .filter_map(|(client_id, matching)| matching.then_some(client_id))
+            .collect();
+        client_ids.sort_unstable();
+        AccountPage {
+            total_count: client_ids.len(),
+            items: client_ids
+                .into_iter()
+                .skip(offset)
+                .take(limit)
+                .filter_map(|client_id| self.accounts.get(client_id).map(AccountObject))
+                .collect(),
+        }
+    }
+
+    /// How the runs have gone so far.
+    async fn stats(&self) -> RunStats {
+        let store = self.accounts.stats();
+        RunStats {
+            accounts: store.accounts,
+            transactions: store.transactions,
+            approximate_bytes: store.approximate_bytes,
+            retries: self.retries.load(Ordering::Relaxed),
+            clients: client_stats(&self.stats, &self.accounts)
+                .into_iter()
+                .map(ClientStatsObject)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Enum, Clone, Copy, PartialEq, Eq)]
+enum AccountStatus {
+    Active,
+    Locked,
+    Closed,
+}
+
+impl From<AccountStatus> for account::AccountStatus {
+    fn from(status: AccountStatus) -> Self {
+        match status {
+            AccountStatus::Active => Self::Active,
+            AccountStatus::Locked => Self::Locked,
+            AccountStatus::Closed => Self::Closed,
+        }
+    }
+}
+
+impl From<account::AccountStatus> for AccountStatus {
+    fn from(status: account::AccountStatus) -> Self {
+        match status {
+            account::AccountStatus::Active => Self::Active,
+            account::AccountStatus::Locked => Self::Locked,
+            account::AccountStatus::Closed => Self::Closed,
+        }
+    }
+}
+
+/// Where a deposit stands as to disputes.
+#[derive(Enum, Clone, Copy, PartialEq, Eq)]
+enum DepositStatus {
+    Accepted,
+    Held,
+    Resolved,
+    ChargedBack,
+}
+
+impl From<account::DepositStatus> for DepositStatus {
+    fn from(status: account::DepositStatus) -> Self {
+        match status {
+            account::DepositStatus::Accepted => Self::Accepted,
+            account::DepositStatus::Held => Self::Held,
+            account::DepositStatus::Resolved => Self::Resolved,
+            account::DepositStatus::ChargedBack => Self::ChargedBack,
+        }
+    }
+}
+
+/// The amounts are written with 4 decimal places, as in the summaries.
+struct AccountObject(Account);
+
+#[Object(name = "Account")]
+impl AccountObject {
+    async fn client(&self) -> ClientId {
+        self.0.client_id
+    }
+
+    async fn available(&self) -> String {
+        self.0.available().to_str()
+    }
+
+    async fn held(&self) -> String {
+        self.0.held().to_str()
+    }
+
+    async fn total(&self) -> String {
+        self.0.total().to_str()
+    }
+
+    async fn status(&self) -> AccountStatus {
+        self.0.status.into()
+    }
+
+    async fn open_disputes(&self) -> usize {
+        self.0.open_disputes()
+    }
+
+    /// The deposits of the given status, all of them if not given, ordered
+    /// by transaction.
+    async fn deposits(
+        &self,
+        status: Option<DepositStatus>,
+        #[graphql(default)] offset: usize,
+        #[graphql(default_with = "DEFAULT_LIMIT")] limit: usize,
+    ) -> DepositPage {
+        let mut deposits: Vec<Deposit> = self
+            .0
+            .deposits()
+            .map(|(transaction_id, deposit)| Deposit {
+                tx: transaction_id,
+                amount: deposit.amount.to_str(),
+                status: deposit.status.into(),
+                timestamp: deposit.timestamp,
+            })
+            .filter(|deposit| status.is_none_or(|status| deposit.status == status))
+            .collect();
+        deposits.sort_unstable_by_key(|deposit| deposit.tx);
+        DepositPage {
+            total_count: deposits.len(),
+            items: deposits.into_iter().skip(offset).take(limit).collect(),
+        }
+    }
+
+    /// The withdrawals, ordered by transaction.
+    async fn withdrawals(
+        &self,
+        #[graphql(default)] offset: usize,
+        #[graphql(default_with = "DEFAULT_LIMIT")] limit: usize,
+    ) -> WithdrawalPage {
+        let mut withdrawals: Vec<Withdrawal> = self
+            .0
+            .withdrawals()
+            .map(|(transaction_id, withdrawal)| Withdrawal {
+                tx: transaction_id,
+                amount: withdrawal.amount.to_str(),
+            })
+            .collect();
+        withdrawals.sort_unstable_by_key(|withdrawal| withdrawal.tx);
+        WithdrawalPage {
+            total_count: withdrawals.len(),
+            items: withdrawals.into_iter().skip(offset).take(limit).collect(),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+struct Deposit {
+    tx: TransactionId,
+    amount: String,
+    status: DepositStatus,
+    /// When the deposit took place, if the input said so.
+    timestamp: Option<u64>,
+}
+
+#[derive(SimpleObject)]
+struct Withdrawal {
+    tx: TransactionId,
+    amount: String,
+}
+
+/// A page of the accounts, along with how many there are in all.
+#[derive(SimpleObject)]
+struct AccountPage {
+    total_count: usize,
+    items: Vec<AccountObject>,
+}
+
+#[derive(SimpleObject)]
+struct DepositPage {
+    total_count: usize,
+    items: Vec<Deposit>,
+}
+
+#[derive(SimpleObject)]
+struct WithdrawalPage {
+    total_count: usize,
+    items: Vec<Withdrawal>,
+}
+
+#[derive(SimpleObject)]
+struct RunStats {
+    accounts: usize,
+    /// The deposits and withdrawals kept across all the accounts.
+    transactions: usize,
+    approximate_bytes: usize,
+    /// The number of times a transaction was tried again.
+    retries: usize,
+    /// The stats of each client, ordered by client, if the engine collects
+    /// them.
+    clients: Vec<ClientStatsObject>,
+}
+
+struct ClientStatsObject(ClientStats);
+
+#[Object(name = "ClientStats")]
+impl ClientStatsObject {
+    async fn client(&self) -> ClientId {
+        self.0.client_id
+    }
+
+    async fn deposits(&self) -> usize {
+        self.0.deposits
+    }
+
+    async fn withdrawals(&self) -> usize {
+        self.0.withdrawals
+    }
+
+    async fn disputes(&self) -> usize {
+        self.0.disputes
+    }
+
+    async fn resolves(&self) -> usize {
+        self.0.resolves
+    }
+
+    async fn chargebacks(&self) -> usize {
+        self.0.chargebacks
+    }
+
+    async fn duplicates(&self) -> usize {
+        self.0.duplicates
+    }
+
+    /// The number of rejected transactions by reason.
+    async fn rejections(&self) -> Vec<Rejections> {
+        self.0
+            .rejections
+            .iter()
+            .map(|(reason, count)| Rejections {
+                reason: reason.clone(),
+                count: *count,
+            })
+            .collect()
+    }
+
+    async fn open_disputes(&self) -> usize {
+        self.0.open_disputes
+    }
+
+    async fn locked(&self) -> bool {
+        self.0.locked
+    }
+}
+
+#[derive(SimpleObject)]
+struct Rejections {
+    reason: String,
+    count: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::engine::{Engine, EngineConfig};
+
+    async fn engine() -> Engine {
+        let engine = Engine::new(EngineConfig {
+            collect_stats: true,
+            ..Default::default()
+        });
+        let input = "
+          type, client, tx, amount
+       deposit,      1,  1,    1.0
+       deposit,      1,  2,    2.0
+       dispute,      1,  2,
+       deposit,      2,  3,    3.0
+    withdrawal,      2,  4,    5.0
+       deposit,      3,  5,    4.0
+    withdrawal,      3,  6,    1.0";
+        engine.process(input.as_bytes()).await.unwrap();
+        engine
+    }
+
+    #[tokio::test]
+    async fn accounts_are_filtered_and_paginated() {
+        let response = engine()
+            .await
+            .graphql_schema()
+            .execute(
+                "{
+                    all: accounts(offset: 1, limit: 1) { totalCount items { client } }
+                    disputed: accounts(hasOpenDisputes: true) {
+                        items {
+                            client
+                            available
+                            held
+                            deposits(status: HELD) { totalCount items { tx amount status } }
+                        }
+                    }
+                }",
+            )
+            .await;
+        assert_eq!(response.errors, vec![]);
+        assert_eq!(
+            response.data.into_json().unwrap(),
+            json!({
+                "all": {"totalCount": 3, "items": [{"client": 2}]},
+                "disputed": {"items": [{
+                    "client": 1,
+                    "available": "1.0000",
+                    "held": "2.0000",
+                    "deposits": {
+                        "totalCount": 1,
+                        "items": [{"tx": 2, "amount": "2.0000", "status": "HELD"}]
+                    }
+                }]}
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn withdrawals_and_stats_are_queried() {
+        let response = engine()
+            .await
+            .graphql_schema()
+            .execute(
+                "{
+                    account(client: 3) { withdrawals { items { tx amount } } }
+                    missing: account(client: 4) { client }
+                    stats {
+                        accounts
+                        transactions
+                        clients { client rejections { reason count } }
+                    }
+                }",
+            )
+            .await;
+        assert_eq!(response.errors, vec![]);
+        assert_eq!(
+            response.data.into_json().unwrap(),
+            json!({
+                "account": {"withdrawals": {"items": [{"tx": 6, "amount": "1.0000"}]}},
+                "missing": null,
+                "stats": {
+                    "accounts": 3,
+                    "transactions": 5,
+                    "clients": [
+                        {"client": 1, "rejections": []},
+                        {
+                            "client": 2,
+                            "rejections": [{
+                                "reason": "Withdrawal(InsufficientFund)",
+                                "count": 1
+                            }]
+                        },
+                        {"client": 3, "rejections": []}
+                    ]
+                }
+            })
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use std::time::Duration;
+
+use futures_util::future::try_join_all;
+use rdkafka::{
+    error::{KafkaError, KafkaResult},
+    producer::{FutureProducer, FutureRecord, Producer},
+    util::Timeout,
+    ClientConfig,
+};
+use thiserror::Error;
+use tokio::{
+    sync::mpsc,
+    task,
+    time::{timeout_at, Instant},
+};
+
+use super::AccountEvent;
+
+/// How many times the events of a transaction are sent before giving up, the
+/// transaction being aborted in between.
+const ATTEMPTS: usize = 3;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum KafkaSinkError {
+    #[error("Failed to set up the Kafka producer: {0}")]
+    SetupError(String),
+
+    #[error("Failed to publish the account events: {0}")]
+    PublishError(String),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct KafkaSinkConfig {
+    /// e.g. `localhost:9092`.
+    pub brokers: String,
+    pub topic: String,
+
+    /// Stays the same across restarts, so that the producer of a restarted
+    /// engine fences off that of the previous one, whose open transaction is
+    /// aborted.
+    pub transactional_id: String,
+
+    /// The most events published in a transaction.
+    pub batch_size: usize,
+
+    /// How long an event waits for others to be published along with before
+    /// its transaction is committed.
+    pub linger: Duration,
+
+    /// How long an event may take to be delivered, and a transaction to be
+    /// committed or aborted.
+    pub timeout: Duration,
+}
+
+impl KafkaSinkConfig {
+    /// Publishing up to 1000 events per transaction, which waits for them for
+    /// up to 100ms.
+    pub fn new(brokers: &str, topic: &str, transactional_id: &str) -> Self {
+        Self {
+            brokers: brokers.to_string(),
+            topic: topic.to_string(),
+            transactional_id: transactional_id.to_string(),
+            batch_size: 1000,
+            linger: Duration::from_millis(100),
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Publishes the [`AccountEvent`]s of an engine to a Kafka topic, keyed by
+/// client, e.g. for downstream systems to keep mirrors of the accounts.
+/// Only with the `kafka` feature.
+/// The events are published in transactions, so that a consumer reading
+/// committed messages only, with `isolation.level=read_committed`, sees each
+/// of them exactly once: the events of a transaction failing to be committed
+/// are sent again in another once it is aborted, and those left in an open
+/// transaction by a crash are aborted by the next producer of the same
+/// transactional id. The events of a client land on the same partition, in
+/// the order its transactions were applied in.
+pub struct KafkaSink {
+    producer: FutureProducer,
+    config: KafkaSinkConfig,
+}
+
+impl KafkaSink {
+    /// Connects to the brokers and fences off any previous producer of the
+    /// same transactional id.
+    pub async fn new(config: KafkaSinkConfig) -> Result<Self, KafkaSinkError> {
+        let producer: FutureProducer = ClientConfig::new()
+            .set("bootstrap.servers", &config.brokers)
+            .set("transactional.id", &config.transactional_id)
+            .set("enable.idempotence", "true")
+            .set("message.timeout.ms", config.timeout.as_millis().to_string())
+            .create()
+            .map_err(|err| KafkaSinkError::SetupError(err.to_string()))?;
+        let sink = Self { producer, config };
+        sink.blocking(|producer, timeout| producer.init_transactions(timeout))
+            .await
+            .map_err(|err| KafkaSinkError::SetupError(err.to_string()))?;
+        Ok(sink)
+    }
+
+    /// Publishes the events until the senders are all dropped, e.g. along
+    /// with the engine, and returns how many were.
+    pub async fn run(
+        self,
+        mut events: mpsc::Receiver<AccountEvent>,
+    ) -> Result<usize, KafkaSinkError> {
+        let mut published = 0;
+        while let Some(event) = events.recv().await {
+            let deadline = Instant::now() + self.config.linger;
+            let mut batch = vec![event];
+            while batch.len() < self.config.batch_size {
+                match timeout_at(deadline, events.recv()).await {
+                    Ok(Some(event)) => batch.push(event),
+                    // the senders are gone, or the events waited long enough
+                    Ok(None) | Err(_) => break,
+                }
+            }
+            self.publish(&batch).await?;
+            published += batch.len();
+        }
+        Ok(published)
+    }
+
+    async fn publish(&self, batch: &[AccountEvent]) -> Result<(), KafkaSinkError> {
+        let mut attempt = 1;
+        loop {
+            let err = match self.transact(batch).await {
+                Ok(()) => return Ok(()),
+                Err(err) => err,
+            };
+            if attempt == ATTEMPTS || !abortable(&err) {
+                return Err(KafkaSinkError::PublishError(err.to_string()));
+            }
+            // the events already sent are never seen by the consumers
+            // reading committed messages only
+            self.blocking(|producer, timeout| producer.abort_transaction(timeout))
+                .await
+                .map_err(|err| KafkaSinkError::PublishError(err.to_string()))?;
+            attempt += 1;
+        }
+    }
+
+    async fn transact(&self, batch: &[AccountEvent]) -> KafkaResult<()> {
+        self.producer.begin_transaction()?;
+        let payloads = batch
+            .iter()
+            .map(|event| {
+                (
+                    event.summary.client_id.to_string(),
+                    serde_json::to_vec(event).expect("An event is always serialisable."),
+                )
+            })
+            .collect::<Vec<_>>();
+        try_join_all(payloads.iter().map(|(key, payload)| {
+            self.producer.send(
+                FutureRecord::to(&self.config.topic)
+                    .key(key)
+                    .payload(payload),
+                Timeout::After(self.config.timeout),
+            )
+        }))
+        .await
+        .map_err(|(err, _)| err)?;
+        self.blocking(|producer, timeout| producer.commit_transaction(timeout))
+            .await
+    }
+
+    /// Runs a call to the producer that blocks for up to the timeout away
+    /// from the runtime's workers.
+    async fn blocking(
+        &self,
+        call: impl FnOnce(&FutureProducer, Duration) -> KafkaResult<()> + Send + 'static,
+    ) -> KafkaResult<()> {
+        let producer = self.producer.clone();
+        let timeout = self.config.timeout;
+        task::spawn_blocking(move || call(&producer, timeout))
+            .await
+            .expect("The calls to the producer do not panic.")
+    }
+}
+
+/// Whether the transaction can be aborted and tried again, which it can
+/// unless the producer can no longer be used, e.g. as it was fenced off.
+fn abortable(err: &KafkaError) -> bool {
+    match err {
+        KafkaError::Transaction(err) => err.txn_requires_abort() || err.is_retriable(),
+        // a delivery failure, e.g. on a timeout
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process, time::Duration};
+
+    use rdkafka::{
+        consumer::{Consumer, StreamConsumer},
+        ClientConfig, Message,
+    };
+    use tokio::{sync::mpsc, time::timeout};
+
+    use crate::engine::{Engine, EngineConfig};
+
+    use super::{KafkaSink, KafkaSinkConfig};
+
+    #[tokio::test]
+    #[ignore = "needs a Kafka broker, at $KAFKA_BROKERS or on localhost:9092"]
+    async fn account_events_are_published_keyed_by_client() {
+        let brokers = env::var("KAFKA_BROKERS").unwrap_or("localhost:9092".to_string());
+        let topic = format!("jouet_paiement_account_events_{}", process::id());
+        let sink = KafkaSink::new(KafkaSinkConfig::new(&brokers, &topic, &topic))
+            .await
+            .unwrap();
+        let (events, received) = mpsc::channel(16);
+        let publishing = tokio::spawn(sink.run(received));
+        let engine = Engine::new(EngineConfig::default()).with_account_events(events);
+        let input = "
+          type, client, tx, amount
+       deposit,      1,  1,    2.0
+       deposit,      2,  2,    1.0
+    withdrawal,      1,  3,    5.0";
+        assert!(engine.process(input.as_bytes()).await.is_err());
+        drop(engine);
+        assert_eq!(publishing.await.unwrap(), Ok(2));
+
+        let consumer: StreamConsumer = ClientConfig::new()
+            .set("bootstrap.servers", &brokers)
+            .set("group.id", &topic)
+            .set("isolation.level", "read_committed")
+            .set("auto.offset.reset", "earliest")
+            .create()
+            .unwrap();
+        consumer.subscribe(&[&topic]).unwrap();
+        let mut received = Vec::new();
+        while received.len() < 2 {
+            let message = timeout(Duration::from_secs(30), consumer.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            received.push((
+                String::from_utf8(message.key().unwrap().to_vec()).unwrap(),
+                String::from_utf8(message.payload().unwrap().to_vec()).unwrap(),
+            ));
+        }
+        received.sort();
+        assert_eq!(
+            received,
+            vec![
+                (
+                    "1".to_string(),
+                    r#"{"tx":1,"type":"deposit","client":1,"available":"2.0000","held":"0.0000","total":"2.0000","locked":false}"#.to_string()
+                ),
+                (
+                    "2".to_string(),
+                    r#"{"tx":2,"type":"deposit","client":2,"available":"1.0000","held":"0.0000","total":"1.0000","locked":false}"#.to_string()
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use csv::WriterBuilder;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::{
+    account::SuccessStatus,
+    model::{Amount, ClientId, Transaction, TransactionId, TransactionKind},
+    transaction_processor::{TransactionProcessor, TransactionProcessorError},
+};
+
+/// A deposit or a withdrawal above the reporting threshold.
+#[derive(Debug, PartialEq, Clone)]
+pub struct LargeTransaction {
+    pub transaction: Transaction,
+    /// Whether it made it to the account, rather than being rejected, blocked
+    /// or let through as a duplicate.
+    pub applied: bool,
+}
+
+/// Records the deposits and withdrawals above the threshold on their way back
+/// from the processor it wraps, whatever became of them.
+pub(super) struct LargeTransactionRecorder {
+    inner: Arc<dyn TransactionProcessor + Send + Sync>,
+    threshold: Amount,
+    recorded: Arc<Mutex<Vec<LargeTransaction>>>,
+}
+
+#[async_trait]
+impl TransactionProcessor for LargeTransactionRecorder {
+    async fn process(
+        &self,
+        transaction: Transaction,
+    ) -> Result<SuccessStatus, TransactionProcessorError> {
+        let large = match &transaction.kind {
+            TransactionKind::Deposit { amount } | TransactionKind::Withdrawal { amount } => {
+                *amount > self.threshold
+            }
+            _ => false,
+        };
+        if !large {
+            return self.inner.process(transaction).await;
+        }
+        let result = self.inner.process(transaction.clone()).await;
+        self.recorded
+            .lock()
+            .expect("The large transactions are never locked across a panic.")
+            .push(LargeTransaction {
+                transaction,
+                applied: result == Ok(SuccessStatus::Transacted),
+            });
+        result
+    }
+}
+
+impl LargeTransactionRecorder {
+    pub(super) fn new(
+        inner: Arc<dyn TransactionProcessor + Send + Sync>,
+        threshold: Amount,
+        recorded: Arc<Mutex<Vec<LargeTransaction>>>,
+    ) -> Self {
+        Self {
+            inner,
+            threshold,
+            recorded,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum LargeTransactionReportWriterError {
+    #[error("Failed to serialise the LargeTransaction: {0}")]
+    SerialisationError(String),
+}
+
+#[derive(Serialize)]
+struct LargeTransactionRow {
+    client: ClientId,
+    tx: TransactionId,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    amount: String,
+    applied: bool,
+    /// The row of the input, if known.
+    row: Option<u64>,
+}
+
+/// Writes the large transactions as CSV with the columns `client`, `tx`,
+/// `type`, `amount`, `applied` and `row`.
+pub struct LargeTransactionReportWriter;
+
+impl LargeTransactionReportWriter {
+    pub fn write(
+        large_transactions: Vec<LargeTransaction>,
+    ) -> Result<Vec<u8>, LargeTransactionReportWriterError> {
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        for LargeTransaction {
+            transaction,
+            applied,
+        } in large_transactions
+        {
+            let amount = match &transaction.kind {
+                TransactionKind::Deposit { amount } | TransactionKind::Withdrawal { amount } => {
+                    amount.to_str()
+                }
+                _ => continue,
+            };
+            wtr.serialize(LargeTransactionRow {
+                client: transaction.client_id,
+                tx: transaction.transaction_id,
+                kind: transaction.kind.name(),
+                amount,
+                applied,
+                row: transaction.ingest_sequence,
+            })
+            .map_err(|err| {
+                LargeTransactionReportWriterError::SerialisationError(err.to_string())
+            })?;
+        }
+        wtr.into_inner()
+            .map_err(|err| LargeTransactionReportWriterError::SerialisationError(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{Amount, Transaction, TransactionKind};
+
+    use super::{LargeTransaction, LargeTransactionReportWriter};
+
+    #[test]
+    fn large_transactions_are_written_as_csv() {
+        let large_transaction = |transaction_id, kind, applied| LargeTransaction {
+            transaction: Transaction {
+                client_id: 2,
+                transaction_id,
+                kind,
+                timestamp: None,
+                memo: None,
+                ingest_sequence: Some(u64::from(transaction_id) + 1),
+            },
+            applied,
+        };
+        let written = LargeTransactionReportWriter::write(vec![
+            large_transaction(
+                1,
+                TransactionKind::Deposit {
+                    amount: Amount::new(150_000_000),
+                },
+                true,
+            ),
+            large_transaction(
+                2,
+                TransactionKind::Withdrawal {
+                    amount: Amount::new(200_000_000),
+                },
+                false,
+            ),
+        ])
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(written).unwrap(),
+            "\
+            client,tx,type,amount,applied,row\n\
+            2,1,deposit,15000.0000,true,2\n\
+            2,2,withdrawal,20000.0000,false,3\n"
+        );
+    }
+}
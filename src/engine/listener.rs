@@ -0,0 +1,162 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    task::JoinSet,
+    time::timeout,
+};
+
+use crate::{
+    model::Transaction,
+    transaction_stream_processor::{
+        async_csv_stream_processor::AsyncCsvStreamProcessor, line_protocol::parse_line,
+        TransactionStreamProcessError,
+    },
+};
+
+use super::Engine;
+
+/// How often the listener checks whether it is cancelled while waiting for
+/// a connection.
+pub(super) const CANCELLATION_CHECK: Duration = Duration::from_millis(100);
+
+impl Engine {
+    /// Processes the transactions sent over the connections accepted by the
+    /// listener, one per line, each client's being routed to its worker as
+    /// they would be from a file.
+    /// A line is either a JSON object with the columns of a
+    /// [`TransactionRecordV2`] as its keys, the amount being a string, e.g.
+    /// `{"type": "deposit", "client": 1, "tx": 1, "amount": "1.5"}`, or a CSV
+    /// row with the columns of a [`TransactionRecordV1`] in order, e.g.
+    /// `deposit, 1, 1, 1.5`.
+    /// A line that cannot be read or processed is answered with `error: `
+    /// and why, the others are not answered.
+    /// Listens until the engine is cancelled, the connections still open
+    /// then being closed.
+    ///
+    /// [`TransactionRecordV1`]: crate::transaction_stream_processor::TransactionRecordV1
+    /// [`TransactionRecordV2`]: crate::transaction_stream_processor::TransactionRecordV2
+    pub async fn listen(&self, listener: TcpListener) -> Result<(), TransactionStreamProcessError> {
+        let processor =
+            Arc::new(self.concurrent_processor(self.processor(), self.accounts.clone()));
+        // the order the lines arrived in, across all the connections
+        let sequence = Arc::new(AtomicU64::new(0));
+        let mut connections = JoinSet::new();
+        while !self.cancellation.is_cancelled() {
+            // so that the cancellation is noticed while waiting for a
+            // connection
+            let Ok(accepted) = timeout(CANCELLATION_CHECK, listener.accept()).await else {
+                continue;
+            };
+            let (stream, _) = accepted
+                .map_err(|err| TransactionStreamProcessError::InternalError(err.to_string()))?;
+            connections.spawn(serve(stream, processor.clone(), sequence.clone()));
+        }
+        connections.shutdown().await;
+        match Arc::try_unwrap(processor) {
+            Ok(processor) => processor.shutdown().await,
+            Err(_) => Err(TransactionStreamProcessError::FailedToShutdown(
+                "The processor is still in use by a connection.".to_string(),
+            )),
+        }
+    }
+}
+
+async fn serve(
+    stream: TcpStream,
+    processor: Arc<AsyncCsvStreamProcessor>,
+    sequence: Arc<AtomicU64>,
+) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let result = match parse_line(&line) {
+            Ok(transaction) => {
+                processor
+                    .process_transaction(Transaction {
+                        ingest_sequence: Some(sequence.fetch_add(1, Ordering::Relaxed) + 1),
+                        ..transaction
+                    })
+                    .await
+            }
+            Err(err) => Err(err),
+        };
+        if let Err(err) = result {
+            if writer
+                .write_all(format!("error: {err}\n").as_bytes())
+                .await
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::{TcpListener, TcpStream},
+        time::{sleep, timeout},
+    };
+
+    use crate::{
+        engine::{Engine, EngineConfig},
+        model::Amount,
+    };
+
+    #[tokio::test]
+    async fn transactions_sent_over_tcp_reach_the_accounts() {
+        let engine = Engine::new(EngineConfig::default());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let client = async {
+            let mut stream = TcpStream::connect(address).await.unwrap();
+            stream
+                .write_all(
+                    b"deposit, 1, 1, 2.0\n\
+                    {\"type\": \"deposit\", \"client\": 1, \"tx\": 2, \"amount\": \"3.0\"}\n\
+                    deposit, 1, 3, three\n\
+                    withdrawal, 1, 4, 1.5\n",
+                )
+                .await
+                .unwrap();
+            let mut lines = BufReader::new(&mut stream).lines();
+            let answer = lines.next_line().await.unwrap().unwrap();
+            // all sent
+            stream.shutdown().await.unwrap();
+            let processed = async {
+                while engine
+                    .accounts()
+                    .get(1)
+                    .is_none_or(|account| account.available() != Amount::new(35_000))
+                {
+                    sleep(Duration::from_millis(10)).await;
+                }
+            };
+            timeout(Duration::from_secs(5), processed).await.unwrap();
+            engine.cancellation().cancel();
+            answer
+        };
+        let (listened, answer) = tokio::join!(engine.listen(listener), client);
+        listened.unwrap();
+        assert!(answer.starts_with("error: "), "{answer}");
+        assert_eq!(
+            engine.accounts().get(1).unwrap().available(),
+            Amount::new(35_000)
+        );
+    }
+}
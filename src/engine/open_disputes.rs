@@ -0,0 +1,112 @@
+use csv::WriterBuilder;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::{
+    account::Account,
+    model::{Amount, ClientId, Timestamp, TransactionId},
+};
+
+/// A transaction still disputed at the end of processing, i.e. money in limbo
+/// until the dispute is resolved or charged back.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct OpenDispute {
+    #[serde(rename = "client")]
+    pub client_id: ClientId,
+    #[serde(rename = "tx")]
+    pub transaction_id: TransactionId,
+    pub amount: String,
+
+    /// The kind of the disputed transaction. Only deposits can be disputed
+    /// for now.
+    pub kind: &'static str,
+}
+
+impl OpenDispute {
+    /// The open disputes of the account, in no particular order.
+    pub(super) fn of(account: &Account) -> Vec<OpenDispute> {
+        account
+            .held_deposits()
+            .map(|(transaction_id, deposit)| OpenDispute {
+                client_id: account.client_id,
+                transaction_id,
+                amount: deposit.amount.to_str(),
+                kind: "deposit",
+            })
+            .collect()
+    }
+}
+
+/// A dispute resolved automatically as it was on hold for longer than the
+/// [`AutoResolution`] of the account policy allows.
+///
+/// [`AutoResolution`]: crate::account::AutoResolution
+#[derive(Debug, PartialEq, Clone)]
+pub struct AutoResolvedDispute {
+    pub client_id: ClientId,
+    pub transaction_id: TransactionId,
+    pub amount: Amount,
+
+    /// When the deposit was disputed, if the input says so.
+    pub disputed_at: Option<Timestamp>,
+}
+
+impl AutoResolvedDispute {
+    /// The disputes resolved automatically for the account, in the order
+    /// they were.
+    pub(super) fn of(account: &Account) -> Vec<AutoResolvedDispute> {
+        account
+            .auto_resolved()
+            .iter()
+            .map(|auto_resolved| AutoResolvedDispute {
+                client_id: account.client_id,
+                transaction_id: auto_resolved.transaction_id,
+                amount: auto_resolved.amount,
+                disputed_at: auto_resolved.held.timestamp,
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum OpenDisputeReportWriterError {
+    #[error("Failed to serialise the OpenDispute: {0}")]
+    SerialisationError(String),
+}
+
+/// Writes the open disputes as CSV with the columns `client`, `tx`, `amount`
+/// and `kind`.
+pub struct OpenDisputeReportWriter;
+
+impl OpenDisputeReportWriter {
+    pub fn write(open_disputes: Vec<OpenDispute>) -> Result<Vec<u8>, OpenDisputeReportWriterError> {
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        for open_dispute in open_disputes {
+            wtr.serialize(open_dispute)
+                .map_err(|err| OpenDisputeReportWriterError::SerialisationError(err.to_string()))?;
+        }
+        wtr.into_inner()
+            .map_err(|err| OpenDisputeReportWriterError::SerialisationError(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OpenDispute, OpenDisputeReportWriter};
+
+    #[test]
+    fn open_disputes_can_be_written_as_csv() {
+        let open_disputes = vec![OpenDispute {
+            client_id: 1,
+            transaction_id: 2,
+            amount: "3.0000".to_string(),
+            kind: "deposit",
+        }];
+        assert_eq!(
+            String::from_utf8(OpenDisputeReportWriter::write(open_disputes).unwrap()).unwrap(),
+            "\
+            client,tx,amount,kind\n\
+            1,2,3.0000,deposit\n"
+        );
+    }
+}
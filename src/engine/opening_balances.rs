@@ -0,0 +1,119 @@
+use std::{collections::HashSet, io::Read};
+
+use csv::{ReaderBuilder, Trim};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    account::{Account, AccountStatus},
+    model::{amount_format, Amount, ClientId},
+};
+
+#[derive(Debug, Error, PartialEq)]
+pub enum OpeningBalancesError {
+    #[error("Failed to read the opening balances: {0}")]
+    ReadError(String),
+    #[error("Client {0} has more than one opening balance.")]
+    DuplicateClient(ClientId),
+    #[error("The held funds of client {0} are negative.")]
+    NegativeHeld(ClientId),
+    #[error("Client {0} has an account already.")]
+    AccountExists(ClientId),
+}
+
+/// The balances and lock of a client's account to start from, e.g. its
+/// closing state of the previous day.
+#[derive(Debug, PartialEq, Clone, Copy, Deserialize)]
+pub struct OpeningBalance {
+    #[serde(rename = "client")]
+    pub client_id: ClientId,
+    #[serde(with = "amount_format")]
+    pub available: Amount,
+    #[serde(with = "amount_format")]
+    pub held: Amount,
+    pub locked: bool,
+}
+
+impl OpeningBalance {
+    /// Reads the balances from a CSV file with the columns `client`,
+    /// `available`, `held` and `locked`, e.g. `1,10.5,2.0,false`, such as
+    /// the summaries less their `total` column. A client given twice, or with
+    /// negative held funds, fails the whole file.
+    pub fn read(r: impl Read) -> Result<Vec<OpeningBalance>, OpeningBalancesError> {
+        let mut rdr = ReaderBuilder::new().trim(Trim::All).from_reader(r);
+        let mut clients = HashSet::new();
+        let mut balances = Vec::new();
+        for record in rdr.deserialize::<OpeningBalance>() {
+            let balance = record.map_err(|err| OpeningBalancesError::ReadError(err.to_string()))?;
+            if !clients.insert(balance.client_id) {
+                return Err(OpeningBalancesError::DuplicateClient(balance.client_id));
+            }
+            if balance.held.is_negative() {
+                return Err(OpeningBalancesError::NegativeHeld(balance.client_id));
+            }
+            balances.push(balance);
+        }
+        Ok(balances)
+    }
+
+    /// An account with the balances and none of the transactions behind
+    /// them, so that the held funds stay held, there being no dispute to
+    /// resolve or charge back.
+    pub(super) fn account(&self) -> Account {
+        let mut account = Account::active_with_capacity(self.client_id, 0);
+        account.snapshot_mut().available = self.available;
+        account.snapshot_mut().held = self.held;
+        if self.locked {
+            account.set_status(AccountStatus::Locked);
+        }
+        account
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::model::Amount;
+
+    use super::{OpeningBalance, OpeningBalancesError};
+
+    #[test]
+    fn opening_balances_can_be_read() {
+        let input = "
+            client, available, held, locked
+                 1,      10.5,  2.0,  false
+                 2,      -1.0,    0,   true";
+        assert_eq!(
+            OpeningBalance::read(input.trim_start().as_bytes()),
+            Ok(vec![
+                OpeningBalance {
+                    client_id: 1,
+                    available: Amount::new(105_000),
+                    held: Amount::new(20_000),
+                    locked: false,
+                },
+                OpeningBalance {
+                    client_id: 2,
+                    available: Amount::new(-10_000),
+                    held: Amount::new(0),
+                    locked: true,
+                },
+            ])
+        );
+    }
+
+    #[rstest]
+    #[case(
+        "1,1.0,0,false\n1,2.0,0,false",
+        OpeningBalancesError::DuplicateClient(1)
+    )]
+    #[case("2,1.0,-0.5,false", OpeningBalancesError::NegativeHeld(2))]
+    fn invalid_opening_balances_are_not_read(
+        #[case] rows: &str,
+        #[case] expected: OpeningBalancesError,
+    ) {
+        let input = format!("client,available,held,locked\n{rows}");
+        assert_eq!(OpeningBalance::read(input.as_bytes()), Err(expected));
+    }
+}
@@ -0,0 +1,113 @@
+use std::{future::Future, sync::Arc};
+
+use async_trait::async_trait;
+use futures_util::{stream, Stream};
+use tokio::sync::mpsc;
+
+use crate::{
+    account::{AccountTransactorError, SuccessStatus},
+    model::Transaction,
+    transaction_processor::{TransactionProcessor, TransactionProcessorError},
+    transaction_stream_processor::TransactionStreamProcessError,
+};
+
+/// What became of a row of the input, see [`Engine::process_with_outcomes`].
+///
+/// [`Engine::process_with_outcomes`]: super::Engine::process_with_outcomes
+#[derive(Debug, PartialEq, Clone)]
+pub struct RecordOutcome {
+    /// The row of the input.
+    pub sequence: u64,
+    pub transaction: Transaction,
+    pub outcome: Outcome,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Outcome {
+    /// The transaction changed the account.
+    Applied,
+
+    /// The transaction was left out of the account, e.g. for want of funds.
+    Rejected(AccountTransactorError),
+
+    /// The transaction was let through without changing the account, e.g.
+    /// as a duplicate or as its client is filtered out.
+    Skipped,
+}
+
+/// Sends what became of each transaction on its way back from the processor
+/// it wraps. A rejection is sent rather than passed on, so that it does not
+/// stop the run. Errors that are not rejections, e.g. an unavailable store,
+/// are passed on.
+pub(super) struct OutcomeRecorder {
+    inner: Arc<dyn TransactionProcessor + Send + Sync>,
+    outcomes: mpsc::UnboundedSender<RecordOutcome>,
+}
+
+#[async_trait]
+impl TransactionProcessor for OutcomeRecorder {
+    async fn process(
+        &self,
+        transaction: Transaction,
+    ) -> Result<SuccessStatus, TransactionProcessorError> {
+        let result = self.inner.process(transaction.clone()).await;
+        let (outcome, result) = match result {
+            Ok(SuccessStatus::Transacted) => (Outcome::Applied, result),
+            Ok(SuccessStatus::Duplicate | SuccessStatus::NoOp) => (Outcome::Skipped, result),
+            Err(TransactionProcessorError::AccountTransactionError(_, reason)) => {
+                (Outcome::Rejected(reason), Ok(SuccessStatus::NoOp))
+            }
+            Err(err) => return Err(err),
+        };
+        // the stream may have been dropped
+        let _ = self.outcomes.send(RecordOutcome {
+            sequence: transaction
+                .ingest_sequence
+                .expect("The rows are numbered as they are read."),
+            transaction,
+            outcome,
+        });
+        result
+    }
+}
+
+impl OutcomeRecorder {
+    pub(super) fn new(
+        inner: Arc<dyn TransactionProcessor + Send + Sync>,
+        outcomes: mpsc::UnboundedSender<RecordOutcome>,
+    ) -> Self {
+        Self { inner, outcomes }
+    }
+}
+
+/// The outcomes sent while the run goes on, followed by its error if it
+/// fails. The run only makes progress while the stream is polled.
+pub(super) fn outcome_stream<'a>(
+    run: impl Future<Output = Result<(), TransactionStreamProcessError>> + Send + 'a,
+    outcomes: mpsc::UnboundedReceiver<RecordOutcome>,
+) -> impl Stream<Item = Result<RecordOutcome, TransactionStreamProcessError>> + Send + 'a {
+    stream::unfold(
+        (Some(Box::pin(run)), outcomes, None),
+        |(mut run, mut outcomes, mut failure)| async move {
+            while let Some(running) = &mut run {
+                let next = tokio::select! {
+                    biased;
+                    outcome = outcomes.recv() => Ok(outcome),
+                    result = &mut *running => Err(result),
+                };
+                match next {
+                    Ok(Some(outcome)) => return Some((Ok(outcome), (run, outcomes, failure))),
+                    Ok(None) => failure = running.await.err(),
+                    Err(result) => failure = result.err(),
+                }
+                // the senders go with the run, ending the outcomes once the
+                // ones already sent are received
+                run = None;
+            }
+            match outcomes.recv().await {
+                Some(outcome) => Some((Ok(outcome), (run, outcomes, failure))),
+                None => failure.take().map(|err| (Err(err), (run, outcomes, None))),
+            }
+        },
+    )
+}
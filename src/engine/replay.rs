@@ -0,0 +1,267 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+
+use crate::{
+    account::{
+        account_transactor::{AccountTransactor, AccountTransactorError},
+        Account, AccountSnapshot, AccountStatus, SimpleAccountTransactor, SuccessStatus,
+    },
+    model::{ClientId, Timestamp, Transaction, TransactionKind},
+    transaction_processor::{TransactionProcessor, TransactionProcessorError},
+};
+
+/// What a single transaction did to the account being replayed.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ReplayStep {
+    pub transaction: Transaction,
+    pub before: AccountSnapshot,
+    pub after: AccountSnapshot,
+    /// The status of the account once the transaction is processed.
+    pub status: AccountStatus,
+    pub result: Result<SuccessStatus, AccountTransactorError>,
+}
+
+impl Display for ReplayStep {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let transaction_id = self.transaction.transaction_id;
+        match &self.transaction.kind {
+            TransactionKind::Deposit { amount } => {
+                write!(f, "tx {transaction_id} deposit {}", amount.to_str())
+            }
+            TransactionKind::Withdrawal { amount } => {
+                write!(f, "tx {transaction_id} withdrawal {}", amount.to_str())
+            }
+            TransactionKind::Dispute => write!(f, "tx {transaction_id} dispute"),
+            TransactionKind::Resolve => write!(f, "tx {transaction_id} resolve"),
+            TransactionKind::ChargeBack => write!(f, "tx {transaction_id} chargeback"),
+            TransactionKind::Representment => write!(f, "tx {transaction_id} representment"),
+            TransactionKind::Close => write!(f, "tx {transaction_id} close"),
+            TransactionKind::Adjustment { amount, reason } => write!(
+                f,
+                "tx {transaction_id} adjustment {} ({reason})",
+                amount.to_str()
+            ),
+        }?;
+        if let Some(memo) = &self.transaction.memo {
+            write!(f, " [{memo}]")?;
+        }
+        write!(
+            f,
+            ": available {} -> {}, held {} -> {}, {}",
+            self.before.available.to_str(),
+            self.after.available.to_str(),
+            self.before.held.to_str(),
+            self.after.held.to_str(),
+            match self.status {
+                AccountStatus::Active => "active",
+                AccountStatus::Locked => "locked",
+                AccountStatus::Closed => "closed",
+            }
+        )?;
+        match &self.result {
+            Ok(SuccessStatus::Duplicate) => write!(f, ", duplicate"),
+            Ok(_) => Ok(()),
+            Err(err) => write!(f, ", rejected: {err}"),
+        }
+    }
+}
+
+/// A point in the history of a client, as replayed from the input.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum HistoryPoint {
+    /// After that many of the client's transactions, in input order.
+    Sequence(usize),
+
+    /// After the client's transactions up to the first one stamped later.
+    /// Transactions without a timestamp are taken as they come.
+    Timestamp(Timestamp),
+}
+
+impl HistoryPoint {
+    /// The balances of the account at the point, rejected transactions
+    /// leaving them as they were.
+    pub(super) fn snapshot_of(self, steps: &[ReplayStep]) -> AccountSnapshot {
+        let reached = match self {
+            HistoryPoint::Sequence(sequence) => sequence.min(steps.len()),
+            HistoryPoint::Timestamp(timestamp) => steps
+                .iter()
+                .position(|step| {
+                    step.transaction
+                        .timestamp
+                        .is_some_and(|stamped| stamped > timestamp)
+                })
+                .unwrap_or(steps.len()),
+        };
+        match reached {
+            0 => AccountSnapshot::empty(),
+            reached => steps[reached - 1].after,
+        }
+    }
+}
+
+/// Applies the transactions of a single client to an account of its own,
+/// recording a [`ReplayStep`] for each of them.
+/// The transactions of other clients are skipped. Rejected transactions are
+/// recorded rather than failing the replay, so that the whole history of the
+/// client can be looked at.
+pub(super) struct ReplayingProcessor {
+    client_id: ClientId,
+    account_transactor: SimpleAccountTransactor,
+    account: Mutex<Account>,
+    steps: Arc<Mutex<Vec<ReplayStep>>>,
+}
+
+#[async_trait]
+impl TransactionProcessor for ReplayingProcessor {
+    async fn process(
+        &self,
+        transaction: Transaction,
+    ) -> Result<SuccessStatus, TransactionProcessorError> {
+        if transaction.client_id != self.client_id {
+            return Ok(SuccessStatus::NoOp);
+        }
+        let mut account = self
+            .account
+            .lock()
+            .expect("The account is only ever locked by the replay itself.");
+        let before = account.account_snapshot;
+        let result = self
+            .account_transactor
+            .transact(&mut account, transaction.clone());
+        let status = result.clone().unwrap_or(SuccessStatus::NoOp);
+        self.steps
+            .lock()
+            .expect("The steps are only ever locked by the replay itself.")
+            .push(ReplayStep {
+                transaction,
+                before,
+                after: account.account_snapshot,
+                status: account.status,
+                result,
+            });
+        Ok(status)
+    }
+}
+
+impl ReplayingProcessor {
+    pub(super) fn new(
+        client_id: ClientId,
+        account_transactor: SimpleAccountTransactor,
+        steps: Arc<Mutex<Vec<ReplayStep>>>,
+    ) -> Self {
+        Self {
+            client_id,
+            account_transactor,
+            account: Mutex::new(Account::active_with_capacity(client_id, 0)),
+            steps,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use rstest::rstest;
+
+    use crate::{
+        account::{
+            account_transactor::AccountTransactorError, AccountSnapshot, AccountStatus,
+            DisputerError, SuccessStatus,
+        },
+        model::{Amount, Memo, Timestamp, Transaction, TransactionKind},
+    };
+
+    use super::{HistoryPoint, ReplayStep};
+
+    #[rustfmt::skip]
+    #[rstest]
+    #[case::before_any      (HistoryPoint::Sequence(0),   (0, 0))]
+    #[case::after_the_first (HistoryPoint::Sequence(1),   (10, 0))]
+    #[case::past_the_end    (HistoryPoint::Sequence(9),   (0, 10))]
+    #[case::too_early       (HistoryPoint::Timestamp(99), (0, 0))]
+    #[case::in_between      (HistoryPoint::Timestamp(150),(10, 0))]
+    #[case::unstamped_taken (HistoryPoint::Timestamp(200),(0, 10))]
+    fn history_point_picks_the_balances_of_the_last_step_reached(
+        #[case] point: HistoryPoint,
+        #[case] expected: (i64, i64),
+    ) {
+        let steps = vec![
+            stamped(step(TransactionKind::Deposit { amount: Amount::new(10) }, (0, 0), (10, 0), AccountStatus::Active, Ok(SuccessStatus::Transacted)), Some(100)),
+            stamped(step(TransactionKind::Dispute, (10, 0), (0, 10), AccountStatus::Active, Ok(SuccessStatus::Transacted)), Some(200)),
+            stamped(step(TransactionKind::Dispute, (0, 10), (0, 10), AccountStatus::Active, Ok(SuccessStatus::Transacted)), None),
+        ];
+        assert_eq!(
+            point.snapshot_of(&steps),
+            AccountSnapshot::new(expected.0, expected.1)
+        );
+    }
+
+    fn stamped(mut step: ReplayStep, timestamp: Option<Timestamp>) -> ReplayStep {
+        step.transaction.timestamp = timestamp;
+        step
+    }
+
+    fn with_memo(mut step: ReplayStep) -> ReplayStep {
+        step.transaction.memo = Some(Box::new(Memo {
+            text: Some("invoice 42".to_string()),
+            metadata: HashMap::from([
+                ("source".to_string(), "sftp".to_string()),
+                ("batch".to_string(), "7".to_string()),
+            ]),
+        }));
+        step
+    }
+
+    #[rstest]
+    #[case(
+        step(TransactionKind::Deposit { amount: Amount::new(15_000) }, (0, 0), (15_000, 0), AccountStatus::Active, Ok(SuccessStatus::Transacted)),
+        "tx 7 deposit 1.5000: available 0.0000 -> 1.5000, held 0.0000 -> 0.0000, active"
+    )]
+    #[case(
+        with_memo(step(TransactionKind::Deposit { amount: Amount::new(15_000) }, (0, 0), (15_000, 0), AccountStatus::Active, Ok(SuccessStatus::Transacted))),
+        "tx 7 deposit 1.5000 [invoice 42; batch=7; source=sftp]: available 0.0000 -> 1.5000, held 0.0000 -> 0.0000, active"
+    )]
+    #[case(
+        step(TransactionKind::ChargeBack, (10_000, 15_000), (10_000, 0), AccountStatus::Locked, Ok(SuccessStatus::Transacted)),
+        "tx 7 chargeback: available 1.0000 -> 1.0000, held 1.5000 -> 0.0000, locked"
+    )]
+    #[case(
+        step(TransactionKind::Dispute, (10_000, 0), (10_000, 0), AccountStatus::Active, Ok(SuccessStatus::Duplicate)),
+        "tx 7 dispute: available 1.0000 -> 1.0000, held 0.0000 -> 0.0000, active, duplicate"
+    )]
+    #[case(
+        step(TransactionKind::Dispute, (10_000, 0), (10_000, 0), AccountStatus::Locked, Err(AccountTransactorError::Dispute(DisputerError::AccountLocked))),
+        "tx 7 dispute: available 1.0000 -> 1.0000, held 0.0000 -> 0.0000, locked, rejected: The account is locked"
+    )]
+    fn replay_step_can_be_displayed(#[case] step: ReplayStep, #[case] expected: &str) {
+        assert_eq!(step.to_string(), expected);
+    }
+
+    fn step(
+        kind: TransactionKind,
+        before: (i64, i64),
+        after: (i64, i64),
+        status: AccountStatus,
+        result: Result<SuccessStatus, AccountTransactorError>,
+    ) -> ReplayStep {
+        ReplayStep {
+            transaction: Transaction {
+                client_id: 1,
+                transaction_id: 7,
+                kind,
+                timestamp: None,
+                memo: None,
+                ingest_sequence: None,
+            },
+            before: AccountSnapshot::new(before.0, before.1),
+            after: AccountSnapshot::new(after.0, after.1),
+            status,
+            result,
+        }
+    }
+}
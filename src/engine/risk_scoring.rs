@@ -0,0 +1,64 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::broadcast;
+
+use crate::{
+    account::{AccountStore, SuccessStatus},
+    model::Transaction,
+    risk::{RiskScorer, ScoredTransaction},
+    transaction_processor::{TransactionProcessor, TransactionProcessorError},
+};
+
+/// Scores each transaction against the client's account as it stands before
+/// passing it on to the processor it wraps, and sends it along with its
+/// score whatever becomes of it.
+/// Nothing is scored while no one is listening.
+pub(super) struct RiskScoring {
+    inner: Arc<dyn TransactionProcessor + Send + Sync>,
+    accounts: Arc<AccountStore>,
+    scorer: Arc<dyn RiskScorer + Send + Sync>,
+    scores: broadcast::Sender<ScoredTransaction>,
+}
+
+#[async_trait]
+impl TransactionProcessor for RiskScoring {
+    async fn process(
+        &self,
+        transaction: Transaction,
+    ) -> Result<SuccessStatus, TransactionProcessorError> {
+        if self.scores.receiver_count() == 0 {
+            return self.inner.process(transaction).await;
+        }
+        let score = self
+            .accounts
+            .inspect(transaction.client_id, |account| {
+                self.scorer.score(Some(account), &transaction)
+            })
+            .unwrap_or_else(|| self.scorer.score(None, &transaction));
+        let scored = ScoredTransaction {
+            transaction: transaction.clone(),
+            score,
+        };
+        let result = self.inner.process(transaction).await;
+        // no one may be listening any more
+        let _ = self.scores.send(scored);
+        result
+    }
+}
+
+impl RiskScoring {
+    pub(super) fn new(
+        inner: Arc<dyn TransactionProcessor + Send + Sync>,
+        accounts: Arc<AccountStore>,
+        scorer: Arc<dyn RiskScorer + Send + Sync>,
+        scores: broadcast::Sender<ScoredTransaction>,
+    ) -> Self {
+        Self {
+            inner,
+            accounts,
+            scorer,
+            scores,
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufReader, Read},
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use super::{ClientStats, ENV_PREFIX};
+
+/// What a batch run was given and what became of it, for the audit trail of
+/// the runs, written as JSON alongside the summaries.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct RunManifest {
+    /// The version of the engine.
+    pub version: String,
+    pub input: FileDigest,
+    pub config: ConfigDigest,
+    /// The number of transactions of each kind in the input, rejected ones
+    /// included, along with the duplicates and no-ops.
+    pub counts: BTreeMap<String, usize>,
+    /// The number of rejected transactions by reason, e.g.
+    /// `Deposit(AccountLocked)`.
+    pub rejections: BTreeMap<String, usize>,
+    /// The number of rows put in quarantine, by type.
+    pub quarantined: BTreeMap<String, usize>,
+    /// When the run started, in seconds since the Unix epoch.
+    pub started_at: u64,
+    pub duration_ms: u128,
+    /// Whether the run was interrupted, the summaries only covering the
+    /// input read so far.
+    pub interrupted: bool,
+}
+
+/// A file as it was read.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct FileDigest {
+    pub path: String,
+    pub bytes: u64,
+    /// The SHA-256 of the file, in hex.
+    pub sha256: String,
+}
+
+/// Where the config of the engine came from, and their fingerprint, the
+/// same for any two runs set up the same way.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct ConfigDigest {
+    pub file: Option<FileDigest>,
+    /// The `JP_` environment variables set.
+    pub env: BTreeMap<String, String>,
+    /// The settings given on the command line, e.g. `shard`.
+    pub flags: BTreeMap<String, String>,
+    /// The SHA-256, in hex, of the content of the config file, the
+    /// environment variables and the flags.
+    pub fingerprint: String,
+}
+
+impl RunManifest {
+    /// The manifest of a run over `input`, going by the stats collected, see
+    /// [`EngineConfig::collect_stats`].
+    ///
+    /// [`EngineConfig::collect_stats`]: super::EngineConfig::collect_stats
+    pub fn new(
+        input: FileDigest,
+        config: ConfigDigest,
+        stats: &[ClientStats],
+        quarantined: BTreeMap<String, usize>,
+        started: SystemTime,
+        interrupted: bool,
+    ) -> Self {
+        let mut counts = BTreeMap::new();
+        let mut rejections = BTreeMap::new();
+        for client_stats in stats {
+            for (kind, count) in [
+                ("deposit", client_stats.deposits),
+                ("withdrawal", client_stats.withdrawals),
+                ("dispute", client_stats.disputes),
+                ("resolve", client_stats.resolves),
+                ("chargeback", client_stats.chargebacks),
+                ("representment", client_stats.representments),
+                ("close", client_stats.closures),
+                ("adjustment", client_stats.adjustments),
+                ("duplicate", client_stats.duplicates),
+                ("no_op", client_stats.no_ops),
+            ] {
+                *counts.entry(kind.to_string()).or_default() += count;
+            }
+            for (reason, count) in &client_stats.rejections {
+                *rejections.entry(reason.clone()).or_default() += count;
+            }
+        }
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            input,
+            config,
+            counts,
+            rejections,
+            quarantined,
+            started_at: started
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            duration_ms: started.elapsed().unwrap_or(Duration::ZERO).as_millis(),
+            interrupted,
+        }
+    }
+
+    pub fn to_json(&self) -> Vec<u8> {
+        serde_json::to_vec_pretty(self).expect("A manifest is always serialisable.")
+    }
+}
+
+impl FileDigest {
+    /// Reads the file at `path` through.
+    pub fn of_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut hasher = Sha256::new();
+        let mut bytes = 0;
+        let mut buffer = [0; 64 * 1024];
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            bytes += read as u64;
+        }
+        Ok(Self {
+            path: path.display().to_string(),
+            bytes,
+            sha256: hex(&hasher.finalize()),
+        })
+    }
+}
+
+impl ConfigDigest {
+    /// The digest of the config file, if any, of the `JP_` variables among
+    /// `vars`, e.g. [`std::env::vars`], and of the flags.
+    pub fn new(
+        file: Option<&Path>,
+        vars: impl IntoIterator<Item = (String, String)>,
+        flags: BTreeMap<String, String>,
+    ) -> io::Result<Self> {
+        let file = file.map(FileDigest::of_file).transpose()?;
+        let env: BTreeMap<String, String> = vars
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(ENV_PREFIX))
+            .collect();
+        let mut hasher = Sha256::new();
+        if let Some(file) = &file {
+            hasher.update(&file.sha256);
+        }
+        for (name, value) in env.iter().chain(&flags) {
+            hasher.update(format!("\n{name}={value}"));
+        }
+        Ok(Self {
+            file,
+            env,
+            flags,
+            fingerprint: hex(&hasher.finalize()),
+        })
+    }
+}
+
+fn hex(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        env, fs, process,
+        time::{Duration, SystemTime},
+    };
+
+    use crate::engine::{Engine, EngineConfig};
+
+    use super::{ConfigDigest, FileDigest, RunManifest};
+
+    fn vars(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn files_are_digested_as_read() {
+        let path = env::temp_dir().join(format!("jouet_paiement_manifest_{}", process::id()));
+        fs::write(&path, "abc").unwrap();
+        let digest = FileDigest::of_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(digest.bytes, 3);
+        assert_eq!(
+            digest.sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn the_fingerprint_only_changes_with_the_config() {
+        let flags = BTreeMap::from([("shard".to_string(), "3/8".to_string())]);
+        let fingerprint = |vars: Vec<(String, String)>, flags: &BTreeMap<String, String>| {
+            ConfigDigest::new(None, vars, flags.clone())
+                .unwrap()
+                .fingerprint
+        };
+        let base = fingerprint(
+            vars(&[("JP_PARSERS", "4"), ("HOME", "/root"), ("JP_SHARD", "1/2")]),
+            &flags,
+        );
+
+        assert_eq!(
+            fingerprint(
+                vars(&[("JP_SHARD", "1/2"), ("JP_PARSERS", "4"), ("HOME", "/tmp")]),
+                &flags
+            ),
+            base
+        );
+        assert_ne!(
+            fingerprint(
+                vars(&[("JP_PARSERS", "4"), ("JP_SHARD", "1/2")]),
+                &BTreeMap::new()
+            ),
+            base
+        );
+        assert_ne!(
+            fingerprint(vars(&[("JP_PARSERS", "8"), ("JP_SHARD", "1/2")]), &flags),
+            base
+        );
+    }
+
+    #[tokio::test]
+    async fn transactions_are_counted_by_kind_and_rejections_by_reason() {
+        let engine = Engine::new(EngineConfig {
+            collect_stats: true,
+            ..Default::default()
+        });
+        let input = "type, client, tx, amount
+deposit, 1, 1, 2.0
+deposit, 2, 2, 1.0
+withdrawal, 1, 3, 5.0
+dispute, 1, 1,
+withdrawal, 2, 4, 3.0";
+        engine.process(input.as_bytes()).await.unwrap();
+        let manifest = RunManifest::new(
+            FileDigest {
+                path: "input.csv".to_string(),
+                bytes: input.len() as u64,
+                sha256: String::new(),
+            },
+            ConfigDigest::new(None, vec![], BTreeMap::new()).unwrap(),
+            &engine.stats(),
+            engine.quarantine().counts(),
+            SystemTime::now() - Duration::from_secs(1),
+            false,
+        );
+
+        assert_eq!(manifest.counts["deposit"], 2);
+        assert_eq!(manifest.counts["withdrawal"], 2);
+        assert_eq!(manifest.counts["dispute"], 1);
+        assert_eq!(manifest.counts["resolve"], 0);
+        assert_eq!(manifest.rejections.values().sum::<usize>(), 2);
+        assert!(manifest.duration_ms >= 1_000);
+        assert_eq!(manifest.version, env!("CARGO_PKG_VERSION"));
+    }
+}
@@ -0,0 +1,251 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::{
+    account::{AccountStore, SuccessStatus},
+    model::{AccountSummary, Amount, ClientId, Transaction, TransactionId},
+    transaction_processor::{TransactionProcessor, TransactionProcessorError},
+};
+
+/// A field of a client's account snapshot, with its value before and after a
+/// transaction changed it.
+#[derive(Debug, PartialEq, Clone)]
+pub enum FieldChange {
+    Available { old: Amount, new: Amount },
+    Held { old: Amount, new: Amount },
+    Total { old: Amount, new: Amount },
+    Locked { old: bool, new: bool },
+}
+
+impl FieldChange {
+    /// The name of the field, as in the summaries.
+    pub fn field(&self) -> &'static str {
+        match self {
+            FieldChange::Available { .. } => "available",
+            FieldChange::Held { .. } => "held",
+            FieldChange::Total { .. } => "total",
+            FieldChange::Locked { .. } => "locked",
+        }
+    }
+}
+
+/// A change to a field of a client's account snapshot, along with the
+/// transaction that caused it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SnapshotDelta {
+    pub client_id: ClientId,
+    pub change: FieldChange,
+    pub cause: Transaction,
+}
+
+/// Records a [`SnapshotDelta`] for each field of the account a transaction
+/// changes, going by the summary of the account before and after the
+/// processor it wraps, a client without an account yet having nothing
+/// available, held or locked.
+/// The transactions of a client are processed one after another, so that
+/// nothing else changes its account in between.
+pub(super) struct DeltaRecorder {
+    inner: Arc<dyn TransactionProcessor + Send + Sync>,
+    accounts: Arc<AccountStore>,
+    recorded: Arc<Mutex<Vec<SnapshotDelta>>>,
+}
+
+#[async_trait]
+impl TransactionProcessor for DeltaRecorder {
+    async fn process(
+        &self,
+        transaction: Transaction,
+    ) -> Result<SuccessStatus, TransactionProcessorError> {
+        let client_id = transaction.client_id;
+        let before = self.summary(client_id);
+        let result = self.inner.process(transaction.clone()).await;
+        let after = self.summary(client_id);
+        let changes = changes(&before, &after);
+        if !changes.is_empty() {
+            self.recorded
+                .lock()
+                .expect("The snapshot deltas are never locked across a panic.")
+                .extend(changes.into_iter().map(|change| SnapshotDelta {
+                    client_id,
+                    change,
+                    cause: transaction.clone(),
+                }));
+        }
+        result
+    }
+}
+
+impl DeltaRecorder {
+    pub(super) fn new(
+        inner: Arc<dyn TransactionProcessor + Send + Sync>,
+        accounts: Arc<AccountStore>,
+        recorded: Arc<Mutex<Vec<SnapshotDelta>>>,
+    ) -> Self {
+        Self {
+            inner,
+            accounts,
+            recorded,
+        }
+    }
+
+    fn summary(&self, client_id: ClientId) -> AccountSummary {
+        self.accounts.summary(client_id).unwrap_or(AccountSummary {
+            client_id,
+            available: Amount::ZERO,
+            held: Amount::ZERO,
+            total: Amount::ZERO,
+            locked: false,
+        })
+    }
+}
+
+/// The fields that differ between the two summaries, in the order of the
+/// summaries' columns.
+fn changes(before: &AccountSummary, after: &AccountSummary) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+    if before.available != after.available {
+        changes.push(FieldChange::Available {
+            old: before.available,
+            new: after.available,
+        });
+    }
+    if before.held != after.held {
+        changes.push(FieldChange::Held {
+            old: before.held,
+            new: after.held,
+        });
+    }
+    if before.total != after.total {
+        changes.push(FieldChange::Total {
+            old: before.total,
+            new: after.total,
+        });
+    }
+    if before.locked != after.locked {
+        changes.push(FieldChange::Locked {
+            old: before.locked,
+            new: after.locked,
+        });
+    }
+    changes
+}
+
+#[derive(Debug, Error)]
+pub enum SnapshotDeltaWriterError {
+    #[error("Failed to serialise the SnapshotDelta: {0}")]
+    SerialisationError(String),
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum FieldValue {
+    Amount(String),
+    Locked(bool),
+}
+
+#[derive(Serialize)]
+struct SnapshotDeltaLine {
+    client: ClientId,
+    field: &'static str,
+    old: FieldValue,
+    new: FieldValue,
+    tx: TransactionId,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    /// The row of the input, if known.
+    row: Option<u64>,
+}
+
+/// Writes the snapshot deltas as newline-delimited JSON, one object per line
+/// with the keys `client`, `field`, `old`, `new`, `tx`, `type` and `row`,
+/// e.g. for a change-data-capture feed.
+pub struct SnapshotDeltaWriter;
+
+impl SnapshotDeltaWriter {
+    pub fn write(deltas: Vec<SnapshotDelta>) -> Result<Vec<u8>, SnapshotDeltaWriterError> {
+        let mut written = Vec::new();
+        for SnapshotDelta {
+            client_id,
+            change,
+            cause,
+        } in deltas
+        {
+            let field = change.field();
+            let (old, new) = match change {
+                FieldChange::Available { old, new }
+                | FieldChange::Held { old, new }
+                | FieldChange::Total { old, new } => (
+                    FieldValue::Amount(old.to_str()),
+                    FieldValue::Amount(new.to_str()),
+                ),
+                FieldChange::Locked { old, new } => {
+                    (FieldValue::Locked(old), FieldValue::Locked(new))
+                }
+            };
+            serde_json::to_writer(
+                &mut written,
+                &SnapshotDeltaLine {
+                    client: client_id,
+                    field,
+                    old,
+                    new,
+                    tx: cause.transaction_id,
+                    kind: cause.kind.name(),
+                    row: cause.ingest_sequence,
+                },
+            )
+            .map_err(|err| SnapshotDeltaWriterError::SerialisationError(err.to_string()))?;
+            written.push(b'\n');
+        }
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{Amount, Transaction, TransactionKind};
+
+    use super::{FieldChange, SnapshotDelta, SnapshotDeltaWriter};
+
+    #[test]
+    fn snapshot_deltas_are_written_as_ndjson() {
+        let chargeback = Transaction {
+            client_id: 2,
+            transaction_id: 1,
+            kind: TransactionKind::ChargeBack,
+            timestamp: None,
+            memo: None,
+            ingest_sequence: Some(4),
+        };
+        let delta = |change| SnapshotDelta {
+            client_id: 2,
+            change,
+            cause: chargeback.clone(),
+        };
+        let written = SnapshotDeltaWriter::write(vec![
+            delta(FieldChange::Held {
+                old: Amount::new(15_000),
+                new: Amount::ZERO,
+            }),
+            delta(FieldChange::Total {
+                old: Amount::new(15_000),
+                new: Amount::ZERO,
+            }),
+            delta(FieldChange::Locked {
+                old: false,
+                new: true,
+            }),
+        ])
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(written).unwrap(),
+            "\
+            {\"client\":2,\"field\":\"held\",\"old\":\"1.5000\",\"new\":\"0.0000\",\"tx\":1,\"type\":\"chargeback\",\"row\":4}\n\
+            {\"client\":2,\"field\":\"total\",\"old\":\"1.5000\",\"new\":\"0.0000\",\"tx\":1,\"type\":\"chargeback\",\"row\":4}\n\
+            {\"client\":2,\"field\":\"locked\",\"old\":false,\"new\":true,\"tx\":1,\"type\":\"chargeback\",\"row\":4}\n"
+        );
+    }
+}
@@ -0,0 +1,313 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use async_trait::async_trait;
+use csv::WriterBuilder;
+use serde::{Serialize, Serializer};
+use thiserror::Error;
+
+use crate::{
+    account::{AccountStatus, AccountStore, SuccessStatus},
+    model::{ClientId, Transaction, TransactionKind},
+    transaction_processor::{TransactionProcessor, TransactionProcessorError},
+};
+
+/// What became of the transactions of a client.
+#[derive(Debug, PartialEq, Clone, Default, Serialize)]
+pub struct ClientStats {
+    #[serde(rename = "client")]
+    pub client_id: ClientId,
+    pub deposits: usize,
+    pub withdrawals: usize,
+    pub disputes: usize,
+    pub resolves: usize,
+    pub chargebacks: usize,
+    pub representments: usize,
+    pub closures: usize,
+    pub adjustments: usize,
+
+    /// The number of transactions let through as already applied.
+    pub duplicates: usize,
+
+    /// The number of transactions let through without getting to the
+    /// account, e.g. skipped by the client filter.
+    pub no_ops: usize,
+
+    /// The number of rejected transactions by reason, written as e.g.
+    /// `Deposit(AccountLocked)=2;Dispute(NoTransactionFound)=1`.
+    #[serde(serialize_with = "serialize_rejections")]
+    pub rejections: BTreeMap<String, usize>,
+
+    /// The number of deposits disputed but neither resolved nor charged back
+    /// at the end.
+    pub open_disputes: usize,
+    pub locked: bool,
+}
+
+#[derive(Debug, Error)]
+pub enum ClientStatsWriterError {
+    #[error("Failed to serialise the ClientStats: {0}")]
+    SerialisationError(String),
+}
+
+/// Writes the stats as CSV, one row per client.
+pub struct ClientStatsCsvWriter;
+
+impl ClientStatsCsvWriter {
+    pub fn write(stats: Vec<ClientStats>) -> Result<Vec<u8>, ClientStatsWriterError> {
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        for client_stats in stats {
+            wtr.serialize(client_stats)
+                .map_err(|err| ClientStatsWriterError::SerialisationError(err.to_string()))?;
+        }
+        wtr.into_inner()
+            .map_err(|err| ClientStatsWriterError::SerialisationError(err.to_string()))
+    }
+}
+
+fn serialize_rejections<S: Serializer>(
+    rejections: &BTreeMap<String, usize>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(
+        &rejections
+            .iter()
+            .map(|(reason, count)| format!("{reason}={count}"))
+            .collect::<Vec<String>>()
+            .join(";"),
+    )
+}
+
+/// The stats collected so far, along with what the accounts now say of
+/// their disputes and locking.
+pub(super) fn client_stats(
+    stats: &Mutex<BTreeMap<ClientId, ClientStats>>,
+    accounts: &AccountStore,
+) -> Vec<ClientStats> {
+    let mut stats = stats
+        .lock()
+        .expect("The stats are never locked across a panic.")
+        .clone();
+    for (client_id, open_disputes, locked) in accounts.map(|account| {
+        (
+            account.client_id,
+            account.open_disputes(),
+            account.status == AccountStatus::Locked,
+        )
+    }) {
+        if let Some(client_stats) = stats.get_mut(&client_id) {
+            client_stats.open_disputes = open_disputes;
+            client_stats.locked = locked;
+        }
+    }
+    stats.into_values().collect()
+}
+
+/// Counts the transactions of each client on their way to the processor it
+/// wraps, and the rejections on their way back.
+pub(super) struct StatsCollector {
+    inner: Arc<dyn TransactionProcessor + Send + Sync>,
+    stats: Arc<Mutex<BTreeMap<ClientId, ClientStats>>>,
+}
+
+#[async_trait]
+impl TransactionProcessor for StatsCollector {
+    async fn process(
+        &self,
+        transaction: Transaction,
+    ) -> Result<SuccessStatus, TransactionProcessorError> {
+        let client_id = transaction.client_id;
+        {
+            let mut stats = self.lock();
+            let client_stats = stats.entry(client_id).or_insert_with(|| ClientStats {
+                client_id,
+                ..Default::default()
+            });
+            *match transaction.kind {
+                TransactionKind::Deposit { .. } => &mut client_stats.deposits,
+                TransactionKind::Withdrawal { .. } => &mut client_stats.withdrawals,
+                TransactionKind::Dispute => &mut client_stats.disputes,
+                TransactionKind::Resolve => &mut client_stats.resolves,
+                TransactionKind::ChargeBack => &mut client_stats.chargebacks,
+                TransactionKind::Representment => &mut client_stats.representments,
+                TransactionKind::Close => &mut client_stats.closures,
+                TransactionKind::Adjustment { .. } => &mut client_stats.adjustments,
+            } += 1;
+        }
+        let result = self.inner.process(transaction).await;
+        let mut stats = self.lock();
+        let client_stats = stats
+            .get_mut(&client_id)
+            .expect("The client is counted before its transaction is processed.");
+        match result {
+            Ok(SuccessStatus::Duplicate) => client_stats.duplicates += 1,
+            Ok(SuccessStatus::NoOp) => client_stats.no_ops += 1,
+            Err(TransactionProcessorError::AccountTransactionError(_, ref reason)) => {
+                *client_stats
+                    .rejections
+                    .entry(format!("{reason:?}"))
+                    .or_default() += 1
+            }
+            _ => {}
+        }
+        result
+    }
+}
+
+impl StatsCollector {
+    pub(super) fn new(
+        inner: Arc<dyn TransactionProcessor + Send + Sync>,
+        stats: Arc<Mutex<BTreeMap<ClientId, ClientStats>>>,
+    ) -> Self {
+        Self { inner, stats }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<ClientId, ClientStats>> {
+        self.stats
+            .lock()
+            .expect("The stats are never locked across a panic.")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        sync::{Arc, Mutex},
+    };
+
+    use async_trait::async_trait;
+
+    use crate::{
+        account::{account_transactor::AccountTransactorError, DisputerError, SuccessStatus},
+        model::{Amount, Transaction, TransactionKind},
+        transaction_processor::{TransactionProcessor, TransactionProcessorError},
+    };
+
+    use super::{ClientStats, ClientStatsCsvWriter, StatsCollector};
+
+    /// Rejects every dispute, takes every withdrawal as a duplicate and skips
+    /// every resolve.
+    struct Picky;
+
+    #[async_trait]
+    impl TransactionProcessor for Picky {
+        async fn process(
+            &self,
+            transaction: Transaction,
+        ) -> Result<SuccessStatus, TransactionProcessorError> {
+            match transaction.kind {
+                TransactionKind::Dispute => {
+                    Err(TransactionProcessorError::AccountTransactionError(
+                        Box::new(transaction),
+                        AccountTransactorError::Dispute(DisputerError::NoTransactionFound),
+                    ))
+                }
+                TransactionKind::Withdrawal { .. } => Ok(SuccessStatus::Duplicate),
+                TransactionKind::Resolve => Ok(SuccessStatus::NoOp),
+                _ => Ok(SuccessStatus::Transacted),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn transactions_and_their_outcomes_are_counted() {
+        let stats = Arc::new(Mutex::new(BTreeMap::new()));
+        let collector = StatsCollector::new(Arc::new(Picky), stats.clone());
+        for (client_id, kind) in [
+            (1, deposit()),
+            (1, deposit()),
+            (1, TransactionKind::Dispute),
+            (1, withdrawal()),
+            (2, TransactionKind::Dispute),
+            (2, TransactionKind::Dispute),
+            (2, TransactionKind::Resolve),
+        ] {
+            let _ = collector.process(transaction(client_id, kind)).await;
+        }
+        assert_eq!(
+            *stats.lock().unwrap(),
+            BTreeMap::from([
+                (
+                    1,
+                    ClientStats {
+                        client_id: 1,
+                        deposits: 2,
+                        withdrawals: 1,
+                        disputes: 1,
+                        duplicates: 1,
+                        rejections: BTreeMap::from([(
+                            "Dispute(NoTransactionFound)".to_string(),
+                            1
+                        )]),
+                        ..Default::default()
+                    }
+                ),
+                (
+                    2,
+                    ClientStats {
+                        client_id: 2,
+                        disputes: 2,
+                        resolves: 1,
+                        no_ops: 1,
+                        rejections: BTreeMap::from([(
+                            "Dispute(NoTransactionFound)".to_string(),
+                            2
+                        )]),
+                        ..Default::default()
+                    }
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn stats_can_be_written_as_csv() {
+        let stats = vec![ClientStats {
+            client_id: 1,
+            deposits: 3,
+            withdrawals: 1,
+            disputes: 2,
+            chargebacks: 1,
+            duplicates: 1,
+            rejections: BTreeMap::from([
+                ("Deposit(AccountLocked)".to_string(), 2),
+                ("Dispute(NoTransactionFound)".to_string(), 1),
+            ]),
+            open_disputes: 1,
+            locked: true,
+            ..Default::default()
+        }];
+        assert_eq!(
+            String::from_utf8(ClientStatsCsvWriter::write(stats).unwrap()).unwrap(),
+            "\
+            client,deposits,withdrawals,disputes,resolves,chargebacks,representments,closures,adjustments,duplicates,no_ops,rejections,open_disputes,locked\n\
+            1,3,1,2,0,1,0,0,0,1,0,Deposit(AccountLocked)=2;Dispute(NoTransactionFound)=1,1,true\n"
+        );
+    }
+
+    fn deposit() -> TransactionKind {
+        TransactionKind::Deposit {
+            amount: Amount::new(10_000),
+        }
+    }
+
+    fn withdrawal() -> TransactionKind {
+        TransactionKind::Withdrawal {
+            amount: Amount::new(10_000),
+        }
+    }
+
+    fn transaction(client_id: u16, kind: TransactionKind) -> Transaction {
+        Transaction {
+            client_id,
+            transaction_id: 1,
+            kind,
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
+        }
+    }
+}
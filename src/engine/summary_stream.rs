@@ -0,0 +1,154 @@
+use std::sync::Arc;
+
+use futures_util::{SinkExt, StreamExt};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::broadcast::{self, error::RecvError},
+    task::JoinSet,
+    time::timeout,
+};
+use tokio_tungstenite::{accept_async, tungstenite::Message, WebSocketStream};
+
+use crate::{
+    account::AccountStore, model::AccountSummary,
+    transaction_stream_processor::TransactionStreamProcessError,
+};
+
+use super::{listener::CANCELLATION_CHECK, Engine};
+
+impl Engine {
+    /// Pushes the summaries of the accounts over the WebSocket connections
+    /// accepted by the listener as they change, e.g. for a dashboard to show
+    /// the balances live while the input is processed.
+    /// Each summary is a JSON text message, e.g.
+    /// `{"client":1,"available":"1.5000","held":"0.0000","total":"1.5000","locked":false}`.
+    /// A connection is first sent the summaries of all the accounts as they
+    /// stand, and then each of them as it changes, or all of them again if
+    /// it fell too far behind to be sent the changes it missed.
+    /// Serves until the engine is cancelled, the connections still open then
+    /// being closed.
+    /// Only with the `websocket` feature.
+    pub async fn serve_summaries(
+        &self,
+        listener: TcpListener,
+    ) -> Result<(), TransactionStreamProcessError> {
+        let mut connections = JoinSet::new();
+        while !self.cancellation.is_cancelled() {
+            // so that the cancellation is noticed while waiting for a
+            // connection
+            let Ok(accepted) = timeout(CANCELLATION_CHECK, listener.accept()).await else {
+                continue;
+            };
+            let (stream, _) = accepted
+                .map_err(|err| TransactionStreamProcessError::InternalError(err.to_string()))?;
+            connections.spawn(push(stream, self.accounts.clone(), self.summary_updates()));
+        }
+        connections.shutdown().await;
+        Ok(())
+    }
+}
+
+/// `updates` being subscribed to before the accounts are first summarised,
+/// so that no change falls in between.
+async fn push(
+    stream: TcpStream,
+    accounts: Arc<AccountStore>,
+    mut updates: broadcast::Receiver<AccountSummary>,
+) {
+    let Ok(mut ws) = accept_async(stream).await else {
+        return;
+    };
+    if send_all(&mut ws, accounts.summaries()).await.is_err() {
+        return;
+    }
+    loop {
+        let summaries = tokio::select! {
+            update = updates.recv() => match update {
+                Ok(summary) => vec![summary],
+                // the changes missed are made up for by the accounts'
+                // summaries as they now stand, some of them unchanged
+                Err(RecvError::Lagged(_)) => {
+                    updates = updates.resubscribe();
+                    accounts.summaries()
+                }
+                Err(RecvError::Closed) => return,
+            },
+            message = ws.next() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                // anything sent by the other end is ignored
+                Some(Ok(_)) => continue,
+            },
+        };
+        if send_all(&mut ws, summaries).await.is_err() {
+            return;
+        }
+    }
+}
+
+async fn send_all(
+    ws: &mut WebSocketStream<TcpStream>,
+    summaries: Vec<AccountSummary>,
+) -> Result<(), tokio_tungstenite::tungstenite::Error> {
+    for summary in summaries {
+        let json = serde_json::to_string(&summary)
+            .expect("An AccountSummary is always serialisable to JSON.");
+        ws.feed(Message::Text(json)).await?;
+    }
+    ws.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures_util::StreamExt;
+    use tokio::{
+        net::{TcpListener, TcpStream},
+        time::timeout,
+    };
+    use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+    use crate::{
+        engine::{Engine, EngineConfig},
+        model::{AccountSummary, Amount},
+    };
+
+    async fn next(ws: &mut WebSocketStream<MaybeTlsStream<TcpStream>>) -> AccountSummary {
+        let message = timeout(Duration::from_secs(5), ws.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        serde_json::from_str(message.to_text().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn summaries_are_pushed_as_they_change() {
+        let engine = Engine::new(EngineConfig::default());
+        engine
+            .process("type, client, tx, amount\ndeposit, 1, 1, 1.0".as_bytes())
+            .await
+            .unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let client = async {
+            let (mut ws, _) = connect_async(format!("ws://{address}")).await.unwrap();
+            // the accounts as they stand
+            let first = next(&mut ws).await;
+            engine
+                .process("type, client, tx, amount\ndeposit, 2, 2, 2.0".as_bytes())
+                .await
+                .unwrap();
+            let second = next(&mut ws).await;
+            engine.cancellation().cancel();
+            (first, second)
+        };
+        let (served, (first, second)) = tokio::join!(engine.serve_summaries(listener), client);
+        served.unwrap();
+        assert_eq!((first.client_id, first.available), (1, Amount::new(10_000)));
+        assert_eq!(
+            (second.client_id, second.available),
+            (2, Amount::new(20_000))
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::broadcast;
+
+use crate::{
+    account::{AccountStore, SuccessStatus},
+    model::{AccountSummary, Transaction},
+    transaction_processor::{TransactionProcessor, TransactionProcessorError},
+};
+
+/// Sends the summary of a client's account each time a transaction changes
+/// it, on its way back from the processor it wraps.
+/// Nothing is summarised while no one is listening.
+pub(super) struct SummaryPublisher {
+    inner: Arc<dyn TransactionProcessor + Send + Sync>,
+    accounts: Arc<AccountStore>,
+    updates: broadcast::Sender<AccountSummary>,
+}
+
+#[async_trait]
+impl TransactionProcessor for SummaryPublisher {
+    async fn process(
+        &self,
+        transaction: Transaction,
+    ) -> Result<SuccessStatus, TransactionProcessorError> {
+        let client_id = transaction.client_id;
+        let result = self.inner.process(transaction).await;
+        if let Ok(SuccessStatus::Transacted) = result {
+            if self.updates.receiver_count() > 0 {
+                if let Some(summary) = self.accounts.summary(client_id) {
+                    // no one may be listening any more
+                    let _ = self.updates.send(summary);
+                }
+            }
+        }
+        result
+    }
+}
+
+impl SummaryPublisher {
+    pub(super) fn new(
+        inner: Arc<dyn TransactionProcessor + Send + Sync>,
+        accounts: Arc<AccountStore>,
+        updates: broadcast::Sender<AccountSummary>,
+    ) -> Self {
+        Self {
+            inner,
+            accounts,
+            updates,
+        }
+    }
+}
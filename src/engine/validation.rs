@@ -0,0 +1,99 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+};
+
+use crate::{model::ClientId, transaction_processor::Rejection};
+
+/// The outcome of a dry run: what would go wrong if the input was processed
+/// for real.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ValidationReport {
+    /// The transactions that would be rejected, ordered by client and then as
+    /// they are in the input.
+    pub rejections: Vec<Rejection>,
+
+    /// The clients whose accounts would end up locked, in ascending order.
+    pub locked_clients: Vec<ClientId>,
+
+    /// The number of transactions of each client the client filter would
+    /// skip.
+    pub skipped: BTreeMap<ClientId, usize>,
+}
+
+impl ValidationReport {
+    /// Whether the input would be processed without any rejection or locked
+    /// account.
+    pub fn is_clean(&self) -> bool {
+        self.rejections.is_empty() && self.locked_clients.is_empty()
+    }
+}
+
+impl Display for ValidationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for Rejection {
+            transaction,
+            reason,
+        } in &self.rejections
+        {
+            writeln!(f, "rejected: {}: {reason}", transaction.described())?;
+        }
+        for client_id in &self.locked_clients {
+            writeln!(f, "locked: client {client_id}")?;
+        }
+        for (client_id, count) in &self.skipped {
+            writeln!(f, "skipped: client {client_id}, {count} transaction(s)")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{
+        account::{account_transactor::AccountTransactorError, WithdrawerError},
+        model::{Amount, Transaction, TransactionKind},
+        transaction_processor::Rejection,
+    };
+
+    use super::ValidationReport;
+
+    #[test]
+    fn validation_report_can_be_displayed() {
+        let report = ValidationReport {
+            rejections: vec![Rejection {
+                transaction: Transaction {
+                    client_id: 2,
+                    transaction_id: 5,
+                    kind: TransactionKind::Withdrawal {
+                        amount: Amount::new(10_000),
+                    },
+                    timestamp: None,
+                    memo: None,
+                    ingest_sequence: Some(6),
+                },
+                reason: AccountTransactorError::Withdrawal(WithdrawerError::InsufficientFund),
+            }],
+            locked_clients: vec![3, 4],
+            skipped: BTreeMap::from([(7, 2)]),
+        };
+        assert!(!report.is_clean());
+        assert_eq!(
+            report.to_string(),
+            "\
+            rejected: row 6, client 2, tx 5 (withdrawal): Insufficient fund for withdrawal\n\
+            locked: client 3\n\
+            locked: client 4\n\
+            skipped: client 7, 2 transaction(s)\n"
+        );
+    }
+
+    #[test]
+    fn empty_report_is_clean() {
+        let report = ValidationReport::default();
+        assert!(report.is_clean());
+        assert_eq!(report.to_string(), "");
+    }
+}
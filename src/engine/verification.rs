@@ -0,0 +1,228 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+
+use crate::{
+    account::Account,
+    model::{AccountSummary, ClientId, Transaction},
+    transaction_processor::TransactionProcessor,
+    transaction_stream_processor::{
+        pipeline::{ClientRouter, Router},
+        InFlight, QueuedChannel, TransactionStreamProcessError,
+    },
+};
+
+/// The account of a client that the sequential and the concurrent pipelines
+/// left in different states, absent on the side that never saw the client.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Divergence {
+    pub client_id: ClientId,
+    pub sequential: Option<Account>,
+    pub concurrent: Option<Account>,
+}
+
+/// The outcome of running one input through both the sequential and the
+/// concurrent pipelines.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct VerificationReport {
+    /// The number of accounts compared.
+    pub accounts: usize,
+
+    /// The accounts that differ, in ascending order of client.
+    pub divergences: Vec<Divergence>,
+}
+
+impl VerificationReport {
+    pub(super) fn compare(
+        mut sequential: BTreeMap<ClientId, Account>,
+        concurrent: BTreeMap<ClientId, Account>,
+    ) -> Self {
+        let mut divergences = BTreeMap::new();
+        let mut accounts = sequential.len();
+        for (client_id, concurrent) in concurrent {
+            let sequential = sequential.remove(&client_id);
+            if sequential.is_none() {
+                accounts += 1;
+            }
+            if sequential.as_ref() != Some(&concurrent) {
+                divergences.insert(
+                    client_id,
+                    Divergence {
+                        client_id,
+                        sequential,
+                        concurrent: Some(concurrent),
+                    },
+                );
+            }
+        }
+        for (client_id, sequential) in sequential {
+            divergences.insert(
+                client_id,
+                Divergence {
+                    client_id,
+                    sequential: Some(sequential),
+                    concurrent: None,
+                },
+            );
+        }
+        Self {
+            accounts,
+            divergences: divergences.into_values().collect(),
+        }
+    }
+
+    /// Whether both pipelines left all the accounts in the same state.
+    pub fn is_consistent(&self) -> bool {
+        self.divergences.is_empty()
+    }
+}
+
+impl Display for VerificationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for Divergence {
+            client_id,
+            sequential,
+            concurrent,
+        } in &self.divergences
+        {
+            writeln!(
+                f,
+                "diverged: client {client_id}, sequential {}, concurrent {}",
+                Balances(sequential.as_ref()),
+                Balances(concurrent.as_ref())
+            )?;
+        }
+        writeln!(
+            f,
+            "{} of {} account(s) diverged",
+            self.divergences.len(),
+            self.accounts
+        )
+    }
+}
+
+/// Processes each transaction on the sequential pipeline, in the order it is
+/// routed in, before routing it to the worker of its client, so that both
+/// pipelines of [`Engine::verify`] are given the input as it is read.
+///
+/// [`Engine::verify`]: super::Engine::verify
+pub(super) struct MirroringRouter {
+    sequential: Arc<dyn TransactionProcessor + Send + Sync>,
+    concurrent: ClientRouter,
+}
+
+#[async_trait]
+impl Router for MirroringRouter {
+    async fn route(
+        &self,
+        transaction: Transaction,
+        in_flight: Option<InFlight>,
+    ) -> Result<(), TransactionStreamProcessError> {
+        self.sequential
+            .process(transaction.clone())
+            .await
+            .map_err(TransactionStreamProcessError::ProcessError)?;
+        self.concurrent.route(transaction, in_flight).await
+    }
+
+    fn queued(&self) -> Vec<QueuedChannel> {
+        self.concurrent.queued()
+    }
+
+    async fn shutdown(&self) -> Result<(), TransactionStreamProcessError> {
+        self.concurrent.shutdown().await
+    }
+}
+
+impl MirroringRouter {
+    pub(super) fn new(
+        sequential: Arc<dyn TransactionProcessor + Send + Sync>,
+        concurrent: ClientRouter,
+    ) -> Self {
+        Self {
+            sequential,
+            concurrent,
+        }
+    }
+}
+
+/// The balances of an account, which may still be the same on both sides
+/// when what diverged is e.g. the transactions kept for disputes.
+struct Balances<'a>(Option<&'a Account>);
+
+impl Display for Balances<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.0.map(AccountSummary::from) {
+            Some(summary) => write!(
+                f,
+                "available={},held={},total={},locked={}",
+                summary.available.to_str(),
+                summary.held.to_str(),
+                summary.total.to_str(),
+                summary.locked
+            ),
+            None => write!(f, "missing"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{
+        account::{Account, AccountSnapshot},
+        model::Amount,
+    };
+
+    use super::{Divergence, VerificationReport};
+
+    fn account(client_id: u16, deposit: i64) -> Account {
+        let mut account = Account::active(client_id);
+        account.record_deposit(1, Amount::new(deposit), None);
+        *account.snapshot_mut() = AccountSnapshot::new(deposit, 0);
+        account
+    }
+
+    #[test]
+    fn differing_and_missing_accounts_are_reported() {
+        let sequential = BTreeMap::from([(1, account(1, 10_000)), (2, account(2, 10_000))]);
+        let concurrent = BTreeMap::from([(1, account(1, 10_000)), (3, account(3, 10_000))]);
+        let report = VerificationReport::compare(sequential, concurrent);
+        assert_eq!(
+            report.divergences,
+            vec![
+                Divergence {
+                    client_id: 2,
+                    sequential: Some(account(2, 10_000)),
+                    concurrent: None,
+                },
+                Divergence {
+                    client_id: 3,
+                    sequential: None,
+                    concurrent: Some(account(3, 10_000)),
+                },
+            ]
+        );
+        assert_eq!(report.accounts, 3);
+        assert!(!report.is_consistent());
+    }
+
+    #[test]
+    fn divergences_are_listed_with_their_balances() {
+        let sequential = BTreeMap::from([(1, account(1, 10_000))]);
+        let concurrent = BTreeMap::from([(1, account(1, 25_000))]);
+        assert_eq!(
+            VerificationReport::compare(sequential, concurrent).to_string(),
+            "\
+            diverged: client 1, \
+            sequential available=1.0000,held=0.0000,total=1.0000,locked=false, \
+            concurrent available=2.5000,held=0.0000,total=2.5000,locked=false\n\
+            1 of 1 account(s) diverged\n"
+        );
+    }
+}
@@ -0,0 +1,184 @@
+//! A C API for embedding the account state machine, built with the `cdylib`
+//! feature, e.g. for a C++ batch system to link it rather than running the
+//! binary. See `include/jouet_paiement.h`.
+//! The transactions are applied one at a time on the calling thread, an
+//! engine being used by one thread at a time. Like with the WASM wrapper, a
+//! rejected transaction is left out of the accounts without stopping anything.
+
+use std::{
+    ffi::{c_char, CStr},
+    ptr,
+};
+
+use crate::{
+    account::{AccountBook, SuccessStatus},
+    model::{
+        AccountSummary, AccountSummaryCsvWriter, ClientId, Transaction, TransactionId,
+        TransactionKind,
+    },
+};
+
+/// The accounts of the clients, behind an opaque pointer.
+pub struct JpEngine {
+    accounts: AccountBook,
+}
+
+/// What became of a pushed transaction.
+#[repr(C)]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum JpStatus {
+    Transacted = 0,
+    Duplicate = 1,
+    NoOp = 2,
+    /// The transaction was left out of the account, e.g. for want of funds.
+    Rejected = 3,
+    /// The transaction could not be read, e.g. of an unknown type.
+    Invalid = -1,
+}
+
+/// Bytes owned by the library, to be given back with [`jp_buffer_free`].
+#[repr(C)]
+pub struct JpBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+/// A new engine with no accounts, to be destroyed with [`jp_engine_free`].
+#[no_mangle]
+pub extern "C" fn jp_engine_new() -> *mut JpEngine {
+    Box::into_raw(Box::new(JpEngine {
+        accounts: AccountBook::default(),
+    }))
+}
+
+/// Applies the transaction of type `kind`, e.g. `deposit`, the amount being
+/// e.g. `1.5`, and only read for a deposit or a withdrawal.
+///
+/// # Safety
+///
+/// `engine` comes from [`jp_engine_new`] and is not used by another thread
+/// meanwhile. `kind` is a NUL-terminated string, and so is `amount` unless
+/// it is null.
+#[no_mangle]
+pub unsafe extern "C" fn jp_engine_push(
+    engine: *mut JpEngine,
+    kind: *const c_char,
+    client: ClientId,
+    tx: TransactionId,
+    amount: *const c_char,
+) -> JpStatus {
+    let engine = &mut *engine;
+    let (Ok(kind), Ok(amount)) = (
+        CStr::from_ptr(kind).to_str(),
+        (!amount.is_null())
+            .then(|| CStr::from_ptr(amount).to_str())
+            .transpose(),
+    ) else {
+        return JpStatus::Invalid;
+    };
+    let Ok(kind) = TransactionKind::from_name(kind, amount) else {
+        return JpStatus::Invalid;
+    };
+    match engine.accounts.apply(Transaction {
+        client_id: client,
+        transaction_id: tx,
+        kind,
+        timestamp: None,
+        memo: None,
+        ingest_sequence: None,
+    }) {
+        Ok(SuccessStatus::Transacted) => JpStatus::Transacted,
+        Ok(SuccessStatus::Duplicate) => JpStatus::Duplicate,
+        Ok(SuccessStatus::NoOp) => JpStatus::NoOp,
+        Err(_) => JpStatus::Rejected,
+    }
+}
+
+/// The summaries of the accounts as CSV, ordered by client, not
+/// NUL-terminated.
+///
+/// # Safety
+///
+/// `engine` comes from [`jp_engine_new`] and is not used by another thread
+/// meanwhile.
+#[no_mangle]
+pub unsafe extern "C" fn jp_engine_summaries(engine: *const JpEngine) -> JpBuffer {
+    let engine = &*engine;
+    let summaries = engine
+        .accounts
+        .accounts()
+        .map(AccountSummary::from)
+        .collect();
+    let bytes = AccountSummaryCsvWriter::write(summaries)
+        .expect("A summary is always serialisable.")
+        .into_boxed_slice();
+    let len = bytes.len();
+    JpBuffer {
+        data: Box::into_raw(bytes).cast(),
+        len,
+    }
+}
+
+/// # Safety
+///
+/// `buffer` comes from [`jp_engine_summaries`], and is not freed twice.
+#[no_mangle]
+pub unsafe extern "C" fn jp_buffer_free(buffer: JpBuffer) {
+    if !buffer.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            buffer.data,
+            buffer.len,
+        )));
+    }
+}
+
+/// # Safety
+///
+/// `engine` comes from [`jp_engine_new`], is not used afterwards and is not
+/// freed twice. A null one is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn jp_engine_free(engine: *mut JpEngine) {
+    if !engine.is_null() {
+        drop(Box::from_raw(engine));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ptr, slice};
+
+    use super::{
+        jp_buffer_free, jp_engine_free, jp_engine_new, jp_engine_push, jp_engine_summaries,
+        JpStatus,
+    };
+
+    #[test]
+    fn transactions_pushed_make_up_the_summaries() {
+        unsafe {
+            let engine = jp_engine_new();
+            assert_eq!(
+                jp_engine_push(engine, c"deposit".as_ptr(), 1, 1, c"2.5".as_ptr()),
+                JpStatus::Transacted
+            );
+            assert_eq!(
+                jp_engine_push(engine, c"withdrawal".as_ptr(), 1, 2, c"5".as_ptr()),
+                JpStatus::Rejected
+            );
+            assert_eq!(
+                jp_engine_push(engine, c"dispute".as_ptr(), 1, 1, ptr::null()),
+                JpStatus::Transacted
+            );
+            assert_eq!(
+                jp_engine_push(engine, c"deposit".as_ptr(), 2, 3, ptr::null()),
+                JpStatus::Invalid
+            );
+            let summaries = jp_engine_summaries(engine);
+            assert_eq!(
+                slice::from_raw_parts(summaries.data, summaries.len),
+                b"client,available,held,total,locked\n1,0.0000,2.5000,2.5000,false\n"
+            );
+            jp_buffer_free(summaries);
+            jp_engine_free(engine);
+        }
+    }
+}
@@ -1,7 +1,31 @@
-#[cfg(test)]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(all(test, feature = "engine"))]
+#[allow(clippy::single_component_path_imports)]
 use rstest_reuse;
 
 pub mod account;
+pub(crate) mod collections;
+#[cfg(feature = "engine")]
+pub mod engine;
+#[cfg(feature = "cdylib")]
+pub mod ffi;
+#[cfg(feature = "engine")]
+pub mod merge;
 pub mod model;
+#[cfg(feature = "engine")]
+pub mod output;
+#[cfg(feature = "engine")]
+pub mod reconciliation;
+#[cfg(feature = "std")]
+pub mod risk;
+#[cfg(feature = "engine")]
 pub mod transaction_processor;
+#[cfg(feature = "engine")]
 pub mod transaction_stream_processor;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "server")]
+pub mod watch;
@@ -1,55 +1,804 @@
 use std::{
+    collections::BTreeMap,
     env,
+    fmt::Display,
     fs::File,
-    io::{BufReader, Read},
-    sync::Arc,
+    future::Future,
+    io::BufReader,
+    path::Path,
+    process,
+    str::FromStr,
+    time::{Duration, SystemTime},
 };
 
-use dashmap::DashMap;
-
-use crate::{
-    account::SimpleAccountTransactor,
-    model::{AccountSummary, AccountSummaryCsvWriter},
-    transaction_processor::SimpleTransactionProcessor,
-    transaction_stream_processor::{
-        async_csv_stream_processor::AsyncCsvStreamProcessor, TransactionStreamProcessor,
+#[cfg(feature = "kafka")]
+use jouet_paiement::engine::{KafkaSink, KafkaSinkConfig};
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+use jouet_paiement::transaction_stream_processor::UringReader;
+use jouet_paiement::{
+    engine::{
+        ClientStatsCsvWriter, ClosingState, ConfigDigest, ConflictReportWriter,
+        ConvertedSummaryCsvWriter, DisputeReportWriter, Engine, EngineConfig, ErrorRecord,
+        ErrorReportWriter, ExchangeRates, FileDigest, LargeTransactionReportWriter,
+        OpenDisputeReportWriter, OpeningBalance, RunManifest, SnapshotDeltaWriter,
     },
+    merge::{DuplicateClients, SummaryMerger},
+    model::{AccountSummaryCsvWriter, Amount, SummaryFilter, SummaryFooter, SummaryFormat},
+    output::AtomicFileWriter,
+    reconciliation::{DifferenceReportWriter, Reconciler},
+    transaction_processor::{ClientList, ProcessedIds},
+    transaction_stream_processor::{Cancellation, Throughput, TransactionStreamProcessError},
+    watch::{DirectoryWatcher, DEFAULT_POLL_INTERVAL},
+};
+#[cfg(feature = "kafka")]
+use tokio::sync::mpsc;
+use tokio::{
+    net::TcpListener,
+    sync::{broadcast::error::RecvError, oneshot},
+    task::JoinHandle,
 };
-#[cfg(test)]
-use rstest_reuse;
 
-mod account;
-mod model;
-mod transaction_processor;
-mod transaction_stream_processor;
+/// The exit code of a run interrupted by a signal, whose output is partial.
+const INTERRUPTED: i32 = 130;
+
+/// The exit code of a command given arguments it cannot run with.
+const USAGE_ERROR: i32 = 2;
+
+/// How many account events can wait to be published to Kafka before the
+/// processing is held back.
+#[cfg(feature = "kafka")]
+const ACCOUNT_EVENT_CAPACITY: usize = 1024;
+
+/// How often `--progress` writes the throughput of the run.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The threshold of `--large-transactions` unless configured, 10,000.
+const DEFAULT_LARGE_TRANSACTION_THRESHOLD: Amount = Amount::new(100_000_000);
 
 #[tokio::main]
 async fn main() {
     let args: Vec<String> = env::args().collect();
-    let filename = args.get(1).unwrap();
-    let file = File::open(filename).unwrap();
-    let reader = BufReader::new(file);
-
-    let result = process_file(reader).await;
+    let result = match args.get(1).map(String::as_str) {
+        Some("reconcile") => reconcile(&args[2..]),
+        Some("merge") => merge(&args[2..]),
+        Some("replay") => replay(&args[2..]).await,
+        Some("validate") => validate(&args[2..]).await,
+        Some("verify") => verify(&args[2..]).await,
+        Some("open-disputes") => open_disputes(&args[2..]).await,
+        Some("watch") => watch(&args[2..]).await,
+        Some("listen") => listen(&args[2..]).await,
+        _ => {
+            let filename = args.get(1).unwrap();
+            let option = |name: &str| {
+                let position = args[2..].iter().position(|arg| arg == name)?;
+                args.get(2 + position + 1)
+            };
+            // the flags take precedence over the environment variables, which
+            // take precedence over the config file
+            let mut config = match option("--config") {
+                Some(path) => EngineConfig::from_file(path).unwrap(),
+                None => EngineConfig::default(),
+            }
+            .with_env()
+            .unwrap();
+            // the manifest counts the transactions going by the stats
+            config.collect_stats |= option("--stats").is_some()
+                || option("--disputes").is_some()
+                || option("--manifest").is_some();
+            config.quarantine_unknown_types |= option("--quarantine").is_some();
+            config.quarantine_conflicts |= option("--conflicts").is_some();
+            config.collect_deltas |= option("--deltas").is_some();
+            if option("--large-transactions").is_some() {
+                config
+                    .large_transaction_threshold
+                    .get_or_insert(DEFAULT_LARGE_TRANSACTION_THRESHOLD);
+            }
+            config.deterministic |= args.iter().any(|arg| arg == "--deterministic");
+            if let Some(shard) = option("--shard") {
+                config.shard = Some(parse_arg("--shard", shard));
+            }
+            if let Some(byte_ranges) = option("--byte-ranges") {
+                config.byte_ranges = Some(parse_arg("--byte-ranges", byte_ranges));
+            }
+            if let Some(records_per_second) = option("--max-records-per-second") {
+                config.max_records_per_second =
+                    Some(parse_arg("--max-records-per-second", records_per_second));
+            }
+            if let Some(seconds) = option("--stall-timeout") {
+                config.stall_timeout =
+                    Some(Duration::from_secs(parse_arg("--stall-timeout", seconds)));
+            }
+            let format = SummaryFormat {
+                decimal_places: option("--decimal-places")
+                    .map(|places| parse_arg("--decimal-places", places)),
+                trailing_zeros: !args.iter().any(|arg| arg == "--no-trailing-zeros"),
+                thousands_separator: args.iter().any(|arg| arg == "--thousands-separator"),
+            };
+            let filter = SummaryFilter {
+                locked_only: args.iter().any(|arg| arg == "--locked-only"),
+                held_only: args.iter().any(|arg| arg == "--held-only"),
+                clients: option("--clients").map(|path| {
+                    ClientList::read_ids(open_arg(path))
+                        .unwrap_or_else(|err| usage_error(err))
+                        .into_iter()
+                        .collect()
+                }),
+            };
+            if args.iter().any(|arg| arg == "--totals") && option("--rates").is_some() {
+                usage_error("--totals cannot be combined with --rates");
+            }
+            let conversion = option("--rates").map(|path| {
+                let reporting_currency = option("--report-currency")
+                    .unwrap_or_else(|| usage_error("--rates requires --report-currency"));
+                let currency = option("--currency")
+                    .unwrap_or_else(|| usage_error("--rates requires --currency"));
+                let rates = ExchangeRates::read(reporting_currency, open_arg(path))
+                    .unwrap_or_else(|err| usage_error(err));
+                (rates, currency)
+            });
+            let writer = AtomicFileWriter::new()
+                .with_done_marker(args.iter().any(|arg| arg == "--done-marker"));
+            let manifest = option("--manifest").map(|path| {
+                // the flags that change what the engine makes of the input
+                let mut flags = BTreeMap::new();
+                if option("--quarantine").is_some() {
+                    flags.insert("quarantine".to_string(), "true".to_string());
+                }
+                if option("--conflicts").is_some() {
+                    flags.insert("conflicts".to_string(), "true".to_string());
+                }
+                if args.iter().any(|arg| arg == "--deterministic") {
+                    flags.insert("deterministic".to_string(), "true".to_string());
+                }
+                if let Some(shard) = option("--shard") {
+                    flags.insert("shard".to_string(), shard.clone());
+                }
+                let config =
+                    ConfigDigest::new(option("--config").map(Path::new), env::vars(), flags)
+                        .unwrap();
+                (path, filename, config)
+            });
+            let json_errors = match option("--errors-format").map(String::as_str) {
+                Some("json") => Some(option("--errors")),
+                Some("text") | None => None,
+                Some(format) => usage_error(format!(
+                    "Unknown errors format `{format}`, expected `json` or `text`"
+                )),
+            };
+            let opening = Opening {
+                balances: option("--opening-balances").map(|path| {
+                    OpeningBalance::read(open_arg(path)).unwrap_or_else(|err| usage_error(err))
+                }),
+                state: option("--opening-state").map(|path| {
+                    ClosingState::read(open_arg(path)).unwrap_or_else(|err| usage_error(err))
+                }),
+            };
+            let summaries = SummaryOptions {
+                format,
+                filter,
+                conversion,
+                totals: args.iter().any(|arg| arg == "--totals"),
+                footer: args.iter().any(|arg| arg == "--footer"),
+            };
+            let outputs = Outputs {
+                stats: option("--stats"),
+                disputes: option("--disputes"),
+                quarantine: option("--quarantine"),
+                conflicts: option("--conflicts"),
+                large_transactions: option("--large-transactions"),
+                deltas: option("--deltas"),
+                summaries: option("--output"),
+                closing_state: option("--emit-closing-state"),
+                manifest,
+                json_errors,
+            };
+            process_file(
+                filename,
+                config,
+                opening,
+                summaries,
+                outputs,
+                writer,
+                args.iter().any(|arg| arg == "--progress"),
+            )
+            .await
+        }
+    };
     println!("{result}");
 }
 
-async fn process_file(reader: impl Read + Send) -> String {
-    let accounts = Arc::new(DashMap::new());
+/// Writes the message to the standard error and exits, rather than panicking
+/// on arguments the user got wrong.
+fn usage_error(message: impl Display) -> ! {
+    eprintln!("{message}");
+    process::exit(USAGE_ERROR);
+}
 
-    let processor = AsyncCsvStreamProcessor::new(
-        Arc::new(SimpleTransactionProcessor::new(
-            accounts.clone(),
-            Box::new(SimpleAccountTransactor::new()),
-        )),
-        DashMap::new(),
-    );
+/// The value of an argument, exiting with a usage error if it cannot be
+/// parsed.
+fn parse_arg<T: FromStr>(name: &str, value: &str) -> T
+where
+    T::Err: Display,
+{
+    value
+        .parse()
+        .unwrap_or_else(|err| usage_error(format!("Invalid {name} `{value}`: {err}")))
+}
 
-    processor.process(reader).await.unwrap();
-    processor.shutdown().await.unwrap();
-    let summaries: Vec<AccountSummary> = accounts
-        .iter()
-        .map(|entry| AccountSummary::from(entry.value()))
-        .collect();
+/// The positional argument at the index, exiting with a usage error if it
+/// was not given.
+fn positional_arg<'a>(args: &'a [String], index: usize, name: &str) -> &'a str {
+    args.get(index)
+        .unwrap_or_else(|| usage_error(format!("Missing {name}")))
+}
+
+/// Writes the error to the standard error and exits, for a command that
+/// could not do what it was asked to.
+fn fail(err: impl Display) -> ! {
+    eprintln!("{err}");
+    process::exit(1);
+}
+
+/// The file given as an argument, exiting with a usage error if it cannot be
+/// opened.
+fn open_arg(path: &str) -> File {
+    File::open(path).unwrap_or_else(|err| usage_error(format!("Cannot open `{path}`: {err}")))
+}
+
+/// The input to be processed, read with io_uring where it is available.
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+fn open_input(filename: &str) -> Box<dyn std::io::Read + Send> {
+    match UringReader::open(filename) {
+        Ok(reader) => Box::new(reader),
+        Err(_) => Box::new(BufReader::new(File::open(filename).unwrap())),
+    }
+}
+
+#[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+fn open_input(filename: &str) -> BufReader<File> {
+    BufReader::new(File::open(filename).unwrap())
+}
+
+/// The accounts a run starts from, besides those of the input.
+struct Opening {
+    balances: Option<Vec<OpeningBalance>>,
+    state: Option<ClosingState>,
+}
+
+/// How the summaries of a run are written.
+struct SummaryOptions<'a> {
+    format: SummaryFormat,
+    filter: SummaryFilter,
+    /// The rates and the currency the accounts are held in.
+    conversion: Option<(ExchangeRates, &'a String)>,
+    totals: bool,
+    footer: bool,
+}
+
+/// The files a run writes, none of them unless given.
+struct Outputs<'a> {
+    stats: Option<&'a String>,
+    disputes: Option<&'a String>,
+    quarantine: Option<&'a String>,
+    conflicts: Option<&'a String>,
+    large_transactions: Option<&'a String>,
+    deltas: Option<&'a String>,
+    /// The standard output unless given.
+    summaries: Option<&'a String>,
+    closing_state: Option<&'a String>,
+    /// The manifest file, the input file and the config the run went by.
+    manifest: Option<(&'a String, &'a String, ConfigDigest)>,
+    /// The errors file, the standard error if none, with the `json` errors
+    /// format.
+    json_errors: Option<Option<&'a String>>,
+}
+
+/// `<file> [--config <config file>] [--opening-balances <balances file>]
+/// [--opening-state <state file>] [--emit-closing-state <state file>]
+/// [--stats <stats file>] [--disputes <disputes file>]
+/// [--quarantine <quarantine file>] [--conflicts <conflicts file>]
+/// [--large-transactions <large transactions file>] [--deltas <deltas file>]
+/// [--shard <shard>] [--deterministic]
+/// [--byte-ranges <count>] [--max-records-per-second <count>] [--progress]
+/// [--stall-timeout <seconds>]
+/// [--decimal-places <places>] [--no-trailing-zeros] [--thousands-separator]
+/// [--locked-only] [--held-only] [--clients <client ids file>]
+/// [--rates <rates file> --report-currency <code> --currency <code>] [--totals]
+/// [--footer] [--output <summaries file>] [--done-marker] [--manifest <manifest file>]
+/// [--errors-format json|text] [--errors <errors file>]`.
+///
+/// The engine is set up as per the config file, if any, and the `JP_`
+/// environment variables, the flags taking precedence. The accounts are
+/// opened with those of the balances and opening state files, if given.
+/// The run is changed by:
+/// - `--shard`: only the clients of the shard are processed,
+/// - `--deterministic`: the transactions are processed in input order,
+/// - `--byte-ranges`: the file is split into ranges parsed at the same time,
+/// - `--max-records-per-second`: the input is read no faster than that,
+/// - `--progress`: the throughput is written to the standard error every
+///   second,
+/// - `--stall-timeout`: every stall is written to the standard error, along
+///   with the channels holding transactions then.
+///
+/// The summaries, formatted as asked, go to the output file or to the
+/// standard output. They can be limited to the locked accounts, to those
+/// with held funds or to the clients of the client ids file, and come with
+/// either their balances converted into the reporting currency, as per the
+/// rates file, or what each client deposited, withdrew, had charged back and
+/// has disputed still, with `--totals`. With `--footer`, they end with a
+/// [`SummaryFooter`] for the receiver to check they came through whole.
+///
+/// The other files, each written if given:
+/// - the stats of each client,
+/// - the outcomes of the disputes of each client and of all of them,
+/// - the rows of an unknown type, quarantined rather than failing the run,
+/// - the transactions conflicting with a previous one of the same id,
+/// - the deposits and withdrawals above the configured threshold, 10,000 if
+///   none,
+/// - every change to the balances and lock of an account, as NDJSON,
+/// - the closing state: the accounts, their transactions included, for the
+///   next run to start from,
+/// - the manifest of the run, once the summaries are written,
+/// - with the `json` errors format, every error of the run, the rejections
+///   that do not fail it included, or to the standard error if no errors
+///   file is given; a failed run then exits with code 1 rather than
+///   panicking.
+///
+/// The files are replaced atomically, each followed by a `.done` marker if
+/// asked for, but for the summaries and closing state of an interrupted run.
+/// The amounts ignored as the types of their rows take none are written to
+/// the standard error.
+async fn process_file(
+    filename: &str,
+    config: EngineConfig,
+    opening: Opening,
+    summaries: SummaryOptions<'_>,
+    outputs: Outputs<'_>,
+    writer: AtomicFileWriter,
+    progress: bool,
+) -> String {
+    let started = SystemTime::now();
+    let byte_ranges = config.byte_ranges.is_some();
+    let watch_stalls = config.stall_timeout.is_some();
+    let engine = Engine::new(config);
+    if let Some(opening_balances) = opening.balances {
+        engine.open_accounts(&opening_balances).unwrap();
+    }
+    if let Some(opening_state) = opening.state {
+        engine.open_state(opening_state).unwrap();
+    }
+    cancel_on_signal(engine.cancellation());
+    if watch_stalls {
+        report_stalls(&engine);
+    }
+    let collecting = outputs.json_errors.map(|_| collect_errors(&engine));
+    let processing = async {
+        match byte_ranges {
+            true => engine.process_file(filename).await,
+            false => engine.process(open_input(filename)).await,
+        }
+    };
+    let result = match progress {
+        true => with_progress(&engine, processing).await,
+        false => processing.await,
+    };
+    if let (Some((done, collected)), Some(errors_path)) = (collecting, outputs.json_errors) {
+        let _ = done.send(());
+        let mut errors = collected.await.unwrap();
+        match &result {
+            // already received as it occurred
+            Ok(_) | Err(TransactionStreamProcessError::ProcessError(_)) => {}
+            Err(err) => errors.push(ErrorRecord::from(err)),
+        }
+        let written = ErrorReportWriter::write_json(&errors);
+        match errors_path {
+            Some(errors_path) => writer.write(errors_path, &written).unwrap(),
+            None => eprintln!("{}", String::from_utf8(written).unwrap()),
+        }
+        if matches!(&result, Err(err) if *err != TransactionStreamProcessError::Cancelled) {
+            process::exit(1);
+        }
+    }
+    let interrupted = match result {
+        Ok(_) => false,
+        Err(TransactionStreamProcessError::Cancelled) => true,
+        Err(err) => panic!("{err}"),
+    };
+    for warning in engine.amount_warnings() {
+        eprintln!("{warning}");
+    }
+    if let Some(stats_path) = outputs.stats {
+        writer
+            .write(
+                stats_path,
+                &ClientStatsCsvWriter::write(engine.stats()).unwrap(),
+            )
+            .unwrap();
+    }
+    if let Some(disputes_path) = outputs.disputes {
+        writer
+            .write(
+                disputes_path,
+                &DisputeReportWriter::write(engine.dispute_report()).unwrap(),
+            )
+            .unwrap();
+    }
+    if let Some(quarantine_path) = outputs.quarantine {
+        writer
+            .write(quarantine_path, &engine.quarantine().write().unwrap())
+            .unwrap();
+        for (txn_type, count) in engine.quarantine().counts() {
+            eprintln!("Quarantined {count} row(s) of type `{txn_type}`.");
+        }
+        let out_of_range_clients = engine.quarantine().out_of_range_clients();
+        if out_of_range_clients > 0 {
+            eprintln!("Quarantined {out_of_range_clients} row(s) of an out of range client.");
+        }
+    }
+    if let Some(conflicts_path) = outputs.conflicts {
+        let conflicts = engine.conflicts();
+        if !conflicts.is_empty() {
+            eprintln!("Put aside {} conflicting transaction(s).", conflicts.len());
+        }
+        writer
+            .write(
+                conflicts_path,
+                &ConflictReportWriter::write(conflicts).unwrap(),
+            )
+            .unwrap();
+    }
+    if let Some(large_transactions_path) = outputs.large_transactions {
+        writer
+            .write(
+                large_transactions_path,
+                &LargeTransactionReportWriter::write(engine.large_transactions()).unwrap(),
+            )
+            .unwrap();
+    }
+    if let Some(deltas_path) = outputs.deltas {
+        writer
+            .write(
+                deltas_path,
+                &SnapshotDeltaWriter::write(engine.snapshot_deltas()).unwrap(),
+            )
+            .unwrap();
+    }
+    let SummaryOptions {
+        format,
+        filter,
+        conversion,
+        totals,
+        footer,
+    } = summaries;
+    let accounts = filter.apply(engine.summaries());
+    let footer = footer.then(|| SummaryFooter::of(&accounts));
+    let mut summaries = match conversion {
+        Some((rates, currency)) => {
+            ConvertedSummaryCsvWriter::write(accounts, currency, &rates, &format).unwrap()
+        }
+        None if totals => {
+            AccountSummaryCsvWriter::write_with_totals(accounts, engine.totals(), &format).unwrap()
+        }
+        None => AccountSummaryCsvWriter::write_formatted(accounts, &format).unwrap(),
+    };
+    if let Some(footer) = footer {
+        footer.append_to(&mut summaries);
+    }
+    if let Some(output_path) = outputs.summaries {
+        // no marker for partial summaries, which are not to be picked up
+        let writer = writer.with_done_marker(!interrupted && writer.done_marker());
+        writer.write(output_path, &summaries).unwrap();
+    }
+    if let Some(closing_state_path) = outputs.closing_state {
+        // nor for the state of an interrupted run
+        let writer = writer.with_done_marker(!interrupted && writer.done_marker());
+        writer
+            .write(closing_state_path, &engine.closing_state().write().unwrap())
+            .unwrap();
+    }
+    if let Some((manifest_path, input_path, config)) = outputs.manifest {
+        let manifest = RunManifest::new(
+            FileDigest::of_file(input_path).unwrap(),
+            config,
+            &engine.stats(),
+            engine.quarantine().counts(),
+            started,
+            interrupted,
+        );
+        writer.write(manifest_path, &manifest.to_json()).unwrap();
+    }
+    if interrupted {
+        eprintln!("Interrupted, the summaries only cover the input read so far.");
+        if outputs.summaries.is_none() {
+            print!("{}", String::from_utf8(summaries).unwrap());
+        }
+        process::exit(INTERRUPTED);
+    }
+    match outputs.summaries {
+        Some(_) => String::new(),
+        None => String::from_utf8(summaries).unwrap(),
+    }
+}
+
+/// Runs the processing, writing the throughput of the engine to the standard
+/// error every second meanwhile.
+async fn with_progress<T>(engine: &Engine, processing: impl Future<Output = T>) -> T {
+    tokio::pin!(processing);
+    let mut ticks = tokio::time::interval(PROGRESS_INTERVAL);
+    // the first tick is immediate
+    ticks.tick().await;
+    loop {
+        tokio::select! {
+            result = &mut processing => return result,
+            _ = ticks.tick() => {
+                let Throughput {
+                    records,
+                    records_per_second,
+                } = engine.throughput();
+                eprintln!("Read {records} record(s), {records_per_second:.0} record(s)/s.");
+            }
+        }
+    }
+}
+
+/// Writes the stalls of the engine to the standard error as they occur.
+fn report_stalls(engine: &Engine) {
+    let mut received = engine.stalls();
+    tokio::spawn(async move {
+        loop {
+            match received.recv().await {
+                Ok(stall) => eprintln!("{stall}"),
+                Err(RecvError::Lagged(missed)) => {
+                    eprintln!("{missed} stall(s) were missed as they came too fast.")
+                }
+                Err(RecvError::Closed) => return,
+            }
+        }
+    });
+}
+
+/// Collects the errors of the engine as they occur, until told the run is
+/// done.
+fn collect_errors(engine: &Engine) -> (oneshot::Sender<()>, JoinHandle<Vec<ErrorRecord>>) {
+    let mut received = engine.errors();
+    let (done, mut finished) = oneshot::channel();
+    let collected = tokio::spawn(async move {
+        let mut errors = Vec::new();
+        loop {
+            let err = tokio::select! {
+                err = received.recv() => err,
+                _ = &mut finished => break,
+            };
+            match err {
+                Ok(err) => errors.push(ErrorRecord::from(&err)),
+                Err(RecvError::Lagged(missed)) => {
+                    eprintln!("{missed} error(s) were missed as they came too fast.")
+                }
+                Err(RecvError::Closed) => return errors,
+            }
+        }
+        // the ones sent before the run ended
+        while let Ok(err) = received.try_recv() {
+            errors.push(ErrorRecord::from(&err));
+        }
+        errors
+    });
+    (done, collected)
+}
+
+/// Cancels the processing on SIGINT or SIGTERM, so that what has been
+/// processed so far can still be written out.
+fn cancel_on_signal(cancellation: Cancellation) {
+    tokio::spawn(async move {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+            let mut terminate = signal(SignalKind::terminate()).unwrap();
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+            }
+        }
+        #[cfg(not(unix))]
+        tokio::signal::ctrl_c().await.unwrap();
+        cancellation.cancel();
+    });
+}
+
+/// `reconcile <ours> <theirs> [tolerance]`, the tolerance defaulting to 0.
+fn reconcile(args: &[String]) -> String {
+    let ours = BufReader::new(open_arg(positional_arg(args, 0, "<ours>")));
+    let theirs = BufReader::new(open_arg(positional_arg(args, 1, "<theirs>")));
+    let tolerance = match args.get(2) {
+        Some(tolerance) => parse_arg("tolerance", tolerance),
+        None => Amount::ZERO,
+    };
+    let differences = Reconciler::new(tolerance)
+        .reconcile(ours, theirs)
+        .unwrap_or_else(|err| fail(err));
+    let written = DifferenceReportWriter::write(differences).unwrap_or_else(|err| fail(err));
+    String::from_utf8(written).unwrap()
+}
+
+/// `merge [--sum-duplicates] <file>...`, the summaries of all the files in
+/// a single report, a client in more than one of them failing the merge
+/// unless its balances are to be summed.
+fn merge(args: &[String]) -> String {
+    let (duplicate_clients, files) = match args.first().map(String::as_str) {
+        Some("--sum-duplicates") => (DuplicateClients::Sum, &args[1..]),
+        _ => (DuplicateClients::Reject, args),
+    };
+    let inputs = files.iter().map(|file| BufReader::new(open_arg(file)));
+    let summaries = SummaryMerger::new(duplicate_clients)
+        .merge(inputs)
+        .unwrap_or_else(|err| fail(err));
     String::from_utf8(AccountSummaryCsvWriter::write(summaries).unwrap()).unwrap()
 }
+
+/// `replay <client> <file>`, one line per transaction of the client.
+async fn replay(args: &[String]) -> String {
+    let client_id = parse_arg("client", positional_arg(args, 0, "<client>"));
+    let reader = BufReader::new(open_arg(positional_arg(args, 1, "<file>")));
+    let engine = Engine::new(EngineConfig::default());
+    engine
+        .replay_client(client_id, reader)
+        .await
+        .unwrap_or_else(|err| fail(err))
+        .iter()
+        .map(|step| step.to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// `validate <file>`, what would be rejected or locked, without any summary.
+async fn validate(args: &[String]) -> String {
+    let reader = BufReader::new(open_arg(positional_arg(args, 0, "<file>")));
+    let engine = Engine::new(EngineConfig::default());
+    engine
+        .validate(reader)
+        .await
+        .unwrap_or_else(|err| fail(err))
+        .to_string()
+}
+
+/// `verify <file>`, the accounts the sequential and the concurrent pipelines
+/// disagree on, failing the run if there is any.
+async fn verify(args: &[String]) -> String {
+    let reader = BufReader::new(open_arg(positional_arg(args, 0, "<file>")));
+    let engine = Engine::new(EngineConfig::default());
+    let report = engine.verify(reader).await.unwrap_or_else(|err| fail(err));
+    if !report.is_consistent() {
+        eprint!("{report}");
+        process::exit(1);
+    }
+    report.to_string()
+}
+
+/// `open-disputes <file>`, the transactions still disputed once the file is
+/// processed.
+async fn open_disputes(args: &[String]) -> String {
+    let reader = BufReader::new(open_arg(positional_arg(args, 0, "<file>")));
+    let engine = Engine::new(EngineConfig::default());
+    engine.process(reader).await.unwrap_or_else(|err| fail(err));
+    String::from_utf8(OpenDisputeReportWriter::write(engine.open_disputes()).unwrap()).unwrap()
+}
+
+/// `watch <dir> [--archive <dir>] [--summaries <file>] [--interval <secs>]
+/// [--config <config file>] [--done-marker] [--processed-ids <file>]`, the
+/// files put in the directory processed into the same accounts as they
+/// appear and moved to the archive, `<dir>/archive` by default. After each
+/// batch of them, the summaries are written to the summaries file, replacing
+/// it atomically, followed by a `.done` marker if asked for, or to the
+/// standard output. Runs until interrupted.
+async fn watch(args: &[String]) -> String {
+    let dir = Path::new(positional_arg(args, 0, "<dir>"));
+    let option = |name: &str| {
+        let position = args[1..].iter().position(|arg| arg == name)?;
+        args.get(1 + position + 1)
+    };
+    let config = match option("--config") {
+        Some(path) => EngineConfig::from_file(path).unwrap(),
+        None => EngineConfig::default(),
+    }
+    .with_env()
+    .unwrap();
+    let archive = match option("--archive") {
+        Some(archive) => Path::new(archive).to_path_buf(),
+        None => dir.join("archive"),
+    };
+    let poll_interval = match option("--interval") {
+        Some(secs) => Duration::from_secs(parse_arg("--interval", secs)),
+        None => DEFAULT_POLL_INTERVAL,
+    };
+    let summaries_path = option("--summaries");
+    let writer =
+        AtomicFileWriter::new().with_done_marker(args.iter().any(|arg| arg == "--done-marker"));
+    let engine = with_processed_ids(Engine::new(config), option("--processed-ids"));
+    cancel_on_signal(engine.cancellation());
+    DirectoryWatcher::new(dir, archive)
+        .with_poll_interval(poll_interval)
+        .run(&engine, |engine, processed| {
+            for file in processed {
+                if let Err(err) = &file.result {
+                    eprintln!("{}: {err}", file.archived.display());
+                }
+            }
+            let summaries = AccountSummaryCsvWriter::write(engine.summaries()).unwrap();
+            match summaries_path {
+                Some(path) => writer.write(path, &summaries).unwrap(),
+                None => println!("{}", String::from_utf8(summaries).unwrap()),
+            }
+        })
+        .await
+        .unwrap();
+    String::new()
+}
+
+/// The engine recognising the transactions recorded in the processed ids
+/// file, if any, as sent again.
+fn with_processed_ids(engine: Engine, path: Option<&String>) -> Engine {
+    match path {
+        Some(path) => engine.with_processed_ids(ProcessedIds::open(path).unwrap()),
+        None => engine,
+    }
+}
+
+/// `listen <address> [--summaries-ws <address>] [--graphql <address>]
+/// [--processed-ids <file>] [--kafka <brokers> --kafka-topic <topic>]`, the
+/// transactions sent to the address over TCP, one per line, processed until
+/// interrupted, the summaries being written then. The transactions processed
+/// are recorded in the processed ids file, if given, for those sent again,
+/// even after a restart, not to be applied twice. With the `kafka` feature,
+/// an event is published to the topic for each transaction applied. With the
+/// `websocket` feature, the summaries are pushed over WebSocket as they
+/// change, and with the `graphql` feature, the accounts can be queried over
+/// HTTP meanwhile.
+async fn listen(args: &[String]) -> String {
+    let listener = TcpListener::bind(&args[0]).await.unwrap();
+    let option = |name: &str| {
+        let position = args[1..].iter().position(|arg| arg == name)?;
+        args.get(1 + position + 1)
+    };
+    let engine = with_processed_ids(
+        Engine::new(EngineConfig::default().with_env().unwrap()),
+        option("--processed-ids"),
+    );
+    #[cfg(feature = "kafka")]
+    let (engine, publishing) = match (option("--kafka"), option("--kafka-topic")) {
+        (Some(brokers), Some(topic)) => {
+            let config = KafkaSinkConfig::new(brokers, topic, &format!("jouet-paiement-{topic}"));
+            let sink = KafkaSink::new(config).await.unwrap();
+            let (events, received) = mpsc::channel(ACCOUNT_EVENT_CAPACITY);
+            (
+                engine.with_account_events(events),
+                Some(tokio::spawn(sink.run(received))),
+            )
+        }
+        _ => (engine, None),
+    };
+    cancel_on_signal(engine.cancellation());
+    let pushed = async {
+        #[cfg(feature = "websocket")]
+        if let Some(address) = option("--summaries-ws") {
+            let listener = TcpListener::bind(address).await.unwrap();
+            engine.serve_summaries(listener).await.unwrap();
+        }
+    };
+    let queried = async {
+        #[cfg(feature = "graphql")]
+        if let Some(address) = option("--graphql") {
+            let listener = TcpListener::bind(address).await.unwrap();
+            engine.serve_graphql(listener).await.unwrap();
+        }
+    };
+    let (listened, (), ()) = tokio::join!(engine.listen(listener), pushed, queried);
+    listened.unwrap();
+    let summaries = AccountSummaryCsvWriter::write(engine.summaries()).unwrap();
+    // the events still waiting are published once the engine lets go of them
+    drop(engine);
+    #[cfg(feature = "kafka")]
+    if let Some(publishing) = publishing {
+        publishing.await.unwrap().unwrap();
+    }
+    String::from_utf8(summaries).unwrap()
+}
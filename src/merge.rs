@@ -0,0 +1,153 @@
+use std::{collections::BTreeMap, io::Read};
+
+use csv::{ReaderBuilder, Trim};
+use thiserror::Error;
+
+use crate::model::{AccountSummary, ClientId};
+
+/// What to do with a client found in more than one of the summaries.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DuplicateClients {
+    /// The summaries are expected to cover disjoint clients, as those of
+    /// the shards of one input do.
+    Reject,
+
+    /// The balances of the client are added up, the account being locked if
+    /// it is in any of the summaries.
+    Sum,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum MergeError {
+    #[error("Failed to read the summaries: {0}")]
+    ParsingError(String),
+
+    #[error("Client {0} appears more than once in the summaries.")]
+    DuplicateClient(ClientId),
+}
+
+/// Merges several sets of account summaries, e.g. those of the instances
+/// each processing a shard of the clients, into a single one.
+pub struct SummaryMerger {
+    duplicate_clients: DuplicateClients,
+}
+
+impl SummaryMerger {
+    pub fn new(duplicate_clients: DuplicateClients) -> Self {
+        Self { duplicate_clients }
+    }
+
+    /// Reads each input as a summary CSV and returns the summaries of all of
+    /// them, ordered by client.
+    pub fn merge<R: Read>(
+        &self,
+        inputs: impl IntoIterator<Item = R>,
+    ) -> Result<Vec<AccountSummary>, MergeError> {
+        let mut merged: BTreeMap<ClientId, AccountSummary> = BTreeMap::new();
+        for input in inputs {
+            for result in ReaderBuilder::new()
+                .trim(Trim::All)
+                // the footer, if the summaries were written with one
+                .comment(Some(b'#'))
+                .from_reader(input)
+                .deserialize::<AccountSummary>()
+            {
+                let summary = result.map_err(|err| MergeError::ParsingError(err.to_string()))?;
+                let summary = match merged.remove(&summary.client_id) {
+                    None => summary,
+                    Some(_) if self.duplicate_clients == DuplicateClients::Reject => {
+                        return Err(MergeError::DuplicateClient(summary.client_id))
+                    }
+                    Some(previous) => sum(&previous, &summary),
+                };
+                merged.insert(summary.client_id, summary);
+            }
+        }
+        Ok(merged.into_values().collect())
+    }
+}
+
+fn sum(a: &AccountSummary, b: &AccountSummary) -> AccountSummary {
+    AccountSummary {
+        client_id: a.client_id,
+        available: a.available + b.available,
+        held: a.held + b.held,
+        total: a.total + b.total,
+        locked: a.locked || b.locked,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+
+    use crate::model::AccountSummaryCsvWriter;
+
+    use super::{DuplicateClients, MergeError, SummaryMerger};
+
+    const SHARD_1: &str = "
+    client, available,   held,   total, locked
+         3,    3.0000, 0.0000,  3.0000,   true
+         1,    1.0000, 0.5000,  1.5000,  false";
+
+    const SHARD_2: &str = "
+    client, available,   held,   total, locked
+         2,    2.0000, 0.0000,  2.0000,  false
+# accounts=1,available=2.0000,held=0.0000,crc32=00000000
+";
+
+    const OVERLAPPING: &str = "
+    client, available,   held,   total, locked
+         1,    2.0000, 0.0000,  2.0000,   true";
+
+    #[test]
+    fn disjoint_summaries_are_merged_in_client_order() {
+        let merged = SummaryMerger::new(DuplicateClients::Reject)
+            .merge([SHARD_1.as_bytes(), SHARD_2.as_bytes()])
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(AccountSummaryCsvWriter::write(merged).unwrap()).unwrap(),
+            "\
+            client,available,held,total,locked\n\
+            1,1.0000,0.5000,1.5000,false\n\
+            2,2.0000,0.0000,2.0000,false\n\
+            3,3.0000,0.0000,3.0000,true\n"
+        );
+    }
+
+    #[test]
+    fn duplicate_clients_are_rejected() {
+        assert_eq!(
+            SummaryMerger::new(DuplicateClients::Reject)
+                .merge([SHARD_1.as_bytes(), OVERLAPPING.as_bytes()])
+                .unwrap_err(),
+            MergeError::DuplicateClient(1)
+        );
+    }
+
+    #[test]
+    fn duplicate_clients_can_be_summed() {
+        let merged = SummaryMerger::new(DuplicateClients::Sum)
+            .merge([SHARD_1.as_bytes(), OVERLAPPING.as_bytes()])
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(AccountSummaryCsvWriter::write(merged).unwrap()).unwrap(),
+            "\
+            client,available,held,total,locked\n\
+            1,3.0000,0.5000,3.5000,true\n\
+            3,3.0000,0.0000,3.0000,true\n"
+        );
+    }
+
+    #[test]
+    fn invalid_summaries_are_rejected() {
+        let invalid = "
+    client, available,   held,   total, locked
+         1,       one, 0.0000,  1.0000,  false";
+        assert_matches!(
+            SummaryMerger::new(DuplicateClients::Sum)
+                .merge([SHARD_2.as_bytes(), invalid.as_bytes()]),
+            Err(MergeError::ParsingError(_))
+        );
+    }
+}
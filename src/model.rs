@@ -1,12 +1,49 @@
-use serde::Serialize;
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::{self, Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::collections::HashMap;
 
 mod account_summary;
+#[cfg(feature = "avro")]
+mod account_summary_avro;
+#[cfg(any(feature = "cdylib", feature = "engine", feature = "wasm"))]
+mod account_summary_csv;
+#[cfg(feature = "parquet")]
+mod account_summary_parquet;
 mod amount;
-pub use account_summary::AccountSummaryCsvWriter;
+pub(crate) mod amount_format;
+#[cfg(feature = "decimal")]
+mod decimal_amount;
+mod summary_filter;
+mod summary_format;
+#[cfg(feature = "avro")]
+pub use account_summary_avro::{AccountSummaryAvroWriter, ACCOUNT_SUMMARY_SCHEMA};
+#[cfg(any(feature = "cdylib", feature = "engine", feature = "wasm"))]
+pub use account_summary_csv::{AccountSummaryCsvWriter, SummaryFooter};
+#[cfg(feature = "parquet")]
+pub use account_summary_parquet::AccountSummaryParquetWriter;
+pub use amount::AmountParseError;
+#[cfg(feature = "decimal")]
+pub use decimal_amount::{AmountConversionError, DecimalAmount};
+pub use summary_filter::SummaryFilter;
+pub use summary_format::SummaryFormat;
 
 pub type ClientId = u16;
 pub type TransactionId = u32;
+#[cfg(not(feature = "decimal"))]
 pub type Amount = Amount4DecimalBased;
+#[cfg(feature = "decimal")]
+pub type Amount = DecimalAmount;
+/// Seconds since the Unix epoch.
+pub type Timestamp = u64;
 
 /// The transaction structure accepted by this application.
 #[derive(Debug, PartialEq, Clone)]
@@ -14,35 +51,223 @@ pub struct Transaction {
     pub client_id: ClientId,
     pub transaction_id: TransactionId,
     pub kind: TransactionKind,
+    /// When the transaction took place, if the input says so.
+    pub timestamp: Option<Timestamp>,
+    /// What upstream attached to the transaction, if anything, boxed as most
+    /// transactions have none.
+    pub memo: Option<Box<Memo>>,
+    /// Where the transaction is in the input, i.e. the line of a CSV one and
+    /// the position of the record, from 1, otherwise, so that it can be
+    /// traced back to its row. Not set for a transaction made up in code,
+    /// nor by anything but the readers of the inputs, see
+    /// [`Transaction::ingest_sequence`].
+    pub(crate) ingest_sequence: Option<u64>,
+}
+
+/// What upstream attached to a transaction for it to be traced back, e.g.
+/// the batch it came in. It plays no part in the processing, and is kept
+/// along with the deposit, withdrawal or adjustment it came with.
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct Memo {
+    pub text: Option<String>,
+    pub metadata: HashMap<String, String>,
+}
+
+/// The text then the metadata, by key, e.g. `invoice 42; batch=7`.
+impl Display for Memo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut metadata: Vec<(&String, &String)> = self.metadata.iter().collect();
+        metadata.sort();
+        let parts: Vec<String> = self
+            .text
+            .iter()
+            .cloned()
+            .chain(metadata.iter().map(|(key, value)| format!("{key}={value}")))
+            .collect();
+        write!(f, "{}", parts.join("; "))
+    }
 }
 
 /// The kinds of transactions.
 #[derive(Debug, PartialEq, Clone)]
 pub enum TransactionKind {
-    Deposit { amount: Amount },
-    Withdrawal { amount: Amount },
+    Deposit {
+        amount: Amount,
+    },
+    Withdrawal {
+        amount: Amount,
+    },
     Dispute,
     Resolve,
     ChargeBack,
+    /// Reverses the chargeback of a deposit.
+    Representment,
+    /// Closes the account of the client for good, e.g. when offboarding it.
+    Close,
+    /// A manual correction by the back office, crediting the available funds
+    /// if the amount is positive and debiting them if it is negative.
+    /// It cannot be disputed.
+    Adjustment {
+        amount: Amount,
+        reason: String,
+    },
 }
 
-/// TODO: Use proper serde to avoid having `String`s as the type of fields.
-#[derive(Debug, Serialize)]
+impl Transaction {
+    /// A transaction made up in code, with neither a timestamp nor a memo,
+    /// which can be set afterwards, and no place in an input.
+    pub fn new(client_id: ClientId, transaction_id: TransactionId, kind: TransactionKind) -> Self {
+        Self {
+            client_id,
+            transaction_id,
+            kind,
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
+        }
+    }
+
+    /// Where the transaction is in the input, i.e. the line of a CSV one and
+    /// the position of the record, from 1, otherwise, none for a transaction
+    /// made up in code.
+    pub fn ingest_sequence(&self) -> Option<u64> {
+        self.ingest_sequence
+    }
+
+    /// How the reports refer to the transaction, e.g.
+    /// `row 5, client 2, tx 5 (withdrawal)`, the row being left out if it is
+    /// not known.
+    #[cfg(feature = "std")]
+    pub(crate) fn described(&self) -> String {
+        let row = match self.ingest_sequence {
+            Some(row) => format!("row {row}, "),
+            None => String::new(),
+        };
+        format!(
+            "{row}client {}, tx {} ({})",
+            self.client_id,
+            self.transaction_id,
+            self.kind.name()
+        )
+    }
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum TransactionKindError {
+    #[error("Unknown transaction type `{0}`")]
+    UnknownType(String),
+
+    #[error("A {0} needs an amount")]
+    MissingAmount(&'static str),
+
+    #[error("Invalid amount `{0}`: {1}")]
+    InvalidAmount(String, String),
+}
+
+impl TransactionKind {
+    /// The kind with the name it has in the input, e.g. `deposit`, the amount
+    /// only being read for a deposit or a withdrawal. An adjustment, which
+    /// comes with a reason, cannot be told this way.
+    pub fn from_name(name: &str, amount: Option<&str>) -> Result<Self, TransactionKindError> {
+        let amount = |name| {
+            let amount = amount.ok_or(TransactionKindError::MissingAmount(name))?;
+            amount.parse::<Amount>().map_err(|err| {
+                TransactionKindError::InvalidAmount(amount.to_string(), err.to_string())
+            })
+        };
+        Ok(match name {
+            "deposit" => TransactionKind::Deposit {
+                amount: amount("deposit")?,
+            },
+            "withdrawal" => TransactionKind::Withdrawal {
+                amount: amount("withdrawal")?,
+            },
+            "dispute" => TransactionKind::Dispute,
+            "resolve" => TransactionKind::Resolve,
+            "chargeback" => TransactionKind::ChargeBack,
+            "representment" => TransactionKind::Representment,
+            "close" => TransactionKind::Close,
+            _ => return Err(TransactionKindError::UnknownType(name.to_string())),
+        })
+    }
+
+    /// The name of the kind as it is in the input.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TransactionKind::Deposit { .. } => "deposit",
+            TransactionKind::Withdrawal { .. } => "withdrawal",
+            TransactionKind::Dispute => "dispute",
+            TransactionKind::Resolve => "resolve",
+            TransactionKind::ChargeBack => "chargeback",
+            TransactionKind::Representment => "representment",
+            TransactionKind::Close => "close",
+            TransactionKind::Adjustment { .. } => "adjustment",
+        }
+    }
+
+    /// Whether the transaction is one of the back office's rather than of the
+    /// client, i.e. an adjustment or the closing of the account.
+    pub fn is_admin(&self) -> bool {
+        matches!(
+            self,
+            TransactionKind::Adjustment { .. } | TransactionKind::Close
+        )
+    }
+}
+
+/// The balances of a client, its amounts being written with 4 decimal
+/// places.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct AccountSummary {
     #[serde(rename = "client")]
     pub client_id: ClientId,
-    #[serde(rename = "available")]
-    available: String,
-    #[serde(rename = "held")]
-    held: String,
-    #[serde(rename = "total")]
-    total: String,
+    #[serde(rename = "available", with = "amount_format")]
+    pub available: Amount,
+    #[serde(rename = "held", with = "amount_format")]
+    pub held: Amount,
+    #[serde(rename = "total", with = "amount_format")]
+    pub total: Amount,
+    #[serde(rename = "locked")]
+    pub locked: bool,
+}
+
+/// An [`AccountSummary`] extended with what the client has been charged.
+#[derive(Debug, Serialize)]
+pub struct ExtendedAccountSummary {
+    #[serde(rename = "client")]
+    pub client_id: ClientId,
+    #[serde(rename = "available", with = "amount_format")]
+    pub available: Amount,
+    #[serde(rename = "held", with = "amount_format")]
+    pub held: Amount,
+    #[serde(rename = "total", with = "amount_format")]
+    pub total: Amount,
     #[serde(rename = "locked")]
-    locked: bool,
+    pub locked: bool,
+    #[serde(rename = "fees", with = "amount_format")]
+    pub fees: Amount,
+    /// How much of its credit line the client is using, i.e. how far below
+    /// zero its available funds are.
+    #[serde(rename = "credit_used", with = "amount_format")]
+    pub credit_used: Amount,
+}
+
+/// What went through the account of a client over the run, so that it need
+/// not be worked out again from the input.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct AccountTotals {
+    pub client_id: ClientId,
+    /// Of the deposits taken, whatever became of them.
+    pub total_deposited: Amount,
+    pub total_withdrawn: Amount,
+    /// Of the deposits charged back and not represented since.
+    pub total_charged_back: Amount,
+    /// Of the deposits disputed but neither resolved nor charged back yet.
+    pub open_disputed_amount: Amount,
 }
 
 /// The amount is stored as an i64 to simplify the handling of precision.
 /// The downside of doing so is that it could only hold up to the amount of
 /// `i64::MAX / 10_000`.
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
 pub struct Amount4DecimalBased(pub i64);
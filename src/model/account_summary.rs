@@ -1,9 +1,6 @@
-use csv::WriterBuilder;
-use thiserror::Error;
+use crate::account::{Account, AccountStatus, DepositStatus};
 
-use crate::account::{Account, AccountSnapshot, AccountStatus};
-
-use super::{AccountSummary, Amount4DecimalBased};
+use super::{AccountSummary, AccountTotals, Amount, ExtendedAccountSummary};
 
 impl From<Account> for AccountSummary {
     fn from(account: Account) -> Self {
@@ -13,79 +10,56 @@ impl From<Account> for AccountSummary {
 
 impl From<&Account> for AccountSummary {
     fn from(account: &Account) -> Self {
-        let AccountSnapshot { available, held } = account.account_snapshot;
-        let total = Amount4DecimalBased(available.0 + held.0);
         Self {
             client_id: account.client_id,
-            available: available.to_str(),
-            held: held.to_str(),
-            total: total.to_str(),
-            locked: account.status == AccountStatus::Locked,
+            available: account.available(),
+            held: account.held(),
+            total: account.total(),
+            // a closed account is just as closed to transactions
+            locked: account.status != AccountStatus::Active,
         }
     }
 }
 
-pub struct AccountSummaryCsvWriter;
-
-#[derive(Debug, Error)]
-pub enum AccountSummaryWriterError {
-    #[error("Failed to serialise the AccountSummary: {0}")]
-    SerialisationError(String),
-}
-
-impl AccountSummaryCsvWriter {
-    pub fn write(summaries: Vec<AccountSummary>) -> Result<Vec<u8>, AccountSummaryWriterError> {
-        let mut wtr = WriterBuilder::new().from_writer(vec![]);
-        for summary in summaries {
-            match wtr.serialize(summary) {
-                Ok(_) => {}
-                Err(err) => {
-                    return Err(AccountSummaryWriterError::SerialisationError(
-                        err.to_string(),
-                    ))
-                }
-            };
-        }
-        match wtr.into_inner() {
-            Ok(chars) => Ok(chars),
-            Err(e) => return Err(AccountSummaryWriterError::SerialisationError(e.to_string())),
+impl From<&Account> for ExtendedAccountSummary {
+    fn from(account: &Account) -> Self {
+        let AccountSummary {
+            client_id,
+            available,
+            held,
+            total,
+            locked,
+        } = AccountSummary::from(account);
+        Self {
+            client_id,
+            available,
+            held,
+            total,
+            locked,
+            fees: account.total_fees(),
+            credit_used: (-account.account_snapshot.available).max(Amount::ZERO),
         }
     }
 }
 
-#[cfg(test)]
-mod tests {
-
-    use crate::model::AccountSummary;
-
-    use super::AccountSummaryCsvWriter;
-
-    #[test]
-    fn can_write_account_summary_data_as_csv() {
-        let account_summary_1 = AccountSummary {
-            client_id: 1122,
-            available: "111".to_string(),
-            held: "222".to_string(),
-            total: "333".to_string(),
-            locked: false,
-        };
-        let account_summary_2 = AccountSummary {
-            client_id: 3344,
-            available: "333".to_string(),
-            held: "444".to_string(),
-            total: "777".to_string(),
-            locked: true,
+impl From<&Account> for AccountTotals {
+    fn from(account: &Account) -> Self {
+        let deposited = |status: Option<DepositStatus>| -> Amount {
+            account
+                .deposits()
+                .filter(|(_, deposit)| status.is_none_or(|status| deposit.status == status))
+                .map(|(_, deposit)| deposit.amount)
+                .sum()
         };
-
-        assert_eq!(
-            String::from_utf8(
-                AccountSummaryCsvWriter::write(vec![account_summary_1, account_summary_2]).unwrap()
-            )
-            .unwrap(),
-            "\
-            client,available,held,total,locked\n\
-            1122,111,222,333,false\n\
-            3344,333,444,777,true\n"
-        );
+        Self {
+            client_id: account.client_id,
+            total_deposited: deposited(None),
+            total_withdrawn: account
+                .withdrawals()
+                .map(|(_, withdrawal)| withdrawal.amount)
+                .sum(),
+            total_charged_back: deposited(Some(DepositStatus::ChargedBack)),
+            open_disputed_amount: deposited(Some(DepositStatus::Held)),
+        }
     }
 }
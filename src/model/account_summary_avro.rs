@@ -0,0 +1,132 @@
+use apache_avro::{Schema, Writer};
+
+use super::{
+    account_summary_csv::{AccountSummaryWriterError, FormattedAccountSummary},
+    AccountSummary, SummaryFormat,
+};
+
+/// The Avro schema of the [`AccountSummary`]s emitted by the
+/// [`AccountSummaryAvroWriter`].
+/// The amounts are kept as strings, formatted as they are in the CSV output.
+pub const ACCOUNT_SUMMARY_SCHEMA: &str = r#"
+{
+    "type": "record",
+    "name": "AccountSummary",
+    "namespace": "jouet_paiement",
+    "fields": [
+        { "name": "client", "type": "int" },
+        { "name": "available", "type": "string" },
+        { "name": "held", "type": "string" },
+        { "name": "total", "type": "string" },
+        { "name": "locked", "type": "boolean" }
+    ]
+}
+"#;
+
+/// Writes [`AccountSummary`]s as an Avro object container file, with
+/// [`ACCOUNT_SUMMARY_SCHEMA`] embedded in its header.
+pub struct AccountSummaryAvroWriter;
+
+impl AccountSummaryAvroWriter {
+    pub fn write(summaries: Vec<AccountSummary>) -> Result<Vec<u8>, AccountSummaryWriterError> {
+        Self::write_formatted(summaries, &SummaryFormat::default())
+    }
+
+    /// Writes the summaries with their amounts formatted as per the
+    /// [`SummaryFormat`].
+    pub fn write_formatted(
+        summaries: Vec<AccountSummary>,
+        format: &SummaryFormat,
+    ) -> Result<Vec<u8>, AccountSummaryWriterError> {
+        let schema = Schema::parse_str(ACCOUNT_SUMMARY_SCHEMA)
+            .expect("The account summary schema is a valid Avro schema.");
+        let mut wtr = Writer::new(&schema, vec![])
+            .map_err(|err| AccountSummaryWriterError::SerialisationError(err.to_string()))?;
+        for summary in &summaries {
+            wtr.append_ser(FormattedAccountSummary::new(summary, format))
+                .map_err(|err| AccountSummaryWriterError::SerialisationError(err.to_string()))?;
+        }
+        wtr.into_inner()
+            .map_err(|err| AccountSummaryWriterError::SerialisationError(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use apache_avro::{types::Value, Reader, Schema};
+
+    use crate::model::{AccountSummary, Amount, SummaryFormat};
+
+    use super::{AccountSummaryAvroWriter, ACCOUNT_SUMMARY_SCHEMA};
+
+    #[test]
+    fn can_write_account_summary_data_as_avro() {
+        let account_summary_1 = AccountSummary {
+            client_id: 1122,
+            available: Amount::new(1_110_000),
+            held: Amount::new(2_220_000),
+            total: Amount::new(3_330_000),
+            locked: false,
+        };
+        let account_summary_2 = AccountSummary {
+            client_id: 3344,
+            available: Amount::new(3_330_000),
+            held: Amount::new(4_440_000),
+            total: Amount::new(7_770_000),
+            locked: true,
+        };
+
+        let bytes =
+            AccountSummaryAvroWriter::write(vec![account_summary_1, account_summary_2]).unwrap();
+
+        let reader = Reader::new(bytes.as_slice()).unwrap();
+        assert_eq!(
+            *reader.writer_schema(),
+            Schema::parse_str(ACCOUNT_SUMMARY_SCHEMA).unwrap()
+        );
+        let values: Vec<Value> = reader.map(|value| value.unwrap()).collect();
+        assert_eq!(
+            values,
+            vec![
+                summary(1122, "111.0000", "222.0000", "333.0000", false),
+                summary(3344, "333.0000", "444.0000", "777.0000", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn can_write_account_summary_data_formatted_as_avro() {
+        let bytes = AccountSummaryAvroWriter::write_formatted(
+            vec![AccountSummary {
+                client_id: 1,
+                available: Amount::new(15_000),
+                held: Amount::ZERO,
+                total: Amount::new(15_000),
+                locked: false,
+            }],
+            &SummaryFormat {
+                decimal_places: Some(2),
+                ..SummaryFormat::default()
+            },
+        )
+        .unwrap();
+        let values: Vec<Value> = Reader::new(bytes.as_slice())
+            .unwrap()
+            .map(|value| value.unwrap())
+            .collect();
+        assert_eq!(values, vec![summary(1, "1.50", "0.00", "1.50", false)]);
+    }
+
+    fn summary(client: i32, available: &str, held: &str, total: &str, locked: bool) -> Value {
+        Value::Record(vec![
+            ("client".to_string(), Value::Int(client)),
+            (
+                "available".to_string(),
+                Value::String(available.to_string()),
+            ),
+            ("held".to_string(), Value::String(held.to_string())),
+            ("total".to_string(), Value::String(total.to_string())),
+            ("locked".to_string(), Value::Boolean(locked)),
+        ])
+    }
+}
@@ -0,0 +1,381 @@
+use crc::{Crc, CRC_32_ISO_HDLC};
+use csv::WriterBuilder;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::collections::HashMap;
+
+use super::{
+    AccountSummary, AccountTotals, Amount, ClientId, ExtendedAccountSummary, SummaryFormat,
+};
+
+pub struct AccountSummaryCsvWriter;
+
+/// The CRC-32 of zlib, gzip and PNG, which most tools compute, e.g.
+/// `crc32` or Python's `zlib.crc32`.
+const CRC_32: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+
+/// The last line of a report for the receiver to check it came through whole,
+/// e.g. `# accounts=2,available=4.0000,held=6.0000,crc32=1c291ca3`: the
+/// number of accounts, the totals of their available and held funds, in the
+/// currency they are held in, and the CRC-32 of everything above it.
+/// It goes below the summaries as written by any of the writers, e.g.
+/// formatted or with their totals.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SummaryFooter {
+    accounts: usize,
+    available: Amount,
+    held: Amount,
+}
+
+impl SummaryFooter {
+    /// The footer of the summaries, to be written once they are.
+    pub fn of(summaries: &[AccountSummary]) -> Self {
+        let mut available = Amount::ZERO;
+        let mut held = Amount::ZERO;
+        for summary in summaries {
+            available += summary.available;
+            held += summary.held;
+        }
+        Self {
+            accounts: summaries.len(),
+            available,
+            held,
+        }
+    }
+
+    /// Appends the footer to the summaries written.
+    pub fn append_to(&self, written: &mut Vec<u8>) {
+        let footer = format!(
+            "# accounts={},available={},held={},crc32={:08x}\n",
+            self.accounts,
+            self.available.to_str(),
+            self.held.to_str(),
+            CRC_32.checksum(written)
+        );
+        written.extend_from_slice(footer.as_bytes());
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum AccountSummaryWriterError {
+    #[error("Failed to serialise the AccountSummary: {0}")]
+    SerialisationError(String),
+}
+
+/// An [`AccountSummary`] with its amounts formatted as per a
+/// [`SummaryFormat`].
+#[derive(Serialize)]
+pub(super) struct FormattedAccountSummary {
+    client: ClientId,
+    available: String,
+    held: String,
+    total: String,
+    locked: bool,
+}
+
+impl FormattedAccountSummary {
+    pub(super) fn new(summary: &AccountSummary, format: &SummaryFormat) -> Self {
+        Self {
+            client: summary.client_id,
+            available: format.format(summary.available),
+            held: format.format(summary.held),
+            total: format.format(summary.total),
+            locked: summary.locked,
+        }
+    }
+}
+
+/// A [`FormattedAccountSummary`] followed by the [`AccountTotals`] of the
+/// client.
+#[derive(Serialize)]
+struct TotalledAccountSummary {
+    client: ClientId,
+    available: String,
+    held: String,
+    total: String,
+    locked: bool,
+    total_deposited: String,
+    total_withdrawn: String,
+    total_charged_back: String,
+    open_disputed_amount: String,
+}
+
+impl TotalledAccountSummary {
+    fn new(summary: &AccountSummary, totals: AccountTotals, format: &SummaryFormat) -> Self {
+        let FormattedAccountSummary {
+            client,
+            available,
+            held,
+            total,
+            locked,
+        } = FormattedAccountSummary::new(summary, format);
+        Self {
+            client,
+            available,
+            held,
+            total,
+            locked,
+            total_deposited: format.format(totals.total_deposited),
+            total_withdrawn: format.format(totals.total_withdrawn),
+            total_charged_back: format.format(totals.total_charged_back),
+            open_disputed_amount: format.format(totals.open_disputed_amount),
+        }
+    }
+}
+
+impl AccountSummaryCsvWriter {
+    pub fn write(summaries: Vec<AccountSummary>) -> Result<Vec<u8>, AccountSummaryWriterError> {
+        Self::write_records(summaries)
+    }
+
+    /// Writes the summaries with their amounts formatted as per the
+    /// [`SummaryFormat`], a field with a thousands separator being quoted.
+    pub fn write_formatted(
+        summaries: Vec<AccountSummary>,
+        format: &SummaryFormat,
+    ) -> Result<Vec<u8>, AccountSummaryWriterError> {
+        Self::write_records(
+            summaries
+                .iter()
+                .map(|summary| FormattedAccountSummary::new(summary, format))
+                .collect(),
+        )
+    }
+
+    /// Writes the summaries formatted as per the [`SummaryFormat`] with
+    /// extra `total_deposited`, `total_withdrawn`, `total_charged_back` and
+    /// `open_disputed_amount` columns, those of a client without totals being
+    /// zero.
+    pub fn write_with_totals(
+        summaries: Vec<AccountSummary>,
+        totals: Vec<AccountTotals>,
+        format: &SummaryFormat,
+    ) -> Result<Vec<u8>, AccountSummaryWriterError> {
+        let totals: HashMap<ClientId, AccountTotals> = totals
+            .into_iter()
+            .map(|totals| (totals.client_id, totals))
+            .collect();
+        Self::write_records(
+            summaries
+                .iter()
+                .map(|summary| {
+                    let totals = totals.get(&summary.client_id).copied().unwrap_or_default();
+                    TotalledAccountSummary::new(summary, totals, format)
+                })
+                .collect(),
+        )
+    }
+
+    /// Writes the summaries followed by their [`SummaryFooter`].
+    pub fn write_with_footer(
+        summaries: Vec<AccountSummary>,
+    ) -> Result<Vec<u8>, AccountSummaryWriterError> {
+        let footer = SummaryFooter::of(&summaries);
+        let mut chars = Self::write_records(summaries)?;
+        footer.append_to(&mut chars);
+        Ok(chars)
+    }
+
+    /// Writes the summaries with an extra `fees` column.
+    pub fn write_extended(
+        summaries: Vec<ExtendedAccountSummary>,
+    ) -> Result<Vec<u8>, AccountSummaryWriterError> {
+        Self::write_records(summaries)
+    }
+
+    fn write_records(summaries: Vec<impl Serialize>) -> Result<Vec<u8>, AccountSummaryWriterError> {
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        for summary in summaries {
+            match wtr.serialize(summary) {
+                Ok(_) => {}
+                Err(err) => {
+                    return Err(AccountSummaryWriterError::SerialisationError(
+                        err.to_string(),
+                    ))
+                }
+            };
+        }
+        match wtr.into_inner() {
+            Ok(chars) => Ok(chars),
+            Err(e) => Err(AccountSummaryWriterError::SerialisationError(e.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::model::{
+        AccountSummary, AccountTotals, Amount, ExtendedAccountSummary, SummaryFormat,
+    };
+
+    use super::{AccountSummaryCsvWriter, SummaryFooter, CRC_32};
+
+    #[test]
+    fn crc32_matches_the_check_value() {
+        assert_eq!(CRC_32.checksum(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn can_write_account_summary_data_with_a_footer() {
+        let summaries = vec![
+            AccountSummary {
+                client_id: 1,
+                available: Amount::new(15_000),
+                held: Amount::new(20_000),
+                total: Amount::new(35_000),
+                locked: false,
+            },
+            AccountSummary {
+                client_id: 2,
+                available: Amount::new(-5_000),
+                held: Amount::new(0),
+                total: Amount::new(-5_000),
+                locked: true,
+            },
+        ];
+        let body = "\
+            client,available,held,total,locked\n\
+            1,1.5000,2.0000,3.5000,false\n\
+            2,-0.5000,0.0000,-0.5000,true\n";
+
+        assert_eq!(
+            String::from_utf8(AccountSummaryCsvWriter::write_with_footer(summaries).unwrap())
+                .unwrap(),
+            format!(
+                "{body}# accounts=2,available=1.0000,held=2.0000,crc32={:08x}\n",
+                CRC_32.checksum(body.as_bytes())
+            )
+        );
+    }
+
+    #[test]
+    fn the_footer_goes_below_the_summaries_of_any_writer() {
+        let summaries = vec![AccountSummary {
+            client_id: 1,
+            available: Amount::new(12_345),
+            held: Amount::new(0),
+            total: Amount::new(12_345),
+            locked: false,
+        }];
+        let format = SummaryFormat {
+            decimal_places: Some(2),
+            trailing_zeros: true,
+            thousands_separator: false,
+        };
+        let footer = SummaryFooter::of(&summaries);
+        let mut written = AccountSummaryCsvWriter::write_with_totals(
+            summaries,
+            vec![AccountTotals::default()],
+            &format,
+        )
+        .unwrap();
+        let body = String::from_utf8(written.clone()).unwrap();
+        footer.append_to(&mut written);
+        assert_eq!(
+            String::from_utf8(written).unwrap(),
+            format!(
+                "{body}# accounts=1,available=1.2345,held=0.0000,crc32={:08x}\n",
+                CRC_32.checksum(body.as_bytes())
+            )
+        );
+    }
+
+    #[test]
+    fn can_write_account_summary_data_formatted() {
+        let summaries = vec![AccountSummary {
+            client_id: 1,
+            available: Amount::new(12_345_678_900),
+            held: Amount::new(5_000),
+            total: Amount::new(12_345_683_900),
+            locked: false,
+        }];
+        let format = SummaryFormat {
+            decimal_places: Some(2),
+            trailing_zeros: false,
+            thousands_separator: true,
+        };
+        assert_eq!(
+            String::from_utf8(
+                AccountSummaryCsvWriter::write_formatted(summaries, &format).unwrap()
+            )
+            .unwrap(),
+            "\
+            client,available,held,total,locked\n\
+            1,\"1,234,567.89\",0.5,\"1,234,568.39\",false\n"
+        );
+    }
+
+    #[test]
+    fn account_summaries_are_read_with_typed_amounts() {
+        let summaries: Vec<AccountSummary> = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader("client, available, held, total, locked\n1, 1.5, 0, 1.5, true".as_bytes())
+            .deserialize()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            summaries,
+            vec![AccountSummary {
+                client_id: 1,
+                available: Amount::new(15_000),
+                held: Amount::ZERO,
+                total: Amount::new(15_000),
+                locked: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn can_write_account_summary_data_as_csv() {
+        let account_summary_1 = AccountSummary {
+            client_id: 1122,
+            available: Amount::new(1_110_000),
+            held: Amount::new(2_220_000),
+            total: Amount::new(3_330_000),
+            locked: false,
+        };
+        let account_summary_2 = AccountSummary {
+            client_id: 3344,
+            available: Amount::new(3_330_000),
+            held: Amount::new(4_440_000),
+            total: Amount::new(7_770_000),
+            locked: true,
+        };
+
+        assert_eq!(
+            String::from_utf8(
+                AccountSummaryCsvWriter::write(vec![account_summary_1, account_summary_2]).unwrap()
+            )
+            .unwrap(),
+            "\
+            client,available,held,total,locked\n\
+            1122,111.0000,222.0000,333.0000,false\n\
+            3344,333.0000,444.0000,777.0000,true\n"
+        );
+    }
+
+    #[test]
+    fn can_write_extended_account_summary_data_as_csv() {
+        let account_summary = ExtendedAccountSummary {
+            client_id: 1122,
+            available: Amount::new(1_110_000),
+            held: Amount::new(2_220_000),
+            total: Amount::new(3_330_000),
+            locked: false,
+            fees: Amount::new(40_000),
+            credit_used: Amount::new(50_000),
+        };
+
+        assert_eq!(
+            String::from_utf8(
+                AccountSummaryCsvWriter::write_extended(vec![account_summary]).unwrap()
+            )
+            .unwrap(),
+            "\
+            client,available,held,total,locked,fees,credit_used\n\
+            1122,111.0000,222.0000,333.0000,false,4.0000,5.0000\n"
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use arrow::{
+    array::{ArrayRef, BooleanArray, StringArray, UInt16Array},
+    compute::{cast_with_options, CastOptions},
+    datatypes::{DataType, Field, Schema},
+    record_batch::RecordBatch,
+};
+use parquet::arrow::ArrowWriter;
+
+use super::{account_summary_csv::AccountSummaryWriterError, AccountSummary, Amount};
+
+/// Enough digits to hold any `Amount4DecimalBased`, which is backed by an i64.
+/// The decimal backed amounts may not fit in it.
+const AMOUNT_PRECISION: u8 = 19;
+const AMOUNT_SCALE: i8 = 4;
+
+/// Writes [`AccountSummary`]s as a single Parquet row group.
+/// The amounts are stored as `DECIMAL(19, 4)` so that they can be loaded into
+/// the data warehouse without going through any string conversion.
+pub struct AccountSummaryParquetWriter;
+
+impl AccountSummaryParquetWriter {
+    pub fn write(summaries: Vec<AccountSummary>) -> Result<Vec<u8>, AccountSummaryWriterError> {
+        let schema = Arc::new(schema());
+        let batch = RecordBatch::try_new(schema.clone(), columns(&summaries)?)
+            .map_err(|err| AccountSummaryWriterError::SerialisationError(err.to_string()))?;
+
+        let mut writer = ArrowWriter::try_new(vec![], schema, None)
+            .map_err(|err| AccountSummaryWriterError::SerialisationError(err.to_string()))?;
+        writer
+            .write(&batch)
+            .map_err(|err| AccountSummaryWriterError::SerialisationError(err.to_string()))?;
+        writer
+            .into_inner()
+            .map_err(|err| AccountSummaryWriterError::SerialisationError(err.to_string()))
+    }
+}
+
+fn schema() -> Schema {
+    let amount = DataType::Decimal128(AMOUNT_PRECISION, AMOUNT_SCALE);
+    Schema::new(vec![
+        Field::new("client", DataType::UInt16, false),
+        Field::new("available", amount.clone(), false),
+        Field::new("held", amount.clone(), false),
+        Field::new("total", amount, false),
+        Field::new("locked", DataType::Boolean, false),
+    ])
+}
+
+fn columns(summaries: &[AccountSummary]) -> Result<Vec<ArrayRef>, AccountSummaryWriterError> {
+    let client_ids = UInt16Array::from_iter_values(summaries.iter().map(|s| s.client_id));
+    let available = amounts(summaries.iter().map(|s| s.available))?;
+    let held = amounts(summaries.iter().map(|s| s.held))?;
+    let total = amounts(summaries.iter().map(|s| s.total))?;
+    let locked = BooleanArray::from_iter(summaries.iter().map(|s| Some(s.locked)));
+    Ok(vec![
+        Arc::new(client_ids),
+        available,
+        held,
+        total,
+        Arc::new(locked),
+    ])
+}
+
+/// The amounts are formatted and parsed back as decimals rather than going
+/// through a float, whatever backs them.
+fn amounts(values: impl Iterator<Item = Amount>) -> Result<ArrayRef, AccountSummaryWriterError> {
+    let strings = StringArray::from_iter_values(values.map(Amount::to_str));
+    let options = CastOptions {
+        safe: false,
+        ..Default::default()
+    };
+    cast_with_options(
+        &strings,
+        &DataType::Decimal128(AMOUNT_PRECISION, AMOUNT_SCALE),
+        &options,
+    )
+    .map_err(|err| AccountSummaryWriterError::SerialisationError(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::{
+        array::{AsArray, RecordBatch},
+        datatypes::{Decimal128Type, UInt16Type},
+    };
+    use bytes::Bytes;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use crate::model::{AccountSummary, Amount};
+
+    use super::AccountSummaryParquetWriter;
+
+    #[test]
+    fn can_write_account_summary_data_as_parquet() {
+        let account_summary_1 = AccountSummary {
+            client_id: 1122,
+            available: Amount::new(11_000),
+            held: Amount::new(22_000),
+            total: Amount::new(33_000),
+            locked: false,
+        };
+        let account_summary_2 = AccountSummary {
+            client_id: 3344,
+            available: Amount::new(-30_001),
+            held: Amount::new(40_000),
+            total: Amount::new(9_999),
+            locked: true,
+        };
+
+        let bytes =
+            AccountSummaryParquetWriter::write(vec![account_summary_1, account_summary_2]).unwrap();
+
+        let batches: Vec<RecordBatch> =
+            ParquetRecordBatchReaderBuilder::try_new(Bytes::from(bytes))
+                .unwrap()
+                .build()
+                .unwrap()
+                .collect::<Result<_, _>>()
+                .unwrap();
+        assert_eq!(batches.len(), 1);
+        let batch = &batches[0];
+
+        let client_ids = batch.column(0).as_primitive::<UInt16Type>();
+        assert_eq!(client_ids.values().to_vec(), vec![1122, 3344]);
+        let available = batch.column(1).as_primitive::<Decimal128Type>();
+        assert_eq!(available.values().to_vec(), vec![11_000, -30_001]);
+        let held = batch.column(2).as_primitive::<Decimal128Type>();
+        assert_eq!(held.values().to_vec(), vec![22_000, 40_000]);
+        let total = batch.column(3).as_primitive::<Decimal128Type>();
+        assert_eq!(total.values().to_vec(), vec![33_000, 9_999]);
+        assert_eq!(total.value_as_string(1), "0.9999");
+        let locked = batch.column(4).as_boolean();
+        assert_eq!(
+            locked.iter().collect::<Vec<_>>(),
+            vec![Some(false), Some(true)]
+        );
+    }
+}
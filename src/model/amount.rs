@@ -1,28 +1,166 @@
-use std::num::ParseFloatError;
+use alloc::{format, string::String};
+use core::{
+    iter::{self, Sum},
+    ops::{Add, AddAssign, Neg, Sub, SubAssign},
+    str::FromStr,
+};
+
+use thiserror::Error;
 
 use super::Amount4DecimalBased;
 
-#[derive(Debug)]
-struct AmountParseError;
+#[derive(Debug, Error, PartialEq, Clone, Copy)]
+pub enum AmountParseError {
+    #[error("the amount has no digits")]
+    Empty,
+    #[error("the amount is not a decimal number")]
+    InvalidDigit,
+    #[error("the amount is out of range")]
+    OutOfRange,
+}
+
+/// Reads a decimal number such as `-1234.5`, digit for digit, the digits
+/// beyond the fourth decimal place being truncated.
+impl FromStr for Amount4DecimalBased {
+    type Err = AmountParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, unsigned) = match s.strip_prefix('-') {
+            Some(unsigned) => (true, unsigned),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let (integer, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+        if integer.is_empty() && fraction.is_empty() {
+            return Err(AmountParseError::Empty);
+        }
+        if !integer
+            .bytes()
+            .chain(fraction.bytes())
+            .all(|digit| digit.is_ascii_digit())
+        {
+            return Err(AmountParseError::InvalidDigit);
+        }
+        let digits = integer
+            .bytes()
+            .chain(fraction.bytes().chain(iter::repeat(b'0')).take(4));
+        let mut ten_thousandths: i64 = 0;
+        for digit in digits {
+            ten_thousandths = ten_thousandths
+                .checked_mul(10)
+                .and_then(|shifted| shifted.checked_add(i64::from(digit - b'0')))
+                .ok_or(AmountParseError::OutOfRange)?;
+        }
+        Ok(Self(match negative {
+            true => -ten_thousandths,
+            false => ten_thousandths,
+        }))
+    }
+}
+
 impl Amount4DecimalBased {
-    pub fn from_str(s: &str) -> Result<Self, ParseFloatError> {
-        let mut v = s.parse::<f64>()?;
-        v *= 10_000f64;
-        Ok(Self(v as i64))
+    pub const ZERO: Self = Self(0);
+
+    /// The amount of the given number of ten-thousandths, e.g. `new(15_000)`
+    /// for 1.5.
+    pub const fn new(ten_thousandths: i64) -> Self {
+        Self(ten_thousandths)
+    }
+
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        self.0.checked_add(rhs.0).map(Self)
+    }
+
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.0.checked_sub(rhs.0).map(Self)
+    }
+
+    pub fn saturating_add(self, rhs: Self) -> Self {
+        Self(self.0.saturating_add(rhs.0))
+    }
+
+    pub fn saturating_sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+
+    pub fn abs(self) -> Self {
+        Self(self.0.abs())
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.0 < 0
     }
 
-    pub(crate) fn to_str(&self) -> String {
+    pub fn is_positive(self) -> bool {
+        self.0 > 0
+    }
+
+    /// The given percentage of the amount, e.g. `1.5` for 1.5%, fractions
+    /// of the smallest unit being truncated.
+    pub fn percentage(self, percentage: Self) -> Self {
+        Self((self.0 as i128 * percentage.0 as i128 / (100 * 10_000)) as i64)
+    }
+
+    #[cfg_attr(feature = "decimal", allow(dead_code))]
+    pub(crate) fn to_str(self) -> String {
         let mut f = self.0 as f64;
-        f /= 10_000 as f64;
+        f /= 10_000f64;
         format!("{:.4}", f)
     }
 }
 
+impl Add for Amount4DecimalBased {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Amount4DecimalBased {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0 - rhs.0)
+    }
+}
+
+impl Neg for Amount4DecimalBased {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self(-self.0)
+    }
+}
+
+impl AddAssign for Amount4DecimalBased {
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 += rhs.0;
+    }
+}
+
+impl SubAssign for Amount4DecimalBased {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl Sum for Amount4DecimalBased {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZERO, Add::add)
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::str::FromStr;
+
     use rstest::rstest;
 
-    use crate::model::Amount4DecimalBased;
+    use crate::model::{Amount4DecimalBased, AmountParseError};
 
     #[rstest]
     #[case("0", Amount4DecimalBased(0))]
@@ -38,10 +176,29 @@ mod tests {
     #[case("1", Amount4DecimalBased(10_000))]
     #[case("1.01", Amount4DecimalBased(10_100))]
     #[case("10.01", Amount4DecimalBased(100_100))]
+    #[case("0.0003", Amount4DecimalBased(3))]
+    #[case("1.00035", Amount4DecimalBased(10_003))]
+    #[case("-1.00035", Amount4DecimalBased(-10_003))]
+    #[case("+2.5", Amount4DecimalBased(25_000))]
+    #[case(".5", Amount4DecimalBased(5_000))]
+    #[case("5.", Amount4DecimalBased(50_000))]
+    #[case("922337203685477.5807", Amount4DecimalBased(i64::MAX))]
     fn deserialsation_works(#[case] input: &str, #[case] expected: Amount4DecimalBased) {
         assert_eq!(Amount4DecimalBased::from_str(input).unwrap(), expected);
     }
 
+    #[rstest]
+    #[case("", AmountParseError::Empty)]
+    #[case("-.", AmountParseError::Empty)]
+    #[case("1e3", AmountParseError::InvalidDigit)]
+    #[case("1.2.3", AmountParseError::InvalidDigit)]
+    #[case(" 1", AmountParseError::InvalidDigit)]
+    #[case("inf", AmountParseError::InvalidDigit)]
+    #[case("922337203685477.5808", AmountParseError::OutOfRange)]
+    fn malformed_amounts_are_not_read(#[case] input: &str, #[case] expected: AmountParseError) {
+        assert_eq!(Amount4DecimalBased::from_str(input), Err(expected));
+    }
+
     #[rstest]
     #[case(Amount4DecimalBased(0), "0.0000")]
     #[case(Amount4DecimalBased(1), "0.0001")]
@@ -54,4 +211,50 @@ mod tests {
     fn serialsation_works(#[case] amount: Amount4DecimalBased, #[case] expected: &str) {
         assert_eq!(amount.to_str(), expected);
     }
+
+    #[test]
+    fn arithmetic_works() {
+        let one = Amount4DecimalBased(10_000);
+        let two = Amount4DecimalBased(20_000);
+        assert_eq!(one + two, Amount4DecimalBased(30_000));
+        assert_eq!(one - two, -one);
+        assert_eq!(
+            [one, two, one].into_iter().sum::<Amount4DecimalBased>(),
+            two + two
+        );
+        let mut amount = Amount4DecimalBased::ZERO;
+        amount += two;
+        amount -= one;
+        assert_eq!(amount, one);
+        assert!(one < two && (-one).is_negative() && one.is_positive());
+        assert_eq!((one - two).abs(), one);
+    }
+
+    #[rustfmt::skip]
+    #[rstest]
+    #[case(Amount4DecimalBased(i64::MAX), Amount4DecimalBased(1),  None,                            Amount4DecimalBased(i64::MAX))]
+    #[case(Amount4DecimalBased(1),        Amount4DecimalBased(2),  Some(Amount4DecimalBased(3)),    Amount4DecimalBased(3))]
+    fn additions_can_be_checked_or_saturated(
+        #[case] a: Amount4DecimalBased,
+        #[case] b: Amount4DecimalBased,
+        #[case] checked: Option<Amount4DecimalBased>,
+        #[case] saturated: Amount4DecimalBased,
+    ) {
+        assert_eq!(a.checked_add(b), checked);
+        assert_eq!(a.saturating_add(b), saturated);
+    }
+
+    #[rustfmt::skip]
+    #[rstest]
+    #[case(Amount4DecimalBased(i64::MIN), Amount4DecimalBased(1),  None,                            Amount4DecimalBased(i64::MIN))]
+    #[case(Amount4DecimalBased(1),        Amount4DecimalBased(2),  Some(Amount4DecimalBased(-1)),   Amount4DecimalBased(-1))]
+    fn subtractions_can_be_checked_or_saturated(
+        #[case] a: Amount4DecimalBased,
+        #[case] b: Amount4DecimalBased,
+        #[case] checked: Option<Amount4DecimalBased>,
+        #[case] saturated: Amount4DecimalBased,
+    ) {
+        assert_eq!(a.checked_sub(b), checked);
+        assert_eq!(a.saturating_sub(b), saturated);
+    }
 }
@@ -0,0 +1,17 @@
+use alloc::{format, string::String};
+use core::str::FromStr;
+
+use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+use super::Amount;
+
+/// Serialises an amount the way the summaries have always written it, i.e.
+/// with 4 decimal places, for `#[serde(with = "amount_format")]`.
+pub(crate) fn serialize<S: Serializer>(amount: &Amount, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&amount.to_str())
+}
+
+pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Amount, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    Amount::from_str(&s).map_err(|err| D::Error::custom(format!("Invalid amount `{s}`: {err}")))
+}
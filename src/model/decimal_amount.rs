@@ -0,0 +1,225 @@
+use std::{
+    iter::Sum,
+    ops::{Add, AddAssign, Neg, Sub, SubAssign},
+    str::FromStr,
+};
+
+use rust_decimal::Decimal;
+use thiserror::Error;
+
+use super::Amount4DecimalBased;
+
+/// The amount backed by a [`Decimal`], for when 4 decimal places or
+/// `i64::MAX / 10_000` are not enough.
+/// It has the same API as [`Amount4DecimalBased`], which it replaces as the
+/// [`Amount`](super::Amount) with the `decimal` feature.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+pub struct DecimalAmount(pub Decimal);
+
+#[derive(Debug, Error, PartialEq)]
+pub enum AmountConversionError {
+    #[error("{0} does not fit in 4 decimal places backed by an i64")]
+    OutOfRange(Decimal),
+}
+
+impl FromStr for DecimalAmount {
+    type Err = rust_decimal::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Decimal::from_str(s).map(Self)
+    }
+}
+
+impl DecimalAmount {
+    pub const ZERO: Self = Self(Decimal::ZERO);
+
+    /// The amount of the given number of ten-thousandths, e.g. `new(15_000)`
+    /// for 1.5.
+    pub const fn new(ten_thousandths: i64) -> Self {
+        let units = ten_thousandths.unsigned_abs();
+        Self(Decimal::from_parts(
+            units as u32,
+            (units >> 32) as u32,
+            0,
+            ten_thousandths < 0,
+            4,
+        ))
+    }
+
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        self.0.checked_add(rhs.0).map(Self)
+    }
+
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.0.checked_sub(rhs.0).map(Self)
+    }
+
+    pub fn saturating_add(self, rhs: Self) -> Self {
+        Self(self.0.saturating_add(rhs.0))
+    }
+
+    pub fn saturating_sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+
+    pub fn abs(self) -> Self {
+        Self(self.0.abs())
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0.is_zero()
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.0 < Decimal::ZERO
+    }
+
+    pub fn is_positive(self) -> bool {
+        self.0 > Decimal::ZERO
+    }
+
+    /// The given percentage of the amount, e.g. `1.5` for 1.5%, with all of
+    /// its decimal places.
+    pub fn percentage(self, percentage: Self) -> Self {
+        Self(self.0 * percentage.0 / Decimal::ONE_HUNDRED)
+    }
+
+    /// With 4 decimal places as ever, or more if the amount has them.
+    pub(crate) fn to_str(self) -> String {
+        let normalized = self.0.normalize();
+        if normalized.scale() <= 4 {
+            format!("{normalized:.4}")
+        } else {
+            normalized.to_string()
+        }
+    }
+}
+
+impl From<Amount4DecimalBased> for DecimalAmount {
+    fn from(amount: Amount4DecimalBased) -> Self {
+        Self::new(amount.0)
+    }
+}
+
+impl TryFrom<DecimalAmount> for Amount4DecimalBased {
+    type Error = AmountConversionError;
+
+    fn try_from(amount: DecimalAmount) -> Result<Self, Self::Error> {
+        let out_of_range = || AmountConversionError::OutOfRange(amount.0);
+        let mut decimal = amount.0.normalize();
+        if decimal.scale() > 4 {
+            return Err(out_of_range());
+        }
+        decimal.rescale(4);
+        if decimal.scale() != 4 {
+            return Err(out_of_range());
+        }
+        i64::try_from(decimal.mantissa())
+            .map(Amount4DecimalBased)
+            .map_err(|_| out_of_range())
+    }
+}
+
+impl Add for DecimalAmount {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl Sub for DecimalAmount {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0 - rhs.0)
+    }
+}
+
+impl Neg for DecimalAmount {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self(-self.0)
+    }
+}
+
+impl AddAssign for DecimalAmount {
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 += rhs.0;
+    }
+}
+
+impl SubAssign for DecimalAmount {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl Sum for DecimalAmount {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZERO, Add::add)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use rstest::rstest;
+    use rust_decimal::Decimal;
+
+    use crate::model::Amount4DecimalBased;
+
+    use super::{AmountConversionError, DecimalAmount};
+
+    #[rstest]
+    #[case(0)]
+    #[case(1)]
+    #[case(-15_000)]
+    #[case(i64::MAX)]
+    #[case(i64::MIN)]
+    fn amounts_convert_both_ways_between_backends(#[case] ten_thousandths: i64) {
+        let amount = Amount4DecimalBased(ten_thousandths);
+        let decimal = DecimalAmount::from(amount);
+        assert_eq!(Amount4DecimalBased::try_from(decimal), Ok(amount));
+    }
+
+    #[rstest]
+    #[case("0.00001")]
+    #[case("922337203685477.5808")]
+    fn amounts_beyond_4_decimal_places_or_an_i64_do_not_convert(#[case] amount: &str) {
+        let decimal = DecimalAmount::from_str(amount).unwrap();
+        assert_eq!(
+            Amount4DecimalBased::try_from(decimal),
+            Err(AmountConversionError::OutOfRange(
+                Decimal::from_str(amount).unwrap()
+            ))
+        );
+    }
+
+    #[rstest]
+    #[case("1", "1.0000")]
+    #[case("1.50", "1.5000")]
+    #[case("0.00001", "0.00001")]
+    #[case("-2.123456", "-2.123456")]
+    fn amounts_are_written_with_at_least_4_decimal_places(
+        #[case] amount: &str,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(DecimalAmount::from_str(amount).unwrap().to_str(), expected);
+    }
+
+    #[test]
+    fn arithmetic_matches_the_default_backend() {
+        let (a, b) = (Amount4DecimalBased(12_300), Amount4DecimalBased(-678));
+        let (decimal_a, decimal_b) = (DecimalAmount::from(a), DecimalAmount::from(b));
+        assert_eq!(DecimalAmount::from(a + b), decimal_a + decimal_b);
+        assert_eq!(DecimalAmount::from(a - b), decimal_a - decimal_b);
+        assert_eq!(
+            DecimalAmount::from(a.percentage(Amount4DecimalBased(20_000))),
+            decimal_a.percentage(DecimalAmount::new(20_000))
+        );
+        assert_eq!(a < b, decimal_a < decimal_b);
+    }
+}
@@ -0,0 +1,89 @@
+use alloc::{collections::BTreeSet, vec::Vec};
+
+use super::{AccountSummary, Amount, ClientId};
+
+/// Which summaries are written, e.g. only the locked accounts for the fraud
+/// team. All of them by default.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SummaryFilter {
+    /// Whether only the locked accounts are kept.
+    pub locked_only: bool,
+
+    /// Whether only the accounts with held funds are kept.
+    pub held_only: bool,
+
+    /// The clients whose accounts are kept, all of them if not set.
+    pub clients: Option<BTreeSet<ClientId>>,
+}
+
+impl SummaryFilter {
+    pub fn keeps(&self, summary: &AccountSummary) -> bool {
+        (!self.locked_only || summary.locked)
+            && (!self.held_only || summary.held != Amount::new(0))
+            && self
+                .clients
+                .as_ref()
+                .is_none_or(|clients| clients.contains(&summary.client_id))
+    }
+
+    /// The summaries kept, in the order they were given.
+    pub fn apply(&self, summaries: Vec<AccountSummary>) -> Vec<AccountSummary> {
+        summaries
+            .into_iter()
+            .filter(|summary| self.keeps(summary))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use rstest::rstest;
+
+    use crate::model::{AccountSummary, Amount};
+
+    use super::SummaryFilter;
+
+    fn summary(client_id: u16, held: i64, locked: bool) -> AccountSummary {
+        AccountSummary {
+            client_id,
+            available: Amount::new(10_000),
+            held: Amount::new(held),
+            total: Amount::new(10_000 + held),
+            locked,
+        }
+    }
+
+    #[rstest]
+    #[case::everything(SummaryFilter::default(), vec![1, 2, 3, 4])]
+    #[case::locked_only(SummaryFilter { locked_only: true, ..Default::default() }, vec![2, 4])]
+    #[case::held_only(SummaryFilter { held_only: true, ..Default::default() }, vec![3, 4])]
+    #[case::locked_and_held(
+        SummaryFilter { locked_only: true, held_only: true, ..Default::default() },
+        vec![4]
+    )]
+    #[case::clients(
+        SummaryFilter { clients: Some(BTreeSet::from([1, 4, 5])), ..Default::default() },
+        vec![1, 4]
+    )]
+    fn summaries_are_kept_as_per_the_filter(
+        #[case] filter: SummaryFilter,
+        #[case] expected: Vec<u16>,
+    ) {
+        let summaries = vec![
+            summary(1, 0, false),
+            summary(2, 0, true),
+            summary(3, 5_000, false),
+            summary(4, 5_000, true),
+        ];
+        assert_eq!(
+            filter
+                .apply(summaries)
+                .iter()
+                .map(|summary| summary.client_id)
+                .collect::<Vec<_>>(),
+            expected
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use alloc::{string::String, vec::Vec};
+
+use super::Amount;
+
+/// How the amounts of the summaries are written.
+/// The default writes them as they have always been, i.e. with 4 decimal
+/// places, or more if an amount has them.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SummaryFormat {
+    /// The number of decimal places, the amounts being rounded half away
+    /// from zero if they have more. All of them if not set.
+    pub decimal_places: Option<u32>,
+
+    /// Whether the decimal places are padded with zeros, e.g. `1.5000` rather
+    /// than `1.5`, and `3.0000` rather than `3`.
+    pub trailing_zeros: bool,
+
+    /// Whether the thousands are separated by a `,`, e.g. `1,234.5000`.
+    pub thousands_separator: bool,
+}
+
+impl Default for SummaryFormat {
+    fn default() -> Self {
+        Self {
+            decimal_places: None,
+            trailing_zeros: true,
+            thousands_separator: false,
+        }
+    }
+}
+
+impl SummaryFormat {
+    /// The amount written as per the format, e.g. `1,234.5` for 1234.5 with
+    /// no trailing zeros and a thousands separator.
+    pub fn format(&self, amount: Amount) -> String {
+        let s = amount.to_str();
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(s) => (true, s),
+            None => (false, s.as_str()),
+        };
+        let (integer, fraction) = s.split_once('.').unwrap_or((s, ""));
+        let mut digits: Vec<u8> = integer.bytes().chain(fraction.bytes()).collect();
+        let mut scale = fraction.len();
+        if let Some(decimal_places) = self.decimal_places {
+            let decimal_places = decimal_places as usize;
+            if decimal_places < scale {
+                let round_up = digits[digits.len() - (scale - decimal_places)] >= b'5';
+                digits.truncate(digits.len() - (scale - decimal_places));
+                if round_up {
+                    increment(&mut digits);
+                }
+            } else {
+                digits.resize(digits.len() + decimal_places - scale, b'0');
+            }
+            scale = decimal_places;
+        }
+        if !self.trailing_zeros {
+            while scale > 0 && digits.last() == Some(&b'0') {
+                digits.pop();
+                scale -= 1;
+            }
+        }
+
+        let (integer, fraction) = digits.split_at(digits.len() - scale);
+        let mut formatted = String::new();
+        // unless rounded to zero, e.g. -0.0001 with 2 decimal places
+        if negative && digits.iter().any(|digit| *digit != b'0') {
+            formatted.push('-');
+        }
+        for (i, digit) in integer.iter().enumerate() {
+            if self.thousands_separator && i > 0 && (integer.len() - i) % 3 == 0 {
+                formatted.push(',');
+            }
+            formatted.push(char::from(*digit));
+        }
+        if !fraction.is_empty() {
+            formatted.push('.');
+            formatted.extend(fraction.iter().map(|digit| char::from(*digit)));
+        }
+        formatted
+    }
+}
+
+/// Adds one to the decimal digits, carrying over as far as needed.
+fn increment(digits: &mut Vec<u8>) {
+    for digit in digits.iter_mut().rev() {
+        if *digit == b'9' {
+            *digit = b'0';
+        } else {
+            *digit += 1;
+            return;
+        }
+    }
+    digits.insert(0, b'1');
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::model::Amount;
+
+    use super::SummaryFormat;
+
+    #[rustfmt::skip]
+    #[rstest]
+    #[case(15_000,         None,    true,  false, "1.5000")]
+    #[case(15_000,         Some(2), true,  false, "1.50")]
+    #[case(10_050,         Some(2), true,  false, "1.01")]
+    #[case(-10_050,        Some(2), true,  false, "-1.01")]
+    #[case(99_999,         Some(2), true,  false, "10.00")]
+    #[case(-1,             Some(2), true,  false, "0.00")]
+    #[case(25_000,         Some(0), true,  false, "3")]
+    #[case(15_000,         None,    false, false, "1.5")]
+    #[case(30_000,         None,    false, false, "3")]
+    #[case(12_000,         Some(2), false, false, "1.2")]
+    #[case(12_345_675_000, None,    true,  true,  "1,234,567.5000")]
+    #[case(-1_234_560_000, Some(2), true,  true,  "-123,456.00")]
+    #[case(9_999_990,      Some(2), false, true,  "1,000")]
+    fn amounts_are_formatted_as_asked(
+        #[case] ten_thousandths: i64,
+        #[case] decimal_places: Option<u32>,
+        #[case] trailing_zeros: bool,
+        #[case] thousands_separator: bool,
+        #[case] expected: &str,
+    ) {
+        let format = SummaryFormat {
+            decimal_places,
+            trailing_zeros,
+            thousands_separator,
+        };
+        assert_eq!(format.format(Amount::new(ten_thousandths)), expected);
+    }
+}
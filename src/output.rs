@@ -0,0 +1,144 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum OutputError {
+    #[error("Failed to write {0}: {1}")]
+    Io(PathBuf, String),
+}
+
+/// Writes reports to disk so that they are never read half written: each
+/// one is written to a hidden temporary file next to it, synced, then
+/// renamed into place, a crash leaving either the previous report or the
+/// new one, but never a truncated one.
+/// With [`AtomicFileWriter::with_done_marker`], an empty `<name>.done` file
+/// is written once the report is in place, for downstream jobs that wait for
+/// it, any marker of a previous report being removed before the new one is
+/// written.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AtomicFileWriter {
+    done_marker: bool,
+}
+
+impl AtomicFileWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_done_marker(self, done_marker: bool) -> Self {
+        Self { done_marker }
+    }
+
+    pub fn done_marker(&self) -> bool {
+        self.done_marker
+    }
+
+    /// Replaces the file at `path` with `contents`.
+    pub fn write(&self, path: impl AsRef<Path>, contents: &[u8]) -> Result<(), OutputError> {
+        let path = path.as_ref();
+        let io_error = |err: std::io::Error| OutputError::Io(path.to_path_buf(), err.to_string());
+        let marker = with_suffix(path, ".done");
+        if self.done_marker && marker.exists() {
+            fs::remove_file(&marker).map_err(io_error)?;
+        }
+        let temporary = temporary_path(path);
+        let mut file = File::create(&temporary).map_err(io_error)?;
+        file.write_all(contents)
+            .and_then(|()| file.sync_all())
+            .map_err(io_error)?;
+        fs::rename(&temporary, path).map_err(io_error)?;
+        sync_parent(path).map_err(io_error)?;
+        if self.done_marker {
+            File::create(&marker)
+                .and_then(|marker| marker.sync_all())
+                .map_err(io_error)?;
+            sync_parent(path).map_err(io_error)?;
+        }
+        Ok(())
+    }
+}
+
+/// `.<name>.tmp` in the same directory, so that the rename does not cross
+/// file systems and the file is left alone by the likes of
+/// [`DirectoryWatcher`] meanwhile.
+///
+/// [`DirectoryWatcher`]: crate::watch::DirectoryWatcher
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_os_string();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Syncs the directory of the file, for its rename to survive a crash.
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> std::io::Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    File::open(parent)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf, process};
+
+    use super::AtomicFileWriter;
+
+    /// A directory of its own for each test, empty.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("jouet_paiement_{name}_{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn reports_replace_the_previous_ones_without_leaving_anything_behind() {
+        let dir = test_dir("output_replaced");
+        let path = dir.join("summaries.csv");
+        fs::write(&path, "previous").unwrap();
+
+        AtomicFileWriter::new().write(&path, b"new").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn the_done_marker_is_written_once_the_report_is_in_place() {
+        let dir = test_dir("output_done");
+        let path = dir.join("summaries.csv");
+        let writer = AtomicFileWriter::new().with_done_marker(true);
+
+        writer.write(&path, b"first").unwrap();
+        writer.write(&path, b"second").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert_eq!(
+            fs::read_to_string(dir.join("summaries.csv.done")).unwrap(),
+            ""
+        );
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,298 @@
+use std::{collections::BTreeMap, io::Read};
+
+use csv::{ReaderBuilder, Trim, WriterBuilder};
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::model::{AccountSummary, Amount, ClientId};
+
+/// What differs between the two sides for a client.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DifferenceKind {
+    /// The client is only in their summaries.
+    MissingFromOurs,
+
+    /// The client is only in our summaries.
+    MissingFromTheirs,
+
+    Available,
+    Held,
+    Total,
+    Locked,
+}
+
+/// A line of the difference report.
+/// `ours` and `theirs` are the values as they are in the summaries, absent
+/// for the side the client is missing from.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Difference {
+    #[serde(rename = "client")]
+    pub client_id: ClientId,
+    #[serde(rename = "difference")]
+    pub kind: DifferenceKind,
+    pub ours: Option<String>,
+    pub theirs: Option<String>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ReconciliationError {
+    #[error("Failed to read the summaries: {0}")]
+    ParsingError(String),
+
+    #[error("Client {0} appears more than once in the summaries.")]
+    DuplicateClient(ClientId),
+
+    #[error("Failed to write the difference report: {0}")]
+    SerialisationError(String),
+}
+
+/// Compares two sets of account summaries, e.g. ours against a partner's
+/// ledger.
+/// Balances are considered to match if they are no further apart than the
+/// tolerance.
+pub struct Reconciler {
+    tolerance: Amount,
+}
+
+impl Reconciler {
+    pub fn new(tolerance: Amount) -> Self {
+        Self { tolerance }
+    }
+
+    /// Reads both sides as summary CSVs and returns their differences,
+    /// ordered by client.
+    pub fn reconcile(
+        &self,
+        ours: impl Read,
+        theirs: impl Read,
+    ) -> Result<Vec<Difference>, ReconciliationError> {
+        let mut ours = read_summaries(ours)?;
+        let theirs = read_summaries(theirs)?;
+
+        let mut differences = Vec::new();
+        for (client_id, theirs) in theirs {
+            match ours.remove(&client_id) {
+                Some(ours) => differences.extend(self.compare(&ours, &theirs)),
+                None => differences.push(Difference {
+                    client_id,
+                    kind: DifferenceKind::MissingFromOurs,
+                    ours: None,
+                    theirs: Some(describe(&theirs)),
+                }),
+            }
+        }
+        differences.extend(ours.into_values().map(|ours| Difference {
+            client_id: ours.client_id,
+            kind: DifferenceKind::MissingFromTheirs,
+            ours: Some(describe(&ours)),
+            theirs: None,
+        }));
+        differences.sort_by_key(|difference| difference.client_id);
+        Ok(differences)
+    }
+
+    fn compare(&self, ours: &AccountSummary, theirs: &AccountSummary) -> Vec<Difference> {
+        let mut differences = Vec::new();
+        for (kind, our_value, their_value) in [
+            (DifferenceKind::Available, ours.available, theirs.available),
+            (DifferenceKind::Held, ours.held, theirs.held),
+            (DifferenceKind::Total, ours.total, theirs.total),
+        ] {
+            if (our_value - their_value).abs() > self.tolerance {
+                differences.push(Difference {
+                    client_id: ours.client_id,
+                    kind,
+                    ours: Some(our_value.to_str()),
+                    theirs: Some(their_value.to_str()),
+                });
+            }
+        }
+        if ours.locked != theirs.locked {
+            differences.push(Difference {
+                client_id: ours.client_id,
+                kind: DifferenceKind::Locked,
+                ours: Some(ours.locked.to_string()),
+                theirs: Some(theirs.locked.to_string()),
+            });
+        }
+        differences
+    }
+}
+
+/// Writes the differences as CSV with the columns `client`, `difference`,
+/// `ours` and `theirs`.
+pub struct DifferenceReportWriter;
+
+impl DifferenceReportWriter {
+    pub fn write(differences: Vec<Difference>) -> Result<Vec<u8>, ReconciliationError> {
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        for difference in differences {
+            wtr.serialize(difference)
+                .map_err(|err| ReconciliationError::SerialisationError(err.to_string()))?;
+        }
+        wtr.into_inner()
+            .map_err(|err| ReconciliationError::SerialisationError(err.to_string()))
+    }
+}
+
+fn read_summaries(r: impl Read) -> Result<BTreeMap<ClientId, AccountSummary>, ReconciliationError> {
+    let mut summaries = BTreeMap::new();
+    for result in ReaderBuilder::new()
+        .trim(Trim::All)
+        // the footer, if the summaries were written with one
+        .comment(Some(b'#'))
+        .from_reader(r)
+        .deserialize::<AccountSummary>()
+    {
+        let summary = result.map_err(|err| ReconciliationError::ParsingError(err.to_string()))?;
+        let client_id = summary.client_id;
+        if summaries.insert(client_id, summary).is_some() {
+            return Err(ReconciliationError::DuplicateClient(client_id));
+        }
+    }
+    Ok(summaries)
+}
+
+fn describe(summary: &AccountSummary) -> String {
+    format!(
+        "available={} held={} total={} locked={}",
+        summary.available.to_str(),
+        summary.held.to_str(),
+        summary.total.to_str(),
+        summary.locked
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use rstest::rstest;
+
+    use crate::model::{Amount, ClientId};
+
+    use super::{
+        Difference, DifferenceKind, DifferenceReportWriter, Reconciler, ReconciliationError,
+    };
+
+    const OURS: &str = "
+    client, available,   held,   total, locked
+         1,    1.0000, 0.0000,  1.0000,  false
+         2,    2.0000, 0.5000,  2.5000,  false
+         3,    3.0000, 0.0000,  3.0000,   true";
+
+    #[rstest]
+    #[case::identical(OURS, 0, vec![])]
+    #[case::with_a_footer("
+    client, available,   held,   total, locked
+         1,    1.0000, 0.0000,  1.0000,  false
+         2,    2.0000, 0.5000,  2.5000,  false
+         3,    3.0000, 0.0000,  3.0000,   true
+# accounts=3,available=6.0000,held=0.5000,crc32=00000000
+", 0, vec![])]
+    #[case::within_tolerance("
+    client, available,   held,   total, locked
+         1,    1.0001, 0.0000,  1.0001,  false
+         2,    2.0000, 0.5000,  2.5000,  false
+         3,    3.0000, 0.0000,  3.0000,   true", 1, vec![])]
+    #[case::beyond_tolerance("
+    client, available,   held,   total, locked
+         1,    1.0002, 0.0000,  1.0002,  false
+         2,    2.0000, 0.5000,  2.5000,  false
+         3,    3.0000, 0.0000,  3.0000,   true", 1, vec![
+        mismatch(1, DifferenceKind::Available, "1.0000", "1.0002"),
+        mismatch(1, DifferenceKind::Total, "1.0000", "1.0002"),
+    ])]
+    #[case::held_and_locked_mismatch("
+    client, available,   held,   total, locked
+         1,    1.0000, 0.0000,  1.0000,  false
+         2,    2.0000, 0.0000,  2.0000,  false
+         3,    3.0000, 0.0000,  3.0000,  false", 0, vec![
+        mismatch(2, DifferenceKind::Held, "0.5000", "0.0000"),
+        mismatch(2, DifferenceKind::Total, "2.5000", "2.0000"),
+        mismatch(3, DifferenceKind::Locked, "true", "false"),
+    ])]
+    #[case::missing_clients("
+    client, available,   held,   total, locked
+         2,    2.0000, 0.5000,  2.5000,  false
+         3,    3.0000, 0.0000,  3.0000,   true
+         4,    4.0000, 0.0000,  4.0000,  false", 0, vec![
+        Difference {
+            client_id: 1,
+            kind: DifferenceKind::MissingFromTheirs,
+            ours: Some("available=1.0000 held=0.0000 total=1.0000 locked=false".to_string()),
+            theirs: None,
+        },
+        Difference {
+            client_id: 4,
+            kind: DifferenceKind::MissingFromOurs,
+            ours: None,
+            theirs: Some("available=4.0000 held=0.0000 total=4.0000 locked=false".to_string()),
+        },
+    ])]
+    fn reconciliation_works(
+        #[case] theirs: &str,
+        #[case] tolerance: i64,
+        #[case] expected: Vec<Difference>,
+    ) {
+        let reconciler = Reconciler::new(Amount::new(tolerance));
+        assert_eq!(
+            reconciler
+                .reconcile(OURS.as_bytes(), theirs.as_bytes())
+                .unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn duplicate_client_is_rejected() {
+        let theirs = "
+    client, available,   held,   total, locked
+         1,    1.0000, 0.0000,  1.0000,  false
+         1,    1.0000, 0.0000,  1.0000,  false";
+        assert_eq!(
+            Reconciler::new(Amount::new(0)).reconcile(OURS.as_bytes(), theirs.as_bytes()),
+            Err(ReconciliationError::DuplicateClient(1))
+        );
+    }
+
+    #[test]
+    fn invalid_amount_is_rejected() {
+        let theirs = "
+    client, available,   held,   total, locked
+         1,       one, 0.0000,  1.0000,  false";
+        assert_matches!(
+            Reconciler::new(Amount::new(0)).reconcile(OURS.as_bytes(), theirs.as_bytes()),
+            Err(ReconciliationError::ParsingError(_))
+        );
+    }
+
+    #[test]
+    fn can_write_the_difference_report() {
+        let differences = vec![
+            mismatch(2, DifferenceKind::Held, "0.5000", "0.0000"),
+            Difference {
+                client_id: 4,
+                kind: DifferenceKind::MissingFromOurs,
+                ours: None,
+                theirs: Some("available=4.0000 held=0.0000 total=4.0000 locked=false".to_string()),
+            },
+        ];
+        assert_eq!(
+            String::from_utf8(DifferenceReportWriter::write(differences).unwrap()).unwrap(),
+            "\
+            client,difference,ours,theirs\n\
+            2,held,0.5000,0.0000\n\
+            4,missing_from_ours,,available=4.0000 held=0.0000 total=4.0000 locked=false\n"
+        );
+    }
+
+    fn mismatch(client_id: ClientId, kind: DifferenceKind, ours: &str, theirs: &str) -> Difference {
+        Difference {
+            client_id,
+            kind,
+            ours: Some(ours.to_string()),
+            theirs: Some(theirs.to_string()),
+        }
+    }
+}
@@ -0,0 +1,107 @@
+mod rules;
+mod scoring;
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::model::Transaction;
+
+pub use rules::{CumulativeWithdrawals, WithdrawalVelocity};
+pub use scoring::{RiskScore, RiskScorer, RuleBasedScorer, ScoredTransaction};
+
+/// What a [`RiskRule`] makes of a transaction.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RiskOutcome {
+    Pass,
+
+    /// The transaction is applied, but reported.
+    Flagged,
+
+    /// The transaction is not applied, and reported.
+    Blocked,
+}
+
+/// A first-pass fraud screening rule, evaluated before a transaction is
+/// applied.
+/// The transactions of a client are evaluated one after another and in the
+/// order of the input, those of different clients possibly concurrently.
+/// Every transaction is evaluated, blocked ones included, so that they count
+/// towards the rules keeping track of what the clients attempt.
+pub trait RiskRule {
+    /// How the rule is referred to in the risk report.
+    fn name(&self) -> &str;
+
+    fn evaluate(&self, transaction: &Transaction) -> RiskOutcome;
+}
+
+/// A transaction flagged or blocked by a rule.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RiskEvent {
+    pub transaction: Transaction,
+    pub rule: String,
+    pub outcome: RiskOutcome,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct RiskReport {
+    /// Ordered by client and then as they are in the input.
+    pub events: Vec<RiskEvent>,
+}
+
+impl Display for RiskReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for RiskEvent {
+            transaction,
+            rule,
+            outcome,
+        } in &self.events
+        {
+            let outcome = match outcome {
+                RiskOutcome::Pass => "passed",
+                RiskOutcome::Flagged => "flagged",
+                RiskOutcome::Blocked => "blocked",
+            };
+            writeln!(f, "{outcome}: {} by {rule}", transaction.described())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{Amount, Transaction, TransactionKind};
+
+    use super::{RiskEvent, RiskOutcome, RiskReport};
+
+    #[test]
+    fn risk_report_can_be_displayed() {
+        let report = RiskReport {
+            events: vec![
+                event(3, RiskOutcome::Flagged, "withdrawal_velocity"),
+                event(4, RiskOutcome::Blocked, "cumulative_withdrawals"),
+            ],
+        };
+        assert_eq!(
+            report.to_string(),
+            "\
+            flagged: client 1, tx 3 (withdrawal) by withdrawal_velocity\n\
+            blocked: client 1, tx 4 (withdrawal) by cumulative_withdrawals\n"
+        );
+    }
+
+    fn event(transaction_id: u32, outcome: RiskOutcome, rule: &str) -> RiskEvent {
+        RiskEvent {
+            transaction: Transaction {
+                client_id: 1,
+                transaction_id,
+                kind: TransactionKind::Withdrawal {
+                    amount: Amount::new(10_000),
+                },
+                timestamp: None,
+                memo: None,
+                ingest_sequence: None,
+            },
+            rule: rule.to_string(),
+            outcome,
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+use crate::model::{Amount, ClientId, Transaction, TransactionKind};
+
+use super::{RiskOutcome, RiskRule};
+
+/// Catches clients withdrawing more than `max_withdrawals` times within
+/// their last `within_records` transactions.
+pub struct WithdrawalVelocity {
+    max_withdrawals: usize,
+    within_records: usize,
+    outcome: RiskOutcome,
+    /// Whether each of the last transactions of the clients is a withdrawal.
+    history: Mutex<HashMap<ClientId, VecDeque<bool>>>,
+}
+
+impl WithdrawalVelocity {
+    pub fn new(max_withdrawals: usize, within_records: usize, outcome: RiskOutcome) -> Self {
+        Self {
+            max_withdrawals,
+            within_records,
+            outcome,
+            history: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl RiskRule for WithdrawalVelocity {
+    fn name(&self) -> &str {
+        "withdrawal_velocity"
+    }
+
+    fn evaluate(&self, transaction: &Transaction) -> RiskOutcome {
+        let is_withdrawal = matches!(transaction.kind, TransactionKind::Withdrawal { .. });
+        let mut history = self
+            .history
+            .lock()
+            .expect("The history is never locked across a panic.");
+        let records = history.entry(transaction.client_id).or_default();
+        records.push_back(is_withdrawal);
+        if records.len() > self.within_records {
+            records.pop_front();
+        }
+        let withdrawals = records
+            .iter()
+            .filter(|is_withdrawal| **is_withdrawal)
+            .count();
+        if is_withdrawal && withdrawals > self.max_withdrawals {
+            self.outcome
+        } else {
+            RiskOutcome::Pass
+        }
+    }
+}
+
+/// Catches clients whose withdrawals add up to more than `limit`.
+pub struct CumulativeWithdrawals {
+    limit: Amount,
+    outcome: RiskOutcome,
+    totals: Mutex<HashMap<ClientId, Amount>>,
+}
+
+impl CumulativeWithdrawals {
+    pub fn new(limit: Amount, outcome: RiskOutcome) -> Self {
+        Self {
+            limit,
+            outcome,
+            totals: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl RiskRule for CumulativeWithdrawals {
+    fn name(&self) -> &str {
+        "cumulative_withdrawals"
+    }
+
+    fn evaluate(&self, transaction: &Transaction) -> RiskOutcome {
+        let TransactionKind::Withdrawal { amount } = transaction.kind else {
+            return RiskOutcome::Pass;
+        };
+        let mut totals = self
+            .totals
+            .lock()
+            .expect("The totals are never locked across a panic.");
+        let total = totals.entry(transaction.client_id).or_default();
+        *total += amount;
+        if *total > self.limit {
+            self.outcome
+        } else {
+            RiskOutcome::Pass
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::{
+        model::{Amount, ClientId, Transaction, TransactionKind},
+        risk::{RiskOutcome, RiskRule},
+    };
+
+    use super::{CumulativeWithdrawals, WithdrawalVelocity};
+
+    use RiskOutcome::{Blocked, Flagged, Pass};
+
+    #[rstest]
+    // at most 2 withdrawals within the last 3 records
+    #[case(vec![withdrawal(1, 1), withdrawal(1, 1), withdrawal(1, 1)],                   vec![Pass, Pass, Flagged])]
+    #[case(vec![withdrawal(1, 1), withdrawal(1, 1), deposit(1), withdrawal(1, 1)],       vec![Pass, Pass, Pass, Pass])]
+    #[case(vec![deposit(1), withdrawal(1, 1), withdrawal(1, 1), withdrawal(1, 1)],       vec![Pass, Pass, Pass, Flagged])]
+    #[case(vec![withdrawal(1, 1), withdrawal(1, 1), withdrawal(2, 1), withdrawal(2, 1)], vec![Pass, Pass, Pass, Pass])]
+    fn withdrawal_velocity_works(
+        #[case] transactions: Vec<Transaction>,
+        #[case] expected: Vec<RiskOutcome>,
+    ) {
+        let rule = WithdrawalVelocity::new(2, 3, Flagged);
+        assert_eq!(
+            transactions
+                .iter()
+                .map(|transaction| rule.evaluate(transaction))
+                .collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case(vec![withdrawal(1, 4), withdrawal(1, 6)],             vec![Pass, Pass])]
+    #[case(vec![withdrawal(1, 4), deposit(1), withdrawal(1, 7)], vec![Pass, Pass, Blocked])]
+    #[case(vec![withdrawal(1, 6), withdrawal(2, 6)],             vec![Pass, Pass])]
+    fn cumulative_withdrawals_works(
+        #[case] transactions: Vec<Transaction>,
+        #[case] expected: Vec<RiskOutcome>,
+    ) {
+        let rule = CumulativeWithdrawals::new(Amount::new(10), Blocked);
+        assert_eq!(
+            transactions
+                .iter()
+                .map(|transaction| rule.evaluate(transaction))
+                .collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    fn withdrawal(client_id: ClientId, amount: i64) -> Transaction {
+        transaction(
+            client_id,
+            TransactionKind::Withdrawal {
+                amount: Amount::new(amount),
+            },
+        )
+    }
+
+    fn deposit(client_id: ClientId) -> Transaction {
+        transaction(
+            client_id,
+            TransactionKind::Deposit {
+                amount: Amount::new(1),
+            },
+        )
+    }
+
+    fn transaction(client_id: ClientId, kind: TransactionKind) -> Transaction {
+        Transaction {
+            client_id,
+            transaction_id: 1,
+            kind,
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use crate::{
+    account::{Account, AccountStatus},
+    model::{Amount, Transaction, TransactionKind},
+};
+
+/// How risky a transaction looks, from 0 to [`RiskScore::MAX`], with why.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct RiskScore {
+    pub points: u8,
+    /// What the points were given for, e.g. `large_amount`.
+    pub reasons: Vec<String>,
+}
+
+impl RiskScore {
+    pub const MAX: u8 = 100;
+
+    /// Adds the points for the reason, up to [`RiskScore::MAX`].
+    pub fn add(&mut self, points: u8, reason: impl Into<String>) {
+        self.points = self.points.saturating_add(points).min(Self::MAX);
+        self.reasons.push(reason.into());
+    }
+}
+
+/// Scores each transaction before it is applied, given the account of the
+/// client as it stands, if any.
+/// Unlike a [`RiskRule`], a scorer never stops a transaction from being
+/// applied: its score only annotates it, see
+/// [`Engine::risk_scores`].
+/// The transactions of a client are scored one after another and in the
+/// order of the input, those of different clients possibly concurrently.
+///
+/// [`RiskRule`]: super::RiskRule
+/// [`Engine::risk_scores`]: crate::engine::Engine::risk_scores
+pub trait RiskScorer {
+    fn score(&self, account: Option<&Account>, transaction: &Transaction) -> RiskScore;
+}
+
+/// A transaction along with its score.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ScoredTransaction {
+    pub transaction: Transaction,
+    pub score: RiskScore,
+}
+
+/// Scores a transaction by a handful of rules of thumb:
+/// - `large_amount`: moving at least `large_amount`,
+/// - `draining_withdrawal`: withdrawing more than half of the available
+///   funds,
+/// - `repeated_dispute`: disputing while another dispute is open,
+/// - `locked_account`: transacting on a locked account.
+pub struct RuleBasedScorer {
+    large_amount: Amount,
+}
+
+impl RuleBasedScorer {
+    pub fn new(large_amount: Amount) -> Self {
+        Self { large_amount }
+    }
+}
+
+impl Default for RuleBasedScorer {
+    /// Amounts of 10,000.0000 or more are large.
+    fn default() -> Self {
+        Self::new(Amount::new(100_000_000))
+    }
+}
+
+impl RiskScorer for RuleBasedScorer {
+    fn score(&self, account: Option<&Account>, transaction: &Transaction) -> RiskScore {
+        let mut score = RiskScore::default();
+        let amount = match &transaction.kind {
+            TransactionKind::Deposit { amount }
+            | TransactionKind::Withdrawal { amount }
+            | TransactionKind::Adjustment { amount, .. } => Some(amount.abs()),
+            _ => None,
+        };
+        if amount.is_some_and(|amount| amount >= self.large_amount) {
+            score.add(40, "large_amount");
+        }
+        let Some(account) = account else {
+            return score;
+        };
+        match &transaction.kind {
+            TransactionKind::Withdrawal { amount }
+                if amount.saturating_add(*amount) > account.available() =>
+            {
+                score.add(30, "draining_withdrawal")
+            }
+            TransactionKind::Dispute if account.open_disputes() > 0 => {
+                score.add(20, "repeated_dispute")
+            }
+            _ => {}
+        }
+        if account.status() == AccountStatus::Locked {
+            score.add(30, "locked_account");
+        }
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        account::{Account, AccountStatus},
+        model::{Amount, Transaction, TransactionKind},
+    };
+
+    use super::{RiskScore, RiskScorer, RuleBasedScorer};
+
+    fn transaction(kind: TransactionKind) -> Transaction {
+        Transaction {
+            client_id: 1,
+            transaction_id: 9,
+            kind,
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
+        }
+    }
+
+    fn funded_account(available: i64) -> Account {
+        let mut account = Account::active(1);
+        account.snapshot_mut().available = Amount::new(available);
+        account
+    }
+
+    #[test]
+    fn ordinary_transactions_score_nothing() {
+        let scorer = RuleBasedScorer::default();
+        assert_eq!(
+            scorer.score(
+                Some(&funded_account(100_000)),
+                &transaction(TransactionKind::Withdrawal {
+                    amount: Amount::new(10_000)
+                })
+            ),
+            RiskScore::default()
+        );
+    }
+
+    #[test]
+    fn the_points_of_each_rule_add_up() {
+        let scorer = RuleBasedScorer::new(Amount::new(50_000));
+        let mut account = funded_account(60_000);
+        account.set_status(AccountStatus::Locked);
+
+        let score = scorer.score(
+            Some(&account),
+            &transaction(TransactionKind::Withdrawal {
+                amount: Amount::new(50_000),
+            }),
+        );
+
+        assert_eq!(score.points, 100);
+        assert_eq!(
+            score.reasons,
+            vec!["large_amount", "draining_withdrawal", "locked_account"]
+        );
+    }
+
+    #[test]
+    fn a_new_client_is_only_scored_by_the_amount() {
+        let scorer = RuleBasedScorer::new(Amount::new(50_000));
+        assert_eq!(
+            scorer
+                .score(
+                    None,
+                    &transaction(TransactionKind::Deposit {
+                        amount: Amount::new(50_000)
+                    })
+                )
+                .reasons,
+            vec!["large_amount"]
+        );
+    }
+}
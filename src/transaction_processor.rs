@@ -1,11 +1,46 @@
+mod client_filter;
+mod conflict_quarantine;
+#[cfg(feature = "fault-injection")]
+mod fault_injecting;
+mod interceptor;
+#[cfg(feature = "postgres")]
+mod postgres_transaction_processor;
+#[cfg(feature = "redis")]
+mod redis_transaction_processor;
+mod rejection_recorder;
+mod replay_guard;
+mod retrying;
+mod risk_screener;
+mod sequence_checker;
 mod simple_transaction_processor;
+use std::sync::Arc;
+
 use async_trait::async_trait;
+pub use client_filter::{
+    ClientFilter, ClientFilterError, ClientFiltering, ClientList, FilterAction,
+};
+pub use conflict_quarantine::{Conflict, ConflictQuarantine};
+#[cfg(feature = "fault-injection")]
+pub use fault_injecting::{FaultConfig, FaultInjectingTransactionProcessor};
+pub use interceptor::{Interception, InterceptorChain, TransactionInterceptor};
 #[cfg(test)]
 pub use mock::{Blackhole, RecordSink};
+#[cfg(feature = "postgres")]
+pub use postgres_transaction_processor::PostgresTransactionProcessor;
+#[cfg(feature = "redis")]
+pub use redis_transaction_processor::RedisTransactionProcessor;
+pub use rejection_recorder::{Rejection, RejectionRecorder};
+pub use replay_guard::{ProcessedIds, ProcessedIdsError, ReplayGuard, DEFAULT_RETENTION};
+pub use retrying::{RetryPolicy, Retrying};
+pub use risk_screener::RiskScreener;
+pub use sequence_checker::{OrderViolation, OutOfOrder, SequenceChecker};
 pub use simple_transaction_processor::SimpleTransactionProcessor;
 use thiserror::Error;
 
-use crate::{account::account_transactor::AccountTransactorError, model::Transaction};
+use crate::{
+    account::{account_transactor::AccountTransactorError, SuccessStatus},
+    model::Transaction,
+};
 
 /// The transction processor.
 /// It takes in a transaction and processes it based on previously seen
@@ -13,13 +48,40 @@ use crate::{account::account_transactor::AccountTransactorError, model::Transact
 /// during the process of it.
 #[async_trait]
 pub trait TransactionProcessor {
-    async fn process(&self, transaction: Transaction) -> Result<(), TransactionProcessorError>;
+    async fn process(
+        &self,
+        transaction: Transaction,
+    ) -> Result<SuccessStatus, TransactionProcessorError>;
+}
+
+/// So that a processor shared with the workers of one stream processor can be
+/// handed to another.
+#[async_trait]
+impl<T: TransactionProcessor + Send + Sync + ?Sized> TransactionProcessor for Arc<T> {
+    async fn process(
+        &self,
+        transaction: Transaction,
+    ) -> Result<SuccessStatus, TransactionProcessorError> {
+        self.as_ref().process(transaction).await
+    }
 }
 
 #[derive(Debug, Error, PartialEq, Clone)]
 pub enum TransactionProcessorError {
     #[error("Failed to process transaction: {0:?}. Error: {1}")]
-    AccountTransactionError(Transaction, AccountTransactorError),
+    AccountTransactionError(Box<Transaction>, AccountTransactorError),
+
+    /// The transaction could not be processed for now, e.g. as the store of
+    /// the accounts could not be reached, and may be tried again.
+    #[error("Failed to process transaction: {0:?} for now. Error: {1}")]
+    Unavailable(Box<Transaction>, String),
+}
+
+impl TransactionProcessorError {
+    /// Whether trying the transaction again may succeed.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, TransactionProcessorError::Unavailable(_, _))
+    }
 }
 
 #[cfg(test)]
@@ -28,7 +90,7 @@ pub(crate) mod mock {
 
     use async_trait::async_trait;
 
-    use crate::model::Transaction;
+    use crate::{account::SuccessStatus, model::Transaction};
 
     use super::{TransactionProcessor, TransactionProcessorError};
 
@@ -38,9 +100,12 @@ pub(crate) mod mock {
 
     #[async_trait]
     impl TransactionProcessor for RecordSink {
-        async fn process(&self, transaction: Transaction) -> Result<(), TransactionProcessorError> {
+        async fn process(
+            &self,
+            transaction: Transaction,
+        ) -> Result<SuccessStatus, TransactionProcessorError> {
             self.records.lock().unwrap().push(transaction);
-            Ok(())
+            Ok(SuccessStatus::Transacted)
         }
     }
 
@@ -50,8 +115,8 @@ pub(crate) mod mock {
         async fn process(
             &self,
             _transaction: Transaction,
-        ) -> Result<(), TransactionProcessorError> {
-            Ok(())
+        ) -> Result<SuccessStatus, TransactionProcessorError> {
+            Ok(SuccessStatus::Transacted)
         }
     }
 }
@@ -0,0 +1,228 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::{BufRead, BufReader, Read},
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use thiserror::Error;
+
+use crate::{
+    account::{account_transactor::AccountTransactorError, SuccessStatus},
+    model::{ClientId, Transaction},
+};
+
+use super::{TransactionProcessor, TransactionProcessorError};
+
+/// The clients whose transactions are let through.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ClientList {
+    /// Only those of the listed clients.
+    Allow(HashSet<ClientId>),
+
+    /// All but those of the listed clients, e.g. the ones under legal hold.
+    Block(HashSet<ClientId>),
+}
+
+/// What becomes of the transactions of the clients that are not let through.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FilterAction {
+    /// They are left out and counted.
+    Skip,
+
+    /// They are rejected with [`AccountTransactorError::ClientNotAllowed`],
+    /// which fails the run.
+    Reject,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ClientFilter {
+    pub clients: ClientList,
+    pub action: FilterAction,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ClientFilterError {
+    #[error("Failed to read the client ids: {0}")]
+    ParsingError(String),
+}
+
+impl ClientList {
+    /// Reads one client id per line. Blank lines and lines starting with `#`
+    /// are ignored.
+    pub fn read_ids(r: impl Read) -> Result<HashSet<ClientId>, ClientFilterError> {
+        let mut client_ids = HashSet::new();
+        for line in BufReader::new(r).lines() {
+            let line = line.map_err(|err| ClientFilterError::ParsingError(err.to_string()))?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let client_id = line.parse().map_err(|err| {
+                ClientFilterError::ParsingError(format!("Invalid client id `{line}`: {err}"))
+            })?;
+            client_ids.insert(client_id);
+        }
+        Ok(client_ids)
+    }
+
+    fn lets_through(&self, client_id: ClientId) -> bool {
+        match self {
+            ClientList::Allow(client_ids) => client_ids.contains(&client_id),
+            ClientList::Block(client_ids) => !client_ids.contains(&client_id),
+        }
+    }
+}
+
+/// Lets the transactions of the clients through to the processor it wraps
+/// as the filter says, counting the skipped ones per client.
+pub struct ClientFiltering {
+    inner: Arc<dyn TransactionProcessor + Send + Sync>,
+    filter: ClientFilter,
+    skipped: Arc<Mutex<BTreeMap<ClientId, usize>>>,
+}
+
+#[async_trait]
+impl TransactionProcessor for ClientFiltering {
+    async fn process(
+        &self,
+        transaction: Transaction,
+    ) -> Result<SuccessStatus, TransactionProcessorError> {
+        if self.filter.clients.lets_through(transaction.client_id) {
+            return self.inner.process(transaction).await;
+        }
+        match self.filter.action {
+            FilterAction::Skip => {
+                *self
+                    .skipped
+                    .lock()
+                    .expect("The skipped counts are never locked across a panic.")
+                    .entry(transaction.client_id)
+                    .or_default() += 1;
+                Ok(SuccessStatus::NoOp)
+            }
+            FilterAction::Reject => Err(TransactionProcessorError::AccountTransactionError(
+                Box::new(transaction),
+                AccountTransactorError::ClientNotAllowed,
+            )),
+        }
+    }
+}
+
+impl ClientFiltering {
+    pub fn new(
+        inner: Arc<dyn TransactionProcessor + Send + Sync>,
+        filter: ClientFilter,
+        skipped: Arc<Mutex<BTreeMap<ClientId, usize>>>,
+    ) -> Self {
+        Self {
+            inner,
+            filter,
+            skipped,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{BTreeMap, HashSet},
+        sync::{Arc, Mutex},
+    };
+
+    use assert_matches::assert_matches;
+    use rstest::rstest;
+
+    use crate::{
+        account::account_transactor::AccountTransactorError,
+        model::{ClientId, Transaction, TransactionKind},
+        transaction_processor::{RecordSink, TransactionProcessor, TransactionProcessorError},
+    };
+
+    use super::{ClientFilter, ClientFilterError, ClientFiltering, ClientList, FilterAction};
+
+    #[rstest]
+    #[case(ClientList::Allow(HashSet::from([1])), vec![dispute(1)],               BTreeMap::from([(2, 2)]))]
+    #[case(ClientList::Block(HashSet::from([1])), vec![dispute(2), dispute(2)],   BTreeMap::from([(1, 1)]))]
+    #[case(ClientList::Block(HashSet::new()),     vec![dispute(1), dispute(2), dispute(2)], BTreeMap::new())]
+    #[tokio::test]
+    async fn skipped_transactions_are_counted(
+        #[case] clients: ClientList,
+        #[case] expected_records: Vec<Transaction>,
+        #[case] expected_skipped: BTreeMap<ClientId, usize>,
+    ) {
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let skipped = Arc::new(Mutex::new(BTreeMap::new()));
+        let filtering = ClientFiltering::new(
+            Arc::new(RecordSink {
+                records: records.clone(),
+            }),
+            ClientFilter {
+                clients,
+                action: FilterAction::Skip,
+            },
+            skipped.clone(),
+        );
+        for transaction in [dispute(1), dispute(2), dispute(2)] {
+            filtering.process(transaction).await.unwrap();
+        }
+        assert_eq!(*records.lock().unwrap(), expected_records);
+        assert_eq!(*skipped.lock().unwrap(), expected_skipped);
+    }
+
+    #[tokio::test]
+    async fn rejected_transactions_are_errors() {
+        let skipped = Arc::new(Mutex::new(BTreeMap::new()));
+        let filtering = ClientFiltering::new(
+            Arc::new(RecordSink {
+                records: Arc::new(Mutex::new(Vec::new())),
+            }),
+            ClientFilter {
+                clients: ClientList::Block(HashSet::from([1])),
+                action: FilterAction::Reject,
+            },
+            skipped.clone(),
+        );
+        assert_eq!(
+            filtering.process(dispute(1)).await,
+            Err(TransactionProcessorError::AccountTransactionError(
+                Box::new(dispute(1)),
+                AccountTransactorError::ClientNotAllowed
+            ))
+        );
+        assert!(skipped.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn client_ids_can_be_read() {
+        let input = "
+        # under legal hold
+        1
+          22
+
+        333";
+        assert_eq!(
+            ClientList::read_ids(input.as_bytes()),
+            Ok(HashSet::from([1, 22, 333]))
+        );
+    }
+
+    #[test]
+    fn invalid_client_id_is_rejected() {
+        assert_matches!(
+            ClientList::read_ids("1\nalice\n".as_bytes()),
+            Err(ClientFilterError::ParsingError(_))
+        );
+    }
+
+    fn dispute(client_id: ClientId) -> Transaction {
+        Transaction {
+            client_id,
+            transaction_id: 1,
+            kind: TransactionKind::Dispute,
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
+        }
+    }
+}
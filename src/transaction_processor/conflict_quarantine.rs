@@ -0,0 +1,177 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+
+use crate::{
+    account::{
+        account_transactor::AccountTransactorError, DepositorError, SuccessStatus, WithdrawerError,
+    },
+    model::Transaction,
+};
+
+use super::{TransactionProcessor, TransactionProcessorError};
+
+/// A transaction whose id was already taken by a different one of the
+/// client, e.g. a deposit sent again with another amount.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Conflict {
+    pub transaction: Transaction,
+    pub reason: AccountTransactorError,
+}
+
+/// Puts aside the transactions that conflict with a previous one of the same
+/// id rather than failing the run, the first one being kept, as a feed
+/// sending an id twice with different amounts is common enough to be
+/// tolerated. Any other error is passed on.
+pub struct ConflictQuarantine {
+    inner: Arc<dyn TransactionProcessor + Send + Sync>,
+    conflicts: Arc<Mutex<Vec<Conflict>>>,
+}
+
+#[async_trait]
+impl TransactionProcessor for ConflictQuarantine {
+    async fn process(
+        &self,
+        transaction: Transaction,
+    ) -> Result<SuccessStatus, TransactionProcessorError> {
+        match self.inner.process(transaction).await {
+            Err(TransactionProcessorError::AccountTransactionError(transaction, reason))
+                if is_conflict(&reason) =>
+            {
+                self.conflicts
+                    .lock()
+                    .expect("The conflicts are never locked across a panic.")
+                    .push(Conflict {
+                        transaction: *transaction,
+                        reason,
+                    });
+                Ok(SuccessStatus::NoOp)
+            }
+            result => result,
+        }
+    }
+}
+
+impl ConflictQuarantine {
+    pub fn new(
+        inner: Arc<dyn TransactionProcessor + Send + Sync>,
+        conflicts: Arc<Mutex<Vec<Conflict>>>,
+    ) -> Self {
+        Self { inner, conflicts }
+    }
+}
+
+fn is_conflict(reason: &AccountTransactorError) -> bool {
+    matches!(
+        reason,
+        AccountTransactorError::Deposit(DepositorError::ConflictingWithPreviousTransaction)
+            | AccountTransactorError::Withdrawal(
+                WithdrawerError::ConflictingWithPreviousTransaction
+            )
+            | AccountTransactorError::ConflictingAdjustment
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use assert_matches::assert_matches;
+
+    use crate::{
+        account::{
+            account_transactor::AccountTransactorError, AccountStore, DepositorError,
+            SimpleAccountTransactor, SuccessStatus, WithdrawerError,
+        },
+        model::{Amount, Transaction, TransactionKind},
+        transaction_processor::{
+            SimpleTransactionProcessor, TransactionProcessor, TransactionProcessorError,
+        },
+    };
+
+    use super::{Conflict, ConflictQuarantine};
+
+    fn transaction(transaction_id: u32, kind: TransactionKind) -> Transaction {
+        Transaction {
+            client_id: 1,
+            transaction_id,
+            kind,
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
+        }
+    }
+
+    fn deposit(transaction_id: u32, amount: i64) -> Transaction {
+        transaction(
+            transaction_id,
+            TransactionKind::Deposit {
+                amount: Amount::new(amount),
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn conflicting_transactions_are_put_aside_and_the_first_kept() {
+        let accounts = Arc::new(AccountStore::new());
+        let conflicts = Arc::new(Mutex::new(Vec::new()));
+        let processor = ConflictQuarantine::new(
+            Arc::new(SimpleTransactionProcessor::new(
+                accounts.clone(),
+                Box::new(SimpleAccountTransactor::new()),
+            )),
+            conflicts.clone(),
+        );
+
+        assert_eq!(
+            processor.process(deposit(1, 10_000)).await,
+            Ok(SuccessStatus::Transacted)
+        );
+        assert_eq!(
+            processor.process(deposit(1, 20_000)).await,
+            Ok(SuccessStatus::NoOp)
+        );
+        assert_eq!(
+            processor.process(deposit(1, 10_000)).await,
+            Ok(SuccessStatus::Duplicate)
+        );
+        assert_eq!(accounts.get(1).unwrap().available(), Amount::new(10_000));
+        assert_eq!(
+            *conflicts.lock().unwrap(),
+            vec![Conflict {
+                transaction: deposit(1, 20_000),
+                reason: AccountTransactorError::Deposit(
+                    DepositorError::ConflictingWithPreviousTransaction
+                ),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn other_rejections_are_passed_on() {
+        let conflicts = Arc::new(Mutex::new(Vec::new()));
+        let processor = ConflictQuarantine::new(
+            Arc::new(SimpleTransactionProcessor::new(
+                Arc::new(AccountStore::new()),
+                Box::new(SimpleAccountTransactor::new()),
+            )),
+            conflicts.clone(),
+        );
+
+        assert_matches!(
+            processor
+                .process(transaction(
+                    1,
+                    TransactionKind::Withdrawal {
+                        amount: Amount::new(10_000)
+                    }
+                ))
+                .await,
+            Err(TransactionProcessorError::AccountTransactionError(
+                _,
+                AccountTransactorError::Withdrawal(WithdrawerError::InsufficientFund)
+            ))
+        );
+        assert!(conflicts.lock().unwrap().is_empty());
+    }
+}
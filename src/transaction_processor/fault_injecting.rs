@@ -0,0 +1,175 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use tokio::time::sleep;
+
+use crate::{account::SuccessStatus, model::Transaction};
+
+use super::{TransactionProcessor, TransactionProcessorError};
+
+/// How often each of the faults is injected, each rate being the odds out of
+/// 1.0 that a transaction gets the fault.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FaultConfig {
+    /// Seeds the draws, so that a run can be repeated.
+    pub seed: u64,
+
+    /// A delayed transaction is held back before being passed on.
+    pub delay_rate: f64,
+
+    /// The longest a delayed transaction is held back for.
+    pub max_delay: Duration,
+
+    /// A duplicated transaction is passed on twice in a row.
+    pub duplicate_rate: f64,
+
+    /// A failed transaction is not passed on, failing with
+    /// [`TransactionProcessorError::Unavailable`] instead.
+    pub failure_rate: f64,
+}
+
+/// Randomly delays, duplicates or fails the transactions before passing them
+/// on to the processor it wraps, to see how the rest of a pipeline copes.
+/// The draws are repeatable for a given seed as long as the transactions
+/// come in the same order, which they do not across clients processed
+/// concurrently.
+pub struct FaultInjectingTransactionProcessor {
+    inner: Arc<dyn TransactionProcessor + Send + Sync>,
+    config: FaultConfig,
+    state: Mutex<u64>,
+}
+
+#[async_trait]
+impl TransactionProcessor for FaultInjectingTransactionProcessor {
+    async fn process(
+        &self,
+        transaction: Transaction,
+    ) -> Result<SuccessStatus, TransactionProcessorError> {
+        let [delay, delay_length, duplicate, failure] = self.draw();
+        if delay < self.config.delay_rate {
+            sleep(self.config.max_delay.mul_f64(delay_length)).await;
+        }
+        if failure < self.config.failure_rate {
+            return Err(TransactionProcessorError::Unavailable(
+                Box::new(transaction),
+                "Injected fault".to_string(),
+            ));
+        }
+        if duplicate < self.config.duplicate_rate {
+            let result = self.inner.process(transaction.clone()).await;
+            let duplicate_result = self.inner.process(transaction).await;
+            return result.and(duplicate_result);
+        }
+        self.inner.process(transaction).await
+    }
+}
+
+impl FaultInjectingTransactionProcessor {
+    pub fn new(inner: Arc<dyn TransactionProcessor + Send + Sync>, config: FaultConfig) -> Self {
+        let state = Mutex::new(config.seed);
+        Self {
+            inner,
+            config,
+            state,
+        }
+    }
+
+    /// The next few draws in `[0, 1)`, as per SplitMix64.
+    fn draw<const N: usize>(&self) -> [f64; N] {
+        let mut state = self
+            .state
+            .lock()
+            .expect("The state is never locked across a panic.");
+        [(); N].map(|_| {
+            *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = *state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^= z >> 31;
+            (z >> 11) as f64 / (1u64 << 53) as f64
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use rstest::rstest;
+
+    use crate::{
+        model::{Transaction, TransactionKind},
+        transaction_processor::{Blackhole, RecordSink, TransactionProcessor},
+    };
+
+    use super::{FaultConfig, FaultInjectingTransactionProcessor};
+
+    #[rustfmt::skip]
+    #[rstest]
+    #[case::none      (0.0, 0.0, 1, true)]
+    #[case::duplicated(1.0, 0.0, 2, true)]
+    #[case::failed    (1.0, 1.0, 0, false)]
+    #[tokio::test]
+    async fn faults_are_injected_at_their_rates(
+        #[case] duplicate_rate: f64,
+        #[case] failure_rate: f64,
+        #[case] expected_passed_on: usize,
+        #[case] ok: bool,
+    ) {
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let processor = FaultInjectingTransactionProcessor::new(
+            Arc::new(RecordSink {
+                records: records.clone(),
+            }),
+            FaultConfig {
+                delay_rate: 1.0,
+                max_delay: Duration::from_millis(1),
+                duplicate_rate,
+                failure_rate,
+                ..Default::default()
+            },
+        );
+        assert_eq!(processor.process(dispute(1)).await.is_ok(), ok);
+        assert_eq!(records.lock().unwrap().len(), expected_passed_on);
+    }
+
+    #[tokio::test]
+    async fn faults_are_repeatable_for_a_seed() {
+        let outcomes = |seed| async move {
+            let processor = FaultInjectingTransactionProcessor::new(
+                Arc::new(Blackhole),
+                FaultConfig {
+                    seed,
+                    failure_rate: 0.5,
+                    ..Default::default()
+                },
+            );
+            let mut outcomes = Vec::new();
+            for transaction_id in 1..=64 {
+                outcomes.push(processor.process(dispute(transaction_id)).await.is_ok());
+            }
+            outcomes
+        };
+        let first = outcomes(7).await;
+        assert_eq!(first, outcomes(7).await);
+        assert_ne!(first, outcomes(8).await);
+        assert!(first.contains(&true) && first.contains(&false));
+    }
+
+    fn dispute(transaction_id: u32) -> Transaction {
+        Transaction {
+            client_id: 1,
+            transaction_id,
+            kind: TransactionKind::Dispute,
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
+        }
+    }
+}
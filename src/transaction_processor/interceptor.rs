@@ -0,0 +1,206 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::{
+    account::{account_transactor::AccountTransactorError, SuccessStatus},
+    model::Transaction,
+};
+
+use super::{TransactionProcessor, TransactionProcessorError};
+
+/// What becomes of a transaction once an interceptor has seen it.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Interception {
+    /// It is passed on to the next interceptor, then to the processor.
+    Proceed,
+
+    /// It is left out without an error, e.g. as a replay already seen.
+    Skip,
+
+    /// It is rejected with the error, as if the processor had rejected it.
+    Reject(AccountTransactorError),
+}
+
+/// A hook around the processing of each transaction, e.g. for logging,
+/// metrics, risk checks or idempotency.
+pub trait TransactionInterceptor {
+    /// Called before the transaction is processed.
+    fn before(&self, _transaction: &Transaction) -> Interception {
+        Interception::Proceed
+    }
+
+    /// Called with the outcome of the transaction, if `before` was called.
+    /// A skipped transaction has a [`SuccessStatus::NoOp`] outcome.
+    fn after(
+        &self,
+        _transaction: &Transaction,
+        _result: &Result<SuccessStatus, TransactionProcessorError>,
+    ) {
+    }
+}
+
+/// Runs the interceptors around the processor it wraps.
+/// The `before` hooks are called in order until one of them does not
+/// proceed, then the `after` hooks of those called are called in reverse
+/// order, so that the first interceptor is the outermost one.
+pub struct InterceptorChain {
+    inner: Arc<dyn TransactionProcessor + Send + Sync>,
+    interceptors: Vec<Arc<dyn TransactionInterceptor + Send + Sync>>,
+}
+
+#[async_trait]
+impl TransactionProcessor for InterceptorChain {
+    async fn process(
+        &self,
+        transaction: Transaction,
+    ) -> Result<SuccessStatus, TransactionProcessorError> {
+        let mut called = 0;
+        let mut interception = Interception::Proceed;
+        for interceptor in &self.interceptors {
+            called += 1;
+            interception = interceptor.before(&transaction);
+            if interception != Interception::Proceed {
+                break;
+            }
+        }
+        let result = match interception {
+            Interception::Proceed => self.inner.process(transaction.clone()).await,
+            Interception::Skip => Ok(SuccessStatus::NoOp),
+            Interception::Reject(err) => Err(TransactionProcessorError::AccountTransactionError(
+                Box::new(transaction.clone()),
+                err,
+            )),
+        };
+        for interceptor in self.interceptors[..called].iter().rev() {
+            interceptor.after(&transaction, &result);
+        }
+        result
+    }
+}
+
+impl InterceptorChain {
+    pub fn new(
+        inner: Arc<dyn TransactionProcessor + Send + Sync>,
+        interceptors: Vec<Arc<dyn TransactionInterceptor + Send + Sync>>,
+    ) -> Self {
+        Self {
+            inner,
+            interceptors,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use rstest::rstest;
+
+    use crate::{
+        account::{account_transactor::AccountTransactorError, DisputerError, SuccessStatus},
+        model::{Transaction, TransactionKind},
+        transaction_processor::{RecordSink, TransactionProcessor, TransactionProcessorError},
+    };
+
+    use super::{Interception, InterceptorChain, TransactionInterceptor};
+
+    /// Logs its hooks being called, giving the interception it is set up
+    /// with to the transaction with the id.
+    struct Logging {
+        name: &'static str,
+        transaction_id: u32,
+        interception: Interception,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl TransactionInterceptor for Logging {
+        fn before(&self, transaction: &Transaction) -> Interception {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("{} before", self.name));
+            if transaction.transaction_id == self.transaction_id {
+                self.interception.clone()
+            } else {
+                Interception::Proceed
+            }
+        }
+
+        fn after(
+            &self,
+            _transaction: &Transaction,
+            result: &Result<SuccessStatus, TransactionProcessorError>,
+        ) {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("{} after {}", self.name, result.is_ok()));
+        }
+    }
+
+    #[rustfmt::skip]
+    #[rstest]
+    #[case::proceeded(1, true,  true,  &["outer before", "inner before", "inner after true", "outer after true"])]
+    #[case::skipped  (2, false, true,  &["outer before", "inner before", "inner after true", "outer after true"])]
+    #[case::rejected (3, false, false, &["outer before", "outer after false"])]
+    #[tokio::test]
+    async fn interceptors_are_called_around_the_processor(
+        #[case] transaction_id: u32,
+        #[case] processed: bool,
+        #[case] ok: bool,
+        #[case] expected_log: &[&str],
+    ) {
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let chain = InterceptorChain::new(
+            Arc::new(RecordSink {
+                records: records.clone(),
+            }),
+            vec![
+                Arc::new(Logging {
+                    name: "outer",
+                    transaction_id: 3,
+                    interception: Interception::Reject(locked()),
+                    log: log.clone(),
+                }),
+                Arc::new(Logging {
+                    name: "inner",
+                    transaction_id: 2,
+                    interception: Interception::Skip,
+                    log: log.clone(),
+                }),
+            ],
+        );
+
+        let result = chain.process(dispute(transaction_id)).await;
+
+        assert_eq!(result.is_ok(), ok);
+        if !ok {
+            assert_eq!(
+                result,
+                Err(TransactionProcessorError::AccountTransactionError(
+                    Box::new(dispute(transaction_id)),
+                    locked()
+                ))
+            );
+        }
+        assert_eq!(records.lock().unwrap().len(), processed as usize);
+        assert_eq!(*log.lock().unwrap(), expected_log);
+    }
+
+    fn locked() -> AccountTransactorError {
+        AccountTransactorError::Dispute(DisputerError::AccountLocked)
+    }
+
+    fn dispute(transaction_id: u32) -> Transaction {
+        Transaction {
+            client_id: 1,
+            transaction_id,
+            kind: TransactionKind::Dispute,
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
+        }
+    }
+}
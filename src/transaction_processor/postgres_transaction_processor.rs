@@ -0,0 +1,80 @@
+use async_trait::async_trait;
+
+use super::{TransactionProcessor, TransactionProcessorError};
+use crate::account::account_transactor::AccountTransactor;
+use crate::account::{PostgresAccountStore, SuccessStatus};
+use crate::model::Transaction;
+
+/// Applies the transactions to the accounts kept in PostgreSQL, see
+/// [`PostgresAccountStore`], each within a database transaction that writes
+/// the account back and records the transaction as applied, or does nothing.
+/// A transaction that was already applied, e.g. before a restart, is let
+/// through as [`SuccessStatus::Duplicate`] without getting to the account.
+/// A rejected transaction is not recorded, and is tried on the account again
+/// if it is sent again.
+/// A transaction for which the database cannot be reached fails with
+/// [`TransactionProcessorError::Unavailable`], to be tried again, e.g. by
+/// [`Retrying`].
+///
+/// [`Retrying`]: super::Retrying
+pub struct PostgresTransactionProcessor {
+    accounts: PostgresAccountStore,
+    account_transactor: Box<dyn AccountTransactor + 'static + Send + Sync>,
+}
+
+#[async_trait]
+impl TransactionProcessor for PostgresTransactionProcessor {
+    async fn process(
+        &self,
+        transaction: Transaction,
+    ) -> Result<SuccessStatus, TransactionProcessorError> {
+        let unavailable = |reason: String| {
+            TransactionProcessorError::Unavailable(Box::new(transaction.clone()), reason)
+        };
+        // rolled back when dropped, unless committed
+        let mut db_transaction = self
+            .accounts
+            .begin()
+            .await
+            .map_err(|err| unavailable(err.to_string()))?;
+        if !PostgresAccountStore::record_applied(&mut db_transaction, &transaction)
+            .await
+            .map_err(|err| unavailable(err.to_string()))?
+        {
+            return Ok(SuccessStatus::Duplicate);
+        }
+        let mut account =
+            PostgresAccountStore::load_for_update(&mut db_transaction, transaction.client_id)
+                .await
+                .map_err(|err| unavailable(err.to_string()))?;
+        let status = self
+            .account_transactor
+            .transact(&mut account, transaction.clone())
+            .map_err(|err| {
+                TransactionProcessorError::AccountTransactionError(
+                    Box::new(transaction.clone()),
+                    err,
+                )
+            })?;
+        PostgresAccountStore::save(&mut db_transaction, &account)
+            .await
+            .map_err(|err| unavailable(err.to_string()))?;
+        db_transaction
+            .commit()
+            .await
+            .map_err(|err| unavailable(err.to_string()))?;
+        Ok(status)
+    }
+}
+
+impl PostgresTransactionProcessor {
+    pub fn new(
+        accounts: PostgresAccountStore,
+        account_transactor: Box<dyn AccountTransactor + 'static + Send + Sync>,
+    ) -> Self {
+        Self {
+            accounts,
+            account_transactor,
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use async_trait::async_trait;
+
+use super::{TransactionProcessor, TransactionProcessorError};
+use crate::account::account_transactor::AccountTransactor;
+use crate::account::{RedisAccountStore, SuccessStatus};
+use crate::model::Transaction;
+
+/// Applies the transactions to the accounts kept in Redis, see
+/// [`RedisAccountStore`].
+/// A transaction whose account was written by another engine between being
+/// loaded and being saved fails with [`TransactionProcessorError::Unavailable`],
+/// as does one for which Redis cannot be reached, to be tried again, e.g.
+/// by [`Retrying`], on the account as it then is.
+///
+/// [`Retrying`]: super::Retrying
+pub struct RedisTransactionProcessor {
+    accounts: RedisAccountStore,
+    account_transactor: Box<dyn AccountTransactor + 'static + Send + Sync>,
+}
+
+#[async_trait]
+impl TransactionProcessor for RedisTransactionProcessor {
+    async fn process(
+        &self,
+        transaction: Transaction,
+    ) -> Result<SuccessStatus, TransactionProcessorError> {
+        let unavailable = |reason: String| {
+            TransactionProcessorError::Unavailable(Box::new(transaction.clone()), reason)
+        };
+        let (mut account, loaded) = self
+            .accounts
+            .load(transaction.client_id)
+            .await
+            .map_err(|err| unavailable(err.to_string()))?;
+        let status = self
+            .account_transactor
+            .transact(&mut account, transaction.clone())
+            .map_err(|err| {
+                TransactionProcessorError::AccountTransactionError(
+                    Box::new(transaction.clone()),
+                    err,
+                )
+            })?;
+        match self.accounts.save(&loaded, &account).await {
+            Ok(true) => Ok(status),
+            Ok(false) => Err(unavailable(
+                "The account was written by another engine in the meantime.".to_string(),
+            )),
+            Err(err) => Err(unavailable(err.to_string())),
+        }
+    }
+}
+
+impl RedisTransactionProcessor {
+    pub fn new(
+        accounts: RedisAccountStore,
+        account_transactor: Box<dyn AccountTransactor + 'static + Send + Sync>,
+    ) -> Self {
+        Self {
+            accounts,
+            account_transactor,
+        }
+    }
+}
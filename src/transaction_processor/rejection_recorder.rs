@@ -0,0 +1,126 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+
+use crate::{
+    account::{account_transactor::AccountTransactorError, SuccessStatus},
+    model::Transaction,
+};
+
+use super::{TransactionProcessor, TransactionProcessorError};
+
+/// A transaction that was not applied, and why.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Rejection {
+    pub transaction: Transaction,
+    pub reason: AccountTransactorError,
+}
+
+/// Records the transactions rejected by the processor it wraps, instead of
+/// passing the errors on.
+/// As no error gets to the error handler, processing carries on past the
+/// errors that would have otherwise failed the run, so that all the
+/// rejections of an input can be collected in one go.
+/// Errors that are not rejections, e.g. an unavailable store, are passed on.
+pub struct RejectionRecorder {
+    inner: Arc<dyn TransactionProcessor + Send + Sync>,
+    rejections: Arc<Mutex<Vec<Rejection>>>,
+}
+
+#[async_trait]
+impl TransactionProcessor for RejectionRecorder {
+    async fn process(
+        &self,
+        transaction: Transaction,
+    ) -> Result<SuccessStatus, TransactionProcessorError> {
+        match self.inner.process(transaction).await {
+            Err(TransactionProcessorError::AccountTransactionError(transaction, reason)) => {
+                self.rejections
+                    .lock()
+                    .expect("The rejections are never locked across a panic.")
+                    .push(Rejection {
+                        transaction: *transaction,
+                        reason,
+                    });
+                Ok(SuccessStatus::NoOp)
+            }
+            result => result,
+        }
+    }
+}
+
+impl RejectionRecorder {
+    pub fn new(
+        inner: Arc<dyn TransactionProcessor + Send + Sync>,
+        rejections: Arc<Mutex<Vec<Rejection>>>,
+    ) -> Self {
+        Self { inner, rejections }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use async_trait::async_trait;
+
+    use crate::{
+        account::{account_transactor::AccountTransactorError, DisputerError, SuccessStatus},
+        model::{Transaction, TransactionKind},
+        transaction_processor::{TransactionProcessor, TransactionProcessorError},
+    };
+
+    use super::{Rejection, RejectionRecorder};
+
+    /// Rejects every other transaction.
+    struct Picky;
+
+    #[async_trait]
+    impl TransactionProcessor for Picky {
+        async fn process(
+            &self,
+            transaction: Transaction,
+        ) -> Result<SuccessStatus, TransactionProcessorError> {
+            match transaction.transaction_id % 2 {
+                0 => Ok(SuccessStatus::Transacted),
+                _ => Err(TransactionProcessorError::AccountTransactionError(
+                    Box::new(transaction),
+                    AccountTransactorError::Dispute(DisputerError::AccountLocked),
+                )),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn records_rejections_and_carries_on() {
+        let rejections = Arc::new(Mutex::new(Vec::new()));
+        let recorder = RejectionRecorder::new(Arc::new(Picky), rejections.clone());
+        for transaction_id in 1..=4 {
+            recorder.process(dispute(transaction_id)).await.unwrap();
+        }
+        assert_eq!(
+            *rejections.lock().unwrap(),
+            vec![
+                Rejection {
+                    transaction: dispute(1),
+                    reason: AccountTransactorError::Dispute(DisputerError::AccountLocked),
+                },
+                Rejection {
+                    transaction: dispute(3),
+                    reason: AccountTransactorError::Dispute(DisputerError::AccountLocked),
+                },
+            ]
+        );
+    }
+
+    fn dispute(transaction_id: u32) -> Transaction {
+        Transaction {
+            client_id: 1,
+            transaction_id,
+            kind: TransactionKind::Dispute,
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
+        }
+    }
+}
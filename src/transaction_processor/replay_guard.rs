@@ -0,0 +1,457 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use async_trait::async_trait;
+use thiserror::Error;
+
+use crate::{
+    account::SuccessStatus,
+    model::{ClientId, Transaction, TransactionId, TransactionKind},
+};
+
+use super::{TransactionProcessor, TransactionProcessorError};
+
+/// How many ids of each client are kept by default, see
+/// [`ProcessedIds::with_retention`].
+pub const DEFAULT_RETENTION: usize = 100_000;
+
+/// The log is only compacted once it has at least as many lines, so that a
+/// small one is not rewritten over and over.
+const MIN_COMPACTION_LINES: usize = 1024;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ProcessedIdsError {
+    #[error("Failed to access the processed ids: {0}")]
+    IoError(String),
+
+    #[error("Malformed processed id on line {0}: `{1}`")]
+    Malformed(usize, String),
+}
+
+/// A transaction as it is told apart from the others of its client: by its
+/// id and its type, as a dispute, a resolve or a chargeback takes the id of
+/// the deposit it refers to.
+type ProcessedId = (TransactionId, &'static str);
+
+/// The transactions processed so far, kept in a file so that they are still
+/// known after a restart, e.g. for an upstream delivering them at least once
+/// to send some of them again.
+/// Each transaction is appended to the file as a `client,tx,type` line once
+/// processed. Only the latest ids of each client are kept, as a transaction
+/// is only expected to be sent again shortly after it was, the file being
+/// rewritten with those alone once it has twice as many lines.
+/// A line left half written, e.g. by a crash, is ignored when the file is
+/// read back.
+pub struct ProcessedIds {
+    path: PathBuf,
+    retention: usize,
+    state: Mutex<State>,
+}
+
+struct State {
+    ids: HashMap<ClientId, ClientIds>,
+    /// The number of ids kept across all the clients.
+    kept: usize,
+    lines: usize,
+    log: BufWriter<File>,
+}
+
+/// The ids of a client, in the order they were processed in.
+#[derive(Default)]
+struct ClientIds {
+    order: VecDeque<ProcessedId>,
+    set: HashSet<ProcessedId>,
+}
+
+impl ProcessedIds {
+    /// Reads the ids processed before from the file, if any, and appends to
+    /// it the ones processed from now on.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ProcessedIdsError> {
+        let path = path.as_ref().to_path_buf();
+        let mut ids = HashMap::<ClientId, ClientIds>::new();
+        let mut lines = 0;
+        let mut torn = false;
+        match File::open(&path) {
+            Ok(file) => {
+                let mut reader = BufReader::new(file);
+                let mut line = String::new();
+                while reader.read_line(&mut line).map_err(io_error)? > 0 {
+                    // the last line is only complete if it was fully written
+                    torn = !line.ends_with('\n');
+                    if !torn {
+                        lines += 1;
+                        let (client_id, id) = parse(line.trim_end()).ok_or_else(|| {
+                            ProcessedIdsError::Malformed(lines, line.trim_end().to_string())
+                        })?;
+                        // evicted as the client's ids are inserted, once the
+                        // retention is known
+                        ids.entry(client_id).or_default().insert(id, usize::MAX);
+                    }
+                    line.clear();
+                }
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(io_error(err)),
+        }
+        let kept = ids.values().map(|ids| ids.order.len()).sum();
+        let log = match torn {
+            // for the next line not to be appended to the half written one
+            true => {
+                lines = kept;
+                rewrite(&path, &ids)?
+            }
+            false => append(&path)?,
+        };
+        Ok(Self {
+            path,
+            retention: DEFAULT_RETENTION,
+            state: Mutex::new(State {
+                ids,
+                kept,
+                lines,
+                log,
+            }),
+        })
+    }
+
+    /// Only the latest `retention` ids of each client are kept, the default
+    /// being [`DEFAULT_RETENTION`]. A transaction sent again after that many
+    /// others of its client is processed as a new one. The ids read from
+    /// the file are all kept until then.
+    pub fn with_retention(self, retention: usize) -> Self {
+        {
+            let mut state = self.state();
+            for client_ids in state.ids.values_mut() {
+                client_ids.evict(retention);
+            }
+            state.kept = state.ids.values().map(|ids| ids.order.len()).sum();
+        }
+        Self { retention, ..self }
+    }
+
+    /// Whether the transaction was processed before.
+    pub fn contains(&self, transaction: &Transaction) -> bool {
+        self.state()
+            .ids
+            .get(&transaction.client_id)
+            .is_some_and(|ids| ids.set.contains(&id(transaction)))
+    }
+
+    /// Records the transaction as processed, the file being compacted if it
+    /// has grown to twice as many lines as there are ids kept.
+    pub fn insert(&self, transaction: &Transaction) -> Result<(), ProcessedIdsError> {
+        let mut state = self.state();
+        let id = id(transaction);
+        let State {
+            ids, kept, lines, ..
+        } = &mut *state;
+        let client_ids = ids.entry(transaction.client_id).or_default();
+        if client_ids.set.contains(&id) {
+            return Ok(());
+        }
+        let before = client_ids.order.len();
+        client_ids.insert(id, self.retention);
+        *kept = *kept + client_ids.order.len() - before;
+        *lines += 1;
+        writeln!(state.log, "{},{},{}", transaction.client_id, id.0, id.1)
+            .and_then(|()| state.log.flush())
+            .map_err(io_error)?;
+        if state.lines >= MIN_COMPACTION_LINES && state.lines >= 2 * state.kept {
+            state.log = rewrite(&self.path, &state.ids)?;
+            state.lines = state.kept;
+        }
+        Ok(())
+    }
+
+    /// The number of ids kept across all the clients.
+    pub fn len(&self) -> usize {
+        self.state().kept
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .expect("The processed ids are never locked across a panic.")
+    }
+}
+
+impl ClientIds {
+    fn insert(&mut self, id: ProcessedId, retention: usize) {
+        if self.set.insert(id) {
+            self.order.push_back(id);
+        }
+        self.evict(retention);
+    }
+
+    fn evict(&mut self, retention: usize) {
+        while self.order.len() > retention {
+            if let Some(evicted) = self.order.pop_front() {
+                self.set.remove(&evicted);
+            }
+        }
+    }
+}
+
+fn id(transaction: &Transaction) -> ProcessedId {
+    (transaction.transaction_id, transaction.kind.name())
+}
+
+/// e.g. `1,42,deposit`.
+fn parse(line: &str) -> Option<(ClientId, ProcessedId)> {
+    let mut fields = line.splitn(3, ',');
+    let client_id = fields.next()?.parse().ok()?;
+    let transaction_id = fields.next()?.parse().ok()?;
+    let kind = match fields.next()? {
+        // which cannot be told by its name alone
+        "adjustment" => "adjustment",
+        // the amount is only needed for the type to be read
+        name => TransactionKind::from_name(name, Some("0")).ok()?.name(),
+    };
+    Some((client_id, (transaction_id, kind)))
+}
+
+fn append(path: &Path) -> Result<BufWriter<File>, ProcessedIdsError> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map(BufWriter::new)
+        .map_err(io_error)
+}
+
+/// Writes the ids kept to a file next to the log, which then replaces it,
+/// so that a crash half way through leaves the log as it was.
+fn rewrite(
+    path: &Path,
+    ids: &HashMap<ClientId, ClientIds>,
+) -> Result<BufWriter<File>, ProcessedIdsError> {
+    let mut compacted = path.as_os_str().to_owned();
+    compacted.push(".compacting");
+    let compacted = PathBuf::from(compacted);
+    let mut writer = BufWriter::new(File::create(&compacted).map_err(io_error)?);
+    for (client_id, client_ids) in ids {
+        for (transaction_id, kind) in &client_ids.order {
+            writeln!(writer, "{client_id},{transaction_id},{kind}").map_err(io_error)?;
+        }
+    }
+    writer
+        .into_inner()
+        .map_err(|err| io_error(err.into_error()))?
+        .sync_all()
+        .map_err(io_error)?;
+    fs::rename(&compacted, path).map_err(io_error)?;
+    append(path)
+}
+
+fn io_error(err: io::Error) -> ProcessedIdsError {
+    ProcessedIdsError::IoError(err.to_string())
+}
+
+/// Recognises the transactions sent again after they were processed, e.g.
+/// after a restart, and lets them through as duplicates rather than applying
+/// them twice.
+/// A transaction is recorded once processed, whether it was applied or
+/// rejected, as sending it again is not meant to change what became of it.
+/// One that could not be processed for now, e.g. as the store of the accounts
+/// could not be reached, is not. Failing to record a transaction fails it
+/// with [`TransactionProcessorError::Unavailable`], as it could no longer be
+/// recognised after a restart.
+pub struct ReplayGuard {
+    inner: Arc<dyn TransactionProcessor + Send + Sync>,
+    processed_ids: Arc<ProcessedIds>,
+}
+
+#[async_trait]
+impl TransactionProcessor for ReplayGuard {
+    async fn process(
+        &self,
+        transaction: Transaction,
+    ) -> Result<SuccessStatus, TransactionProcessorError> {
+        if self.processed_ids.contains(&transaction) {
+            return Ok(SuccessStatus::Duplicate);
+        }
+        let result = self.inner.process(transaction.clone()).await;
+        if matches!(
+            result,
+            Ok(_) | Err(TransactionProcessorError::AccountTransactionError(_, _))
+        ) {
+            self.processed_ids.insert(&transaction).map_err(|err| {
+                TransactionProcessorError::Unavailable(Box::new(transaction), err.to_string())
+            })?;
+        }
+        result
+    }
+}
+
+impl ReplayGuard {
+    pub fn new(
+        inner: Arc<dyn TransactionProcessor + Send + Sync>,
+        processed_ids: Arc<ProcessedIds>,
+    ) -> Self {
+        Self {
+            inner,
+            processed_ids,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process, sync::Arc};
+
+    use assert_matches::assert_matches;
+
+    use crate::{
+        account::{AccountStore, SimpleAccountTransactor, SuccessStatus},
+        model::{Amount, Transaction, TransactionKind},
+        transaction_processor::{
+            SimpleTransactionProcessor, TransactionProcessor, TransactionProcessorError,
+        },
+    };
+
+    use super::{ProcessedIds, ProcessedIdsError, ReplayGuard};
+
+    fn transaction(client_id: u16, transaction_id: u32, kind: TransactionKind) -> Transaction {
+        Transaction {
+            client_id,
+            transaction_id,
+            kind,
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
+        }
+    }
+
+    fn deposit(client_id: u16, transaction_id: u32) -> Transaction {
+        transaction(
+            client_id,
+            transaction_id,
+            TransactionKind::Deposit {
+                amount: Amount::new(10_000),
+            },
+        )
+    }
+
+    fn guard(accounts: &Arc<AccountStore>, processed_ids: ProcessedIds) -> ReplayGuard {
+        ReplayGuard::new(
+            Arc::new(SimpleTransactionProcessor::new(
+                accounts.clone(),
+                Box::new(SimpleAccountTransactor::new()),
+            )),
+            Arc::new(processed_ids),
+        )
+    }
+
+    #[tokio::test]
+    async fn transactions_sent_again_after_a_restart_are_duplicates() {
+        let path = env::temp_dir().join(format!("jouet_paiement_processed_{}", process::id()));
+        let _ = fs::remove_file(&path);
+        let accounts = Arc::new(AccountStore::new());
+
+        let processor = guard(&accounts, ProcessedIds::open(&path).unwrap());
+        for transaction in [
+            deposit(1, 1),
+            transaction(1, 1, TransactionKind::Dispute),
+            deposit(2, 1),
+        ] {
+            assert_eq!(
+                processor.process(transaction).await,
+                Ok(SuccessStatus::Transacted)
+            );
+        }
+        assert_matches!(
+            processor
+                .process(transaction(
+                    2,
+                    2,
+                    TransactionKind::Withdrawal {
+                        amount: Amount::new(20_000)
+                    }
+                ))
+                .await,
+            Err(TransactionProcessorError::AccountTransactionError(_, _))
+        );
+        drop(processor);
+        // a crash half way through writing a line
+        fs::write(&path, fs::read_to_string(&path).unwrap() + "2,3,depo").unwrap();
+
+        // the accounts are kept elsewhere, e.g. in Redis, and survive the
+        // restart
+        let processor = guard(&accounts, ProcessedIds::open(&path).unwrap());
+        for transaction in [
+            deposit(1, 1),
+            transaction(1, 1, TransactionKind::Dispute),
+            transaction(
+                2,
+                2,
+                TransactionKind::Withdrawal {
+                    amount: Amount::new(20_000),
+                },
+            ),
+        ] {
+            assert_eq!(
+                processor.process(transaction).await,
+                Ok(SuccessStatus::Duplicate)
+            );
+        }
+        assert_eq!(
+            processor
+                .process(transaction(1, 1, TransactionKind::Resolve))
+                .await,
+            Ok(SuccessStatus::Transacted)
+        );
+        assert_eq!(accounts.get(1).unwrap().available(), Amount::new(10_000));
+        assert_eq!(
+            fs::read_to_string(&path).unwrap().lines().last(),
+            Some("1,1,resolve")
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn only_the_latest_ids_of_each_client_are_kept_once_compacted() {
+        let path = env::temp_dir().join(format!("jouet_paiement_compacted_{}", process::id()));
+        let _ = fs::remove_file(&path);
+
+        let processed_ids = ProcessedIds::open(&path).unwrap().with_retention(500);
+        for transaction_id in 0..1_500 {
+            processed_ids.insert(&deposit(1, transaction_id)).unwrap();
+        }
+        processed_ids.insert(&deposit(2, 0)).unwrap();
+        assert_eq!(processed_ids.len(), 501);
+        assert!(!processed_ids.contains(&deposit(1, 999)));
+        assert!(processed_ids.contains(&deposit(1, 1_000)));
+        // compacted once 1024 lines were written, with the 500 ids kept
+        assert_eq!(
+            fs::read_to_string(&path).unwrap().lines().count(),
+            500 + 477
+        );
+        drop(processed_ids);
+
+        let processed_ids = ProcessedIds::open(&path).unwrap().with_retention(500);
+        assert_eq!(processed_ids.len(), 501);
+        assert!(processed_ids.contains(&deposit(1, 1_499)));
+        assert!(processed_ids.contains(&deposit(2, 0)));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn malformed_lines_are_refused() {
+        let path = env::temp_dir().join(format!("jouet_paiement_malformed_{}", process::id()));
+        fs::write(&path, "1,1,deposit\n1,x,deposit\n").unwrap();
+        assert_eq!(
+            ProcessedIds::open(&path).err(),
+            Some(ProcessedIdsError::Malformed(2, "1,x,deposit".to_string()))
+        );
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,174 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use tokio::time::sleep;
+
+use crate::{account::SuccessStatus, model::Transaction};
+
+use super::{TransactionProcessor, TransactionProcessorError};
+
+/// How many times a transaction is tried, and how long to wait in between.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// The first try included, so that `1` means no retry.
+    pub max_attempts: usize,
+
+    /// The wait before the first retry, doubled before each of the next ones.
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            backoff: Duration::from_millis(10),
+        }
+    }
+}
+
+/// Tries the transactions failing with a retryable error again, as the
+/// policy says, counting the retries.
+/// Other errors are passed on straight away, as are the retryable ones once
+/// the attempts run out.
+pub struct Retrying {
+    inner: Arc<dyn TransactionProcessor + Send + Sync>,
+    policy: RetryPolicy,
+    retries: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl TransactionProcessor for Retrying {
+    async fn process(
+        &self,
+        transaction: Transaction,
+    ) -> Result<SuccessStatus, TransactionProcessorError> {
+        let mut backoff = self.policy.backoff;
+        let mut attempts = 1;
+        loop {
+            match self.inner.process(transaction.clone()).await {
+                Err(err) if err.is_retryable() && attempts < self.policy.max_attempts => {
+                    self.retries.fetch_add(1, Ordering::Relaxed);
+                    sleep(backoff).await;
+                    backoff *= 2;
+                    attempts += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl Retrying {
+    pub fn new(
+        inner: Arc<dyn TransactionProcessor + Send + Sync>,
+        policy: RetryPolicy,
+        retries: Arc<AtomicUsize>,
+    ) -> Self {
+        Self {
+            inner,
+            policy,
+            retries,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+    use rstest::rstest;
+
+    use crate::{
+        account::{account_transactor::AccountTransactorError, DisputerError, SuccessStatus},
+        model::{Transaction, TransactionKind},
+        transaction_processor::{TransactionProcessor, TransactionProcessorError},
+    };
+
+    use super::{RetryPolicy, Retrying};
+
+    /// Fails the given number of times with the error, then succeeds.
+    struct Flaky {
+        failures: AtomicUsize,
+        error: fn(Transaction) -> TransactionProcessorError,
+    }
+
+    #[async_trait]
+    impl TransactionProcessor for Flaky {
+        async fn process(
+            &self,
+            transaction: Transaction,
+        ) -> Result<SuccessStatus, TransactionProcessorError> {
+            match self
+                .failures
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |failures| {
+                    failures.checked_sub(1)
+                }) {
+                Ok(_) => Err((self.error)(transaction)),
+                Err(_) => Ok(SuccessStatus::Transacted),
+            }
+        }
+    }
+
+    #[rustfmt::skip]
+    #[rstest]
+    #[case::recovered          (2, unavailable as fn(_) -> _, true,  2)]
+    #[case::attempts_run_out   (3, unavailable as fn(_) -> _, false, 2)]
+    #[case::not_retryable      (1, locked      as fn(_) -> _, false, 0)]
+    #[tokio::test]
+    async fn retryable_errors_are_retried_until_the_attempts_run_out(
+        #[case] failures: usize,
+        #[case] error: fn(Transaction) -> TransactionProcessorError,
+        #[case] ok: bool,
+        #[case] expected_retries: usize,
+    ) {
+        let retries = Arc::new(AtomicUsize::new(0));
+        let retrying = Retrying::new(
+            Arc::new(Flaky {
+                failures: AtomicUsize::new(failures),
+                error,
+            }),
+            RetryPolicy {
+                max_attempts: 3,
+                backoff: Duration::from_millis(1),
+            },
+            retries.clone(),
+        );
+        assert_eq!(retrying.process(dispute()).await.is_ok(), ok);
+        assert_eq!(retries.load(Ordering::Relaxed), expected_retries);
+    }
+
+    fn unavailable(transaction: Transaction) -> TransactionProcessorError {
+        TransactionProcessorError::Unavailable(Box::new(transaction), "timed out".to_string())
+    }
+
+    fn locked(transaction: Transaction) -> TransactionProcessorError {
+        TransactionProcessorError::AccountTransactionError(
+            Box::new(transaction),
+            AccountTransactorError::Dispute(DisputerError::AccountLocked),
+        )
+    }
+
+    fn dispute() -> Transaction {
+        Transaction {
+            client_id: 1,
+            transaction_id: 1,
+            kind: TransactionKind::Dispute,
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
+        }
+    }
+}
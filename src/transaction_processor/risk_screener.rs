@@ -0,0 +1,139 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+
+use crate::{
+    account::SuccessStatus,
+    model::Transaction,
+    risk::{RiskEvent, RiskOutcome, RiskRule},
+};
+
+use super::{TransactionProcessor, TransactionProcessorError};
+
+/// Evaluates the rules against each transaction before passing it on to the
+/// processor it wraps, recording what the rules flag or block.
+/// A transaction blocked by any of the rules is not passed on.
+pub struct RiskScreener {
+    inner: Arc<dyn TransactionProcessor + Send + Sync>,
+    rules: Vec<Arc<dyn RiskRule + Send + Sync>>,
+    events: Arc<Mutex<Vec<RiskEvent>>>,
+}
+
+#[async_trait]
+impl TransactionProcessor for RiskScreener {
+    async fn process(
+        &self,
+        transaction: Transaction,
+    ) -> Result<SuccessStatus, TransactionProcessorError> {
+        let mut blocked = false;
+        for rule in &self.rules {
+            let outcome = rule.evaluate(&transaction);
+            if outcome == RiskOutcome::Pass {
+                continue;
+            }
+            blocked |= outcome == RiskOutcome::Blocked;
+            self.events
+                .lock()
+                .expect("The risk events are never locked across a panic.")
+                .push(RiskEvent {
+                    transaction: transaction.clone(),
+                    rule: rule.name().to_string(),
+                    outcome,
+                });
+        }
+        if blocked {
+            return Ok(SuccessStatus::NoOp);
+        }
+        self.inner.process(transaction).await
+    }
+}
+
+impl RiskScreener {
+    pub fn new(
+        inner: Arc<dyn TransactionProcessor + Send + Sync>,
+        rules: Vec<Arc<dyn RiskRule + Send + Sync>>,
+        events: Arc<Mutex<Vec<RiskEvent>>>,
+    ) -> Self {
+        Self {
+            inner,
+            rules,
+            events,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::{
+        model::{Transaction, TransactionKind},
+        risk::{RiskEvent, RiskOutcome, RiskRule},
+        transaction_processor::{RecordSink, TransactionProcessor},
+    };
+
+    use super::RiskScreener;
+
+    /// Gives the outcome it is set up with to the transaction with the id.
+    struct Picking(u32, RiskOutcome, &'static str);
+
+    impl RiskRule for Picking {
+        fn name(&self) -> &str {
+            self.2
+        }
+
+        fn evaluate(&self, transaction: &Transaction) -> RiskOutcome {
+            if transaction.transaction_id == self.0 {
+                self.1
+            } else {
+                RiskOutcome::Pass
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn flagged_transactions_are_passed_on_but_blocked_ones_are_not() {
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let screener = RiskScreener::new(
+            Arc::new(RecordSink {
+                records: records.clone(),
+            }),
+            vec![
+                Arc::new(Picking(2, RiskOutcome::Flagged, "flagging")),
+                Arc::new(Picking(3, RiskOutcome::Blocked, "blocking")),
+            ],
+            events.clone(),
+        );
+        for transaction_id in 1..=3 {
+            screener.process(dispute(transaction_id)).await.unwrap();
+        }
+        assert_eq!(*records.lock().unwrap(), vec![dispute(1), dispute(2)]);
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                RiskEvent {
+                    transaction: dispute(2),
+                    rule: "flagging".to_string(),
+                    outcome: RiskOutcome::Flagged,
+                },
+                RiskEvent {
+                    transaction: dispute(3),
+                    rule: "blocking".to_string(),
+                    outcome: RiskOutcome::Blocked,
+                },
+            ]
+        );
+    }
+
+    fn dispute(transaction_id: u32) -> Transaction {
+        Transaction {
+            client_id: 1,
+            transaction_id,
+            kind: TransactionKind::Dispute,
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
+        }
+    }
+}
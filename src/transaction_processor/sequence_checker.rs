@@ -0,0 +1,174 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+
+use crate::{
+    account::SuccessStatus,
+    model::{ClientId, Transaction, TransactionId},
+};
+
+use super::{TransactionProcessor, TransactionProcessorError};
+
+/// A transaction that reached the processor before one read ahead of it for
+/// the same client.
+#[derive(Debug, PartialEq, Clone)]
+pub struct OrderViolation {
+    pub client_id: ClientId,
+    pub transaction_id: TransactionId,
+    /// Where the transaction is in the input.
+    pub sequence: u64,
+    /// Where the transaction of the client processed before it is.
+    pub previous: u64,
+}
+
+impl Display for OrderViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "client {}, tx {} read at {} arrived after the one read at {}",
+            self.client_id, self.transaction_id, self.sequence, self.previous
+        )
+    }
+}
+
+/// What becomes of a transaction arriving out of order.
+#[derive(Debug, Clone)]
+pub enum OutOfOrder {
+    /// The worker panics, e.g. in a test.
+    Panic,
+
+    /// The violation is recorded and the transaction processed all the same.
+    Report(Arc<Mutex<Vec<OrderViolation>>>),
+}
+
+/// Checks that the transactions of each client reach the processor it wraps
+/// in the order they were read, going by their
+/// [`Transaction::ingest_sequence`], so that the routing of the transactions
+/// to the workers does not silently reorder them.
+/// The transactions without one are let through unchecked.
+pub struct SequenceChecker {
+    inner: Arc<dyn TransactionProcessor + Send + Sync>,
+    out_of_order: OutOfOrder,
+    last_seen: Mutex<HashMap<ClientId, u64>>,
+}
+
+#[async_trait]
+impl TransactionProcessor for SequenceChecker {
+    async fn process(
+        &self,
+        transaction: Transaction,
+    ) -> Result<SuccessStatus, TransactionProcessorError> {
+        if let Some(sequence) = transaction.ingest_sequence {
+            self.check(&transaction, sequence);
+        }
+        self.inner.process(transaction).await
+    }
+}
+
+impl SequenceChecker {
+    pub fn new(
+        inner: Arc<dyn TransactionProcessor + Send + Sync>,
+        out_of_order: OutOfOrder,
+    ) -> Self {
+        Self {
+            inner,
+            out_of_order,
+            last_seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn check(&self, transaction: &Transaction, sequence: u64) {
+        let previous = self
+            .last_seen
+            .lock()
+            .expect("The sequences are never locked across a panic.")
+            .insert(transaction.client_id, sequence);
+        let violation = match previous {
+            Some(previous) if previous >= sequence => OrderViolation {
+                client_id: transaction.client_id,
+                transaction_id: transaction.transaction_id,
+                sequence,
+                previous,
+            },
+            _ => return,
+        };
+        match &self.out_of_order {
+            OutOfOrder::Panic => panic!("Out of order: {violation}"),
+            OutOfOrder::Report(violations) => violations
+                .lock()
+                .expect("The violations are never locked across a panic.")
+                .push(violation),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use rstest::rstest;
+
+    use crate::{
+        model::{Amount, ClientId, Transaction, TransactionKind},
+        transaction_processor::{Blackhole, TransactionProcessor},
+    };
+
+    use super::{OrderViolation, OutOfOrder, SequenceChecker};
+
+    fn deposit(client_id: ClientId, sequence: Option<u64>) -> Transaction {
+        Transaction {
+            client_id,
+            transaction_id: sequence.unwrap_or_default() as u32,
+            kind: TransactionKind::Deposit {
+                amount: Amount::new(10_000),
+            },
+            timestamp: None,
+            memo: None,
+            ingest_sequence: sequence,
+        }
+    }
+
+    fn violation(client_id: ClientId, sequence: u64, previous: u64) -> OrderViolation {
+        OrderViolation {
+            client_id,
+            transaction_id: sequence as u32,
+            sequence,
+            previous,
+        }
+    }
+
+    #[rustfmt::skip]
+    #[rstest]
+    #[case(vec![(1, Some(0)), (2, Some(1)), (1, Some(2))], vec![])]
+    // the clients are checked apart from one another
+    #[case(vec![(2, Some(1)), (1, Some(0))],               vec![])]
+    #[case(vec![(1, Some(2)), (1, Some(0))],               vec![violation(1, 0, 2)])]
+    #[case(vec![(1, Some(2)), (1, Some(2))],               vec![violation(1, 2, 2)])]
+    #[case(vec![(1, Some(2)), (1, None), (1, Some(3))],    vec![])]
+    #[tokio::test]
+    async fn transactions_out_of_order_are_reported(
+        #[case] arrivals: Vec<(ClientId, Option<u64>)>,
+        #[case] expected: Vec<OrderViolation>,
+    ) {
+        let violations = Arc::new(Mutex::new(Vec::new()));
+        let checker = SequenceChecker::new(Arc::new(Blackhole), OutOfOrder::Report(violations.clone()));
+        for (client_id, sequence) in arrivals {
+            checker.process(deposit(client_id, sequence)).await.unwrap();
+        }
+        assert_eq!(*violations.lock().unwrap(), expected);
+    }
+
+    #[tokio::test]
+    #[should_panic(
+        expected = "Out of order: client 1, tx 0 read at 0 arrived after the one read at 1"
+    )]
+    async fn transactions_out_of_order_can_panic() {
+        let checker = SequenceChecker::new(Arc::new(Blackhole), OutOfOrder::Panic);
+        checker.process(deposit(1, Some(1))).await.unwrap();
+        checker.process(deposit(1, Some(0))).await.unwrap();
+    }
+}
@@ -1,35 +1,33 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use dashmap::DashMap;
 
 use super::{TransactionProcessor, TransactionProcessorError};
 use crate::account::account_transactor::AccountTransactor;
+use crate::account::{lock, AccountStore, SuccessStatus};
 use crate::model::Transaction;
-use crate::{account::Account, model::ClientId};
 
 pub struct SimpleTransactionProcessor {
-    accounts: Arc<DashMap<ClientId, Account>>,
-    account_transaction_processor: Box<dyn AccountTransactor + 'static + Send + Sync>,
+    accounts: Arc<AccountStore>,
+    account_transactor: Box<dyn AccountTransactor + 'static + Send + Sync>,
 }
 
 #[async_trait]
 impl TransactionProcessor for SimpleTransactionProcessor {
-    async fn process(&self, transaction: Transaction) -> Result<(), TransactionProcessorError> {
-        let client_id = transaction.client_id;
-        let mut binding = self
-            .accounts
-            .entry(client_id)
-            .or_insert_with(|| Account::active(client_id));
-        let account = binding.value_mut();
+    async fn process(
+        &self,
+        transaction: Transaction,
+    ) -> Result<SuccessStatus, TransactionProcessorError> {
+        let account = self.accounts.get_or_create(transaction.client_id);
+        let _transacting = self.accounts.transacting();
+        let result = self
+            .account_transactor
+            .transact(&mut lock(&account), transaction.clone());
 
-        match self
-            .account_transaction_processor
-            .transact(account, transaction.clone())
-        {
-            Ok(_status) => Ok(()),
+        match result {
+            Ok(status) => Ok(status),
             Err(err) => Err(TransactionProcessorError::AccountTransactionError(
-                transaction,
+                Box::new(transaction),
                 err,
             )),
         }
@@ -38,12 +36,12 @@ impl TransactionProcessor for SimpleTransactionProcessor {
 
 impl SimpleTransactionProcessor {
     pub fn new(
-        accounts: Arc<DashMap<ClientId, Account>>,
-        account_transaction_processor: Box<dyn AccountTransactor + 'static + Send + Sync>,
+        accounts: Arc<AccountStore>,
+        account_transactor: Box<dyn AccountTransactor + 'static + Send + Sync>,
     ) -> Self {
         Self {
             accounts,
-            account_transaction_processor,
+            account_transactor,
         }
     }
 }
@@ -52,16 +50,12 @@ impl SimpleTransactionProcessor {
 mod tests {
     use std::sync::Arc;
 
-    use dashmap::DashMap;
-
     use crate::{
         account::{
             account_transactor::{AccountTransactor, AccountTransactorError},
-            Account,
-        },
-        model::{
-            Amount, Amount4DecimalBased, ClientId, Transaction, TransactionId, TransactionKind,
+            Account, AccountStore, SuccessStatus,
         },
+        model::{Amount, ClientId, Transaction, TransactionId, TransactionKind},
         transaction_processor::TransactionProcessor,
     };
 
@@ -69,19 +63,19 @@ mod tests {
 
     const CLIENT_ID: ClientId = 123;
     const TRANSACTION_ID: TransactionId = 456;
-    const AMOUNT: Amount = Amount4DecimalBased(7890);
+    const AMOUNT: Amount = Amount::new(7890);
 
-    pub struct MockAccountTransactionProcessor {
+    pub struct MockAccountTransactor {
         expected_request: (Account, Transaction),
-        return_val: Result<(), AccountTransactorError>,
+        return_val: Result<SuccessStatus, AccountTransactorError>,
     }
 
-    impl AccountTransactor for MockAccountTransactionProcessor {
+    impl AccountTransactor for MockAccountTransactor {
         fn transact(
             &self,
             account: &mut Account,
             transaction: Transaction,
-        ) -> Result<(), AccountTransactorError> {
+        ) -> Result<SuccessStatus, AccountTransactorError> {
             let (expected_account, expected_transaction) = self.expected_request.clone();
             assert_eq!(*account, expected_account);
             assert_eq!(transaction, expected_transaction);
@@ -95,17 +89,23 @@ mod tests {
             client_id: CLIENT_ID,
             transaction_id: TRANSACTION_ID,
             kind: TransactionKind::Deposit { amount: AMOUNT },
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
         };
         let account = Account::active(CLIENT_ID);
-        let accounts = Arc::new(DashMap::new());
-        accounts.insert(CLIENT_ID, account.clone());
-        let account_transaction_processor = MockAccountTransactionProcessor {
+        let accounts = Arc::new(AccountStore::new());
+        accounts.insert(account.clone());
+        let account_transactor = MockAccountTransactor {
             expected_request: (account.clone(), transaction.clone()),
-            return_val: Ok(()),
+            return_val: Ok(SuccessStatus::Duplicate),
         };
         let transaction_processor =
-            SimpleTransactionProcessor::new(accounts, Box::new(account_transaction_processor));
-        transaction_processor.process(transaction).await.unwrap();
+            SimpleTransactionProcessor::new(accounts, Box::new(account_transactor));
+        assert_eq!(
+            transaction_processor.process(transaction).await,
+            Ok(SuccessStatus::Duplicate)
+        );
     }
 
     #[tokio::test]
@@ -114,21 +114,19 @@ mod tests {
             client_id: CLIENT_ID,
             transaction_id: TRANSACTION_ID,
             kind: TransactionKind::Deposit { amount: AMOUNT },
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
         };
         let account = Account::active(CLIENT_ID);
-        let accounts = Arc::new(DashMap::new());
-        let account_transaction_processor = MockAccountTransactionProcessor {
+        let accounts = Arc::new(AccountStore::new());
+        let account_transactor = MockAccountTransactor {
             expected_request: (account.clone(), transaction.clone()),
-            return_val: Ok(()),
+            return_val: Ok(SuccessStatus::Transacted),
         };
-        let transaction_processor = SimpleTransactionProcessor::new(
-            accounts.clone(),
-            Box::new(account_transaction_processor),
-        );
+        let transaction_processor =
+            SimpleTransactionProcessor::new(accounts.clone(), Box::new(account_transactor));
         transaction_processor.process(transaction).await.unwrap();
-        assert_eq!(
-            *accounts.get(&CLIENT_ID).unwrap().value(),
-            Account::active(CLIENT_ID)
-        );
+        assert_eq!(accounts.get(CLIENT_ID), Some(Account::active(CLIENT_ID)));
     }
 }
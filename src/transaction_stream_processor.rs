@@ -1,9 +1,35 @@
+mod amount_profile;
+mod amount_rules;
 pub mod async_csv_stream_processor;
+#[cfg(feature = "avro")]
+pub mod avro_stream_processor;
+pub mod byte_range_stream_processor;
+mod chunker;
+mod csv_reader;
 pub mod csv_stream_processor;
 mod error_handler;
+mod header_validator;
+#[cfg(feature = "server")]
+pub(crate) mod line_protocol;
+mod memory_budget;
+#[cfg(feature = "parquet")]
+pub mod parquet_stream_processor;
+pub mod pipeline;
+mod quarantine;
+mod rate_limiter;
+mod shard;
 mod transaction_record_converter;
-
-use std::{io::Read, num::ParseFloatError};
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+mod uring_reader;
+mod watchdog;
+
+use std::{
+    io::Read,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
 use async_trait::async_trait;
 
@@ -12,10 +38,23 @@ use serde::Serialize;
 use thiserror::Error;
 
 use crate::{
-    model::{ClientId, TransactionId},
+    model::{AmountParseError, ClientId, TransactionId},
     transaction_processor::TransactionProcessorError,
 };
 
+pub use amount_profile::AmountProfile;
+pub use amount_rules::{
+    AmountRule, AmountStrictness, AmountStrictnessError, AmountWarning, AmountWarnings,
+};
+pub use csv_reader::CsvOptions;
+pub use memory_budget::{InFlight, MemoryBudget};
+pub use quarantine::Quarantine;
+pub use rate_limiter::{RateLimiter, Throughput};
+pub use shard::{Shard, ShardError};
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub use uring_reader::UringReader;
+pub use watchdog::{QueuedChannel, Stall, Watchdog};
+
 #[async_trait]
 pub trait TransactionStreamProcessor {
     async fn process(&self, r: impl Read + Send) -> Result<(), TransactionStreamProcessError>;
@@ -32,16 +71,63 @@ trait ErrorHandler {
 pub enum TransactionStreamProcessError {
     #[error("Error occurred during parsing the input data: {0}")]
     ParsingError(String),
+    #[error("The headers of the input data are invalid: {0}")]
+    InvalidHeaders(String),
     #[error("Error occurred during processing the `TransactionRecord` {0:?}")]
     ProcessError(TransactionProcessorError),
     #[error("Failed to shutdown the processor: {0}")]
     FailedToShutdown(String),
     #[error("An internal error has occurred: {0}")]
     InternalError(String),
+    #[error("The processing was cancelled before the end of the input.")]
+    Cancelled,
+}
+
+/// Tells a stream processor to stop reading its input.
+/// The transactions read before the cancellation are still processed, so
+/// that the accounts are left as they are after the last of them.
+#[derive(Debug, Clone, Default)]
+pub struct Cancellation(Arc<AtomicBool>);
+
+impl Cancellation {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A row of the input, in one of the supported formats.
+#[derive(Debug, PartialEq)]
+pub enum TransactionRecord {
+    V1(TransactionRecordV1),
+    V2(TransactionRecordV2),
+}
+
+/// The original format: `type`, `client`, `tx` and `amount`.
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct TransactionRecordV1 {
+    #[serde(rename = "type")]
+    pub txn_type: TransactionRecordType,
+    #[serde(rename = "client")]
+    pub client_id: ClientId,
+    #[serde(rename = "tx")]
+    pub transaction_id: TransactionId,
+    #[serde(rename = "amount")]
+    pub optional_amount: Option<String>,
 }
 
+/// The format with the `timestamp`, `currency` and `memo` columns added
+/// upstream.
+/// The timestamp, memo and metadata are carried into the [`Transaction`], the
+/// `currency` column is accepted but not used by the engine yet.
+/// The `metadata` column is optional.
+///
+/// [`Transaction`]: crate::model::Transaction
 #[derive(Debug, Deserialize, PartialEq, Serialize)]
-pub struct TransactionRecord {
+pub struct TransactionRecordV2 {
     #[serde(rename = "type")]
     pub txn_type: TransactionRecordType,
     #[serde(rename = "client")]
@@ -50,6 +136,28 @@ pub struct TransactionRecord {
     pub transaction_id: TransactionId,
     #[serde(rename = "amount")]
     pub optional_amount: Option<String>,
+    /// Seconds since the Unix epoch.
+    #[serde(rename = "timestamp")]
+    pub optional_timestamp: Option<u64>,
+    #[serde(rename = "currency")]
+    pub optional_currency: Option<String>,
+    #[serde(rename = "memo")]
+    pub optional_memo: Option<String>,
+    /// `key=value` pairs separated by `;`, e.g. `batch=7;source=sftp`.
+    #[serde(rename = "metadata")]
+    pub optional_metadata: Option<String>,
+}
+
+impl From<TransactionRecordV1> for TransactionRecord {
+    fn from(record: TransactionRecordV1) -> Self {
+        Self::V1(record)
+    }
+}
+
+impl From<TransactionRecordV2> for TransactionRecord {
+    fn from(record: TransactionRecordV2) -> Self {
+        Self::V2(record)
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, PartialEq)]
@@ -64,18 +172,29 @@ pub enum TransactionRecordType {
     Resolve,
     #[serde(rename = "chargeback")]
     Chargeback,
+    #[serde(rename = "representment")]
+    Representment,
+    #[serde(rename = "close")]
+    Close,
+    #[serde(rename = "adjustment")]
+    Adjustment,
 }
 
 impl From<TransactionProcessorError> for TransactionStreamProcessError {
     fn from(err: TransactionProcessorError) -> Self {
-        match err {
-            TransactionProcessorError::AccountTransactionError(_, _) => Self::ProcessError(err),
-        }
+        Self::ProcessError(err)
     }
 }
 
-impl From<ParseFloatError> for TransactionStreamProcessError {
-    fn from(err: ParseFloatError) -> Self {
+impl From<AmountParseError> for TransactionStreamProcessError {
+    fn from(err: AmountParseError) -> Self {
+        Self::ParsingError(err.to_string())
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl From<rust_decimal::Error> for TransactionStreamProcessError {
+    fn from(err: rust_decimal::Error) -> Self {
         Self::ParsingError(err.to_string())
     }
 }
@@ -89,20 +208,19 @@ mod tests {
     use rstest::rstest;
     use rstest_reuse::{apply, template};
 
-    use super::TransactionStreamProcessError;
-    use crate::account::account_transactor::AccountTransactorError::{
-        self, AccountLocked, IncompatibleTransaction,
-    };
+    use super::{AmountProfile, AmountStrictness, CsvOptions, TransactionStreamProcessError};
+    use crate::account::account_transactor::AccountTransactorError;
     use crate::account::AccountStatus::Active;
     use crate::account::DepositStatus::Accepted;
-    use crate::account::{Account, AccountSnapshot, Deposit, SimpleAccountTransactor, Withdrawal};
+    use crate::account::{
+        Account, AccountSnapshot, AccountStore, Deposit, DepositorError, Incompatibility,
+        ResolverError, SimpleAccountTransactor, Withdrawal,
+    };
     use crate::transaction_stream_processor::async_csv_stream_processor::AsyncCsvStreamProcessor;
     use crate::transaction_stream_processor::csv_stream_processor::CsvStreamProcessor;
     use crate::transaction_stream_processor::TransactionStreamProcessor;
 
-    use crate::model::{
-        Amount4DecimalBased, ClientId, Transaction, TransactionId, TransactionKind,
-    };
+    use crate::model::{Amount, ClientId, Transaction, TransactionId, TransactionKind};
     use crate::transaction_processor::{
         RecordSink, SimpleTransactionProcessor, TransactionProcessorError,
     };
@@ -112,23 +230,23 @@ mod tests {
     #[case("
     type,    client, tx, amount
     deposit,      1,  2,    3.0",
-            vec![deposit(1, 2, 30_000)])]
+            vec![at_line(3, deposit(1, 2, 30_000))])]
     #[case("
     type,       client, tx, amount
     withdrawal,      4,  5,    6.0",
-            vec![withdrawal(4, 5, 60_000)])]
+            vec![at_line(3, withdrawal(4, 5, 60_000))])]
     #[case("
     type,    client, tx, amount
     dispute,      7,  8,       ",
-            vec![dispute(7, 8)])]
+            vec![at_line(3, dispute(7, 8))])]
     #[case("
     type,    client, tx, amount
     resolve,      9, 10,       ",
-            vec![resolve(9, 10)])]
+            vec![at_line(3, resolve(9, 10))])]
     #[case("
     type,       client, tx, amount
     chargeback,     11, 12,       ",
-            vec![chargeback(11, 12)])]
+            vec![at_line(3, chargeback(11, 12))])]
     #[case("
     type,       client,  tx, amount
     deposit,         1,  2,     3.0
@@ -136,11 +254,11 @@ mod tests {
     dispute,         7,  8,
     resolve,         9, 10,
     chargeback,     11, 12,",
-            vec![deposit(1, 2, 30_000),
-            withdrawal(1, 5, 60_000),
-            dispute(7, 8),
-            resolve(9, 10),
-            chargeback(11, 12)])]
+            vec![at_line(3, deposit(1, 2, 30_000)),
+            at_line(4, withdrawal(1, 5, 60_000)),
+            at_line(5, dispute(7, 8)),
+            at_line(6, resolve(9, 10)),
+            at_line(7, chargeback(11, 12))])]
     fn happy_path_cases(#[case] input: &str, #[case] expected: Vec<Transaction>) {}
 
     #[apply(happy_path_cases)]
@@ -176,6 +294,62 @@ mod tests {
         assert_eq!(*records.lock().unwrap(), expected);
     }
 
+    const PARTNER_INPUT: &str = "
+    txn_type; client_id; txn_id; value
+    deposit;          1;      2;   3.0
+    dispute;          1;      2;";
+
+    #[tokio::test]
+    async fn csv_options_are_honoured_by_async_stream_processor() {
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let record_sink = RecordSink {
+            records: records.clone(),
+        };
+
+        let processor = AsyncCsvStreamProcessor::with_options(
+            Arc::new(record_sink),
+            DashMap::new(),
+            partner_csv_options(),
+        );
+        processor.process(PARTNER_INPUT.as_bytes()).await.unwrap();
+        processor.shutdown().await.unwrap();
+        assert_eq!(
+            *records.lock().unwrap(),
+            vec![at_line(3, deposit(1, 2, 30_000)), at_line(4, dispute(1, 2))]
+        );
+    }
+
+    #[tokio::test]
+    async fn csv_options_are_honoured_by_simple_stream_processor() {
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let record_sink = RecordSink {
+            records: records.clone(),
+        };
+
+        let processor =
+            CsvStreamProcessor::with_options(Box::new(record_sink), partner_csv_options());
+        processor.process(PARTNER_INPUT.as_bytes()).await.unwrap();
+        assert_eq!(
+            *records.lock().unwrap(),
+            vec![at_line(3, deposit(1, 2, 30_000)), at_line(4, dispute(1, 2))]
+        );
+    }
+
+    fn partner_csv_options() -> CsvOptions {
+        CsvOptions {
+            delimiter: b';',
+            has_headers: true,
+            column_map: HashMap::from([
+                ("txn_type".to_string(), "type".to_string()),
+                ("client_id".to_string(), "client".to_string()),
+                ("txn_id".to_string(), "tx".to_string()),
+                ("value".to_string(), "amount".to_string()),
+            ]),
+            amount_strictness: AmountStrictness::default(),
+            amount_profile: AmountProfile::default(),
+        }
+    }
+
     #[template]
     #[rstest]
     #[case("
@@ -193,8 +367,7 @@ mod tests {
         Err(TransactionStreamProcessError::ProcessError(incompatible(Transaction {
             client_id: 2,
             transaction_id: 2,
-            kind: TransactionKind::Resolve
-        })))
+            kind: TransactionKind::Resolve, timestamp: None, memo: None, ingest_sequence: Some(5), })))
     )]
     #[case(
         "
@@ -207,8 +380,7 @@ mod tests {
         Err(TransactionStreamProcessError::ProcessError(account_lock(Transaction {
             client_id: 2,
             transaction_id: 3,
-            kind: TransactionKind::Deposit { amount: Amount4DecimalBased(10_000) }
-        })))
+            kind: TransactionKind::Deposit { amount: Amount::new(10_000) }, timestamp: None, memo: None, ingest_sequence: Some(7), })))
     )]
     fn transaction_error_cases(
         #[case] input: &str,
@@ -222,7 +394,7 @@ mod tests {
         #[case] input: &str,
         #[case] expected: Result<(), TransactionStreamProcessError>,
     ) {
-        let accounts = Arc::new(DashMap::new());
+        let accounts = Arc::new(AccountStore::new());
 
         let processor = AsyncCsvStreamProcessor::new(
             Arc::new(SimpleTransactionProcessor::new(
@@ -241,7 +413,7 @@ mod tests {
         #[case] input: &str,
         #[case] expected: Result<(), TransactionStreamProcessError>,
     ) {
-        let accounts = Arc::new(DashMap::new());
+        let accounts = Arc::new(AccountStore::new());
 
         let processor = CsvStreamProcessor::new(Box::new(SimpleTransactionProcessor::new(
             accounts.clone(),
@@ -252,12 +424,10 @@ mod tests {
 
     #[tokio::test]
     async fn e2_account_storage_with_small_input_using_async_processor() {
-        let accounts = Arc::new(DashMap::new());
-        let account_transaction_processor = SimpleAccountTransactor::new();
-        let transaction_processor = SimpleTransactionProcessor::new(
-            accounts.clone(),
-            Box::new(account_transaction_processor),
-        );
+        let accounts = Arc::new(AccountStore::new());
+        let account_transactor = SimpleAccountTransactor::new();
+        let transaction_processor =
+            SimpleTransactionProcessor::new(accounts.clone(), Box::new(account_transactor));
         let senders_and_handles = DashMap::new();
 
         let processor =
@@ -270,11 +440,11 @@ mod tests {
     deposit,      2, 30,    6.0";
 
         let mut client_1_deposits = HashMap::new();
-        client_1_deposits.insert(10, accepted_deposit(40_000));
-        client_1_deposits.insert(20, accepted_deposit(50_000));
+        client_1_deposits.insert(10, accepted_deposit(40_000, 0));
+        client_1_deposits.insert(20, accepted_deposit(50_000, 1));
 
         let mut client_2_deposits = HashMap::new();
-        client_2_deposits.insert(30, accepted_deposit(60_000));
+        client_2_deposits.insert(30, accepted_deposit(60_000, 0));
 
         let mut expected_accounts = HashMap::new();
         expected_accounts.insert(
@@ -289,11 +459,11 @@ mod tests {
         processor.process(input.as_bytes()).await.unwrap();
         processor.shutdown().await.unwrap();
         assert_eq!(accounts.len(), expected_accounts.len());
-        accounts.iter().for_each(|entry| {
-            let key = entry.key();
-            let value = entry.value();
-            assert_eq!(value, expected_accounts.get(key).unwrap());
-        });
+        expected_accounts
+            .into_iter()
+            .for_each(|(client_id, expected)| {
+                assert_eq!(accounts.get(client_id), Some(expected));
+            });
     }
 
     fn deposit(client_id: ClientId, transaction_id: TransactionId, amount: i64) -> Transaction {
@@ -301,8 +471,11 @@ mod tests {
             client_id,
             transaction_id,
             kind: TransactionKind::Deposit {
-                amount: Amount4DecimalBased(amount),
+                amount: Amount::new(amount),
             },
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
         }
     }
 
@@ -311,8 +484,11 @@ mod tests {
             client_id,
             transaction_id,
             kind: TransactionKind::Withdrawal {
-                amount: Amount4DecimalBased(amount),
+                amount: Amount::new(amount),
             },
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
         }
     }
 
@@ -321,6 +497,9 @@ mod tests {
             client_id,
             transaction_id,
             kind: TransactionKind::Dispute,
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
         }
     }
 
@@ -329,6 +508,9 @@ mod tests {
             client_id,
             transaction_id,
             kind: TransactionKind::Resolve,
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
         }
     }
 
@@ -337,6 +519,16 @@ mod tests {
             client_id,
             transaction_id,
             kind: TransactionKind::ChargeBack,
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
+        }
+    }
+
+    fn at_line(line: u64, transaction: Transaction) -> Transaction {
+        Transaction {
+            ingest_sequence: Some(line),
+            ..transaction
         }
     }
 
@@ -353,25 +545,42 @@ mod tests {
         Account::new(client_id, Active, account_snapshot, deposits, withdrawals)
     }
 
-    fn accepted_deposit(amount: i64) -> Deposit {
+    fn accepted_deposit(amount: i64, sequence: usize) -> Deposit {
         Deposit {
-            amount: Amount4DecimalBased(amount),
+            amount: Amount::new(amount),
             status: Accepted,
+            timestamp: None,
+            sequence,
         }
     }
 
     fn account_lock(transaction: Transaction) -> TransactionProcessorError {
-        transaction_processor_error(transaction, AccountLocked)
+        transaction_processor_error(
+            transaction,
+            AccountTransactorError::Deposit(DepositorError::AccountLocked),
+        )
     }
 
     fn incompatible(transaction: Transaction) -> TransactionProcessorError {
-        transaction_processor_error(transaction, IncompatibleTransaction)
+        let incompatibility = Incompatibility {
+            client_id: transaction.client_id,
+            transaction_id: transaction.transaction_id,
+            requested: TransactionKind::Resolve,
+            status: Accepted,
+        };
+        transaction_processor_error(
+            transaction,
+            AccountTransactorError::Resolve(ResolverError::NonDisputedTransaction(incompatibility)),
+        )
     }
 
     fn transaction_processor_error(
         transaction: Transaction,
         account_transactor_error: AccountTransactorError,
     ) -> TransactionProcessorError {
-        TransactionProcessorError::AccountTransactionError(transaction, account_transactor_error)
+        TransactionProcessorError::AccountTransactionError(
+            Box::new(transaction),
+            account_transactor_error,
+        )
     }
 }
@@ -0,0 +1,176 @@
+use std::borrow::Cow;
+
+use super::TransactionStreamProcessError;
+
+/// How the amounts of an input are written, for the feeds that do not write
+/// them as `1234.5`, e.g. `1,50` or `1e3`. They are rewritten as such, their
+/// digits moved rather than computed, then parsed digit for digit, so that
+/// no precision is lost on the way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AmountProfile {
+    /// Whether amounts like `1e3` or `2.5E-2` are accepted.
+    pub scientific_notation: bool,
+
+    /// The decimal separator, `.` by default, e.g. `,` for `1,50`.
+    pub decimal_separator: char,
+
+    /// The thousands separator, if any, e.g. `.` for `1.234,50`.
+    pub thousands_separator: Option<char>,
+}
+
+impl Default for AmountProfile {
+    fn default() -> Self {
+        Self {
+            scientific_notation: false,
+            decimal_separator: '.',
+            thousands_separator: None,
+        }
+    }
+}
+
+/// The largest exponent of an amount in scientific notation, well beyond any
+/// amount the engine can hold, so that `1e999999` is not written out.
+const MAX_EXPONENT: u32 = 32;
+
+impl AmountProfile {
+    /// The amount written as `1234.5`, untouched with the default profile
+    /// unless it is in scientific notation, which only the profiles accepting
+    /// it do.
+    pub(super) fn normalise<'a>(
+        &self,
+        amount: &'a str,
+    ) -> Result<Cow<'a, str>, TransactionStreamProcessError> {
+        let invalid = |reason: &str| {
+            TransactionStreamProcessError::ParsingError(format!(
+                "Invalid amount `{amount}`, {reason}."
+            ))
+        };
+        if !self.scientific_notation && amount.contains(['e', 'E']) {
+            return Err(invalid("scientific notation is not accepted"));
+        }
+        if *self == Self::default() {
+            return Ok(Cow::Borrowed(amount));
+        }
+        if self.thousands_separator == Some(self.decimal_separator) {
+            return Err(invalid("the thousands and decimal separators are the same"));
+        }
+        let trimmed = amount.trim();
+        let (sign, unsigned) = match trimmed.strip_prefix('-') {
+            Some(unsigned) => ("-", unsigned),
+            None => ("", trimmed.strip_prefix('+').unwrap_or(trimmed)),
+        };
+        let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+            Some(at) => {
+                let exponent: i32 = unsigned[at + 1..]
+                    .parse()
+                    .map_err(|_| invalid("its exponent is not a number"))?;
+                if exponent.unsigned_abs() > MAX_EXPONENT {
+                    return Err(invalid("its exponent is out of range"));
+                }
+                (&unsigned[..at], exponent)
+            }
+            _ => (unsigned, 0),
+        };
+        let mantissa: String = mantissa
+            .chars()
+            .filter(|c| Some(*c) != self.thousands_separator)
+            .collect();
+        let (integer, fraction) = match mantissa.split_once(self.decimal_separator) {
+            Some((integer, fraction)) => (integer, fraction),
+            None => (mantissa.as_str(), ""),
+        };
+        if fraction.contains(self.decimal_separator) {
+            return Err(invalid("it has more than one decimal separator"));
+        }
+        Ok(Cow::Owned(format!(
+            "{sign}{}",
+            shift_point(integer, fraction, exponent)
+        )))
+    }
+}
+
+/// The number of the integer and fraction digits, its point moved by the
+/// exponent.
+fn shift_point(integer: &str, fraction: &str, exponent: i32) -> String {
+    let digits = format!("{integer}{fraction}");
+    let point = integer.len() as i64 + i64::from(exponent);
+    if point <= 0 {
+        format!("0.{}{digits}", "0".repeat(point.unsigned_abs() as usize))
+    } else if point as usize >= digits.len() {
+        format!("{digits}{}", "0".repeat(point as usize - digits.len()))
+    } else {
+        let (integer, fraction) = digits.split_at(point as usize);
+        format!("{integer}.{fraction}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use rstest::rstest;
+
+    use crate::transaction_stream_processor::TransactionStreamProcessError;
+
+    use super::AmountProfile;
+
+    fn profile(
+        scientific_notation: bool,
+        decimal_separator: char,
+        thousands_separator: Option<char>,
+    ) -> AmountProfile {
+        AmountProfile {
+            scientific_notation,
+            decimal_separator,
+            thousands_separator,
+        }
+    }
+
+    #[rstest]
+    #[case::default(AmountProfile::default(), "-1.5", "-1.5")]
+    #[case::scientific(profile(true, '.', None), "1e3", "1000")]
+    #[case::negative_exponent(profile(true, '.', None), "2.5E-2", "0.025")]
+    #[case::point_inside(profile(true, '.', None), "-1.2345e2", "-123.45")]
+    #[case::without_exponent(profile(true, '.', None), "1.5", "1.5")]
+    #[case::comma_decimal(profile(false, ',', None), "1,50", "1.50")]
+    #[case::thousands(profile(false, ',', Some('.')), "1.234.567,5", "1234567.5")]
+    #[case::thousands_and_point(profile(false, '.', Some(',')), "1,234.5", "1234.5")]
+    #[case::everything(profile(true, ',', Some(' ')), "1 234,5e-1", "123.45")]
+    fn amounts_are_written_as_the_engine_parses_them(
+        #[case] profile: AmountProfile,
+        #[case] amount: &str,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(profile.normalise(amount).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case(AmountProfile::default(), "1e3", "scientific notation is not accepted")]
+    #[case(
+        profile(false, ',', None),
+        "2,5E-2",
+        "scientific notation is not accepted"
+    )]
+    #[case(profile(true, '.', None), "1e", "its exponent is not a number")]
+    #[case(profile(true, '.', None), "1e99", "its exponent is out of range")]
+    #[case(
+        profile(false, ',', None),
+        "1,5,0",
+        "it has more than one decimal separator"
+    )]
+    #[case(
+        profile(false, ',', Some(',')),
+        "1,5",
+        "the thousands and decimal separators are the same"
+    )]
+    fn malformed_amounts_are_rejected(
+        #[case] profile: AmountProfile,
+        #[case] amount: &str,
+        #[case] reason: &str,
+    ) {
+        assert_matches!(
+            profile.normalise(amount),
+            Err(TransactionStreamProcessError::ParsingError(message))
+                if message == format!("Invalid amount `{amount}`, {reason}.")
+        );
+    }
+}
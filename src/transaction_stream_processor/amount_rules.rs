@@ -0,0 +1,251 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use super::{TransactionRecordType, TransactionStreamProcessError};
+
+/// Whether the records of a type carry an amount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AmountRule {
+    Required,
+    Forbidden,
+    Optional,
+}
+
+impl TransactionRecordType {
+    /// The one place telling which types of records carry an amount, which
+    /// the records of every format are checked against as they are read.
+    pub fn amount_rule(&self) -> AmountRule {
+        match self {
+            Self::Deposit | Self::Withdrawal | Self::Adjustment => AmountRule::Required,
+            Self::Dispute
+            | Self::Resolve
+            | Self::Chargeback
+            | Self::Representment
+            | Self::Close => AmountRule::Forbidden,
+        }
+    }
+
+    /// The name of the type, as in the input.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Deposit => "deposit",
+            Self::Withdrawal => "withdrawal",
+            Self::Dispute => "dispute",
+            Self::Resolve => "resolve",
+            Self::Chargeback => "chargeback",
+            Self::Representment => "representment",
+            Self::Close => "close",
+            Self::Adjustment => "adjustment",
+        }
+    }
+}
+
+/// What becomes of a record with an amount when its type takes none, e.g. a
+/// dispute with the amount of the deposit it disputes. A record without the
+/// amount its type requires always fails to be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AmountStrictness {
+    /// The amount is ignored, an [`AmountWarning`] being recorded.
+    #[default]
+    Warn,
+    /// The record fails to be read.
+    Reject,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum AmountStrictnessError {
+    #[error("Invalid amount strictness `{0}`, expected `warn` or `reject`.")]
+    InvalidStrictness(String),
+}
+
+impl FromStr for AmountStrictness {
+    type Err = AmountStrictnessError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "warn" => Ok(Self::Warn),
+            "reject" => Ok(Self::Reject),
+            _ => Err(AmountStrictnessError::InvalidStrictness(s.to_string())),
+        }
+    }
+}
+
+/// A record whose amount was ignored, as its type takes none.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AmountWarning {
+    /// The line of the input the record starts at, if known.
+    pub line: Option<u64>,
+    pub txn_type: &'static str,
+    pub amount: String,
+}
+
+impl Display for AmountWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Ignored the amount `{}` of the {}{}, which takes none.",
+            self.amount,
+            self.txn_type,
+            on_line(self.line)
+        )
+    }
+}
+
+/// The [`AmountWarning`]s of a run, shared by the readers of its input.
+#[derive(Debug, Clone, Default)]
+pub struct AmountWarnings(Arc<Mutex<Vec<AmountWarning>>>);
+
+impl AmountWarnings {
+    /// The warnings so far, in the order of their lines.
+    pub fn warnings(&self) -> Vec<AmountWarning> {
+        let mut warnings = self
+            .0
+            .lock()
+            .expect("The amount warnings are never locked across a panic.")
+            .clone();
+        warnings.sort_by_key(|warning| warning.line);
+        warnings
+    }
+
+    fn add(&self, warning: AmountWarning) {
+        self.0
+            .lock()
+            .expect("The amount warnings are never locked across a panic.")
+            .push(warning);
+    }
+}
+
+/// Checks the amounts of the records against the [`AmountRule`]s of their
+/// types, as strict as told.
+#[derive(Debug, Clone, Default)]
+pub(super) struct AmountCheck {
+    strictness: AmountStrictness,
+    /// Where the ignored amounts are recorded, if anywhere.
+    warnings: Option<AmountWarnings>,
+}
+
+impl AmountCheck {
+    pub(super) fn new(strictness: AmountStrictness) -> Self {
+        Self {
+            strictness,
+            warnings: None,
+        }
+    }
+
+    /// The ignored amounts are recorded in the warnings, if any.
+    pub(super) fn with_warnings(self, warnings: Option<AmountWarnings>) -> Self {
+        Self { warnings, ..self }
+    }
+
+    /// The amount of a record, none if its type takes none.
+    pub(super) fn apply<'a>(
+        &self,
+        txn_type: &TransactionRecordType,
+        amount: Option<&'a str>,
+        line: Option<u64>,
+    ) -> Result<Option<&'a str>, TransactionStreamProcessError> {
+        match (txn_type.amount_rule(), amount) {
+            (AmountRule::Required, None) => {
+                Err(TransactionStreamProcessError::ParsingError(format!(
+                    "The {}{} has no amount, which it requires.",
+                    txn_type.name(),
+                    on_line(line)
+                )))
+            }
+            (AmountRule::Forbidden, Some(amount)) => match self.strictness {
+                AmountStrictness::Reject => {
+                    Err(TransactionStreamProcessError::ParsingError(format!(
+                        "The {}{} has an amount, `{amount}`, which it does not take.",
+                        txn_type.name(),
+                        on_line(line)
+                    )))
+                }
+                AmountStrictness::Warn => {
+                    if let Some(warnings) = &self.warnings {
+                        warnings.add(AmountWarning {
+                            line,
+                            txn_type: txn_type.name(),
+                            amount: amount.to_string(),
+                        });
+                    }
+                    Ok(None)
+                }
+            },
+            (_, amount) => Ok(amount),
+        }
+    }
+}
+
+fn on_line(line: Option<u64>) -> String {
+    line.map(|line| format!(" on line {line}"))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use rstest::rstest;
+
+    use crate::transaction_stream_processor::{
+        TransactionRecordType, TransactionStreamProcessError,
+    };
+
+    use super::{AmountCheck, AmountStrictness, AmountWarning, AmountWarnings};
+
+    #[rstest]
+    #[case::required_and_given(TransactionRecordType::Deposit, Some("1.0"), Ok(Some("1.0")))]
+    #[case::required_and_missing(
+        TransactionRecordType::Withdrawal,
+        None,
+        Err("The withdrawal on line 4 has no amount, which it requires.")
+    )]
+    #[case::forbidden_and_missing(TransactionRecordType::Dispute, None, Ok(None))]
+    #[case::forbidden_and_given(
+        TransactionRecordType::Chargeback,
+        Some("1.0"),
+        Err("The chargeback on line 4 has an amount, `1.0`, which it does not take.")
+    )]
+    fn amounts_are_checked_against_the_rule_of_their_type(
+        #[case] txn_type: TransactionRecordType,
+        #[case] amount: Option<&str>,
+        #[case] expected: Result<Option<&str>, &str>,
+    ) {
+        let check = AmountCheck::new(AmountStrictness::Reject);
+        match expected {
+            Ok(expected) => assert_eq!(check.apply(&txn_type, amount, Some(4)), Ok(expected)),
+            Err(message) => assert_matches!(
+                check.apply(&txn_type, amount, Some(4)),
+                Err(TransactionStreamProcessError::ParsingError(err)) if err == message
+            ),
+        }
+    }
+
+    #[test]
+    fn an_amount_a_type_does_not_take_is_ignored_with_a_warning() {
+        let warnings = AmountWarnings::default();
+        let check = AmountCheck::new(AmountStrictness::Warn).with_warnings(Some(warnings.clone()));
+        assert_eq!(
+            check.apply(&TransactionRecordType::Resolve, Some("2.5"), Some(7)),
+            Ok(None)
+        );
+        assert_eq!(
+            warnings.warnings(),
+            vec![AmountWarning {
+                line: Some(7),
+                txn_type: "resolve",
+                amount: "2.5".to_string(),
+            }]
+        );
+        assert_eq!(
+            warnings.warnings()[0].to_string(),
+            "Ignored the amount `2.5` of the resolve on line 7, which takes none."
+        );
+    }
+}
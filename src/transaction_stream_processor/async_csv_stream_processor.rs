@@ -1,159 +1,558 @@
-use std::{io::Read, sync::Arc};
+use std::{
+    io::Read,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
 
 use async_trait::async_trait;
-use csv::Trim;
-use dashmap::DashMap;
-use tokio::{
-    sync::mpsc::{channel, Sender},
-    task::JoinHandle,
-};
+use tokio::sync::broadcast;
 
 use crate::{
-    model::{ClientId, Transaction},
+    model::Transaction,
     transaction_processor::{TransactionProcessor, TransactionProcessorError},
 };
 
 use super::{
-    error_handler::SimpleErrorHandler, transaction_record_converter::to_transaction, ErrorHandler,
-    TransactionStreamProcessError, TransactionStreamProcessor,
+    csv_reader::{CsvOptions, TransactionRecordReader},
+    pipeline::{
+        ClientRouter, ParallelCsvSource, Pipeline, ProcessingSink, Router, TransactionSink,
+        CHUNK_LEN,
+    },
+    AmountWarnings, Cancellation, MemoryBudget, Quarantine, RateLimiter, Shard,
+    TransactionStreamProcessError, TransactionStreamProcessor, Watchdog,
 };
 
+pub use super::pipeline::{Lanes, SendersAndHandles, WorkerHandle, DEFAULT_CHANNEL_CAPACITY};
+
+/// Reads a CSV input, as it goes or with several parsers, into a
+/// [`Pipeline`] routing the transactions to a worker per client, unless told
+/// to route them otherwise.
 pub struct AsyncCsvStreamProcessor {
     transaction_processor: Arc<dyn TransactionProcessor + Send + Sync>,
-    senders_and_handles: DashMap<
-        ClientId,
-        (
-            Sender<Transaction>,
-            JoinHandle<Result<(), TransactionProcessorError>>,
-        ),
-    >,
-    error_handler: Arc<dyn ErrorHandler + Send + Sync>,
+    senders_and_handles: Arc<SendersAndHandles>,
+    idle_timeout: Option<Duration>,
+    csv_options: CsvOptions,
+    cancellation: Cancellation,
+    memory_budget: Option<MemoryBudget>,
+    rate_limiter: Option<RateLimiter>,
+    watchdog: Option<Watchdog>,
+    admin_priority: bool,
+    errors: Option<broadcast::Sender<TransactionProcessorError>>,
+    quarantine: Option<Quarantine>,
+    client_quarantine: Option<Quarantine>,
+    amount_warnings: Option<AmountWarnings>,
+    shard: Option<Shard>,
+    channel_capacity: usize,
+    parsers: usize,
+    chunk_len: usize,
+    /// Routes the transactions instead of a [`ClientRouter`], if given.
+    router: Option<Arc<dyn Router>>,
+    /// Built from the settings above once the first transaction is routed.
+    pipeline: OnceLock<Pipeline>,
 }
 
 #[async_trait]
 impl TransactionStreamProcessor for AsyncCsvStreamProcessor {
     async fn process(&self, r: impl Read + Send) -> Result<(), TransactionStreamProcessError> {
-        let mut rdr = csv::ReaderBuilder::new().trim(Trim::All).from_reader(r);
-        for result in rdr.deserialize() {
-            match result {
-                Ok(it) => self.do_process(to_transaction(it)?).await?,
-                Err(err) => {
-                    return Err(TransactionStreamProcessError::ParsingError(err.to_string()))
-                }
-            };
+        if self.parsers > 1 {
+            let source = ParallelCsvSource::new(r, &self.csv_options, self.parsers)?
+                .with_quarantine(self.quarantine.clone())
+                .with_client_quarantine(self.client_quarantine.clone())
+                .with_amount_warnings(self.amount_warnings.clone())
+                .with_shard(self.shard)
+                .with_chunk_len(self.chunk_len);
+            return self.pipeline().run(source).await;
         }
-        Ok(())
+        let source = TransactionRecordReader::new(r, &self.csv_options)?
+            .with_quarantine(self.quarantine.clone())
+            .with_client_quarantine(self.client_quarantine.clone())
+            .with_amount_warnings(self.amount_warnings.clone())
+            .with_shard(self.shard)
+            .transactions();
+        self.pipeline().run(source).await
     }
 }
 
 impl AsyncCsvStreamProcessor {
-    async fn do_process(
+    /// Sends a transaction read by other means than
+    /// [`TransactionStreamProcessor::process`], e.g. from a socket, to the
+    /// worker of its client.
+    pub async fn process_transaction(
         &self,
         transaction: Transaction,
     ) -> Result<(), TransactionStreamProcessError> {
-        let client_id = transaction.client_id;
-        let binding = self
-            .senders_and_handles
-            .entry(client_id)
-            .or_insert_with(|| self.create_channel());
-        let sender = &binding.0;
-        match sender.send(transaction).await {
-            Ok(_) => {}
-            Err(err) => {
-                return Err(TransactionStreamProcessError::InternalError(
-                    err.to_string(),
-                ));
-            }
-        };
-        Ok(())
+        self.pipeline().dispatch(transaction).await
     }
 
-    fn create_channel(
-        &self,
-    ) -> (
-        Sender<Transaction>,
-        JoinHandle<Result<(), TransactionProcessorError>>,
-    ) {
-        // TODO: make this configurable
-        let (sender, mut receiver) = channel::<Transaction>(256);
-        let clone = self.transaction_processor.clone();
-        let error_handler_clone = self.error_handler.clone();
-        let handle = tokio::spawn(async move {
-            while let Some(transaction) = receiver.recv().await {
-                match clone.process(transaction).await {
-                    Ok(_) => {}
-                    Err(err) => error_handler_clone.handle(err)?,
-                };
-            }
-            Ok(())
-        });
-        (sender, handle)
+    fn pipeline(&self) -> &Pipeline {
+        self.pipeline.get_or_init(|| {
+            let router = match &self.router {
+                Some(router) => router.clone(),
+                None => Arc::new(
+                    ClientRouter::new(self.sink(), self.senders_and_handles.clone())
+                        .with_channel_capacity(self.channel_capacity)
+                        .with_admin_priority(self.admin_priority)
+                        .with_idle_timeout(self.idle_timeout),
+                ),
+            };
+            Pipeline::new(router)
+                .with_cancellation(self.cancellation.clone())
+                .with_memory_budget(self.memory_budget.clone())
+                .with_rate_limiter(self.rate_limiter.clone())
+                .with_watchdog(self.watchdog.clone())
+        })
+    }
+
+    /// Where the workers give the transactions: the processor, along with the
+    /// watchdog and the errors given so far, for a router
+    /// of one's own, see [`AsyncCsvStreamProcessor::with_router`].
+    pub fn sink(&self) -> Arc<dyn TransactionSink> {
+        Arc::new(
+            ProcessingSink::new(self.transaction_processor.clone())
+                .with_watchdog(self.watchdog.clone())
+                .with_errors(self.errors.clone()),
+        )
     }
 
     pub fn new(
         consumer: Arc<dyn TransactionProcessor + Send + Sync>,
-        senders_and_handles: DashMap<
-            ClientId,
-            (
-                Sender<Transaction>,
-                JoinHandle<Result<(), TransactionProcessorError>>,
-            ),
-        >,
+        senders_and_handles: SendersAndHandles,
+    ) -> Self {
+        Self::with_options(consumer, senders_and_handles, CsvOptions::default())
+    }
+
+    pub fn with_options(
+        consumer: Arc<dyn TransactionProcessor + Send + Sync>,
+        senders_and_handles: SendersAndHandles,
+        csv_options: CsvOptions,
     ) -> Self {
-        let error_handler = SimpleErrorHandler;
         Self {
             transaction_processor: consumer,
-            senders_and_handles,
-            error_handler: Arc::new(error_handler),
+            senders_and_handles: Arc::new(senders_and_handles),
+            idle_timeout: None,
+            csv_options,
+            cancellation: Cancellation::default(),
+            memory_budget: None,
+            rate_limiter: None,
+            watchdog: None,
+            admin_priority: false,
+            errors: None,
+            quarantine: None,
+            client_quarantine: None,
+            amount_warnings: None,
+            shard: None,
+            channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+            parsers: 1,
+            chunk_len: CHUNK_LEN,
+            router: None,
+            pipeline: OnceLock::new(),
+        }
+    }
+
+    /// Reading the input is held back whenever the accounts and the
+    /// transactions in flight are over budget.
+    pub fn with_memory_budget(self, memory_budget: MemoryBudget) -> Self {
+        Self {
+            memory_budget: Some(memory_budget),
+            ..self
+        }
+    }
+
+    /// The input is read no faster than the rate limiter lets it.
+    pub fn with_rate_limiter(self, rate_limiter: RateLimiter) -> Self {
+        Self {
+            rate_limiter: Some(rate_limiter),
+            ..self
+        }
+    }
+
+    /// A [`Stall`] is reported whenever no transaction is dispatched to a
+    /// worker, nor processed by one, for the watchdog's timeout while the
+    /// input is read, along with the channels holding transactions then.
+    ///
+    /// [`Stall`]: super::Stall
+    pub fn with_watchdog(self, watchdog: Watchdog) -> Self {
+        Self {
+            watchdog: Some(watchdog),
+            ..self
+        }
+    }
+
+    /// The admin transactions of a client, see [`TransactionKind::is_admin`],
+    /// are processed ahead of its regular ones waiting to be, e.g. to lock an
+    /// account in a live stream straight away. The regular transactions are
+    /// still processed in the order they were read.
+    ///
+    /// [`TransactionKind::is_admin`]: crate::model::TransactionKind::is_admin
+    pub fn with_admin_priority(self, admin_priority: bool) -> Self {
+        Self {
+            admin_priority,
+            ..self
+        }
+    }
+
+    /// The worker of a client exits once it has not been given a transaction
+    /// for that long, its channel being dropped, so that the clients seen
+    /// once do not hold on to them for the whole run. A worker is spawned
+    /// again for the next transaction of the client, once the previous one
+    /// is done.
+    pub fn with_idle_timeout(self, idle_timeout: Duration) -> Self {
+        Self {
+            idle_timeout: Some(idle_timeout),
+            ..self
+        }
+    }
+
+    /// The input stops being read once the cancellation is cancelled, the
+    /// processing then failing with [`TransactionStreamProcessError::Cancelled`].
+    pub fn with_cancellation(self, cancellation: Cancellation) -> Self {
+        Self {
+            cancellation,
+            ..self
+        }
+    }
+
+    /// Every error of the processor is sent as soon as it occurs, whether it
+    /// fails the run or not, so that it can be acted upon mid-run.
+    pub fn with_errors(self, errors: broadcast::Sender<TransactionProcessorError>) -> Self {
+        Self {
+            errors: Some(errors),
+            ..self
         }
     }
 
+    /// The records of a type the engine does not know of are put in the
+    /// quarantine rather than failing the processing.
+    pub fn with_quarantine(self, quarantine: Quarantine) -> Self {
+        Self {
+            quarantine: Some(quarantine),
+            ..self
+        }
+    }
+
+    /// The records whose client id is out of range are put in the quarantine
+    /// rather than failing the processing.
+    pub fn with_client_quarantine(self, quarantine: Quarantine) -> Self {
+        Self {
+            client_quarantine: Some(quarantine),
+            ..self
+        }
+    }
+
+    /// The amounts ignored as the types of their records take none, see
+    /// [`CsvOptions::amount_strictness`], are recorded in the warnings.
+    pub fn with_amount_warnings(self, amount_warnings: AmountWarnings) -> Self {
+        Self {
+            amount_warnings: Some(amount_warnings),
+            ..self
+        }
+    }
+
+    /// Only the records of the clients of the shard are processed.
+    pub fn with_shard(self, shard: Shard) -> Self {
+        Self {
+            shard: Some(shard),
+            ..self
+        }
+    }
+
+    /// Reading the input waits for a client's transactions to be processed
+    /// once that many of them are waiting.
+    pub fn with_channel_capacity(self, channel_capacity: usize) -> Self {
+        Self {
+            channel_capacity,
+            ..self
+        }
+    }
+
+    /// The input is parsed by that many tasks at the same time, rather than
+    /// as it is read, for when parsing rather than processing is what holds
+    /// the run back.
+    pub fn with_parsers(self, parsers: usize) -> Self {
+        Self { parsers, ..self }
+    }
+
+    /// The transactions are routed by the router given, e.g. a
+    /// [`WorkerPoolRouter`], rather than to a worker per client, the admin
+    /// priority, the idle timeout and the channel capacity being then up to
+    /// it. To be given a router built on [`AsyncCsvStreamProcessor::sink`]
+    /// once the other settings are.
+    ///
+    /// [`WorkerPoolRouter`]: super::pipeline::WorkerPoolRouter
+    pub fn with_router(self, router: Arc<dyn Router>) -> Self {
+        Self {
+            router: Some(router),
+            ..self
+        }
+    }
+
+    /// Waits for the workers to be done with the transactions routed to
+    /// them.
     pub async fn shutdown(self) -> Result<(), TransactionStreamProcessError> {
-        for (_, (sender, handle)) in self.senders_and_handles {
-            drop(sender);
-            match handle.await {
-                Ok(process_reesult) => match process_reesult {
-                    Ok(_) => {}
-                    Err(process_err) => {
-                        return Err(TransactionStreamProcessError::ProcessError(process_err));
-                    }
-                },
-                Err(e) => {
-                    return Err(TransactionStreamProcessError::FailedToShutdown(
-                        e.to_string(),
-                    ))
-                }
-            }
+        match self.pipeline.get() {
+            Some(pipeline) => pipeline.shutdown().await,
+            // nothing was ever routed
+            None => Ok(()),
         }
-        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::sync::Arc;
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
 
     use assert_matches::assert_matches;
 
+    use async_trait::async_trait;
     use dashmap::DashMap;
+    use rstest::rstest;
+    use tokio::{
+        sync::{broadcast, Notify},
+        time::sleep,
+    };
 
-    use crate::transaction_processor::Blackhole;
+    use crate::account::SuccessStatus;
+    use crate::model::{Amount, Transaction, TransactionId, TransactionKind};
+    use crate::transaction_processor::{
+        Blackhole, OutOfOrder, RecordSink, SequenceChecker, TransactionProcessor,
+        TransactionProcessorError,
+    };
     use crate::transaction_stream_processor::async_csv_stream_processor::AsyncCsvStreamProcessor;
     use crate::transaction_stream_processor::{
-        TransactionStreamProcessError, TransactionStreamProcessor,
+        QueuedChannel, TransactionStreamProcessError, TransactionStreamProcessor, Watchdog,
     };
 
+    #[rstest]
+    #[case::as_read(1)]
+    #[case::in_parallel(4)]
     #[tokio::test]
-    async fn missing_coma_for_the_optional_field_results_in_parsing_error() {
+    async fn missing_coma_for_the_optional_field_results_in_parsing_error(#[case] parsers: usize) {
         let input = "
     type,    client, tx, amount
     dispute,      7,  8";
         let blackhole = Blackhole;
-        let processor = AsyncCsvStreamProcessor::new(Arc::new(blackhole), DashMap::new());
+        let processor =
+            AsyncCsvStreamProcessor::new(Arc::new(blackhole), DashMap::new()).with_parsers(parsers);
         assert_matches!(
             processor.process(input.as_bytes()).await,
             Err(TransactionStreamProcessError::ParsingError(_))
         );
         processor.shutdown().await.unwrap();
     }
+
+    #[rstest]
+    #[case::as_read(1)]
+    #[case::in_parallel(4)]
+    #[tokio::test]
+    async fn transactions_of_each_client_reach_the_worker_in_input_order(#[case] parsers: usize) {
+        let mut input = String::from("type, client, tx, amount\n");
+        // the clients interleaved unevenly, so that some of them have their
+        // channel full while others are idle
+        for transaction_id in 0..2_000 {
+            let client_id = (transaction_id * 7 + transaction_id / 13) % 37;
+            input.push_str(&format!("deposit, {client_id}, {transaction_id}, 1.0\n"));
+        }
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let violations = Arc::new(Mutex::new(Vec::new()));
+        let processor = AsyncCsvStreamProcessor {
+            // a few records per chunk
+            chunk_len: 64,
+            ..AsyncCsvStreamProcessor::new(
+                Arc::new(SequenceChecker::new(
+                    Arc::new(RecordSink {
+                        records: records.clone(),
+                    }),
+                    OutOfOrder::Report(violations.clone()),
+                )),
+                DashMap::new(),
+            )
+        }
+        .with_channel_capacity(1)
+        .with_parsers(parsers);
+        processor.process(input.as_bytes()).await.unwrap();
+        processor.shutdown().await.unwrap();
+
+        assert_eq!(*violations.lock().unwrap(), vec![]);
+        let records = records.lock().unwrap();
+        assert_eq!(records.len(), 2_000);
+        // the headers being on the first line
+        assert!(records.iter().all(|transaction| transaction.ingest_sequence
+            == Some(u64::from(transaction.transaction_id) + 2)));
+    }
+
+    /// Holds the first transaction back until opened, so that the others
+    /// wait in the channels.
+    struct Gate {
+        entered: Notify,
+        open: Notify,
+        processed: Mutex<Vec<TransactionId>>,
+    }
+
+    #[async_trait]
+    impl TransactionProcessor for Gate {
+        async fn process(
+            &self,
+            transaction: Transaction,
+        ) -> Result<SuccessStatus, TransactionProcessorError> {
+            if transaction.transaction_id == 1 {
+                self.entered.notify_one();
+                self.open.notified().await;
+            }
+            self.processed
+                .lock()
+                .unwrap()
+                .push(transaction.transaction_id);
+            Ok(SuccessStatus::Transacted)
+        }
+    }
+
+    #[rstest]
+    #[case::with_priority(true, vec![1, 4, 2, 3, 5])]
+    #[case::without_priority(false, vec![1, 2, 3, 4, 5])]
+    #[tokio::test]
+    async fn admin_transactions_overtake_the_regular_ones_waiting(
+        #[case] admin_priority: bool,
+        #[case] expected: Vec<TransactionId>,
+    ) {
+        let gate = Arc::new(Gate {
+            entered: Notify::new(),
+            open: Notify::new(),
+            processed: Mutex::new(Vec::new()),
+        });
+        let processor = AsyncCsvStreamProcessor::new(gate.clone(), DashMap::new())
+            .with_admin_priority(admin_priority);
+        let transaction = |transaction_id, kind| Transaction {
+            client_id: 1,
+            transaction_id,
+            kind,
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
+        };
+        let deposit = TransactionKind::Deposit {
+            amount: Amount::new(10_000),
+        };
+        processor
+            .process_transaction(transaction(1, deposit.clone()))
+            .await
+            .unwrap();
+        gate.entered.notified().await;
+        for (transaction_id, kind) in [
+            (2, deposit.clone()),
+            (3, deposit.clone()),
+            (4, TransactionKind::Close),
+            (5, deposit),
+        ] {
+            processor
+                .process_transaction(transaction(transaction_id, kind))
+                .await
+                .unwrap();
+        }
+        gate.open.notify_one();
+        processor.shutdown().await.unwrap();
+
+        assert_eq!(*gate.processed.lock().unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn a_full_channel_holding_the_input_back_is_reported() {
+        let gate = Arc::new(Gate {
+            entered: Notify::new(),
+            open: Notify::new(),
+            processed: Mutex::new(Vec::new()),
+        });
+        let (stalls, mut received) = broadcast::channel(4);
+        let processor = AsyncCsvStreamProcessor::new(gate.clone(), DashMap::new())
+            .with_channel_capacity(1)
+            .with_watchdog(Watchdog::new(Duration::from_millis(20), stalls));
+        // the worker is stuck with the first transaction, the second one
+        // filling the channel and the third one waiting for room in it
+        let input = "
+    type,    client, tx, amount
+    deposit,      1,  1,    1.0
+    deposit,      1,  2,    1.0
+    deposit,      1,  3,    1.0";
+        let (processed, stall) = tokio::join!(processor.process(input.as_bytes()), async {
+            let stall = received.recv().await.unwrap();
+            gate.open.notify_one();
+            stall
+        });
+        processed.unwrap();
+        processor.shutdown().await.unwrap();
+
+        assert_eq!(
+            stall.channels,
+            vec![QueuedChannel {
+                client_id: 1,
+                queued: 1,
+                capacity: 1,
+            }]
+        );
+        assert_eq!(*gate.processed.lock().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn idle_workers_give_their_channels_back() {
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let processor = AsyncCsvStreamProcessor::new(
+            Arc::new(RecordSink {
+                records: records.clone(),
+            }),
+            DashMap::new(),
+        )
+        .with_idle_timeout(Duration::from_millis(10));
+        let input = "
+    type,    client, tx, amount
+    deposit,      1,  1,    1.0
+    deposit,      2,  2,    1.0";
+        processor.process(input.as_bytes()).await.unwrap();
+        sleep(Duration::from_millis(100)).await;
+        assert!(processor.senders_and_handles.is_empty());
+
+        // a worker is spawned again for the client
+        let input = "
+    type,    client, tx, amount
+    deposit,      1,  3,    1.0";
+        processor.process(input.as_bytes()).await.unwrap();
+        processor.shutdown().await.unwrap();
+        let mut transaction_ids: Vec<TransactionId> = records
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|transaction| transaction.transaction_id)
+            .collect();
+        transaction_ids.sort();
+        assert_eq!(transaction_ids, vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn transactions_of_a_client_keep_their_order_across_its_workers() {
+        let mut input = String::from("type, client, tx, amount\n");
+        for transaction_id in 0..2_000 {
+            let client_id = transaction_id % 7;
+            input.push_str(&format!("deposit, {client_id}, {transaction_id}, 1.0\n"));
+        }
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let violations = Arc::new(Mutex::new(Vec::new()));
+        let processor = AsyncCsvStreamProcessor::new(
+            Arc::new(SequenceChecker::new(
+                Arc::new(RecordSink {
+                    records: records.clone(),
+                }),
+                OutOfOrder::Report(violations.clone()),
+            )),
+            DashMap::new(),
+        )
+        // the workers exit as soon as they wait for a transaction
+        .with_idle_timeout(Duration::ZERO)
+        .with_channel_capacity(1);
+        processor.process(input.as_bytes()).await.unwrap();
+        processor.shutdown().await.unwrap();
+
+        assert_eq!(*violations.lock().unwrap(), vec![]);
+        assert_eq!(records.lock().unwrap().len(), 2_000);
+    }
 }
@@ -0,0 +1,264 @@
+use std::io::Read;
+
+use apache_avro::{from_value, schema_compatibility::SchemaCompatibility, Reader, Schema};
+use async_trait::async_trait;
+
+use crate::transaction_processor::TransactionProcessor;
+
+use super::{
+    error_handler::SimpleErrorHandler, transaction_record_converter::to_transaction, ErrorHandler,
+    TransactionRecordV1, TransactionStreamProcessError, TransactionStreamProcessor,
+};
+
+/// The Avro schema of [`TransactionRecordV1`]s accepted by the
+/// [`AvroStreamProcessor`].
+/// This is the schema to register with the schema registry. Files written with
+/// any schema that this one can read (as per the Avro schema resolution rules)
+/// are accepted.
+pub const TRANSACTION_RECORD_SCHEMA: &str = r#"
+{
+    "type": "record",
+    "name": "TransactionRecord",
+    "namespace": "jouet_paiement",
+    "fields": [
+        {
+            "name": "type",
+            "type": {
+                "type": "enum",
+                "name": "TransactionRecordType",
+                "symbols": ["deposit", "withdrawal", "dispute", "resolve", "chargeback", "representment", "close", "adjustment"]
+            }
+        },
+        { "name": "client", "type": "int" },
+        { "name": "tx", "type": "long" },
+        { "name": "amount", "type": ["null", "string"], "default": null }
+    ]
+}
+"#;
+
+/// Processes transactions from an Avro object container file.
+/// The writer's schema embedded in the file is resolved against
+/// [`TRANSACTION_RECORD_SCHEMA`] before any record is processed.
+pub struct AvroStreamProcessor {
+    consumer: Box<dyn TransactionProcessor + Send + Sync>,
+    error_handler: Box<dyn ErrorHandler + Send + Sync>,
+    schema: Schema,
+}
+
+#[async_trait]
+impl TransactionStreamProcessor for AvroStreamProcessor {
+    async fn process(&self, r: impl Read + Send) -> Result<(), TransactionStreamProcessError> {
+        let reader = Reader::builder(r)
+            .reader_schema(&self.schema)
+            .build()
+            .map_err(|err| TransactionStreamProcessError::ParsingError(err.to_string()))?;
+        if let Err(err) = SchemaCompatibility::can_read(reader.writer_schema(), &self.schema) {
+            return Err(TransactionStreamProcessError::ParsingError(format!(
+                "The writer's schema is incompatible: {err}"
+            )));
+        }
+        for (position, result) in (1..).zip(reader) {
+            let record = result
+                .and_then(|value| from_value::<TransactionRecordV1>(&value))
+                .map_err(|err| TransactionStreamProcessError::ParsingError(err.to_string()))?;
+            let mut transaction = to_transaction(record)?;
+            transaction.ingest_sequence = Some(position);
+            match self.consumer.process(transaction).await {
+                Ok(_) => {}
+                Err(err) => self.error_handler.handle(err)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl AvroStreamProcessor {
+    pub fn new(consumer: Box<dyn TransactionProcessor + Send + Sync>) -> Self {
+        let error_handler = SimpleErrorHandler;
+        Self {
+            consumer,
+            error_handler: Box::new(error_handler),
+            schema: Schema::parse_str(TRANSACTION_RECORD_SCHEMA)
+                .expect("The transaction record schema is a valid Avro schema."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use apache_avro::{Schema, Writer};
+    use assert_matches::assert_matches;
+    use serde::Serialize;
+
+    use crate::{
+        model::{Amount, Transaction, TransactionKind},
+        transaction_processor::{Blackhole, RecordSink},
+        transaction_stream_processor::{
+            TransactionRecordType, TransactionRecordV1, TransactionStreamProcessError,
+            TransactionStreamProcessor,
+        },
+    };
+
+    use super::{AvroStreamProcessor, TRANSACTION_RECORD_SCHEMA};
+
+    #[tokio::test]
+    async fn avro_parsing_works() {
+        let input = avro(
+            TRANSACTION_RECORD_SCHEMA,
+            vec![
+                record(TransactionRecordType::Deposit, 1, 10, Some("3.0")),
+                record(TransactionRecordType::Withdrawal, 1, 20, Some("1.5")),
+                record(TransactionRecordType::Dispute, 2, 30, None),
+            ],
+        );
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let record_sink = RecordSink {
+            records: records.clone(),
+        };
+
+        let processor = AvroStreamProcessor::new(Box::new(record_sink));
+        processor.process(input.as_slice()).await.unwrap();
+        assert_eq!(
+            *records.lock().unwrap(),
+            vec![
+                transaction(1, 1, 10, deposit(30_000)),
+                transaction(2, 1, 20, withdrawal(15_000)),
+                transaction(3, 2, 30, TransactionKind::Dispute),
+            ]
+        );
+    }
+
+    #[derive(Serialize)]
+    struct RecordWithoutAmount {
+        #[serde(rename = "type")]
+        txn_type: TransactionRecordType,
+        client: u16,
+        tx: u32,
+    }
+
+    #[tokio::test]
+    async fn compatible_writer_schema_is_resolved() {
+        // an older producer that never had the `amount` field
+        let writer_schema = r#"
+        {
+            "type": "record",
+            "name": "TransactionRecord",
+            "namespace": "jouet_paiement",
+            "fields": [
+                {
+                    "type": {
+                        "type": "enum",
+                        "name": "TransactionRecordType",
+                        "symbols": ["deposit", "withdrawal", "dispute", "resolve", "chargeback"]
+                    },
+                    "name": "type"
+                },
+                { "name": "client", "type": "int" },
+                { "name": "tx", "type": "long" }
+            ]
+        }"#;
+        let input = avro(
+            writer_schema,
+            vec![RecordWithoutAmount {
+                txn_type: TransactionRecordType::Resolve,
+                client: 7,
+                tx: 8,
+            }],
+        );
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let record_sink = RecordSink {
+            records: records.clone(),
+        };
+
+        let processor = AvroStreamProcessor::new(Box::new(record_sink));
+        processor.process(input.as_slice()).await.unwrap();
+        assert_eq!(
+            *records.lock().unwrap(),
+            vec![transaction(1, 7, 8, TransactionKind::Resolve)]
+        );
+    }
+
+    #[tokio::test]
+    async fn incompatible_writer_schema_results_in_parsing_error() {
+        let writer_schema = r#"
+        {
+            "type": "record",
+            "name": "TransactionRecord",
+            "namespace": "jouet_paiement",
+            "fields": [{ "name": "client", "type": "int" }]
+        }"#;
+        #[derive(Serialize)]
+        struct ClientOnly {
+            client: u16,
+        }
+        let input = avro(writer_schema, vec![ClientOnly { client: 1 }]);
+
+        let processor = AvroStreamProcessor::new(Box::new(Blackhole));
+        assert_matches!(
+            processor.process(input.as_slice()).await,
+            Err(TransactionStreamProcessError::ParsingError(_))
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_input_results_in_parsing_error() {
+        let processor = AvroStreamProcessor::new(Box::new(Blackhole));
+        assert_matches!(
+            processor.process("type,client,tx,amount".as_bytes()).await,
+            Err(TransactionStreamProcessError::ParsingError(_))
+        );
+    }
+
+    fn avro<T: Serialize>(schema: &str, records: Vec<T>) -> Vec<u8> {
+        let schema = Schema::parse_str(schema).unwrap();
+        let mut writer = Writer::new(&schema, vec![]).unwrap();
+        for record in records {
+            writer.append_ser(record).unwrap();
+        }
+        writer.into_inner().unwrap()
+    }
+
+    fn record(
+        txn_type: TransactionRecordType,
+        client_id: u16,
+        transaction_id: u32,
+        optional_amount: Option<&str>,
+    ) -> TransactionRecordV1 {
+        TransactionRecordV1 {
+            txn_type,
+            client_id,
+            transaction_id,
+            optional_amount: optional_amount.map(|s| s.to_string()),
+        }
+    }
+
+    fn transaction(
+        position: u64,
+        client_id: u16,
+        transaction_id: u32,
+        kind: TransactionKind,
+    ) -> Transaction {
+        Transaction {
+            client_id,
+            transaction_id,
+            kind,
+            timestamp: None,
+            memo: None,
+            ingest_sequence: Some(position),
+        }
+    }
+
+    fn deposit(amount: i64) -> TransactionKind {
+        TransactionKind::Deposit {
+            amount: Amount::new(amount),
+        }
+    }
+
+    fn withdrawal(amount: i64) -> TransactionKind {
+        TransactionKind::Withdrawal {
+            amount: Amount::new(amount),
+        }
+    }
+}
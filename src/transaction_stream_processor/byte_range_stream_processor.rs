@@ -0,0 +1,470 @@
+use std::{
+    fs::File,
+    future::Future,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+    mem,
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+};
+
+use tokio::{
+    sync::{broadcast, mpsc},
+    task::{spawn_blocking, JoinHandle},
+};
+
+use crate::{
+    model::Transaction,
+    transaction_processor::{TransactionProcessor, TransactionProcessorError},
+};
+
+use super::{
+    csv_reader::{CsvOptions, Layout, TransactionRecordReader},
+    error_handler::SimpleErrorHandler,
+    AmountWarnings, Cancellation, ErrorHandler, Quarantine, RateLimiter, Shard,
+    TransactionStreamProcessError,
+};
+
+/// How many transactions a parser hands over to a worker at a time.
+const BATCH_LEN: usize = 1024;
+
+/// How many batches of a range a worker can fall behind by before the parser
+/// of the range waits for it.
+const BATCHES_IN_FLIGHT: usize = 16;
+
+/// A part of a file made of whole records.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    /// Splits the file into at most `count` ranges of about the same length,
+    /// each of them ending with a line break, but for the last one.
+    /// The records are told apart by their line breaks, so a quoted field
+    /// spanning several lines may be split across two ranges.
+    pub fn plan(file: &mut (impl Read + Seek), count: usize) -> io::Result<Vec<Self>> {
+        let len = file.seek(SeekFrom::End(0))?;
+        let count = count.max(1) as u64;
+        let mut ranges = Vec::new();
+        let mut start = 0;
+        for i in 1..=count {
+            if start == len {
+                break;
+            }
+            let end = match i == count {
+                true => len,
+                false => next_line_start(file, (len * i / count).max(start))?,
+            };
+            if end > start {
+                ranges.push(ByteRange { start, end });
+                start = end;
+            }
+        }
+        Ok(ranges)
+    }
+
+    fn len(&self) -> u64 {
+        self.end - self.start
+    }
+
+    fn reader(&self, path: &Path) -> io::Result<impl Read> {
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(self.start))?;
+        Ok(BufReader::new(file).take(self.len()))
+    }
+}
+
+/// Where the line following the given offset starts, or the end of the file.
+fn next_line_start(file: &mut (impl Read + Seek), offset: u64) -> io::Result<u64> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut rest_of_line = Vec::new();
+    BufReader::new(file).read_until(b'\n', &mut rest_of_line)?;
+    Ok(offset + rest_of_line.len() as u64)
+}
+
+/// Processes a single file by splitting it into [`ByteRange`]s, parsed at
+/// the same time, e.g. for a file too large for a single parser to keep up
+/// with.
+/// The transactions are processed by a worker per group of clients, each
+/// taking those of the first range, then those of the second one and so on,
+/// so that the transactions of a client are processed in the order of the
+/// file. A parser runs ahead of the workers by a bounded number of batches.
+pub struct ByteRangeStreamProcessor {
+    consumer: Arc<dyn TransactionProcessor + Send + Sync>,
+    error_handler: Arc<dyn ErrorHandler + Send + Sync>,
+    csv_options: CsvOptions,
+    ranges: usize,
+    workers: usize,
+    quarantine: Option<Quarantine>,
+    client_quarantine: Option<Quarantine>,
+    amount_warnings: Option<AmountWarnings>,
+    shard: Option<Shard>,
+    cancellation: Cancellation,
+    rate_limiter: Option<RateLimiter>,
+    errors: Option<broadcast::Sender<TransactionProcessorError>>,
+}
+
+type Batch = Result<Vec<Transaction>, TransactionStreamProcessError>;
+
+impl ByteRangeStreamProcessor {
+    pub fn with_options(
+        consumer: Arc<dyn TransactionProcessor + Send + Sync>,
+        csv_options: CsvOptions,
+        ranges: usize,
+    ) -> Self {
+        Self {
+            consumer,
+            error_handler: Arc::new(SimpleErrorHandler),
+            csv_options,
+            ranges,
+            workers: thread::available_parallelism().map_or(1, |workers| workers.get()),
+            quarantine: None,
+            client_quarantine: None,
+            amount_warnings: None,
+            shard: None,
+            cancellation: Cancellation::default(),
+            rate_limiter: None,
+            errors: None,
+        }
+    }
+
+    /// The transactions are processed by that many workers, rather than one
+    /// per CPU.
+    pub fn with_workers(self, workers: usize) -> Self {
+        Self {
+            workers: workers.max(1),
+            ..self
+        }
+    }
+
+    /// The records of a type the engine does not know of are put in the
+    /// quarantine rather than failing the processing.
+    pub fn with_quarantine(self, quarantine: Quarantine) -> Self {
+        Self {
+            quarantine: Some(quarantine),
+            ..self
+        }
+    }
+
+    /// The records whose client id is out of range are put in the quarantine
+    /// rather than failing the processing.
+    pub fn with_client_quarantine(self, quarantine: Quarantine) -> Self {
+        Self {
+            client_quarantine: Some(quarantine),
+            ..self
+        }
+    }
+
+    /// The amounts ignored as the types of their records take none, see
+    /// [`CsvOptions::amount_strictness`], are recorded in the warnings.
+    pub fn with_amount_warnings(self, amount_warnings: AmountWarnings) -> Self {
+        Self {
+            amount_warnings: Some(amount_warnings),
+            ..self
+        }
+    }
+
+    /// Only the records of the clients of the shard are processed.
+    pub fn with_shard(self, shard: Shard) -> Self {
+        Self {
+            shard: Some(shard),
+            ..self
+        }
+    }
+
+    /// The file stops being read once the cancellation is cancelled, the
+    /// processing then failing with [`TransactionStreamProcessError::Cancelled`].
+    pub fn with_cancellation(self, cancellation: Cancellation) -> Self {
+        Self {
+            cancellation,
+            ..self
+        }
+    }
+
+    /// The ranges are read no faster than the rate limiter lets them, all of
+    /// them together.
+    pub fn with_rate_limiter(self, rate_limiter: RateLimiter) -> Self {
+        Self {
+            rate_limiter: Some(rate_limiter),
+            ..self
+        }
+    }
+
+    /// Every error of the processor is sent as soon as it occurs, whether it
+    /// fails the run or not.
+    pub fn with_errors(self, errors: broadcast::Sender<TransactionProcessorError>) -> Self {
+        Self {
+            errors: Some(errors),
+            ..self
+        }
+    }
+
+    pub async fn process_file(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<(), TransactionStreamProcessError> {
+        let path = path.as_ref().to_path_buf();
+        let io_error =
+            |err: io::Error| TransactionStreamProcessError::ParsingError(err.to_string());
+        let ranges = {
+            let path = path.clone();
+            let count = self.ranges;
+            spawn_blocking(move || ByteRange::plan(&mut File::open(path)?, count))
+                .await
+                .map_err(internal_error)?
+                .map_err(io_error)?
+        };
+        let layout =
+            TransactionRecordReader::new(File::open(&path).map_err(io_error)?, &self.csv_options)?
+                .layout()
+                .clone();
+        let line_offsets = self.line_offsets(&path, &ranges).await?;
+
+        // a channel per range and worker, each worker taking its channels in
+        // the order of the ranges
+        let mut senders = Vec::with_capacity(ranges.len());
+        let mut receivers: Vec<Vec<mpsc::Receiver<Batch>>> = (0..self.workers)
+            .map(|_| Vec::with_capacity(ranges.len()))
+            .collect();
+        for _ in &ranges {
+            let (range_senders, range_receivers): (Vec<_>, Vec<_>) = (0..self.workers)
+                .map(|_| mpsc::channel(BATCHES_IN_FLIGHT))
+                .unzip();
+            senders.push(range_senders);
+            for (worker, receiver) in range_receivers.into_iter().enumerate() {
+                receivers[worker].push(receiver);
+            }
+        }
+        let workers: Vec<JoinHandle<Result<(), TransactionStreamProcessError>>> = receivers
+            .into_iter()
+            .map(|receivers| tokio::spawn(self.work(receivers)))
+            .collect();
+        let parsers: Vec<JoinHandle<()>> = ranges
+            .into_iter()
+            .zip(line_offsets)
+            .zip(senders)
+            .enumerate()
+            .map(|(index, ((range, line_offset), senders))| {
+                let parser = RangeParser {
+                    path: path.clone(),
+                    range,
+                    first: index == 0,
+                    line_offset,
+                    csv_options: self.csv_options.clone(),
+                    layout: layout.clone(),
+                    quarantine: self.quarantine.clone(),
+                    client_quarantine: self.client_quarantine.clone(),
+                    amount_warnings: self.amount_warnings.clone(),
+                    shard: self.shard,
+                    cancellation: self.cancellation.clone(),
+                    rate_limiter: self.rate_limiter.clone(),
+                };
+                spawn_blocking(move || parser.parse(senders))
+            })
+            .collect();
+
+        let mut result = Ok(());
+        for worker in workers {
+            let worker_result = worker
+                .await
+                .map_err(internal_error)
+                .and_then(|result| result);
+            result = result.and(worker_result);
+        }
+        for parser in parsers {
+            parser.await.map_err(internal_error)?;
+        }
+        result
+    }
+
+    /// The lines of the file before each range, counted by a task per range.
+    async fn line_offsets(
+        &self,
+        path: &Path,
+        ranges: &[ByteRange],
+    ) -> Result<Vec<u64>, TransactionStreamProcessError> {
+        let counting: Vec<JoinHandle<io::Result<u64>>> = ranges
+            .iter()
+            .map(|range| {
+                let path = path.to_path_buf();
+                let range = *range;
+                spawn_blocking(move || {
+                    let mut lines = 0;
+                    let mut reader = BufReader::new(range.reader(&path)?);
+                    loop {
+                        let buffer = reader.fill_buf()?;
+                        if buffer.is_empty() {
+                            return Ok(lines);
+                        }
+                        lines += buffer.iter().filter(|byte| **byte == b'\n').count() as u64;
+                        let len = buffer.len();
+                        reader.consume(len);
+                    }
+                })
+            })
+            .collect();
+        let mut line_offsets = Vec::with_capacity(ranges.len());
+        let mut lines = 0;
+        for count in counting {
+            line_offsets.push(lines);
+            lines += count
+                .await
+                .map_err(internal_error)?
+                .map_err(|err| TransactionStreamProcessError::ParsingError(err.to_string()))?;
+        }
+        Ok(line_offsets)
+    }
+
+    /// Processes the batches of each range in turn, stopping at the first
+    /// error of the parsers or of the processor that is not to be let
+    /// through.
+    fn work(
+        &self,
+        receivers: Vec<mpsc::Receiver<Batch>>,
+    ) -> impl Future<Output = Result<(), TransactionStreamProcessError>> + Send + 'static {
+        let consumer = self.consumer.clone();
+        let error_handler = self.error_handler.clone();
+        let errors = self.errors.clone();
+        async move {
+            for mut receiver in receivers {
+                while let Some(batch) = receiver.recv().await {
+                    for transaction in batch? {
+                        if let Err(err) = consumer.process(transaction).await {
+                            if let Some(errors) = &errors {
+                                // only fails when no one is listening
+                                let _ = errors.send(err.clone());
+                            }
+                            error_handler
+                                .handle(err)
+                                .map_err(TransactionStreamProcessError::ProcessError)?;
+                        }
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Parses a range, handing its transactions over to the workers of their
+/// clients.
+struct RangeParser {
+    path: PathBuf,
+    range: ByteRange,
+    /// Whether the range starts the file, and so holds its headers, if any.
+    first: bool,
+    line_offset: u64,
+    csv_options: CsvOptions,
+    layout: Layout,
+    quarantine: Option<Quarantine>,
+    client_quarantine: Option<Quarantine>,
+    amount_warnings: Option<AmountWarnings>,
+    shard: Option<Shard>,
+    cancellation: Cancellation,
+    rate_limiter: Option<RateLimiter>,
+}
+
+impl RangeParser {
+    /// Stops at the first record that cannot be read, the workers being told
+    /// so, or as soon as the workers stop taking the batches.
+    fn parse(self, senders: Vec<mpsc::Sender<Batch>>) {
+        let mut batches: Vec<Vec<Transaction>> = vec![Vec::new(); senders.len()];
+        let failure = match self.read(&senders, &mut batches) {
+            Ok(()) => None,
+            Err(Stopped::Failed(err)) => Some(err),
+            Err(Stopped::WorkerGone) => return,
+        };
+        for (sender, batch) in senders.iter().zip(batches) {
+            // the worker may have stopped already
+            let _ = sender.blocking_send(Ok(batch));
+            if let Some(err) = &failure {
+                let _ = sender.blocking_send(Err(err.clone()));
+            }
+        }
+    }
+
+    fn read(
+        &self,
+        senders: &[mpsc::Sender<Batch>],
+        batches: &mut [Vec<Transaction>],
+    ) -> Result<(), Stopped> {
+        let r = self.range.reader(&self.path).map_err(|err| {
+            Stopped::Failed(TransactionStreamProcessError::ParsingError(err.to_string()))
+        })?;
+        let reader = match self.first {
+            true => TransactionRecordReader::new(r, &self.csv_options).map_err(Stopped::Failed)?,
+            false => TransactionRecordReader::resuming(
+                r,
+                &self.csv_options,
+                self.layout.clone(),
+                self.line_offset,
+            ),
+        };
+        for result in reader
+            .with_quarantine(self.quarantine.clone())
+            .with_client_quarantine(self.client_quarantine.clone())
+            .with_amount_warnings(self.amount_warnings.clone())
+            .with_shard(self.shard)
+            .transactions()
+        {
+            if self.cancellation.is_cancelled() {
+                return Err(Stopped::Failed(TransactionStreamProcessError::Cancelled));
+            }
+            if let Some(rate_limiter) = &self.rate_limiter {
+                rate_limiter.admit_blocking();
+            }
+            let transaction = result.map_err(Stopped::Failed)?;
+            let worker = usize::from(transaction.client_id) % senders.len();
+            batches[worker].push(transaction);
+            if batches[worker].len() == BATCH_LEN {
+                let batch = mem::take(&mut batches[worker]);
+                senders[worker]
+                    .blocking_send(Ok(batch))
+                    .map_err(|_| Stopped::WorkerGone)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+enum Stopped {
+    Failed(TransactionStreamProcessError),
+    WorkerGone,
+}
+
+fn internal_error(err: impl ToString) -> TransactionStreamProcessError {
+    TransactionStreamProcessError::InternalError(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use rstest::rstest;
+
+    use super::ByteRange;
+
+    #[rstest]
+    #[case(1, vec![(0, 23)])]
+    #[case(2, vec![(0, 16), (16, 23)])]
+    #[case(3, vec![(0, 8), (8, 16), (16, 23)])]
+    #[case(30, vec![(0, 8), (8, 16), (16, 23)])]
+    fn ranges_end_with_a_line_break(#[case] count: usize, #[case] expected: Vec<(u64, u64)>) {
+        // three lines of 8 bytes, the last one without its line break
+        let mut file = Cursor::new("a,1,2,3\nb,4,5,6\nc,7,8,9");
+        assert_eq!(
+            ByteRange::plan(&mut file, count).unwrap(),
+            expected
+                .into_iter()
+                .map(|(start, end)| ByteRange { start, end })
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn an_empty_file_has_no_ranges() {
+        assert_eq!(ByteRange::plan(&mut Cursor::new(""), 4).unwrap(), vec![]);
+    }
+}
@@ -0,0 +1,157 @@
+use std::{
+    io::{self, Read},
+    mem,
+};
+
+/// How many bytes are read from the input at a time.
+const READ_LEN: u64 = 16 * 1024;
+
+/// A part of the input made of whole records.
+#[derive(Debug, PartialEq)]
+pub(super) struct Chunk {
+    pub(super) bytes: Vec<u8>,
+    /// The lines of the input before the chunk.
+    pub(super) line_offset: u64,
+}
+
+/// Splits a CSV input into [`Chunk`]s of whole records, so that they can be
+/// parsed apart from one another.
+/// The records are told apart by the line breaks outside of quotes, without
+/// parsing them.
+pub(super) struct Chunker<R> {
+    r: R,
+    buffer: Vec<u8>,
+    /// How much of the buffer has been looked through for a line break.
+    scanned: usize,
+    in_quotes: bool,
+    lines: u64,
+}
+
+impl<R: Read> Chunker<R> {
+    pub(super) fn new(r: R) -> Self {
+        Self {
+            r,
+            buffer: Vec::new(),
+            scanned: 0,
+            in_quotes: false,
+            lines: 0,
+        }
+    }
+
+    /// The first record that is not an empty line, e.g. the headers.
+    pub(super) fn first_record(&mut self) -> io::Result<Option<Chunk>> {
+        while let Some(chunk) = self.next_chunk(0)? {
+            if chunk
+                .bytes
+                .iter()
+                .any(|byte| !matches!(byte, b'\r' | b'\n'))
+            {
+                return Ok(Some(chunk));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The records up to the first line break past `len` bytes, or the rest
+    /// of the input if there is none.
+    pub(super) fn next_chunk(&mut self, len: usize) -> io::Result<Option<Chunk>> {
+        loop {
+            while self.scanned < self.buffer.len() {
+                let byte = self.buffer[self.scanned];
+                self.scanned += 1;
+                match byte {
+                    // an escaped quote being two of them, it leaves the state
+                    // as it was
+                    b'"' => self.in_quotes = !self.in_quotes,
+                    b'\n' if !self.in_quotes && self.scanned > len => {
+                        let rest = self.buffer.split_off(self.scanned);
+                        self.scanned = 0;
+                        let bytes = mem::replace(&mut self.buffer, rest);
+                        return Ok(Some(self.chunk(bytes)));
+                    }
+                    _ => {}
+                }
+            }
+            if (&mut self.r).take(READ_LEN).read_to_end(&mut self.buffer)? == 0 {
+                self.scanned = 0;
+                let bytes = mem::take(&mut self.buffer);
+                return Ok((!bytes.is_empty()).then(|| self.chunk(bytes)));
+            }
+        }
+    }
+
+    fn chunk(&mut self, bytes: Vec<u8>) -> Chunk {
+        let line_offset = self.lines;
+        self.lines += bytes.iter().filter(|byte| **byte == b'\n').count() as u64;
+        Chunk { bytes, line_offset }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::{Chunk, Chunker};
+
+    #[rstest]
+    #[case(0, &["a,1\n", "b,2\n", "c,3"])]
+    #[case(4, &["a,1\nb,2\n", "c,3"])]
+    #[case(100, &["a,1\nb,2\nc,3"])]
+    fn chunks_end_at_the_first_line_break_past_their_length(
+        #[case] len: usize,
+        #[case] expected: &[&str],
+    ) {
+        let mut chunker = Chunker::new("a,1\nb,2\nc,3".as_bytes());
+        let mut chunks = Vec::new();
+        while let Some(chunk) = chunker.next_chunk(len).unwrap() {
+            chunks.push(String::from_utf8(chunk.bytes).unwrap());
+        }
+        assert_eq!(chunks, expected);
+    }
+
+    #[test]
+    fn line_breaks_in_quotes_do_not_end_a_chunk() {
+        let mut chunker = Chunker::new("a,\"x\ny\"\nb,\"\"\"\"\nc,3\n".as_bytes());
+        assert_eq!(
+            chunker.next_chunk(0).unwrap(),
+            Some(Chunk {
+                bytes: b"a,\"x\ny\"\n".to_vec(),
+                line_offset: 0,
+            })
+        );
+        assert_eq!(
+            chunker.next_chunk(0).unwrap(),
+            Some(Chunk {
+                bytes: b"b,\"\"\"\"\n".to_vec(),
+                line_offset: 2,
+            })
+        );
+        assert_eq!(
+            chunker.next_chunk(0).unwrap(),
+            Some(Chunk {
+                bytes: b"c,3\n".to_vec(),
+                line_offset: 3,
+            })
+        );
+        assert_eq!(chunker.next_chunk(0).unwrap(), None);
+    }
+
+    #[test]
+    fn first_record_skips_empty_lines() {
+        let mut chunker = Chunker::new("\n\r\n  type, client\ndeposit, 1\n".as_bytes());
+        assert_eq!(
+            chunker.first_record().unwrap(),
+            Some(Chunk {
+                bytes: b"  type, client\n".to_vec(),
+                line_offset: 2,
+            })
+        );
+        assert_eq!(
+            chunker.next_chunk(100).unwrap(),
+            Some(Chunk {
+                bytes: b"deposit, 1\n".to_vec(),
+                line_offset: 3,
+            })
+        );
+    }
+}
@@ -0,0 +1,764 @@
+use std::{collections::HashMap, io::Read, iter};
+
+use csv::{ReaderBuilder, StringRecord, Trim};
+use serde::{de::IntoDeserializer, Deserialize};
+
+use crate::model::{ClientId, Transaction};
+
+use super::{
+    amount_rules::AmountCheck, header_validator::validate_headers,
+    transaction_record_converter::RecordFields, AmountProfile, AmountStrictness, AmountWarnings,
+    Quarantine, Shard, TransactionRecord, TransactionRecordType, TransactionRecordV1,
+    TransactionRecordV2, TransactionStreamProcessError,
+};
+
+/// The columns of a [`TransactionRecordV1`], in the order they are expected
+/// when the input has no headers.
+pub(super) const V1_COLUMNS: [&str; 4] = ["type", "client", "tx", "amount"];
+
+/// The columns of a [`TransactionRecordV2`].
+pub(super) const V2_COLUMNS: [&str; 7] = [
+    "type",
+    "client",
+    "tx",
+    "amount",
+    "timestamp",
+    "currency",
+    "memo",
+];
+
+/// The columns a [`TransactionRecordV2`] may go without.
+pub(super) const V2_OPTIONAL_COLUMNS: [&str; 1] = ["metadata"];
+
+/// How the CSV input is laid out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvOptions {
+    /// The field delimiter, `,` by default.
+    pub delimiter: u8,
+
+    /// Whether the first row holds the column names.
+    /// The format of the input is told by its headers: any of the `timestamp`,
+    /// `currency` or `memo` columns makes it a [`TransactionRecordV2`].
+    /// Without headers, the columns are expected to be `type`, `client`, `tx`
+    /// and `amount`, in that order.
+    pub has_headers: bool,
+
+    /// Maps the column names found in the input to the ones expected, e.g.
+    /// `txn_type` -> `type`. Columns not in the map are taken as they are.
+    pub column_map: HashMap<String, String>,
+
+    /// Whether a record with an amount its type does not take, e.g. a
+    /// dispute, fails to be read rather than its amount being ignored, see
+    /// [`TransactionRecordType::amount_rule`].
+    pub amount_strictness: AmountStrictness,
+
+    /// How the amounts are written, e.g. `1,50` rather than `1.50`.
+    pub amount_profile: AmountProfile,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            has_headers: true,
+            column_map: HashMap::new(),
+            amount_strictness: AmountStrictness::default(),
+            amount_profile: AmountProfile::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Version {
+    V1,
+    V2,
+}
+
+impl Version {
+    fn of(headers: &StringRecord) -> Self {
+        let v2_only = &V2_COLUMNS[V1_COLUMNS.len()..];
+        match headers.iter().any(|header| v2_only.contains(&header)) {
+            true => Self::V2,
+            false => Self::V1,
+        }
+    }
+
+    fn columns(self) -> &'static [&'static str] {
+        match self {
+            Self::V1 => &V1_COLUMNS,
+            Self::V2 => &V2_COLUMNS,
+        }
+    }
+
+    fn optional_columns(self) -> &'static [&'static str] {
+        match self {
+            Self::V1 => &[],
+            Self::V2 => &V2_OPTIONAL_COLUMNS,
+        }
+    }
+}
+
+/// The columns of an input, as told by its headers if it has any.
+#[derive(Debug, Clone)]
+pub(super) struct Layout {
+    version: Version,
+    headers: StringRecord,
+    /// The headers as they are in the input, if it has any.
+    raw_headers: Option<StringRecord>,
+}
+
+/// Reads [`TransactionRecord`]s out of a CSV input laid out as per the
+/// [`CsvOptions`].
+pub(super) struct TransactionRecordReader<R> {
+    rdr: csv::Reader<R>,
+    layout: Layout,
+    record: StringRecord,
+    /// The lines of the input before the ones read, if it is read from
+    /// somewhere other than its start.
+    line_offset: u64,
+    /// The number of fields each record must have, if the reader does not
+    /// check it itself from the headers.
+    field_count: Option<usize>,
+    quarantine: Option<Quarantine>,
+    client_quarantine: Option<Quarantine>,
+    shard: Option<Shard>,
+    amount_check: AmountCheck,
+    amount_profile: AmountProfile,
+}
+
+impl<R: Read> TransactionRecordReader<R> {
+    pub(super) fn new(r: R, options: &CsvOptions) -> Result<Self, TransactionStreamProcessError> {
+        let mut rdr = csv_reader(r, options);
+        let mut record = StringRecord::new();
+        let mut raw_headers = None;
+        let headers = match options.has_headers {
+            true => match rdr.read_record(&mut record) {
+                Ok(true) => {
+                    raw_headers = Some(record.clone());
+                    let headers: StringRecord = record
+                        .iter()
+                        .map(|column| match options.column_map.get(column) {
+                            Some(mapped) => mapped.as_str(),
+                            None => column,
+                        })
+                        .collect();
+                    let version = Version::of(&headers);
+                    validate_headers(&headers, version.columns(), version.optional_columns())?;
+                    headers
+                }
+                Ok(false) => StringRecord::from(V1_COLUMNS.to_vec()),
+                Err(err) => {
+                    return Err(TransactionStreamProcessError::ParsingError(err.to_string()))
+                }
+            },
+            false => StringRecord::from(V1_COLUMNS.to_vec()),
+        };
+        Ok(Self {
+            rdr,
+            layout: Layout {
+                version: Version::of(&headers),
+                headers,
+                raw_headers,
+            },
+            record,
+            line_offset: 0,
+            field_count: None,
+            quarantine: None,
+            client_quarantine: None,
+            shard: None,
+            amount_check: AmountCheck::new(options.amount_strictness),
+            amount_profile: options.amount_profile.clone(),
+        })
+    }
+
+    /// Reads a part of an input whose headers, if any, were read by another
+    /// reader, starting after the given number of lines of the input.
+    pub(super) fn resuming(r: R, options: &CsvOptions, layout: Layout, line_offset: u64) -> Self {
+        let options = CsvOptions {
+            has_headers: false,
+            ..options.clone()
+        };
+        Self {
+            rdr: csv_reader(r, &options),
+            field_count: Some(layout.headers.len()),
+            layout,
+            record: StringRecord::new(),
+            line_offset,
+            quarantine: None,
+            client_quarantine: None,
+            shard: None,
+            amount_check: AmountCheck::new(options.amount_strictness),
+            amount_profile: options.amount_profile.clone(),
+        }
+    }
+
+    pub(super) fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    /// Only the records of the clients of the shard, if any, are read, the
+    /// others being skipped before they are deserialised.
+    pub(super) fn with_shard(self, shard: Option<Shard>) -> Self {
+        Self { shard, ..self }
+    }
+
+    /// The client of the record just read, if it can be told.
+    fn client_id(&self) -> Option<ClientId> {
+        self.client_field()?.parse().ok()
+    }
+
+    /// The client of the record just read as it is written, if it has one.
+    fn client_field(&self) -> Option<&str> {
+        let column = self
+            .layout
+            .headers
+            .iter()
+            .position(|header| header == "client")?;
+        self.record.get(column)
+    }
+
+    /// The client of the record just read, if it is a number above
+    /// [`ClientId::MAX`].
+    fn out_of_range_client(&self) -> Option<&str> {
+        let client = self.client_field()?;
+        (client.parse::<ClientId>().is_err() && client.parse::<u64>().is_ok()).then_some(client)
+    }
+
+    /// The records of an unknown type are put in the quarantine, if any,
+    /// rather than failing to be read.
+    pub(super) fn with_quarantine(self, quarantine: Option<Quarantine>) -> Self {
+        Self { quarantine, ..self }
+    }
+
+    /// The amounts ignored as the types of their records take none are
+    /// recorded in the warnings, if any.
+    pub(super) fn with_amount_warnings(self, warnings: Option<AmountWarnings>) -> Self {
+        Self {
+            amount_check: self.amount_check.with_warnings(warnings),
+            ..self
+        }
+    }
+
+    /// The records whose client id is above [`ClientId::MAX`] are put in
+    /// the quarantine, if any, rather than failing to be read, with an error
+    /// naming their line.
+    pub(super) fn with_client_quarantine(self, client_quarantine: Option<Quarantine>) -> Self {
+        Self {
+            client_quarantine,
+            ..self
+        }
+    }
+
+    /// The transactions, each with the line of the input it starts at as its
+    /// ingest sequence.
+    /// Unlike the [`TransactionRecord`]s, they are deserialised straight out
+    /// of the row, borrowing its strings, so that reading one allocates
+    /// nothing but its memo, if any.
+    pub(super) fn transactions(
+        mut self,
+    ) -> impl Iterator<Item = Result<Transaction, TransactionStreamProcessError>> {
+        iter::from_fn(move || {
+            if let Err(err) = self.advance()? {
+                return Some(Err(err));
+            }
+            let line = self.line();
+            Some(
+                self.record
+                    .deserialize::<RecordFields>(Some(&self.layout.headers))
+                    .map_err(|err| TransactionStreamProcessError::ParsingError(err.to_string()))
+                    .and_then(|fields| {
+                        fields.into_transaction(
+                            &self.amount_check,
+                            &self.amount_profile,
+                            Some(line),
+                        )
+                    })
+                    .map(|transaction| Transaction {
+                        ingest_sequence: Some(line),
+                        ..transaction
+                    }),
+            )
+        })
+    }
+
+    /// Reads the next row to be deserialised, skipping those of the other
+    /// shards and putting those of an unknown type or of an out of range
+    /// client in the quarantine.
+    fn advance(&mut self) -> Option<Result<(), TransactionStreamProcessError>> {
+        loop {
+            match self.rdr.read_record(&mut self.record) {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(err) => {
+                    return Some(Err(TransactionStreamProcessError::ParsingError(
+                        err.to_string(),
+                    )))
+                }
+            }
+            if let Some(field_count) = self.field_count {
+                if self.record.len() != field_count {
+                    return Some(Err(TransactionStreamProcessError::ParsingError(format!(
+                        "The record on line {} has {} fields, {field_count} were expected.",
+                        self.line(),
+                        self.record.len()
+                    ))));
+                }
+            }
+            if let Some(client) = self.out_of_range_client() {
+                match &self.client_quarantine {
+                    Some(quarantine) => {
+                        quarantine.add_out_of_range_client(
+                            self.layout.raw_headers.as_ref(),
+                            &self.record,
+                        );
+                        continue;
+                    }
+                    None => {
+                        return Some(Err(TransactionStreamProcessError::ParsingError(format!(
+                            "The client id {client} on line {} is above the largest one, {}.",
+                            self.line(),
+                            ClientId::MAX
+                        ))))
+                    }
+                }
+            }
+            if let Some(shard) = self.shard {
+                if self
+                    .client_id()
+                    .is_some_and(|client_id| !shard.contains(client_id))
+                {
+                    continue;
+                }
+            }
+            if let (Some(quarantine), Some(txn_type)) = (&self.quarantine, self.unknown_type()) {
+                quarantine.add(self.layout.raw_headers.as_ref(), &self.record, txn_type);
+                continue;
+            }
+            return Some(Ok(()));
+        }
+    }
+
+    /// The line of the input the record just read starts at.
+    fn line(&self) -> u64 {
+        self.line_offset + self.record.position().map_or(0, |position| position.line())
+    }
+
+    /// The type of the record just read, if it is not one of the
+    /// [`TransactionRecordType`]s.
+    fn unknown_type(&self) -> Option<&str> {
+        let column = self
+            .layout
+            .headers
+            .iter()
+            .position(|header| header == "type")?;
+        let txn_type = self.record.get(column)?;
+        let known: Result<TransactionRecordType, serde::de::value::Error> =
+            TransactionRecordType::deserialize(txn_type.into_deserializer());
+        known.is_err().then_some(txn_type)
+    }
+}
+
+fn csv_reader<R: Read>(r: R, options: &CsvOptions) -> csv::Reader<R> {
+    ReaderBuilder::new()
+        .trim(Trim::All)
+        .delimiter(options.delimiter)
+        .has_headers(false)
+        .from_reader(r)
+}
+
+impl<R: Read> Iterator for TransactionRecordReader<R> {
+    type Item = Result<TransactionRecord, TransactionStreamProcessError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Err(err) = self.advance()? {
+            return Some(Err(err));
+        }
+        Some(
+            match self.layout.version {
+                Version::V1 => self
+                    .record
+                    .deserialize::<TransactionRecordV1>(Some(&self.layout.headers))
+                    .map(TransactionRecord::from),
+                Version::V2 => self
+                    .record
+                    .deserialize::<TransactionRecordV2>(Some(&self.layout.headers))
+                    .map(TransactionRecord::from),
+            }
+            .map_err(|err| TransactionStreamProcessError::ParsingError(err.to_string())),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use assert_matches::assert_matches;
+    use rstest::rstest;
+
+    use crate::{
+        model::{Amount, Transaction, TransactionKind},
+        transaction_stream_processor::{
+            transaction_record_converter::to_transaction,
+            AmountProfile, AmountStrictness, AmountWarning, AmountWarnings, Quarantine,
+            TransactionRecord,
+            TransactionRecordType::{self, Deposit, Dispute},
+            TransactionRecordV1, TransactionRecordV2, TransactionStreamProcessError,
+        },
+    };
+
+    use super::{CsvOptions, TransactionRecordReader};
+
+    #[rstest]
+    #[case::default_options(
+        "
+    type,    client, tx, amount
+    deposit,      1,  2,    3.0
+    dispute,      1,  2,",
+        CsvOptions::default()
+    )]
+    #[case::columns_in_a_different_order(
+        "
+    client, amount, tx, type
+         1,    3.0,  2, deposit
+         1,       ,  2, dispute",
+        CsvOptions::default()
+    )]
+    #[case::semicolon_delimited_with_mapped_headers(
+        "
+    txn_type; client_id; txn_id; value
+    deposit;          1;      2;   3.0
+    dispute;          1;      2;",
+        partner_options()
+    )]
+    #[case::no_headers(
+        "
+    deposit, 1, 2, 3.0
+    dispute, 1, 2,",
+        no_headers(b',')
+    )]
+    #[case::semicolon_delimited_without_headers(
+        "
+    deposit; 1; 2; 3.0
+    dispute; 1; 2;",
+        no_headers(b';')
+    )]
+    fn reads_records_as_per_the_options(#[case] input: &str, #[case] options: CsvOptions) {
+        let records: Vec<TransactionRecord> =
+            TransactionRecordReader::new(input.as_bytes(), &options)
+                .unwrap()
+                .map(|record| record.unwrap())
+                .collect();
+        assert_eq!(
+            records,
+            vec![
+                record(Deposit, 1, 2, Some("3.0")),
+                record(Dispute, 1, 2, None)
+            ]
+        );
+    }
+
+    #[test]
+    fn v2_is_detected_from_the_headers() {
+        let input = "
+    type,    client, tx, amount,  timestamp, currency, memo
+    deposit,      1,  2,    3.0, 1700000000,      EUR, invoice 42
+    dispute,      1,  2,       ,           ,         ,";
+        let records: Vec<TransactionRecord> =
+            TransactionRecordReader::new(input.as_bytes(), &CsvOptions::default())
+                .unwrap()
+                .map(|record| record.unwrap())
+                .collect();
+        assert_eq!(
+            records,
+            vec![
+                TransactionRecord::V2(TransactionRecordV2 {
+                    txn_type: Deposit,
+                    client_id: 1,
+                    transaction_id: 2,
+                    optional_amount: Some("3.0".to_string()),
+                    optional_timestamp: Some(1_700_000_000),
+                    optional_currency: Some("EUR".to_string()),
+                    optional_memo: Some("invoice 42".to_string()),
+                    optional_metadata: None,
+                }),
+                TransactionRecord::V2(TransactionRecordV2 {
+                    txn_type: Dispute,
+                    client_id: 1,
+                    transaction_id: 2,
+                    optional_amount: None,
+                    optional_timestamp: None,
+                    optional_currency: None,
+                    optional_memo: None,
+                    optional_metadata: None,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn partial_v2_headers_are_rejected() {
+        let input = "
+    type,    client, tx, amount, memo
+    deposit,      1,  2,    3.0, invoice 42";
+        assert_matches!(
+            TransactionRecordReader::new(input.as_bytes(), &CsvOptions::default()).err(),
+            Some(TransactionStreamProcessError::InvalidHeaders(message))
+                if message == "missing column(s): `timestamp`, `currency`"
+        );
+    }
+
+    #[test]
+    fn unmapped_headers_are_rejected_before_reading_any_record() {
+        let input = "
+    txn_type; client_id; txn_id; value
+    deposit;          1;      2;   3.0";
+        let options = CsvOptions {
+            delimiter: b';',
+            ..Default::default()
+        };
+        assert_matches!(
+            TransactionRecordReader::new(input.as_bytes(), &options).err(),
+            Some(TransactionStreamProcessError::InvalidHeaders(_))
+        );
+    }
+
+    #[test]
+    fn misnamed_header_is_reported_with_a_suggestion() {
+        let input = "
+    type,    clinet, tx, amount
+    deposit,      1,  2,    3.0";
+        assert_matches!(
+            TransactionRecordReader::new(input.as_bytes(), &CsvOptions::default()).err(),
+            Some(TransactionStreamProcessError::InvalidHeaders(message))
+                if message.contains("`clinet` looks like `client`")
+        );
+    }
+
+    #[rstest]
+    #[case::quarantined(Some(Quarantine::default()), 1)]
+    #[case::not_quarantined(None, 0)]
+    fn unknown_types_are_quarantined_if_asked(
+        #[case] quarantine: Option<Quarantine>,
+        #[case] expected_quarantined: usize,
+    ) {
+        let input = "
+    txn_type; client_id; txn_id; value
+    transfer;         1;      2;   3.0
+    dispute;          1;      2;";
+        let results: Vec<Result<TransactionRecord, TransactionStreamProcessError>> =
+            TransactionRecordReader::new(input.as_bytes(), &partner_options())
+                .unwrap()
+                .with_quarantine(quarantine.clone())
+                .collect();
+        assert_eq!(results.len(), 2 - expected_quarantined);
+        assert_eq!(results.last(), Some(&Ok(record(Dispute, 1, 2, None))));
+        if let Some(quarantine) = quarantine {
+            assert_eq!(
+                String::from_utf8(quarantine.write().unwrap()).unwrap(),
+                "txn_type,client_id,txn_id,value\ntransfer,1,2,3.0\n"
+            );
+        }
+    }
+
+    #[rstest]
+    #[case::quarantined(Some(Quarantine::default()))]
+    #[case::not_quarantined(None)]
+    fn out_of_range_clients_are_quarantined_if_asked(#[case] quarantine: Option<Quarantine>) {
+        let input = "
+    type,    client, tx, amount
+    deposit,  70000,  1,    3.0
+    deposit,      1,  2,    3.0";
+        let results: Vec<Result<TransactionRecord, TransactionStreamProcessError>> =
+            TransactionRecordReader::new(input.as_bytes(), &CsvOptions::default())
+                .unwrap()
+                .with_client_quarantine(quarantine.clone())
+                .collect();
+        match quarantine {
+            Some(quarantine) => {
+                assert_eq!(results, vec![Ok(record(Deposit, 1, 2, Some("3.0")))]);
+                assert_eq!(quarantine.out_of_range_clients(), 1);
+                assert_eq!(
+                    String::from_utf8(quarantine.write().unwrap()).unwrap(),
+                    "type,client,tx,amount\ndeposit,70000,1,3.0\n"
+                );
+            }
+            None => assert_matches!(
+                results.first(),
+                Some(Err(TransactionStreamProcessError::ParsingError(message)))
+                    if message == "The client id 70000 on line 3 is above the largest one, 65535."
+            ),
+        }
+    }
+
+    #[rstest]
+    #[case::warned(AmountStrictness::Warn)]
+    #[case::rejected(AmountStrictness::Reject)]
+    fn amounts_a_type_does_not_take_are_warned_about_or_rejected(
+        #[case] amount_strictness: AmountStrictness,
+    ) {
+        let input = "
+    type,    client, tx, amount
+    deposit,      1,  2,    3.0
+    dispute,      1,  2,    3.0";
+        let options = CsvOptions {
+            amount_strictness,
+            ..CsvOptions::default()
+        };
+        let warnings = AmountWarnings::default();
+        let results: Vec<Result<Transaction, TransactionStreamProcessError>> =
+            TransactionRecordReader::new(input.as_bytes(), &options)
+                .unwrap()
+                .with_amount_warnings(Some(warnings.clone()))
+                .transactions()
+                .collect();
+        match amount_strictness {
+            AmountStrictness::Warn => {
+                assert!(results.iter().all(Result::is_ok));
+                assert_eq!(
+                    warnings.warnings(),
+                    vec![AmountWarning {
+                        line: Some(4),
+                        txn_type: "dispute",
+                        amount: "3.0".to_string(),
+                    }]
+                );
+            }
+            AmountStrictness::Reject => {
+                assert_matches!(
+                    results.last(),
+                    Some(Err(TransactionStreamProcessError::ParsingError(message)))
+                        if message == "The dispute on line 4 has an amount, `3.0`, which it does not take."
+                );
+                assert_eq!(warnings.warnings(), vec![]);
+            }
+        }
+    }
+
+    #[test]
+    fn amounts_are_read_as_per_the_profile() {
+        let input = "
+    type;    client; tx; amount
+    deposit;      1;  1; 1.234,5
+    deposit;      1;  2;   1,5e3";
+        let options = CsvOptions {
+            delimiter: b';',
+            amount_profile: AmountProfile {
+                scientific_notation: true,
+                decimal_separator: ',',
+                thousands_separator: Some('.'),
+            },
+            ..CsvOptions::default()
+        };
+        let amounts: Vec<TransactionKind> =
+            TransactionRecordReader::new(input.as_bytes(), &options)
+                .unwrap()
+                .transactions()
+                .map(|transaction| transaction.unwrap().kind)
+                .collect();
+        assert_eq!(
+            amounts,
+            vec![
+                TransactionKind::Deposit {
+                    amount: Amount::new(12_345_000)
+                },
+                TransactionKind::Deposit {
+                    amount: Amount::new(15_000_000)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn a_missing_amount_is_reported_with_its_line() {
+        let input = "
+    type,    client, tx, amount
+    deposit,      1,  2,";
+        assert_matches!(
+            TransactionRecordReader::new(input.as_bytes(), &CsvOptions::default())
+                .unwrap()
+                .transactions()
+                .next(),
+            Some(Err(TransactionStreamProcessError::ParsingError(message)))
+                if message == "The deposit on line 3 has no amount, which it requires."
+        );
+    }
+
+    #[rstest]
+    #[case::v1(
+        "
+    type,    client, tx, amount
+    deposit,      1,  2,    3.0
+    dispute,      1,  2,"
+    )]
+    #[case::v2(
+        r#"
+    type,       client, tx, amount,  timestamp, currency, memo,                   metadata
+    deposit,         1,  2,    3.0, 1700000000,      EUR, "invoice ""42""",      batch=7
+    adjustment,      1,  3,   -0.5,           ,         , goodwill,
+    dispute,         1,  2,       ,           ,         ,                      ,"#
+    )]
+    fn transactions_are_read_as_their_records_would_convert(#[case] input: &str) {
+        let transactions: Vec<Transaction> =
+            TransactionRecordReader::new(input.as_bytes(), &CsvOptions::default())
+                .unwrap()
+                .transactions()
+                .map(|transaction| transaction.unwrap())
+                .collect();
+        let converted: Vec<Transaction> =
+            TransactionRecordReader::new(input.as_bytes(), &CsvOptions::default())
+                .unwrap()
+                .zip(3..)
+                .map(|(record, line)| Transaction {
+                    ingest_sequence: Some(line),
+                    ..to_transaction(record.unwrap()).unwrap()
+                })
+                .collect();
+        assert_eq!(transactions, converted);
+    }
+
+    #[test]
+    fn empty_input_has_no_records() {
+        let mut rdr = TransactionRecordReader::new("".as_bytes(), &CsvOptions::default()).unwrap();
+        assert_matches!(rdr.next(), None);
+    }
+
+    fn no_headers(delimiter: u8) -> CsvOptions {
+        CsvOptions {
+            delimiter,
+            has_headers: false,
+            column_map: HashMap::new(),
+            amount_strictness: AmountStrictness::default(),
+            amount_profile: AmountProfile::default(),
+        }
+    }
+
+    fn partner_options() -> CsvOptions {
+        CsvOptions {
+            delimiter: b';',
+            has_headers: true,
+            column_map: HashMap::from([
+                ("txn_type".to_string(), "type".to_string()),
+                ("client_id".to_string(), "client".to_string()),
+                ("txn_id".to_string(), "tx".to_string()),
+                ("value".to_string(), "amount".to_string()),
+            ]),
+            amount_strictness: AmountStrictness::default(),
+            amount_profile: AmountProfile::default(),
+        }
+    }
+
+    fn record(
+        txn_type: TransactionRecordType,
+        client_id: u16,
+        transaction_id: u32,
+        optional_amount: Option<&str>,
+    ) -> TransactionRecord {
+        TransactionRecord::V1(TransactionRecordV1 {
+            txn_type,
+            client_id,
+            transaction_id,
+            optional_amount: optional_amount.map(|s| s.to_string()),
+        })
+    }
+}
@@ -1,32 +1,55 @@
 use std::io::Read;
 
 use async_trait::async_trait;
-use csv::Trim;
+use tokio::sync::broadcast;
 
-use crate::transaction_processor::TransactionProcessor;
+use crate::transaction_processor::{TransactionProcessor, TransactionProcessorError};
 
 use super::{
-    error_handler::SimpleErrorHandler, transaction_record_converter::to_transaction, ErrorHandler,
+    csv_reader::{CsvOptions, TransactionRecordReader},
+    error_handler::SimpleErrorHandler,
+    AmountWarnings, Cancellation, ErrorHandler, Quarantine, RateLimiter, Shard,
     TransactionStreamProcessError, TransactionStreamProcessor,
 };
 
+/// Processes the transactions one after another, in the order of the input.
 pub struct CsvStreamProcessor {
     consumer: Box<dyn TransactionProcessor + Send + Sync>,
     error_handler: Box<dyn ErrorHandler + Send + Sync>,
+    csv_options: CsvOptions,
+    quarantine: Option<Quarantine>,
+    client_quarantine: Option<Quarantine>,
+    amount_warnings: Option<AmountWarnings>,
+    shard: Option<Shard>,
+    cancellation: Cancellation,
+    rate_limiter: Option<RateLimiter>,
+    errors: Option<broadcast::Sender<TransactionProcessorError>>,
 }
 
 #[async_trait]
 impl TransactionStreamProcessor for CsvStreamProcessor {
     async fn process(&self, r: impl Read + Send) -> Result<(), TransactionStreamProcessError> {
-        let mut rdr = csv::ReaderBuilder::new().trim(Trim::All).from_reader(r);
-        for result in rdr.deserialize() {
-            match result {
-                Ok(it) => match self.consumer.process(to_transaction(it)?).await {
-                    Ok(_) => {}
-                    Err(err) => self.error_handler.handle(err)?,
-                },
+        for result in TransactionRecordReader::new(r, &self.csv_options)?
+            .with_quarantine(self.quarantine.clone())
+            .with_client_quarantine(self.client_quarantine.clone())
+            .with_amount_warnings(self.amount_warnings.clone())
+            .with_shard(self.shard)
+            .transactions()
+        {
+            if self.cancellation.is_cancelled() {
+                return Err(TransactionStreamProcessError::Cancelled);
+            }
+            if let Some(rate_limiter) = &self.rate_limiter {
+                rate_limiter.admit().await;
+            }
+            match self.consumer.process(result?).await {
+                Ok(_) => {}
                 Err(err) => {
-                    return Err(TransactionStreamProcessError::ParsingError(err.to_string()));
+                    if let Some(errors) = &self.errors {
+                        // only fails when no one is listening
+                        let _ = errors.send(err.clone());
+                    }
+                    self.error_handler.handle(err)?
                 }
             };
         }
@@ -35,14 +58,87 @@ impl TransactionStreamProcessor for CsvStreamProcessor {
 }
 
 impl CsvStreamProcessor {
-    // This struct is an early stage of implementation.
-    // It is only used in test code now.
-    #[allow(dead_code)]
     pub fn new(consumer: Box<dyn TransactionProcessor + Send + Sync>) -> Self {
+        Self::with_options(consumer, CsvOptions::default())
+    }
+
+    pub fn with_options(
+        consumer: Box<dyn TransactionProcessor + Send + Sync>,
+        csv_options: CsvOptions,
+    ) -> Self {
         let error_handler = SimpleErrorHandler;
         Self {
             consumer,
             error_handler: Box::new(error_handler),
+            csv_options,
+            quarantine: None,
+            client_quarantine: None,
+            amount_warnings: None,
+            shard: None,
+            cancellation: Cancellation::default(),
+            rate_limiter: None,
+            errors: None,
+        }
+    }
+
+    /// The records of a type the engine does not know of are put in the
+    /// quarantine rather than failing the processing.
+    pub fn with_quarantine(self, quarantine: Quarantine) -> Self {
+        Self {
+            quarantine: Some(quarantine),
+            ..self
+        }
+    }
+
+    /// The records whose client id is out of range are put in the quarantine
+    /// rather than failing the processing.
+    pub fn with_client_quarantine(self, quarantine: Quarantine) -> Self {
+        Self {
+            client_quarantine: Some(quarantine),
+            ..self
+        }
+    }
+
+    /// The amounts ignored as the types of their records take none, see
+    /// [`CsvOptions::amount_strictness`], are recorded in the warnings.
+    pub fn with_amount_warnings(self, amount_warnings: AmountWarnings) -> Self {
+        Self {
+            amount_warnings: Some(amount_warnings),
+            ..self
+        }
+    }
+
+    /// Only the records of the clients of the shard are processed.
+    pub fn with_shard(self, shard: Shard) -> Self {
+        Self {
+            shard: Some(shard),
+            ..self
+        }
+    }
+
+    /// The input stops being read once the cancellation is cancelled, the
+    /// processing then failing with [`TransactionStreamProcessError::Cancelled`].
+    pub fn with_cancellation(self, cancellation: Cancellation) -> Self {
+        Self {
+            cancellation,
+            ..self
+        }
+    }
+
+    /// The input is read no faster than the rate limiter lets it.
+    pub fn with_rate_limiter(self, rate_limiter: RateLimiter) -> Self {
+        Self {
+            rate_limiter: Some(rate_limiter),
+            ..self
+        }
+    }
+
+    /// Every error of the processor is sent as soon as it occurs, whether it
+    /// fails the run or not.
+    pub fn with_errors(self, errors: broadcast::Sender<TransactionProcessorError>) -> Self {
+        Self {
+            errors: Some(errors),
+            ..self
         }
     }
 }
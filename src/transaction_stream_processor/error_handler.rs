@@ -1,6 +1,13 @@
 use crate::{
-    account::account_transactor::AccountTransactorError::{
-        AccountLocked, IncompatibleTransaction, InsufficientFundForWithdrawal, NoTransactionFound,
+    account::{
+        account_transactor::AccountTransactorError::{
+            AccountClosed, Chargeback, ClientNotAllowed, ConflictingAdjustment, Deposit,
+            DepositLimitExceeded, Dispute, DisputeWindowExpired, MinimumBalanceBreached,
+            Representment, Resolve, TooManyOpenDisputes, TransactionLimitExceeded, Withdrawal,
+            WithdrawalLimitExceeded,
+        },
+        BackchargerError, DepositorError, DisputerError, RepresenterError, ResolverError,
+        WithdrawerError,
     },
     transaction_processor::TransactionProcessorError,
 };
@@ -19,11 +26,35 @@ impl ErrorHandler for SimpleErrorHandler {
                 ref _transaction,
                 ref account_transactor_error,
             ) => match account_transactor_error {
-                AccountLocked => Err(transaction_processor_error),
-                IncompatibleTransaction => Err(transaction_processor_error),
-                InsufficientFundForWithdrawal => Ok(()),
-                NoTransactionFound => Ok(()),
+                Deposit(DepositorError::AccountLocked)
+                | Withdrawal(WithdrawerError::AccountLocked)
+                | Dispute(DisputerError::AccountLocked)
+                | Resolve(ResolverError::AccountLocked)
+                | Chargeback(BackchargerError::AccountLocked) => Err(transaction_processor_error),
+                Deposit(DepositorError::ConflictingWithPreviousTransaction)
+                | Withdrawal(WithdrawerError::ConflictingWithPreviousTransaction)
+                | ConflictingAdjustment => Err(transaction_processor_error),
+                Resolve(ResolverError::NonDisputedTransaction(_))
+                | Chargeback(BackchargerError::NonDisputedTransaction(_))
+                | Representment(RepresenterError::NonChargedBackTransaction(_)) => {
+                    Err(transaction_processor_error)
+                }
+                Withdrawal(WithdrawerError::InsufficientFund) => Ok(()),
+                Dispute(DisputerError::NoTransactionFound)
+                | Resolve(ResolverError::NoTransactionFound)
+                | Chargeback(BackchargerError::NoTransactionFound)
+                | Representment(RepresenterError::NoTransactionFound) => Ok(()),
+                AccountClosed => Ok(()),
+                DisputeWindowExpired => Ok(()),
+                TooManyOpenDisputes => Ok(()),
+                DepositLimitExceeded => Ok(()),
+                WithdrawalLimitExceeded => Ok(()),
+                TransactionLimitExceeded => Ok(()),
+                MinimumBalanceBreached => Ok(()),
+                ClientNotAllowed => Err(transaction_processor_error),
             },
+            // still failing once retried, if at all
+            TransactionProcessorError::Unavailable(_, _) => Err(transaction_processor_error),
         }
     }
 }
@@ -33,11 +64,17 @@ mod tests {
     use rstest::rstest;
 
     use crate::{
-        account::account_transactor::AccountTransactorError::{
-            self, AccountLocked, IncompatibleTransaction, InsufficientFundForWithdrawal,
-            NoTransactionFound,
+        account::{
+            account_transactor::AccountTransactorError::{
+                self, AccountClosed, Chargeback, ClientNotAllowed, ConflictingAdjustment, Deposit,
+                DepositLimitExceeded, Dispute, DisputeWindowExpired, MinimumBalanceBreached,
+                Representment, Resolve, TooManyOpenDisputes, TransactionLimitExceeded, Withdrawal,
+                WithdrawalLimitExceeded,
+            },
+            BackchargerError, DepositStatus, DepositorError, DisputerError, Incompatibility,
+            RepresenterError, ResolverError, WithdrawerError,
         },
-        model::{Amount4DecimalBased, Transaction},
+        model::{Amount, Transaction, TransactionKind},
         transaction_processor::TransactionProcessorError,
         transaction_stream_processor::ErrorHandler,
     };
@@ -45,46 +82,93 @@ mod tests {
     use super::SimpleErrorHandler;
 
     #[rstest]
-    #[case(account_lock(), Err(account_lock()))]
-    #[case(incompatible(), Err(incompatible()))]
-    #[case(insufficient_fund(),    Ok(()))]
-    #[case(no_transaction_found(), Ok(()))]
-    fn simple_error_handler_works(
-        #[case] error: TransactionProcessorError,
-        #[case] after_handling: Result<(), TransactionProcessorError>,
-    ) {
+    #[case(Deposit(DepositorError::AccountLocked))]
+    #[case(Withdrawal(WithdrawerError::AccountLocked))]
+    #[case(Dispute(DisputerError::AccountLocked))]
+    #[case(Resolve(ResolverError::AccountLocked))]
+    #[case(Chargeback(BackchargerError::AccountLocked))]
+    #[case(Resolve(ResolverError::NonDisputedTransaction(incompatible(
+        TransactionKind::Resolve
+    ))))]
+    #[case(Chargeback(BackchargerError::NonDisputedTransaction(incompatible(
+        TransactionKind::ChargeBack
+    ))))]
+    #[case(
+        Representment(RepresenterError::NonChargedBackTransaction(incompatible(
+            TransactionKind::Representment
+        )))
+    )]
+    #[case(ClientNotAllowed)]
+    #[case(Deposit(DepositorError::ConflictingWithPreviousTransaction))]
+    #[case(Withdrawal(WithdrawerError::ConflictingWithPreviousTransaction))]
+    #[case(ConflictingAdjustment)]
+    fn simple_error_handler_passes_fatal_errors_on(#[case] error: AccountTransactorError) {
         let handler = SimpleErrorHandler;
-        assert_eq!(handler.handle(error), after_handling);
-    }
-
-    fn account_lock() -> TransactionProcessorError {
-        transaction_processor_error(AccountLocked)
+        assert_eq!(
+            handler.handle(transaction_processor_error(error.clone())),
+            Err(transaction_processor_error(error))
+        );
     }
 
-    fn incompatible() -> TransactionProcessorError {
-        transaction_processor_error(IncompatibleTransaction)
-    }
-
-    fn insufficient_fund() -> TransactionProcessorError {
-        transaction_processor_error(InsufficientFundForWithdrawal)
+    #[test]
+    fn simple_error_handler_passes_unavailable_errors_on() {
+        let handler = SimpleErrorHandler;
+        let error = TransactionProcessorError::Unavailable(
+            Box::new(Transaction {
+                client_id: 123,
+                transaction_id: 456,
+                kind: TransactionKind::Dispute,
+                timestamp: None,
+                memo: None,
+                ingest_sequence: None,
+            }),
+            "timed out".to_string(),
+        );
+        assert_eq!(handler.handle(error.clone()), Err(error));
     }
 
-    fn no_transaction_found() -> TransactionProcessorError {
-        transaction_processor_error(NoTransactionFound)
+    #[rstest]
+    #[case(Withdrawal(WithdrawerError::InsufficientFund))]
+    #[case(Dispute(DisputerError::NoTransactionFound))]
+    #[case(Resolve(ResolverError::NoTransactionFound))]
+    #[case(Chargeback(BackchargerError::NoTransactionFound))]
+    #[case(Representment(RepresenterError::NoTransactionFound))]
+    #[case(AccountClosed)]
+    #[case(DisputeWindowExpired)]
+    #[case(TooManyOpenDisputes)]
+    #[case(DepositLimitExceeded)]
+    #[case(WithdrawalLimitExceeded)]
+    #[case(TransactionLimitExceeded)]
+    #[case(MinimumBalanceBreached)]
+    fn simple_error_handler_suppresses_other_errors(#[case] error: AccountTransactorError) {
+        let handler = SimpleErrorHandler;
+        assert_eq!(handler.handle(transaction_processor_error(error)), Ok(()));
     }
 
     fn transaction_processor_error(
         account_transactor_error: AccountTransactorError,
     ) -> TransactionProcessorError {
         TransactionProcessorError::AccountTransactionError(
-            Transaction {
+            Box::new(Transaction {
                 client_id: 123,
                 transaction_id: 456,
                 kind: crate::model::TransactionKind::Deposit {
-                    amount: Amount4DecimalBased(1),
+                    amount: Amount::new(1),
                 },
-            },
+                timestamp: None,
+                memo: None,
+                ingest_sequence: None,
+            }),
             account_transactor_error,
         )
     }
+
+    fn incompatible(requested: TransactionKind) -> Incompatibility {
+        Incompatibility {
+            client_id: 123,
+            transaction_id: 456,
+            requested,
+            status: DepositStatus::Accepted,
+        }
+    }
 }
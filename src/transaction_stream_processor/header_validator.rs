@@ -0,0 +1,164 @@
+use csv::StringRecord;
+
+use super::TransactionStreamProcessError;
+
+/// The furthest (in edits) an unknown column can be from a missing one to be
+/// suggested as a typo of it.
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+/// Checks the headers before any record is read, so that a misnamed column
+/// is reported once, rather than as a deserialisation error on every row.
+/// The `optional` columns are accepted but not required.
+pub(super) fn validate_headers(
+    headers: &StringRecord,
+    columns: &[&str],
+    optional: &[&str],
+) -> Result<(), TransactionStreamProcessError> {
+    let missing: Vec<&str> = columns
+        .iter()
+        .filter(|column| !headers.iter().any(|header| header == **column))
+        .copied()
+        .collect();
+    let unknown: Vec<&str> = headers
+        .iter()
+        .filter(|header| !columns.contains(header) && !optional.contains(header))
+        .collect();
+    if missing.is_empty() && unknown.is_empty() {
+        return Ok(());
+    }
+
+    let mut problems = Vec::new();
+    if !missing.is_empty() {
+        problems.push(format!("missing column(s): {}", quoted(&missing)));
+    }
+    if !unknown.is_empty() {
+        problems.push(format!("unknown column(s): {}", quoted(&unknown)));
+    }
+    for header in unknown {
+        if let Some(column) = closest(header, &missing) {
+            problems.push(format!("`{header}` looks like `{column}`"));
+        }
+    }
+    Err(TransactionStreamProcessError::InvalidHeaders(
+        problems.join("; "),
+    ))
+}
+
+fn quoted(columns: &[&str]) -> String {
+    columns
+        .iter()
+        .map(|column| format!("`{column}`"))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn closest<'a>(header: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    candidates
+        .iter()
+        .map(|candidate| (distance(header, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= MAX_SUGGESTION_DISTANCE)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Case-insensitive Damerau-Levenshtein (optimal string alignment) distance,
+/// so that a swap of two letters ("clinet") counts as a single edit.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use csv::StringRecord;
+    use rstest::rstest;
+
+    use crate::transaction_stream_processor::{
+        csv_reader::{V1_COLUMNS, V2_COLUMNS, V2_OPTIONAL_COLUMNS},
+        TransactionStreamProcessError,
+    };
+
+    use super::{distance, validate_headers};
+
+    #[rstest]
+    #[case(vec!["type", "client", "tx", "amount"], Ok(()))]
+    #[case(vec!["amount", "tx", "client", "type"], Ok(()))]
+    #[case(vec!["type", "clinet", "tx", "amount"],
+        invalid("missing column(s): `client`; unknown column(s): `clinet`; `clinet` looks like `client`"))]
+    #[case(vec!["Type", "client", "tx", "amount"],
+        invalid("missing column(s): `type`; unknown column(s): `Type`; `Type` looks like `type`"))]
+    #[case(vec!["type", "client", "tx"],
+        invalid("missing column(s): `amount`"))]
+    #[case(vec!["type", "client", "tx", "amount", "memo"],
+        invalid("unknown column(s): `memo`"))]
+    #[case(vec!["type", "customer", "tx", "amount"],
+        invalid("missing column(s): `client`; unknown column(s): `customer`"))]
+    fn v1_headers_are_validated(
+        #[case] headers: Vec<&str>,
+        #[case] expected: Result<(), TransactionStreamProcessError>,
+    ) {
+        assert_eq!(
+            validate_headers(&StringRecord::from(headers), &V1_COLUMNS, &[]),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case(vec!["type", "client", "tx", "amount", "timestamp", "currency", "memo"], Ok(()))]
+    #[case(vec!["memo", "type", "client", "tx", "amount", "currency", "timestamp"], Ok(()))]
+    #[case(vec!["type", "client", "tx", "amount", "timestamp", "currency", "memo", "metadata"], Ok(()))]
+    #[case(vec!["type", "client", "tx", "amount", "timestamp", "memo"],
+        invalid("missing column(s): `currency`"))]
+    #[case(vec!["type", "client", "tx", "amount", "timestamp", "curency", "memo"],
+        invalid("missing column(s): `currency`; unknown column(s): `curency`; `curency` looks like `currency`"))]
+    fn v2_headers_are_validated(
+        #[case] headers: Vec<&str>,
+        #[case] expected: Result<(), TransactionStreamProcessError>,
+    ) {
+        assert_eq!(
+            validate_headers(
+                &StringRecord::from(headers),
+                &V2_COLUMNS,
+                &V2_OPTIONAL_COLUMNS
+            ),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case("client", "client", 0)]
+    #[case("clinet", "client", 1)]
+    #[case("Client", "client", 0)]
+    #[case("clent", "client", 1)]
+    #[case("clients", "client", 1)]
+    #[case("amnt", "amount", 2)]
+    #[case("tx", "type", 3)]
+    fn distance_works(#[case] a: &str, #[case] b: &str, #[case] expected: usize) {
+        assert_eq!(distance(a, b), expected);
+    }
+
+    fn invalid(message: &str) -> Result<(), TransactionStreamProcessError> {
+        Err(TransactionStreamProcessError::InvalidHeaders(
+            message.to_string(),
+        ))
+    }
+}
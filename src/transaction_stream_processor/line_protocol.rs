@@ -0,0 +1,96 @@
+use crate::model::Transaction;
+
+use super::{
+    csv_reader::{CsvOptions, TransactionRecordReader},
+    transaction_record_converter::to_transaction,
+    TransactionRecordV2, TransactionStreamProcessError,
+};
+
+/// The transaction sent as a single line, either a JSON object with the
+/// columns of a [`TransactionRecordV2`] as its keys, the amount being a
+/// string, e.g. `{"type": "deposit", "client": 1, "tx": 1, "amount": "1.5"}`,
+/// or a CSV row with the columns of a
+/// [`TransactionRecordV1`](super::TransactionRecordV1) in order, e.g.
+/// `deposit, 1, 1, 1.5`.
+pub(crate) fn parse_line(line: &str) -> Result<Transaction, TransactionStreamProcessError> {
+    if line.trim_start().starts_with('{') {
+        let record: TransactionRecordV2 = serde_json::from_str(line)
+            .map_err(|err| TransactionStreamProcessError::ParsingError(err.to_string()))?;
+        return to_transaction(record);
+    }
+    let options = CsvOptions {
+        has_headers: false,
+        ..CsvOptions::default()
+    };
+    TransactionRecordReader::new(line.as_bytes(), &options)?
+        .transactions()
+        .next()
+        .unwrap_or_else(|| {
+            Err(TransactionStreamProcessError::ParsingError(
+                "Empty line.".to_string(),
+            ))
+        })
+        .map(|transaction| Transaction {
+            ingest_sequence: None,
+            ..transaction
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use rstest::rstest;
+
+    use crate::{
+        model::{Amount, Memo, Transaction, TransactionKind},
+        transaction_stream_processor::TransactionStreamProcessError,
+    };
+
+    use super::parse_line;
+
+    #[rstest]
+    #[case::csv("deposit, 1, 2, 1.5")]
+    #[case::json(r#"{"type": "deposit", "client": 1, "tx": 2, "amount": "1.5"}"#)]
+    fn lines_are_read_as_csv_or_json(#[case] line: &str) {
+        assert_eq!(
+            parse_line(line),
+            Ok(Transaction {
+                client_id: 1,
+                transaction_id: 2,
+                kind: TransactionKind::Deposit {
+                    amount: Amount::new(15_000)
+                },
+                timestamp: None,
+                memo: None,
+                ingest_sequence: None,
+            })
+        );
+    }
+
+    #[test]
+    fn json_lines_can_have_the_columns_of_the_later_format() {
+        let transaction = parse_line(
+            r#"{"type": "dispute", "client": 1, "tx": 2, "timestamp": 1700000000, "memo": "chargeback expected"}"#,
+        )
+        .unwrap();
+        assert_eq!(transaction.timestamp, Some(1_700_000_000));
+        assert_eq!(
+            transaction.memo.as_deref(),
+            Some(&Memo {
+                text: Some("chargeback expected".to_string()),
+                metadata: Default::default(),
+            })
+        );
+    }
+
+    #[rstest]
+    #[case::empty("")]
+    #[case::missing_amount("deposit, 1, 2,")]
+    #[case::amount_as_a_number(r#"{"type": "deposit", "client": 1, "tx": 2, "amount": 1.5}"#)]
+    fn invalid_lines_are_rejected(#[case] line: &str) {
+        assert_matches!(
+            parse_line(line),
+            Err(TransactionStreamProcessError::ParsingError(_))
+        );
+    }
+}
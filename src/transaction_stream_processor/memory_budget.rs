@@ -0,0 +1,103 @@
+use std::{
+    mem::size_of,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use crate::{account::AccountStore, model::Transaction};
+
+/// Caps the approximate memory held by the accounts and by the transactions
+/// read but not processed yet.
+/// Over budget, the input stops being read until the transactions in flight
+/// are processed. The accounts themselves cannot be shrunk, so once they
+/// alone are over budget the input is read at the pace of the processing,
+/// rather than ahead of it.
+#[derive(Debug, Clone)]
+pub struct MemoryBudget {
+    bytes: usize,
+    accounts: Arc<AccountStore>,
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl MemoryBudget {
+    pub fn new(bytes: usize, accounts: Arc<AccountStore>) -> Self {
+        Self {
+            bytes,
+            accounts,
+            in_flight: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Counts a transaction as in flight until the guard is dropped, whether
+    /// it was processed or dropped along with the channel of a worker that
+    /// failed.
+    pub(super) fn sent(&self) -> InFlight {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlight(self.in_flight.clone())
+    }
+
+    /// The approximate memory held by the accounts, which means going through
+    /// all of them.
+    pub(super) fn accounts_bytes(&self) -> usize {
+        self.accounts.approximate_bytes()
+    }
+
+    /// Whether reading the input should wait for the transactions in flight
+    /// to be processed, the accounts holding `accounts_bytes` as per
+    /// [`MemoryBudget::accounts_bytes`].
+    pub(super) fn should_throttle(&self, accounts_bytes: usize) -> bool {
+        let in_flight = self.in_flight.load(Ordering::Relaxed);
+        in_flight > 0 && accounts_bytes + in_flight * size_of::<Transaction>() > self.bytes
+    }
+}
+
+/// A transaction counted as in flight by a [`MemoryBudget`], until dropped.
+#[derive(Debug)]
+pub struct InFlight(Arc<AtomicUsize>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{mem::size_of, sync::Arc};
+
+    use rstest::rstest;
+
+    use crate::{account::AccountStore, model::Transaction};
+
+    use super::MemoryBudget;
+
+    #[rstest]
+    #[case(0, 0, false)]
+    #[case(1, 1, false)]
+    #[case(1, 0, true)]
+    #[case(2, 1, true)]
+    #[case(2, 2, false)]
+    fn throttles_over_budget_while_transactions_are_in_flight(
+        #[case] in_flight: usize,
+        #[case] budgeted_transactions: usize,
+        #[case] expected: bool,
+    ) {
+        let budget = MemoryBudget::new(
+            budgeted_transactions * size_of::<Transaction>(),
+            Arc::new(AccountStore::new()),
+        );
+        let _in_flight: Vec<_> = (0..in_flight).map(|_| budget.sent()).collect();
+        assert_eq!(budget.should_throttle(budget.accounts_bytes()), expected);
+    }
+
+    #[test]
+    fn transactions_let_go_of_are_no_longer_in_flight() {
+        let budget = MemoryBudget::new(0, Arc::new(AccountStore::new()));
+        let in_flight = budget.sent();
+        assert!(budget.should_throttle(0));
+        drop(in_flight);
+        assert!(!budget.should_throttle(0));
+    }
+}
@@ -0,0 +1,260 @@
+use std::io::Read;
+
+use arrow::{
+    array::{Array, ArrayRef, AsArray, RecordBatch},
+    compute::cast,
+    datatypes::{DataType, UInt16Type, UInt32Type},
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use serde::{de::value::StrDeserializer, Deserialize};
+
+use crate::transaction_processor::TransactionProcessor;
+
+use super::{
+    error_handler::SimpleErrorHandler, transaction_record_converter::to_transaction, ErrorHandler,
+    TransactionRecordType, TransactionRecordV1, TransactionStreamProcessError,
+    TransactionStreamProcessor,
+};
+
+/// Processes transactions stored in Parquet, with the same `type`, `client`,
+/// `tx` and `amount` columns as the CSV input.
+/// The column types do not have to match exactly, e.g. `client` can be any
+/// integer type and `amount` can be a decimal, as long as they can be cast.
+///
+/// Parquet files can only be read with random access, so the whole input is
+/// loaded into memory before the records are streamed to the consumer.
+pub struct ParquetStreamProcessor {
+    consumer: Box<dyn TransactionProcessor + Send + Sync>,
+    error_handler: Box<dyn ErrorHandler + Send + Sync>,
+}
+
+#[async_trait]
+impl TransactionStreamProcessor for ParquetStreamProcessor {
+    async fn process(&self, mut r: impl Read + Send) -> Result<(), TransactionStreamProcessError> {
+        let mut buf = Vec::new();
+        r.read_to_end(&mut buf)
+            .map_err(|err| TransactionStreamProcessError::ParsingError(err.to_string()))?;
+        let reader = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(buf))
+            .and_then(|builder| builder.build())
+            .map_err(|err| TransactionStreamProcessError::ParsingError(err.to_string()))?;
+        let mut position = 0;
+        for batch in reader {
+            let batch = batch
+                .map_err(|err| TransactionStreamProcessError::ParsingError(err.to_string()))?;
+            for record in to_records(&batch)? {
+                position += 1;
+                let mut transaction = to_transaction(record)?;
+                transaction.ingest_sequence = Some(position);
+                match self.consumer.process(transaction).await {
+                    Ok(_) => {}
+                    Err(err) => self.error_handler.handle(err)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ParquetStreamProcessor {
+    pub fn new(consumer: Box<dyn TransactionProcessor + Send + Sync>) -> Self {
+        let error_handler = SimpleErrorHandler;
+        Self {
+            consumer,
+            error_handler: Box::new(error_handler),
+        }
+    }
+}
+
+fn to_records(
+    batch: &RecordBatch,
+) -> Result<Vec<TransactionRecordV1>, TransactionStreamProcessError> {
+    let types = column(batch, "type", &DataType::Utf8)?;
+    let client_ids = column(batch, "client", &DataType::UInt16)?;
+    let transaction_ids = column(batch, "tx", &DataType::UInt32)?;
+    let amounts = column(batch, "amount", &DataType::Utf8)?;
+
+    let types = types.as_string::<i32>();
+    let client_ids = client_ids.as_primitive::<UInt16Type>();
+    let transaction_ids = transaction_ids.as_primitive::<UInt32Type>();
+    let amounts = amounts.as_string::<i32>();
+
+    (0..batch.num_rows())
+        .map(|i| {
+            if types.is_null(i) || client_ids.is_null(i) || transaction_ids.is_null(i) {
+                return Err(TransactionStreamProcessError::ParsingError(format!(
+                    "Row {i} has a null `type`, `client` or `tx`."
+                )));
+            }
+            Ok(TransactionRecordV1 {
+                txn_type: record_type(types.value(i))?,
+                client_id: client_ids.value(i),
+                transaction_id: transaction_ids.value(i),
+                optional_amount: match amounts.is_null(i) {
+                    true => None,
+                    false => Some(amounts.value(i).to_string()),
+                },
+            })
+        })
+        .collect()
+}
+
+fn column(
+    batch: &RecordBatch,
+    name: &str,
+    data_type: &DataType,
+) -> Result<ArrayRef, TransactionStreamProcessError> {
+    match batch.column_by_name(name) {
+        Some(column) => cast(column, data_type).map_err(|err| {
+            TransactionStreamProcessError::ParsingError(format!("Column `{name}`: {err}"))
+        }),
+        None => Err(TransactionStreamProcessError::ParsingError(format!(
+            "Column `{name}` not found."
+        ))),
+    }
+}
+
+/// Goes through serde so that the accepted values are the same as the CSV's.
+fn record_type(s: &str) -> Result<TransactionRecordType, TransactionStreamProcessError> {
+    TransactionRecordType::deserialize(StrDeserializer::<serde::de::value::Error>::new(s))
+        .map_err(|err| TransactionStreamProcessError::ParsingError(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use arrow::{
+        array::{ArrayRef, Decimal128Array, Int64Array, RecordBatch, StringArray},
+        datatypes::{Field, Schema},
+    };
+    use assert_matches::assert_matches;
+    use parquet::arrow::ArrowWriter;
+
+    use crate::{
+        model::{Amount, Transaction, TransactionKind},
+        transaction_processor::{Blackhole, RecordSink},
+        transaction_stream_processor::{TransactionStreamProcessError, TransactionStreamProcessor},
+    };
+
+    use super::ParquetStreamProcessor;
+
+    #[tokio::test]
+    async fn parquet_parsing_works() {
+        let input = parquet(vec![
+            (
+                "type",
+                Arc::new(StringArray::from(vec!["deposit", "withdrawal", "dispute"])),
+            ),
+            ("client", Arc::new(Int64Array::from(vec![1, 1, 2]))),
+            ("tx", Arc::new(Int64Array::from(vec![10, 20, 30]))),
+            (
+                "amount",
+                Arc::new(
+                    Decimal128Array::from(vec![Some(30_000), Some(12_500), None])
+                        .with_precision_and_scale(19, 4)
+                        .unwrap(),
+                ),
+            ),
+        ]);
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let record_sink = RecordSink {
+            records: records.clone(),
+        };
+
+        let processor = ParquetStreamProcessor::new(Box::new(record_sink));
+        processor.process(input.as_slice()).await.unwrap();
+        assert_eq!(
+            *records.lock().unwrap(),
+            vec![
+                transaction(1, 1, 10, deposit(30_000)),
+                transaction(2, 1, 20, withdrawal(12_500)),
+                transaction(3, 2, 30, TransactionKind::Dispute),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_column_results_in_parsing_error() {
+        let input = parquet(vec![
+            ("type", Arc::new(StringArray::from(vec!["dispute"]))),
+            ("client", Arc::new(Int64Array::from(vec![1]))),
+            ("amount", Arc::new(StringArray::from(vec![None::<&str>]))),
+        ]);
+        let processor = ParquetStreamProcessor::new(Box::new(Blackhole));
+        assert_matches!(
+            processor.process(input.as_slice()).await,
+            Err(TransactionStreamProcessError::ParsingError(_))
+        );
+    }
+
+    #[tokio::test]
+    async fn unknown_transaction_type_results_in_parsing_error() {
+        let input = parquet(vec![
+            ("type", Arc::new(StringArray::from(vec!["refund"]))),
+            ("client", Arc::new(Int64Array::from(vec![1]))),
+            ("tx", Arc::new(Int64Array::from(vec![1]))),
+            ("amount", Arc::new(StringArray::from(vec![Some("1.0")]))),
+        ]);
+        let processor = ParquetStreamProcessor::new(Box::new(Blackhole));
+        assert_matches!(
+            processor.process(input.as_slice()).await,
+            Err(TransactionStreamProcessError::ParsingError(_))
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_input_results_in_parsing_error() {
+        let processor = ParquetStreamProcessor::new(Box::new(Blackhole));
+        assert_matches!(
+            processor.process("type,client,tx,amount".as_bytes()).await,
+            Err(TransactionStreamProcessError::ParsingError(_))
+        );
+    }
+
+    fn parquet(columns: Vec<(&str, ArrayRef)>) -> Vec<u8> {
+        let schema = Arc::new(Schema::new(
+            columns
+                .iter()
+                .map(|(name, array)| Field::new(*name, array.data_type().clone(), true))
+                .collect::<Vec<Field>>(),
+        ));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            columns.into_iter().map(|(_, array)| array).collect(),
+        )
+        .unwrap();
+        let mut writer = ArrowWriter::try_new(vec![], schema, None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.into_inner().unwrap()
+    }
+
+    fn transaction(
+        position: u64,
+        client_id: u16,
+        transaction_id: u32,
+        kind: TransactionKind,
+    ) -> Transaction {
+        Transaction {
+            client_id,
+            transaction_id,
+            kind,
+            timestamp: None,
+            memo: None,
+            ingest_sequence: Some(position),
+        }
+    }
+
+    fn deposit(amount: i64) -> TransactionKind {
+        TransactionKind::Deposit {
+            amount: Amount::new(amount),
+        }
+    }
+
+    fn withdrawal(amount: i64) -> TransactionKind {
+        TransactionKind::Withdrawal {
+            amount: Amount::new(amount),
+        }
+    }
+}
@@ -0,0 +1,267 @@
+//! The stages transactions go through once read: a [`TransactionSource`]
+//! gives them, a [`Router`] hands each of them to a worker, keeping those of
+//! a client in order, and the workers give them to a [`TransactionSink`].
+//! A [`Pipeline`] drives them, so that a new source, e.g. a Kafka topic, or a
+//! new routing strategy, e.g. a pool of workers, is only that stage.
+
+mod client_router;
+mod parallel_csv_source;
+mod worker_pool_router;
+
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use tokio::{sync::broadcast, time::sleep};
+
+use crate::{
+    model::Transaction,
+    transaction_processor::{TransactionProcessor, TransactionProcessorError},
+};
+
+use super::{
+    error_handler::SimpleErrorHandler, Cancellation, ErrorHandler, InFlight, MemoryBudget,
+    QueuedChannel, RateLimiter, TransactionStreamProcessError, Watchdog,
+};
+
+pub use client_router::{
+    ClientRouter, Lanes, SendersAndHandles, WorkerHandle, DEFAULT_CHANNEL_CAPACITY,
+};
+pub(super) use parallel_csv_source::{ParallelCsvSource, CHUNK_LEN};
+pub use worker_pool_router::WorkerPoolRouter;
+
+/// Where the transactions of a run come from, e.g. a CSV input or a socket.
+/// Any iterator of transactions is one.
+#[async_trait]
+pub trait TransactionSource: Send {
+    /// The next transaction, none once the source is exhausted. An error
+    /// fails the run.
+    async fn next_transaction(
+        &mut self,
+    ) -> Option<Result<Transaction, TransactionStreamProcessError>>;
+}
+
+#[async_trait]
+impl<I> TransactionSource for I
+where
+    I: Iterator<Item = Result<Transaction, TransactionStreamProcessError>> + Send,
+{
+    async fn next_transaction(
+        &mut self,
+    ) -> Option<Result<Transaction, TransactionStreamProcessError>> {
+        self.next()
+    }
+}
+
+/// A transaction waiting for a worker, counted as in flight until it is
+/// taken by the sink or dropped.
+type Queued = (Transaction, Option<InFlight>);
+
+/// Hands the transactions to the workers giving them to a sink, those of a
+/// client being given to it in the order they are routed in.
+#[async_trait]
+pub trait Router: Send + Sync {
+    /// Waits for room for the transaction if its worker has too many waiting
+    /// already. The transaction is counted as in flight until `in_flight`
+    /// is dropped, which is to be once the sink took it, or once it is
+    /// dropped itself, e.g. along with the channel of a worker that failed.
+    async fn route(
+        &self,
+        transaction: Transaction,
+        in_flight: Option<InFlight>,
+    ) -> Result<(), TransactionStreamProcessError>;
+
+    /// The channels of the clients, for a [`Watchdog`] to tell which of them
+    /// hold transactions once stalled, none by default.
+    fn queued(&self) -> Vec<QueuedChannel> {
+        Vec::new()
+    }
+
+    /// Waits for the workers to be done with the transactions routed to
+    /// them, failing with the error of the first of them that failed.
+    async fn shutdown(&self) -> Result<(), TransactionStreamProcessError>;
+}
+
+/// Where the workers give the transactions routed to them.
+#[async_trait]
+pub trait TransactionSink: Send + Sync {
+    /// Fails with the error the worker is to stop at, if any.
+    async fn take(&self, transaction: Transaction) -> Result<(), TransactionProcessorError>;
+}
+
+/// Gives the transactions to a [`TransactionProcessor`], its errors being
+/// handled as the stream processors do.
+pub struct ProcessingSink {
+    processor: Arc<dyn TransactionProcessor + Send + Sync>,
+    error_handler: Arc<dyn ErrorHandler + Send + Sync>,
+    watchdog: Option<Watchdog>,
+    errors: Option<broadcast::Sender<TransactionProcessorError>>,
+}
+
+#[async_trait]
+impl TransactionSink for ProcessingSink {
+    async fn take(&self, transaction: Transaction) -> Result<(), TransactionProcessorError> {
+        let result = self.processor.process(transaction).await;
+        if let Some(watchdog) = &self.watchdog {
+            watchdog.progressed();
+        }
+        if let Err(err) = result {
+            if let Some(errors) = &self.errors {
+                // only fails when no one is listening
+                let _ = errors.send(err.clone());
+            }
+            self.error_handler.handle(err)?
+        }
+        Ok(())
+    }
+}
+
+impl ProcessingSink {
+    pub fn new(processor: Arc<dyn TransactionProcessor + Send + Sync>) -> Self {
+        Self {
+            processor,
+            error_handler: Arc::new(SimpleErrorHandler),
+            watchdog: None,
+            errors: None,
+        }
+    }
+
+    /// Every transaction processed counts as progress.
+    pub fn with_watchdog(self, watchdog: Option<Watchdog>) -> Self {
+        Self { watchdog, ..self }
+    }
+
+    /// Every error of the processor is sent as soon as it occurs.
+    pub fn with_errors(self, errors: Option<broadcast::Sender<TransactionProcessorError>>) -> Self {
+        Self { errors, ..self }
+    }
+}
+
+/// How many records are read between two checks of the memory budget, as
+/// sizing up the accounts means going through all of them.
+const RECORDS_PER_BUDGET_CHECK: usize = 1024;
+
+/// How long reading waits for the transactions in flight to be processed
+/// when over budget, before checking again.
+const THROTTLE_PAUSE: Duration = Duration::from_millis(1);
+
+/// Routes the transactions of the sources it runs, as long as the run is
+/// not cancelled, and no faster than the memory budget and the rate limiter
+/// let it.
+pub struct Pipeline {
+    router: Arc<dyn Router>,
+    cancellation: Cancellation,
+    memory_budget: Option<MemoryBudget>,
+    rate_limiter: Option<RateLimiter>,
+    watchdog: Option<Watchdog>,
+}
+
+impl Pipeline {
+    pub fn new(router: Arc<dyn Router>) -> Self {
+        Self {
+            router,
+            cancellation: Cancellation::default(),
+            memory_budget: None,
+            rate_limiter: None,
+            watchdog: None,
+        }
+    }
+
+    /// The source stops being read once the cancellation is cancelled, the
+    /// run then failing with [`TransactionStreamProcessError::Cancelled`].
+    pub fn with_cancellation(self, cancellation: Cancellation) -> Self {
+        Self {
+            cancellation,
+            ..self
+        }
+    }
+
+    /// Reading the source is held back whenever the accounts and the
+    /// transactions in flight are over budget.
+    pub fn with_memory_budget(self, memory_budget: Option<MemoryBudget>) -> Self {
+        Self {
+            memory_budget,
+            ..self
+        }
+    }
+
+    /// The source is read no faster than the rate limiter lets it.
+    pub fn with_rate_limiter(self, rate_limiter: Option<RateLimiter>) -> Self {
+        Self {
+            rate_limiter,
+            ..self
+        }
+    }
+
+    /// Every transaction routed counts as progress, a [`Stall`] being
+    /// reported along with the channels of the router holding transactions
+    /// while the source is read.
+    ///
+    /// [`Stall`]: super::Stall
+    pub fn with_watchdog(self, watchdog: Option<Watchdog>) -> Self {
+        Self { watchdog, ..self }
+    }
+
+    /// Routes the transactions of the source, one after another, up to the
+    /// first error.
+    pub async fn run(
+        &self,
+        mut source: impl TransactionSource,
+    ) -> Result<(), TransactionStreamProcessError> {
+        let _watching = self.watchdog.as_ref().map(|watchdog| {
+            let router = self.router.clone();
+            watchdog.watch(move || {
+                router
+                    .queued()
+                    .into_iter()
+                    .filter(|channel| channel.queued > 0)
+                    .collect()
+            })
+        });
+        let mut read = 0;
+        while let Some(result) = source.next_transaction().await {
+            if self.cancellation.is_cancelled() {
+                return Err(TransactionStreamProcessError::Cancelled);
+            }
+            if read % RECORDS_PER_BUDGET_CHECK == 0 {
+                self.throttle().await;
+            }
+            read += 1;
+            if let Some(rate_limiter) = &self.rate_limiter {
+                rate_limiter.admit().await;
+            }
+            self.dispatch(result?).await?;
+        }
+        Ok(())
+    }
+
+    /// Routes a single transaction, e.g. one read from a socket.
+    pub async fn dispatch(
+        &self,
+        transaction: Transaction,
+    ) -> Result<(), TransactionStreamProcessError> {
+        let in_flight = self
+            .memory_budget
+            .as_ref()
+            .map(|memory_budget| memory_budget.sent());
+        self.router.route(transaction, in_flight).await?;
+        if let Some(watchdog) = &self.watchdog {
+            watchdog.progressed();
+        }
+        Ok(())
+    }
+
+    pub async fn shutdown(&self) -> Result<(), TransactionStreamProcessError> {
+        self.router.shutdown().await
+    }
+
+    async fn throttle(&self) {
+        if let Some(memory_budget) = &self.memory_budget {
+            // sized up once, the accounts only growing by the transactions
+            // processed meanwhile, until the next check
+            let accounts_bytes = memory_budget.accounts_bytes();
+            while memory_budget.should_throttle(accounts_bytes) {
+                sleep(THROTTLE_PAUSE).await;
+            }
+        }
+    }
+}
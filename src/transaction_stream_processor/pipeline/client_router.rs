@@ -0,0 +1,373 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use dashmap::{mapref::entry::Entry, DashMap};
+use tokio::{
+    sync::{
+        mpsc::{channel, error::SendError, Receiver, Sender},
+        oneshot,
+    },
+    task::JoinHandle,
+    time::timeout,
+};
+
+use crate::{
+    model::{ClientId, Transaction},
+    transaction_processor::TransactionProcessorError,
+    transaction_stream_processor::{InFlight, QueuedChannel, TransactionStreamProcessError},
+};
+
+use super::{Queued, Router, TransactionSink};
+
+/// The per-client channel senders and the handles of the tasks consuming them.
+pub type SendersAndHandles = DashMap<ClientId, (Lanes, WorkerHandle)>;
+
+/// The handle of the task processing the transactions of a client.
+pub type WorkerHandle = JoinHandle<Result<(), TransactionProcessorError>>;
+
+/// How a worker that exited idle ends, once done with the transactions it was
+/// left with.
+type Retired = oneshot::Receiver<Result<(), TransactionProcessorError>>;
+
+/// The channels the worker of a client takes its transactions from.
+#[derive(Debug, Clone)]
+pub struct Lanes {
+    /// Tells the channels of a client apart from those it had before.
+    id: u64,
+    regular: Sender<Queued>,
+    /// The admin transactions, taken ahead of the regular ones waiting, if
+    /// they are given priority.
+    priority: Option<Sender<Queued>>,
+}
+
+impl Lanes {
+    /// The lane the transaction goes through.
+    fn of(&self, transaction: &Transaction) -> &Sender<Queued> {
+        match &self.priority {
+            Some(priority) if transaction.kind.is_admin() => priority,
+            _ => &self.regular,
+        }
+    }
+
+    /// How many transactions of the client are waiting in its channels.
+    fn queued(&self, client_id: ClientId) -> QueuedChannel {
+        let lanes = std::iter::once(&self.regular).chain(self.priority.as_ref());
+        let (queued, capacity) = lanes.fold((0, 0), |(queued, capacity), sender| {
+            (
+                queued + sender.max_capacity() - sender.capacity(),
+                capacity + sender.max_capacity(),
+            )
+        });
+        QueuedChannel {
+            client_id,
+            queued,
+            capacity,
+        }
+    }
+}
+
+/// Routes the transactions to a worker per client, spawned for its first
+/// transaction, so that the clients are processed at the same time and the
+/// transactions of each of them one after another.
+pub struct ClientRouter {
+    sink: Arc<dyn TransactionSink>,
+    senders_and_handles: Arc<SendersAndHandles>,
+    /// How the workers that exited idle end, each until the next worker of
+    /// its client waits for it.
+    retired: Arc<DashMap<ClientId, Retired>>,
+    next_lanes_id: AtomicU64,
+    idle_timeout: Option<Duration>,
+    admin_priority: bool,
+    channel_capacity: usize,
+}
+
+#[async_trait]
+impl Router for ClientRouter {
+    async fn route(
+        &self,
+        transaction: Transaction,
+        in_flight: Option<InFlight>,
+    ) -> Result<(), TransactionStreamProcessError> {
+        let client_id = transaction.client_id;
+        let mut queued = (transaction, in_flight);
+        loop {
+            // cloned so that the shard lock is not held while waiting for
+            // room in the channel
+            let lanes = self
+                .senders_and_handles
+                .entry(client_id)
+                .or_insert_with(|| self.create_channel(client_id))
+                .0
+                .clone();
+            let err = match lanes.of(&queued.0).send(queued).await {
+                Ok(_) => return Ok(()),
+                Err(err) => err,
+            };
+            // the worker exited idle meanwhile, rather than failing, if its
+            // channels were taken out of the map
+            let failed = matches!(
+                self.senders_and_handles.get(&client_id),
+                Some(entry) if entry.0.id == lanes.id
+            );
+            if failed {
+                return Err(TransactionStreamProcessError::InternalError(
+                    err.to_string(),
+                ));
+            }
+            let SendError(returned) = err;
+            queued = returned;
+        }
+    }
+
+    fn queued(&self) -> Vec<QueuedChannel> {
+        self.senders_and_handles
+            .iter()
+            .map(|entry| entry.0.queued(*entry.key()))
+            .collect()
+    }
+
+    async fn shutdown(&self) -> Result<(), TransactionStreamProcessError> {
+        let clients: Vec<ClientId> = self
+            .senders_and_handles
+            .iter()
+            .map(|entry| *entry.key())
+            .collect();
+        for client_id in clients {
+            // gone if the worker exited idle in the meantime
+            if let Some((_, (lanes, handle))) = self.senders_and_handles.remove(&client_id) {
+                drop(lanes);
+                join(handle).await?;
+            }
+        }
+        // no worker is left to exit idle, nor to wait for those that did
+        let retired: Vec<ClientId> = self.retired.iter().map(|entry| *entry.key()).collect();
+        for client_id in retired {
+            if let Some((_, retired)) = self.retired.remove(&client_id) {
+                retired
+                    .await
+                    .map_err(|err| {
+                        TransactionStreamProcessError::FailedToShutdown(err.to_string())
+                    })?
+                    .map_err(TransactionStreamProcessError::ProcessError)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How many transactions of a client can wait to be processed, unless told
+/// otherwise.
+pub const DEFAULT_CHANNEL_CAPACITY: usize = 256;
+
+impl ClientRouter {
+    /// The channels of the clients are kept in the map given.
+    pub fn new(
+        sink: Arc<dyn TransactionSink>,
+        senders_and_handles: Arc<SendersAndHandles>,
+    ) -> Self {
+        Self {
+            sink,
+            senders_and_handles,
+            retired: Arc::new(DashMap::new()),
+            next_lanes_id: AtomicU64::new(0),
+            idle_timeout: None,
+            admin_priority: false,
+            channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+        }
+    }
+
+    /// The admin transactions of a client, see [`TransactionKind::is_admin`],
+    /// are taken ahead of its regular ones waiting to be.
+    ///
+    /// [`TransactionKind::is_admin`]: crate::model::TransactionKind::is_admin
+    pub fn with_admin_priority(self, admin_priority: bool) -> Self {
+        Self {
+            admin_priority,
+            ..self
+        }
+    }
+
+    /// The worker of a client exits once it has not been given a transaction
+    /// for that long, a worker being spawned again for the next transaction
+    /// of the client once the previous one is done.
+    pub fn with_idle_timeout(self, idle_timeout: Option<Duration>) -> Self {
+        Self {
+            idle_timeout,
+            ..self
+        }
+    }
+
+    /// Routing waits for a client's transactions to be processed once that
+    /// many of them are waiting.
+    pub fn with_channel_capacity(self, channel_capacity: usize) -> Self {
+        Self {
+            channel_capacity,
+            ..self
+        }
+    }
+
+    /// Spawns the worker of a client, which waits for the previous one, if it
+    /// exited idle, to be done with the transactions it was left with.
+    fn create_channel(&self, client_id: ClientId) -> (Lanes, WorkerHandle) {
+        let id = self.next_lanes_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = channel::<Queued>(self.channel_capacity);
+        let (priority_sender, priority_receiver) = match self.admin_priority {
+            true => {
+                let (sender, receiver) = channel::<Queued>(self.channel_capacity);
+                (Some(sender), Some(receiver))
+            }
+            false => (None, None),
+        };
+        let worker = Worker {
+            sink: self.sink.clone(),
+            receiver,
+            priority_receiver,
+            reclamation: self.idle_timeout.map(|idle_timeout| Reclamation {
+                idle_timeout,
+                client_id,
+                lanes_id: id,
+                senders_and_handles: self.senders_and_handles.clone(),
+                retired: self.retired.clone(),
+            }),
+            done: None,
+        };
+        let previous = self.retired.remove(&client_id).map(|(_, retired)| retired);
+        let handle = tokio::spawn(worker.run(previous));
+        let lanes = Lanes {
+            id,
+            regular: sender,
+            priority: priority_sender,
+        };
+        (lanes, handle)
+    }
+}
+
+async fn join(handle: WorkerHandle) -> Result<(), TransactionStreamProcessError> {
+    match handle.await {
+        Ok(process_reesult) => match process_reesult {
+            Ok(_) => Ok(()),
+            Err(process_err) => Err(TransactionStreamProcessError::ProcessError(process_err)),
+        },
+        Err(e) => Err(TransactionStreamProcessError::FailedToShutdown(
+            e.to_string(),
+        )),
+    }
+}
+
+/// Gives the transactions of a client to the sink, one after another.
+struct Worker {
+    sink: Arc<dyn TransactionSink>,
+    receiver: Receiver<Queued>,
+    priority_receiver: Option<Receiver<Queued>>,
+    /// None once the channels are given back, or if they never are.
+    reclamation: Option<Reclamation>,
+    /// Tells the next worker of the client how this one ended, once it
+    /// exited idle.
+    done: Option<oneshot::Sender<Result<(), TransactionProcessorError>>>,
+}
+
+impl Worker {
+    async fn run(mut self, previous: Option<Retired>) -> Result<(), TransactionProcessorError> {
+        let result = self.work(previous).await;
+        if let Some(done) = self.done.take() {
+            // only fails when the run is shut down without it
+            let _ = done.send(result.clone());
+        }
+        result
+    }
+
+    async fn work(&mut self, previous: Option<Retired>) -> Result<(), TransactionProcessorError> {
+        if let Some(previous) = previous {
+            previous
+                .await
+                .expect("The previous worker of the client does not panic.")?;
+        }
+        // those left once failed are dropped along with the receivers, their
+        // guards with them
+        while let Some((transaction, _in_flight)) = self.next().await {
+            self.sink.take(transaction).await?;
+        }
+        Ok(())
+    }
+
+    /// The next transaction, none once the channels are closed and drained.
+    /// Once idle for the timeout, the channels are given back and closed, the
+    /// transactions already sent still being taken.
+    async fn next(&mut self) -> Option<Queued> {
+        loop {
+            let idle_timeout = match &self.reclamation {
+                Some(reclamation) => reclamation.idle_timeout,
+                None => {
+                    return next_transaction(&mut self.receiver, self.priority_receiver.as_mut())
+                        .await
+                }
+            };
+            let next = next_transaction(&mut self.receiver, self.priority_receiver.as_mut());
+            if let Ok(next) = timeout(idle_timeout, next).await {
+                return next;
+            }
+            self.done = self.reclamation.take().and_then(Reclamation::retire);
+            if self.done.is_some() {
+                self.receiver.close();
+                if let Some(priority_receiver) = &mut self.priority_receiver {
+                    priority_receiver.close();
+                }
+            }
+        }
+    }
+}
+
+/// What the worker of a client needs to give its channels back once idle.
+struct Reclamation {
+    idle_timeout: Duration,
+    client_id: ClientId,
+    lanes_id: u64,
+    senders_and_handles: Arc<SendersAndHandles>,
+    retired: Arc<DashMap<ClientId, Retired>>,
+}
+
+impl Reclamation {
+    /// Takes the channels of the worker out of the map, for the next worker
+    /// of the client to wait for this one to end, unless they were taken by
+    /// the shutdown already.
+    fn retire(self) -> Option<oneshot::Sender<Result<(), TransactionProcessorError>>> {
+        match self.senders_and_handles.entry(self.client_id) {
+            Entry::Occupied(entry) if entry.get().0.id == self.lanes_id => {
+                let (done, retired) = oneshot::channel();
+                // before the entry is let go of, so that the next worker of
+                // the client cannot miss it
+                self.retired.insert(self.client_id, retired);
+                // the task runs to its end all the same
+                entry.remove();
+                Some(done)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The next transaction for a worker, those of the priority lane first, none
+/// once both lanes are closed and drained.
+async fn next_transaction(
+    regular: &mut Receiver<Queued>,
+    priority: Option<&mut Receiver<Queued>>,
+) -> Option<Queued> {
+    let priority = match priority {
+        Some(priority) => priority,
+        None => return regular.recv().await,
+    };
+    tokio::select! {
+        biased;
+        Some(transaction) = priority.recv() => Some(transaction),
+        transaction = regular.recv() => match transaction {
+            Some(transaction) => Some(transaction),
+            None => priority.recv().await,
+        },
+    }
+}
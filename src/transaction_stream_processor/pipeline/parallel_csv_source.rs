@@ -0,0 +1,199 @@
+use std::{collections::VecDeque, io::Read};
+
+use async_trait::async_trait;
+use tokio::task::{spawn_blocking, JoinHandle};
+
+use crate::{
+    model::Transaction,
+    transaction_stream_processor::{
+        chunker::{Chunk, Chunker},
+        csv_reader::{CsvOptions, Layout, TransactionRecordReader},
+        AmountWarnings, Quarantine, Shard, TransactionStreamProcessError,
+    },
+};
+
+use super::TransactionSource;
+
+/// How many bytes of the input each parser is given at a time, unless told
+/// otherwise.
+pub(crate) const CHUNK_LEN: usize = 64 * 1024;
+
+type Parsed = Vec<Result<Transaction, TransactionStreamProcessError>>;
+
+/// The transactions of a CSV input split into chunks of whole records, as
+/// many of them being parsed at the same time as there are parsers while
+/// those before them are taken.
+/// The chunks are given in the order of the input, each of them once parsed,
+/// so that the transactions of a client come in that order whichever parser
+/// they went through.
+pub(crate) struct ParallelCsvSource<R: Read> {
+    chunker: Chunker<R>,
+    csv_options: CsvOptions,
+    layout: Layout,
+    quarantine: Option<Quarantine>,
+    client_quarantine: Option<Quarantine>,
+    amount_warnings: Option<AmountWarnings>,
+    shard: Option<Shard>,
+    parsers: usize,
+    chunk_len: usize,
+    parsing: VecDeque<JoinHandle<Parsed>>,
+    parsed: std::vec::IntoIter<Result<Transaction, TransactionStreamProcessError>>,
+    /// Whether the last chunk was handed to a parser already.
+    read_all: bool,
+}
+
+#[async_trait]
+impl<R: Read + Send> TransactionSource for ParallelCsvSource<R> {
+    async fn next_transaction(
+        &mut self,
+    ) -> Option<Result<Transaction, TransactionStreamProcessError>> {
+        loop {
+            if let Some(result) = self.parsed.next() {
+                return Some(result);
+            }
+            while !self.read_all && self.parsing.len() < self.parsers {
+                let chunk = match self.chunker.next_chunk(self.chunk_len) {
+                    Ok(Some(chunk)) => chunk,
+                    Ok(None) => {
+                        self.read_all = true;
+                        break;
+                    }
+                    Err(err) => {
+                        self.read_all = true;
+                        return Some(Err(TransactionStreamProcessError::ParsingError(
+                            err.to_string(),
+                        )));
+                    }
+                };
+                self.parsing.push_back(self.spawn_parser(chunk));
+            }
+            let parsed = match self.parsing.pop_front()?.await {
+                Ok(parsed) => parsed,
+                Err(err) => {
+                    return Some(Err(TransactionStreamProcessError::InternalError(
+                        err.to_string(),
+                    )))
+                }
+            };
+            self.parsed = parsed.into_iter();
+        }
+    }
+}
+
+impl<R: Read> ParallelCsvSource<R> {
+    /// Reads the headers of the input, if it has any, the layout of the
+    /// records following from them.
+    pub(crate) fn new(
+        r: R,
+        csv_options: &CsvOptions,
+        parsers: usize,
+    ) -> Result<Self, TransactionStreamProcessError> {
+        let mut chunker = Chunker::new(r);
+        let (headers, read_all) = match csv_options.has_headers {
+            true => match chunker
+                .first_record()
+                .map_err(|err| TransactionStreamProcessError::ParsingError(err.to_string()))?
+            {
+                Some(headers) => (headers.bytes, false),
+                None => (Vec::new(), true),
+            },
+            false => (Vec::new(), false),
+        };
+        let layout = TransactionRecordReader::new(headers.as_slice(), csv_options)?
+            .layout()
+            .clone();
+        Ok(Self {
+            chunker,
+            csv_options: csv_options.clone(),
+            layout,
+            quarantine: None,
+            client_quarantine: None,
+            amount_warnings: None,
+            shard: None,
+            parsers: parsers.max(1),
+            chunk_len: CHUNK_LEN,
+            parsing: VecDeque::new(),
+            parsed: Vec::new().into_iter(),
+            read_all,
+        })
+    }
+
+    pub(crate) fn with_quarantine(self, quarantine: Option<Quarantine>) -> Self {
+        Self { quarantine, ..self }
+    }
+
+    pub(crate) fn with_client_quarantine(self, client_quarantine: Option<Quarantine>) -> Self {
+        Self {
+            client_quarantine,
+            ..self
+        }
+    }
+
+    pub(crate) fn with_amount_warnings(self, amount_warnings: Option<AmountWarnings>) -> Self {
+        Self {
+            amount_warnings,
+            ..self
+        }
+    }
+
+    pub(crate) fn with_shard(self, shard: Option<Shard>) -> Self {
+        Self { shard, ..self }
+    }
+
+    pub(crate) fn with_chunk_len(self, chunk_len: usize) -> Self {
+        Self { chunk_len, ..self }
+    }
+
+    fn spawn_parser(&self, chunk: Chunk) -> JoinHandle<Parsed> {
+        let csv_options = self.csv_options.clone();
+        let layout = self.layout.clone();
+        let quarantine = self.quarantine.clone();
+        let client_quarantine = self.client_quarantine.clone();
+        let amount_warnings = self.amount_warnings.clone();
+        let shard = self.shard;
+        spawn_blocking(move || {
+            parse(
+                chunk,
+                &csv_options,
+                layout,
+                quarantine,
+                client_quarantine,
+                amount_warnings,
+                shard,
+            )
+        })
+    }
+}
+
+/// The transactions of a chunk, up to the first of its records that cannot
+/// be read.
+fn parse(
+    chunk: Chunk,
+    csv_options: &CsvOptions,
+    layout: Layout,
+    quarantine: Option<Quarantine>,
+    client_quarantine: Option<Quarantine>,
+    amount_warnings: Option<AmountWarnings>,
+    shard: Option<Shard>,
+) -> Parsed {
+    let mut transactions = Vec::new();
+    for result in TransactionRecordReader::resuming(
+        chunk.bytes.as_slice(),
+        csv_options,
+        layout,
+        chunk.line_offset,
+    )
+    .with_quarantine(quarantine)
+    .with_client_quarantine(client_quarantine)
+    .with_amount_warnings(amount_warnings)
+    .with_shard(shard)
+    .transactions()
+    {
+        let failed = result.is_err();
+        transactions.push(result);
+        if failed {
+            break;
+        }
+    }
+    transactions
+}
@@ -0,0 +1,196 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use tokio::{
+    sync::mpsc::{channel, Sender},
+    task::JoinHandle,
+};
+
+use crate::{
+    model::Transaction,
+    transaction_processor::TransactionProcessorError,
+    transaction_stream_processor::{InFlight, TransactionStreamProcessError},
+};
+
+#[cfg(doc)]
+use super::DEFAULT_CHANNEL_CAPACITY;
+use super::{Queued, Router, TransactionSink};
+
+/// Routes the transactions to a fixed number of workers, each client being
+/// given to one of them, for inputs with more clients than are worth a task
+/// each. The transactions of a client are processed one after another, and
+/// those of the clients sharing a worker in the order they are routed in.
+pub struct WorkerPoolRouter {
+    /// None once shut down.
+    senders: Mutex<Option<Vec<Sender<Queued>>>>,
+    handles: Mutex<Vec<JoinHandle<Result<(), TransactionProcessorError>>>>,
+}
+
+#[async_trait]
+impl Router for WorkerPoolRouter {
+    async fn route(
+        &self,
+        transaction: Transaction,
+        in_flight: Option<InFlight>,
+    ) -> Result<(), TransactionStreamProcessError> {
+        // cloned so that the lock is not held while waiting for room in the
+        // channel
+        let sender = self
+            .senders
+            .lock()
+            .expect("The senders are never locked across a panic.")
+            .as_ref()
+            .map(|senders| senders[usize::from(transaction.client_id) % senders.len()].clone())
+            .ok_or_else(|| {
+                TransactionStreamProcessError::InternalError(
+                    "The workers are shut down.".to_string(),
+                )
+            })?;
+        sender
+            .send((transaction, in_flight))
+            .await
+            .map_err(|err| TransactionStreamProcessError::InternalError(err.to_string()))
+    }
+
+    async fn shutdown(&self) -> Result<(), TransactionStreamProcessError> {
+        drop(
+            self.senders
+                .lock()
+                .expect("The senders are never locked across a panic.")
+                .take(),
+        );
+        let handles: Vec<_> = self
+            .handles
+            .lock()
+            .expect("The handles are never locked across a panic.")
+            .drain(..)
+            .collect();
+        let mut result = Ok(());
+        for handle in handles {
+            let joined = match handle.await {
+                Ok(processed) => processed.map_err(TransactionStreamProcessError::ProcessError),
+                Err(err) => Err(TransactionStreamProcessError::FailedToShutdown(
+                    err.to_string(),
+                )),
+            };
+            // every worker is waited for, the first failure being kept
+            result = result.and(joined);
+        }
+        result
+    }
+}
+
+impl WorkerPoolRouter {
+    /// Spawns the workers, at least one of them, routing waiting for a
+    /// worker's transactions to be processed once `channel_capacity` of them
+    /// are waiting, e.g. [`DEFAULT_CHANNEL_CAPACITY`].
+    pub fn new(sink: Arc<dyn TransactionSink>, workers: usize, channel_capacity: usize) -> Self {
+        let (senders, handles) = (0..workers.max(1))
+            .map(|_| {
+                let (sender, mut receiver) = channel::<Queued>(channel_capacity);
+                let sink = sink.clone();
+                let handle = tokio::spawn(async move {
+                    // those left once failed are dropped along with the
+                    // receiver, their guards with them
+                    while let Some((transaction, _in_flight)) = receiver.recv().await {
+                        sink.take(transaction).await?;
+                    }
+                    Ok(())
+                });
+                (sender, handle)
+            })
+            .unzip();
+        Self {
+            senders: Mutex::new(Some(senders)),
+            handles: Mutex::new(handles),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+    use tokio::time::{sleep, timeout};
+
+    use crate::{
+        account::AccountStore,
+        model::{Amount, Transaction, TransactionKind},
+        transaction_processor::{
+            OutOfOrder, RecordSink, SequenceChecker, TransactionProcessorError,
+        },
+        transaction_stream_processor::{
+            pipeline::{Pipeline, ProcessingSink, TransactionSink},
+            MemoryBudget,
+        },
+    };
+
+    use super::WorkerPoolRouter;
+
+    fn deposit(client_id: u16, transaction_id: u32) -> Transaction {
+        Transaction {
+            client_id,
+            transaction_id,
+            kind: TransactionKind::Deposit {
+                amount: Amount::new(10_000),
+            },
+            timestamp: None,
+            memo: None,
+            ingest_sequence: Some(u64::from(transaction_id) + 1),
+        }
+    }
+
+    /// Fails once the transactions up to the first budget check are queued.
+    struct FailingSink;
+
+    #[async_trait]
+    impl TransactionSink for FailingSink {
+        async fn take(&self, transaction: Transaction) -> Result<(), TransactionProcessorError> {
+            sleep(Duration::from_millis(50)).await;
+            Err(TransactionProcessorError::Unavailable(
+                Box::new(transaction),
+                "down".to_string(),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn transactions_left_to_a_failed_worker_are_no_longer_in_flight() {
+        let pipeline = Pipeline::new(Arc::new(WorkerPoolRouter::new(
+            Arc::new(FailingSink),
+            1,
+            10_000,
+        )))
+        .with_memory_budget(Some(MemoryBudget::new(0, Arc::new(AccountStore::new()))));
+        let transactions = (0..5_000u32).map(|transaction_id| Ok(deposit(1, transaction_id)));
+        let run = timeout(Duration::from_secs(10), pipeline.run(transactions))
+            .await
+            .expect("Throttling does not wait for the transactions dropped.");
+        assert!(run.is_err());
+        assert!(pipeline.shutdown().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn transactions_of_each_client_keep_their_order_across_the_pool() {
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let violations = Arc::new(Mutex::new(Vec::new()));
+        let sink = ProcessingSink::new(Arc::new(SequenceChecker::new(
+            Arc::new(RecordSink {
+                records: records.clone(),
+            }),
+            OutOfOrder::Report(violations.clone()),
+        )));
+        let pipeline = Pipeline::new(Arc::new(WorkerPoolRouter::new(Arc::new(sink), 3, 1)));
+        let transactions = (0..1_000u32)
+            .map(|transaction_id| Ok(deposit((transaction_id % 11) as u16, transaction_id)));
+        pipeline.run(transactions).await.unwrap();
+        pipeline.shutdown().await.unwrap();
+
+        assert_eq!(*violations.lock().unwrap(), vec![]);
+        assert_eq!(records.lock().unwrap().len(), 1_000);
+    }
+}
@@ -0,0 +1,85 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use csv::{StringRecord, WriterBuilder};
+
+use super::TransactionStreamProcessError;
+
+/// Collects the rows of the input whose `type` is not one the engine knows
+/// of, or whose client id is out of range, so that the rest of the input can
+/// be processed and those rows looked into later.
+/// The rows are kept as they were read, under the headers of the input they
+/// came from, if it had any.
+#[derive(Debug, Clone, Default)]
+pub struct Quarantine(Arc<Mutex<QuarantinedRows>>);
+
+#[derive(Debug, Default)]
+struct QuarantinedRows {
+    headers: Option<StringRecord>,
+    rows: Vec<StringRecord>,
+    counts: BTreeMap<String, usize>,
+    out_of_range_clients: usize,
+}
+
+impl Quarantine {
+    pub(super) fn add(&self, headers: Option<&StringRecord>, row: &StringRecord, txn_type: &str) {
+        *self
+            .push(headers, row)
+            .counts
+            .entry(txn_type.to_string())
+            .or_default() += 1;
+    }
+
+    pub(super) fn add_out_of_range_client(
+        &self,
+        headers: Option<&StringRecord>,
+        row: &StringRecord,
+    ) {
+        self.push(headers, row).out_of_range_clients += 1;
+    }
+
+    /// The number of rows quarantined for their unknown type, by type.
+    pub fn counts(&self) -> BTreeMap<String, usize> {
+        self.lock().counts.clone()
+    }
+
+    /// The number of rows quarantined for their client id being out of
+    /// range.
+    pub fn out_of_range_clients(&self) -> usize {
+        self.lock().out_of_range_clients
+    }
+
+    /// Writes the rows quarantined as CSV, under the headers of the first
+    /// input that had any quarantined.
+    pub fn write(&self) -> Result<Vec<u8>, TransactionStreamProcessError> {
+        let quarantined = self.lock();
+        let mut wtr = WriterBuilder::new().flexible(true).from_writer(vec![]);
+        for row in quarantined.headers.iter().chain(&quarantined.rows) {
+            wtr.write_record(row)
+                .map_err(|err| TransactionStreamProcessError::InternalError(err.to_string()))?;
+        }
+        wtr.into_inner()
+            .map_err(|err| TransactionStreamProcessError::InternalError(err.to_string()))
+    }
+
+    fn push(
+        &self,
+        headers: Option<&StringRecord>,
+        row: &StringRecord,
+    ) -> MutexGuard<'_, QuarantinedRows> {
+        let mut quarantined = self.lock();
+        if quarantined.headers.is_none() {
+            quarantined.headers = headers.cloned();
+        }
+        quarantined.rows.push(row.clone());
+        quarantined
+    }
+
+    fn lock(&self) -> MutexGuard<'_, QuarantinedRows> {
+        self.0
+            .lock()
+            .expect("The quarantine is never locked across a panic.")
+    }
+}
@@ -0,0 +1,167 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Caps how many records are read per second, e.g. so that a batch run on a
+/// shared host does not starve the services next to it, and measures how
+/// many are, see [`RateLimiter::throughput`].
+/// The records are given evenly spaced slots, a record read late not making
+/// room for a burst of them: the input is read at the pace of the limit at
+/// best, never above it over any second.
+/// Clones share their limit, so that the records of the ranges or parsers
+/// of a single run are counted together.
+#[derive(Debug, Clone)]
+pub struct RateLimiter(Arc<Inner>);
+
+#[derive(Debug)]
+struct Inner {
+    /// The time between two records, none without a limit.
+    interval: Option<Duration>,
+    started: Instant,
+    /// When the next record can be read, in nanoseconds since `started`.
+    next_slot: AtomicU64,
+    read: AtomicU64,
+    last_sample: Mutex<Sample>,
+}
+
+/// The records read at a point in time, for the throughput since then.
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    at: Instant,
+    read: u64,
+    records_per_second: f64,
+}
+
+/// How many records were read, and at what pace lately.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Throughput {
+    pub records: u64,
+    pub records_per_second: f64,
+}
+
+/// The shortest time the throughput is measured over, so that it does not
+/// swing from one record to the next.
+const MIN_SAMPLE_PERIOD: Duration = Duration::from_secs(1);
+
+impl RateLimiter {
+    pub fn new(records_per_second: u32) -> Self {
+        Self::with_interval(Some(Duration::from_secs(1) / records_per_second.max(1)))
+    }
+
+    /// Only measures the throughput.
+    pub fn unlimited() -> Self {
+        Self::with_interval(None)
+    }
+
+    fn with_interval(interval: Option<Duration>) -> Self {
+        let started = Instant::now();
+        Self(Arc::new(Inner {
+            interval,
+            started,
+            next_slot: AtomicU64::new(0),
+            read: AtomicU64::new(0),
+            last_sample: Mutex::new(Sample {
+                at: started,
+                read: 0,
+                records_per_second: 0.0,
+            }),
+        }))
+    }
+
+    /// Takes the next slot for a record, and gives how long to wait for it.
+    pub(super) fn reserve(&self) -> Duration {
+        self.0.read.fetch_add(1, Ordering::Relaxed);
+        let interval = match self.0.interval {
+            Some(interval) => interval.as_nanos() as u64,
+            None => return Duration::ZERO,
+        };
+        let now = self.0.started.elapsed().as_nanos() as u64;
+        let slot = self
+            .0
+            .next_slot
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |next| {
+                Some(next.max(now) + interval)
+            })
+            .expect("The next slot is always updated.")
+            .max(now);
+        Duration::from_nanos(slot - now)
+    }
+
+    /// Waits for the slot of the next record.
+    pub(super) async fn admit(&self) {
+        let wait = self.reserve();
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Waits for the slot of the next record, blocking the thread, e.g. that
+    /// of a parser.
+    pub(super) fn admit_blocking(&self) {
+        let wait = self.reserve();
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+    }
+
+    /// The records read so far, and how many of them were per second since
+    /// the previous call at least a second ago, or since the start.
+    pub fn throughput(&self) -> Throughput {
+        let records = self.0.read.load(Ordering::Relaxed);
+        let mut last_sample = self
+            .0
+            .last_sample
+            .lock()
+            .expect("The throughput is never locked across a panic.");
+        let elapsed = last_sample.at.elapsed();
+        if elapsed >= MIN_SAMPLE_PERIOD {
+            *last_sample = Sample {
+                at: Instant::now(),
+                read: records,
+                records_per_second: (records - last_sample.read) as f64 / elapsed.as_secs_f64(),
+            };
+        }
+        Throughput {
+            records,
+            records_per_second: last_sample.records_per_second,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use rstest::rstest;
+
+    use super::RateLimiter;
+
+    #[rstest]
+    #[case(RateLimiter::new(10), Duration::from_millis(400))]
+    #[case(RateLimiter::unlimited(), Duration::ZERO)]
+    fn records_are_given_evenly_spaced_slots(
+        #[case] limiter: RateLimiter,
+        #[case] fifth_wait: Duration,
+    ) {
+        let waits: Vec<Duration> = (0..5).map(|_| limiter.reserve()).collect();
+        // the slots are taken in quick succession, each a tenth of a second
+        // after the previous one
+        assert!(waits[4] <= fifth_wait);
+        assert!(waits[4] + Duration::from_millis(50) >= fifth_wait);
+        assert_eq!(limiter.throughput().records, 5);
+    }
+
+    #[tokio::test]
+    async fn reading_is_held_to_the_limit() {
+        let limiter = RateLimiter::new(100);
+        let started = Instant::now();
+        for _ in 0..21 {
+            limiter.admit().await;
+        }
+        assert!(started.elapsed() >= Duration::from_millis(200));
+    }
+}
@@ -0,0 +1,109 @@
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::model::ClientId;
+
+/// The clients a single instance processes when several of them split one
+/// input, the rows of the other clients being skipped as they are read.
+/// As each client goes to a single shard, the summaries of the instances can
+/// simply be concatenated.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Shard {
+    /// The `index`th of `count` shards, from 1, going by the client id
+    /// modulo `count`, e.g. `3/8`.
+    Hash { index: u16, count: u16 },
+
+    /// The clients from `first` to `last` included, e.g. `100-199`.
+    Range { first: ClientId, last: ClientId },
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ShardError {
+    #[error("Invalid shard `{0}`, expected e.g. `3/8` or `100-199`")]
+    InvalidShard(String),
+}
+
+impl Shard {
+    pub fn contains(&self, client_id: ClientId) -> bool {
+        match *self {
+            Shard::Hash { index, count } => client_id % count == index - 1,
+            Shard::Range { first, last } => (first..=last).contains(&client_id),
+        }
+    }
+}
+
+impl FromStr for Shard {
+    type Err = ShardError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ShardError::InvalidShard(s.to_string());
+        if let Some((index, count)) = s.split_once('/') {
+            let index: u16 = index.trim().parse().map_err(|_| invalid())?;
+            let count: u16 = count.trim().parse().map_err(|_| invalid())?;
+            if index == 0 || index > count {
+                return Err(invalid());
+            }
+            return Ok(Shard::Hash { index, count });
+        }
+        let (first, last) = s.split_once('-').ok_or_else(invalid)?;
+        let first = first.trim().parse().map_err(|_| invalid())?;
+        let last = last.trim().parse().map_err(|_| invalid())?;
+        if first > last {
+            return Err(invalid());
+        }
+        Ok(Shard::Range { first, last })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::{Shard, ShardError};
+
+    #[rustfmt::skip]
+    #[rstest]
+    #[case("3/8",     Ok(Shard::Hash { index: 3, count: 8 }))]
+    #[case("1/1",     Ok(Shard::Hash { index: 1, count: 1 }))]
+    #[case("100-199", Ok(Shard::Range { first: 100, last: 199 }))]
+    #[case("0/8",     Err(ShardError::InvalidShard("0/8".to_string())))]
+    #[case("9/8",     Err(ShardError::InvalidShard("9/8".to_string())))]
+    #[case("199-100", Err(ShardError::InvalidShard("199-100".to_string())))]
+    #[case("3",       Err(ShardError::InvalidShard("3".to_string())))]
+    fn shards_are_parsed(#[case] s: &str, #[case] expected: Result<Shard, ShardError>) {
+        assert_eq!(s.parse::<Shard>(), expected);
+    }
+
+    #[test]
+    fn every_client_is_in_exactly_one_hash_shard() {
+        let shards: Vec<Shard> = (1..=8)
+            .map(|index| Shard::Hash { index, count: 8 })
+            .collect();
+        for client_id in 0..=1_000 {
+            assert_eq!(
+                shards
+                    .iter()
+                    .filter(|shard| shard.contains(client_id))
+                    .count(),
+                1
+            );
+        }
+    }
+
+    #[rstest]
+    #[case(99, false)]
+    #[case(100, true)]
+    #[case(199, true)]
+    #[case(200, false)]
+    fn range_shards_include_both_ends(#[case] client_id: u16, #[case] expected: bool) {
+        assert_eq!(
+            Shard::Range {
+                first: 100,
+                last: 199
+            }
+            .contains(client_id),
+            expected
+        );
+    }
+}
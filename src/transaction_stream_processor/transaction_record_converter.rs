@@ -1,88 +1,180 @@
-use crate::model::{Amount, Transaction, TransactionKind};
+use std::{collections::HashMap, str::FromStr};
 
-use super::{TransactionRecord, TransactionRecordType, TransactionStreamProcessError};
+use serde::Deserialize;
 
-// TODO:
-// This whole function could have been avoided if the deserialsation can be
-// implemented directly on top of `Transaction` instead of going through
-// `TransactionRecord`.
+use crate::model::{
+    Amount, ClientId, Memo, Timestamp, Transaction, TransactionId, TransactionKind,
+};
+
+#[cfg(any(test, feature = "server", feature = "avro", feature = "parquet"))]
+use super::TransactionRecord;
+use super::{
+    amount_rules::AmountCheck, AmountProfile, TransactionRecordType, TransactionStreamProcessError,
+};
+
+/// The fields of a record of either format, with its strings borrowed rather
+/// than owned, so that a CSV row is turned into a [`Transaction`] without
+/// allocating them.
+/// The columns a format does not have are `None`.
+#[derive(Debug, Deserialize)]
+pub(super) struct RecordFields<'a> {
+    #[serde(rename = "type")]
+    pub(super) txn_type: TransactionRecordType,
+    #[serde(rename = "client")]
+    pub(super) client_id: ClientId,
+    #[serde(rename = "tx")]
+    pub(super) transaction_id: TransactionId,
+    #[serde(rename = "amount", borrow)]
+    pub(super) amount: Option<&'a str>,
+    #[serde(rename = "timestamp")]
+    pub(super) timestamp: Option<Timestamp>,
+    #[serde(rename = "memo", borrow)]
+    pub(super) memo: Option<&'a str>,
+    #[serde(rename = "metadata", borrow)]
+    pub(super) metadata: Option<&'a str>,
+}
+
+impl RecordFields<'_> {
+    /// The transaction of the record, whose amount is checked against the
+    /// rule of its type first, then read as per the profile, the line it
+    /// starts at, if known, being named by the errors and warnings.
+    pub(super) fn into_transaction(
+        self,
+        check: &AmountCheck,
+        profile: &AmountProfile,
+        line: Option<u64>,
+    ) -> Result<Transaction, TransactionStreamProcessError> {
+        let amount = match check.apply(&self.txn_type, self.amount, line)? {
+            Some(amount) => Some(Amount::from_str(&profile.normalise(amount)?)?),
+            None => None,
+        };
+        let amount = || amount.expect("The amount of a type requiring one is checked.");
+        let mut memo = self.memo.map(str::to_string);
+        let kind = match self.txn_type {
+            TransactionRecordType::Deposit => TransactionKind::Deposit { amount: amount() },
+            TransactionRecordType::Withdrawal => TransactionKind::Withdrawal { amount: amount() },
+            TransactionRecordType::Dispute => TransactionKind::Dispute,
+            TransactionRecordType::Resolve => TransactionKind::Resolve,
+            TransactionRecordType::Chargeback => TransactionKind::ChargeBack,
+            TransactionRecordType::Representment => TransactionKind::Representment,
+            TransactionRecordType::Close => TransactionKind::Close,
+            TransactionRecordType::Adjustment => TransactionKind::Adjustment {
+                amount: amount(),
+                // the memo of an adjustment is its reason
+                reason: memo.take().unwrap_or_default(),
+            },
+        };
+        let metadata = match self.metadata {
+            Some(metadata) => parse_metadata(metadata)?,
+            None => HashMap::new(),
+        };
+        Ok(Transaction {
+            client_id: self.client_id,
+            transaction_id: self.transaction_id,
+            kind,
+            timestamp: self.timestamp,
+            memo: (memo.is_some() || !metadata.is_empty()).then(|| {
+                Box::new(Memo {
+                    text: memo,
+                    metadata,
+                })
+            }),
+            ingest_sequence: None,
+        })
+    }
+}
+
+/// The [`Transaction`] of a record read by the formats that cannot borrow
+/// from their input, e.g. JSON, an amount given to a type that takes none
+/// being ignored.
+#[cfg(any(test, feature = "server", feature = "avro", feature = "parquet"))]
 pub(super) fn to_transaction(
-    record: TransactionRecord,
+    record: impl Into<TransactionRecord>,
 ) -> Result<Transaction, TransactionStreamProcessError> {
-    let TransactionRecord {
-        txn_type,
-        client_id,
-        transaction_id,
-        optional_amount,
-    } = record;
-    let transaction = match txn_type {
-        TransactionRecordType::Deposit => Transaction {
-            client_id,
-            transaction_id,
-            kind: TransactionKind::Deposit {
-                amount: match optional_amount {
-                    Some(amount) => Amount::from_str(&amount)?,
-                    None => {
-                        return Err(TransactionStreamProcessError::ParsingError(
-                            "Amount not found for deposit.".to_string(),
-                        ))
-                    }
-                },
-            },
-        },
-        TransactionRecordType::Withdrawal => Transaction {
-            client_id,
-            transaction_id,
-            kind: TransactionKind::Withdrawal {
-                amount: match optional_amount {
-                    Some(amount) => Amount::from_str(&amount)?,
-                    None => {
-                        return Err(TransactionStreamProcessError::ParsingError(
-                            "Amount not found for withdrawal.".to_string(),
-                        ))
-                    }
-                },
-            },
-        },
-        TransactionRecordType::Dispute => Transaction {
-            client_id,
-            transaction_id,
-            kind: TransactionKind::Dispute,
-        },
-        TransactionRecordType::Resolve => Transaction {
-            client_id,
-            transaction_id,
-            kind: TransactionKind::Resolve,
-        },
-        TransactionRecordType::Chargeback => Transaction {
-            client_id,
-            transaction_id,
-            kind: TransactionKind::ChargeBack,
-        },
-    };
-    Ok(transaction)
+    match record.into() {
+        TransactionRecord::V1(record) => RecordFields {
+            txn_type: record.txn_type,
+            client_id: record.client_id,
+            transaction_id: record.transaction_id,
+            amount: record.optional_amount.as_deref(),
+            timestamp: None,
+            memo: None,
+            metadata: None,
+        }
+        .into_transaction(&AmountCheck::default(), &AmountProfile::default(), None),
+        TransactionRecord::V2(record) => RecordFields {
+            txn_type: record.txn_type,
+            client_id: record.client_id,
+            transaction_id: record.transaction_id,
+            amount: record.optional_amount.as_deref(),
+            timestamp: record.optional_timestamp,
+            memo: record.optional_memo.as_deref(),
+            metadata: record.optional_metadata.as_deref(),
+        }
+        .into_transaction(&AmountCheck::default(), &AmountProfile::default(), None),
+    }
+}
+
+/// Parses `key=value` pairs separated by `;`, e.g. `batch=7;source=sftp`.
+fn parse_metadata(
+    metadata: &str,
+) -> Result<HashMap<String, String>, TransactionStreamProcessError> {
+    metadata
+        .split(';')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => {
+                Ok((key.trim().to_string(), value.trim().to_string()))
+            }
+            _ => Err(TransactionStreamProcessError::ParsingError(format!(
+                "Invalid metadata `{metadata}`, expected e.g. `batch=7;source=sftp`."
+            ))),
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
+    use std::{collections::HashMap, str::FromStr};
+
+    use assert_matches::assert_matches;
     use rstest::rstest;
 
     use crate::transaction_stream_processor::transaction_record_converter::to_transaction;
 
-    use crate::model::{Amount, ClientId, Transaction, TransactionId, TransactionKind};
+    use crate::model::{
+        Amount, ClientId, Memo, Timestamp, Transaction, TransactionId, TransactionKind,
+    };
 
-    use super::{TransactionRecord, TransactionRecordType};
+    use crate::transaction_stream_processor::{
+        amount_rules::AmountCheck, transaction_record_converter::RecordFields, AmountProfile,
+        TransactionRecord, TransactionRecordType, TransactionRecordV1, TransactionRecordV2,
+        TransactionStreamProcessError,
+    };
 
     const CLIENT_ID: ClientId = 1234;
     const TRANSACTION_ID: TransactionId = 5678;
-    const AMOUNT: &'static str = "0.9";
+    const AMOUNT: &str = "0.9";
+    const TIMESTAMP: Timestamp = 1_700_000_000;
 
     #[rstest]
-    #[case(deposit_record(Some(AMOUNT)), deposit_transaction(AMOUNT))]
-    #[case(withdrawal_record(Some(AMOUNT)), withdrawal_transaction(AMOUNT))]
-    #[case(dispute_record(None), dispute_transaction())]
-    #[case(resolve_record(None), resolve_transaction())]
-    #[case(chargeback_record(None), chargeback_transaction())]
+    #[case(deposit_record(Some(AMOUNT)).into(), deposit_transaction(AMOUNT))]
+    #[case(withdrawal_record(Some(AMOUNT)).into(), withdrawal_transaction(AMOUNT))]
+    #[case(dispute_record(None).into(), dispute_transaction())]
+    #[case(resolve_record(None).into(), resolve_transaction())]
+    #[case(chargeback_record(None).into(), chargeback_transaction())]
+    #[case(representment_record(None).into(), representment_transaction())]
+    #[case(transaction_record(TransactionRecordType::Close, None).into(), transaction(TransactionKind::Close))]
+    #[case(v2_record(TransactionRecordType::Deposit, Some(AMOUNT)).into(), noted(at(deposit_transaction(AMOUNT)), &[]))]
+    #[case(v2_record(TransactionRecordType::Withdrawal, Some(AMOUNT)).into(), noted(at(withdrawal_transaction(AMOUNT)), &[]))]
+    #[case(v2_record(TransactionRecordType::Dispute, None).into(), noted(at(dispute_transaction()), &[]))]
+    #[case(transaction_record(TransactionRecordType::Adjustment, Some("-0.9")).into(), adjustment_transaction("-0.9", ""))]
+    #[case(v2_record(TransactionRecordType::Adjustment, Some(AMOUNT)).into(), at(adjustment_transaction(AMOUNT, "invoice 42")))]
+    #[case(with_metadata(v2_record(TransactionRecordType::Deposit, Some(AMOUNT)), " batch=7; source = sftp;").into(),
+        noted(at(deposit_transaction(AMOUNT)), &[("batch", "7"), ("source", "sftp")]))]
+    #[case(with_metadata(v2_record(TransactionRecordType::Adjustment, Some(AMOUNT)), "batch=7").into(),
+        Transaction { memo: Some(Box::new(Memo { text: None, metadata: metadata(&[("batch", "7")]) })), ..at(adjustment_transaction(AMOUNT, "invoice 42")) })]
     fn conversion_from_transaction_record_to_transaction_works(
         #[case] transaction_record: TransactionRecord,
         #[case] expected: Transaction,
@@ -90,15 +182,73 @@ mod tests {
         assert_eq!(to_transaction(transaction_record).unwrap(), expected);
     }
 
+    fn deposit_of(
+        amount: &str,
+        profile: &AmountProfile,
+    ) -> Result<Transaction, TransactionStreamProcessError> {
+        RecordFields {
+            txn_type: TransactionRecordType::Deposit,
+            client_id: CLIENT_ID,
+            transaction_id: TRANSACTION_ID,
+            amount: Some(amount),
+            timestamp: None,
+            memo: None,
+            metadata: None,
+        }
+        .into_transaction(&AmountCheck::default(), profile, None)
+    }
+
+    #[rstest]
+    #[case(AmountProfile::default(), "0.0003", 3)]
+    #[case(AmountProfile::default(), "-1.0001", -10_001)]
+    #[case(AmountProfile { scientific_notation: true, ..AmountProfile::default() }, "1e-4", 1)]
+    #[case(AmountProfile { scientific_notation: true, ..AmountProfile::default() }, "3E-4", 3)]
+    #[case(AmountProfile { decimal_separator: ',', ..AmountProfile::default() }, "1,0003", 10_003)]
+    fn amounts_are_read_digit_for_digit(
+        #[case] profile: AmountProfile,
+        #[case] amount: &str,
+        #[case] ten_thousandths: i64,
+    ) {
+        assert_eq!(
+            deposit_of(amount, &profile).unwrap().kind,
+            TransactionKind::Deposit {
+                amount: Amount::new(ten_thousandths)
+            }
+        );
+    }
+
+    /// Unlike before the amount profiles, which read such amounts as floats.
+    #[test]
+    fn scientific_notation_is_rejected_by_default() {
+        assert_matches!(
+            deposit_of("1e3", &AmountProfile::default()),
+            Err(TransactionStreamProcessError::ParsingError(_))
+        );
+    }
+
+    #[rstest]
+    #[case("batch")]
+    #[case("=7")]
+    #[case("batch=7;source")]
+    fn malformed_metadata_is_rejected(#[case] metadata: &str) {
+        assert_matches!(
+            to_transaction(with_metadata(
+                v2_record(TransactionRecordType::Deposit, Some(AMOUNT)),
+                metadata
+            )),
+            Err(TransactionStreamProcessError::ParsingError(_))
+        );
+    }
+
     fn deposit_transaction(amount: &str) -> Transaction {
         transaction(TransactionKind::Deposit {
-            amount: Amount::from_str(&amount).unwrap(),
+            amount: Amount::from_str(amount).unwrap(),
         })
     }
 
     fn withdrawal_transaction(amount: &str) -> Transaction {
         transaction(TransactionKind::Withdrawal {
-            amount: Amount::from_str(&amount).unwrap(),
+            amount: Amount::from_str(amount).unwrap(),
         })
     }
 
@@ -114,43 +264,109 @@ mod tests {
         transaction(TransactionKind::ChargeBack {})
     }
 
+    fn representment_transaction() -> Transaction {
+        transaction(TransactionKind::Representment {})
+    }
+
+    fn adjustment_transaction(amount: &str, reason: &str) -> Transaction {
+        transaction(TransactionKind::Adjustment {
+            amount: Amount::from_str(amount).unwrap(),
+            reason: reason.to_string(),
+        })
+    }
+
     fn transaction(kind: TransactionKind) -> Transaction {
         Transaction {
             client_id: CLIENT_ID,
             transaction_id: TRANSACTION_ID,
             kind,
+            timestamp: None,
+            memo: None,
+            ingest_sequence: None,
+        }
+    }
+
+    fn at(transaction: Transaction) -> Transaction {
+        Transaction {
+            timestamp: Some(TIMESTAMP),
+            ..transaction
+        }
+    }
+
+    /// With the memo of [`v2_record`] and the given metadata.
+    fn noted(transaction: Transaction, pairs: &[(&str, &str)]) -> Transaction {
+        Transaction {
+            memo: Some(Box::new(Memo {
+                text: Some("invoice 42".to_string()),
+                metadata: metadata(pairs),
+            })),
+            ..transaction
         }
     }
 
-    fn deposit_record(optional_amount: Option<&str>) -> TransactionRecord {
+    fn metadata(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn with_metadata(record: TransactionRecordV2, metadata: &str) -> TransactionRecordV2 {
+        TransactionRecordV2 {
+            optional_metadata: Some(metadata.to_string()),
+            ..record
+        }
+    }
+
+    fn deposit_record(optional_amount: Option<&str>) -> TransactionRecordV1 {
         transaction_record(TransactionRecordType::Deposit, optional_amount)
     }
 
-    fn withdrawal_record(optional_amount: Option<&str>) -> TransactionRecord {
+    fn withdrawal_record(optional_amount: Option<&str>) -> TransactionRecordV1 {
         transaction_record(TransactionRecordType::Withdrawal, optional_amount)
     }
 
-    fn dispute_record(optional_amount: Option<&str>) -> TransactionRecord {
+    fn dispute_record(optional_amount: Option<&str>) -> TransactionRecordV1 {
         transaction_record(TransactionRecordType::Dispute, optional_amount)
     }
 
-    fn resolve_record(optional_amount: Option<&str>) -> TransactionRecord {
+    fn resolve_record(optional_amount: Option<&str>) -> TransactionRecordV1 {
         transaction_record(TransactionRecordType::Resolve, optional_amount)
     }
 
-    fn chargeback_record(optional_amount: Option<&str>) -> TransactionRecord {
+    fn chargeback_record(optional_amount: Option<&str>) -> TransactionRecordV1 {
         transaction_record(TransactionRecordType::Chargeback, optional_amount)
     }
 
+    fn representment_record(optional_amount: Option<&str>) -> TransactionRecordV1 {
+        transaction_record(TransactionRecordType::Representment, optional_amount)
+    }
+
     fn transaction_record(
         txn_type: TransactionRecordType,
         optional_amount: Option<&str>,
-    ) -> TransactionRecord {
-        TransactionRecord {
+    ) -> TransactionRecordV1 {
+        TransactionRecordV1 {
+            txn_type,
+            client_id: CLIENT_ID,
+            transaction_id: TRANSACTION_ID,
+            optional_amount: optional_amount.map(|s| s.to_string()),
+        }
+    }
+
+    fn v2_record(
+        txn_type: TransactionRecordType,
+        optional_amount: Option<&str>,
+    ) -> TransactionRecordV2 {
+        TransactionRecordV2 {
             txn_type,
             client_id: CLIENT_ID,
             transaction_id: TRANSACTION_ID,
             optional_amount: optional_amount.map(|s| s.to_string()),
+            optional_timestamp: Some(TIMESTAMP),
+            optional_currency: Some("EUR".to_string()),
+            optional_memo: Some("invoice 42".to_string()),
+            optional_metadata: None,
         }
     }
 }
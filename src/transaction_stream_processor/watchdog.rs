@@ -0,0 +1,180 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tokio::{
+    sync::broadcast,
+    task::JoinHandle,
+    time::{interval, Instant},
+};
+
+use crate::model::ClientId;
+
+/// The channel of a client holding transactions waiting to be processed.
+#[derive(Debug, PartialEq, Clone)]
+pub struct QueuedChannel {
+    pub client_id: ClientId,
+    pub queued: usize,
+    pub capacity: usize,
+}
+
+impl QueuedChannel {
+    /// Whether reading the input waits for room in the channel.
+    pub fn is_full(&self) -> bool {
+        self.queued >= self.capacity
+    }
+}
+
+/// A run in which no transaction was dispatched to a worker, nor processed by
+/// one, for a while, along with the channels holding transactions then, the
+/// fullest first.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Stall {
+    pub stalled_for: Duration,
+    pub channels: Vec<QueuedChannel>,
+}
+
+impl Display for Stall {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "No transaction dispatched or processed for {:?}",
+            self.stalled_for
+        )?;
+        if self.channels.is_empty() {
+            return write!(f, ", no channel holding any.");
+        }
+        write!(f, ", channels holding some:")?;
+        for channel in &self.channels {
+            write!(
+                f,
+                " client {} ({}/{}{})",
+                channel.client_id,
+                channel.queued,
+                channel.capacity,
+                if channel.is_full() { ", full" } else { "" }
+            )?;
+        }
+        write!(f, ".")
+    }
+}
+
+/// Reports a [`Stall`] when the transactions stop being dispatched to the
+/// workers and processed by them for the timeout, e.g. as a single full
+/// channel holds the reading of the input back while its worker is stuck.
+/// A stall is reported once, until the run moves on again.
+/// Clones share their progress, so that the router and the workers of a run
+/// report to the same watchdog.
+#[derive(Debug, Clone)]
+pub struct Watchdog {
+    stall_timeout: Duration,
+    progress: Arc<AtomicU64>,
+    stalls: broadcast::Sender<Stall>,
+}
+
+/// How many times per timeout the progress is checked.
+const CHECKS_PER_TIMEOUT: u32 = 4;
+
+impl Watchdog {
+    pub fn new(stall_timeout: Duration, stalls: broadcast::Sender<Stall>) -> Self {
+        Self {
+            stall_timeout,
+            progress: Arc::new(AtomicU64::new(0)),
+            stalls,
+        }
+    }
+
+    /// Tells the watchdog a transaction was dispatched or processed.
+    pub(super) fn progressed(&self) {
+        self.progress.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Watches the progress until the guard is dropped, going by the
+    /// channels to tell which of them hold transactions once stalled.
+    pub(super) fn watch(
+        &self,
+        channels: impl Fn() -> Vec<QueuedChannel> + Send + 'static,
+    ) -> WatchGuard {
+        let watchdog = self.clone();
+        WatchGuard(tokio::spawn(async move {
+            let mut checks = interval(
+                (watchdog.stall_timeout / CHECKS_PER_TIMEOUT).max(Duration::from_millis(1)),
+            );
+            let mut last_progress = watchdog.progress.load(Ordering::Relaxed);
+            let mut since = Instant::now();
+            let mut reported = false;
+            loop {
+                checks.tick().await;
+                let progress = watchdog.progress.load(Ordering::Relaxed);
+                if progress != last_progress {
+                    last_progress = progress;
+                    since = Instant::now();
+                    reported = false;
+                } else if !reported && since.elapsed() >= watchdog.stall_timeout {
+                    reported = true;
+                    let mut channels = channels();
+                    channels.sort_by(|a, b| {
+                        b.queued.cmp(&a.queued).then(a.client_id.cmp(&b.client_id))
+                    });
+                    // only fails when no one is listening
+                    let _ = watchdog.stalls.send(Stall {
+                        stalled_for: since.elapsed(),
+                        channels,
+                    });
+                }
+            }
+        }))
+    }
+}
+
+/// Stops the watching once dropped.
+pub(super) struct WatchGuard(JoinHandle<()>);
+
+impl Drop for WatchGuard {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::{sync::broadcast, time::sleep};
+
+    use super::{QueuedChannel, Stall, Watchdog};
+
+    #[tokio::test]
+    async fn a_stall_is_reported_once_until_the_run_moves_on() {
+        let (stalls, mut received) = broadcast::channel(4);
+        let watchdog = Watchdog::new(Duration::from_millis(20), stalls);
+        let queued = |client_id, queued| QueuedChannel {
+            client_id,
+            queued,
+            capacity: 2,
+        };
+        let _guard = watchdog.watch(move || vec![queued(1, 1), queued(2, 2)]);
+        let stall = received.recv().await.unwrap();
+        assert_eq!(stall.channels, vec![queued(2, 2), queued(1, 1)]);
+        assert!(stall.stalled_for >= Duration::from_millis(20));
+        assert_eq!(
+            Stall {
+                stalled_for: Duration::from_secs(5),
+                ..stall
+            }
+            .to_string(),
+            "No transaction dispatched or processed for 5s, channels holding some: \
+            client 2 (2/2, full) client 1 (1/2)."
+        );
+
+        sleep(Duration::from_millis(60)).await;
+        assert!(received.try_recv().is_err());
+        watchdog.progressed();
+        assert!(received.recv().await.is_ok());
+    }
+}
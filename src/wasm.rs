@@ -0,0 +1,199 @@
+//! The account state machine for the browser, built with the `wasm` feature,
+//! e.g. for a reconciliation tool to go through an input client-side, either
+//! whole with [`process_csv`] or one transaction at a time with a
+//! [`WasmEngine`].
+//! Unlike [`Engine::process`], a rejected transaction never stops the run:
+//! it is left out of the accounts and the caller is told why.
+//!
+//! [`Engine::process`]: crate::engine::Engine::process
+
+use csv::{ReaderBuilder, Trim};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use wasm_bindgen::prelude::{wasm_bindgen, JsError};
+
+use crate::{
+    account::{AccountBook, SuccessStatus},
+    model::{
+        AccountSummary, AccountSummaryCsvWriter, ClientId, Transaction, TransactionId,
+        TransactionKind,
+    },
+};
+
+#[derive(Debug, Error, PartialEq)]
+pub enum WasmError {
+    #[error("Failed to read the input: {0}")]
+    ParsingError(String),
+
+    #[error("Invalid transaction: {0}")]
+    InvalidTransaction(String),
+}
+
+/// The accounts of the clients, kept across the transactions applied.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct WasmEngine {
+    accounts: AccountBook,
+}
+
+/// What became of a transaction, written as JSON, e.g.
+/// `{"status":"rejected","reason":"...","account":{"client":1,...}}`.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct AppliedTransaction {
+    /// `transacted`, `duplicate`, `no_op` or `rejected`.
+    pub status: &'static str,
+    /// Why the transaction was rejected, if it was.
+    pub reason: Option<String>,
+    /// The account of the client once the transaction is applied.
+    pub account: AccountSummary,
+}
+
+#[derive(Deserialize)]
+struct Row {
+    #[serde(rename = "type")]
+    kind: String,
+    client: ClientId,
+    tx: TransactionId,
+    amount: Option<String>,
+}
+
+#[wasm_bindgen]
+impl WasmEngine {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies the transaction of type `kind`, e.g. `deposit`, the amount
+    /// being a string, e.g. `"1.5"`, and only given for a deposit or a
+    /// withdrawal. Returns an [`AppliedTransaction`] as JSON.
+    pub fn apply_transaction(
+        &mut self,
+        kind: &str,
+        client: ClientId,
+        tx: TransactionId,
+        amount: Option<String>,
+    ) -> Result<String, JsError> {
+        let applied = self.apply(transaction(kind, client, tx, amount.as_deref())?);
+        Ok(serde_json::to_string(&applied).expect("A summary is always serialisable."))
+    }
+
+    /// The summaries of the accounts as CSV, ordered by client.
+    pub fn summaries(&self) -> String {
+        let summaries = self.accounts.accounts().map(AccountSummary::from).collect();
+        String::from_utf8(
+            AccountSummaryCsvWriter::write(summaries).expect("A summary is always serialisable."),
+        )
+        .expect("CSV is always written as UTF-8.")
+    }
+}
+
+impl WasmEngine {
+    pub fn apply(&mut self, transaction: Transaction) -> AppliedTransaction {
+        let client_id = transaction.client_id;
+        let (status, reason) = match self.accounts.apply(transaction) {
+            Ok(SuccessStatus::Transacted) => ("transacted", None),
+            Ok(SuccessStatus::Duplicate) => ("duplicate", None),
+            Ok(SuccessStatus::NoOp) => ("no_op", None),
+            Err(err) => ("rejected", Some(err.to_string())),
+        };
+        AppliedTransaction {
+            status,
+            reason,
+            account: AccountSummary::from(
+                self.accounts
+                    .get(client_id)
+                    .expect("The account is created by the first transaction of the client."),
+            ),
+        }
+    }
+
+    /// Applies the transactions of a CSV input with the `type`, `client`,
+    /// `tx` and `amount` columns.
+    pub fn apply_csv(&mut self, input: &str) -> Result<(), WasmError> {
+        let mut rdr = ReaderBuilder::new()
+            .trim(Trim::All)
+            .flexible(true)
+            .from_reader(input.as_bytes());
+        for row in rdr.deserialize::<Row>() {
+            let row = row.map_err(|err| WasmError::ParsingError(err.to_string()))?;
+            self.apply(transaction(
+                &row.kind,
+                row.client,
+                row.tx,
+                row.amount.as_deref().filter(|amount| !amount.is_empty()),
+            )?);
+        }
+        Ok(())
+    }
+}
+
+/// The summaries, as CSV, of the accounts once the transactions of the CSV
+/// input are applied, see [`WasmEngine::apply_csv`].
+#[wasm_bindgen]
+pub fn process_csv(input: &str) -> Result<String, JsError> {
+    let mut engine = WasmEngine::new();
+    engine.apply_csv(input)?;
+    Ok(engine.summaries())
+}
+
+fn transaction(
+    kind: &str,
+    client_id: ClientId,
+    transaction_id: TransactionId,
+    amount: Option<&str>,
+) -> Result<Transaction, WasmError> {
+    Ok(Transaction {
+        client_id,
+        transaction_id,
+        kind: TransactionKind::from_name(kind, amount)
+            .map_err(|err| WasmError::InvalidTransaction(err.to_string()))?,
+        timestamp: None,
+        memo: None,
+        ingest_sequence: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::Amount;
+
+    use super::{transaction, WasmEngine, WasmError};
+
+    #[test]
+    fn rejected_transactions_are_left_out_and_reported() {
+        let mut engine = WasmEngine::new();
+        engine
+            .apply_csv(
+                "type, client, tx, amount
+                deposit, 1, 1, 2.0
+                withdrawal, 1, 2, 5.0
+                dispute, 1, 1,",
+            )
+            .unwrap();
+        let applied = engine.apply(transaction("withdrawal", 1, 3, Some("1.0")).unwrap());
+
+        assert_eq!(applied.status, "rejected");
+        assert_eq!(applied.account.held, Amount::new(20_000));
+        assert_eq!(
+            engine.summaries(),
+            "client,available,held,total,locked\n1,0.0000,2.0000,2.0000,false\n"
+        );
+    }
+
+    #[test]
+    fn invalid_transactions_are_refused() {
+        assert_eq!(
+            transaction("deposit", 1, 1, None),
+            Err(WasmError::InvalidTransaction(
+                "A deposit needs an amount".to_string()
+            ))
+        );
+        assert_eq!(
+            transaction("refund", 1, 1, None),
+            Err(WasmError::InvalidTransaction(
+                "Unknown transaction type `refund`".to_string()
+            ))
+        );
+    }
+}
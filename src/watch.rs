@@ -0,0 +1,222 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::BufReader,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use thiserror::Error;
+use tokio::time::sleep;
+
+use crate::{engine::Engine, transaction_stream_processor::TransactionStreamProcessError};
+
+/// How long the directory is left alone between two looks, unless told
+/// otherwise.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Error, PartialEq)]
+pub enum WatchError {
+    #[error("Failed to go through the directory: {0}")]
+    Io(String),
+}
+
+/// A file taken from the watched directory, and how processing it went.
+#[derive(Debug, PartialEq)]
+pub struct ProcessedFile {
+    /// Where the file is now, in the archive.
+    pub archived: PathBuf,
+    pub result: Result<(), TransactionStreamProcessError>,
+}
+
+/// Processes the transaction files put in a directory as they appear, into
+/// the accounts of one [`Engine`], and moves each of them to an archive once
+/// processed.
+/// The files are expected to be moved into the directory once written, e.g.
+/// written as `.name` and renamed, the files whose name starts with a `.`
+/// being left alone.
+pub struct DirectoryWatcher {
+    dir: PathBuf,
+    archive: PathBuf,
+    poll_interval: Duration,
+}
+
+impl DirectoryWatcher {
+    pub fn new(dir: impl Into<PathBuf>, archive: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            archive: archive.into(),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        }
+    }
+
+    pub fn with_poll_interval(self, poll_interval: Duration) -> Self {
+        Self {
+            poll_interval,
+            ..self
+        }
+    }
+
+    /// Processes the files in the directory, oldest first, and archives
+    /// them.
+    /// A file that cannot be processed to its end is archived all the same,
+    /// with `.failed` added to its name, what it held up to the failure
+    /// being kept in the accounts.
+    pub async fn poll(&self, engine: &Engine) -> Result<Vec<ProcessedFile>, WatchError> {
+        fs::create_dir_all(&self.archive).map_err(io_error)?;
+        let mut processed = Vec::new();
+        for path in self.pending().map_err(io_error)? {
+            let file = File::open(&path).map_err(io_error)?;
+            let result = engine.process(BufReader::new(file)).await;
+            let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+            if result.is_err() {
+                name.push(".failed");
+            }
+            let archived = self.free_archive_path(name);
+            fs::rename(&path, &archived).map_err(io_error)?;
+            processed.push(ProcessedFile { archived, result });
+        }
+        Ok(processed)
+    }
+
+    /// Polls the directory until the engine is cancelled, `refreshed` being
+    /// called after each poll that processed any file, e.g. to write the
+    /// summaries.
+    pub async fn run(
+        &self,
+        engine: &Engine,
+        mut refreshed: impl FnMut(&Engine, &[ProcessedFile]),
+    ) -> Result<(), WatchError> {
+        let cancellation = engine.cancellation();
+        while !cancellation.is_cancelled() {
+            let processed = self.poll(engine).await?;
+            if !processed.is_empty() {
+                refreshed(engine, &processed);
+            }
+            sleep(self.poll_interval).await;
+        }
+        Ok(())
+    }
+
+    /// The files waiting to be processed, oldest first.
+    fn pending(&self) -> std::io::Result<Vec<PathBuf>> {
+        let mut pending: Vec<(SystemTime, PathBuf)> = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            if metadata.is_file() && !hidden {
+                pending.push((metadata.modified()?, entry.path()));
+            }
+        }
+        pending.sort();
+        Ok(pending.into_iter().map(|(_, path)| path).collect())
+    }
+
+    /// The path in the archive for a file of the given name, suffixed with a
+    /// number if one of that name was archived before.
+    fn free_archive_path(&self, name: OsString) -> PathBuf {
+        let mut path = self.archive.join(&name);
+        let mut suffix = 1;
+        while path.exists() {
+            let mut numbered = name.clone();
+            numbered.push(format!(".{suffix}"));
+            path = self.archive.join(numbered);
+            suffix += 1;
+        }
+        path
+    }
+}
+
+fn io_error(err: std::io::Error) -> WatchError {
+    WatchError::Io(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf, process};
+
+    use assert_matches::assert_matches;
+
+    use crate::{
+        engine::{Engine, EngineConfig},
+        model::Amount,
+    };
+
+    use super::{DirectoryWatcher, ProcessedFile};
+
+    /// A directory of its own for each test, empty.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("jouet_paiement_{name}_{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn files_are_processed_into_the_same_accounts_and_archived() {
+        let dir = test_dir("watch_processed");
+        let archive = dir.join("archive");
+        fs::write(
+            dir.join("1.csv"),
+            "type, client, tx, amount\ndeposit, 1, 1, 2.0",
+        )
+        .unwrap();
+        fs::write(
+            dir.join(".2.csv"),
+            "type, client, tx, amount\ndeposit, 1, 2, 5.0",
+        )
+        .unwrap();
+        let watcher = DirectoryWatcher::new(&dir, &archive);
+        let engine = Engine::new(EngineConfig::default());
+
+        assert_eq!(
+            watcher.poll(&engine).await.unwrap(),
+            vec![ProcessedFile {
+                archived: archive.join("1.csv"),
+                result: Ok(()),
+            }]
+        );
+        // the second file being renamed into place once written
+        fs::rename(dir.join(".2.csv"), dir.join("2.csv")).unwrap();
+        fs::write(
+            dir.join("1.csv"),
+            "type, client, tx, amount\ndeposit, 1, 3, 1.0",
+        )
+        .unwrap();
+        let processed = watcher.poll(&engine).await.unwrap();
+        let mut archived: Vec<PathBuf> = processed.into_iter().map(|file| file.archived).collect();
+        archived.sort();
+        assert_eq!(
+            archived,
+            vec![archive.join("1.csv.1"), archive.join("2.csv")]
+        );
+        assert_eq!(watcher.poll(&engine).await.unwrap(), vec![]);
+        assert_eq!(
+            engine.accounts().get(1).unwrap().available(),
+            Amount::new(80_000)
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn files_failing_to_be_processed_are_archived_as_failed() {
+        let dir = test_dir("watch_failed");
+        let archive = dir.join("archive");
+        fs::write(
+            dir.join("1.csv"),
+            "type, client, tx, amount\ndeposit, 1, 1, one",
+        )
+        .unwrap();
+        let engine = Engine::new(EngineConfig::default());
+
+        let processed = DirectoryWatcher::new(&dir, &archive)
+            .poll(&engine)
+            .await
+            .unwrap();
+        assert_eq!(processed[0].archived, archive.join("1.csv.failed"));
+        assert_matches!(processed[0].result, Err(_));
+        assert!(!dir.join("1.csv").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -8,19 +8,23 @@ use assert_matches::assert_matches;
 use csv::WriterBuilder;
 use dashmap::DashMap;
 use jouet_paiement::{
-    account::SimpleAccountTransactor,
-    model::{AccountSummary, AccountSummaryCsvWriter},
+    account::{
+        Account, AccountStatus, AccountStore, Depositor, DepositorError, SimpleAccountTransactor,
+        SuccessStatus,
+    },
+    engine::{Engine, EngineConfig},
+    model::{AccountSummaryCsvWriter, Amount, Timestamp, TransactionId},
     transaction_processor::SimpleTransactionProcessor,
     transaction_stream_processor::{
         async_csv_stream_processor::AsyncCsvStreamProcessor,
-        csv_stream_processor::CsvStreamProcessor, TransactionRecord,
-        TransactionRecordType::Deposit, TransactionStreamProcessError, TransactionStreamProcessor,
+        csv_stream_processor::CsvStreamProcessor, TransactionRecordType::Deposit,
+        TransactionRecordV1, TransactionStreamProcessError, TransactionStreamProcessor,
     },
 };
 
 #[tokio::test]
 async fn e2e_small_input_using_async_processor() {
-    let accounts = Arc::new(DashMap::new());
+    let accounts = Arc::new(AccountStore::new());
 
     let processor = AsyncCsvStreamProcessor::new(
         Arc::new(SimpleTransactionProcessor::new(
@@ -36,8 +40,7 @@ async fn e2e_small_input_using_async_processor() {
     processor.process(reader).await.unwrap();
     processor.shutdown().await.unwrap();
 
-    let mut summaries: Vec<AccountSummary> =
-        accounts.iter().map(|entry| entry.value().into()).collect();
+    let mut summaries = accounts.summaries();
     summaries.sort_by(|a, b| {
         a.client_id
             .partial_cmp(&b.client_id)
@@ -54,7 +57,7 @@ async fn e2e_small_input_using_async_processor() {
 
 #[tokio::test]
 async fn e2e_small_input_with_transaction_process_error_using_async_processor() {
-    let accounts = Arc::new(DashMap::new());
+    let accounts = Arc::new(AccountStore::new());
 
     let processor = AsyncCsvStreamProcessor::new(
         Arc::new(SimpleTransactionProcessor::new(
@@ -73,8 +76,7 @@ async fn e2e_small_input_with_transaction_process_error_using_async_processor()
         Err(TransactionStreamProcessError::ProcessError(_))
     );
 
-    let mut summaries: Vec<AccountSummary> =
-        accounts.iter().map(|entry| entry.value().into()).collect();
+    let mut summaries = accounts.summaries();
     summaries.sort_by(|a, b| {
         a.client_id
             .partial_cmp(&b.client_id)
@@ -89,28 +91,98 @@ async fn e2e_small_input_with_transaction_process_error_using_async_processor()
     );
 }
 
+/// Holds the deposits until they clear, rather than making them available
+/// straight away.
+struct HoldingDepositor;
+
+impl Depositor for HoldingDepositor {
+    fn deposit(
+        &self,
+        account: &mut Account,
+        transaction_id: TransactionId,
+        amount: Amount,
+        timestamp: Option<Timestamp>,
+    ) -> Result<SuccessStatus, DepositorError> {
+        if account.deposit(transaction_id).is_some() {
+            return Ok(SuccessStatus::Duplicate);
+        }
+        if account.status() == AccountStatus::Locked {
+            return Err(DepositorError::AccountLocked);
+        }
+        account.snapshot_mut().held.0 += amount.0;
+        account.record_deposit(transaction_id, amount, timestamp);
+        Ok(SuccessStatus::Transacted)
+    }
+}
+
+#[tokio::test]
+async fn e2e_small_input_using_a_custom_depositor() {
+    let accounts = Arc::new(AccountStore::new());
+
+    let processor = CsvStreamProcessor::new(Box::new(SimpleTransactionProcessor::new(
+        accounts.clone(),
+        Box::new(SimpleAccountTransactor::new().with_depositor(Box::new(HoldingDepositor))),
+    )));
+
+    let file = File::open("tests/small_input.txt").unwrap();
+    processor.process(BufReader::new(file)).await.unwrap();
+
+    let mut summaries = accounts.summaries();
+    summaries.sort_by_key(|summary| summary.client_id);
+    assert_eq!(
+        String::from_utf8(AccountSummaryCsvWriter::write(summaries).unwrap()).unwrap(),
+        "\
+        client,available,held,total,locked\n\
+        1,0.0000,9.0000,9.0000,false\n\
+        2,0.0000,6.0000,6.0000,false\n"
+    );
+}
+
+#[tokio::test]
+async fn e2e_accounts_can_be_inspected() {
+    let engine = Engine::new(EngineConfig::default());
+    let input = "\
+    type,    client, tx, amount
+    deposit,      1, 10,    4.0
+    deposit,      1, 20,    5.0
+    dispute,      1, 20,
+    withdrawal,   1, 30,    1.0";
+    engine.process(input.as_bytes()).await.unwrap();
+
+    let account = engine.accounts().get(1).unwrap();
+    assert_eq!(account.status(), AccountStatus::Active);
+    assert_eq!(account.available(), Amount::new(30_000));
+    assert_eq!(account.held(), Amount::new(50_000));
+    assert_eq!(account.total(), Amount::new(80_000));
+    assert_eq!(account.deposit(10).unwrap().amount, Amount::new(40_000));
+    assert_eq!(
+        account
+            .held_deposits()
+            .map(|(transaction_id, _)| transaction_id)
+            .collect::<Vec<TransactionId>>(),
+        vec![20]
+    );
+    assert_eq!(account.deposits().count(), 2);
+    assert_eq!(account.withdrawals().count(), 1);
+    assert!(account.fees().is_empty());
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 16)]
 #[ignore = "this test takes time to run and should be enabled ondemand"]
 async fn e2e_large_input_using_async_processor() {
-    let accounts = Arc::new(DashMap::new());
-
-    let processor = AsyncCsvStreamProcessor::new(
-        Arc::new(SimpleTransactionProcessor::new(
-            accounts.clone(),
-            Box::new(SimpleAccountTransactor::new()),
-        )),
-        DashMap::new(),
-    );
+    let engine = Engine::new(EngineConfig {
+        expected_client_count: 10,
+        expected_transactions_per_client: 1_000_000,
+        ..Default::default()
+    });
 
     create_test_file("/tmp/large_input.txt", create_test_records(10, 1_000_000));
     let file = File::open("/tmp/large_input.txt").unwrap();
     let reader = BufReader::new(file);
 
-    processor.process(reader).await.unwrap();
-    processor.shutdown().await.unwrap();
+    engine.process(reader).await.unwrap();
 
-    let mut summaries: Vec<AccountSummary> =
-        accounts.iter().map(|entry| entry.value().into()).collect();
+    let mut summaries = engine.summaries();
     summaries.sort_by(|a, b| {
         a.client_id
             .partial_cmp(&b.client_id)
@@ -136,7 +208,7 @@ async fn e2e_large_input_using_async_processor() {
 #[tokio::test]
 #[ignore = "this test takes time to run and should be enabled ondemand"]
 async fn e2e_large_input_using_blocking_processor() {
-    let accounts = Arc::new(DashMap::new());
+    let accounts = Arc::new(AccountStore::new());
 
     let processor = CsvStreamProcessor::new(Box::new(SimpleTransactionProcessor::new(
         accounts.clone(),
@@ -149,8 +221,7 @@ async fn e2e_large_input_using_blocking_processor() {
 
     processor.process(reader).await.unwrap();
 
-    let mut summaries: Vec<AccountSummary> =
-        accounts.iter().map(|entry| entry.value().into()).collect();
+    let mut summaries = accounts.summaries();
     summaries.sort_by(|a, b| {
         a.client_id
             .partial_cmp(&b.client_id)
@@ -173,12 +244,12 @@ async fn e2e_large_input_using_blocking_processor() {
     );
 }
 
-fn create_test_records(client_count: u16, transaction_count: u32) -> Vec<TransactionRecord> {
+fn create_test_records(client_count: u16, transaction_count: u32) -> Vec<TransactionRecordV1> {
     let mut records = Vec::new();
     let mut transaction_id = 1u32;
     for _ in 1..=transaction_count {
         for client_id in 1..=client_count {
-            records.push(TransactionRecord {
+            records.push(TransactionRecordV1 {
                 txn_type: Deposit,
                 client_id,
                 transaction_id,
@@ -190,7 +261,7 @@ fn create_test_records(client_count: u16, transaction_count: u32) -> Vec<Transac
     records
 }
 
-fn create_test_file(filename: &str, records: Vec<TransactionRecord>) {
+fn create_test_file(filename: &str, records: Vec<TransactionRecordV1>) {
     let file = File::create(filename).unwrap();
     let buf_writer = BufWriter::new(file);
     let mut wtr = WriterBuilder::new().from_writer(buf_writer);